@@ -0,0 +1,44 @@
+use cgmath::{InnerSpace, Quaternion, Vector2, Vector3, Vector4, VectorSpace};
+
+/// Values that can be blended between two states.
+pub trait Interpolate: Copy {
+    fn interpolate(self, other: Self, amount: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(self, other: Self, amount: f32) -> Self {
+        self + (other - self) * amount
+    }
+}
+
+impl<const N: usize> Interpolate for [f32; N] {
+    fn interpolate(self, other: Self, amount: f32) -> Self {
+        std::array::from_fn(|i| self[i].interpolate(other[i], amount))
+    }
+}
+
+impl Interpolate for Vector2<f32> {
+    fn interpolate(self, other: Self, amount: f32) -> Self {
+        self.lerp(other, amount)
+    }
+}
+
+impl Interpolate for Vector3<f32> {
+    fn interpolate(self, other: Self, amount: f32) -> Self {
+        self.lerp(other, amount)
+    }
+}
+
+impl Interpolate for Vector4<f32> {
+    fn interpolate(self, other: Self, amount: f32) -> Self {
+        self.lerp(other, amount)
+    }
+}
+
+impl Interpolate for Quaternion<f32> {
+    fn interpolate(self, other: Self, amount: f32) -> Self {
+        // Take the shortest path between the two rotations
+        let other = if self.dot(other) < 0.0 { -other } else { other };
+        self.slerp(other, amount)
+    }
+}
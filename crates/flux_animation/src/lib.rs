@@ -0,0 +1,28 @@
+use crate::skeletal::animate_skins;
+use flux_ecs::plugin::Plugin;
+use flux_ecs::schedule::CoreSchedule;
+use flux_ecs::world::World;
+use flux_time::TimePlugin;
+use flux_time::time::Time;
+
+pub mod interpolation;
+pub mod skeletal;
+pub mod tween;
+
+/// Adds skeletal animation playback, driven by the [`Time`]. Adds the [`TimePlugin`] unless the
+/// time was added already.
+///
+/// Tweens are generic over the animated component, add
+/// [`animate_tweens::<T>`](tween::animate_tweens) to the `Main` schedule for every tweened
+/// component type.
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn init(&self, world: &mut World) {
+        if world.get_resource::<Time>().is_none() {
+            world.add_plugin(TimePlugin);
+        }
+
+        world.add_system(CoreSchedule::Main, animate_skins);
+    }
+}
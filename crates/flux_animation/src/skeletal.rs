@@ -0,0 +1,354 @@
+use crate::interpolation::Interpolate;
+use cgmath::{InnerSpace, Matrix4, One, Quaternion, SquareMatrix, Vector3};
+use flux_ecs::component::Component;
+use flux_ecs::query::Query;
+use flux_ecs::resource::Res;
+use flux_time::time::Time;
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct Joint {
+    pub name: String,
+    /// The index of the parent joint, parents must come before their children.
+    pub parent: Option<usize>,
+    /// Transforms from model space into the joint space of the bind pose.
+    pub inverse_bind_matrix: Matrix4<f32>,
+    pub rest_pose: JointPose,
+}
+
+#[derive(Debug, Clone)]
+pub struct Skeleton {
+    pub joints: Vec<Joint>,
+}
+
+impl Skeleton {
+    /// # Panics
+    /// Panics if a joint references a parent that does not come before it.
+    pub fn new(joints: Vec<Joint>) -> Self {
+        for (index, joint) in joints.iter().enumerate() {
+            if let Some(parent) = joint.parent {
+                assert!(
+                    parent < index,
+                    "Joint '{}' must come after its parent joint",
+                    joint.name
+                );
+            }
+        }
+
+        Self { joints }
+    }
+
+    pub fn find_joint(&self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+}
+
+/// The local transform of a joint relative to its parent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JointPose {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for JointPose {
+    fn default() -> Self {
+        Self {
+            translation: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Quaternion::one(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl JointPose {
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+/// Values sampled at increasing points in time, linearly interpolated in between.
+#[derive(Debug, Clone)]
+pub struct Keyframes<T: Interpolate> {
+    times: Vec<f32>,
+    values: Vec<T>,
+}
+
+impl<T: Interpolate> Keyframes<T> {
+    /// # Panics
+    /// Panics if the keyframes are empty, their lengths differ or the times are not sorted.
+    pub fn new(times: Vec<f32>, values: Vec<T>) -> Self {
+        assert!(!times.is_empty(), "Keyframes must not be empty");
+        assert_eq!(times.len(), values.len(), "Every keyframe needs a value");
+        assert!(
+            times.windows(2).all(|pair| pair[0] <= pair[1]),
+            "Keyframe times must be sorted"
+        );
+
+        Self { times, values }
+    }
+
+    pub fn duration(&self) -> f32 {
+        *self.times.last().expect("Keyframes are never empty")
+    }
+
+    pub fn sample(&self, time: f32) -> T {
+        let next = self.times.partition_point(|&t| t <= time);
+
+        if next == 0 {
+            return self.values[0];
+        }
+        if next == self.times.len() {
+            return self.values[next - 1];
+        }
+
+        let previous = next - 1;
+        let span = self.times[next] - self.times[previous];
+        let amount = if span > 0.0 {
+            (time - self.times[previous]) / span
+        } else {
+            0.0
+        };
+
+        self.values[previous].interpolate(self.values[next], amount)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JointTrack {
+    pub joint: usize,
+    pub translation: Option<Keyframes<Vector3<f32>>>,
+    pub rotation: Option<Keyframes<Quaternion<f32>>>,
+    pub scale: Option<Keyframes<Vector3<f32>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+    pub name: String,
+    pub tracks: Vec<JointTrack>,
+    duration: f32,
+}
+
+impl AnimationClip {
+    pub fn new(name: impl Into<String>, tracks: Vec<JointTrack>) -> Self {
+        let duration = tracks
+            .iter()
+            .flat_map(|track| {
+                [
+                    track.translation.as_ref().map(Keyframes::duration),
+                    track.rotation.as_ref().map(Keyframes::duration),
+                    track.scale.as_ref().map(Keyframes::duration),
+                ]
+            })
+            .flatten()
+            .fold(0.0, f32::max);
+
+        Self {
+            name: name.into(),
+            tracks,
+            duration,
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// Writes the local joint poses at `time` into `poses`. Joints without a track keep their
+    /// current pose.
+    pub fn sample(&self, time: f32, poses: &mut [JointPose]) {
+        for track in &self.tracks {
+            let Some(pose) = poses.get_mut(track.joint) else {
+                continue;
+            };
+
+            if let Some(translation) = &track.translation {
+                pose.translation = translation.sample(time);
+            }
+            if let Some(rotation) = &track.rotation {
+                pose.rotation = rotation.sample(time).normalize();
+            }
+            if let Some(scale) = &track.scale {
+                pose.scale = scale.sample(time);
+            }
+        }
+    }
+}
+
+/// Plays an [`AnimationClip`] on the [`Skin`] of the same entity.
+pub struct AnimationPlayer {
+    pub clip: Arc<AnimationClip>,
+    pub time: f32,
+    pub speed: f32,
+    pub looping: bool,
+    pub playing: bool,
+}
+
+impl Component for AnimationPlayer {}
+
+impl AnimationPlayer {
+    pub fn new(clip: Arc<AnimationClip>) -> Self {
+        Self {
+            clip,
+            time: 0.0,
+            speed: 1.0,
+            looping: true,
+            playing: true,
+        }
+    }
+
+    pub fn advance(&mut self, delta: f32) {
+        if !self.playing {
+            return;
+        }
+
+        let duration = self.clip.duration();
+        self.time += delta * self.speed;
+
+        if duration <= 0.0 {
+            self.time = 0.0;
+        } else if self.looping {
+            self.time = self.time.rem_euclid(duration);
+        } else if self.time >= duration {
+            self.time = duration;
+            self.playing = false;
+        }
+    }
+}
+
+/// The animated skeleton of an entity and the resulting joint matrix palette.
+///
+/// The palette holds one matrix per joint transforming a bind pose vertex into its animated
+/// model space position, ready to be uploaded for GPU skinning.
+pub struct Skin {
+    pub skeleton: Arc<Skeleton>,
+    pub poses: Vec<JointPose>,
+    pub palette: Vec<Matrix4<f32>>,
+}
+
+impl Component for Skin {}
+
+impl Skin {
+    pub fn new(skeleton: Arc<Skeleton>) -> Self {
+        let poses = skeleton.joints.iter().map(|joint| joint.rest_pose).collect();
+        let palette = vec![Matrix4::identity(); skeleton.joints.len()];
+
+        Self {
+            skeleton,
+            poses,
+            palette,
+        }
+    }
+
+    pub fn update_palette(&mut self) {
+        let mut global_transforms: Vec<Matrix4<f32>> = Vec::with_capacity(self.poses.len());
+
+        for (joint, pose) in self.skeleton.joints.iter().zip(&self.poses) {
+            let local = pose.to_matrix();
+            let global = match joint.parent {
+                Some(parent) => global_transforms[parent] * local,
+                None => local,
+            };
+            global_transforms.push(global);
+        }
+
+        for ((matrix, global), joint) in self
+            .palette
+            .iter_mut()
+            .zip(&global_transforms)
+            .zip(&self.skeleton.joints)
+        {
+            *matrix = global * joint.inverse_bind_matrix;
+        }
+    }
+
+    /// The palette as raw bytes in column major order.
+    pub fn palette_bytes(&self) -> &[u8] {
+        // Matrix4 is `repr(C)` and made of four `repr(C)` column vectors
+        unsafe {
+            std::slice::from_raw_parts(
+                self.palette.as_ptr().cast::<u8>(),
+                size_of_val(self.palette.as_slice()),
+            )
+        }
+    }
+}
+
+pub fn animate_skins(time: Res<Time>, players: Query<(&mut AnimationPlayer, &mut Skin)>) {
+    for (player, skin) in players {
+        player.advance(time.delta_secs());
+        player.clip.sample(player.time, &mut skin.poses);
+        skin.update_palette();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Rotation3, Transform, Vector4};
+
+    fn joint(name: &str, parent: Option<usize>, rest_translation: Vector3<f32>) -> Joint {
+        Joint {
+            name: name.to_string(),
+            parent,
+            inverse_bind_matrix: Matrix4::identity(),
+            rest_pose: JointPose {
+                translation: rest_translation,
+                ..JointPose::default()
+            },
+        }
+    }
+
+    #[test]
+    fn keyframes_are_interpolated_and_clamped() {
+        let keyframes = Keyframes::new(
+            vec![0.0, 1.0, 3.0],
+            vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(2.0, 0.0, 0.0),
+                Vector3::new(2.0, 4.0, 0.0),
+            ],
+        );
+
+        assert_eq!(keyframes.sample(-1.0), Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(keyframes.sample(0.5), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(keyframes.sample(2.0), Vector3::new(2.0, 2.0, 0.0));
+        assert_eq!(keyframes.sample(10.0), Vector3::new(2.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn child_joints_inherit_parent_transforms() {
+        let skeleton = Arc::new(Skeleton::new(vec![
+            joint("root", None, Vector3::new(0.0, 0.0, 0.0)),
+            joint("arm", Some(0), Vector3::new(1.0, 0.0, 0.0)),
+        ]));
+
+        let clip = Arc::new(AnimationClip::new(
+            "turn",
+            vec![JointTrack {
+                joint: 0,
+                translation: None,
+                rotation: Some(Keyframes::new(
+                    vec![0.0, 1.0],
+                    vec![Quaternion::one(), Quaternion::from_angle_z(Deg(90.0))],
+                )),
+                scale: None,
+            }],
+        ));
+
+        let mut skin = Skin::new(skeleton);
+        let mut player = AnimationPlayer::new(clip);
+        player.looping = false;
+        player.advance(2.0);
+        player.clip.sample(player.time, &mut skin.poses);
+        skin.update_palette();
+
+        assert!(!player.playing);
+        let arm_origin = skin.palette[1].transform_point(cgmath::Point3::new(0.0, 0.0, 0.0));
+        assert!((arm_origin.x - 0.0).abs() < 1e-5);
+        assert!((arm_origin.y - 1.0).abs() < 1e-5);
+        assert_eq!(skin.palette_bytes().len(), 2 * size_of::<Vector4<f32>>() * 4);
+    }
+}
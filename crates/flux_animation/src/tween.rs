@@ -0,0 +1,227 @@
+use crate::interpolation::Interpolate;
+use flux_ecs::component::Component;
+use flux_ecs::query::Query;
+use flux_ecs::resource::Res;
+use flux_time::time::Time;
+use std::f32::consts::PI;
+
+/// Maps the linear progress of a tween onto an eased progress, both in `0.0..=1.0`.
+#[derive(Debug, Clone, Copy)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    Custom(fn(f32) -> f32),
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Easing::SineOut => (t * PI / 2.0).sin(),
+            Easing::SineInOut => -((PI * t).cos() - 1.0) / 2.0,
+            Easing::Custom(function) => function(t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RepeatMode {
+    #[default]
+    Once,
+    Loop,
+    /// Plays forward and backward alternately.
+    PingPong,
+}
+
+type TweenLens<T> = Box<dyn Fn(&mut T, f32)>;
+
+/// Animates a property of the component `T` on the same entity over time.
+///
+/// The lens receives the eased progress and writes the animated value into the component, use
+/// [`Tween::between`] to interpolate between two values. Tweens are advanced by
+/// [`animate_tweens`], which has to be added once per animated component type.
+pub struct Tween<T: Component> {
+    lens: TweenLens<T>,
+    duration: f32,
+    elapsed: f32,
+    easing: Easing,
+    repeat: RepeatMode,
+    paused: bool,
+    /// Set once a [`RepeatMode::Once`] tween applied its end value.
+    finished: bool,
+}
+
+impl<T: Component> Component for Tween<T> {}
+
+impl<T: Component> Tween<T> {
+    pub fn new(duration: f32, easing: Easing, lens: impl Fn(&mut T, f32) + 'static) -> Self {
+        Self {
+            lens: Box::new(lens),
+            duration,
+            elapsed: 0.0,
+            easing,
+            repeat: RepeatMode::Once,
+            paused: false,
+            finished: false,
+        }
+    }
+
+    /// Creates a tween that interpolates from `from` to `to` and applies the value with `apply`.
+    pub fn between<V: Interpolate + 'static>(
+        from: V,
+        to: V,
+        duration: f32,
+        easing: Easing,
+        apply: impl Fn(&mut T, V) + 'static,
+    ) -> Self {
+        Self::new(duration, easing, move |component, progress| {
+            apply(component, from.interpolate(to, progress));
+        })
+    }
+
+    #[must_use]
+    pub fn with_repeat(mut self, repeat: RepeatMode) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// The eased progress of the tween, tweens without a duration are always at their end.
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+
+        let linear = match self.repeat {
+            RepeatMode::Once => self.elapsed / self.duration,
+            RepeatMode::Loop => (self.elapsed % self.duration) / self.duration,
+            RepeatMode::PingPong => {
+                let cycle = (self.elapsed / self.duration) as u32;
+                let progress = (self.elapsed % self.duration) / self.duration;
+                if cycle.is_multiple_of(2) { progress } else { 1.0 - progress }
+            }
+        };
+
+        self.easing.apply(linear)
+    }
+
+    /// Advances the tween by `delta` seconds and applies it to `target`.
+    pub fn tick(&mut self, delta: f32, target: &mut T) {
+        if self.paused || self.is_finished() {
+            return;
+        }
+
+        self.elapsed += delta;
+        if self.repeat == RepeatMode::Once {
+            self.elapsed = self.elapsed.min(self.duration);
+        }
+
+        (self.lens)(target, self.progress());
+        self.finished = self.repeat == RepeatMode::Once && self.elapsed >= self.duration;
+    }
+}
+
+pub fn animate_tweens<T: Component>(time: Res<Time>, tweens: Query<(&mut Tween<T>, &mut T)>) {
+    for (tween, target) in tweens {
+        tween.tick(time.delta_secs(), target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Opacity(f32);
+
+    impl Component for Opacity {}
+
+    #[test]
+    fn tween_interpolates_and_finishes() {
+        let mut opacity = Opacity(0.0);
+        let mut tween = Tween::between(0.0, 1.0, 2.0, Easing::Linear, |o: &mut Opacity, v| {
+            o.0 = v;
+        });
+
+        tween.tick(0.5, &mut opacity);
+        assert_eq!(opacity.0, 0.25);
+
+        tween.tick(5.0, &mut opacity);
+        assert_eq!(opacity.0, 1.0);
+        assert!(tween.is_finished());
+    }
+
+    #[test]
+    fn zero_duration_tweens_apply_their_end_value() {
+        let mut opacity = Opacity(0.0);
+        let mut tween = Tween::between(0.2, 0.8, 0.0, Easing::Linear, |o: &mut Opacity, v| {
+            o.0 = v;
+        });
+        assert!(!tween.is_finished());
+        assert_eq!(tween.progress(), 1.0);
+
+        tween.tick(0.0, &mut opacity);
+        assert_eq!(opacity.0, 0.8);
+        assert!(tween.is_finished());
+    }
+
+    #[test]
+    fn ping_pong_reverses_direction() {
+        let mut opacity = Opacity(0.0);
+        let mut tween = Tween::between(0.0, 1.0, 1.0, Easing::Linear, |o: &mut Opacity, v| {
+            o.0 = v;
+        })
+        .with_repeat(RepeatMode::PingPong);
+
+        tween.tick(1.25, &mut opacity);
+        assert_eq!(opacity.0, 0.75);
+        assert!(!tween.is_finished());
+    }
+
+    #[test]
+    fn easing_curves_keep_their_endpoints() {
+        for easing in [Easing::QuadInOut, Easing::CubicOut, Easing::SineInOut] {
+            assert!(easing.apply(0.0).abs() < 1e-6);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{DeriveInput, Token, Type, parse_macro_input};
+
+/// Implements `Component`, components listed in `#[component(require(A, B))]` are inserted with
+/// their `Default` value whenever the component is added to an entity without them.
+#[proc_macro_derive(Component, attributes(component))]
+pub fn derive_component(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let required = match required_components(&input) {
+        Ok(required) => required,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let insert_required = (!required.is_empty()).then(|| {
+        quote! {
+            fn insert_required(
+                world: &mut ::flux_ecs::world::World,
+                entity: ::flux_ecs::entity::Entity,
+            ) {
+                #(
+                    if world.get::<#required>(entity).is_none() {
+                        let component = <#required as ::core::default::Default>::default();
+                        world.insert_component(entity, component);
+                    }
+                )*
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::flux_ecs::component::Component
+            for #name #type_generics #where_clause
+        {
+            #insert_required
+        }
+    }
+    .into()
+}
+
+/// Collects the types of every `#[component(require(..))]` attribute.
+fn required_components(input: &DeriveInput) -> syn::Result<Vec<Type>> {
+    let mut required = Vec::new();
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("component"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("require") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                let types = Punctuated::<Type, Token![,]>::parse_terminated(&content)?;
+                required.extend(types);
+                Ok(())
+            } else {
+                Err(meta.error("unknown component attribute, expected `require(..)`"))
+            }
+        })?;
+    }
+
+    Ok(required)
+}
+
+/// Implements `Resource`.
+#[proc_macro_derive(Resource)]
+pub fn derive_resource(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::flux_ecs::resource::Resource for #name #type_generics #where_clause {}
+    }
+    .into()
+}
@@ -0,0 +1,234 @@
+use crate::event::Events;
+use crate::logging::targets;
+use crate::plugin::Plugin;
+use crate::resource::{NonSendResource, Resource};
+use crate::schedule::control::ScheduleControl;
+use crate::schedule::{CoreSchedule, ScheduleError};
+use crate::system::SystemError;
+use crate::world::World;
+use log::{debug, error};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+type InitErrorHandler = Box<dyn FnMut(&mut World, &ScheduleError)>;
+
+/// Sent as an [`Events<AppExit>`] event to stop the runner after the current frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppExit;
+
+/// Limits how often the runner updates the app.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FramePacing {
+    /// The minimum duration of a frame, `None` updates as fast as possible.
+    pub target_frame_time: Option<Duration>,
+}
+
+impl Resource for FramePacing {}
+
+impl FramePacing {
+    pub fn from_fps(fps: u32) -> Self {
+        Self {
+            target_frame_time: Some(Duration::from_secs(1) / fps.max(1)),
+        }
+    }
+}
+
+/// Replaces the default runner of [`App::run`], e.g. with one driven by a window event loop.
+///
+/// A runner owns the app and is responsible for initializing it, calling [`App::update`] every
+/// frame until [`App::should_exit`] and shutting it down.
+pub struct AppRunner(Box<dyn FnOnce(App)>);
+
+impl NonSendResource for AppRunner {}
+
+impl AppRunner {
+    pub fn new(runner: impl FnOnce(App) + 'static) -> Self {
+        Self(Box::new(runner))
+    }
+}
+
+/// Owns the world and drives its schedules.
+pub struct App {
+    world: World,
+    init_error_handler: InitErrorHandler,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl App {
+    /// Creates an app with the resources every frame relies on, e.g. the [`ScheduleControl`].
+    pub fn new() -> Self {
+        let mut world = World::new();
+        world.add_resource(ScheduleControl::default());
+        Self {
+            world,
+            init_error_handler: Box::new(log_init_error),
+        }
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    pub fn add_plugin<P: Plugin>(&mut self, plugin: P) -> &mut Self {
+        self.world.add_plugin(plugin);
+        self
+    }
+
+    /// Replaces the handler called when the Initialization schedule fails, by default every
+    /// system error is logged.
+    ///
+    /// The handler can present the error to the user or insert fallback resources into the world.
+    pub fn on_init_error(
+        &mut self,
+        handler: impl FnMut(&mut World, &ScheduleError) + 'static,
+    ) -> &mut Self {
+        self.init_error_handler = Box::new(handler);
+        self
+    }
+
+    /// Runs the Initialization schedule and passes a failure to the init error handler.
+    pub fn initialize(&mut self) -> Result<(), ScheduleError> {
+        self.world
+            .run_system(&CoreSchedule::Initialization)
+            .inspect_err(|error| (self.init_error_handler)(&mut self.world, error))
+    }
+
+    /// Runs one frame: updates the events, see [`World::update_events`], and runs the
+    /// [`CoreSchedule::FRAME`] schedules.
+    ///
+    /// A failing schedule does not stop the frame, the errors of all schedules are returned.
+    pub fn update(&mut self) -> Result<(), Vec<ScheduleError>> {
+        self.world.update_events();
+
+        let errors: Vec<_> = CoreSchedule::FRAME
+            .iter()
+            .filter_map(|label| self.world.run_system(label).err())
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Whether an [`AppExit`] event was sent.
+    pub fn should_exit(&self) -> bool {
+        self.world
+            .get_resource::<Events<AppExit>>()
+            .is_some_and(|events| !events.is_empty())
+    }
+
+    /// Hands the app to the installed [`AppRunner`], or to [`run_loop`] if there is none.
+    pub fn run(mut self) {
+        match self.world.remove_non_send_resource::<AppRunner>() {
+            Some(runner) => (runner.0)(self),
+            None => run_loop(self),
+        }
+    }
+
+    /// Updates the app until it should exit, keeping the [`FramePacing`].
+    pub fn run_frames(&mut self) {
+        while !self.should_exit() {
+            let frame_start = Instant::now();
+            self.update_logged();
+
+            let pacing = self.world.get_resource::<FramePacing>().copied();
+            if let Some(target) = pacing.and_then(|pacing| pacing.target_frame_time) {
+                sleep(target.saturating_sub(frame_start.elapsed()));
+            }
+        }
+    }
+
+    /// Runs a frame with [`App::update`] and logs its errors.
+    pub fn update_logged(&mut self) {
+        if let Err(errors) = self.update() {
+            errors.iter().for_each(log_schedule_error);
+        }
+    }
+
+    /// Runs the Destroy schedule, failing systems are logged since nothing can recover from them
+    /// anymore. Systems skipped because their resources were never created are expected after a
+    /// failed initialization and only logged at debug level.
+    pub fn shutdown(&mut self) {
+        if let Err(error) = self.world.run_system(&CoreSchedule::Destroy) {
+            log_schedule_error(&error);
+        }
+    }
+}
+
+/// The default runner: initializes the app, updates it until it should exit and shuts it down.
+pub fn run_loop(mut app: App) {
+    if app.initialize().is_ok() {
+        app.run_frames();
+    }
+    app.shutdown();
+}
+
+/// Logs failing systems as errors, systems skipped because their resources do not exist are
+/// expected for optional features and after a failed initialization.
+fn log_schedule_error(error: &ScheduleError) {
+    for system_error in &error.errors {
+        match system_error {
+            SystemError::MissingResource { .. } => {
+                debug!(target: targets::SCHEDULE, "{system_error}")
+            }
+            SystemError::Failed { .. } => error!(target: targets::SCHEDULE, "{system_error}"),
+        }
+    }
+}
+
+fn log_init_error(_: &mut World, error: &ScheduleError) {
+    error!(target: targets::SCHEDULE, "Initialization failed");
+    for system_error in &error.errors {
+        error!(target: targets::SCHEDULE, "{system_error}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Commands;
+    use crate::resource::Res;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Trace(Mutex<Vec<CoreSchedule>>);
+
+    impl Resource for Trace {}
+
+    #[test]
+    fn default_runner_runs_frames_in_order_until_exit() {
+        let mut app = App::new();
+        app.world_mut().add_resource(Trace::default());
+        for label in CoreSchedule::FRAME {
+            app.world_mut().add_system(label, move |trace: Res<Trace>| {
+                trace.0.lock().unwrap().push(label)
+            });
+        }
+        app.world_mut().add_system(
+            CoreSchedule::Render,
+            |trace: Res<Trace>, mut commands: Commands| {
+                if trace.0.lock().unwrap().len() == 2 * CoreSchedule::FRAME.len() {
+                    commands.send_event(AppExit);
+                }
+            },
+        );
+
+        app.run_frames();
+
+        let trace = app.world().get_resource::<Trace>().unwrap();
+        let trace = trace.0.lock().unwrap();
+        assert_eq!(trace[..5], CoreSchedule::FRAME);
+        assert_eq!(trace[5..], CoreSchedule::FRAME);
+    }
+}
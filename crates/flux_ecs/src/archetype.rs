@@ -1,68 +1,148 @@
-use crate::component::{ComponentId, ComponentRegistry};
+use crate::component::{ComponentId, ComponentInfo, ComponentRegistry};
 use crate::entity::Entity;
+use crate::storage::{GlobalStorage, StorageAllocator};
 use std::alloc::Layout;
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::ptr::NonNull;
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ArchetypeId(pub usize);
 
 pub struct Column {
-    data: Vec<u8>,
+    data: NonNull<u8>,
+    len: usize,
+    capacity: usize,
     layout: Layout,
+    drop: Option<unsafe fn(*mut u8)>,
+    allocator: Arc<dyn StorageAllocator>,
 }
 
+// The column owns its buffer like a `Vec`, the allocator is `Send + Sync` itself
+unsafe impl Send for Column {}
+unsafe impl Sync for Column {}
+
 impl Column {
     pub fn new(layout: Layout) -> Self {
+        Self::with_allocator(layout, Arc::new(GlobalStorage))
+    }
+
+    pub fn with_allocator(layout: Layout, allocator: Arc<dyn StorageAllocator>) -> Self {
         Self {
-            data: Vec::new(),
+            // Zero-sized components never allocate and only need an aligned pointer
+            data: NonNull::new(std::ptr::without_provenance_mut(layout.align()))
+                .expect("Alignments are never zero"),
+            len: 0,
+            capacity: 0,
             layout,
+            drop: None,
+            allocator,
         }
     }
 
+    /// Drops the components with `drop` when they are removed or the column is dropped.
+    pub fn with_drop(mut self, drop: Option<unsafe fn(*mut u8)>) -> Self {
+        self.drop = drop;
+        self
+    }
+
     pub fn len(&self) -> usize {
-        if self.layout.size() == 0 {
-            return self.data.len();
+        self.len
+    }
+
+    fn array_layout(&self, capacity: usize) -> Layout {
+        let size = self
+            .layout
+            .size()
+            .checked_mul(capacity)
+            .expect("Column capacity overflow");
+        Layout::from_size_align(size, self.layout.align()).expect("Column capacity overflow")
+    }
+
+    fn grow(&mut self) {
+        let new_capacity = (self.capacity * 2).max(4);
+        let new_layout = self.array_layout(new_capacity);
+        let new_data = self
+            .allocator
+            .allocate(new_layout)
+            .unwrap_or_else(|| std::alloc::handle_alloc_error(new_layout));
+
+        if self.capacity > 0 {
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    self.data.as_ptr(),
+                    new_data.as_ptr(),
+                    self.len * self.layout.size(),
+                );
+                self.allocator
+                    .deallocate(self.data, self.array_layout(self.capacity));
+            }
         }
-        self.data.len() / self.layout.size()
+
+        self.data = new_data;
+        self.capacity = new_capacity;
     }
 
     pub unsafe fn push(&mut self, component_ptr: *const u8) {
         let size = self.layout.size();
         if size > 0 {
+            if self.len == self.capacity {
+                self.grow();
+            }
             unsafe {
-                let src_slice = std::slice::from_raw_parts(component_ptr, size);
-                self.data.extend_from_slice(src_slice);
+                let dst = self.data.as_ptr().add(self.len * size);
+                std::ptr::copy_nonoverlapping(component_ptr, dst, size);
             }
-        } else {
-            // For zero-sized types, we just push a placeholder
-            self.data.push(0);
         }
+        self.len += 1;
     }
 
+    /// Drops the component at `row` and moves the last component into its place.
+    ///
+    /// # Safety
+    /// `row` must be in bounds.
     pub unsafe fn swap_remove(&mut self, row: usize) {
+        if let Some(drop) = self.drop {
+            unsafe { drop(self.get_mut_ptr(row)) };
+        }
+        unsafe { self.swap_remove_moved(row) };
+    }
+
+    /// Removes the component at `row` without dropping it, after it was copied elsewhere.
+    ///
+    /// # Safety
+    /// `row` must be in bounds.
+    pub unsafe fn swap_remove_moved(&mut self, row: usize) {
+        debug_assert!(
+            row < self.len(),
+            "Column row {row} is out of bounds for length {}",
+            self.len()
+        );
         let size = self.layout.size();
         let last_index = self.len() - 1;
 
-        if size > 0 {
-            if row != last_index {
-                unsafe {
-                    let row_ptr = self.data.as_mut_ptr().add(row * size);
-                    let last_ptr = self.data.as_ptr().add(last_index * size);
-                    std::ptr::copy_nonoverlapping(last_ptr, row_ptr, size);
-                }
-            }
-            let new_len = self.data.len() - size;
+        if size > 0 && row != last_index {
             unsafe {
-                self.data.set_len(new_len);
+                let row_ptr = self.data.as_ptr().add(row * size);
+                let last_ptr = self.data.as_ptr().add(last_index * size);
+                std::ptr::copy_nonoverlapping(last_ptr, row_ptr, size);
             }
-        } else {
-            // For zero-sized types, we just remove the last element
-            self.data.pop();
         }
+        self.len -= 1;
+    }
+
+    /// A pointer to the first element, valid for reads of `len` elements.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.data.as_ptr()
     }
 
     pub fn get_ptr(&self, row: usize) -> *const u8 {
+        debug_assert!(
+            row < self.len(),
+            "Column row {row} is out of bounds for length {}",
+            self.len()
+        );
         let size = self.layout.size();
 
         unsafe { self.data.as_ptr().add(row * size) }
@@ -71,20 +151,48 @@ impl Column {
     pub fn get_mut_ptr(&self, row: usize) -> *mut u8 {
         self.get_ptr(row) as *mut u8
     }
+
+    /// Like [`Column::get_ptr`] but returns `None` if the row is out of bounds.
+    pub fn checked_get_ptr(&self, row: usize) -> Option<*const u8> {
+        (row < self.len()).then(|| self.get_ptr(row))
+    }
+}
+
+impl Drop for Column {
+    fn drop(&mut self) {
+        if let Some(drop) = self.drop {
+            for row in 0..self.len {
+                unsafe { drop(self.get_mut_ptr(row)) };
+            }
+        }
+        if self.capacity > 0 {
+            unsafe {
+                self.allocator
+                    .deallocate(self.data, self.array_layout(self.capacity));
+            }
+        }
+    }
 }
 
 pub struct Archetype {
     id: ArchetypeId,
     columns: HashMap<ComponentId, Column>,
     entities: Vec<Entity>,
+    allocator: Arc<dyn StorageAllocator>,
 }
 
 impl Archetype {
     pub fn new(id: ArchetypeId) -> Self {
+        Self::with_allocator(id, Arc::new(GlobalStorage))
+    }
+
+    /// Creates an archetype whose columns allocate from `allocator`.
+    pub fn with_allocator(id: ArchetypeId, allocator: Arc<dyn StorageAllocator>) -> Self {
         Self {
             id,
             columns: HashMap::new(),
             entities: Vec::new(),
+            allocator,
         }
     }
 
@@ -108,6 +216,13 @@ impl Archetype {
         &self.entities
     }
 
+    /// Creates the column of the component if the archetype does not have it yet.
+    pub fn init_column(&mut self, info: &ComponentInfo) -> &mut Column {
+        self.columns.entry(info.id).or_insert_with(|| {
+            Column::with_allocator(info.layout, Arc::clone(&self.allocator)).with_drop(info.drop)
+        })
+    }
+
     /// Adds a new entity to the archetype, along with its components.
     /// Returns the row index where the entity was inserted.
     ///
@@ -120,12 +235,10 @@ impl Archetype {
         registry: &ComponentRegistry,
     ) -> usize {
         for (id, ptr) in component_data {
-            let column = self.columns.entry(*id).or_insert_with(|| {
-                let info = registry
-                    .get_info(*id)
-                    .expect("Component must be registered before being added to an archetype");
-                Column::new(info.layout)
-            });
+            let info = registry
+                .get_info(*id)
+                .expect("Component must be registered before being added to an archetype");
+            let column = self.init_column(info);
 
             unsafe {
                 column.push(*ptr);
@@ -137,7 +250,7 @@ impl Archetype {
         row
     }
 
-    /// Removes an entity from the specified row using `swap_remove`.
+    /// Removes an entity from the specified row using `swap_remove`, dropping its components.
     ///
     /// # Returns
     /// A tuple containing:
@@ -146,13 +259,51 @@ impl Archetype {
     ///     to replace the removed one. This is `None` if the removed entity was the last one.
     ///     The `World` needs this information to update the moved entity's `EntityLocation`.
     pub fn remove(&mut self, row: usize) -> (Entity, Option<Entity>) {
+        assert!(
+            row < self.len(),
+            "Archetype row {row} is out of bounds for length {}",
+            self.len()
+        );
+
         for column in self.columns.values_mut() {
             unsafe {
                 column.swap_remove(row);
             }
         }
 
-        let removed_entity = self.entities.remove(row);
+        self.swap_remove_entity(row)
+    }
+
+    /// Like [`Archetype::remove`] but only drops the components that `target` does not have,
+    /// the others were copied into it by [`Archetype::add_moved_entity`]. The `taken`
+    /// components were moved out by the caller and are not dropped either.
+    pub fn remove_moved(
+        &mut self,
+        row: usize,
+        target: &Archetype,
+        taken: &[ComponentId],
+    ) -> (Entity, Option<Entity>) {
+        assert!(
+            row < self.len(),
+            "Archetype row {row} is out of bounds for length {}",
+            self.len()
+        );
+
+        for (component_id, column) in &mut self.columns {
+            unsafe {
+                if target.has_component(*component_id) || taken.contains(component_id) {
+                    column.swap_remove_moved(row);
+                } else {
+                    column.swap_remove(row);
+                }
+            }
+        }
+
+        self.swap_remove_entity(row)
+    }
+
+    fn swap_remove_entity(&mut self, row: usize) -> (Entity, Option<Entity>) {
+        let removed_entity = self.entities.swap_remove(row);
 
         let moved_entity = if row < self.entities.len() {
             Some(self.entities[row])
@@ -164,7 +315,7 @@ impl Archetype {
     }
 
     /// Adds an entity to this archetype by copying all of its existing component
-    /// data from a source archetype.
+    /// data from a source archetype, and the `added` components the source does not have.
     ///
     /// Returns the new row index of the added entity.
     ///
@@ -174,20 +325,33 @@ impl Archetype {
     /// 2. For every `ComponentId` present in `self.columns`, if that component also
     ///    exists in the `source_archetype`, the `source_column` must be valid.
     /// 3. The `source_archetype` reference must be valid and distinct from `self`.
+    /// 4. Every other column of `self` must have a valid pointer in `added`, the components
+    ///    are moved into the archetype and must not be dropped by the caller.
     pub unsafe fn add_moved_entity(
         &mut self,
         entity: Entity,
         source_archetype: &Archetype,
         source_row: usize,
+        added: &[(ComponentId, *const u8)],
     ) -> usize {
+        debug_assert!(
+            source_row < source_archetype.len(),
+            "Source row {source_row} is out of bounds for length {}",
+            source_archetype.len()
+        );
         let new_row = self.len();
 
         for (component_id, target_column) in &mut self.columns {
-            if let Some(source_column) = source_archetype.columns.get(component_id) {
-                let component_ptr = source_column.get_ptr(source_row);
-                unsafe {
-                    target_column.push(component_ptr);
-                }
+            let component_ptr = match source_archetype.columns.get(component_id) {
+                Some(source_column) => source_column.get_ptr(source_row),
+                None => added
+                    .iter()
+                    .find(|(id, _)| id == component_id)
+                    .map(|&(_, ptr)| ptr)
+                    .expect("Component added by the move is missing"),
+            };
+            unsafe {
+                target_column.push(component_ptr);
             }
         }
 
@@ -207,3 +371,61 @@ impl Archetype {
         self.columns.contains_key(&component_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_get_ptr_rejects_rows_out_of_bounds() {
+        let mut column = Column::new(Layout::new::<u32>());
+        let value = 7u32;
+        unsafe { column.push((&raw const value).cast()) };
+
+        assert!(column.checked_get_ptr(0).is_some());
+        assert!(column.checked_get_ptr(1).is_none());
+    }
+
+    #[test]
+    fn columns_allocate_from_their_allocator() {
+        use std::sync::atomic::{AtomicIsize, Ordering};
+
+        #[derive(Default)]
+        struct CountingStorage {
+            live: AtomicIsize,
+        }
+
+        unsafe impl StorageAllocator for CountingStorage {
+            fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+                self.live.fetch_add(1, Ordering::Relaxed);
+                GlobalStorage.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                self.live.fetch_sub(1, Ordering::Relaxed);
+                unsafe { GlobalStorage.deallocate(ptr, layout) }
+            }
+        }
+
+        let storage = Arc::new(CountingStorage::default());
+        let mut column = Column::with_allocator(Layout::new::<u64>(), storage.clone());
+        for value in 0..100u64 {
+            unsafe { column.push((&raw const value).cast()) };
+        }
+        unsafe { column.swap_remove(0) };
+
+        assert_eq!(storage.live.load(Ordering::Relaxed), 1);
+        assert_eq!(unsafe { *column.get_ptr(0).cast::<u64>() }, 99);
+
+        drop(column);
+        assert_eq!(storage.live.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "out of bounds")]
+    fn get_ptr_panics_out_of_bounds_in_debug_builds() {
+        let column = Column::new(Layout::new::<u32>());
+        column.get_ptr(0);
+    }
+}
@@ -44,8 +44,21 @@ impl ArchetypeGraph {
     pub fn get_add_edge(&self, start: ArchetypeId, component: ComponentId) -> Option<ArchetypeId> {
         self.add_component_edges.get(&(start, component)).copied()
     }
+
+    pub fn get_remove_edge(
+        &self,
+        start: ArchetypeId,
+        component: ComponentId,
+    ) -> Option<ArchetypeId> {
+        self.remove_component_edges.get(&(start, component)).copied()
+    }
     
     pub fn get_signature(&self, id: ArchetypeId) -> Option<&ArchetypeSignature> {
         self.signatures.get(id.0)
     }
+
+    /// The number of archetypes, ids are assigned in creation order.
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
 }
@@ -2,11 +2,19 @@ use crate::archetype::{Archetype, ArchetypeId};
 use crate::archetype_graph::ArchetypeGraph;
 use crate::component::{ComponentBundle, ComponentId, ComponentRegistry};
 use crate::entity::{Entity, EntityLocation};
+use crate::storage::{GlobalStorage, StorageAllocator};
+use std::sync::Arc;
 
-#[derive(Default)]
 pub struct Archetypes {
     graph: ArchetypeGraph,
     storage: Vec<Archetype>,
+    allocator: Arc<dyn StorageAllocator>,
+}
+
+impl Default for Archetypes {
+    fn default() -> Self {
+        Self::with_allocator(Arc::new(GlobalStorage))
+    }
 }
 
 impl Archetypes {
@@ -14,6 +22,15 @@ impl Archetypes {
         Self::default()
     }
 
+    /// Creates the archetype storage with every column allocating from `allocator`.
+    pub fn with_allocator(allocator: Arc<dyn StorageAllocator>) -> Self {
+        Self {
+            graph: ArchetypeGraph::default(),
+            storage: Vec::new(),
+            allocator,
+        }
+    }
+
     pub fn get_or_create_for_bundle<B: ComponentBundle>(
         &mut self,
         registry: &mut ComponentRegistry,
@@ -21,17 +38,31 @@ impl Archetypes {
         let mut component_ids = B::register_components(registry);
 
         let archetype_id = self.graph.get_or_create_archetype(&mut component_ids);
-
-        if archetype_id.0 >= self.storage.len() {
-            self.storage.resize_with(archetype_id.0 + 1, || {
-                Archetype::new(ArchetypeId(usize::MAX))
-            });
-            self.storage[archetype_id.0] = Archetype::new(archetype_id);
-        }
+        self.create_missing_archetypes(registry);
 
         archetype_id
     }
 
+    /// Creates the storage of the archetypes the graph added, with a column for each of their
+    /// components.
+    fn create_missing_archetypes(&mut self, registry: &ComponentRegistry) {
+        for index in self.storage.len()..self.graph.len() {
+            let id = ArchetypeId(index);
+            let mut archetype = Archetype::with_allocator(id, Arc::clone(&self.allocator));
+            let signature = self
+                .graph
+                .get_signature(id)
+                .expect("Archetype signature not found");
+            for component_id in signature {
+                let info = registry
+                    .get_info(*component_id)
+                    .expect("Component must be registered before being added to an archetype");
+                archetype.init_column(info);
+            }
+            self.storage.push(archetype);
+        }
+    }
+
     pub fn get_mut(&mut self, id: ArchetypeId) -> Option<&mut Archetype> {
         self.storage.get_mut(id.0)
     }
@@ -40,6 +71,7 @@ impl Archetypes {
         &mut self,
         start_id: ArchetypeId,
         component_id: ComponentId,
+        registry: &ComponentRegistry,
     ) -> ArchetypeId {
         if let Some(id) = self.graph.get_add_edge(start_id, component_id) {
             return id;
@@ -53,14 +85,47 @@ impl Archetypes {
 
         new_signature.push(component_id);
 
-        self.graph.get_or_create_archetype(&mut new_signature)
+        let id = self.graph.get_or_create_archetype(&mut new_signature);
+        self.create_missing_archetypes(registry);
+        id
     }
 
+    /// The archetype of an entity of `start_id` after removing the component, `None` if the
+    /// archetype does not have the component.
+    pub fn get_remove_component_destination(
+        &self,
+        start_id: ArchetypeId,
+        component_id: ComponentId,
+    ) -> Option<ArchetypeId> {
+        // Creating an archetype also creates all archetypes with one component less
+        self.graph.get_remove_edge(start_id, component_id)
+    }
+
+    /// Moves the entity into the target archetype, dropping the components the target does not
+    /// have. Returns the new location of the entity and the entity that took its row in the
+    /// source archetype.
     pub fn move_entity(
         &mut self,
         entity: Entity,
         location: EntityLocation,
         target_archetype_id: ArchetypeId,
+    ) -> (EntityLocation, Option<Entity>) {
+        unsafe { self.move_entity_with(entity, location, target_archetype_id, &[], &[]) }
+    }
+
+    /// Like [`Archetypes::move_entity`], additionally moving the `added` components into the
+    /// target, while the `taken` components are left to the caller instead of being dropped.
+    ///
+    /// # Safety
+    /// See [`Archetype::add_moved_entity`], the caller must have read the `taken` components
+    /// out of the source archetype.
+    pub unsafe fn move_entity_with(
+        &mut self,
+        entity: Entity,
+        location: EntityLocation,
+        target_archetype_id: ArchetypeId,
+        added: &[(ComponentId, *const u8)],
+        taken: &[ComponentId],
     ) -> (EntityLocation, Option<Entity>) {
         let (source_slice, target_slice) = self.storage.split_at_mut(std::cmp::max(
             location.archetype_id.0,
@@ -82,10 +147,12 @@ impl Archetypes {
 
         let new_row;
         unsafe {
-            new_row = target_archetype.add_moved_entity(entity, source_archetype, location.row);
+            new_row =
+                target_archetype.add_moved_entity(entity, source_archetype, location.row, added);
         }
 
-        let (_removed_entity, moved_entity_in_source) = source_archetype.remove(location.row);
+        let (_removed_entity, moved_entity_in_source) =
+            source_archetype.remove_moved(location.row, target_archetype, taken);
 
         let new_location = EntityLocation {
             archetype_id: target_archetype_id,
@@ -107,6 +174,7 @@ impl Archetypes {
 pub struct ArchetypeIter<'a> {
     archetypes: &'a [Archetype],
     index: usize,
+    required: &'a [ComponentId],
 }
 
 impl<'a> ArchetypeIter<'a> {
@@ -114,20 +182,28 @@ impl<'a> ArchetypeIter<'a> {
         Self {
             archetypes,
             index: 0,
+            required: &[],
         }
     }
+
+    /// Only yields the archetypes that have every one of the components.
+    pub fn with_components(mut self, components: &'a [ComponentId]) -> Self {
+        self.required = components;
+        self
+    }
 }
 
 impl<'a> Iterator for ArchetypeIter<'a> {
     type Item = &'a Archetype;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index < self.archetypes.len() {
+        while self.index < self.archetypes.len() {
             let archetype = &self.archetypes[self.index];
             self.index += 1;
-            Some(archetype)
-        } else {
-            None
+            if self.required.iter().all(|&id| archetype.has_component(id)) {
+                return Some(archetype);
+            }
         }
+        None
     }
 }
@@ -0,0 +1,61 @@
+use crate::component::ComponentId;
+use crate::entity::Entity;
+use std::any::TypeId;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// A structural change of the world, sent to the receivers of [`World::subscribe_changes`].
+///
+/// [`World::subscribe_changes`]: crate::world::World::subscribe_changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorldChange {
+    EntitySpawned(Entity),
+    EntityDespawned(Entity),
+    ComponentAdded {
+        entity: Entity,
+        component: ComponentId,
+        name: &'static str,
+    },
+    ComponentRemoved {
+        entity: Entity,
+        component: ComponentId,
+        name: &'static str,
+    },
+    /// Also sent when an existing resource is replaced.
+    ResourceInserted {
+        type_id: TypeId,
+        name: &'static str,
+    },
+    ResourceRemoved {
+        type_id: TypeId,
+        name: &'static str,
+    },
+}
+
+/// The channels of the change subscribers, changes are only built while someone listens.
+#[derive(Default)]
+pub(crate) struct ChangeSubscribers {
+    senders: Vec<Sender<WorldChange>>,
+}
+
+impl ChangeSubscribers {
+    pub fn subscribe(&mut self) -> Receiver<WorldChange> {
+        let (sender, receiver) = mpsc::channel();
+        self.senders.push(sender);
+        receiver
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    /// Sends the change to every subscriber, subscribers whose receiver was dropped are removed.
+    pub fn notify(&mut self, change: impl FnOnce() -> WorldChange) {
+        if self.senders.is_empty() {
+            return;
+        }
+
+        let change = change();
+        self.senders
+            .retain(|sender| sender.send(change.clone()).is_ok());
+    }
+}
@@ -1,31 +1,202 @@
-use crate::resource::Resource;
+use crate::component::{Component, ComponentBundle};
+use crate::entity::{Entity, EntityReserver};
+use crate::resource::{NonSendResource, Resource};
 use crate::system::parameter::SystemParam;
 use crate::world::World;
-use std::cell::RefCell;
+use std::any::type_name;
 use std::collections::VecDeque;
-use std::rc::Rc;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, ThreadId};
 
 pub trait Command {
-    fn execute(self: Box<Self>, world: &mut World);
+    /// Applies the command to the world.
+    ///
+    /// A returned error is sent as an [`Events<CommandError>`](crate::event::Events) event by
+    /// [`World::flush_commands`].
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError>;
+
+    /// The entity the command operates on, included in the logs when the command fails.
+    fn entity(&self) -> Option<Entity> {
+        None
+    }
+}
+
+/// Reports a deferred command that could not be applied when the command queue was flushed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    ResourceNotFound {
+        operation: &'static str,
+        resource: &'static str,
+    },
+    EntityNotFound {
+        operation: &'static str,
+        entity: Entity,
+    },
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::ResourceNotFound {
+                operation,
+                resource,
+            } => write!(f, "{operation} failed: resource {resource} does not exist"),
+            CommandError::EntityNotFound { operation, entity } => {
+                write!(f, "{operation} failed: entity {entity} does not exist")
+            }
+        }
+    }
 }
 
+impl std::error::Error for CommandError {}
+
 pub struct CreateResource<T: Resource> {
     pub resource: T,
 }
 
 impl<T: Resource> Command for CreateResource<T> {
-    fn execute(self: Box<Self>, world: &mut World) {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
         world.add_resource(self.resource);
+        Ok(())
     }
 }
 
 pub struct RemoveResource<T: Resource> {
-    _phantom: std::marker::PhantomData<T>,
+    _phantom: std::marker::PhantomData<fn() -> T>,
 }
 
 impl<T: Resource> Command for RemoveResource<T> {
-    fn execute(self: Box<Self>, world: &mut World) {
-        world.remove_resource::<T>();
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        world
+            .remove_resource::<T>()
+            .map(|_| ())
+            .ok_or(CommandError::ResourceNotFound {
+                operation: "remove_resource",
+                resource: type_name::<T>(),
+            })
+    }
+}
+
+pub struct CreateNonSendResource<T: NonSendResource> {
+    pub resource: T,
+}
+
+impl<T: NonSendResource> Command for CreateNonSendResource<T> {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        world.add_non_send_resource(self.resource);
+        Ok(())
+    }
+}
+
+pub struct RemoveNonSendResource<T: NonSendResource> {
+    _phantom: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: NonSendResource> Command for RemoveNonSendResource<T> {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        world
+            .remove_non_send_resource::<T>()
+            .map(|_| ())
+            .ok_or(CommandError::ResourceNotFound {
+                operation: "remove_non_send_resource",
+                resource: type_name::<T>(),
+            })
+    }
+}
+
+pub struct SendEvent<T: Send + Sync + 'static> {
+    pub event: T,
+}
+
+impl<T: Send + Sync + 'static> Command for SendEvent<T> {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        world.send_event(self.event);
+        Ok(())
+    }
+}
+
+/// Spawns the bundle as an entity reserved by [`Commands::spawn`].
+pub struct Spawn<C: ComponentBundle> {
+    entity: Entity,
+    bundle: C,
+}
+
+impl<C: ComponentBundle> Command for Spawn<C> {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        world.spawn_reserved(self.entity, self.bundle);
+        Ok(())
+    }
+
+    fn entity(&self) -> Option<Entity> {
+        Some(self.entity)
+    }
+}
+
+pub struct Despawn {
+    pub entity: Entity,
+}
+
+impl Command for Despawn {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        if world.despawn(self.entity) {
+            Ok(())
+        } else {
+            Err(CommandError::EntityNotFound {
+                operation: "despawn",
+                entity: self.entity,
+            })
+        }
+    }
+
+    fn entity(&self) -> Option<Entity> {
+        Some(self.entity)
+    }
+}
+
+pub struct InsertComponent<T: Component> {
+    pub entity: Entity,
+    pub component: T,
+}
+
+impl<T: Component> Command for InsertComponent<T> {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        if world.insert_component(self.entity, self.component) {
+            Ok(())
+        } else {
+            Err(CommandError::EntityNotFound {
+                operation: "insert_component",
+                entity: self.entity,
+            })
+        }
+    }
+
+    fn entity(&self) -> Option<Entity> {
+        Some(self.entity)
+    }
+}
+
+/// Removing a component the entity does not have is not an error.
+pub struct RemoveComponent<T: Component> {
+    pub entity: Entity,
+    _phantom: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: Component> Command for RemoveComponent<T> {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        if !world.contains(self.entity) {
+            return Err(CommandError::EntityNotFound {
+                operation: "remove_component",
+                entity: self.entity,
+            });
+        }
+
+        world.remove_component::<T>(self.entity);
+        Ok(())
+    }
+
+    fn entity(&self) -> Option<Entity> {
+        Some(self.entity)
     }
 }
 
@@ -54,50 +225,261 @@ impl CommandQueue {
     }
 }
 
+type CommandBuffer = Arc<Mutex<VecDeque<Box<dyn Command + Send>>>>;
+
+/// Queues changes to the world that are applied after the system ran.
+///
+/// Commands can be cloned and sent to other threads, e.g. to tasks spawned by a system. Commands
+/// queued from any clone are applied together, in the order they were queued.
+#[derive(Clone)]
 pub struct Commands {
-    buffer: Rc<RefCell<VecDeque<Box<dyn Command>>>>,
+    buffer: CommandBuffer,
+    entities: EntityReserver,
+    /// The thread running the system, the only one allowed to queue non-send resources.
+    world_thread: ThreadId,
 }
 
 impl Commands {
-    pub fn push(&mut self, command: impl Command + 'static) {
-        self.buffer.borrow_mut().push_back(Box::new(command));
+    fn queue(&self, command: impl Command + Send + 'static) {
+        self.buffer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push_back(Box::new(command));
+    }
+
+    pub fn push(&mut self, command: impl Command + Send + 'static) {
+        self.queue(command);
     }
 
     pub fn insert_resource<T: Resource>(&mut self, resource: T) {
-        self.buffer.borrow_mut().push_back(Box::new(CreateResource { resource }));
+        self.queue(CreateResource { resource });
     }
-    
+
     pub fn remove_resource<T: Resource>(&mut self) {
-        self.buffer.borrow_mut().push_back(Box::new(RemoveResource::<T> {
+        self.queue(RemoveResource::<T> {
             _phantom: std::marker::PhantomData,
-        }));
+        });
+    }
+
+    /// # Panics
+    /// Panics if called from another thread than the one running the system.
+    pub fn insert_non_send_resource<T: NonSendResource>(&mut self, resource: T) {
+        assert_eq!(
+            thread::current().id(),
+            self.world_thread,
+            "Non-send resource {} can only be inserted from the thread running the system",
+            type_name::<T>()
+        );
+        self.queue(WorldThreadCommand(CreateNonSendResource { resource }));
+    }
+
+    pub fn remove_non_send_resource<T: NonSendResource>(&mut self) {
+        self.queue(RemoveNonSendResource::<T> {
+            _phantom: std::marker::PhantomData,
+        });
+    }
+
+    /// Spawns the bundle once the commands are flushed. The returned entity is reserved right
+    /// away and can be used by other commands, it is not alive until the spawn was applied.
+    pub fn spawn<C: ComponentBundle + Send + 'static>(&mut self, bundle: C) -> Entity {
+        let entity = self.entities.reserve();
+        self.queue(Spawn { entity, bundle });
+        entity
+    }
+
+    /// Queues commands for the entity.
+    pub fn entity(&mut self, entity: Entity) -> EntityCommands<'_> {
+        EntityCommands {
+            entity,
+            commands: self,
+        }
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        self.queue(Despawn { entity });
+    }
+
+    pub fn insert_component<T: Component + Send>(&mut self, entity: Entity, component: T) {
+        self.queue(InsertComponent { entity, component });
+    }
+
+    pub fn remove_component<T: Component>(&mut self, entity: Entity) {
+        self.queue(RemoveComponent::<T> {
+            entity,
+            _phantom: std::marker::PhantomData,
+        });
+    }
+
+    /// Sends the event once the commands are flushed, see [`World::send_event`].
+    pub fn send_event<T: Send + Sync + 'static>(&mut self, event: T) {
+        self.queue(SendEvent { event });
+    }
+}
+
+/// Queues commands for a single entity, see [`Commands::entity`].
+pub struct EntityCommands<'a> {
+    entity: Entity,
+    commands: &'a mut Commands,
+}
+
+impl EntityCommands<'_> {
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn insert<T: Component + Send>(&mut self, component: T) -> &mut Self {
+        self.commands.insert_component(self.entity, component);
+        self
+    }
+
+    pub fn remove<T: Component>(&mut self) -> &mut Self {
+        self.commands.remove_component::<T>(self.entity);
+        self
+    }
+
+    pub fn despawn(&mut self) {
+        self.commands.despawn(self.entity);
+    }
+}
+
+/// A command that is queued and applied on the thread running the system.
+struct WorldThreadCommand<C>(C);
+
+// Only created on the thread running the system, and the buffer is drained by the system on the
+// same thread, so the command never crosses threads
+unsafe impl<C> Send for WorldThreadCommand<C> {}
+
+impl<C: Command> Command for WorldThreadCommand<C> {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        Box::new(self.0).execute(world)
+    }
+
+    fn entity(&self) -> Option<Entity> {
+        self.0.entity()
     }
 }
 
 pub struct CommandsState {
-    buffer: Rc<RefCell<VecDeque<Box<dyn Command>>>>,
+    buffer: CommandBuffer,
+    entities: EntityReserver,
 }
 
 impl SystemParam for Commands {
     type State = CommandsState;
     type Item<'world, 'state> = Commands;
 
-    fn init_state(_: &mut World) -> Self::State {
+    fn init_state(world: &mut World) -> Self::State {
         CommandsState {
-            buffer: Rc::new(RefCell::new(VecDeque::new())),
+            buffer: CommandBuffer::default(),
+            entities: world.entity_reserver(),
         }
     }
 
     fn get_param<'world, 'state>(state: &'state Self::State, _: &'world mut World) -> Self::Item<'world, 'state> {
         Commands {
-            buffer: Rc::clone(&state.buffer),
+            buffer: Arc::clone(&state.buffer),
+            entities: state.entities.clone(),
+            world_thread: thread::current().id(),
         }
     }
 
     fn apply_buffers(state: &Self::State, world: &mut World) {
-        let mut buffer = state.buffer.borrow_mut();
+        let mut buffer = state.buffer.lock().unwrap_or_else(PoisonError::into_inner);
         for command in buffer.drain(..) {
             world.add_command(command);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Events;
+
+    struct Missing;
+
+    impl Resource for Missing {}
+
+    #[test]
+    fn failed_commands_are_reported_as_events() {
+        let mut world = World::new();
+        world.add_command(Box::new(RemoveResource::<Missing> {
+            _phantom: std::marker::PhantomData,
+        }));
+
+        world.flush_commands();
+
+        let errors = world.get_resource::<Events<CommandError>>().unwrap();
+        assert_eq!(
+            errors.iter().collect::<Vec<_>>(),
+            [&CommandError::ResourceNotFound {
+                operation: "remove_resource",
+                resource: type_name::<Missing>(),
+            }]
+        );
+    }
+
+    #[test]
+    fn commands_can_be_queued_from_other_threads() {
+        struct Spawned;
+
+        impl Resource for Spawned {}
+
+        let mut world = World::new();
+        world
+            .run_system_once(|mut commands: Commands| {
+                thread::scope(|scope| {
+                    for count in 1..=4 {
+                        let mut commands = commands.clone();
+                        scope.spawn(move || commands.send_event(count));
+                    }
+                });
+                commands.insert_resource(Spawned);
+            })
+            .unwrap();
+
+        let mut events: Vec<_> = world.get_resource::<Events<i32>>().unwrap().iter().collect();
+        events.sort();
+        assert_eq!(events, [&1, &2, &3, &4]);
+        assert!(world.get_resource::<Spawned>().is_some());
+    }
+
+    #[test]
+    fn entities_spawned_by_commands_are_reserved_immediately() {
+        #[derive(Debug, PartialEq)]
+        struct Health(u32);
+
+        impl Component for Health {}
+
+        struct Marker;
+
+        impl Component for Marker {}
+
+        let mut world = World::new();
+        let existing = world.spawn((Health(1),));
+        world.despawn(existing);
+
+        world
+            .run_system_once(|mut commands: Commands| {
+                let entity = commands.spawn((Marker,));
+                commands.entity(entity).insert(Health(10)).remove::<Marker>();
+                commands.send_event(entity);
+            })
+            .unwrap();
+
+        // The index of the despawned entity is reused with the next generation
+        let entity = *world.get_resource::<Events<Entity>>().unwrap().iter().next().unwrap();
+        assert_eq!(entity.index(), existing.index());
+        assert_eq!(entity.generation(), 1);
+        assert!(world.is_alive(entity));
+        assert!(!world.is_alive(existing));
+        assert_eq!(world.get::<Health>(entity), Some(&Health(10)));
+        assert!(world.get::<Marker>(entity).is_none());
+
+        world
+            .run_system_once(move |mut commands: Commands| commands.entity(entity).despawn())
+            .unwrap();
+        assert!(!world.is_alive(entity));
+        assert_eq!(world.spawn((Marker,)).generation(), 2);
+    }
+}
@@ -1,9 +1,19 @@
+use crate::entity::Entity;
+use crate::world::World;
 use std::alloc::Layout;
 use std::any::TypeId;
 use std::collections::HashMap;
 use variadics_please::all_tuples;
 
-pub trait Component: 'static {}
+/// Implemented with `#[derive(Component)]`, which also accepts
+/// `#[component(require(Transform, Visibility))]` to declare required components.
+pub use flux_ecs_macros::Component;
+
+pub trait Component: 'static {
+    /// Inserts the components this component requires that the entity does not have yet, called
+    /// whenever the component is spawned or inserted.
+    fn insert_required(_world: &mut World, _entity: Entity) {}
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct ComponentId(pub usize);
@@ -14,13 +24,27 @@ pub struct ComponentInfo {
     pub type_id: TypeId,
     pub layout: Layout,
     pub name: &'static str,
-    // TODO: Add custom drop functions if needed
+    /// Drops a component in place, `None` if the component does not need to be dropped.
+    pub drop: Option<unsafe fn(*mut u8)>,
+}
+
+unsafe fn drop_component<T>(ptr: *mut u8) {
+    unsafe { ptr.cast::<T>().drop_in_place() }
 }
 
 pub trait ComponentBundle {
     fn register_components(registry: &mut ComponentRegistry) -> Vec<ComponentId>;
 
+    /// Pointers to the components of the bundle, in the order of
+    /// [`ComponentBundle::register_components`].
+    ///
+    /// # Safety
+    /// The components are copied out of the bundle, it must not be dropped afterwards.
     unsafe fn get_component_painters(&self) -> Vec<*const u8>;
+
+    /// Inserts the required components of every component of the bundle, see
+    /// [`Component::insert_required`].
+    fn insert_required(world: &mut World, entity: Entity);
 }
 
 macro_rules! impl_component_bundle_for_tuple {
@@ -36,6 +60,10 @@ macro_rules! impl_component_bundle_for_tuple {
 
                 vec![$($T as *const $T as *const u8),+]
             }
+
+            fn insert_required(world: &mut World, entity: Entity) {
+                $($T::insert_required(world, entity);)+
+            }
         }
     };
 }
@@ -59,6 +87,7 @@ impl ComponentRegistry {
                 type_id,
                 layout: Layout::new::<T>(),
                 name: std::any::type_name::<T>(),
+                drop: std::mem::needs_drop::<T>().then_some(drop_component::<T> as _),
             };
 
             self.infos.push(info);
@@ -1,28 +1,212 @@
 use crate::archetype::ArchetypeId;
+use crate::world::WorldId;
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::{Arc, Mutex, PoisonError};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+/// A handle to an entity of a [`World`](crate::world::World).
+///
+/// The index of a despawned entity is reused by a later spawn with the next generation, handles
+/// of the despawned entity are stale from then on and no longer resolve.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Entity {
     index: u32,
+    generation: u32,
+    world: WorldId,
 }
 
-pub(crate) struct EntityManager {
+impl Entity {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// How often the index was used by a despawned entity before.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// The world that spawned the entity.
+    pub fn world(&self) -> WorldId {
+        self.world
+    }
+}
+
+/// Formats the entity as its index and generation, e.g. `42v3`.
+impl Display for Entity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+impl Debug for Entity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Entity({self})")
+    }
+}
+
+/// Hands out entity handles, shared with the [`Commands`](crate::commands::Commands) of the world
+/// so that entities can be reserved before the command spawning them is applied.
+#[derive(Clone)]
+pub(crate) struct EntityReserver {
+    world: WorldId,
+    indices: Arc<Mutex<FreeIndices>>,
+}
+
+#[derive(Default)]
+struct FreeIndices {
+    /// The first index that was never handed out.
     next_index: u32,
+    /// The indices of despawned entities with their next generation, reused before new indices
+    /// are allocated.
+    free: Vec<(u32, u32)>,
+}
+
+impl EntityReserver {
+    /// A handle to a new entity, it is not alive until it is spawned with its location set.
+    pub fn reserve(&self) -> Entity {
+        let mut indices = self.indices.lock().unwrap_or_else(PoisonError::into_inner);
+        let (index, generation) = indices.free.pop().unwrap_or_else(|| {
+            let index = indices.next_index;
+            indices.next_index = index.checked_add(1).expect("Too many entities");
+            (index, 0)
+        });
+
+        Entity {
+            index,
+            generation,
+            world: self.world,
+        }
+    }
+
+    fn free(&self, index: u32, generation: u32) {
+        self.indices
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .free
+            .push((index, generation));
+    }
+}
+
+pub(crate) struct EntityManager {
+    reserver: EntityReserver,
+    /// The current generation of every index ever spawned.
+    generations: Vec<u32>,
+    locations: EntityLocations,
 }
 
 impl EntityManager {
     pub fn new() -> Self {
-        Self { next_index: 0 }
+        Self {
+            reserver: EntityReserver {
+                world: WorldId::new(),
+                indices: Arc::default(),
+            },
+            generations: Vec::new(),
+            locations: EntityLocations::default(),
+        }
     }
 
+    pub fn world(&self) -> WorldId {
+        self.reserver.world
+    }
+
+    pub fn reserver(&self) -> EntityReserver {
+        self.reserver.clone()
+    }
+
+    /// Creates a new entity, its location must be set once it was added to an archetype.
     pub fn spawn(&mut self) -> Entity {
-        let entity = Entity {
-            index: self.next_index,
-        };
-        self.next_index += 1;
+        let entity = self.reserver.reserve();
+        self.spawn_reserved(entity);
         entity
     }
+
+    /// Makes the generation of a reserved entity current, its location must be set once it was
+    /// added to an archetype.
+    pub fn spawn_reserved(&mut self, entity: Entity) {
+        debug_assert_eq!(entity.world, self.world());
+        let index = entity.index as usize;
+        if index >= self.generations.len() {
+            self.generations.resize(index + 1, 0);
+        }
+        self.generations[index] = entity.generation;
+    }
+
+    /// Whether the entity was spawned by this world, panics in debug builds if it was not.
+    ///
+    /// Indices are only unique within a world, an entity of another world would address an
+    /// unrelated row.
+    fn owns(&self, entity: Entity) -> bool {
+        debug_assert_eq!(
+            entity.world,
+            self.world(),
+            "Entity {entity} of {:?} was passed to {:?}",
+            entity.world,
+            self.world()
+        );
+        entity.world == self.world()
+    }
+
+    /// Whether the entity belongs to this world and its index was not recycled since.
+    fn is_current(&self, entity: Entity) -> bool {
+        self.owns(entity)
+            && self.generations.get(entity.index as usize) == Some(&entity.generation)
+    }
+
+    pub fn location(&self, entity: Entity) -> Option<EntityLocation> {
+        if !self.is_current(entity) {
+            return None;
+        }
+        self.locations.get(entity)
+    }
+
+    pub fn set_location(&mut self, entity: Entity, location: EntityLocation) {
+        debug_assert_eq!(entity.world, self.world());
+        self.locations.insert(entity, location);
+    }
+
+    /// Forgets the entity and returns its last location, `None` if it did not exist. Bumps the
+    /// generation of its index and frees the index for reuse.
+    pub fn despawn(&mut self, entity: Entity) -> Option<EntityLocation> {
+        if !self.is_current(entity) {
+            return None;
+        }
+        let location = self.locations.remove(entity)?;
+
+        let generation = &mut self.generations[entity.index as usize];
+        *generation = generation.wrapping_add(1);
+        self.reserver.free(entity.index, *generation);
+        Some(location)
+    }
+}
+
+/// The archetype and row of every living entity, indexed by the entity index.
+///
+/// Kept up to date whenever an entity is spawned, moved between archetypes or takes over the
+/// row of a removed entity.
+#[derive(Default)]
+pub(crate) struct EntityLocations {
+    locations: Vec<Option<EntityLocation>>,
+}
+
+impl EntityLocations {
+    pub fn get(&self, entity: Entity) -> Option<EntityLocation> {
+        self.locations.get(entity.index as usize).copied().flatten()
+    }
+
+    pub fn insert(&mut self, entity: Entity, location: EntityLocation) {
+        let index = entity.index as usize;
+        if index >= self.locations.len() {
+            self.locations.resize(index + 1, None);
+        }
+        self.locations[index] = Some(location);
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<EntityLocation> {
+        self.locations.get_mut(entity.index as usize)?.take()
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct EntityLocation {
     pub archetype_id: ArchetypeId,
     pub row: usize,
@@ -0,0 +1,108 @@
+use crate::resource::Resource;
+use std::iter::Chain;
+use std::slice::Iter;
+
+/// A double buffered queue of events of type `T` stored as a resource.
+///
+/// Events stay readable for two frames so that systems running before the sender in the next
+/// frame see them as well: [`Events::update`], called for every registered event type at the
+/// start of each frame, drops the events sent before the previous update. Consumers that must
+/// handle an event only once drain it.
+pub struct Events<T: 'static> {
+    /// The events sent before the last update.
+    previous: Vec<T>,
+    /// The events sent since the last update.
+    current: Vec<T>,
+}
+
+impl<T: Send + Sync + 'static> Resource for Events<T> {}
+
+impl<T: 'static> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+        }
+    }
+}
+
+impl<T: 'static> Events<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    /// Drops the events sent before the previous update, the events sent since are kept for
+    /// one more update.
+    pub fn update(&mut self) {
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+    }
+
+    /// The events in the order they were sent.
+    pub fn iter(&self) -> Chain<Iter<'_, T>, Iter<'_, T>> {
+        self.previous.iter().chain(&self.current)
+    }
+
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.previous.drain(..).chain(self.current.drain(..))
+    }
+
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty() && self.current.is_empty()
+    }
+}
+
+impl<'a, T: 'static> IntoIterator for &'a Events<T> {
+    type Item = &'a T;
+    type IntoIter = Chain<Iter<'a, T>, Iter<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::World;
+
+    #[test]
+    fn events_expire_after_two_updates() {
+        let mut events = Events::new();
+        events.send(1);
+        events.update();
+        events.send(2);
+        assert_eq!(events.iter().collect::<Vec<_>>(), [&1, &2]);
+
+        events.update();
+        assert_eq!(events.iter().collect::<Vec<_>>(), [&2]);
+        events.update();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn the_world_updates_the_events_it_created() {
+        let mut world = World::new();
+        world.send_event(1);
+        world.add_event::<u8>();
+
+        world.update_events();
+        assert_eq!(world.get_resource::<Events<i32>>().unwrap().len(), 1);
+        world.update_events();
+        assert!(world.get_resource::<Events<i32>>().unwrap().is_empty());
+        assert!(world.get_resource::<Events<u8>>().is_some());
+    }
+}
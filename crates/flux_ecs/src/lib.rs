@@ -1,13 +1,22 @@
+// Lets the derive macros refer to `::flux_ecs` from within this crate
+extern crate self as flux_ecs;
+
+pub mod app;
 mod archetype;
 mod archetype_graph;
 mod archetypes;
+pub mod changes;
 pub mod commands;
 pub mod component;
-mod entity;
+pub mod entity;
+pub mod event;
+pub mod logging;
 pub mod module;
 pub mod plugin;
 pub mod query;
 pub mod resource;
 pub mod schedule;
+pub mod storage;
 pub mod system;
+pub mod tick;
 pub mod world;
@@ -0,0 +1,167 @@
+use crate::resource::Resource;
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Log targets used by the ECS.
+pub mod targets {
+    pub const SCHEDULE: &str = "flux_ecs::schedule";
+    pub const WORLD: &str = "flux_ecs::world";
+}
+
+/// Per-target log levels.
+///
+/// Targets are matched by their `::` separated path, the most specific configured target wins.
+/// Setting a level for `flux_renderer` therefore also applies to `flux_renderer::swapchain`
+/// unless that target has its own level.
+///
+/// The settings are shared with the logger installed by [`init`], changing a level on the
+/// resource takes effect immediately.
+#[derive(Clone)]
+pub struct LogSettings {
+    filter: Arc<RwLock<LogFilter>>,
+}
+
+impl Resource for LogSettings {}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self::new(LevelFilter::Info)
+    }
+}
+
+impl LogSettings {
+    pub fn new(default_level: LevelFilter) -> Self {
+        Self {
+            filter: Arc::new(RwLock::new(LogFilter {
+                default_level,
+                targets: Vec::new(),
+            })),
+        }
+    }
+
+    #[must_use]
+    pub fn with_target(mut self, target: impl Into<String>, level: LevelFilter) -> Self {
+        self.set_level(target, level);
+        self
+    }
+
+    pub fn set_level(&mut self, target: impl Into<String>, level: LevelFilter) {
+        let target = target.into();
+        let mut filter = self.filter.write().unwrap_or_else(PoisonError::into_inner);
+
+        match filter.targets.iter_mut().find(|(t, _)| *t == target) {
+            Some((_, existing)) => *existing = level,
+            None => filter.targets.push((target, level)),
+        }
+    }
+
+    /// Removes the level of the given target, it will fall back to its parent target or the
+    /// default level.
+    pub fn clear_level(&mut self, target: &str) {
+        let mut filter = self.filter.write().unwrap_or_else(PoisonError::into_inner);
+        filter.targets.retain(|(t, _)| t != target);
+    }
+
+    pub fn set_default_level(&mut self, level: LevelFilter) {
+        self.filter
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .default_level = level;
+    }
+
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.filter
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .level_for(target)
+    }
+}
+
+struct LogFilter {
+    default_level: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .filter(|(configured, _)| matches_target(configured, target))
+            .max_by_key(|(configured, _)| configured.len())
+            .map_or(self.default_level, |(_, level)| *level)
+    }
+}
+
+fn matches_target(configured: &str, target: &str) -> bool {
+    match target.strip_prefix(configured) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+/// A logger that filters records using [`LogSettings`] before forwarding them to `inner`.
+pub struct FilteredLogger<L: Log> {
+    inner: L,
+    filter: Arc<RwLock<LogFilter>>,
+}
+
+impl<L: Log> Log for FilteredLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let level = self
+            .filter
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .level_for(metadata.target());
+
+        metadata.level() <= level && self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Installs `inner` as the global logger, filtered by `settings`.
+///
+/// The inner logger should let every level through, filtering is done by the settings.
+pub fn init<L: Log + 'static>(inner: L, settings: &LogSettings) -> Result<(), SetLoggerError> {
+    log::set_boxed_logger(Box::new(FilteredLogger {
+        inner,
+        filter: Arc::clone(&settings.filter),
+    }))?;
+    log::set_max_level(LevelFilter::Trace);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn most_specific_target_wins() {
+        let settings = LogSettings::new(LevelFilter::Info)
+            .with_target("flux_renderer", LevelFilter::Debug)
+            .with_target("flux_renderer::swapchain", LevelFilter::Off);
+
+        assert_eq!(settings.level_for("flux_ecs::schedule"), LevelFilter::Info);
+        assert_eq!(settings.level_for("flux_renderer::device"), LevelFilter::Debug);
+        assert_eq!(settings.level_for("flux_renderer::swapchain"), LevelFilter::Off);
+        assert_eq!(settings.level_for("flux_renderer_extra"), LevelFilter::Info);
+    }
+
+    #[test]
+    fn changes_are_shared_with_clones() {
+        let settings = LogSettings::default();
+        let mut handle = settings.clone();
+
+        handle.set_level("flux_ecs", LevelFilter::Trace);
+
+        assert_eq!(settings.level_for("flux_ecs::world"), LevelFilter::Trace);
+    }
+}
@@ -1,9 +1,12 @@
 use crate::archetype::{Archetype, ArchetypeId};
+#[cfg(debug_assertions)]
+use crate::archetype::Column;
 use crate::component::{Component, ComponentId};
 use crate::entity::Entity;
 use crate::system::parameter::SystemParam;
 use crate::world::World;
 use std::marker::PhantomData;
+use std::ops::Range;
 use variadics_please::all_tuples;
 
 pub unsafe trait QueryData {
@@ -14,17 +17,44 @@ pub unsafe trait QueryData {
 
     unsafe fn fetch<'w>(fetch: &mut Self::Fetch<'w>, row: usize) -> Self::Item<'w>;
 
+    /// The items of a run of consecutive rows, see [`Query::for_each_batched`].
+    type Batch<'w>;
+
+    /// Fetches the rows as slices of their columns.
+    ///
+    /// # Safety
+    /// `rows` must be in bounds of the archetype the fetch was created for.
+    unsafe fn fetch_batch<'w>(fetch: &mut Self::Fetch<'w>, rows: Range<usize>) -> Self::Batch<'w>;
+
     /// Gets the component accesses required by this query. Used for archetype matching and safety checks.
     /// # Returns
     /// A vector of tuples where each tuple containing:
     /// - `ComponentId`: The ID of the component.
     /// - `bool`: Whether the component is mutable (`true`) or read-only (`false`).
     fn get_access(world: &mut World) -> Vec<(ComponentId, bool)>;
+
+    /// The components an entity must have to match the query, every accessed component unless
+    /// the access is optional.
+    fn get_required(world: &mut World) -> Vec<ComponentId> {
+        Self::get_access(world)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
 }
 
+/// [`QueryData`] that only reads from the world and can be iterated through a shared reference
+/// to the query.
+///
+/// # Safety
+/// Implementors must not hand out mutable access to any component.
+pub unsafe trait ReadOnlyQueryData: QueryData {}
+
 #[doc(hidden)]
 pub struct ReadFetch<'w, T: Component> {
     column_ptr: *const T,
+    #[cfg(debug_assertions)]
+    column: &'w Column,
     _marker: PhantomData<&'w ()>,
 }
 
@@ -37,24 +67,43 @@ unsafe impl<T: Component> QueryData for &T {
         let column = archetype.columns().get(&component_id)?;
 
         Some(ReadFetch {
-            column_ptr: column.get_ptr(0).cast::<T>(),
+            column_ptr: column.as_ptr().cast::<T>(),
+            #[cfg(debug_assertions)]
+            column,
             _marker: PhantomData,
         })
     }
 
     #[inline]
     unsafe fn fetch<'w>(fetch: &mut Self::Fetch<'w>, row: usize) -> Self::Item<'w> {
+        #[cfg(debug_assertions)]
+        check_row(fetch.column, row);
+
         unsafe { &*fetch.column_ptr.add(row) }
     }
 
+    type Batch<'w> = &'w [T];
+
+    #[inline]
+    unsafe fn fetch_batch<'w>(fetch: &mut Self::Fetch<'w>, rows: Range<usize>) -> Self::Batch<'w> {
+        #[cfg(debug_assertions)]
+        check_rows(fetch.column, &rows);
+
+        unsafe { std::slice::from_raw_parts(fetch.column_ptr.add(rows.start), rows.len()) }
+    }
+
     fn get_access(world: &mut World) -> Vec<(ComponentId, bool)> {
         vec![(world.component_registry.register::<T>(), false)]
     }
 }
 
+unsafe impl<T: Component> ReadOnlyQueryData for &T {}
+
 #[doc(hidden)]
 pub struct WriteFetch<'w, T: Component> {
     column_ptr: *mut T,
+    #[cfg(debug_assertions)]
+    column: &'w Column,
     _marker: PhantomData<&'w mut ()>,
 }
 
@@ -68,35 +117,108 @@ unsafe impl<T: Component> QueryData for &mut T {
 
         Some(WriteFetch {
             // TODO: Maybe we have to use as *const T here?
-            column_ptr: column.get_mut_ptr(0).cast::<T>(),
+            column_ptr: column.as_ptr().cast_mut().cast::<T>(),
+            #[cfg(debug_assertions)]
+            column,
             _marker: PhantomData,
         })
     }
 
     #[inline]
     unsafe fn fetch<'w>(fetch: &mut Self::Fetch<'w>, row: usize) -> Self::Item<'w> {
+        #[cfg(debug_assertions)]
+        check_row(fetch.column, row);
+
         unsafe { &mut *fetch.column_ptr.add(row) }
     }
 
+    type Batch<'w> = &'w mut [T];
+
+    #[inline]
+    unsafe fn fetch_batch<'w>(fetch: &mut Self::Fetch<'w>, rows: Range<usize>) -> Self::Batch<'w> {
+        #[cfg(debug_assertions)]
+        check_rows(fetch.column, &rows);
+
+        unsafe { std::slice::from_raw_parts_mut(fetch.column_ptr.add(rows.start), rows.len()) }
+    }
+
     fn get_access(world: &mut World) -> Vec<(ComponentId, bool)> {
         vec![(world.component_registry.register::<T>(), true)]
     }
 }
 
+/// Validates a fetched row through the bounds checked column accessor in debug builds.
+#[cfg(debug_assertions)]
+fn check_row(column: &Column, row: usize) {
+    if column.checked_get_ptr(row).is_none() {
+        panic!("Query fetched row {row} of a column with {} rows", column.len());
+    }
+}
+
+#[cfg(debug_assertions)]
+fn check_rows(column: &Column, rows: &Range<usize>) {
+    if rows.end > column.len() {
+        panic!("Query fetched rows {rows:?} of a column with {} rows", column.len());
+    }
+}
+
 unsafe impl QueryData for Entity {
     type Item<'w> = Entity;
-    type Fetch<'w> = *const Entity;
+    type Fetch<'w> = &'w [Entity];
 
     unsafe fn new_fetch<'w>(
         _world: &'w World,
         archetype: &'w Archetype,
     ) -> Option<Self::Fetch<'w>> {
-        Some(archetype.entities().as_ptr())
+        Some(archetype.entities())
     }
 
     #[inline]
     unsafe fn fetch<'w>(fetch: &mut Self::Fetch<'w>, row: usize) -> Self::Item<'w> {
-        unsafe { *fetch.add(row) }
+        debug_assert!(row < fetch.len(), "Query fetched row {row} of {} entities", fetch.len());
+        unsafe { *fetch.get_unchecked(row) }
+    }
+
+    type Batch<'w> = &'w [Entity];
+
+    #[inline]
+    unsafe fn fetch_batch<'w>(fetch: &mut Self::Fetch<'w>, rows: Range<usize>) -> Self::Batch<'w> {
+        &fetch[rows]
+    }
+
+    fn get_access(_world: &mut World) -> Vec<(ComponentId, bool)> {
+        Vec::new()
+    }
+}
+
+unsafe impl ReadOnlyQueryData for Entity {}
+
+/// Yields whether the entity has the component `T` without accessing it. Unlike `&T` it does not
+/// restrict the matched entities.
+pub struct Has<T: Component>(PhantomData<T>);
+
+unsafe impl<T: Component> QueryData for Has<T> {
+    type Item<'w> = bool;
+    type Fetch<'w> = bool;
+
+    unsafe fn new_fetch<'w>(world: &'w World, archetype: &'w Archetype) -> Option<Self::Fetch<'w>> {
+        let has_component = world
+            .component_registry
+            .get_id::<T>()
+            .is_some_and(|component_id| archetype.has_component(component_id));
+        Some(has_component)
+    }
+
+    #[inline]
+    unsafe fn fetch<'w>(fetch: &mut Self::Fetch<'w>, _row: usize) -> Self::Item<'w> {
+        *fetch
+    }
+
+    type Batch<'w> = bool;
+
+    #[inline]
+    unsafe fn fetch_batch<'w>(fetch: &mut Self::Fetch<'w>, _rows: Range<usize>) -> Self::Batch<'w> {
+        *fetch
     }
 
     fn get_access(_world: &mut World) -> Vec<(ComponentId, bool)> {
@@ -104,6 +226,45 @@ unsafe impl QueryData for Entity {
     }
 }
 
+unsafe impl<T: Component> ReadOnlyQueryData for Has<T> {}
+
+/// Yields the component if the entity has it. Unlike `&T` it does not restrict the matched
+/// entities.
+unsafe impl<T: Component> QueryData for Option<&T> {
+    type Item<'w> = Option<&'w T>;
+    type Fetch<'w> = Option<ReadFetch<'w, T>>;
+
+    unsafe fn new_fetch<'w>(world: &'w World, archetype: &'w Archetype) -> Option<Self::Fetch<'w>> {
+        Some(unsafe { <&T>::new_fetch(world, archetype) })
+    }
+
+    #[inline]
+    unsafe fn fetch<'w>(fetch: &mut Self::Fetch<'w>, row: usize) -> Self::Item<'w> {
+        fetch
+            .as_mut()
+            .map(|fetch| unsafe { <&T>::fetch(fetch, row) })
+    }
+
+    type Batch<'w> = Option<&'w [T]>;
+
+    #[inline]
+    unsafe fn fetch_batch<'w>(fetch: &mut Self::Fetch<'w>, rows: Range<usize>) -> Self::Batch<'w> {
+        fetch
+            .as_mut()
+            .map(|fetch| unsafe { <&T>::fetch_batch(fetch, rows) })
+    }
+
+    fn get_access(world: &mut World) -> Vec<(ComponentId, bool)> {
+        <&T>::get_access(world)
+    }
+
+    fn get_required(_world: &mut World) -> Vec<ComponentId> {
+        Vec::new()
+    }
+}
+
+unsafe impl<T: Component> ReadOnlyQueryData for Option<&T> {}
+
 macro_rules! impl_query_data_for_tuple {
     ($($T:ident),+) => {
         #[allow(non_snake_case)]
@@ -128,6 +289,16 @@ macro_rules! impl_query_data_for_tuple {
                 }
             }
 
+            type Batch<'w> = ($($T::Batch<'w>,)+);
+
+            #[inline]
+            unsafe fn fetch_batch<'w>(fetch: &mut Self::Fetch<'w>, rows: Range<usize>) -> Self::Batch<'w> {
+                unsafe {
+                    let ($($T,)+) = fetch;
+                    ($($T::fetch_batch($T, rows.clone()),)+)
+                }
+            }
+
             fn get_access(world: &mut World) -> Vec<(ComponentId, bool)> {
                 let mut access = Vec::new();
                 $(access.extend($T::get_access(world));)+
@@ -135,33 +306,92 @@ macro_rules! impl_query_data_for_tuple {
                 // TODO: Check for mutability conflicts
                 access
             }
+
+            fn get_required(world: &mut World) -> Vec<ComponentId> {
+                let mut required = Vec::new();
+                $(required.extend($T::get_required(world));)+
+                required
+            }
         }
+
+        unsafe impl<$($T: ReadOnlyQueryData),+> ReadOnlyQueryData for ($($T,)+) {}
     }
 }
 
 all_tuples!(impl_query_data_for_tuple, 1, 15, T);
 
-pub struct QueryState<Q: QueryData> {
+/// Restricts the archetypes a query matches without fetching anything, the second parameter of
+/// [`Query`], e.g. `Query<&Position, (With<Player>, Without<Disabled>)>`.
+///
+/// Tuples of filters match if all of them match, see [`Or`] to match any.
+pub trait QueryFilter {
+    fn matches(world: &World, archetype: &Archetype) -> bool;
+}
+
+impl QueryFilter for () {
+    fn matches(_world: &World, _archetype: &Archetype) -> bool {
+        true
+    }
+}
+
+/// Matches entities that have the component `T`, without accessing it.
+pub struct With<T: Component>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for With<T> {
+    fn matches(world: &World, archetype: &Archetype) -> bool {
+        world
+            .component_registry
+            .get_id::<T>()
+            .is_some_and(|component_id| archetype.has_component(component_id))
+    }
+}
+
+/// Matches entities that do not have the component `T`.
+pub struct Without<T: Component>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Without<T> {
+    fn matches(world: &World, archetype: &Archetype) -> bool {
+        !With::<T>::matches(world, archetype)
+    }
+}
+
+/// Matches entities that match any of the filters of the tuple, e.g.
+/// `Or<(With<Enemy>, With<Projectile>)>`.
+pub struct Or<F>(PhantomData<F>);
+
+macro_rules! impl_query_filter_for_tuple {
+    ($($F:ident),+) => {
+        impl<$($F: QueryFilter),+> QueryFilter for ($($F,)+) {
+            fn matches(world: &World, archetype: &Archetype) -> bool {
+                $($F::matches(world, archetype))&&+
+            }
+        }
+
+        impl<$($F: QueryFilter),+> QueryFilter for Or<($($F,)+)> {
+            fn matches(world: &World, archetype: &Archetype) -> bool {
+                $($F::matches(world, archetype))||+
+            }
+        }
+    }
+}
+
+all_tuples!(impl_query_filter_for_tuple, 1, 15, F);
+
+pub struct QueryState<Q: QueryData, F: QueryFilter = ()> {
     matching_archetypes: Vec<ArchetypeId>,
-    _marker: PhantomData<Q>,
+    _marker: PhantomData<(Q, F)>,
 }
 
-impl<Q: QueryData> QueryState<Q> {
+impl<Q: QueryData, F: QueryFilter> QueryState<Q, F> {
+    /// Matches the archetypes with every component required by `Q` that pass the filter `F`.
     pub fn new(world: &mut World) -> Self {
-        let required_access = Q::get_access(world);
-        let required_ids = required_access
-            .iter()
-            .map(|(id, _)| *id)
-            .collect::<Vec<_>>();
+        let required_ids = Q::get_required(world);
 
         let matching_archetypes = world
             .archetypes()
             .iter()
-            .filter(|archetype| {
-                required_ids
-                    .iter()
-                    .all(|req_id| archetype.has_component(*req_id))
-            })
+            .with_components(&required_ids)
+            .filter(|archetype| F::matches(world, archetype))
             .map(Archetype::id)
             .collect();
 
@@ -170,38 +400,111 @@ impl<Q: QueryData> QueryState<Q> {
             _marker: PhantomData,
         }
     }
+
+    pub fn matching_archetypes(&self) -> &[ArchetypeId] {
+        &self.matching_archetypes
+    }
 }
 
-pub struct Query<'world, 'state, Q: QueryData> {
+pub struct Query<'world, 'state, Q: QueryData, F: QueryFilter = ()> {
     world: &'world World,
-    state: &'state QueryState<Q>,
+    state: &'state QueryState<Q, F>,
+}
+
+impl<'world, 'state, Q: QueryData, F: QueryFilter> Query<'world, 'state, Q, F> {
+    /// Iterates the matching entities without consuming the query.
+    pub fn iter(&self) -> QueryIter<'_, 'state, Q>
+    where
+        Q: ReadOnlyQueryData,
+    {
+        QueryIter::new(self.world, &self.state.matching_archetypes)
+    }
+
+    /// Iterates the matching entities with mutable access, the items borrow the query so at most
+    /// one such iterator exists at a time.
+    pub fn iter_mut(&mut self) -> QueryIter<'_, 'state, Q> {
+        QueryIter::new(self.world, &self.state.matching_archetypes)
+    }
+
+    /// Calls `f` with the matching rows in batches of up to `N` rows, each component is handed
+    /// over as a slice of its column, e.g. `(&mut [Position], &[Velocity])`.
+    ///
+    /// Working on contiguous slices lets the compiler vectorize the loop body and avoids the
+    /// per-row bookkeeping of [`QueryIter`]. A batch never spans two archetypes, so the last
+    /// batch of every archetype can be shorter than `N`.
+    pub fn for_each_batched<'a, const N: usize>(&'a mut self, mut f: impl FnMut(Q::Batch<'a>)) {
+        const { assert!(N > 0, "Batches must contain at least one row") };
+
+        let world: &'a World = self.world;
+        for &archetype_id in &self.state.matching_archetypes {
+            let archetype = world
+                .archetypes()
+                .get(archetype_id)
+                .expect("Archetype not found");
+            let Some(mut fetch) = (unsafe { Q::new_fetch(world, archetype) }) else {
+                continue;
+            };
+
+            let len = archetype.len();
+            for start in (0..len).step_by(N) {
+                let rows = start..(start + N).min(len);
+                f(unsafe { Q::fetch_batch(&mut fetch, rows) });
+            }
+        }
+    }
 }
 
-impl<'world, 'state, Q: QueryData> IntoIterator for Query<'world, 'state, Q> {
+impl<'world, 'state, Q: QueryData, F: QueryFilter> IntoIterator for Query<'world, 'state, Q, F> {
     type Item = Q::Item<'world>;
     type IntoIter = QueryIter<'world, 'state, Q>;
 
     fn into_iter(self) -> Self::IntoIter {
-        QueryIter {
-            world: self.world,
-            state: self.state,
-            archetype_index: 0,
-            current_fetch: None,
-            current_archetype_len: 0,
-            row_index: 0,
-        }
+        QueryIter::new(self.world, &self.state.matching_archetypes)
+    }
+}
+
+impl<'a, 'state, Q: ReadOnlyQueryData, F: QueryFilter> IntoIterator
+    for &'a Query<'_, 'state, Q, F>
+{
+    type Item = Q::Item<'a>;
+    type IntoIter = QueryIter<'a, 'state, Q>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, 'state, Q: QueryData, F: QueryFilter> IntoIterator for &'a mut Query<'_, 'state, Q, F> {
+    type Item = Q::Item<'a>;
+    type IntoIter = QueryIter<'a, 'state, Q>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
     }
 }
 
 pub struct QueryIter<'w, 's, Q: QueryData> {
     world: &'w World,
-    state: &'s QueryState<Q>,
+    matching_archetypes: &'s [ArchetypeId],
     archetype_index: usize,
     current_fetch: Option<Q::Fetch<'w>>,
     current_archetype_len: usize,
     row_index: usize,
 }
 
+impl<'w, 's, Q: QueryData> QueryIter<'w, 's, Q> {
+    fn new(world: &'w World, matching_archetypes: &'s [ArchetypeId]) -> Self {
+        Self {
+            world,
+            matching_archetypes,
+            archetype_index: 0,
+            current_fetch: None,
+            current_archetype_len: 0,
+            row_index: 0,
+        }
+    }
+}
+
 impl<'w, 's, Q: QueryData> Iterator for QueryIter<'w, 's, Q> {
     type Item = Q::Item<'w>;
 
@@ -215,11 +518,11 @@ impl<'w, 's, Q: QueryData> Iterator for QueryIter<'w, 's, Q> {
                 return Some(item);
             }
 
-            if self.archetype_index == self.state.matching_archetypes.len() {
+            if self.archetype_index == self.matching_archetypes.len() {
                 return None;
             }
 
-            let archetype_id = self.state.matching_archetypes[self.archetype_index];
+            let archetype_id = self.matching_archetypes[self.archetype_index];
             self.archetype_index += 1;
 
             let archetype = self
@@ -237,9 +540,9 @@ impl<'w, 's, Q: QueryData> Iterator for QueryIter<'w, 's, Q> {
     }
 }
 
-impl<Q: QueryData + 'static> SystemParam for Query<'_, '_, Q> {
-    type State = QueryState<Q>;
-    type Item<'world, 'state> = Query<'world, 'state, Q>;
+impl<Q: QueryData + 'static, F: QueryFilter + 'static> SystemParam for Query<'_, '_, Q, F> {
+    type State = QueryState<Q, F>;
+    type Item<'world, 'state> = Query<'world, 'state, Q, F>;
 
     fn init_state(world: &mut World) -> Self::State {
         QueryState::new(world)
@@ -252,3 +555,175 @@ impl<Q: QueryData + 'static> SystemParam for Query<'_, '_, Q> {
         Query { world, state }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Health(u32);
+
+    impl Component for Health {}
+
+    #[test]
+    fn queries_can_be_iterated_repeatedly() {
+        let mut world = World::new();
+        world.spawn((Health(10),));
+        world.spawn((Health(20),));
+
+        let state = QueryState::<&mut Health>::new(&mut world);
+        let mut query = Query {
+            world: &world,
+            state: &state,
+        };
+
+        for health in &mut query {
+            health.0 += 1;
+        }
+        for health in query.iter_mut() {
+            health.0 *= 2;
+        }
+
+        let read_state = QueryState::<(Entity, &Health)>::new(&mut world);
+        let read_query = Query {
+            world: &world,
+            state: &read_state,
+        };
+        let total: u32 = read_query.iter().map(|(_, health)| health.0).sum();
+
+        assert_eq!(total, 64);
+        assert_eq!((&read_query).into_iter().count(), 2);
+    }
+
+    #[test]
+    fn has_reports_component_presence() {
+        struct Selected;
+
+        impl Component for Selected {}
+
+        let mut world = World::new();
+        world.spawn((Health(1),));
+        world.spawn((Health(2), Selected));
+
+        let state = QueryState::<(&Health, Has<Selected>)>::new(&mut world);
+        let query = Query {
+            world: &world,
+            state: &state,
+        };
+
+        let mut selected: Vec<_> = query.iter().map(|(health, has)| (health.0, has)).collect();
+        selected.sort_unstable();
+        assert_eq!(selected, [(1, false), (2, true)]);
+    }
+
+    #[test]
+    fn optional_components_do_not_restrict_the_matched_entities() {
+        struct Armor(u32);
+
+        impl Component for Armor {}
+
+        let mut world = World::new();
+        world.spawn((Health(1),));
+        world.spawn((Health(2), Armor(5)));
+
+        let state = QueryState::<(&Health, Option<&Armor>)>::new(&mut world);
+        let query = Query {
+            world: &world,
+            state: &state,
+        };
+
+        let mut armor: Vec<_> = query
+            .iter()
+            .map(|(health, armor)| (health.0, armor.map(|armor| armor.0)))
+            .collect();
+        armor.sort_unstable();
+        assert_eq!(armor, [(1, None), (2, Some(5))]);
+    }
+
+    #[test]
+    fn optional_components_are_fetched_in_batches() {
+        struct Armor(u32);
+
+        impl Component for Armor {}
+
+        let mut world = World::new();
+        for i in 0..3 {
+            world.spawn((Health(i),));
+        }
+        for i in 0..2 {
+            world.spawn((Health(i), Armor(i)));
+        }
+
+        let state = QueryState::<(&Health, Option<&Armor>)>::new(&mut world);
+        let mut query = Query {
+            world: &world,
+            state: &state,
+        };
+
+        let (mut armor_total, mut unarmored) = (0, 0);
+        query.for_each_batched::<4>(|(health, armor)| match armor {
+            Some(armor) => {
+                assert_eq!(armor.len(), health.len());
+                armor_total += armor.iter().map(|armor| armor.0).sum::<u32>();
+            }
+            None => unarmored += health.len(),
+        });
+        assert_eq!((armor_total, unarmored), (1, 3));
+    }
+
+    #[test]
+    fn batches_cover_every_row() {
+        let mut world = World::new();
+        for i in 0..10 {
+            world.spawn((Health(i),));
+        }
+
+        let state = QueryState::<(Entity, &mut Health)>::new(&mut world);
+        let mut query = Query {
+            world: &world,
+            state: &state,
+        };
+
+        let mut batch_sizes = Vec::new();
+        query.for_each_batched::<4>(|(entities, health)| {
+            assert_eq!(entities.len(), health.len());
+            batch_sizes.push(health.len());
+            for health in health {
+                health.0 += 1;
+            }
+        });
+
+        assert_eq!(batch_sizes, [4, 4, 2]);
+        assert_eq!(query.iter_mut().map(|(_, health)| health.0).sum::<u32>(), 55);
+    }
+
+    #[test]
+    fn filters_restrict_the_matched_archetypes() {
+        struct Player;
+        struct Enemy;
+        struct Disabled;
+
+        impl Component for Player {}
+        impl Component for Enemy {}
+        impl Component for Disabled {}
+
+        let mut world = World::new();
+        world.spawn((Health(1), Player));
+        world.spawn((Health(2), Enemy));
+        world.spawn((Health(4), Enemy, Disabled));
+        world.spawn((Health(8),));
+
+        fn total<F: QueryFilter>(world: &mut World) -> u32 {
+            let state = QueryState::<&Health, F>::new(world);
+            let query = Query {
+                world,
+                state: &state,
+            };
+            query.iter().map(|health| health.0).sum()
+        }
+
+        assert_eq!(total::<With<Enemy>>(&mut world), 6);
+        assert_eq!(total::<(With<Enemy>, Without<Disabled>)>(&mut world), 2);
+        assert_eq!(total::<Or<(With<Player>, With<Disabled>)>>(&mut world), 5);
+        assert_eq!(total::<Without<Player>>(&mut world), 14);
+    }
+}
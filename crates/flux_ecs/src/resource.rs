@@ -1,27 +1,95 @@
-use crate::system::parameter::SystemParam;
+use crate::system::parameter::{SystemAccess, SystemParam};
 use crate::world::World;
 use std::any::{Any, TypeId, type_name};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::marker::PhantomData;
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
+use std::thread::{self, ThreadId};
 
-pub trait Resource: 'static {}
+/// Implemented with `#[derive(Resource)]`.
+pub use flux_ecs_macros::Resource;
+
+/// A resource that can be accessed from any thread.
+pub trait Resource: Send + Sync + 'static {}
+
+/// A resource that must stay on the thread it was inserted on, e.g. window and event loop
+/// handles. Accessing it from another thread panics.
+pub trait NonSendResource: 'static {}
+
+struct NonSendEntry {
+    owner: ThreadId,
+    value: Box<dyn Any>,
+}
+
+impl NonSendEntry {
+    fn assert_owner<T>(&self) {
+        assert_eq!(
+            self.owner,
+            thread::current().id(),
+            "Non-send resource {} accessed from a thread other than the one it was inserted on",
+            type_name::<T>()
+        );
+    }
+}
+
+/// A stored resource, see [`Resources::iter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResourceInfo {
+    pub type_id: TypeId,
+    pub name: &'static str,
+    pub non_send: bool,
+    /// Orders the resources by their first insertion, replacing a resource keeps its position.
+    pub insertion: u64,
+}
 
 pub struct Resources {
     // TODO: Use component id, but it can't be called `ComponentId` as its for components and resources
-    data: HashMap<TypeId, Box<dyn Any>>,
+    data: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    non_send: HashMap<TypeId, NonSendEntry>,
+    infos: HashMap<TypeId, ResourceInfo>,
+    insertions: u64,
 }
 
 impl Resources {
     pub fn new() -> Self {
         Self {
             data: HashMap::new(),
+            non_send: HashMap::new(),
+            infos: HashMap::new(),
+            insertions: 0,
         }
     }
 
+    /// The type names of all stored resources, including non-send resources, in no particular
+    /// order.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.infos.values().map(|info| info.name)
+    }
+
+    /// All stored resources, including non-send resources, in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = ResourceInfo> + use<> {
+        let mut infos: Vec<_> = self.infos.values().copied().collect();
+        infos.sort_unstable_by_key(|info| info.insertion);
+        infos.into_iter()
+    }
+
+    fn register<T: 'static>(&mut self, non_send: bool) {
+        let insertion = self.insertions;
+        self.infos
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| ResourceInfo {
+                type_id: TypeId::of::<T>(),
+                name: type_name::<T>(),
+                non_send,
+                insertion,
+            });
+        self.insertions += 1;
+    }
+
     pub fn insert<T: Resource>(&mut self, value: T) {
         self.data.insert(TypeId::of::<T>(), Box::new(value));
+        self.register::<T>(false);
     }
 
     pub fn get<T: Resource>(&self) -> Option<&T> {
@@ -37,11 +105,54 @@ impl Resources {
     }
 
     pub fn remove<T: Resource>(&mut self) -> Option<T> {
+        self.infos.remove(&TypeId::of::<T>());
         self.data
             .remove(&TypeId::of::<T>())
             .and_then(|boxed| boxed.downcast().ok())
             .map(|boxed| *boxed)
     }
+
+    pub fn insert_non_send<T: NonSendResource>(&mut self, value: T) {
+        if let Some(existing) = self.non_send.get(&TypeId::of::<T>()) {
+            existing.assert_owner::<T>();
+        }
+        self.register::<T>(true);
+        self.non_send.insert(
+            TypeId::of::<T>(),
+            NonSendEntry {
+                owner: thread::current().id(),
+                value: Box::new(value),
+            },
+        );
+    }
+
+    /// # Panics
+    /// Panics if the resource was inserted on another thread.
+    pub fn get_non_send<T: NonSendResource>(&self) -> Option<&T> {
+        let entry = self.non_send.get(&TypeId::of::<T>())?;
+        entry.assert_owner::<T>();
+        entry.value.downcast_ref()
+    }
+
+    /// # Panics
+    /// Panics if the resource was inserted on another thread.
+    pub fn get_non_send_mut<T: NonSendResource>(&mut self) -> Option<&mut T> {
+        let entry = self.non_send.get_mut(&TypeId::of::<T>())?;
+        entry.assert_owner::<T>();
+        entry.value.downcast_mut()
+    }
+
+    /// # Panics
+    /// Panics if the resource was inserted on another thread.
+    pub fn remove_non_send<T: NonSendResource>(&mut self) -> Option<T> {
+        let entry = self.non_send.get(&TypeId::of::<T>())?;
+        entry.assert_owner::<T>();
+        self.infos.remove(&TypeId::of::<T>());
+        self.non_send
+            .remove(&TypeId::of::<T>())
+            .and_then(|entry| entry.value.downcast().ok())
+            .map(|boxed| *boxed)
+    }
 }
 
 // TODO: This is more related to a query than a resource
@@ -99,6 +210,17 @@ impl<T: Resource> SystemParam for Res<'_, T> {
             .unwrap_or_else(|| panic!("Resource {} not found", type_name::<T>()));
         Res::new(resource)
     }
+
+    fn missing_resource(world: &World) -> Option<&'static str> {
+        world
+            .get_resource::<T>()
+            .is_none()
+            .then(type_name::<T>)
+    }
+
+    fn register_access(access: &mut SystemAccess) {
+        access.add_resource_read::<T>();
+    }
 }
 
 impl<T: Resource> SystemParam for Option<Res<'_, T>> {
@@ -116,4 +238,241 @@ impl<T: Resource> SystemParam for Option<Res<'_, T>> {
     ) -> Self::Item<'world, 'state> {
         world.get_resource::<T>().map(Res::new)
     }
+
+    fn register_access(access: &mut SystemAccess) {
+        access.add_resource_read::<T>();
+    }
+}
+
+/// Exclusive access to a resource, no other parameter of the system may access the same resource.
+pub struct ResMut<'world, T: Resource> {
+    resource: &'world mut T,
+}
+
+impl<'world, T: Resource + Debug> Debug for ResMut<'world, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResMut")
+            .field("resource", &self.resource)
+            .finish()
+    }
+}
+
+impl<'world, T: Resource> ResMut<'world, T> {
+    pub fn new(resource: &'world mut T) -> Self {
+        ResMut { resource }
+    }
+}
+
+impl<T: Resource> Deref for ResMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.resource
+    }
+}
+
+impl<T: Resource> DerefMut for ResMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.resource
+    }
+}
+
+impl<T: Resource> SystemParam for ResMut<'_, T> {
+    type State = ();
+
+    type Item<'world, 'state> = ResMut<'world, T>;
+
+    fn init_state(_: &mut World) -> Self::State {}
+
+    fn get_param<'world, 'state>(
+        _state: &'state Self::State,
+        world: &'world mut World,
+    ) -> Self::Item<'world, 'state> {
+        let resource = world
+            .get_resource_mut::<T>()
+            .unwrap_or_else(|| panic!("Resource {} not found", type_name::<T>()));
+        ResMut::new(resource)
+    }
+
+    fn missing_resource(world: &World) -> Option<&'static str> {
+        world
+            .get_resource::<T>()
+            .is_none()
+            .then(type_name::<T>)
+    }
+
+    fn register_access(access: &mut SystemAccess) {
+        access.add_resource_write::<T>();
+    }
+}
+
+impl<T: Resource> SystemParam for Option<ResMut<'_, T>> {
+    type State = ();
+
+    type Item<'world, 'state> = Option<ResMut<'world, T>>;
+
+    fn init_state(_: &mut World) -> Self::State {}
+
+    fn get_param<'world, 'state>(
+        _state: &'state Self::State,
+        world: &'world mut World,
+    ) -> Self::Item<'world, 'state> {
+        world.get_resource_mut::<T>().map(ResMut::new)
+    }
+
+    fn register_access(access: &mut SystemAccess) {
+        access.add_resource_write::<T>();
+    }
+}
+
+/// Shared access to a [`NonSendResource`], systems using it must run on the thread the resource
+/// was inserted on.
+pub struct NonSend<'world, T: NonSendResource> {
+    resource: &'world T,
+}
+
+impl<'world, T: NonSendResource> NonSend<'world, T> {
+    pub fn new(resource: &'world T) -> Self {
+        NonSend { resource }
+    }
+}
+
+impl<T: NonSendResource> Deref for NonSend<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.resource
+    }
+}
+
+impl<T: NonSendResource> SystemParam for NonSend<'_, T> {
+    type State = ();
+
+    type Item<'world, 'state> = NonSend<'world, T>;
+
+    fn init_state(_: &mut World) -> Self::State {}
+
+    fn get_param<'world, 'state>(
+        _state: &'state Self::State,
+        world: &'world mut World,
+    ) -> Self::Item<'world, 'state> {
+        let resource = world
+            .get_non_send_resource::<T>()
+            .unwrap_or_else(|| panic!("Non-send resource {} not found", type_name::<T>()));
+        NonSend::new(resource)
+    }
+
+    fn missing_resource(world: &World) -> Option<&'static str> {
+        world
+            .get_non_send_resource::<T>()
+            .is_none()
+            .then(type_name::<T>)
+    }
+}
+
+impl<T: NonSendResource> SystemParam for Option<NonSend<'_, T>> {
+    type State = ();
+
+    type Item<'world, 'state> = Option<NonSend<'world, T>>;
+
+    fn init_state(_: &mut World) -> Self::State {}
+
+    fn get_param<'world, 'state>(
+        _state: &'state Self::State,
+        world: &'world mut World,
+    ) -> Self::Item<'world, 'state> {
+        world.get_non_send_resource::<T>().map(NonSend::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    struct Handle(Rc<u32>);
+
+    impl NonSendResource for Handle {}
+
+    #[test]
+    fn non_send_resources_are_stored_separately() {
+        let mut resources = Resources::new();
+        resources.insert_non_send(Handle(Rc::new(3)));
+
+        assert_eq!(*resources.get_non_send::<Handle>().unwrap().0, 3);
+        assert!(resources.remove_non_send::<Handle>().is_some());
+        assert!(resources.get_non_send::<Handle>().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "accessed from a thread other than the one it was inserted on")]
+    fn non_send_resources_panic_on_other_threads() {
+        let mut resources = Resources::new();
+        resources.insert_non_send(Handle(Rc::new(3)));
+        resources.non_send.get_mut(&TypeId::of::<Handle>()).unwrap().owner =
+            thread::spawn(|| thread::current().id()).join().unwrap();
+
+        resources.get_non_send::<Handle>();
+    }
+
+    struct Score(u32);
+
+    impl Resource for Score {}
+
+    struct Settings;
+
+    impl Resource for Settings {}
+
+    #[test]
+    fn resources_are_listed_in_insertion_order() {
+        let mut resources = Resources::new();
+        resources.insert(Score(1));
+        resources.insert_non_send(Handle(Rc::new(3)));
+        resources.insert(Settings);
+        resources.insert(Score(2));
+
+        let listed: Vec<_> = resources
+            .iter()
+            .map(|info| (info.name, info.non_send))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                (type_name::<Score>(), false),
+                (type_name::<Handle>(), true),
+                (type_name::<Settings>(), false),
+            ]
+        );
+
+        resources.remove::<Score>();
+        assert_eq!(resources.iter().next().unwrap().type_id, TypeId::of::<Handle>());
+    }
+
+    struct Missing;
+
+    impl Resource for Missing {}
+
+    #[test]
+    fn res_mut_writes_the_resource() {
+        let mut world = World::new();
+        world.add_resource(Score(1));
+
+        world
+            .run_system_once(|mut score: ResMut<Score>, missing: Option<ResMut<Missing>>| {
+                assert!(missing.is_none());
+                score.0 += 1;
+            })
+            .unwrap();
+
+        assert_eq!(world.get_resource::<Score>().unwrap().0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "writes resource")]
+    fn aliasing_res_and_res_mut_panics_on_initialize() {
+        let mut world = World::new();
+        world.add_resource(Score(1));
+
+        let _ = world.run_system_once(|_: Res<Score>, _: ResMut<Score>| {});
+    }
 }
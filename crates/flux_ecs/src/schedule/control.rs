@@ -0,0 +1,117 @@
+use crate::resource::Resource;
+use crate::schedule::ScheduleLabel;
+use std::collections::HashSet;
+
+/// A named group of systems, added with [`World::add_system_to_set`].
+///
+/// [`World::add_system_to_set`]: crate::world::World::add_system_to_set
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct SystemSet(pub &'static str);
+
+impl SystemSet {
+    /// Engine systems that prepare and submit frames.
+    pub const RENDERING: SystemSet = SystemSet("rendering");
+    pub const UI: SystemSet = SystemSet("ui");
+}
+
+/// Pauses and resumes schedules, e.g. to stop gameplay while a menu is open.
+///
+/// Systems of the [`always_run`](ScheduleControl::always_run) sets keep running in paused
+/// schedules, by default [`SystemSet::RENDERING`] and [`SystemSet::UI`].
+#[derive(Debug, Clone)]
+pub struct ScheduleControl {
+    paused: HashSet<Box<dyn ScheduleLabel>>,
+    always_run: HashSet<SystemSet>,
+}
+
+impl Resource for ScheduleControl {}
+
+impl Default for ScheduleControl {
+    fn default() -> Self {
+        Self {
+            paused: HashSet::new(),
+            always_run: HashSet::from([SystemSet::RENDERING, SystemSet::UI]),
+        }
+    }
+}
+
+impl ScheduleControl {
+    pub fn pause(&mut self, label: impl ScheduleLabel) {
+        self.paused.insert(Box::new(label));
+    }
+
+    pub fn resume(&mut self, label: &dyn ScheduleLabel) {
+        self.paused.remove(label);
+    }
+
+    pub fn is_paused(&self, label: &dyn ScheduleLabel) -> bool {
+        self.paused.contains(label)
+    }
+
+    /// Keeps the systems of the set running while their schedule is paused.
+    pub fn always_run(&mut self, set: SystemSet) {
+        self.always_run.insert(set);
+    }
+
+    /// Pauses the systems of the set together with their schedule again.
+    pub fn pause_with_schedule(&mut self, set: SystemSet) {
+        self.always_run.remove(&set);
+    }
+
+    /// Whether a system of `set` runs in the schedule.
+    pub fn should_run(&self, label: &dyn ScheduleLabel, set: Option<SystemSet>) -> bool {
+        !self.is_paused(label) || set.is_some_and(|set| self.always_run.contains(&set))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Commands;
+    use crate::resource::Res;
+    use crate::schedule::CoreSchedule;
+    use crate::world::World;
+
+    #[derive(Default)]
+    struct Runs(Vec<&'static str>);
+
+    impl Resource for Runs {}
+
+    fn record(name: &'static str) -> impl FnMut(Res<Runs>, Commands) {
+        move |runs: Res<Runs>, mut commands: Commands| {
+            let mut recorded = runs.0.clone();
+            recorded.push(name);
+            commands.insert_resource(Runs(recorded));
+        }
+    }
+
+    #[test]
+    fn paused_schedules_only_run_always_run_sets() {
+        let mut world = World::new();
+        world.add_resource(Runs::default());
+        world.add_resource(ScheduleControl::default());
+        world.add_system(CoreSchedule::Main, record("gameplay"));
+        world.add_system_to_set(
+            CoreSchedule::Main,
+            SystemSet::RENDERING,
+            record("rendering"),
+        );
+
+        world
+            .get_resource_mut::<ScheduleControl>()
+            .unwrap()
+            .pause(CoreSchedule::Main);
+        world.run_system(&CoreSchedule::Main).unwrap();
+        assert_eq!(world.get_resource::<Runs>().unwrap().0, ["rendering"]);
+
+        world
+            .get_resource_mut::<ScheduleControl>()
+            .unwrap()
+            .resume(&CoreSchedule::Main);
+        world.run_system(&CoreSchedule::Main).unwrap();
+        assert_eq!(
+            world.get_resource::<Runs>().unwrap().0,
+            ["rendering", "gameplay", "rendering"]
+        );
+    }
+}
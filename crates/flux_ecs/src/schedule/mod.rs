@@ -1,22 +1,161 @@
+use crate::schedule::control::SystemSet;
 use crate::system::systems::Systems;
-use crate::system::IntoSystem;
+use crate::system::{IntoSystem, SystemError};
 use crate::world::World;
+use std::any::{Any, TypeId};
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
 
-#[derive(Debug, Hash, Eq, PartialEq)]
-pub enum ScheduleLabel {
+pub mod control;
+
+/// Identifies a schedule. The engine runs the [`CoreSchedule`]s, plugins can define their own
+/// labels for a `Clone + Eq + Hash` type, register them with
+/// [`World::add_schedule`](crate::world::World::add_schedule) and run them with
+/// [`World::run_system`](crate::world::World::run_system):
+///
+/// ```
+/// # use flux_ecs::schedule::ScheduleLabel;
+/// #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+/// enum RenderLabel {
+///     ExtractPhase,
+/// }
+///
+/// impl ScheduleLabel for RenderLabel {}
+/// ```
+pub trait ScheduleLabel: DynScheduleLabel + Debug + Send + Sync + 'static {}
+
+/// The object safe parts of [`ScheduleLabel`], implemented for every label.
+pub trait DynScheduleLabel {
+    fn dyn_clone(&self) -> Box<dyn ScheduleLabel>;
+    fn dyn_eq(&self, other: &dyn ScheduleLabel) -> bool;
+    fn dyn_hash(&self, state: &mut dyn Hasher);
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: ScheduleLabel + Clone + Eq + Hash> DynScheduleLabel for T {
+    fn dyn_clone(&self) -> Box<dyn ScheduleLabel> {
+        Box::new(self.clone())
+    }
+
+    fn dyn_eq(&self, other: &dyn ScheduleLabel) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        // Labels of different types must not collide just because their values hash alike
+        TypeId::of::<T>().hash(&mut state);
+        self.hash(&mut state);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PartialEq for dyn ScheduleLabel {
+    fn eq(&self, other: &Self) -> bool {
+        self.dyn_eq(other)
+    }
+}
+
+impl Eq for dyn ScheduleLabel {}
+
+impl Hash for dyn ScheduleLabel {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.dyn_hash(state);
+    }
+}
+
+impl Clone for Box<dyn ScheduleLabel> {
+    fn clone(&self) -> Self {
+        self.dyn_clone()
+    }
+}
+
+/// The schedules of the engine, all of them exist in every [`World`].
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum CoreSchedule {
     Initialization,
+    /// Runs first every frame, e.g. to collect input and window events.
+    PreUpdate,
+    /// Gameplay and simulation.
+    Update,
+    /// Engine systems that prepare the frame, runs between Update and PostUpdate.
     Main,
+    /// Reacts to the changes of the frame, e.g. propagating transforms.
+    PostUpdate,
+    /// Records and submits the frame.
+    Render,
     Destroy,
 }
 
+impl ScheduleLabel for CoreSchedule {}
+
+impl CoreSchedule {
+    pub const ALL: [CoreSchedule; 7] = [
+        CoreSchedule::Initialization,
+        CoreSchedule::PreUpdate,
+        CoreSchedule::Update,
+        CoreSchedule::Main,
+        CoreSchedule::PostUpdate,
+        CoreSchedule::Render,
+        CoreSchedule::Destroy,
+    ];
+
+    /// The schedules run by [`App::update`](crate::app::App::update), in order.
+    pub const FRAME: [CoreSchedule; 5] = [
+        CoreSchedule::PreUpdate,
+        CoreSchedule::Update,
+        CoreSchedule::Main,
+        CoreSchedule::PostUpdate,
+        CoreSchedule::Render,
+    ];
+}
+
+/// The errors of all systems that failed or were skipped while running a schedule, in the order
+/// the systems ran.
+#[derive(Debug)]
+pub struct ScheduleError {
+    pub schedule: Box<dyn ScheduleLabel>,
+    pub errors: Vec<SystemError>,
+}
+
+impl ScheduleError {
+    /// The first failure of the schedule, later errors are often caused by it.
+    pub fn first(&self) -> &SystemError {
+        self.errors
+            .first()
+            .expect("A schedule error contains at least one system error")
+    }
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} system(s) failed in schedule {:?}, first error: {}",
+            self.errors.len(),
+            self.schedule,
+            self.first()
+        )
+    }
+}
+
+impl Error for ScheduleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.first())
+    }
+}
+
 #[derive(Default)]
 pub struct Schedule {
     pub systems: Systems,
 }
 
 pub struct Schedules {
-    schedule_map: HashMap<ScheduleLabel, Schedule>,
+    schedule_map: HashMap<Box<dyn ScheduleLabel>, Schedule>,
 }
 
 impl Default for Schedules {
@@ -27,41 +166,113 @@ impl Default for Schedules {
 
 impl Schedules {
     pub fn new() -> Self {
-        Self {
-            schedule_map: HashMap::from([
-                (ScheduleLabel::Initialization, Schedule::default()),
-                (ScheduleLabel::Main, Schedule::default()),
-            ]),
+        let mut schedules = Self {
+            schedule_map: HashMap::new(),
+        };
+        for label in CoreSchedule::ALL {
+            schedules.add_schedule(label);
         }
+        schedules
     }
 
-    pub fn add<M>(&mut self, schedule: ScheduleLabel, system: impl IntoSystem<M>) {
+    /// Registers an empty schedule, does nothing if the schedule exists already.
+    pub fn add_schedule(&mut self, label: impl ScheduleLabel) {
+        self.schedule_map.entry(Box::new(label)).or_default();
+    }
+
+    pub fn add<M>(&mut self, schedule: impl ScheduleLabel, system: impl IntoSystem<M>) {
         let schedules = self.schedule_map
-            .entry(schedule)
+            .entry(Box::new(schedule))
             .or_default();
 
         schedules.systems.add_system(system);
     }
 
-    pub fn get_schedule(&self, schedule: &ScheduleLabel) -> Option<&Schedule> {
+    pub fn add_to_set<M>(
+        &mut self,
+        schedule: impl ScheduleLabel,
+        set: SystemSet,
+        system: impl IntoSystem<M>,
+    ) {
+        self.schedule_map
+            .entry(Box::new(schedule))
+            .or_default()
+            .systems
+            .add_system_to_set(set, system);
+    }
+
+    pub fn get_schedule(&self, schedule: &dyn ScheduleLabel) -> Option<&Schedule> {
         self.schedule_map.get(schedule)
     }
 
-    pub fn run_schedule(&mut self, schedule: &ScheduleLabel, world: &mut World) {
-        if let Some(schedule) = self.schedule_map.get_mut(schedule) {
-            schedule.systems.run(world);
-        }
+    pub fn run_schedule(
+        &mut self,
+        label: &dyn ScheduleLabel,
+        world: &mut World,
+    ) -> Result<(), ScheduleError> {
+        let Some(schedule) = self.schedule_map.get_mut(label) else {
+            return Ok(());
+        };
+
+        schedule
+            .systems
+            .run(world)
+            .map_err(|errors| ScheduleError {
+                schedule: label.dyn_clone(),
+                errors,
+            })
     }
 
-    pub fn take_systems(&mut self, schedule: &ScheduleLabel) -> Option<Systems> {
+    pub fn take_systems(&mut self, schedule: &dyn ScheduleLabel) -> Option<Systems> {
         self.schedule_map.get_mut(schedule).map(|schedule| {
             std::mem::take(&mut schedule.systems)
         })
     }
 
-    pub fn put_systems(&mut self, schedule: &ScheduleLabel, systems: Systems) {
+    pub fn put_systems(&mut self, schedule: &dyn ScheduleLabel, systems: Systems) {
         if let Some(sched) = self.schedule_map.get_mut(schedule) {
             sched.systems = systems;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{Res, Resource};
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+    enum RenderLabel {
+        ExtractPhase,
+    }
+
+    impl ScheduleLabel for RenderLabel {}
+
+    #[derive(Default)]
+    struct Runs(Mutex<Vec<&'static str>>);
+
+    impl Resource for Runs {}
+
+    #[test]
+    fn custom_labels_are_distinct_schedules() {
+        let schedules = Schedules::new();
+        assert!(schedules.get_schedule(&CoreSchedule::Destroy).is_some());
+        assert!(schedules.get_schedule(&RenderLabel::ExtractPhase).is_none());
+
+        let extract: Box<dyn ScheduleLabel> = Box::new(RenderLabel::ExtractPhase);
+        assert!(*extract.clone() == *extract);
+        // Both are the first variant of their enum
+        assert!(!extract.dyn_eq(&CoreSchedule::Initialization));
+
+        let mut world = World::new();
+        world.add_resource(Runs::default());
+        world.add_schedule(RenderLabel::ExtractPhase);
+        world.add_system(RenderLabel::ExtractPhase, |runs: Res<Runs>| {
+            runs.0.lock().unwrap().push("extract")
+        });
+        world.run_system(&CoreSchedule::Initialization).unwrap();
+        world.run_system(&RenderLabel::ExtractPhase).unwrap();
+        assert_eq!(*world.get_resource::<Runs>().unwrap().0.lock().unwrap(), ["extract"]);
+    }
+}
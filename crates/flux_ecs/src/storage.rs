@@ -0,0 +1,37 @@
+use std::alloc::Layout;
+use std::ptr::NonNull;
+
+/// Allocates the component columns of archetypes, see [`World::with_storage_allocator`].
+///
+/// Routing the storage through a dedicated allocator keeps it apart from other allocations, e.g.
+/// to attribute it to a memory region or to place it in an arena.
+///
+/// # Safety
+/// A successful allocation must return memory valid for reads and writes of `layout`, which stays
+/// valid until it is passed to [`StorageAllocator::deallocate`].
+///
+/// [`World::with_storage_allocator`]: crate::world::World::with_storage_allocator
+pub unsafe trait StorageAllocator: Send + Sync {
+    /// Allocates memory for `layout`, its size is never zero. Returns `None` if the allocation
+    /// failed.
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>>;
+
+    /// # Safety
+    /// `ptr` must have been returned by [`StorageAllocator::allocate`] of this allocator with the
+    /// same `layout`.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+}
+
+/// Allocates from the global allocator, the default for every world.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GlobalStorage;
+
+unsafe impl StorageAllocator for GlobalStorage {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        NonNull::new(unsafe { std::alloc::alloc(layout) })
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { std::alloc::dealloc(ptr.as_ptr(), layout) }
+    }
+}
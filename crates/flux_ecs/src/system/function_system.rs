@@ -1,7 +1,9 @@
+use crate::tick::Tick;
 use crate::world::World;
 use crate::{
-    system::parameter::{SystemParam, SystemParamItem},
-    system::{IntoSystem, System},
+    system::meta::SystemMeta,
+    system::parameter::{SystemAccess, SystemParam, SystemParamItem},
+    system::{IntoSystem, System, SystemError},
 };
 use std::convert::Infallible;
 use std::error::Error;
@@ -24,6 +26,7 @@ where
     func: F,
     state: Option<FunctionSystemState<F::Param>>,
     name: &'static str,
+    last_run: Option<Tick>,
     _marker: PhantomData<fn() -> Marker>,
 }
 
@@ -46,6 +49,7 @@ where
             func: self,
             state: None,
             name: std::any::type_name::<F>(),
+            last_run: None,
             _marker: PhantomData,
         }
     }
@@ -56,34 +60,66 @@ where
     Marker: 'static,
     F: SystemParamFunction<Marker>,
 {
-    fn run(&mut self, world: &mut World) {
+    fn run(&mut self, world: &mut World) -> Result<(), SystemError> {
         if self.state.is_none() {
             self.initialize(world);
         }
 
+        if let Some(resource) = F::Param::missing_resource(world) {
+            return Err(SystemError::MissingResource {
+                system: self.name,
+                resource,
+            });
+        }
+
         let state = self
             .state
             .as_ref()
             .expect("FunctionSystem::run called before FunctionSystem::initialize");
+        let this_run = world.increment_change_tick();
+        let previous = world.set_running_system(Some(SystemMeta {
+            name: self.name,
+            this_run,
+            last_run: self.last_run,
+        }));
+        self.last_run = Some(this_run);
         let params = F::Param::get_param(&state.param, world);
-
-        if let Err(e) = self.func.run(params) {
-            panic!("Error in function system '{}': {}", self.name, e);
-        }
+        let result = self.func.run(params);
+        world.set_running_system(previous);
 
         // TODO: This is just a placeholder.
         F::Param::apply_buffers(&state.param, world);
+
+        result.map_err(|error| SystemError::Failed {
+            system: self.name,
+            source: Box::new(error),
+        })
     }
 
+    /// # Panics
+    /// Panics if a resource written by one parameter is accessed by another one.
     fn initialize(&mut self, world: &mut World) {
         if self.state.is_some() {
             return;
         }
 
+        let mut access = SystemAccess::default();
+        F::Param::register_access(&mut access);
+        if let Some(resource) = access.conflict() {
+            panic!(
+                "System '{}' writes resource {resource} while another parameter accesses it",
+                self.name
+            );
+        }
+
         self.state = Some(FunctionSystemState {
             param: F::Param::init_state(world),
         });
     }
+
+    fn last_run(&self) -> Option<Tick> {
+        self.last_run
+    }
 }
 
 macro_rules! impl_infallible_system_param_function {
@@ -0,0 +1,153 @@
+use crate::system::parameter::SystemParam;
+use crate::tick::Tick;
+use crate::world::World;
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+
+/// Describes the system that is currently running, recorded by the
+/// [`FunctionSystem`](crate::system::function_system::FunctionSystem) before it fetches its
+/// parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SystemMeta {
+    pub(crate) name: &'static str,
+    pub(crate) this_run: Tick,
+    pub(crate) last_run: Option<Tick>,
+}
+
+impl SystemMeta {
+    fn running(world: &World) -> SystemMeta {
+        world
+            .running_system()
+            .expect("System metadata parameters can only be used by a running system")
+    }
+}
+
+/// The name of the system taking this parameter, e.g. to identify the system in logs without a
+/// hardcoded string.
+///
+/// The name is the type name of the system function, see [`std::any::type_name`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemName(&'static str);
+
+impl SystemName {
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+
+    /// The name without its module path, e.g. `update_camera` for
+    /// `my_game::camera::update_camera`. Closures keep the name of their defining function,
+    /// `setup::{{closure}}`.
+    pub fn short(&self) -> &'static str {
+        let path = self.0.split('<').next().unwrap_or(self.0);
+        let start = path
+            .trim_end_matches("::{{closure}}")
+            .rfind("::")
+            .map_or(0, |index| index + 2);
+        &self.0[start..]
+    }
+}
+
+impl Deref for SystemName {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl Display for SystemName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl SystemParam for SystemName {
+    type State = ();
+    type Item<'world, 'state> = SystemName;
+
+    fn init_state(_world: &mut World) -> Self::State {}
+
+    fn get_param<'world, 'state>(
+        _state: &'state Self::State,
+        world: &'world mut World,
+    ) -> Self::Item<'world, 'state> {
+        SystemName(SystemMeta::running(world).name)
+    }
+}
+
+/// The change ticks of the current and the previous run of the system taking this parameter,
+/// see [`Tick`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemTicks {
+    this_run: Tick,
+    last_run: Option<Tick>,
+}
+
+impl SystemTicks {
+    pub fn this_run(&self) -> Tick {
+        self.this_run
+    }
+
+    /// The tick of the previous run, `None` on the first run.
+    pub fn last_run(&self) -> Option<Tick> {
+        self.last_run
+    }
+
+    pub fn is_first_run(&self) -> bool {
+        self.last_run.is_none()
+    }
+}
+
+impl SystemParam for SystemTicks {
+    type State = ();
+    type Item<'world, 'state> = SystemTicks;
+
+    fn init_state(_world: &mut World) -> Self::State {}
+
+    fn get_param<'world, 'state>(
+        _state: &'state Self::State,
+        world: &'world mut World,
+    ) -> Self::Item<'world, 'state> {
+        let meta = SystemMeta::running(world);
+        SystemTicks {
+            this_run: meta.this_run,
+            last_run: meta.last_run,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{IntoSystem, System};
+    use std::sync::{Arc, Mutex};
+
+    fn named_system(name: SystemName, ticks: SystemTicks) {
+        assert_eq!(name.short(), "named_system");
+        assert!(name.as_str().ends_with("meta::tests::named_system"));
+        assert!(ticks.is_first_run());
+    }
+
+    #[test]
+    fn systems_see_their_name_and_ticks() {
+        let mut world = World::new();
+        let mut system = named_system.into_system();
+        system.run(&mut world).unwrap();
+        assert_eq!(world.running_system(), None);
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = seen.clone();
+        let mut closure = (move |name: SystemName, ticks: SystemTicks| {
+            recorded.lock().unwrap().push((name.short(), ticks));
+        })
+        .into_system();
+        closure.run(&mut world).unwrap();
+        closure.run(&mut world).unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0].0, "systems_see_their_name_and_ticks::{{closure}}");
+        assert!(seen[0].1.is_first_run());
+        assert_eq!(seen[1].1.last_run(), Some(seen[0].1.this_run()));
+        assert_eq!(seen[1].1.this_run(), world.change_tick());
+    }
+}
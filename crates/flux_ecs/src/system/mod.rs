@@ -1,19 +1,99 @@
+use crate::tick::Tick;
 use crate::world::World;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 
 pub mod function_system;
+pub mod meta;
 pub mod parameter;
 pub mod systems;
 
 pub trait System: 'static {
-    fn run(&mut self, world: &mut World);
+    fn run(&mut self, world: &mut World) -> Result<(), SystemError>;
 
     fn initialize(&mut self, world: &mut World);
+
+    /// The [`World::change_tick`] of the last run, `None` if the system never ran. Anything that
+    /// changed after this tick was not observed by the system yet.
+    fn last_run(&self) -> Option<Tick>;
+}
+
+/// Reports a system that failed or could not run.
+#[derive(Debug)]
+pub enum SystemError {
+    /// The system itself returned an error.
+    Failed {
+        system: &'static str,
+        source: Box<dyn Error>,
+    },
+    /// A resource required by one of the system parameters does not exist, usually because the
+    /// system creating it failed earlier.
+    MissingResource {
+        system: &'static str,
+        resource: &'static str,
+    },
+}
+
+impl SystemError {
+    pub fn system(&self) -> &'static str {
+        match self {
+            SystemError::Failed { system, .. } | SystemError::MissingResource { system, .. } => {
+                system
+            }
+        }
+    }
 }
 
+impl Display for SystemError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SystemError::Failed { system, source } => {
+                write!(f, "System '{system}' failed: {source}")
+            }
+            SystemError::MissingResource { system, resource } => {
+                write!(f, "System '{system}' was skipped: resource {resource} does not exist")
+            }
+        }
+    }
+}
+
+impl Error for SystemError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SystemError::Failed { source, .. } => Some(source.as_ref()),
+            SystemError::MissingResource { .. } => None,
+        }
+    }
+}
+
+/// Converts functions and closures taking [`SystemParam`](parameter::SystemParam)s into systems.
+///
+/// Closures may capture their configuration, e.g.
+/// `world.add_system(CoreSchedule::Main, move |time: Res<Time>| { ... settings ... })`, the
+/// captured state lives as long as the system and persists between runs.
 pub trait IntoSystem<Marker>: Sized {
     type System: System;
 
     fn into_system(self) -> Self::System;
+
+    /// Boxes the system, e.g. to store systems of different types before adding them.
+    fn boxed(self) -> Box<dyn System> {
+        Box::new(self.into_system())
+    }
+}
+
+impl System for Box<dyn System> {
+    fn run(&mut self, world: &mut World) -> Result<(), SystemError> {
+        (**self).run(world)
+    }
+
+    fn initialize(&mut self, world: &mut World) {
+        (**self).initialize(world)
+    }
+
+    fn last_run(&self) -> Option<Tick> {
+        (**self).last_run()
+    }
 }
 
 // Every system can be converted into a system ... kinda obvious, isn't it?
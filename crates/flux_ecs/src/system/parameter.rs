@@ -1,4 +1,5 @@
 use crate::world::World;
+use std::any::{TypeId, type_name};
 use variadics_please::all_tuples;
 
 pub trait SystemParam: Sized {
@@ -14,6 +15,47 @@ pub trait SystemParam: Sized {
     ) -> Self::Item<'world, 'state>;
 
     fn apply_buffers(_state: &Self::State, _world: &mut World) {}
+
+    /// Returns the name of a resource this parameter requires but that does not exist in the
+    /// world, in which case the system is not run.
+    fn missing_resource(_world: &World) -> Option<&'static str> {
+        None
+    }
+
+    /// Records the resources the parameter reads or writes, see [`SystemAccess`].
+    fn register_access(_access: &mut SystemAccess) {}
+}
+
+/// The resources the parameters of a system access, collected when the system is initialized.
+///
+/// A resource that is written by one parameter must not be accessed by any other parameter of
+/// the same system, e.g. `(Res<T>, ResMut<T>)`, as both would point at the same value.
+#[derive(Debug, Default)]
+pub struct SystemAccess {
+    resources: Vec<(TypeId, &'static str, bool)>,
+}
+
+impl SystemAccess {
+    pub fn add_resource_read<T: 'static>(&mut self) {
+        self.resources.push((TypeId::of::<T>(), type_name::<T>(), false));
+    }
+
+    pub fn add_resource_write<T: 'static>(&mut self) {
+        self.resources.push((TypeId::of::<T>(), type_name::<T>(), true));
+    }
+
+    /// The name of the first resource that is written by one parameter and accessed by another.
+    pub fn conflict(&self) -> Option<&'static str> {
+        self.resources
+            .iter()
+            .enumerate()
+            .find(|&(index, &(type_id, _, write))| {
+                self.resources[index + 1..]
+                    .iter()
+                    .any(|&(other, _, other_write)| other == type_id && (write || other_write))
+            })
+            .map(|(_, &(_, name, _))| name)
+    }
 }
 
 pub type SystemParamItem<'world, 'state, P> = <P as SystemParam>::Item<'world, 'state>;
@@ -42,6 +84,14 @@ macro_rules! impl_system_param {
                 let ($($t,)*) = state;
                 $($T::apply_buffers($t, world);)*
             }
+
+            fn missing_resource(#[allow(unused_variables)] world: &World) -> Option<&'static str> {
+                None$(.or_else(|| $T::missing_resource(world)))*
+            }
+
+            fn register_access(#[allow(unused_variables)] access: &mut SystemAccess) {
+                $($T::register_access(access);)*
+            }
         }
     };
 }
@@ -1,4 +1,5 @@
-use crate::system::{IntoSystem, System};
+use crate::schedule::control::SystemSet;
+use crate::system::{IntoSystem, System, SystemError};
 use crate::world::World;
 
 #[derive(Default, PartialEq, Clone, Debug)]
@@ -11,6 +12,8 @@ pub enum CommandFlushTechnique {
 #[derive(Default)]
 pub struct Systems {
     pub(crate) systems: Vec<Box<dyn System>>,
+    /// The set of each system, parallel to `systems`.
+    sets: Vec<Option<SystemSet>>,
     command_flush_technique: CommandFlushTechnique,
 }
 
@@ -18,17 +21,42 @@ impl Systems {
     pub fn new(command_flush_technique: CommandFlushTechnique) -> Self {
         Self {
             systems: Vec::new(),
+            sets: Vec::new(),
             command_flush_technique,
         }
     }
 
     pub fn add_system<M>(&mut self, system: impl IntoSystem<M>) {
         self.systems.push(Box::new(IntoSystem::into_system(system)));
+        self.sets.push(None);
     }
 
-    pub fn run(&mut self, world: &mut World) {
-        for system in &mut self.systems {
-            system.run(world);
+    pub fn add_system_to_set<M>(&mut self, set: SystemSet, system: impl IntoSystem<M>) {
+        self.systems.push(Box::new(IntoSystem::into_system(system)));
+        self.sets.push(Some(set));
+    }
+
+    /// Runs every system, systems after a failing one still run. Returns the errors of all
+    /// systems that failed or were skipped.
+    pub fn run(&mut self, world: &mut World) -> Result<(), Vec<SystemError>> {
+        self.run_filtered(world, |_| true)
+    }
+
+    /// Like [`Systems::run`], but only runs the systems whose set passes `filter`.
+    pub fn run_filtered(
+        &mut self,
+        world: &mut World,
+        filter: impl Fn(Option<SystemSet>) -> bool,
+    ) -> Result<(), Vec<SystemError>> {
+        let mut errors = Vec::new();
+
+        for (system, set) in self.systems.iter_mut().zip(&self.sets) {
+            if !filter(*set) {
+                continue;
+            }
+            if let Err(error) = system.run(world) {
+                errors.push(error);
+            }
 
             if self.command_flush_technique == CommandFlushTechnique::AfterEach {
                 world.flush_commands()
@@ -38,5 +66,76 @@ impl Systems {
         if self.command_flush_technique == CommandFlushTechnique::AfterAll {
             world.flush_commands()
         }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Commands;
+    use crate::resource::{Res, Resource};
+
+    struct Device;
+
+    impl Resource for Device {}
+
+    #[derive(Debug)]
+    struct NoDevice;
+
+    impl std::fmt::Display for NoDevice {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "no suitable device")
+        }
+    }
+
+    impl std::error::Error for NoDevice {}
+
+    fn create_device(_: Commands) -> Result<(), NoDevice> {
+        Err(NoDevice)
+    }
+
+    fn use_device(_: Res<Device>) {}
+
+    #[test]
+    fn errors_of_all_systems_are_collected() {
+        let mut world = World::new();
+        let mut systems = Systems::default();
+        systems.add_system(create_device);
+        systems.add_system(use_device);
+
+        let errors = systems.run(&mut world).unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], SystemError::Failed { .. }));
+        assert!(matches!(
+            errors[1],
+            SystemError::MissingResource { resource, .. }
+                if resource == std::any::type_name::<Device>()
+        ));
+    }
+
+    #[test]
+    fn capturing_closures_and_boxed_systems_run() {
+        struct Counter(u32);
+
+        impl Resource for Counter {}
+
+        let step = 5;
+        let mut runs = 0;
+        let count = move |counter: Res<Counter>, mut commands: Commands| {
+            runs += 1;
+            commands.insert_resource(Counter(counter.0 + step * runs));
+        };
+
+        let mut world = World::new();
+        world.add_resource(Counter(0));
+        let mut systems = Systems::default();
+        systems.add_system(count.boxed());
+
+        systems.run(&mut world).unwrap();
+        systems.run(&mut world).unwrap();
+        assert_eq!(world.get_resource::<Counter>().unwrap().0, 15);
     }
 }
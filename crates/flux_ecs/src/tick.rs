@@ -0,0 +1,73 @@
+use std::fmt::{Display, Formatter};
+
+/// A point in the change history of a [`World`](crate::world::World). The world tick advances
+/// every time a system runs, a system remembers the tick of its last run to tell what changed
+/// since, see [`System::last_run`](crate::system::System::last_run).
+///
+/// Ticks wrap around, compare them with [`Tick::is_newer_than`] instead of their values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Tick(u32);
+
+impl Tick {
+    pub const fn new(tick: u32) -> Self {
+        Self(tick)
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Whether this tick lies after `last_run` as seen from `this_run`, e.g. whether a change
+    /// happened after a system last ran. Ticks further than `u32::MAX / 2` in the past are
+    /// treated as old.
+    pub fn is_newer_than(self, last_run: Tick, this_run: Tick) -> bool {
+        let age = this_run.0.wrapping_sub(self.0);
+        let since_last_run = this_run.0.wrapping_sub(last_run.0);
+        age < since_last_run && age <= u32::MAX / 2
+    }
+
+    pub(crate) fn increment(&mut self) -> Tick {
+        self.0 = self.0.wrapping_add(1);
+        *self
+    }
+}
+
+impl Display for Tick {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{IntoSystem, System};
+    use crate::world::World;
+
+    #[test]
+    fn ticks_compare_across_wrap_around() {
+        let last_run = Tick::new(u32::MAX - 1);
+        let this_run = Tick::new(3);
+
+        assert!(Tick::new(u32::MAX).is_newer_than(last_run, this_run));
+        assert!(Tick::new(2).is_newer_than(last_run, this_run));
+        assert!(!Tick::new(u32::MAX - 1).is_newer_than(last_run, this_run));
+        assert!(!Tick::new(10).is_newer_than(Tick::new(5), Tick::new(8)));
+    }
+
+    #[test]
+    fn systems_remember_the_tick_of_their_last_run() {
+        let mut world = World::new();
+        let mut first = (|| {}).into_system();
+        let mut second = (|| {}).into_system();
+        assert_eq!(first.last_run(), None);
+
+        first.run(&mut world).unwrap();
+        second.run(&mut world).unwrap();
+        let first_run = first.last_run().unwrap();
+        let second_run = second.last_run().unwrap();
+
+        assert_eq!(second_run, world.change_tick());
+        assert!(second_run.is_newer_than(first_run, world.change_tick()));
+    }
+}
@@ -1,12 +1,46 @@
+use crate::archetype::Archetype;
 use crate::archetypes::Archetypes;
-use crate::commands::{Command, CommandQueue};
-use crate::component::{ComponentBundle, ComponentRegistry};
-use crate::entity::{Entity, EntityManager};
+use crate::changes::{ChangeSubscribers, WorldChange};
+use crate::commands::{Command, CommandError, CommandQueue};
+use crate::component::{Component, ComponentBundle, ComponentRegistry};
+use crate::entity::{Entity, EntityLocation, EntityManager, EntityReserver};
+use crate::event::Events;
+use crate::logging::targets;
 use crate::module::Module;
 use crate::plugin::Plugin;
-use crate::resource::{Resource, Resources};
-use crate::schedule::{ScheduleLabel, Schedules};
-use crate::system::IntoSystem;
+use crate::query::{QueryData, QueryFilter, QueryState};
+use crate::resource::{NonSendResource, Resource, ResourceInfo, Resources};
+use crate::schedule::control::{ScheduleControl, SystemSet};
+use crate::schedule::{ScheduleError, ScheduleLabel, Schedules};
+use crate::storage::StorageAllocator;
+use crate::system::meta::SystemMeta;
+use crate::system::{IntoSystem, System, SystemError};
+use crate::tick::Tick;
+use log::{debug, trace, warn};
+use std::any::{TypeId, type_name};
+use std::mem::ManuallyDrop;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::Receiver;
+
+/// Identifies a [`World`], unique within the process.
+///
+/// Every [`Entity`] is stamped with the id of the world that spawned it, passing it to another
+/// world panics in debug builds and is treated as a missing entity in release builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WorldId(u32);
+
+impl WorldId {
+    pub(crate) fn new() -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        let id = NEXT.fetch_add(1, Ordering::Relaxed);
+        assert_ne!(id, u32::MAX, "Too many worlds were created");
+        Self(id)
+    }
+}
+
+/// Calls [`Events::update`] on the events of one type, see [`World::add_event`].
+type EventUpdater = fn(&mut World);
 
 pub struct World {
     entity_manager: EntityManager,
@@ -15,6 +49,10 @@ pub struct World {
     resources: Resources,
     schedules: Schedules,
     command_queue: CommandQueue,
+    change_subscribers: ChangeSubscribers,
+    change_tick: Tick,
+    running_system: Option<SystemMeta>,
+    event_updaters: Vec<(TypeId, EventUpdater)>,
 }
 
 impl Default for World {
@@ -25,18 +63,49 @@ impl Default for World {
 
 impl World {
     pub fn new() -> Self {
+        Self::with_archetypes(Archetypes::new())
+    }
+
+    /// Creates a world whose component storage allocates from `allocator` instead of the global
+    /// allocator.
+    ///
+    /// The allocator is shared by all archetypes and released once the world is dropped.
+    pub fn with_storage_allocator(allocator: impl StorageAllocator + 'static) -> Self {
+        Self::with_archetypes(Archetypes::with_allocator(Arc::new(allocator)))
+    }
+
+    fn with_archetypes(archetypes: Archetypes) -> Self {
         Self {
             entity_manager: EntityManager::new(),
-            archetypes: Archetypes::new(),
+            archetypes,
             component_registry: ComponentRegistry::default(),
             resources: Resources::new(),
             schedules: Schedules::new(),
             command_queue: CommandQueue::new(),
+            change_subscribers: ChangeSubscribers::default(),
+            change_tick: Tick::default(),
+            running_system: None,
+            event_updaters: Vec::new(),
         }
     }
 
     pub fn spawn<C: ComponentBundle>(&mut self, bundle: C) -> Entity {
         let entity = self.entity_manager.spawn();
+        self.spawn_at(entity, bundle);
+        entity
+    }
+
+    /// Spawns the bundle as an entity reserved by [`EntityReserver::reserve`].
+    pub(crate) fn spawn_reserved<C: ComponentBundle>(&mut self, entity: Entity, bundle: C) {
+        self.entity_manager.spawn_reserved(entity);
+        self.spawn_at(entity, bundle);
+    }
+
+    pub(crate) fn entity_reserver(&self) -> EntityReserver {
+        self.entity_manager.reserver()
+    }
+
+    fn spawn_at<C: ComponentBundle>(&mut self, entity: Entity, bundle: C) {
 
         let component_ids = C::register_components(&mut self.component_registry);
 
@@ -49,6 +118,8 @@ impl World {
             .get_mut(archetype_id)
             .expect("Archetype was not found for the given bundle");
 
+        // The columns take ownership of the components, the bundle must not drop them
+        let bundle = ManuallyDrop::new(bundle);
         let pointers = unsafe { bundle.get_component_painters() };
 
         let component_data_to_add: Vec<_> = component_ids.into_iter().zip(pointers).collect();
@@ -56,15 +127,310 @@ impl World {
         let row =
             unsafe { archetype.add(entity, &component_data_to_add, &self.component_registry) };
 
-        // TODO: Update entity location
+        self.entity_manager
+            .set_location(entity, EntityLocation { archetype_id, row });
 
-        entity
+        if !self.change_subscribers.is_empty() {
+            self.change_subscribers.notify(|| WorldChange::EntitySpawned(entity));
+            for &(component, _) in &component_data_to_add {
+                let name = self
+                    .component_registry
+                    .get_info(component)
+                    .map_or("<unknown>", |info| info.name);
+                self.change_subscribers.notify(|| WorldChange::ComponentAdded {
+                    entity,
+                    component,
+                    name,
+                });
+            }
+        }
+
+        C::insert_required(self, entity);
+    }
+
+    /// The tick of the system that ran last.
+    pub fn change_tick(&self) -> Tick {
+        self.change_tick
+    }
+
+    /// Advances the change tick, called by every system run.
+    pub fn increment_change_tick(&mut self) -> Tick {
+        self.change_tick.increment()
+    }
+
+    /// The system whose parameters are fetched or that is running, see
+    /// [`SystemName`](crate::system::meta::SystemName).
+    pub(crate) fn running_system(&self) -> Option<SystemMeta> {
+        self.running_system
+    }
+
+    /// Replaces the running system and returns the previous one.
+    pub(crate) fn set_running_system(&mut self, system: Option<SystemMeta>) -> Option<SystemMeta> {
+        std::mem::replace(&mut self.running_system, system)
+    }
+
+    pub fn id(&self) -> WorldId {
+        self.entity_manager.world()
+    }
+
+    /// Whether the entity was spawned and not despawned yet. Handles of despawned entities stay
+    /// dead even once their index is reused.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entity_manager.location(entity).is_some()
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.is_alive(entity)
+    }
+
+    /// Despawns the entity and drops its components, returns `false` if it did not exist.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        let Some(location) = self.entity_manager.despawn(entity) else {
+            return false;
+        };
+
+        let archetype = self
+            .archetypes
+            .get_mut(location.archetype_id)
+            .expect("Archetype of the entity was not found");
+        let (_, swapped) = archetype.remove(location.row);
+        if let Some(swapped) = swapped {
+            self.entity_manager.set_location(swapped, location);
+        }
+
+        self.change_subscribers.notify(|| WorldChange::EntityDespawned(entity));
+        true
+    }
+
+    /// The archetype and row the components of the entity are stored in.
+    pub fn location(&self, entity: Entity) -> Option<EntityLocation> {
+        self.entity_manager.location(entity)
+    }
+
+    /// The component of the entity, `None` if the entity does not exist or does not have it.
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<&T> {
+        let ptr = self.component_ptr::<T>(entity)?;
+        Some(unsafe { &*ptr })
+    }
+
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
+        let ptr = self.component_ptr::<T>(entity)?;
+        Some(unsafe { &mut *ptr })
+    }
+
+    fn component_ptr<T: Component>(&self, entity: Entity) -> Option<*mut T> {
+        let location = self.entity_manager.location(entity)?;
+        let component_id = self.component_registry.get_id::<T>()?;
+        let column = self
+            .archetypes
+            .get(location.archetype_id)?
+            .columns()
+            .get(&component_id)?;
+
+        Some(column.get_mut_ptr(location.row).cast::<T>())
+    }
+
+    /// Adds the component to the entity, replacing the existing one. Returns `false` if the
+    /// entity does not exist.
+    ///
+    /// Adding a new component moves the entity into the archetype with the component.
+    pub fn insert_component<T: Component>(&mut self, entity: Entity, component: T) -> bool {
+        let Some(location) = self.entity_manager.location(entity) else {
+            return false;
+        };
+
+        let component_id = self.component_registry.register::<T>();
+        let archetype = self
+            .archetypes
+            .get(location.archetype_id)
+            .expect("Archetype of the entity was not found");
+        if let Some(column) = archetype.columns().get(&component_id) {
+            // Dropping the replaced component
+            unsafe { *column.get_mut_ptr(location.row).cast::<T>() = component };
+            return true;
+        }
+
+        let target = self.archetypes.get_add_component_destination(
+            location.archetype_id,
+            component_id,
+            &self.component_registry,
+        );
+        // The target column takes ownership of the component
+        let component = ManuallyDrop::new(component);
+        let added = [(component_id, (&raw const *component).cast::<u8>())];
+        let (new_location, swapped) = unsafe {
+            self.archetypes
+                .move_entity_with(entity, location, target, &added, &[])
+        };
+        self.update_moved_locations(entity, location, new_location, swapped);
+
+        self.change_subscribers.notify(|| WorldChange::ComponentAdded {
+            entity,
+            component: component_id,
+            name: type_name::<T>(),
+        });
+        T::insert_required(self, entity);
+        true
+    }
+
+    /// Removes the component from the entity and returns it, `None` if the entity does not
+    /// exist or does not have the component.
+    pub fn remove_component<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        let location = self.entity_manager.location(entity)?;
+        let component_id = self.component_registry.get_id::<T>()?;
+        let target = self
+            .archetypes
+            .get_remove_component_destination(location.archetype_id, component_id)?;
+
+        let column = self
+            .archetypes
+            .get(location.archetype_id)
+            .and_then(|archetype| archetype.columns().get(&component_id))
+            .expect("Archetype of the entity is missing the removed component");
+        let component = unsafe { column.get_ptr(location.row).cast::<T>().read() };
+
+        let (new_location, swapped) = unsafe {
+            self.archetypes
+                .move_entity_with(entity, location, target, &[], &[component_id])
+        };
+        self.update_moved_locations(entity, location, new_location, swapped);
+
+        self.change_subscribers.notify(|| WorldChange::ComponentRemoved {
+            entity,
+            component: component_id,
+            name: type_name::<T>(),
+        });
+        Some(component)
+    }
+
+    /// Updates the locations after [`Archetypes::move_entity`], the `swapped` entity took the
+    /// row the moved entity left behind.
+    fn update_moved_locations(
+        &mut self,
+        entity: Entity,
+        old_location: EntityLocation,
+        new_location: EntityLocation,
+        swapped: Option<Entity>,
+    ) {
+        self.entity_manager.set_location(entity, new_location);
+        if let Some(swapped) = swapped {
+            self.entity_manager.set_location(swapped, old_location);
+        }
+    }
+
+    /// Subscribes to structural changes of the world, e.g. for an editor mirroring its state.
+    ///
+    /// Changes are queued in the channel until they are received, dropping the receiver ends the
+    /// subscription.
+    pub fn subscribe_changes(&mut self) -> Receiver<WorldChange> {
+        self.change_subscribers.subscribe()
+    }
+
+    fn notify_resource_inserted<T: 'static>(&mut self) {
+        self.change_subscribers.notify(|| WorldChange::ResourceInserted {
+            type_id: TypeId::of::<T>(),
+            name: type_name::<T>(),
+        });
+    }
+
+    fn notify_resource_removed<T: 'static>(&mut self) {
+        self.change_subscribers.notify(|| WorldChange::ResourceRemoved {
+            type_id: TypeId::of::<T>(),
+            name: type_name::<T>(),
+        });
+    }
+
+    /// Iterates all entities, grouped by archetype.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.archetypes
+            .iter()
+            .flat_map(|archetype| archetype.entities().iter().copied())
+    }
+
+    /// The type names of all resources, sorted.
+    pub fn resource_names(&self) -> Vec<&'static str> {
+        let mut names: Vec<_> = self.resources.names().collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// All resources in insertion order, e.g. to find resources a `Destroy` system missed.
+    pub fn iter_resources(&self) -> impl Iterator<Item = ResourceInfo> + use<> {
+        self.resources.iter()
+    }
+
+    /// Logs every resource in insertion order at debug level.
+    pub fn log_resources(&self) {
+        for info in self.resources.iter() {
+            let kind = if info.non_send { " (non-send)" } else { "" };
+            debug!(target: targets::WORLD, "Resource #{}: {}{kind}", info.insertion, info.name);
+        }
+    }
+
+    /// Lists the names of the components of the entity for debugging, `None` if the entity does
+    /// not exist.
+    ///
+    /// Allocates the list of names, it is not meant to be called every frame.
+    pub fn inspect_entity(&self, entity: Entity) -> Option<Vec<&'static str>> {
+        let location = self.entity_manager.location(entity)?;
+        let archetype = self.archetypes.get(location.archetype_id)?;
+
+        let mut names: Vec<_> = archetype
+            .columns()
+            .keys()
+            .filter_map(|&id| self.component_registry.get_info(id))
+            .map(|info| info.name)
+            .collect();
+        names.sort_unstable();
+
+        Some(names)
     }
 
     pub fn archetypes(&self) -> &Archetypes {
         &self.archetypes
     }
 
+    /// Calls `f` once per non-empty archetype matching `Q` and the filter `F`, with every
+    /// component of `Q` as its whole column, e.g. `(&[Entity], &mut [Transform])`.
+    ///
+    /// Meant for engine systems that process entire columns at once, like render extraction or
+    /// serialization, instead of going through a [`Query`](crate::query::Query) row by row.
+    ///
+    /// # Panics
+    /// Panics if `Q` writes a component it accesses more than once.
+    pub fn for_each_archetype<'w, Q: QueryData, F: QueryFilter>(
+        &'w mut self,
+        mut f: impl FnMut(&'w Archetype, Q::Batch<'w>),
+    ) {
+        let access = Q::get_access(self);
+        for &(id, write) in &access {
+            let accesses = access.iter().filter(|&&(other, _)| other == id).count();
+            assert!(
+                !write || accesses == 1,
+                "{} writes a component it accesses more than once",
+                type_name::<Q>()
+            );
+        }
+
+        let state = QueryState::<Q, F>::new(self);
+        let world: &'w World = self;
+        for &archetype_id in state.matching_archetypes() {
+            let archetype = world
+                .archetypes
+                .get(archetype_id)
+                .expect("Archetype not found");
+            if archetype.is_empty() {
+                continue;
+            }
+
+            // Every archetype is visited once and the world is borrowed mutably, so the column
+            // slices handed out never alias
+            if let Some(mut fetch) = unsafe { Q::new_fetch(world, archetype) } {
+                f(archetype, unsafe { Q::fetch_batch(&mut fetch, 0..archetype.len()) });
+            }
+        }
+    }
+
     pub fn get_resource<T: Resource>(&self) -> Option<&T> {
         self.resources.get::<T>()
     }
@@ -75,23 +441,156 @@ impl World {
 
     pub fn add_resource<T: Resource>(&mut self, resource: T) {
         self.resources.insert(resource);
+        self.notify_resource_inserted::<T>();
     }
     
     pub fn remove_resource<T: Resource>(&mut self) -> Option<T> {
-        self.resources.remove::<T>()
+        let resource = self.resources.remove::<T>()?;
+        self.notify_resource_removed::<T>();
+        Some(resource)
     }
 
-    pub fn add_system<M>(&mut self, label: ScheduleLabel, system: impl IntoSystem<M>) {
-        self.schedules.add(label, system);
+    pub fn add_non_send_resource<T: NonSendResource>(&mut self, resource: T) {
+        self.resources.insert_non_send(resource);
+        self.notify_resource_inserted::<T>();
+    }
+
+    pub fn get_non_send_resource<T: NonSendResource>(&self) -> Option<&T> {
+        self.resources.get_non_send::<T>()
     }
 
-    pub fn run_system(&mut self, label: &ScheduleLabel) {
-        if let Some(mut systems) = self.schedules.take_systems(label) {
-            systems.run(self);
-            self.schedules.put_systems(label, systems);
+    pub fn get_non_send_resource_mut<T: NonSendResource>(&mut self) -> Option<&mut T> {
+        self.resources.get_non_send_mut::<T>()
+    }
+
+    pub fn remove_non_send_resource<T: NonSendResource>(&mut self) -> Option<T> {
+        let resource = self.resources.remove_non_send::<T>()?;
+        self.notify_resource_removed::<T>();
+        Some(resource)
+    }
+
+    /// Temporarily removes the resource `T` from the world and runs `f` with both the world and
+    /// the resource, reinserting the resource afterward.
+    ///
+    /// The resource is not accessible through the world while `f` runs. If `f` inserts a new `T`
+    /// it is overwritten by the scoped one.
+    ///
+    /// # Panics
+    /// Panics if the resource does not exist, see [`World::try_resource_scope`].
+    pub fn resource_scope<T: Resource, R>(
+        &mut self,
+        f: impl FnOnce(&mut World, &mut T) -> R,
+    ) -> R {
+        self.try_resource_scope(f)
+            .unwrap_or_else(|| panic!("Resource {} not found", type_name::<T>()))
+    }
+
+    /// Like [`World::resource_scope`] but returns `None` if the resource does not exist.
+    pub fn try_resource_scope<T: Resource, R>(
+        &mut self,
+        f: impl FnOnce(&mut World, &mut T) -> R,
+    ) -> Option<R> {
+        let mut resource = self.resources.remove::<T>()?;
+        let result = f(self, &mut resource);
+        self.resources.insert(resource);
+        Some(result)
+    }
+
+    /// Sends an event to the [`Events<T>`] resource, adding it with [`World::add_event`] if it
+    /// does not exist yet.
+    pub fn send_event<T: Send + Sync + 'static>(&mut self, event: T) {
+        self.add_event::<T>();
+        self.resources
+            .get_mut::<Events<T>>()
+            .expect("The events were added")
+            .send(event);
+    }
+
+    /// Inserts an empty [`Events<T>`] resource if it does not exist yet and registers it to be
+    /// updated by [`World::update_events`].
+    pub fn add_event<T: Send + Sync + 'static>(&mut self) {
+        let type_id = TypeId::of::<T>();
+        if !self.event_updaters.iter().any(|(id, _)| *id == type_id) {
+            self.event_updaters.push((type_id, |world| {
+                if let Some(events) = world.resources.get_mut::<Events<T>>() {
+                    events.update();
+                }
+            }));
+        }
+        if self.resources.get_mut::<Events<T>>().is_none() {
+            self.resources.insert(Events::<T>::new());
+            self.notify_resource_inserted::<Events<T>>();
         }
     }
 
+    /// Calls [`Events::update`] on the events added with [`World::add_event`], the app does so
+    /// at the start of every frame.
+    pub fn update_events(&mut self) {
+        for index in 0..self.event_updaters.len() {
+            (self.event_updaters[index].1)(self);
+        }
+    }
+
+    /// Registers an empty schedule to run with [`World::run_system`], e.g. for a custom
+    /// [`ScheduleLabel`] of a plugin. Adding a system registers its schedule as well.
+    pub fn add_schedule(&mut self, label: impl ScheduleLabel) {
+        self.schedules.add_schedule(label);
+    }
+
+    pub fn add_system<M>(&mut self, label: impl ScheduleLabel, system: impl IntoSystem<M>) {
+        self.schedules.add(label, system);
+    }
+
+    /// Adds the system to a [`SystemSet`], e.g. to keep it running while its schedule is paused
+    /// with the [`ScheduleControl`].
+    pub fn add_system_to_set<M>(
+        &mut self,
+        label: impl ScheduleLabel,
+        set: SystemSet,
+        system: impl IntoSystem<M>,
+    ) {
+        self.schedules.add_to_set(label, set, system);
+    }
+
+    /// Runs all systems of the schedule, see [`Systems::run`](crate::system::systems::Systems::run).
+    ///
+    /// Only the systems of [`ScheduleControl::always_run`] sets run while the schedule is paused.
+    pub fn run_system(&mut self, label: &dyn ScheduleLabel) -> Result<(), ScheduleError> {
+        trace!(target: targets::SCHEDULE, "Running schedule {label:?}");
+        let control = self
+            .get_resource::<ScheduleControl>()
+            .filter(|control| control.is_paused(label))
+            .cloned();
+        let Some(mut systems) = self.schedules.take_systems(label) else {
+            return Ok(());
+        };
+
+        let result = match control {
+            Some(control) => {
+                systems.run_filtered(self, |set| control.should_run(label, set))
+            }
+            None => systems.run(self),
+        };
+        self.schedules.put_systems(label, systems);
+
+        result.map_err(|errors| ScheduleError {
+            schedule: label.dyn_clone(),
+            errors,
+        })
+    }
+
+    /// Initializes and runs a system immediately without adding it to a schedule, its commands
+    /// are applied before returning.
+    ///
+    /// The system state is discarded afterward, so local state does not carry over between calls.
+    pub fn run_system_once<M>(&mut self, system: impl IntoSystem<M>) -> Result<(), SystemError> {
+        let mut system = system.into_system();
+        system.initialize(self);
+        let result = system.run(self);
+        self.flush_commands();
+        result
+    }
+
     pub fn register_module<T: Module>(&mut self) {
         T::register(self);
     }
@@ -104,13 +603,304 @@ impl World {
         while !self.command_queue.is_empty() {
             let commands = std::mem::take(&mut self.command_queue.commands);
 
+            trace!(target: targets::WORLD, "Flushing {} commands", commands.len());
+
             for command in commands {
-                command.execute(self);
+                let entity = command.entity();
+                if let Err(error) = command.execute(self) {
+                    match entity {
+                        Some(entity) => warn!(
+                            target: targets::WORLD,
+                            "Command on entity {entity} failed: {error}"
+                        ),
+                        None => warn!(target: targets::WORLD, "Command failed: {error}"),
+                    }
+                    self.send_event::<CommandError>(error);
+                }
             }
         }
     }
 
-    pub fn add_plugin(&mut self, plugin: impl Plugin) {
+    pub fn add_plugin<P: Plugin>(&mut self, plugin: P) {
+        debug!(target: targets::WORLD, "Adding plugin {}", type_name::<P>());
         plugin.init(self);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Commands;
+    use crate::component::Component;
+    use crate::resource::Res;
+
+    struct Position;
+    struct Velocity;
+
+    impl Component for Position {}
+    impl Component for Velocity {}
+
+    struct Score(u32);
+
+    impl Resource for Score {}
+
+    #[test]
+    fn inspect_entity_lists_component_names() {
+        let mut world = World::new();
+        world.spawn((Position,));
+        let entity = world.spawn((Velocity, Position));
+
+        assert_eq!(
+            world.inspect_entity(entity),
+            Some(vec![
+                std::any::type_name::<Position>(),
+                std::any::type_name::<Velocity>()
+            ])
+        );
+        assert_eq!(entity.to_string(), "1v0");
+    }
+
+    #[test]
+    fn run_system_once_applies_commands() {
+        struct Counter(u32);
+
+        impl Resource for Counter {}
+
+        fn insert_counter(mut commands: Commands) {
+            commands.insert_resource(Counter(1));
+        }
+
+        fn increment(counter: Res<Counter>, mut commands: Commands) {
+            commands.insert_resource(Counter(counter.0 + 1));
+        }
+
+        let mut world = World::new();
+        world.run_system_once(insert_counter).unwrap();
+        world.run_system_once(increment).unwrap();
+
+        assert_eq!(world.get_resource::<Counter>().unwrap().0, 2);
+        assert!(World::new().run_system_once(increment).is_err());
+    }
+
+    #[test]
+    fn subscribers_receive_structural_changes() {
+        struct Settings;
+
+        impl Resource for Settings {}
+
+        let mut world = World::new();
+        let changes = world.subscribe_changes();
+
+        let entity = world.spawn((Position,));
+        world.add_resource(Settings);
+        world.remove_resource::<Settings>();
+
+        let changes: Vec<_> = changes.try_iter().collect();
+        assert_eq!(changes.len(), 4);
+        assert_eq!(changes[0], WorldChange::EntitySpawned(entity));
+        assert!(matches!(
+            changes[1],
+            WorldChange::ComponentAdded { entity: e, name, .. }
+                if e == entity && name == type_name::<Position>()
+        ));
+        assert!(matches!(changes[3], WorldChange::ResourceRemoved { .. }));
+    }
+
+    #[test]
+    fn components_are_inserted_and_removed_and_entities_despawned() {
+        #[derive(Debug, PartialEq)]
+        struct Health(u32);
+
+        impl Component for Health {}
+
+        let mut world = World::new();
+        let first = world.spawn((Position,));
+        let second = world.spawn((Position,));
+
+        assert!(world.insert_component(first, Health(10)));
+        assert!(world.insert_component(first, Health(5)));
+        assert_eq!(world.get::<Health>(first), Some(&Health(5)));
+        // The second entity took the row the first one left behind
+        assert!(world.insert_component(second, Velocity));
+        assert_eq!(world.inspect_entity(second).unwrap().len(), 2);
+
+        assert_eq!(world.remove_component::<Health>(first), Some(Health(5)));
+        assert_eq!(world.remove_component::<Health>(first), None);
+        assert_eq!(
+            world.inspect_entity(first),
+            Some(vec![type_name::<Position>()])
+        );
+
+        world
+            .run_system_once(move |mut commands: Commands| {
+                commands.despawn(first);
+                commands.remove_component::<Velocity>(second);
+            })
+            .unwrap();
+        assert!(!world.contains(first));
+        assert!(!world.despawn(first));
+        assert!(!world.insert_component(first, Velocity));
+        assert_eq!(world.entities().collect::<Vec<_>>(), [second]);
+        assert_eq!(
+            world.inspect_entity(second),
+            Some(vec![type_name::<Position>()])
+        );
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "was passed to"))]
+    fn entities_of_other_worlds_are_rejected() {
+        let mut world = World::new();
+        let mut other = World::new();
+        let entity = world.spawn((Position,));
+        other.spawn((Position,));
+
+        assert_ne!(world.id(), other.id());
+        assert_eq!(entity.world(), world.id());
+        // Only reached in release builds
+        assert!(!other.despawn(entity));
+        assert!(world.contains(entity));
+    }
+
+    #[test]
+    fn components_are_read_and_written_through_their_location() {
+        #[derive(Debug, PartialEq)]
+        struct Health(u32);
+
+        impl Component for Health {}
+
+        let mut world = World::new();
+        let first = world.spawn((Health(1), Position));
+        let second = world.spawn((Health(2), Position));
+
+        world.get_mut::<Health>(second).unwrap().0 += 10;
+        assert_eq!(world.get::<Health>(second), Some(&Health(12)));
+        assert_eq!(world.location(second).unwrap().row, 1);
+        assert!(world.get::<Velocity>(second).is_none());
+
+        world.despawn(first);
+        assert_eq!(world.location(second).unwrap().row, 0);
+        assert_eq!(world.get::<Health>(second), Some(&Health(12)));
+        assert!(world.get_mut::<Health>(first).is_none());
+    }
+
+    #[test]
+    fn archetypes_are_processed_column_wise() {
+        use crate::query::Without;
+
+        #[derive(Debug, PartialEq)]
+        struct Health(u32);
+
+        impl Component for Health {}
+
+        let mut world = World::new();
+        world.spawn((Health(1),));
+        world.spawn((Health(2),));
+        world.spawn((Health(4), Position));
+        world.spawn((Health(8), Velocity));
+        world.spawn((Velocity,));
+
+        let mut columns = Vec::new();
+        world.for_each_archetype::<(Entity, &mut Health), Without<Velocity>>(
+            |archetype, (entities, health)| {
+                assert_eq!(entities, archetype.entities());
+                health.iter_mut().for_each(|health| health.0 *= 10);
+                columns.push(health.len());
+            },
+        );
+        columns.sort_unstable();
+        assert_eq!(columns, [1, 2]);
+
+        let mut total = 0;
+        world.for_each_archetype::<&Health, ()>(|_, health| {
+            total += health.iter().map(|health| health.0).sum::<u32>();
+        });
+        assert_eq!(total, 78);
+
+        let velocity = world.component_registry.get_id::<Velocity>().unwrap();
+        assert_eq!(world.archetypes().iter().with_components(&[velocity]).count(), 2);
+    }
+
+    #[test]
+    fn derived_components_insert_their_required_components() {
+        #[derive(Component, Default, Debug, PartialEq)]
+        struct Transform(u32);
+
+        #[derive(Component, Default, Debug, PartialEq)]
+        #[component(require(Transform))]
+        struct Visibility(bool);
+
+        #[derive(Component)]
+        #[component(require(Visibility))]
+        struct Sprite;
+
+        #[derive(Resource)]
+        struct Gravity<T: Send + Sync + 'static>(T);
+
+        let mut world = World::new();
+        let sprite = world.spawn((Sprite,));
+        assert_eq!(world.get::<Visibility>(sprite), Some(&Visibility(false)));
+        assert_eq!(world.get::<Transform>(sprite), Some(&Transform(0)));
+
+        // Required components the entity already has are kept
+        let entity = world.spawn((Transform(5),));
+        world.insert_component(entity, Visibility(true));
+        assert_eq!(world.get::<Transform>(entity), Some(&Transform(5)));
+
+        world.add_resource(Gravity(9.81f32));
+        assert_eq!(world.get_resource::<Gravity<f32>>().unwrap().0, 9.81);
+    }
+
+    #[test]
+    fn despawned_indices_are_recycled_with_a_new_generation() {
+        let mut world = World::new();
+        let first = world.spawn((Position,));
+        let second = world.spawn((Velocity,));
+
+        assert!(world.despawn(first));
+        let recycled = world.spawn((Velocity,));
+        assert_eq!(recycled.index(), first.index());
+        assert_eq!(recycled.generation(), 1);
+        assert_eq!(recycled.to_string(), "0v1");
+
+        assert!(!world.is_alive(first));
+        assert!(world.is_alive(recycled));
+        assert!(world.get::<Velocity>(first).is_none());
+        assert!(!world.despawn(first));
+        assert!(world.is_alive(recycled));
+        assert_eq!(world.entities().count(), 2);
+
+        assert_eq!(world.spawn((Position,)).index(), 2);
+        assert!(world.is_alive(second));
+    }
+
+    #[test]
+    fn resource_scopes_modify_the_resource_and_the_world() {
+        let mut world = World::new();
+        world.add_resource(Score(1));
+
+        let entity = world.resource_scope(|world, score: &mut Score| {
+            assert!(world.get_resource::<Score>().is_none());
+            score.0 += 1;
+            world.add_resource(Score(100));
+            world.spawn((Position,))
+        });
+
+        assert_eq!(world.get_resource::<Score>().unwrap().0, 2);
+        assert!(world.get::<Position>(entity).is_some());
+    }
+
+    #[test]
+    fn missing_resources_are_not_scoped() {
+        let mut world = World::new();
+        let result = world.try_resource_scope(|_, _: &mut Score| unreachable!());
+        assert!(result.is_none());
+        assert!(world.get_resource::<Score>().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "not found")]
+    fn resource_scope_panics_without_the_resource() {
+        World::new().resource_scope(|_, _: &mut Score| {});
+    }
+}
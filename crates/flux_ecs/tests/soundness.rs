@@ -0,0 +1,158 @@
+//! Exercises the raw pointer storage of the ECS through the public API with components that own
+//! heap memory, so Miri can check column pushes, growth and swap removes, archetype moves, bundle
+//! pointers and query fetches for undefined behavior and leaks:
+//!
+//! ```text
+//! cargo miri test -p flux_ecs --test soundness
+//! ```
+
+use flux_ecs::component::{Component, ComponentBundle};
+use flux_ecs::query::Query;
+use flux_ecs::world::World;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Name(String);
+
+impl Component for Name {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Position(u8, u64);
+
+impl Component for Position {}
+
+struct Marker;
+
+impl Component for Marker {}
+
+/// Counts its drops to catch double drops and leaked components.
+struct Tracked(Arc<AtomicUsize>);
+
+impl Component for Tracked {}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn bundle_pointers_point_at_each_component() {
+    let bundle = (Marker, Position(1, u64::MAX), Name("bundle".into()));
+    let pointers = unsafe { bundle.get_component_painters() };
+
+    unsafe {
+        assert!(pointers[0].cast::<Marker>().is_aligned());
+        assert_eq!(*pointers[1].cast::<Position>(), Position(1, u64::MAX));
+        assert_eq!(&(*pointers[2].cast::<Name>()).0, "bundle");
+    }
+}
+
+#[test]
+fn zero_sized_components_never_touch_memory() {
+    let mut world = World::new();
+    for _ in 0..5 {
+        world.spawn((Marker,));
+    }
+
+    world
+        .run_system_once(|query: Query<&Marker>| {
+            let mut count = 0;
+            for marker in query {
+                assert!((&raw const *marker).is_aligned());
+                count += 1;
+            }
+            assert_eq!(count, 5);
+        })
+        .unwrap();
+}
+
+#[test]
+fn queries_read_and_write_spawned_components() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let mut world = World::new();
+    // Enough entities to grow the columns a few times
+    for index in 0..20 {
+        world.spawn((Name(index.to_string()), Position(0, index)));
+        world.spawn((Name(index.to_string()), Tracked(drops.clone())));
+    }
+
+    world
+        .run_system_once(|query: Query<(&mut Name, &Position)>| {
+            for (name, position) in query {
+                name.0.push_str(&format!("/{}", position.1));
+            }
+        })
+        .unwrap();
+    world
+        .run_system_once(|mut query: Query<&mut Name>| {
+            query.for_each_batched::<8>(|names| {
+                names.iter_mut().for_each(|name| name.0.push('!'));
+            });
+        })
+        .unwrap();
+    world
+        .run_system_once(|query: Query<(&Name, &Position)>| {
+            for (name, position) in &query {
+                assert_eq!(name.0, format!("{0}/{0}!", position.1));
+            }
+        })
+        .unwrap();
+
+    drop(world);
+    assert_eq!(drops.load(Ordering::Relaxed), 20);
+}
+
+#[test]
+fn archetype_moves_keep_and_despawns_drop_components() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let mut world = World::new();
+    let entities: Vec<_> = (0..10)
+        .map(|index| world.spawn((Name(index.to_string()), Tracked(drops.clone()))))
+        .collect();
+
+    // The components are copied into the archetype with the marker, not dropped
+    world.insert_component(entities[4], Marker);
+    assert_eq!(drops.load(Ordering::Relaxed), 0);
+
+    world.despawn(entities[0]);
+    world.despawn(entities[9]);
+    assert_eq!(drops.load(Ordering::Relaxed), 2);
+    assert_eq!(world.get::<Name>(entities[4]), Some(&Name("4".into())));
+    assert_eq!(world.get::<Name>(entities[8]), Some(&Name("8".into())));
+
+    drop(world);
+    assert_eq!(drops.load(Ordering::Relaxed), 10);
+}
+
+#[test]
+fn inserting_removing_and_despawning_drop_every_component_once() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let mut world = World::new();
+    let entities: Vec<_> = (0..6)
+        .map(|index| world.spawn((Name(index.to_string()),)))
+        .collect();
+
+    for entity in &entities {
+        world.insert_component(*entity, Tracked(drops.clone()));
+    }
+    // Replacing drops the old component
+    world.insert_component(entities[0], Tracked(drops.clone()));
+    assert_eq!(drops.load(Ordering::Relaxed), 1);
+
+    let removed = world.remove_component::<Tracked>(entities[1]);
+    assert_eq!(drops.load(Ordering::Relaxed), 1);
+    drop(removed);
+    assert_eq!(drops.load(Ordering::Relaxed), 2);
+
+    world.despawn(entities[2]);
+    world.despawn(entities[1]);
+    assert_eq!(drops.load(Ordering::Relaxed), 3);
+    for (index, entity) in entities.iter().enumerate().skip(3) {
+        assert_eq!(world.get::<Name>(*entity), Some(&Name(index.to_string())));
+    }
+
+    drop(world);
+    assert_eq!(drops.load(Ordering::Relaxed), 7);
+}
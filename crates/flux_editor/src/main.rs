@@ -0,0 +1,47 @@
+//! A terminal inspector that hosts the engine in-process and periodically prints the state of
+//! its world.
+
+mod snapshot;
+
+use crate::snapshot::WorldSnapshot;
+use flux_ecs::app::App;
+use flux_ecs::logging::{self, LogSettings};
+use flux_ecs::schedule::CoreSchedule;
+use flux_engine_memory::MemoryPlugin;
+use flux_renderer::{ConfigPlugin, RendererPlugin};
+use log::{LevelFilter, error};
+use std::thread::sleep;
+use std::time::Duration;
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+const REFRESH_COUNT: usize = 4;
+
+fn main() {
+    let log_settings = LogSettings::new(LevelFilter::Warn);
+    let logger = pretty_env_logger::formatted_builder()
+        .filter_level(LevelFilter::Trace)
+        .build();
+    logging::init(logger, &log_settings).expect("Failed to initialize the logger");
+
+    let mut app = App::new();
+    app.world_mut().add_resource(log_settings);
+    app.add_plugin(ConfigPlugin)
+        .add_plugin(MemoryPlugin)
+        .add_plugin(RendererPlugin)
+        .on_init_error(|_, init_error| {
+            error!("The inspected app could not start: {}", init_error.first());
+        });
+
+    // The world is still inspected if the renderer could not start
+    let _ = app.initialize();
+
+    for _ in 0..REFRESH_COUNT {
+        if let Err(err) = app.world_mut().run_system(&CoreSchedule::Main) {
+            error!("{err}");
+        }
+        println!("{}", WorldSnapshot::capture(app.world()));
+        sleep(REFRESH_INTERVAL);
+    }
+
+    app.shutdown();
+}
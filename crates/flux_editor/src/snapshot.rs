@@ -0,0 +1,202 @@
+use flux_ecs::entity::Entity;
+use flux_ecs::world::World;
+use flux_engine_memory::{MemoryUsage, Region};
+use flux_renderer::RenderStats;
+use flux_transform::hierarchy::{Children, Parent};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+pub struct EntitySnapshot {
+    pub entity: Entity,
+    /// The [`Parent`] of the entity, `None` for roots.
+    pub parent: Option<Entity>,
+    /// The [`Children`] of the entity in their order.
+    pub children: Vec<Entity>,
+    pub components: Vec<&'static str>,
+}
+
+pub struct FrameSnapshot {
+    pub frame_index: u64,
+    pub draw_calls: u32,
+    pub triangles: u64,
+    pub skipped_frames: u64,
+}
+
+/// The state of the world at one point in time, as shown by the inspector.
+pub struct WorldSnapshot {
+    pub entities: Vec<EntitySnapshot>,
+    pub resources: Vec<&'static str>,
+    pub memory: Option<MemoryUsage>,
+    pub frame: Option<FrameSnapshot>,
+}
+
+impl WorldSnapshot {
+    pub fn capture(world: &World) -> Self {
+        let mut entities: Vec<_> = world
+            .entities()
+            .map(|entity| EntitySnapshot {
+                entity,
+                parent: world.get::<Parent>(entity).map(|parent| parent.0),
+                children: world
+                    .get::<Children>(entity)
+                    .map(|children| children.iter().collect())
+                    .unwrap_or_default(),
+                components: world.inspect_entity(entity).unwrap_or_default(),
+            })
+            .collect();
+        entities.sort_unstable_by_key(|snapshot| snapshot.entity.index());
+
+        let frame = world
+            .get_resource::<RenderStats>()
+            .map(|stats| FrameSnapshot {
+                frame_index: stats.frame_index(),
+                draw_calls: stats.draw_calls(),
+                triangles: stats.triangles(),
+                skipped_frames: stats.skipped_frames(),
+            });
+
+        Self {
+            entities,
+            resources: world.resource_names(),
+            memory: world.get_resource::<MemoryUsage>().cloned(),
+            frame,
+        }
+    }
+}
+
+impl WorldSnapshot {
+    /// Writes the entity with its components, followed by its children one level deeper.
+    fn write_entity(
+        &self,
+        f: &mut Formatter<'_>,
+        snapshot: &EntitySnapshot,
+        by_entity: &HashMap<Entity, &EntitySnapshot>,
+        depth: usize,
+    ) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+        writeln!(f, "{indent}{}", snapshot.entity)?;
+        for component in &snapshot.components {
+            writeln!(f, "{indent}  - {}", short_name(component))?;
+        }
+        for child in &snapshot.children {
+            if let Some(child) = by_entity.get(child) {
+                self.write_entity(f, child, by_entity, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders the snapshot as an indented tree, children are nested below their parents.
+impl Display for WorldSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let by_entity: HashMap<_, _> = self
+            .entities
+            .iter()
+            .map(|snapshot| (snapshot.entity, snapshot))
+            .collect();
+        writeln!(f, "Entities ({})", self.entities.len())?;
+        let roots = self.entities.iter().filter(|snapshot| {
+            snapshot
+                .parent
+                .is_none_or(|parent| !by_entity.contains_key(&parent))
+        });
+        for snapshot in roots {
+            self.write_entity(f, snapshot, &by_entity, 1)?;
+        }
+
+        writeln!(f, "Resources ({})", self.resources.len())?;
+        for resource in &self.resources {
+            writeln!(f, "  {}", short_name(resource))?;
+        }
+
+        if let Some(memory) = &self.memory {
+            writeln!(f, "Memory ({} bytes)", memory.total_bytes())?;
+            for region in Region::ALL {
+                let usage = memory.get(region);
+                writeln!(
+                    f,
+                    "  {region:?}: {} bytes in {} allocations",
+                    usage.bytes, usage.allocations
+                )?;
+            }
+        }
+
+        if let Some(frame) = &self.frame {
+            writeln!(
+                f,
+                "Frame {}: {} draw calls, {} triangles, {} skipped frames",
+                frame.frame_index, frame.draw_calls, frame.triangles, frame.skipped_frames
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Strips the module paths of a type name, keeping its generic arguments readable.
+pub fn short_name(type_name: &str) -> String {
+    let mut name = String::with_capacity(type_name.len());
+    let mut segment_start = 0;
+
+    for (index, char) in type_name.char_indices() {
+        match char {
+            ':' => segment_start = index + 1,
+            '<' | '>' | ',' | ' ' | '(' | ')' | '[' | ']' | '&' | ';' => {
+                name.push_str(&type_name[segment_start..index]);
+                name.push(char);
+                segment_start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    name.push_str(&type_name[segment_start..]);
+
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flux_ecs::component::Component;
+    use flux_transform::hierarchy::set_parent;
+
+    struct Selected;
+
+    impl Component for Selected {}
+
+    #[test]
+    fn type_names_are_shortened() {
+        assert_eq!(
+            short_name("flux_ecs::event::Events<flux_ecs::commands::CommandError>"),
+            "Events<CommandError>"
+        );
+        assert_eq!(short_name("(u32, alloc::string::String)"), "(u32, String)");
+    }
+
+    #[test]
+    fn snapshots_list_entities_and_components() {
+        let mut world = World::new();
+        world.spawn((Selected,));
+
+        let snapshot = WorldSnapshot::capture(&world);
+
+        assert_eq!(snapshot.entities.len(), 1);
+        assert!(snapshot.to_string().contains("    - Selected\n"));
+    }
+
+    #[test]
+    fn children_are_nested_below_their_parents() {
+        let mut world = World::new();
+        let child = world.spawn((Selected,));
+        let parent = world.spawn((Selected,));
+        set_parent(&mut world, child, parent);
+
+        let rendered = WorldSnapshot::capture(&world).to_string();
+
+        let parent_line = rendered.find(&format!("\n  {parent}\n")).unwrap();
+        let child_line = rendered.find(&format!("\n    {child}\n")).unwrap();
+        assert!(parent_line < child_line, "{rendered}");
+        assert!(rendered.contains("      - Parent\n"), "{rendered}");
+    }
+}
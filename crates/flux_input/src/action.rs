@@ -0,0 +1,221 @@
+use crate::raw::{Axis, AxisInput, ButtonInput, GamepadButton, Key, MouseButton};
+use flux_ecs::commands::Commands;
+use flux_ecs::resource::{Res, Resource};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A physical input an action is bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+    /// An analog axis, its value is multiplied by `scale`, e.g. `-1.0` to invert it.
+    Axis {
+        axis: Axis,
+        scale: f32,
+    },
+    /// Two keys acting as an axis, `-1.0` while `negative` is held and `1.0` while `positive` is.
+    KeyAxis {
+        negative: Key,
+        positive: Key,
+    },
+}
+
+/// Binds named actions, e.g. `"Jump"` or `"MoveForward"`, to physical inputs.
+///
+/// Gameplay systems read the resolved [`ActionState`] instead of raw keys so the bindings can be
+/// changed by configuration. The map can be deserialized, e.g. from a settings file:
+///
+/// ```toml
+/// dead_zone = 0.1
+///
+/// [actions]
+/// Jump = [{ Key = "Space" }, { Mouse = "Right" }, { Gamepad = "South" }]
+/// MoveForward = [{ KeyAxis = { negative = "S", positive = "W" } }]
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActionMap {
+    #[serde(default)]
+    pub actions: HashMap<String, Vec<Binding>>,
+    /// Axis values with a smaller magnitude are treated as zero.
+    #[serde(default)]
+    pub dead_zone: f32,
+}
+
+impl Resource for ActionMap {}
+
+impl ActionMap {
+    #[must_use]
+    pub fn with(mut self, action: impl Into<String>, binding: Binding) -> Self {
+        self.bind(action, binding);
+        self
+    }
+
+    pub fn bind(&mut self, action: impl Into<String>, binding: Binding) {
+        self.actions.entry(action.into()).or_default().push(binding);
+    }
+
+    pub fn unbind_all(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+}
+
+/// The pressed keys, mouse buttons and gamepad buttons.
+pub type RawButtons<'a> = (
+    &'a ButtonInput<Key>,
+    &'a ButtonInput<MouseButton>,
+    &'a ButtonInput<GamepadButton>,
+);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ActionValue {
+    pub pressed: bool,
+    pub just_pressed: bool,
+    pub just_released: bool,
+    /// The value of the binding with the largest magnitude, `1.0` for pressed buttons.
+    pub value: f32,
+}
+
+/// The state of every action of the [`ActionMap`], resolved once per frame by
+/// [`update_action_state`].
+#[derive(Clone, Debug, Default)]
+pub struct ActionState {
+    actions: HashMap<String, ActionValue>,
+}
+
+impl Resource for ActionState {}
+
+impl ActionState {
+    /// Resolves the bindings of the map against the raw input, `previous` is the state of the
+    /// last frame.
+    pub fn resolve(
+        map: &ActionMap,
+        (keys, mouse, gamepad): RawButtons,
+        axes: &AxisInput,
+        previous: Option<&ActionState>,
+    ) -> Self {
+        let actions = map
+            .actions
+            .iter()
+            .map(|(name, bindings)| {
+                let value = bindings
+                    .iter()
+                    .map(|binding| binding_value(*binding, (keys, mouse, gamepad), axes))
+                    .fold(
+                        0.0f32,
+                        |max, value| {
+                            if value.abs() > max.abs() { value } else { max }
+                        },
+                    );
+                let value = if value.abs() <= map.dead_zone {
+                    0.0
+                } else {
+                    value
+                };
+
+                let pressed = value != 0.0;
+                let was_pressed = previous.is_some_and(|state| state.pressed(name));
+
+                let state = ActionValue {
+                    pressed,
+                    just_pressed: pressed && !was_pressed,
+                    just_released: !pressed && was_pressed,
+                    value,
+                };
+                (name.clone(), state)
+            })
+            .collect();
+
+        Self { actions }
+    }
+
+    /// The state of the action, the default state if the action is not bound.
+    pub fn get(&self, action: &str) -> ActionValue {
+        self.actions.get(action).copied().unwrap_or_default()
+    }
+
+    pub fn pressed(&self, action: &str) -> bool {
+        self.get(action).pressed
+    }
+
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.get(action).just_pressed
+    }
+
+    pub fn just_released(&self, action: &str) -> bool {
+        self.get(action).just_released
+    }
+
+    pub fn value(&self, action: &str) -> f32 {
+        self.get(action).value
+    }
+}
+
+fn binding_value(binding: Binding, (keys, mouse, gamepad): RawButtons, axes: &AxisInput) -> f32 {
+    let button = |pressed: bool| if pressed { 1.0 } else { 0.0 };
+
+    match binding {
+        Binding::Key(key) => button(keys.pressed(key)),
+        Binding::Mouse(mouse_button) => button(mouse.pressed(mouse_button)),
+        Binding::Gamepad(gamepad_button) => button(gamepad.pressed(gamepad_button)),
+        Binding::Axis { axis, scale } => axes.get(axis) * scale,
+        Binding::KeyAxis { negative, positive } => {
+            button(keys.pressed(positive)) - button(keys.pressed(negative))
+        }
+    }
+}
+
+pub fn update_action_state(
+    map: Res<ActionMap>,
+    keys: Res<ButtonInput<Key>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepad: Res<ButtonInput<GamepadButton>>,
+    axes: Res<AxisInput>,
+    previous: Option<Res<ActionState>>,
+    mut commands: Commands,
+) {
+    let buttons = (&*keys, &*mouse, &*gamepad);
+    let state = ActionState::resolve(&map, buttons, &axes, previous.as_deref());
+    commands.insert_resource(state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_follow_their_bindings() {
+        let map = ActionMap::default()
+            .with("Jump", Binding::Key(Key::Space))
+            .with("Jump", Binding::Mouse(MouseButton::Right))
+            .with("Jump", Binding::Gamepad(GamepadButton::South))
+            .with(
+                "MoveForward",
+                Binding::KeyAxis {
+                    negative: Key::S,
+                    positive: Key::W,
+                },
+            );
+        let mut keys = ButtonInput::default();
+        let mouse = ButtonInput::default();
+        let mut gamepad = ButtonInput::default();
+        let axes = AxisInput::default();
+
+        keys.press(Key::Space);
+        keys.press(Key::S);
+        let first = ActionState::resolve(&map, (&keys, &mouse, &gamepad), &axes, None);
+        assert!(first.just_pressed("Jump"));
+        assert_eq!(first.value("MoveForward"), -1.0);
+
+        keys.release(Key::Space);
+        let second = ActionState::resolve(&map, (&keys, &mouse, &gamepad), &axes, Some(&first));
+        assert!(second.just_released("Jump"));
+        assert!(!second.pressed("Jump"));
+        assert!(!second.pressed("Unbound"));
+
+        gamepad.press(GamepadButton::South);
+        let third = ActionState::resolve(&map, (&keys, &mouse, &gamepad), &axes, Some(&second));
+        assert!(third.just_pressed("Jump"));
+    }
+}
@@ -0,0 +1,34 @@
+use crate::action::{ActionMap, ActionState, update_action_state};
+use crate::raw::{AxisInput, ButtonInput, GamepadButton, Key, MouseButton};
+use flux_ecs::plugin::Plugin;
+use flux_ecs::schedule::control::SystemSet;
+use flux_ecs::schedule::CoreSchedule;
+use flux_ecs::world::World;
+
+pub mod action;
+pub mod raw;
+
+/// Adds the raw input resources and resolves the [`ActionMap`] into the [`ActionState`] every
+/// frame.
+///
+/// The platform layer, e.g. the winit runner of the renderer, feeds the raw input resources before
+/// the `Main` schedule runs and clears them after the frame. Insert the
+/// application's [`ActionMap`] before adding the plugin, an empty map is used otherwise.
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn init(&self, world: &mut World) {
+        if world.get_resource::<ActionMap>().is_none() {
+            world.add_resource(ActionMap::default());
+        }
+
+        world.add_resource(ButtonInput::<Key>::default());
+        world.add_resource(ButtonInput::<MouseButton>::default());
+        world.add_resource(ButtonInput::<GamepadButton>::default());
+        world.add_resource(AxisInput::default());
+        world.add_resource(ActionState::default());
+
+        // Menus shown while gameplay is paused still read actions
+        world.add_system_to_set(CoreSchedule::Main, SystemSet::UI, update_action_state);
+    }
+}
@@ -0,0 +1,192 @@
+use flux_ecs::resource::Resource;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// A physical keyboard key, independent of the keyboard layout.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Key {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Digit0,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    Up,
+    Down,
+    Left,
+    Right,
+    Space,
+    Enter,
+    Escape,
+    Tab,
+    Backspace,
+    ShiftLeft,
+    ShiftRight,
+    ControlLeft,
+    ControlRight,
+    AltLeft,
+    AltRight,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+}
+
+/// A gamepad button named after its position, e.g. `South` is `A` on Xbox and `Cross` on
+/// PlayStation controllers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    /// Analog triggers also report their value as [`Axis::GamepadLeftTrigger`].
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Axis {
+    /// The mouse movement since the last frame.
+    MouseX,
+    MouseY,
+    MouseWheel,
+    GamepadLeftX,
+    GamepadLeftY,
+    GamepadRightX,
+    GamepadRightY,
+    GamepadLeftTrigger,
+    GamepadRightTrigger,
+}
+
+/// The pressed state of buttons of type `T`, fed by the platform layer.
+///
+/// Call [`ButtonInput::clear`] once per frame after the frame's input has been consumed so that
+/// `just_pressed` and `just_released` only hold for one frame.
+#[derive(Clone, Debug)]
+pub struct ButtonInput<T: Copy + Eq + Hash> {
+    pressed: HashSet<T>,
+    just_pressed: HashSet<T>,
+    just_released: HashSet<T>,
+}
+
+impl<T: Copy + Eq + Hash + Send + Sync + 'static> Resource for ButtonInput<T> {}
+
+impl<T: Copy + Eq + Hash> Default for ButtonInput<T> {
+    fn default() -> Self {
+        Self {
+            pressed: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> ButtonInput<T> {
+    pub fn press(&mut self, button: T) {
+        if self.pressed.insert(button) {
+            self.just_pressed.insert(button);
+        }
+    }
+
+    pub fn release(&mut self, button: T) {
+        if self.pressed.remove(&button) {
+            self.just_released.insert(button);
+        }
+    }
+
+    pub fn pressed(&self, button: T) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn just_pressed(&self, button: T) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    pub fn just_released(&self, button: T) -> bool {
+        self.just_released.contains(&button)
+    }
+
+    pub fn clear(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+}
+
+/// The current values of analog axes, fed by the platform layer.
+#[derive(Clone, Debug, Default)]
+pub struct AxisInput {
+    values: HashMap<Axis, f32>,
+}
+
+impl Resource for AxisInput {}
+
+impl AxisInput {
+    pub fn set(&mut self, axis: Axis, value: f32) {
+        self.values.insert(axis, value);
+    }
+
+    /// The value of the axis, zero if it was never set.
+    pub fn get(&self, axis: Axis) -> f32 {
+        self.values.get(&axis).copied().unwrap_or(0.0)
+    }
+}
@@ -0,0 +1,208 @@
+use crate::log_targets;
+use crate::region::Region;
+use crate::tracking_allocator::{TrackedAllocator, ALLOCATOR};
+use log::warn;
+use std::fmt::{Debug, Display, Formatter};
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+const NO_BUDGET: usize = usize::MAX;
+
+/// What happens when a region exceeds its budget, see [`TrackedAllocator::set_budget`].
+#[derive(Clone, Default)]
+pub enum BudgetPolicy {
+    /// Logs a warning.
+    #[default]
+    Warn,
+    /// Panics in debug builds and logs a warning in release builds.
+    PanicInDebug,
+    Callback(Arc<dyn Fn(&BudgetExceeded) + Send + Sync>),
+}
+
+impl BudgetPolicy {
+    pub fn callback(callback: impl Fn(&BudgetExceeded) + Send + Sync + 'static) -> Self {
+        Self::Callback(Arc::new(callback))
+    }
+
+    fn apply(&self, exceeded: &BudgetExceeded) {
+        match self {
+            BudgetPolicy::PanicInDebug if cfg!(debug_assertions) => panic!("{exceeded}"),
+            BudgetPolicy::Warn | BudgetPolicy::PanicInDebug => {
+                warn!(target: log_targets::BUDGET, "{exceeded}")
+            }
+            BudgetPolicy::Callback(callback) => callback(exceeded),
+        }
+    }
+}
+
+impl Debug for BudgetPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetPolicy::Warn => f.write_str("Warn"),
+            BudgetPolicy::PanicInDebug => f.write_str("PanicInDebug"),
+            BudgetPolicy::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// A region that went over its budget since the budgets were last checked.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BudgetExceeded {
+    pub region: Region,
+    pub budget: usize,
+    /// The live bytes of the region when the budgets were checked.
+    pub bytes: usize,
+    pub peak_bytes: usize,
+}
+
+impl Display for BudgetExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Memory region {:?} exceeded its budget of {} bytes: {} bytes live, {} bytes peak",
+            self.region, self.budget, self.bytes, self.peak_bytes
+        )
+    }
+}
+
+/// The byte budget of every region of the [`TrackedAllocator`].
+///
+/// Allocations only flag a region that grows past its budget, the policies run when the budgets
+/// are checked. The allocator must neither unwind nor log, which could allocate while the logger
+/// holds its lock.
+pub(crate) struct Budgets {
+    limits: [AtomicUsize; mem::variant_count::<Region>()],
+    exceeded: [AtomicBool; mem::variant_count::<Region>()],
+    policies: Mutex<[BudgetPolicy; mem::variant_count::<Region>()]>,
+}
+
+impl Budgets {
+    pub(crate) const fn new() -> Self {
+        Self {
+            limits: [const { AtomicUsize::new(NO_BUDGET) }; mem::variant_count::<Region>()],
+            exceeded: [const { AtomicBool::new(false) }; mem::variant_count::<Region>()],
+            policies: Mutex::new([const { BudgetPolicy::Warn }; mem::variant_count::<Region>()]),
+        }
+    }
+
+    /// Flags the region if its live bytes grew from `before` past its budget to `after`.
+    pub(crate) fn record_growth(&self, index: usize, before: usize, after: usize) {
+        let limit = self.limits[index].load(Ordering::Relaxed);
+        if before <= limit && after > limit {
+            self.exceeded[index].store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for Budgets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrackedAllocator {
+    /// Limits the live bytes of the region, going over the budget is handled by the region's
+    /// [`BudgetPolicy`] once the budgets are checked with [`TrackedAllocator::check_budgets`].
+    ///
+    /// A region that is over the new budget already is reported on the next check.
+    pub fn set_budget(&self, region: Region, bytes: usize) {
+        let index = Self::region_to_index(region);
+        self.budgets.limits[index].store(bytes, Ordering::SeqCst);
+        if self.get_bytes(region) > bytes {
+            self.budgets.exceeded[index].store(true, Ordering::SeqCst);
+        }
+    }
+
+    pub fn remove_budget(&self, region: Region) {
+        let index = Self::region_to_index(region);
+        self.budgets.limits[index].store(NO_BUDGET, Ordering::SeqCst);
+        self.budgets.exceeded[index].store(false, Ordering::SeqCst);
+    }
+
+    pub fn get_budget(&self, region: Region) -> Option<usize> {
+        let limit = self.budgets.limits[Self::region_to_index(region)].load(Ordering::SeqCst);
+        (limit != NO_BUDGET).then_some(limit)
+    }
+
+    /// Sets how going over the budget of the region is handled, [`BudgetPolicy::Warn`] by
+    /// default.
+    pub fn set_budget_policy(&self, region: Region, policy: BudgetPolicy) {
+        self.policies()[Self::region_to_index(region)] = policy;
+    }
+
+    /// Applies the policy of every region that went over its budget since the last check and
+    /// returns them. Run every frame by the [`MemoryPlugin`](crate::MemoryPlugin).
+    pub fn check_budgets(&self) -> Vec<BudgetExceeded> {
+        let exceeded: Vec<_> = Region::ALL
+            .into_iter()
+            .filter_map(|region| {
+                let index = Self::region_to_index(region);
+                if !self.budgets.exceeded[index].swap(false, Ordering::SeqCst) {
+                    return None;
+                }
+                Some(BudgetExceeded {
+                    region,
+                    budget: self.get_budget(region)?,
+                    bytes: self.get_bytes(region),
+                    peak_bytes: self.get_peak_bytes(region),
+                })
+            })
+            .collect();
+
+        for exceeded in &exceeded {
+            // Cloned so callbacks may change the policies
+            let policy = self.policies()[Self::region_to_index(exceeded.region)].clone();
+            policy.apply(exceeded);
+        }
+        exceeded
+    }
+
+    fn policies(&self) -> MutexGuard<'_, [BudgetPolicy; mem::variant_count::<Region>()]> {
+        self.budgets
+            .policies
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+pub fn check_memory_budgets() {
+    ALLOCATOR.check_budgets();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RegionGuard;
+
+    #[test]
+    fn crossing_a_budget_runs_the_policy_once() {
+        let budgets = Budgets::new();
+        budgets.limits[0].store(100, Ordering::SeqCst);
+        budgets.record_growth(0, 50, 100);
+        assert!(!budgets.exceeded[0].load(Ordering::SeqCst));
+        budgets.record_growth(0, 100, 101);
+        assert!(budgets.exceeded[0].load(Ordering::SeqCst));
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let recorded = reports.clone();
+        ALLOCATOR.set_budget_policy(
+            Region::Audio,
+            BudgetPolicy::callback(move |exceeded| recorded.lock().unwrap().push(*exceeded)),
+        );
+        ALLOCATOR.set_budget(Region::Audio, ALLOCATOR.get_bytes(Region::Audio) + 1024);
+
+        let _region_guard = RegionGuard::new(Region::Audio);
+        let buffer = vec![0u8; 4096];
+        ALLOCATOR.check_budgets();
+        ALLOCATOR.check_budgets();
+        ALLOCATOR.remove_budget(Region::Audio);
+        ALLOCATOR.set_budget_policy(Region::Audio, BudgetPolicy::Warn);
+        drop(buffer);
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].region, Region::Audio);
+        assert!(reports[0].peak_bytes > reports[0].budget);
+    }
+}
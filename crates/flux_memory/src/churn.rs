@@ -0,0 +1,191 @@
+use crate::log_targets;
+use crate::region::Region;
+use crate::tracking_allocator::{TrackedAllocator, ALLOCATOR};
+use crate::usage::RegionUsage;
+use flux_ecs::resource::{Res, ResMut, Resource};
+use flux_time::time::Time;
+use log::warn;
+
+/// Allocations and bytes per frame above which a region is reported as churning. Heap usage in
+/// hot systems that is freed again within the frame does not show up in the
+/// [`MemoryUsage`](crate::MemoryUsage), only in the churn.
+#[derive(Clone, Debug)]
+pub struct MemoryChurnThresholds {
+    thresholds: Vec<(Region, RegionUsage)>,
+}
+
+impl Resource for MemoryChurnThresholds {}
+
+/// Reports more than 1000 allocations per frame in the [`Region::ECS`].
+impl Default for MemoryChurnThresholds {
+    fn default() -> Self {
+        Self::empty().with_allocations(Region::ECS, 1000)
+    }
+}
+
+impl MemoryChurnThresholds {
+    pub fn empty() -> Self {
+        Self {
+            thresholds: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_allocations(mut self, region: Region, allocations: usize) -> Self {
+        self.threshold_mut(region).allocations = allocations;
+        self
+    }
+
+    #[must_use]
+    pub fn with_bytes(mut self, region: Region, bytes: usize) -> Self {
+        self.threshold_mut(region).bytes = bytes;
+        self
+    }
+
+    /// The threshold of a region, a limit of zero is not checked.
+    pub fn get(&self, region: Region) -> Option<RegionUsage> {
+        self.thresholds
+            .iter()
+            .find(|(threshold_region, _)| *threshold_region == region)
+            .map(|(_, threshold)| *threshold)
+    }
+
+    fn threshold_mut(&mut self, region: Region) -> &mut RegionUsage {
+        let index = match self.thresholds.iter().position(|(r, _)| *r == region) {
+            Some(index) => index,
+            None => {
+                self.thresholds.push((region, RegionUsage::default()));
+                self.thresholds.len() - 1
+            }
+        };
+        &mut self.thresholds[index].1
+    }
+}
+
+/// The allocations and bytes allocated per region during the last frame, freed or not.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryChurn {
+    totals: [RegionUsage; Region::ALL.len()],
+    last_frame: [RegionUsage; Region::ALL.len()],
+}
+
+impl Resource for MemoryChurn {}
+
+impl MemoryChurn {
+    pub fn new(allocator: &TrackedAllocator) -> Self {
+        Self {
+            totals: Self::totals(allocator),
+            last_frame: Default::default(),
+        }
+    }
+
+    fn totals(allocator: &TrackedAllocator) -> [RegionUsage; Region::ALL.len()] {
+        Region::ALL.map(|region| RegionUsage {
+            allocations: allocator.get_total_count(region),
+            bytes: allocator.get_total_bytes(region),
+        })
+    }
+
+    /// Records everything allocated since the previous frame boundary as the last frame.
+    pub fn end_frame(&mut self, allocator: &TrackedAllocator) {
+        let totals = Self::totals(allocator);
+        for ((last_frame, total), previous) in
+            self.last_frame.iter_mut().zip(totals).zip(self.totals)
+        {
+            *last_frame = RegionUsage {
+                allocations: total.allocations - previous.allocations,
+                bytes: total.bytes - previous.bytes,
+            };
+        }
+        self.totals = totals;
+    }
+
+    pub fn last_frame(&self, region: Region) -> RegionUsage {
+        self.last_frame[TrackedAllocator::region_to_index(region)]
+    }
+
+    /// The regions whose churn of the last frame exceeds their threshold.
+    pub fn exceeding(&self, thresholds: &MemoryChurnThresholds) -> Vec<(Region, RegionUsage)> {
+        thresholds
+            .thresholds
+            .iter()
+            .filter_map(|&(region, threshold)| {
+                let churn = self.last_frame(region);
+                let exceeds = |value: usize, limit: usize| limit > 0 && value > limit;
+                (exceeds(churn.allocations, threshold.allocations)
+                    || exceeds(churn.bytes, threshold.bytes))
+                .then_some((region, churn))
+            })
+            .collect()
+    }
+}
+
+/// Runs at the end of every frame and warns about the regions exceeding their
+/// [`MemoryChurnThresholds`].
+pub fn report_memory_churn(
+    mut churn: ResMut<MemoryChurn>,
+    thresholds: Option<Res<MemoryChurnThresholds>>,
+    time: Option<Res<Time>>,
+) {
+    churn.end_frame(&ALLOCATOR);
+
+    let Some(thresholds) = thresholds else {
+        return;
+    };
+    let exceeding = churn.exceeding(&thresholds);
+    if exceeding.is_empty() {
+        return;
+    }
+
+    let regions = exceeding
+        .iter()
+        .map(|(region, usage)| {
+            format!(
+                "{region:?}: {} allocations, {} bytes",
+                usage.allocations, usage.bytes
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let frame_time = time.map_or(0.0, |time| time.raw_delta_secs() * 1000.0);
+    warn!(
+        target: log_targets::CHURN,
+        "Memory churn above threshold in a frame of {frame_time:.2} ms: {regions}"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RegionGuard;
+
+    #[test]
+    fn allocations_freed_within_the_frame_are_churn() {
+        let mut churn = MemoryChurn::new(&ALLOCATOR);
+        {
+            let _region_guard = RegionGuard::new(Region::Physics);
+            for i in 0..10 {
+                drop(Box::new(i));
+            }
+        }
+        churn.end_frame(&ALLOCATOR);
+
+        // Other tests may allocate in the same region concurrently
+        assert!(churn.last_frame(Region::Physics).allocations >= 10);
+
+        let thresholds = MemoryChurnThresholds::empty()
+            .with_allocations(Region::Physics, 5)
+            .with_bytes(Region::Audio, usize::MAX);
+        let exceeding = churn.exceeding(&thresholds);
+        assert_eq!(exceeding.len(), 1);
+        assert_eq!(exceeding[0].0, Region::Physics);
+
+        assert_eq!(
+            MemoryChurnThresholds::default().get(Region::ECS),
+            Some(RegionUsage {
+                allocations: 1000,
+                bytes: 0,
+            })
+        );
+    }
+}
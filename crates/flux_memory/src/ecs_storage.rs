@@ -0,0 +1,149 @@
+use crate::region::{Region, RegionGuard};
+use flux_ecs::storage::StorageAllocator;
+use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use std::ptr::NonNull;
+use std::sync::Mutex;
+
+/// Attributes all component storage of a world to a memory region, [`Region::ECS`] by default.
+#[derive(Clone, Copy, Debug)]
+pub struct RegionStorage {
+    region: Region,
+}
+
+impl RegionStorage {
+    pub fn new(region: Region) -> Self {
+        Self { region }
+    }
+}
+
+impl Default for RegionStorage {
+    fn default() -> Self {
+        Self::new(Region::ECS)
+    }
+}
+
+unsafe impl StorageAllocator for RegionStorage {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let _region_guard = RegionGuard::new(self.region);
+        NonNull::new(unsafe { alloc(layout) })
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let _region_guard = RegionGuard::new(self.region);
+        unsafe { dealloc(ptr.as_ptr(), layout) }
+    }
+}
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+struct Chunks {
+    chunks: Vec<(NonNull<u8>, Layout)>,
+    cursor: usize,
+    end: usize,
+}
+
+/// Bump allocates component storage from large chunks attributed to [`Region::ECS`].
+///
+/// Deallocations are no-ops, all chunks are released at once when the arena is dropped, i.e.
+/// together with the world owning it. Columns that grow leave their old buffers behind, so this
+/// suits worlds that are filled once and cleared as a whole, like streamed levels.
+pub struct ArenaStorage {
+    chunks: Mutex<Chunks>,
+}
+
+// The chunks are owned by the arena and only handed out behind the mutex
+unsafe impl Send for ArenaStorage {}
+unsafe impl Sync for ArenaStorage {}
+
+impl Default for ArenaStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArenaStorage {
+    pub fn new() -> Self {
+        Self {
+            chunks: Mutex::new(Chunks {
+                chunks: Vec::new(),
+                cursor: 0,
+                end: 0,
+            }),
+        }
+    }
+
+    /// The bytes reserved from the system, including unused space at the end of chunks.
+    pub fn reserved_bytes(&self) -> usize {
+        let chunks = self.chunks.lock().unwrap();
+        chunks.chunks.iter().map(|(_, layout)| layout.size()).sum()
+    }
+}
+
+unsafe impl StorageAllocator for ArenaStorage {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let mut chunks = self.chunks.lock().unwrap();
+
+        let start = chunks.cursor.next_multiple_of(layout.align());
+        if chunks.cursor != 0 && start + layout.size() <= chunks.end {
+            chunks.cursor = start + layout.size();
+            return NonNull::new(start as *mut u8);
+        }
+
+        let chunk_layout =
+            Layout::from_size_align(layout.size().max(CHUNK_SIZE), layout.align()).ok()?;
+        let chunk = {
+            let _region_guard = RegionGuard::new(Region::ECS);
+            NonNull::new(unsafe { alloc(chunk_layout) })
+                .unwrap_or_else(|| handle_alloc_error(chunk_layout))
+        };
+
+        chunks.chunks.push((chunk, chunk_layout));
+        let start = chunk.as_ptr() as usize;
+        chunks.cursor = start + layout.size();
+        chunks.end = start + chunk_layout.size();
+        Some(chunk)
+    }
+
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+impl Drop for ArenaStorage {
+    fn drop(&mut self) {
+        let _region_guard = RegionGuard::new(Region::ECS);
+        let chunks = self.chunks.get_mut().unwrap();
+        for (chunk, layout) in chunks.chunks.drain(..) {
+            unsafe { dealloc(chunk.as_ptr(), layout) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ALLOCATOR;
+    use flux_ecs::component::Component;
+    use flux_ecs::world::World;
+
+    struct Health(#[allow(dead_code)] u32);
+
+    impl Component for Health {}
+
+    #[test]
+    fn arena_storage_is_released_with_the_world() {
+        let arena = ArenaStorage::new();
+        let first = arena.allocate(Layout::new::<u64>()).unwrap();
+        let second = arena.allocate(Layout::new::<u64>()).unwrap();
+        assert_eq!(second.as_ptr() as usize - first.as_ptr() as usize, 8);
+        assert_eq!(arena.reserved_bytes(), CHUNK_SIZE);
+        drop(arena);
+
+        let mut world = World::with_storage_allocator(ArenaStorage::new());
+        for health in 0..1000 {
+            world.spawn((Health(health),));
+        }
+        let bytes = ALLOCATOR.get_bytes(Region::ECS);
+        assert!(bytes >= CHUNK_SIZE);
+        drop(world);
+        assert!(ALLOCATOR.get_bytes(Region::ECS) <= bytes - CHUNK_SIZE);
+    }
+}
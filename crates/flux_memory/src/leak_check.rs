@@ -0,0 +1,238 @@
+//! Records every live allocation in a side table, enabled by the `leak-check` feature.
+//!
+//! The side table itself allocates through the global allocator, those allocations are marked
+//! as internal and neither tracked nor counted in the region statistics.
+
+use crate::region::Region;
+use crate::tracking_allocator::TrackedAllocator;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// The number of size classes of a [`SizeHistogram`].
+pub const SIZE_CLASSES: usize = 16;
+
+const REGION_COUNT: usize = mem::variant_count::<Region>();
+
+thread_local! {
+    static INTERNAL: Cell<bool> = const { Cell::new(false) };
+}
+
+static LIVE_ALLOCATIONS: Mutex<BTreeMap<usize, LiveAllocation>> = Mutex::new(BTreeMap::new());
+
+static SIZE_CLASS_COUNTS: [[AtomicUsize; SIZE_CLASSES]; REGION_COUNT] =
+    [const { [const { AtomicUsize::new(0) }; SIZE_CLASSES] }; REGION_COUNT];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LiveAllocation {
+    pub address: usize,
+    pub size: usize,
+    /// The region that was active when the memory was allocated.
+    pub region: Region,
+}
+
+/// Whether the current allocation is made by the side table and must not be tracked.
+pub(crate) fn is_internal() -> bool {
+    INTERNAL.with(Cell::get)
+}
+
+/// Runs `f` with exclusive access to the side table. Allocations made by `f` are internal and
+/// not tracked.
+fn with_table<R>(f: impl FnOnce(&mut BTreeMap<usize, LiveAllocation>) -> R) -> R {
+    INTERNAL.with(|internal| internal.set(true));
+    let result = {
+        let mut table = LIVE_ALLOCATIONS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&mut table)
+    };
+    INTERNAL.with(|internal| internal.set(false));
+    result
+}
+
+pub(crate) fn record_allocation(ptr: *mut u8, size: usize, region: Region) {
+    let index = TrackedAllocator::region_to_index(region);
+    SIZE_CLASS_COUNTS[index][size_class(size)].fetch_add(1, Ordering::Relaxed);
+
+    let address = ptr as usize;
+    with_table(|table| {
+        table.insert(
+            address,
+            LiveAllocation {
+                address,
+                size,
+                region,
+            },
+        )
+    });
+}
+
+pub(crate) fn record_deallocation(ptr: *mut u8) {
+    with_table(|table| table.remove(&(ptr as usize)));
+}
+
+/// Moves the record of a reallocated block, it keeps the region it was allocated in.
+pub(crate) fn record_reallocation(old_ptr: *mut u8, new_ptr: *mut u8, new_size: usize) {
+    with_table(|table| {
+        if let Some(mut allocation) = table.remove(&(old_ptr as usize)) {
+            allocation.address = new_ptr as usize;
+            allocation.size = new_size;
+            table.insert(allocation.address, allocation);
+        }
+    });
+}
+
+/// The size class of an allocation, class `n` holds sizes up to `16 << n` bytes and the last
+/// class holds everything larger.
+pub fn size_class(size: usize) -> usize {
+    let class = size.max(1).next_power_of_two().trailing_zeros().saturating_sub(4) as usize;
+    class.min(SIZE_CLASSES - 1)
+}
+
+/// The number of allocations per size class made in a region since startup.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SizeHistogram {
+    pub counts: [usize; SIZE_CLASSES],
+}
+
+impl SizeHistogram {
+    /// The largest size in bytes of the class, `None` for the last, unbounded class.
+    pub fn upper_bound(class: usize) -> Option<usize> {
+        (class < SIZE_CLASSES - 1).then(|| 16 << class)
+    }
+}
+
+/// The allocations that are still alive, aggregated per region.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LeakReport {
+    pub counts: [usize; REGION_COUNT],
+    pub bytes: [usize; REGION_COUNT],
+}
+
+impl LeakReport {
+    pub fn count(&self, region: Region) -> usize {
+        self.counts[TrackedAllocator::region_to_index(region)]
+    }
+
+    pub fn bytes(&self, region: Region) -> usize {
+        self.bytes[TrackedAllocator::region_to_index(region)]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|&count| count == 0)
+    }
+}
+
+impl Display for LeakReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for region in Region::ALL {
+            if self.count(region) > 0 {
+                writeln!(
+                    f,
+                    "{region:?}: {} live allocations ({} bytes)",
+                    self.count(region),
+                    self.bytes(region)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TrackedAllocator {
+    /// Aggregates the allocations that are still alive, call it at shutdown to find leaks.
+    pub fn leak_report(&self) -> LeakReport {
+        with_table(|table| {
+            let mut report = LeakReport {
+                counts: [0; REGION_COUNT],
+                bytes: [0; REGION_COUNT],
+            };
+            for allocation in table.values() {
+                let index = Self::region_to_index(allocation.region);
+                report.counts[index] += 1;
+                report.bytes[index] += allocation.size;
+            }
+            report
+        })
+    }
+
+    /// A snapshot of all live allocations of a region, ordered by address.
+    pub fn live_allocations(&self, region: Region) -> Vec<LiveAllocation> {
+        let in_region = |allocation: &&LiveAllocation| allocation.region == region;
+
+        loop {
+            let len = with_table(|table| table.values().filter(in_region).count());
+            // Allocated outside of the table lock so the snapshot is tracked like any other
+            // allocation, it is filled without reallocating below. Other threads may allocate in
+            // the meantime, in which case it is retried.
+            let mut allocations = Vec::with_capacity(len + 1);
+            let own_address = allocations.as_ptr() as usize;
+
+            let complete = with_table(|table| {
+                let live = table
+                    .values()
+                    .filter(in_region)
+                    .filter(|allocation| allocation.address != own_address);
+                if live.clone().count() > allocations.capacity() {
+                    return false;
+                }
+                allocations.extend(live);
+                true
+            });
+
+            if complete {
+                return allocations;
+            }
+        }
+    }
+
+    pub fn size_histogram(&self, region: Region) -> SizeHistogram {
+        let index = Self::region_to_index(region);
+        SizeHistogram {
+            counts: std::array::from_fn(|class| {
+                SIZE_CLASS_COUNTS[index][class].load(Ordering::Relaxed)
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RegionGuard, ALLOCATOR};
+
+    #[test]
+    fn size_classes_are_powers_of_two() {
+        assert_eq!(size_class(0), 0);
+        assert_eq!(size_class(16), 0);
+        assert_eq!(size_class(17), 1);
+        assert_eq!(size_class(1 << 20), SIZE_CLASSES - 1);
+        assert_eq!(SizeHistogram::upper_bound(1), Some(32));
+    }
+
+    #[test]
+    fn live_allocations_are_reported_per_region() {
+        let _region_guard = RegionGuard::new(Region::Physics);
+        let histogram = ALLOCATOR.size_histogram(Region::Physics);
+        let leaked = Box::leak(Box::new([0u8; 100]));
+        let leaked_address = leaked.as_ptr() as usize;
+
+        let report = ALLOCATOR.leak_report();
+        assert!(report.count(Region::Physics) >= 1);
+        assert!(report.bytes(Region::Physics) >= 100);
+        assert!(ALLOCATOR
+            .live_allocations(Region::Physics)
+            .iter()
+            .any(|allocation| allocation.address == leaked_address && allocation.size == 100));
+        assert!(ALLOCATOR.size_histogram(Region::Physics).counts[3] > histogram.counts[3]);
+
+        unsafe { drop(Box::from_raw(leaked)) };
+        assert!(!ALLOCATOR
+            .live_allocations(Region::Physics)
+            .iter()
+            .any(|allocation| allocation.address == leaked_address));
+    }
+}
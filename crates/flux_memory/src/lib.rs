@@ -1,7 +1,29 @@
 #![feature(variant_count)]
 
+mod budget;
+mod churn;
+mod ecs_storage;
+#[cfg(feature = "leak-check")]
+mod leak_check;
+pub mod log_targets;
 mod region;
+mod stats;
 mod tracking_allocator;
+mod usage;
 
+#[cfg(feature = "leak-check")]
+pub use leak_check::{LeakReport, LiveAllocation, SizeHistogram, SIZE_CLASSES};
+
+pub use budget::{check_memory_budgets, BudgetExceeded, BudgetPolicy};
+pub use churn::{report_memory_churn, MemoryChurn, MemoryChurnThresholds};
+pub use ecs_storage::{ArenaStorage, RegionStorage};
 pub use region::{get_current_region, Region, RegionGuard};
+pub use stats::{
+    log_memory_stats, update_memory_stats, MemorySnapshot, MemoryStats, MemoryStatsLogging,
+    RegionSnapshot,
+};
 pub use tracking_allocator::ALLOCATOR;
+pub use usage::{
+    crossed_thresholds, sample_memory_usage, CrossingDirection, MemoryPlugin,
+    MemoryThresholdCrossed, MemoryThresholds, MemoryUsage, RegionUsage,
+};
@@ -0,0 +1,6 @@
+//! Log targets used by the memory tracking, configure their levels with
+//! [`LogSettings`](flux_ecs::logging::LogSettings).
+
+pub const STATS: &str = "flux_memory::stats";
+pub const BUDGET: &str = "flux_memory::budget";
+pub const CHURN: &str = "flux_memory::churn";
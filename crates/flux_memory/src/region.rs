@@ -10,6 +10,17 @@ pub enum Region {
     ECS,
 }
 
+impl Region {
+    pub const ALL: [Region; 6] = [
+        Region::Graphics,
+        Region::Physics,
+        Region::Audio,
+        Region::Scene,
+        Region::General,
+        Region::ECS,
+    ];
+}
+
 thread_local! {
     static CURRENT_REGION: RefCell<Region> = const { RefCell::new(Region::General) };
 }
@@ -0,0 +1,184 @@
+use crate::log_targets;
+use crate::region::Region;
+use crate::tracking_allocator::{TrackedAllocator, ALLOCATOR};
+use crate::usage::RegionUsage;
+use flux_ecs::resource::{Res, ResMut, Resource};
+use flux_time::time::Time;
+use log::info;
+use std::time::Duration;
+
+/// The tracked memory of a region at the time of a [`MemorySnapshot`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RegionSnapshot {
+    /// The live allocations and their bytes.
+    pub allocations: usize,
+    pub bytes: usize,
+    /// The allocations and bytes since startup, including freed ones.
+    pub total_allocations: usize,
+    pub total_bytes: usize,
+    /// The most live allocations and bytes since startup.
+    pub peak_allocations: usize,
+    pub peak_bytes: usize,
+}
+
+/// The counters of every region of the [`TrackedAllocator`], read with
+/// [`TrackedAllocator::snapshot`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemorySnapshot {
+    regions: [RegionSnapshot; Region::ALL.len()],
+}
+
+impl MemorySnapshot {
+    pub fn get(&self, region: Region) -> RegionSnapshot {
+        self.regions[TrackedAllocator::region_to_index(region)]
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = (Region, RegionSnapshot)> + '_ {
+        Region::ALL.into_iter().zip(self.regions.iter().copied())
+    }
+
+    /// The live bytes of all regions.
+    pub fn total_bytes(&self) -> usize {
+        self.regions.iter().map(|region| region.bytes).sum()
+    }
+}
+
+impl TrackedAllocator {
+    /// Reads the counters of every region. The regions are read one after another, allocations
+    /// on other threads may land between them.
+    pub fn snapshot(&self) -> MemorySnapshot {
+        MemorySnapshot {
+            regions: Region::ALL.map(|region| RegionSnapshot {
+                allocations: self.get_count(region),
+                bytes: self.get_bytes(region),
+                total_allocations: self.get_total_count(region),
+                total_bytes: self.get_total_bytes(region),
+                peak_allocations: self.get_peak_count(region),
+                peak_bytes: self.get_peak_bytes(region),
+            }),
+        }
+    }
+}
+
+/// The latest [`MemorySnapshot`] and the peak usage per region, updated every frame by the
+/// [`MemoryPlugin`](crate::MemoryPlugin).
+///
+/// The peaks are the high-water marks tracked by the allocator, which include usage that is freed
+/// again within a frame.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStats {
+    current: MemorySnapshot,
+    peaks: [RegionUsage; Region::ALL.len()],
+}
+
+impl Resource for MemoryStats {}
+
+impl MemoryStats {
+    pub fn new(snapshot: MemorySnapshot) -> Self {
+        let mut stats = Self::default();
+        stats.record(snapshot);
+        stats
+    }
+
+    pub fn record(&mut self, snapshot: MemorySnapshot) {
+        for (peak, (_, region)) in self.peaks.iter_mut().zip(snapshot.regions()) {
+            peak.allocations = peak
+                .allocations
+                .max(region.allocations.max(region.peak_allocations));
+            peak.bytes = peak.bytes.max(region.bytes.max(region.peak_bytes));
+        }
+        self.current = snapshot;
+    }
+
+    pub fn current(&self) -> &MemorySnapshot {
+        &self.current
+    }
+
+    /// The most live allocations and bytes of the region, the two peaks may stem from different
+    /// moments.
+    pub fn peak(&self, region: Region) -> RegionUsage {
+        self.peaks[TrackedAllocator::region_to_index(region)]
+    }
+}
+
+/// How often [`log_memory_stats`] logs the [`MemoryStats`] of every region, `None` disables the
+/// logging.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MemoryStatsLogging {
+    pub interval: Option<Duration>,
+}
+
+impl Resource for MemoryStatsLogging {}
+
+pub fn update_memory_stats(mut stats: ResMut<MemoryStats>) {
+    stats.record(ALLOCATOR.snapshot());
+}
+
+/// Logs the [`MemoryStats`] whenever another [`MemoryStatsLogging::interval`] of unscaled time
+/// passed.
+pub fn log_memory_stats(
+    stats: Res<MemoryStats>,
+    logging: Res<MemoryStatsLogging>,
+    time: Option<Res<Time>>,
+) {
+    let (Some(interval), Some(time)) = (logging.interval, time) else {
+        return;
+    };
+    if !interval_passed(time.raw_elapsed(), time.raw_delta(), interval) {
+        return;
+    }
+
+    info!(
+        target: log_targets::STATS,
+        "Memory usage: {} bytes in {} regions",
+        stats.current().total_bytes(),
+        Region::ALL.len()
+    );
+    for (region, usage) in stats.current().regions() {
+        let peak = stats.peak(region);
+        info!(
+            target: log_targets::STATS,
+            "  {region:?}: {} allocations, {} bytes (peak {} allocations, {} bytes)",
+            usage.allocations, usage.bytes, peak.allocations, peak.bytes
+        );
+    }
+}
+
+/// Whether the last frame of length `delta` ending at `elapsed` crossed a multiple of
+/// `interval`.
+fn interval_passed(elapsed: Duration, delta: Duration, interval: Duration) -> bool {
+    let interval = interval.as_nanos().max(1);
+    let previous = elapsed.saturating_sub(delta).as_nanos() / interval;
+    elapsed.as_nanos() / interval > previous
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RegionGuard;
+
+    #[test]
+    fn peaks_outlive_the_current_snapshot() {
+        let mut snapshot = MemorySnapshot::default();
+        snapshot.regions[TrackedAllocator::region_to_index(Region::Scene)].bytes = 4096;
+        let mut stats = MemoryStats::new(snapshot);
+
+        stats.record(MemorySnapshot::default());
+        assert_eq!(stats.current().get(Region::Scene).bytes, 0);
+        assert_eq!(stats.peak(Region::Scene).bytes, 4096);
+
+        let _region_guard = RegionGuard::new(Region::Scene);
+        let before = ALLOCATOR.snapshot().get(Region::Scene);
+        let boxed = Box::new([0u8; 64]);
+        let after = ALLOCATOR.snapshot().get(Region::Scene);
+        assert!(after.total_allocations > before.total_allocations);
+        assert!(after.total_bytes >= before.total_bytes + 64);
+        assert!(after.peak_bytes >= after.bytes);
+        drop(boxed);
+
+        let second = Duration::from_secs(1);
+        let frame = Duration::from_millis(16);
+        assert!(interval_passed(Duration::from_millis(1004), frame, second));
+        assert!(!interval_passed(Duration::from_millis(1020), frame, second));
+    }
+}
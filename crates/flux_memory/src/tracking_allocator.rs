@@ -1,3 +1,6 @@
+use crate::budget::Budgets;
+#[cfg(feature = "leak-check")]
+use crate::leak_check;
 use crate::region::{get_current_region, Region};
 use std::alloc::{GlobalAlloc, Layout, System};
 use std::mem;
@@ -10,6 +13,13 @@ pub static ALLOCATOR: TrackedAllocator = TrackedAllocator::new();
 pub struct TrackedAllocator {
     allocations: [AtomicUsize; mem::variant_count::<Region>()],
     allocated_bytes: [AtomicUsize; mem::variant_count::<Region>()],
+    /// Only ever increase, so allocations freed within a frame still show up as churn.
+    total_allocations: [AtomicUsize; mem::variant_count::<Region>()],
+    total_allocated_bytes: [AtomicUsize; mem::variant_count::<Region>()],
+    /// The high-water marks of the live allocations and bytes.
+    peak_allocations: [AtomicUsize; mem::variant_count::<Region>()],
+    peak_allocated_bytes: [AtomicUsize; mem::variant_count::<Region>()],
+    pub(crate) budgets: Budgets,
 }
 
 impl TrackedAllocator {
@@ -31,10 +41,43 @@ impl TrackedAllocator {
                 AtomicUsize::new(0),
                 AtomicUsize::new(0),
             ],
+            total_allocations: [
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+            ],
+            total_allocated_bytes: [
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+            ],
+            peak_allocations: [
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+            ],
+            peak_allocated_bytes: [
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+                AtomicUsize::new(0),
+            ],
+            budgets: Budgets::new(),
         }
     }
 
-    fn region_to_index(region: Region) -> usize {
+    pub(crate) fn region_to_index(region: Region) -> usize {
         match region {
             Region::Graphics => 0,
             Region::Physics => 1,
@@ -54,23 +97,131 @@ impl TrackedAllocator {
         let index = Self::region_to_index(region);
         self.allocated_bytes[index].load(Ordering::SeqCst)
     }
+
+    /// The number of allocations made in the region since startup, including freed ones.
+    pub fn get_total_count(&self, region: Region) -> usize {
+        let index = Self::region_to_index(region);
+        self.total_allocations[index].load(Ordering::SeqCst)
+    }
+
+    /// The bytes allocated in the region since startup, including freed ones and reallocation
+    /// growth.
+    pub fn get_total_bytes(&self, region: Region) -> usize {
+        let index = Self::region_to_index(region);
+        self.total_allocated_bytes[index].load(Ordering::SeqCst)
+    }
+
+    /// The most live allocations the region had at once since startup.
+    pub fn get_peak_count(&self, region: Region) -> usize {
+        let index = Self::region_to_index(region);
+        self.peak_allocations[index].load(Ordering::SeqCst)
+    }
+
+    /// The most live bytes the region had at once since startup, e.g. to size its budget.
+    pub fn get_peak_bytes(&self, region: Region) -> usize {
+        let index = Self::region_to_index(region);
+        self.peak_allocated_bytes[index].load(Ordering::SeqCst)
+    }
+
+    /// Adds to the live counters of the region, raises its peaks to the new values and flags it
+    /// if it went over its budget.
+    fn grow(&self, index: usize, allocations: usize, added_bytes: usize) {
+        let allocations =
+            self.allocations[index].fetch_add(allocations, Ordering::SeqCst) + allocations;
+        let bytes =
+            self.allocated_bytes[index].fetch_add(added_bytes, Ordering::SeqCst) + added_bytes;
+        self.peak_allocations[index].fetch_max(allocations, Ordering::SeqCst);
+        self.peak_allocated_bytes[index].fetch_max(bytes, Ordering::SeqCst);
+        self.budgets
+            .record_growth(index, bytes - added_bytes, bytes);
+    }
+}
+
+/// Every tracked allocation is preceded by a header byte holding the index of the region it was
+/// allocated in, so that freeing and resizing it updates that region no matter which region is
+/// active at the time.
+///
+/// Returns the layout of the allocation including the header and the offset of the memory handed
+/// out, which keeps the alignment of `layout`.
+fn with_header(layout: Layout) -> Option<(Layout, usize)> {
+    Layout::new::<u8>().extend(layout).ok()
 }
 
 unsafe impl GlobalAlloc for TrackedAllocator {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        let index = Self::region_to_index(get_current_region());
-        self.allocations[index].fetch_add(1, Ordering::SeqCst);
-        self.allocated_bytes[index].fetch_add(layout.size(), Ordering::SeqCst);
+        #[cfg(feature = "leak-check")]
+        if leak_check::is_internal() {
+            return System.alloc(layout);
+        }
+
+        let Some((padded, offset)) = with_header(layout) else {
+            return std::ptr::null_mut();
+        };
+        let base = System.alloc(padded);
+        if base.is_null() {
+            return base;
+        }
+
+        let region = get_current_region();
+        let index = Self::region_to_index(region);
+        self.grow(index, 1, layout.size());
+        self.total_allocations[index].fetch_add(1, Ordering::SeqCst);
+        self.total_allocated_bytes[index].fetch_add(layout.size(), Ordering::SeqCst);
 
-        System.alloc(layout)
+        let ptr = base.add(offset);
+        ptr.sub(1).write(index as u8);
+        #[cfg(feature = "leak-check")]
+        leak_check::record_allocation(ptr, layout.size(), region);
+        ptr
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        let index = Self::region_to_index(get_current_region());
+        #[cfg(feature = "leak-check")]
+        if leak_check::is_internal() {
+            return System.dealloc(ptr, layout);
+        }
+
+        // The header fit when the memory was allocated
+        let (padded, offset) = with_header(layout).unwrap_unchecked();
+        let index = ptr.sub(1).read() as usize;
         self.allocations[index].fetch_sub(1, Ordering::SeqCst);
         self.allocated_bytes[index].fetch_sub(layout.size(), Ordering::SeqCst);
 
-        System.dealloc(ptr, layout);
+        #[cfg(feature = "leak-check")]
+        leak_check::record_deallocation(ptr);
+        System.dealloc(ptr.sub(offset), padded);
+    }
+
+    /// Resizes in place through the system allocator when possible. The allocation count is left
+    /// untouched and only the size delta is attributed to the region the memory was allocated
+    /// in.
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        #[cfg(feature = "leak-check")]
+        if leak_check::is_internal() {
+            return System.realloc(ptr, layout, new_size);
+        }
+
+        let (padded, offset) = with_header(layout).unwrap_unchecked();
+        let Some(padded_size) = offset.checked_add(new_size) else {
+            return std::ptr::null_mut();
+        };
+        let index = ptr.sub(1).read() as usize;
+        let new_base = System.realloc(ptr.sub(offset), padded, padded_size);
+        if new_base.is_null() {
+            return new_base;
+        }
+
+        if new_size >= layout.size() {
+            self.grow(index, 0, new_size - layout.size());
+            self.total_allocated_bytes[index].fetch_add(new_size - layout.size(), Ordering::SeqCst);
+        } else {
+            self.allocated_bytes[index].fetch_sub(layout.size() - new_size, Ordering::SeqCst);
+        }
+
+        let new_ptr = new_base.add(offset);
+        #[cfg(feature = "leak-check")]
+        leak_check::record_reallocation(ptr, new_ptr, new_size);
+        new_ptr
     }
 }
 
@@ -89,4 +240,52 @@ mod tests {
 
         assert_eq!(ALLOCATOR.get_count(Region::Graphics), allocation_count + 1);
     }
+
+    #[test]
+    fn realloc_only_tracks_the_size_delta() {
+        let _region_guard = crate::RegionGuard::new(Region::Audio);
+        let mut vec = Vec::<u8>::with_capacity(16);
+        let allocation_count = ALLOCATOR.get_count(Region::Audio);
+        let allocated_bytes = ALLOCATOR.get_bytes(Region::Audio);
+
+        vec.reserve_exact(64);
+        assert_eq!(ALLOCATOR.get_count(Region::Audio), allocation_count);
+        assert_eq!(ALLOCATOR.get_bytes(Region::Audio), allocated_bytes + 48);
+
+        vec.shrink_to(8);
+        assert_eq!(ALLOCATOR.get_count(Region::Audio), allocation_count);
+        assert_eq!(ALLOCATOR.get_bytes(Region::Audio), allocated_bytes - 8);
+    }
+
+    #[test]
+    fn peaks_remember_freed_allocations() {
+        let _region_guard = crate::RegionGuard::new(Region::Physics);
+        let buffer = vec![0u8; 1 << 20];
+        let peak_bytes = ALLOCATOR.get_peak_bytes(Region::Physics);
+        assert!(peak_bytes >= ALLOCATOR.get_bytes(Region::Physics));
+        assert!(peak_bytes >= 1 << 20);
+        assert!(ALLOCATOR.get_peak_count(Region::Physics) >= 1);
+
+        drop(buffer);
+        assert!(ALLOCATOR.get_peak_bytes(Region::Physics) >= peak_bytes);
+        assert!(ALLOCATOR.get_bytes(Region::Physics) < peak_bytes);
+    }
+
+    #[test]
+    fn frees_are_attributed_to_the_allocating_region() {
+        let buffer = {
+            let _region_guard = crate::RegionGuard::new(Region::Scene);
+            vec![0u8; 1 << 20]
+        };
+        let scene_bytes = ALLOCATOR.get_bytes(Region::Scene);
+        let graphics_bytes = ALLOCATOR.get_bytes(Region::Graphics);
+
+        {
+            let _region_guard = crate::RegionGuard::new(Region::Graphics);
+            drop(buffer);
+        }
+        // Other tests allocate in these regions concurrently, but far less than the buffer
+        assert!(ALLOCATOR.get_bytes(Region::Scene) <= scene_bytes - (1 << 19));
+        assert!(ALLOCATOR.get_bytes(Region::Graphics) >= graphics_bytes.saturating_sub(1 << 19));
+    }
 }
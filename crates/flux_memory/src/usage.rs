@@ -0,0 +1,215 @@
+use crate::budget::check_memory_budgets;
+use crate::churn::{report_memory_churn, MemoryChurn, MemoryChurnThresholds};
+use crate::region::Region;
+use crate::stats::{log_memory_stats, update_memory_stats, MemoryStats, MemoryStatsLogging};
+use crate::tracking_allocator::{TrackedAllocator, ALLOCATOR};
+use flux_ecs::commands::Commands;
+use flux_ecs::plugin::Plugin;
+use flux_ecs::resource::{Res, Resource};
+use flux_ecs::schedule::CoreSchedule;
+use flux_ecs::world::World;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RegionUsage {
+    pub allocations: usize,
+    pub bytes: usize,
+}
+
+/// The memory usage per region, sampled from the tracking allocator every frame.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemoryUsage {
+    regions: [RegionUsage; Region::ALL.len()],
+}
+
+impl Resource for MemoryUsage {}
+
+impl MemoryUsage {
+    pub fn sample(allocator: &TrackedAllocator) -> Self {
+        Self {
+            regions: Region::ALL.map(|region| RegionUsage {
+                allocations: allocator.get_count(region),
+                bytes: allocator.get_bytes(region),
+            }),
+        }
+    }
+
+    pub fn get(&self, region: Region) -> RegionUsage {
+        self.regions[TrackedAllocator::region_to_index(region)]
+    }
+
+    pub fn set(&mut self, region: Region, usage: RegionUsage) {
+        self.regions[TrackedAllocator::region_to_index(region)] = usage;
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.regions.iter().map(|usage| usage.bytes).sum()
+    }
+}
+
+/// Byte thresholds per region that emit a [`MemoryThresholdCrossed`] event when crossed.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryThresholds {
+    thresholds: Vec<(Region, usize)>,
+}
+
+impl Resource for MemoryThresholds {}
+
+impl MemoryThresholds {
+    #[must_use]
+    pub fn with(mut self, region: Region, bytes: usize) -> Self {
+        self.add(region, bytes);
+        self
+    }
+
+    pub fn add(&mut self, region: Region, bytes: usize) {
+        self.thresholds.push((region, bytes));
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CrossingDirection {
+    /// The usage reached or exceeded the threshold.
+    Above,
+    /// The usage dropped below the threshold again.
+    Below,
+}
+
+/// Sent as an [`Events<MemoryThresholdCrossed>`](flux_ecs::event::Events) event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MemoryThresholdCrossed {
+    pub region: Region,
+    pub threshold: usize,
+    pub bytes: usize,
+    pub direction: CrossingDirection,
+}
+
+/// The thresholds crossed between two samples.
+pub fn crossed_thresholds(
+    previous: &MemoryUsage,
+    current: &MemoryUsage,
+    thresholds: &MemoryThresholds,
+) -> Vec<MemoryThresholdCrossed> {
+    thresholds
+        .thresholds
+        .iter()
+        .filter_map(|&(region, threshold)| {
+            let before = previous.get(region).bytes;
+            let bytes = current.get(region).bytes;
+
+            let direction = if before < threshold && bytes >= threshold {
+                CrossingDirection::Above
+            } else if before >= threshold && bytes < threshold {
+                CrossingDirection::Below
+            } else {
+                return None;
+            };
+
+            Some(MemoryThresholdCrossed {
+                region,
+                threshold,
+                bytes,
+                direction,
+            })
+        })
+        .collect()
+}
+
+pub fn sample_memory_usage(
+    previous: Option<Res<MemoryUsage>>,
+    thresholds: Option<Res<MemoryThresholds>>,
+    mut commands: Commands,
+) {
+    let current = MemoryUsage::sample(&ALLOCATOR);
+
+    if let (Some(previous), Some(thresholds)) = (previous, thresholds) {
+        for event in crossed_thresholds(&previous, &current, &thresholds) {
+            commands.send_event(event);
+        }
+    }
+
+    commands.insert_resource(current);
+}
+
+/// Samples the tracking allocator into the [`MemoryUsage`] resource on every run of the `Main`
+/// schedule and emits [`MemoryThresholdCrossed`] events for the configured
+/// [`MemoryThresholds`], the [`MemoryStats`] are updated and the memory budgets are checked
+/// alongside, see [`TrackedAllocator::set_budget`]. At the end of every frame the
+/// [`MemoryChurn`] is recorded, the regions exceeding the [`MemoryChurnThresholds`] are reported
+/// and the stats are logged as configured by the [`MemoryStatsLogging`].
+pub struct MemoryPlugin;
+
+impl Plugin for MemoryPlugin {
+    fn init(&self, world: &mut World) {
+        if world.get_resource::<MemoryThresholds>().is_none() {
+            world.add_resource(MemoryThresholds::default());
+        }
+        if world.get_resource::<MemoryChurnThresholds>().is_none() {
+            world.add_resource(MemoryChurnThresholds::default());
+        }
+        if world.get_resource::<MemoryStatsLogging>().is_none() {
+            world.add_resource(MemoryStatsLogging::default());
+        }
+        world.add_resource(MemoryUsage::sample(&ALLOCATOR));
+        world.add_resource(MemoryChurn::new(&ALLOCATOR));
+        world.add_resource(MemoryStats::new(ALLOCATOR.snapshot()));
+
+        world.add_system(CoreSchedule::Main, sample_memory_usage);
+        world.add_system(CoreSchedule::Main, update_memory_stats);
+        world.add_system(CoreSchedule::Main, check_memory_budgets);
+        world.add_system(CoreSchedule::Render, report_memory_churn);
+        world.add_system(CoreSchedule::Render, log_memory_stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(region: Region, bytes: usize) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+        usage.set(
+            region,
+            RegionUsage {
+                allocations: 1,
+                bytes,
+            },
+        );
+        usage
+    }
+
+    #[test]
+    fn thresholds_are_reported_in_both_directions() {
+        let thresholds = MemoryThresholds::default()
+            .with(Region::Graphics, 1024)
+            .with(Region::Audio, 10);
+
+        let crossed = crossed_thresholds(
+            &usage(Region::Graphics, 512),
+            &usage(Region::Graphics, 2048),
+            &thresholds,
+        );
+        assert_eq!(
+            crossed,
+            [MemoryThresholdCrossed {
+                region: Region::Graphics,
+                threshold: 1024,
+                bytes: 2048,
+                direction: CrossingDirection::Above,
+            }]
+        );
+
+        let crossed = crossed_thresholds(
+            &usage(Region::Graphics, 2048),
+            &usage(Region::Graphics, 0),
+            &thresholds,
+        );
+        assert_eq!(crossed[0].direction, CrossingDirection::Below);
+
+        assert!(crossed_thresholds(
+            &usage(Region::Audio, 0),
+            &usage(Region::Audio, 5),
+            &thresholds
+        )
+        .is_empty());
+    }
+}
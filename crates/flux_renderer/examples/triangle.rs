@@ -0,0 +1,184 @@
+//! End to end smoke test of the render stack: renders a triangle into a hidden window, reads a
+//! frame back and checks that exactly the pixels of the triangle were drawn.
+//!
+//! There is no camera, so the view and projection are the identity and the triangle is placed
+//! in clip space by its model matrix alone.
+//!
+//! Run it with `cargo run -p flux_renderer --example triangle`, it exits with a failure status if
+//! the renderer could not start or the captured frame does not match the expected footprint.
+//! Pass `--headless` to render offscreen without a window, e.g. in CI.
+
+use ash::vk;
+use flux_ecs::app::{App, AppExit};
+use flux_ecs::commands::Commands;
+use flux_ecs::logging::{self, LogSettings};
+use flux_ecs::resource::Res;
+use flux_ecs::schedule::CoreSchedule;
+use flux_renderer::{
+    CapturedFrame, FrameCapture, GraphicsSettings, Headless, Mesh, MeshBuilder, MeshVertex,
+    RendererPlugin, RendererUnavailable, WindowDescriptor,
+};
+use flux_transform::TransformPlugin;
+use flux_transform::transform::Transform;
+use log::{LevelFilter, error, info};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+/// Frames rendered before the capture, gives the swapchain time to settle.
+const WARM_UP_FRAMES: u32 = 10;
+/// Frames after which the test fails if no frame was captured.
+const MAX_FRAMES: u32 = 120;
+/// The clip space corners of the triangle mesh, counter-clockwise on screen.
+const CORNERS: [[f32; 2]; 3] = [[-0.5, -0.5], [0.0, 0.5], [0.5, -0.5]];
+/// The clip space translation of the model matrix.
+const OFFSET: [f32; 2] = [0.25, 0.0];
+/// Pixels whose center is closer to an edge than this are not checked.
+const EDGE_TOLERANCE: f32 = 1.0;
+
+type Outcome = Arc<Mutex<Option<Result<(), String>>>>;
+
+fn main() -> ExitCode {
+    let log_settings = LogSettings::default();
+    let logger = pretty_env_logger::formatted_builder()
+        .filter_level(LevelFilter::Info)
+        .build();
+    logging::init(logger, &log_settings).expect("Failed to initialize the logger");
+
+    let outcome = Outcome::default();
+
+    let mut app = App::new();
+    app.world_mut().add_resource(log_settings);
+    if std::env::args().any(|arg| arg == "--headless") {
+        app.world_mut().add_resource(Headless {
+            width: 256,
+            height: 256,
+        });
+    } else {
+        app.world_mut().add_resource(WindowDescriptor {
+            title: Some("Flux triangle smoke test".to_string()),
+            width: 256,
+            height: 256,
+            resizable: false,
+            visible: false,
+        });
+    }
+    app.world_mut().add_resource(GraphicsSettings {
+        swapchain_usage: vk::ImageUsageFlags::TRANSFER_SRC,
+        ..GraphicsSettings::default()
+    });
+    app.world_mut().spawn((
+        triangle(),
+        Transform::from_translation(OFFSET[0], OFFSET[1], 0.0),
+    ));
+    app.world_mut()
+        .add_system(CoreSchedule::Main, check_frames(outcome.clone()));
+
+    let init_outcome = outcome.clone();
+    app.add_plugin(TransformPlugin);
+    app.add_plugin(RendererPlugin)
+        .on_init_error(move |world, init_error| {
+            let reason = match world.get_resource::<RendererUnavailable>() {
+                Some(unavailable) => unavailable.reason.clone(),
+                None => init_error.first().to_string(),
+            };
+            *init_outcome.lock().unwrap() = Some(Err(format!("initialization failed: {reason}")));
+        });
+
+    app.run();
+
+    match outcome.lock().unwrap().take() {
+        Some(Ok(())) => {
+            info!("The triangle was rendered");
+            ExitCode::SUCCESS
+        }
+        Some(Err(reason)) => {
+            error!("Smoke test failed: {reason}");
+            ExitCode::FAILURE
+        }
+        None => {
+            error!("Smoke test failed: the app exited before a frame was captured");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Requests a capture after the warm up frames and exits once the captured frame was checked.
+fn check_frames(outcome: Outcome) -> impl FnMut(Res<FrameCapture>, Commands) {
+    let mut frames = 0;
+    move |capture: Res<FrameCapture>, mut commands: Commands| {
+        frames += 1;
+
+        let result = match capture.take() {
+            Some(frame) => check_footprint(&frame),
+            None if frames > MAX_FRAMES => Err(format!("no frame captured after {frames} frames")),
+            None => {
+                if frames == WARM_UP_FRAMES {
+                    capture.request();
+                }
+                return;
+            }
+        };
+
+        *outcome.lock().unwrap() = Some(result);
+        commands.send_event(AppExit);
+    }
+}
+
+/// Compares the drawn pixels with the pixels inside the translated triangle.
+fn check_footprint(frame: &CapturedFrame) -> Result<(), String> {
+    let (width, height) = (frame.width as f32, frame.height as f32);
+    // Clip space y points down like the framebuffer
+    let corners = CORNERS.map(|[x, y]| {
+        [
+            (x + OFFSET[0] + 1.0) / 2.0 * width,
+            (y + OFFSET[1] + 1.0) / 2.0 * height,
+        ]
+    });
+    let mismatched = frame.mismatched_pixels(|x, y| {
+        let distance = distance_inside(corners, [x as f32 + 0.5, y as f32 + 0.5]);
+        (distance.abs() >= EDGE_TOLERANCE).then_some(distance > 0.0)
+    });
+    let drawn = frame.mismatched_pixels(|_, _| Some(false)).len();
+
+    info!("The captured frame has {drawn} drawn pixels");
+    match mismatched.first() {
+        None if drawn > 0 => Ok(()),
+        None => Err("the captured frame is black".to_string()),
+        Some((x, y)) => Err(format!(
+            "{} pixels do not match the triangle, the first is at ({x}, {y})",
+            mismatched.len()
+        )),
+    }
+}
+
+/// The distance of `point` to the closest edge of the triangle, negative outside of it.
+fn distance_inside(corners: [[f32; 2]; 3], point: [f32; 2]) -> f32 {
+    let cross = |a: [f32; 2], b: [f32; 2], c: [f32; 2]| {
+        (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+    };
+    let winding = cross(corners[0], corners[1], corners[2]).signum();
+    (0..3)
+        .map(|i| {
+            let (a, b) = (corners[i], corners[(i + 1) % 3]);
+            let length = (b[0] - a[0]).hypot(b[1] - a[1]);
+            winding * cross(a, b, point) / length
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+fn triangle() -> Mesh {
+    let mut builder = MeshBuilder::new(MeshVertex::layout());
+    let colors = [[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]];
+    let tex_coords = [[0.0, 1.0], [1.0, 0.0], [1.0, 1.0]];
+    let [a, b, c] = [0, 1, 2].map(|i| {
+        let [x, y] = CORNERS[i];
+        builder.vertex(MeshVertex {
+            position: [x, y, 0.0],
+            color: colors[i],
+            tex_coords: tex_coords[i],
+        })
+    });
+    // Back faces are culled, the corners are counter-clockwise in the y down framebuffer
+    builder.triangle(a, b, c);
+    builder.build()
+}
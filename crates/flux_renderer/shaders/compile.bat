@@ -1,3 +1,10 @@
 %VULKAN_SDK%/bin/glslc shader.vert -o vert.spv
 %VULKAN_SDK%/bin/glslc shader.frag -o frag.spv
+%VULKAN_SDK%/bin/glslc standard_uncolored.vert -o standard_uncolored_vert.spv
+%VULKAN_SDK%/bin/glslc terrain.vert -o terrain_vert.spv
+%VULKAN_SDK%/bin/glslc terrain.frag -o terrain_frag.spv
+%VULKAN_SDK%/bin/glslc particles.comp -o particles_comp.spv
+%VULKAN_SDK%/bin/glslc particles.vert -o particles_vert.spv
+%VULKAN_SDK%/bin/glslc particles.frag -o particles_frag.spv
+%VULKAN_SDK%/bin/glslc fullscreen.vert -o fullscreen_vert.spv
 pause
@@ -0,0 +1,11 @@
+#version 450
+
+// Covers the screen with a single triangle, see `FullscreenPass`. Draw it with 3 vertices and no
+// vertex buffer.
+
+layout(location = 0) out vec2 fragTexCoord;
+
+void main() {
+    fragTexCoord = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(fragTexCoord * 2.0 - 1.0, 0.0, 1.0);
+}
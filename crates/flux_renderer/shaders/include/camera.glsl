@@ -0,0 +1,17 @@
+#ifndef FLUX_CAMERA_GLSL
+#define FLUX_CAMERA_GLSL
+
+layout(set = 0, binding = 0) uniform UniformBufferObject {
+    mat4 view;
+    mat4 projection;
+} ubo;
+
+layout(push_constant) uniform Model {
+    mat4 model;
+} push;
+
+vec4 flux_to_clip_space(vec3 position) {
+    return ubo.projection * ubo.view * push.model * vec4(position, 1.0);
+}
+
+#endif
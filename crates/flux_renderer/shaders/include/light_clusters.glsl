@@ -0,0 +1,50 @@
+#ifndef FLUX_LIGHT_CLUSTERS_GLSL
+#define FLUX_LIGHT_CLUSTERS_GLSL
+
+// The point lights assigned to the clusters of the view frustum, see `LightClusterBuffers`
+
+struct FluxPointLight {
+    vec4 position_radius;
+    vec4 color;
+};
+
+layout(std430, set = 0, binding = 2) readonly buffer ClusterGrid {
+    // The tiles in x and y, the depth slices and the number of lights
+    uvec4 size;
+    // The view space depths the slices start and end at and the viewport size in pixels
+    vec4 depth_viewport;
+    // The offset and count of the light indices of every cluster
+    uvec2 cells[];
+} flux_clusters;
+
+layout(std430, set = 0, binding = 3) readonly buffer ClusterLightIndices {
+    uint indices[];
+} flux_cluster_light_indices;
+
+layout(std430, set = 0, binding = 4) readonly buffer PointLights {
+    FluxPointLight lights[];
+} flux_point_lights;
+
+// The offset and count of the light indices of the cluster containing the fragment, the count is
+// zero outside of the clustered depth range
+uvec2 flux_cluster_lights(vec2 frag_coord, float view_depth) {
+    uvec3 size = flux_clusters.size.xyz;
+    vec4 depth_viewport = flux_clusters.depth_viewport;
+    if (size.z == 0 || view_depth < depth_viewport.x || view_depth >= depth_viewport.y) {
+        return uvec2(0);
+    }
+
+    // Tile rows are counted from the bottom of the view, framebuffer rows from the top
+    vec2 uv = vec2(frag_coord.x / depth_viewport.z, 1.0 - frag_coord.y / depth_viewport.w);
+    uvec2 tile = min(uvec2(uv * vec2(size.xy)), size.xy - 1);
+    // The slices are distributed exponentially between the near and far depth
+    float depth = log(view_depth / depth_viewport.x) / log(depth_viewport.y / depth_viewport.x);
+    uint slice = min(uint(depth * float(size.z)), size.z - 1);
+    return flux_clusters.cells[(slice * size.y + tile.y) * size.x + tile.x];
+}
+
+FluxPointLight flux_cluster_light(uvec2 cluster, uint index) {
+    return flux_point_lights.lights[flux_cluster_light_indices.indices[cluster.x + index]];
+}
+
+#endif
@@ -0,0 +1,20 @@
+#ifndef FLUX_LIGHTING_GLSL
+#define FLUX_LIGHTING_GLSL
+
+float flux_lambert(vec3 normal, vec3 light_direction) {
+    return max(dot(normalize(normal), normalize(-light_direction)), 0.0);
+}
+
+vec3 flux_apply_light(vec3 albedo, vec3 normal, vec3 light_direction, vec3 light_color, vec3 ambient) {
+    return albedo * (ambient + light_color * flux_lambert(normal, light_direction));
+}
+
+// A point light at `light_position` fading out quadratically up to its radius
+vec3 flux_point_light(vec3 albedo, vec3 normal, vec3 position, vec3 light_position, float radius,
+                      vec3 light_color) {
+    vec3 offset = position - light_position;
+    float falloff = max(1.0 - length(offset) / radius, 0.0);
+    return albedo * light_color * flux_lambert(normal, offset) * falloff * falloff;
+}
+
+#endif
@@ -0,0 +1,43 @@
+#version 450
+
+// Draws the particles of one emitter as camera facing quads, one instance per alive particle.
+
+layout(set = 0, binding = 0) uniform UniformBufferObject {
+    mat4 view;
+    mat4 projection;
+} ubo;
+
+struct Particle {
+    vec4 position_age;
+    vec4 velocity_lifetime;
+};
+
+// The particles simulated this frame, bound through the descriptor set of the simulation
+layout(std430, set = 1, binding = 2) readonly buffer Particles { Particle particles[]; };
+
+layout(push_constant) uniform Appearance {
+    vec4 color_start;
+    vec4 color_end;
+    float size;
+} appearance;
+
+layout(location = 0) out vec2 fragCorner;
+layout(location = 1) out vec4 fragColor;
+
+const vec2 CORNERS[6] = vec2[](
+    vec2(-0.5, -0.5), vec2(0.5, 0.5), vec2(0.5, -0.5),
+    vec2(-0.5, -0.5), vec2(-0.5, 0.5), vec2(0.5, 0.5)
+);
+
+void main() {
+    Particle particle = particles[gl_InstanceIndex];
+    vec2 corner = CORNERS[gl_VertexIndex];
+
+    vec4 view_position = ubo.view * vec4(particle.position_age.xyz, 1.0);
+    view_position.xy += corner * appearance.size;
+    gl_Position = ubo.projection * view_position;
+
+    float life = clamp(particle.position_age.w / particle.velocity_lifetime.w, 0.0, 1.0);
+    fragCorner = corner;
+    fragColor = mix(appearance.color_start, appearance.color_end, life);
+}
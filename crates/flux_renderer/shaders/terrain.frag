@@ -0,0 +1,29 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+#include "include/lighting.glsl"
+#include "include/light_clusters.glsl"
+
+layout(location = 0) in vec3 fragNormal;
+layout(location = 1) in vec3 fragPosition;
+layout(location = 2) in float fragViewDepth;
+
+layout(location = 0) out vec4 outColor;
+
+const vec3 ALBEDO = vec3(0.35, 0.5, 0.25);
+const vec3 SUN_DIRECTION = vec3(-0.4, -1.0, -0.3);
+const vec3 SUN_COLOR = vec3(1.0, 0.95, 0.85);
+const vec3 AMBIENT = vec3(0.2, 0.22, 0.25);
+
+void main() {
+    vec3 color = flux_apply_light(ALBEDO, fragNormal, SUN_DIRECTION, SUN_COLOR, AMBIENT);
+
+    uvec2 cluster = flux_cluster_lights(gl_FragCoord.xy, fragViewDepth);
+    for (uint i = 0; i < cluster.y; i++) {
+        FluxPointLight light = flux_cluster_light(cluster, i);
+        color += flux_point_light(ALBEDO, fragNormal, fragPosition, light.position_radius.xyz,
+                                  light.position_radius.w, light.color.rgb);
+    }
+
+    outColor = vec4(color, 1.0);
+}
@@ -0,0 +1,22 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+// The chunks of the terrain, see `TerrainVertex`
+#include "include/camera.glsl"
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inNormal;
+
+layout(location = 0) out vec3 fragNormal;
+layout(location = 1) out vec3 fragPosition;
+layout(location = 2) out float fragViewDepth;
+
+void main() {
+    vec4 position = push.model * vec4(inPosition, 1.0);
+    gl_Position = ubo.projection * ubo.view * position;
+    // Chunks are only translated and uniformly scaled, which keeps the normals perpendicular
+    fragNormal = mat3(push.model) * inNormal;
+    fragPosition = position.xyz;
+    // The camera looks down the negative z axis
+    fragViewDepth = -(ubo.view * position).z;
+}
@@ -0,0 +1,387 @@
+use crate::device::{Device, PhysicalDevice};
+use crate::instance::VulkanInstance;
+use crate::log_targets;
+use ash::vk;
+use flux_ecs::commands::Commands;
+use flux_ecs::resource::{Res, Resource};
+use log::{debug, warn};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// The size of the memory blocks allocations are carved from, allocations larger than half a
+/// block get a dedicated one.
+pub const DEFAULT_BLOCK_SIZE: vk::DeviceSize = 64 * 1024 * 1024;
+
+/// A range of device memory handed out by the [`GpuAllocator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allocation {
+    memory: vk::DeviceMemory,
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+    block: u64,
+    mapped: *mut u8,
+}
+
+// The mapped pointer is only written through by the owner of the allocation
+unsafe impl Send for Allocation {}
+unsafe impl Sync for Allocation {}
+
+impl Allocation {
+    pub fn memory(&self) -> vk::DeviceMemory {
+        self.memory
+    }
+
+    pub fn offset(&self) -> vk::DeviceSize {
+        self.offset
+    }
+
+    pub fn size(&self) -> vk::DeviceSize {
+        self.size
+    }
+
+    /// The host address of the allocation, `None` unless it was allocated host visible.
+    pub fn mapped_ptr(&self) -> Option<*mut u8> {
+        (!self.mapped.is_null()).then_some(self.mapped)
+    }
+}
+
+/// The free ranges of a memory block as `(offset, size)`, sorted by offset.
+#[derive(Debug)]
+struct FreeList {
+    size: vk::DeviceSize,
+    free: Vec<(vk::DeviceSize, vk::DeviceSize)>,
+}
+
+impl FreeList {
+    fn new(size: vk::DeviceSize) -> Self {
+        Self {
+            size,
+            free: vec![(0, size)],
+        }
+    }
+
+    /// Takes the first range that fits, returns its aligned offset.
+    fn allocate(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> Option<vk::DeviceSize> {
+        let (index, offset) = self
+            .free
+            .iter()
+            .enumerate()
+            .find_map(|(index, &(start, len))| {
+                let offset = start.next_multiple_of(alignment.max(1));
+                (offset + size <= start + len).then_some((index, offset))
+            })?;
+
+        let (start, len) = self.free.remove(index);
+        let end = offset + size;
+        if end < start + len {
+            self.free.insert(index, (end, start + len - end));
+        }
+        if offset > start {
+            self.free.insert(index, (start, offset - start));
+        }
+        Some(offset)
+    }
+
+    /// Returns the range to the list, merging it with its free neighbours.
+    fn free(&mut self, offset: vk::DeviceSize, size: vk::DeviceSize) {
+        let index = self.free.partition_point(|&(start, _)| start < offset);
+        self.free.insert(index, (offset, size));
+
+        if index + 1 < self.free.len() && offset + size == self.free[index + 1].0 {
+            self.free[index].1 += self.free.remove(index + 1).1;
+        }
+        if index > 0 && self.free[index - 1].0 + self.free[index - 1].1 == offset {
+            self.free[index - 1].1 += self.free.remove(index).1;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.free == [(0, self.size)]
+    }
+}
+
+struct MemoryBlock {
+    id: u64,
+    memory: vk::DeviceMemory,
+    memory_type: u32,
+    mapped: *mut u8,
+    free: FreeList,
+    dedicated: bool,
+}
+
+#[derive(Default)]
+struct AllocatorState {
+    blocks: Vec<MemoryBlock>,
+    next_block: u64,
+    allocations: usize,
+}
+
+// The mapped pointers of the blocks are only handed out through allocations
+unsafe impl Send for AllocatorState {}
+
+/// Sub-allocates buffers and images from large memory blocks, so the number of
+/// `vkAllocateMemory` calls stays far below `maxMemoryAllocationCount`.
+///
+/// Host visible blocks stay mapped for their whole lifetime, see [`Allocation::mapped_ptr`].
+pub struct GpuAllocator {
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    /// Linear and optimal resources may share a block, every allocation is aligned to
+    /// `bufferImageGranularity` to keep them on separate pages.
+    granularity: vk::DeviceSize,
+    block_size: vk::DeviceSize,
+    state: Mutex<AllocatorState>,
+}
+
+impl Resource for GpuAllocator {}
+
+impl GpuAllocator {
+    pub fn new(instance: &VulkanInstance, physical_device: &PhysicalDevice) -> Self {
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(**physical_device) };
+        Self {
+            memory_properties,
+            granularity: physical_device.properties.limits.buffer_image_granularity,
+            block_size: DEFAULT_BLOCK_SIZE,
+            state: Mutex::default(),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, AllocatorState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The number of device memory objects currently allocated.
+    pub fn block_count(&self) -> usize {
+        self.state().blocks.len()
+    }
+
+    /// The number of live allocations.
+    pub fn allocation_count(&self) -> usize {
+        self.state().allocations
+    }
+
+    pub fn allocate(
+        &self,
+        device: &Device,
+        requirements: vk::MemoryRequirements,
+        properties: vk::MemoryPropertyFlags,
+    ) -> Result<Allocation, vk::Result> {
+        let Some(memory_type) = find_memory_type(
+            &self.memory_properties,
+            requirements.memory_type_bits,
+            properties,
+        ) else {
+            warn!(
+                target: log_targets::RESOURCES,
+                "No memory type with {properties:?} in {:#b}",
+                requirements.memory_type_bits
+            );
+            return Err(vk::Result::ERROR_FEATURE_NOT_PRESENT);
+        };
+        let size = requirements.size;
+        let alignment = requirements.alignment.max(self.granularity);
+
+        let mut state = self.state();
+        let dedicated = size > self.block_size / 2;
+        if !dedicated {
+            let found = state.blocks.iter_mut().find_map(|block| {
+                if block.dedicated || block.memory_type != memory_type {
+                    return None;
+                }
+                let offset = block.free.allocate(size, alignment)?;
+                Some(block_allocation(block, offset, size))
+            });
+            if let Some(allocation) = found {
+                state.allocations += 1;
+                return Ok(allocation);
+            }
+        }
+
+        let block_size = if dedicated { size } else { self.block_size };
+        let info = vk::MemoryAllocateInfo::default()
+            .allocation_size(block_size)
+            .memory_type_index(memory_type);
+        let memory = unsafe { device.allocate_memory(&info, None)? };
+
+        let host_visible = self.memory_properties.memory_types[memory_type as usize]
+            .property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE);
+        let mapped = if host_visible {
+            let mapped = unsafe {
+                device.map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())
+            };
+            match mapped {
+                Ok(mapped) => mapped.cast(),
+                Err(err) => {
+                    unsafe { device.free_memory(memory, None) };
+                    return Err(err);
+                }
+            }
+        } else {
+            std::ptr::null_mut()
+        };
+
+        debug!(
+            target: log_targets::RESOURCES,
+            "Allocating {}memory block of {block_size} bytes with memory type {memory_type}",
+            if dedicated { "dedicated " } else { "" }
+        );
+
+        let mut free = FreeList::new(block_size);
+        let offset = free
+            .allocate(size, alignment)
+            .expect("A new block fits the allocation");
+        let block = MemoryBlock {
+            id: state.next_block,
+            memory,
+            memory_type,
+            mapped,
+            free,
+            dedicated,
+        };
+        state.next_block += 1;
+        state.allocations += 1;
+
+        let allocation = block_allocation(&block, offset, size);
+        state.blocks.push(block);
+        Ok(allocation)
+    }
+
+    /// Returns the memory of the allocation to its block. Dedicated blocks are freed, empty
+    /// blocks too unless they are the last block of their memory type.
+    ///
+    /// The resource bound to the allocation must be destroyed first.
+    pub fn free(&self, device: &Device, allocation: Allocation) {
+        let mut state = self.state();
+        let Some(index) = state
+            .blocks
+            .iter()
+            .position(|block| block.id == allocation.block)
+        else {
+            warn!(target: log_targets::RESOURCES, "Freeing unknown allocation {allocation:?}");
+            return;
+        };
+        state.allocations -= 1;
+
+        let block = &mut state.blocks[index];
+        block.free.free(allocation.offset, allocation.size);
+        let (memory_type, empty, dedicated) =
+            (block.memory_type, block.free.is_empty(), block.dedicated);
+        let shared_blocks = state
+            .blocks
+            .iter()
+            .filter(|block| !block.dedicated && block.memory_type == memory_type)
+            .count();
+        if dedicated || (empty && shared_blocks > 1) {
+            let block = state.blocks.swap_remove(index);
+            unsafe { device.free_memory(block.memory, None) };
+        }
+    }
+
+    /// Frees all memory blocks, called once every resource was destroyed.
+    pub fn destroy(&self, device: &Device) {
+        let mut state = self.state();
+        if state.allocations > 0 {
+            warn!(
+                target: log_targets::RESOURCES,
+                "Destroying the GPU allocator with {} live allocations",
+                state.allocations
+            );
+        }
+        for block in state.blocks.drain(..) {
+            unsafe { device.free_memory(block.memory, None) };
+        }
+        state.allocations = 0;
+    }
+}
+
+fn block_allocation(
+    block: &MemoryBlock,
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+) -> Allocation {
+    let mapped = if block.mapped.is_null() {
+        block.mapped
+    } else {
+        unsafe { block.mapped.add(offset as usize) }
+    };
+    Allocation {
+        memory: block.memory,
+        offset,
+        size,
+        block: block.id,
+        mapped,
+    }
+}
+
+/// The first memory type allowed by `type_bits` that has all `properties`.
+pub(crate) fn find_memory_type(
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    type_bits: u32,
+    properties: vk::MemoryPropertyFlags,
+) -> Option<u32> {
+    (0..memory_properties.memory_type_count).find(|&i| {
+        let suitable = type_bits & (1 << i) != 0;
+        let memory_type = memory_properties.memory_types[i as usize];
+        suitable && memory_type.property_flags.contains(properties)
+    })
+}
+
+pub fn create_gpu_allocator(
+    instance: Res<VulkanInstance>,
+    physical_device: Res<PhysicalDevice>,
+    mut commands: Commands,
+) {
+    debug!(target: log_targets::RESOURCES, "Creating GPU allocator");
+    commands.insert_resource(GpuAllocator::new(&instance, &physical_device));
+}
+
+pub fn destroy_gpu_allocator(
+    device: Res<Device>,
+    allocator: Option<Res<GpuAllocator>>,
+    mut commands: Commands,
+) {
+    let Some(allocator) = allocator else {
+        return;
+    };
+
+    debug!(target: log_targets::RESOURCES, "Destroying GPU allocator");
+    allocator.destroy(&device);
+    commands.remove_resource::<GpuAllocator>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_ranges_are_aligned_and_merged() {
+        let mut list = FreeList::new(1024);
+
+        assert_eq!(list.allocate(100, 256), Some(0));
+        assert_eq!(list.allocate(100, 256), Some(256));
+        assert_eq!(list.allocate(10, 1), Some(100));
+        assert_eq!(list.allocate(1024, 1), None);
+
+        list.free(256, 100);
+        list.free(0, 100);
+        assert!(!list.is_empty());
+        list.free(100, 10);
+        assert!(list.is_empty());
+        assert_eq!(list.allocate(1024, 1), Some(0));
+
+        let mut memory_properties = vk::PhysicalDeviceMemoryProperties {
+            memory_type_count: 2,
+            ..Default::default()
+        };
+        memory_properties.memory_types[0].property_flags = vk::MemoryPropertyFlags::DEVICE_LOCAL;
+        memory_properties.memory_types[1].property_flags =
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+        let host = vk::MemoryPropertyFlags::HOST_VISIBLE;
+        assert_eq!(find_memory_type(&memory_properties, 0b11, host), Some(1));
+        assert_eq!(find_memory_type(&memory_properties, 0b01, host), None);
+    }
+}
@@ -0,0 +1,258 @@
+use ash::vk;
+use flux_ecs::resource::Resource;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// A buffer or image whose accesses are declared to the [`BarrierValidator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GpuResource {
+    Buffer(vk::Buffer),
+    Image(vk::Image),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PendingWrite {
+    pass: &'static str,
+    stages: vk::PipelineStageFlags,
+    access: vk::AccessFlags,
+    /// The stages and accesses the barriers recorded since the write made it visible to.
+    visible_stages: vk::PipelineStageFlags,
+    visible_access: vk::AccessFlags,
+}
+
+#[derive(Debug)]
+struct ValidatorState {
+    pass: &'static str,
+    writes: HashMap<GpuResource, PendingWrite>,
+}
+
+impl Default for ValidatorState {
+    fn default() -> Self {
+        Self {
+            pass: "<no pass>",
+            writes: HashMap::new(),
+        }
+    }
+}
+
+/// Checks in debug builds that the barriers recorded between passes cover every read-after-write
+/// hazard of the buffers and images the passes declare, panicking with the writing and reading
+/// pass instead of leaving it to sporadic synchronization errors of the validation layers.
+///
+/// Passes declare their accesses in recording order, the checks reflect the command buffer
+/// order, not the GPU timeline. Does nothing in release builds.
+#[derive(Debug, Default)]
+pub struct BarrierValidator {
+    state: Mutex<ValidatorState>,
+}
+
+impl Resource for BarrierValidator {}
+
+impl BarrierValidator {
+    fn state(&self) -> MutexGuard<'_, ValidatorState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Attributes the following accesses to the pass `name`.
+    pub fn begin_pass(&self, name: &'static str) {
+        if cfg!(debug_assertions) {
+            self.state().pass = name;
+        }
+    }
+
+    pub fn write(
+        &self,
+        resource: GpuResource,
+        stages: vk::PipelineStageFlags,
+        access: vk::AccessFlags,
+    ) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        let mut state = self.state();
+        let pass = state.pass;
+        state.writes.insert(
+            resource,
+            PendingWrite {
+                pass,
+                stages,
+                access,
+                visible_stages: vk::PipelineStageFlags::empty(),
+                visible_access: vk::AccessFlags::empty(),
+            },
+        );
+    }
+
+    /// # Panics
+    /// Panics if the last write of the resource was not made visible to the read by a barrier.
+    pub fn read(
+        &self,
+        resource: GpuResource,
+        stages: vk::PipelineStageFlags,
+        access: vk::AccessFlags,
+    ) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        let state = self.state();
+        let Some(write) = state.writes.get(&resource) else {
+            return;
+        };
+        if !covers_stages(write.visible_stages, stages)
+            || !covers_access(write.visible_access, access, vk::AccessFlags::MEMORY_READ)
+        {
+            panic!(
+                "Read-after-write hazard on {resource:?}: pass '{}' reads it in {stages:?} \
+                 ({access:?}) after pass '{}' wrote it in {:?} ({:?}) without a barrier covering \
+                 the read",
+                state.pass, write.pass, write.stages, write.access
+            );
+        }
+    }
+
+    /// Records a barrier on the resource, it covers the last write if its source scope includes
+    /// the stages and accesses of the write.
+    pub fn barrier(
+        &self,
+        resource: GpuResource,
+        (src_stages, src_access): (vk::PipelineStageFlags, vk::AccessFlags),
+        (dst_stages, dst_access): (vk::PipelineStageFlags, vk::AccessFlags),
+    ) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        let mut state = self.state();
+        let Some(write) = state.writes.get_mut(&resource) else {
+            return;
+        };
+        if covers_stages(src_stages, write.stages)
+            && covers_access(src_access, write.access, vk::AccessFlags::MEMORY_WRITE)
+        {
+            write.visible_stages |= dst_stages;
+            write.visible_access |= dst_access;
+        }
+    }
+
+    /// Records the buffer barriers of a `vkCmdPipelineBarrier`.
+    pub fn buffer_barriers(
+        &self,
+        src_stages: vk::PipelineStageFlags,
+        dst_stages: vk::PipelineStageFlags,
+        barriers: &[vk::BufferMemoryBarrier],
+    ) {
+        for barrier in barriers {
+            self.barrier(
+                GpuResource::Buffer(barrier.buffer),
+                (src_stages, barrier.src_access_mask),
+                (dst_stages, barrier.dst_access_mask),
+            );
+        }
+    }
+
+    /// Records the image barriers of a `vkCmdPipelineBarrier`, e.g. the layout transitions of
+    /// the [`ImageLayoutTracker`](crate::ImageLayoutTracker).
+    pub fn image_barriers(
+        &self,
+        src_stages: vk::PipelineStageFlags,
+        dst_stages: vk::PipelineStageFlags,
+        barriers: &[vk::ImageMemoryBarrier],
+    ) {
+        for barrier in barriers {
+            self.barrier(
+                GpuResource::Image(barrier.image),
+                (src_stages, barrier.src_access_mask),
+                (dst_stages, barrier.dst_access_mask),
+            );
+        }
+    }
+
+    /// Stops tracking the resource, call it before the resource is destroyed since handles are
+    /// reused.
+    pub fn forget(&self, resource: GpuResource) {
+        if cfg!(debug_assertions) {
+            self.state().writes.remove(&resource);
+        }
+    }
+}
+
+fn covers_stages(scope: vk::PipelineStageFlags, stages: vk::PipelineStageFlags) -> bool {
+    scope.contains(vk::PipelineStageFlags::ALL_COMMANDS) || scope.contains(stages)
+}
+
+/// Whether the access scope includes `access`, `all` covers every read or write access.
+fn covers_access(scope: vk::AccessFlags, access: vk::AccessFlags, all: vk::AccessFlags) -> bool {
+    scope.contains(all) || scope.contains(access)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    #[test]
+    #[cfg_attr(
+        debug_assertions,
+        should_panic(expected = "pass 'draw' reads it in DRAW_INDIRECT")
+    )]
+    fn reads_need_a_barrier_covering_the_write() {
+        let draw = GpuResource::Buffer(vk::Buffer::from_raw(1));
+        let validator = BarrierValidator::default();
+
+        validator.begin_pass("reset");
+        validator.write(
+            draw,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::AccessFlags::TRANSFER_WRITE,
+        );
+        validator.barrier(
+            draw,
+            (
+                vk::PipelineStageFlags::TRANSFER,
+                vk::AccessFlags::TRANSFER_WRITE,
+            ),
+            (
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+            ),
+        );
+
+        validator.begin_pass("simulate");
+        validator.read(
+            draw,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::AccessFlags::SHADER_READ,
+        );
+        validator.write(
+            draw,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::AccessFlags::SHADER_WRITE,
+        );
+
+        // The barrier after the simulation only covers the vertex shader
+        validator.barrier(
+            draw,
+            (
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::AccessFlags::SHADER_WRITE,
+            ),
+            (
+                vk::PipelineStageFlags::VERTEX_SHADER,
+                vk::AccessFlags::SHADER_READ,
+            ),
+        );
+        validator.begin_pass("draw");
+        validator.read(
+            draw,
+            vk::PipelineStageFlags::VERTEX_SHADER,
+            vk::AccessFlags::SHADER_READ,
+        );
+        validator.read(
+            draw,
+            vk::PipelineStageFlags::DRAW_INDIRECT,
+            vk::AccessFlags::INDIRECT_COMMAND_READ,
+        );
+    }
+}
@@ -1,65 +1,48 @@
+use crate::allocator::{Allocation, GpuAllocator};
+use crate::camera::Camera;
 use crate::command_pool::CommandPools;
-use crate::device::{Device, PhysicalDevice};
-use crate::image::get_memory_type_index;
-use crate::instance::VulkanInstance;
+use crate::device::Device;
+use crate::stats::RenderStats;
 use crate::swapchain::Swapchain;
 use ash::vk;
+use cgmath::{SquareMatrix, Vector2};
 use flux_ecs::commands::Commands;
 use flux_ecs::resource::{Res, Resource};
+use crate::log_targets;
 use log::debug;
-use std::ptr::copy_nonoverlapping as memcpy;
 
-type Vec2 = cgmath::Vector2<f32>;
-type Vec3 = cgmath::Vector3<f32>;
 type Mat4 = cgmath::Matrix4<f32>;
 
-const VERTICES: [Vertex; 3] = [
-    Vertex {
-        pos: Vec3::new(-0.5, -0.5, 0.0),
-        color: Vec3::new(1.0, 0.0, 0.0),
-        tex_coords: Vec2::new(0.0, 1.0),
-    },
-    Vertex {
-        pos: Vec3::new(0.5, -0.5, 0.0),
-        color: Vec3::new(0.0, 1.0, 0.0),
-        tex_coords: Vec2::new(1.0, 1.0),
-    },
-    Vertex {
-        pos: Vec3::new(0.0, 0.5, 0.0),
-        color: Vec3::new(0.0, 0.0, 1.0),
-        tex_coords: Vec2::new(1.0, 0.0),
-    },
-];
-
-pub struct Vertex {
-    pos: Vec3,
-    color: Vec3,
-    tex_coords: Vec2,
-}
-
+/// The per frame uniforms of the main pipeline at binding `0`, the model matrix of each mesh is a
+/// push constant.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct UniformBufferObject {
-    pub model: Mat4,
     pub view: Mat4,
     pub projection: Mat4,
 }
 
-pub struct VertexBuffer {
-    pub buffer: vk::Buffer,
-    pub memory: vk::DeviceMemory,
-}
-
-impl Resource for VertexBuffer {}
-
-pub struct IndexBuffer {
-    pub buffer: vk::Buffer,
-    pub memory: vk::DeviceMemory,
+impl UniformBufferObject {
+    /// The view and projection of `camera` for a target of the given extent. Without a camera
+    /// both are the identity and mesh positions are in clip space.
+    pub fn from_camera(camera: Option<&Camera>, extent: vk::Extent2D) -> Self {
+        match camera {
+            Some(camera) => Self {
+                view: camera.view,
+                projection: camera
+                    .projection(Vector2::new(extent.width as f32, extent.height as f32)),
+            },
+            None => Self {
+                view: Mat4::identity(),
+                projection: Mat4::identity(),
+            },
+        }
+    }
 }
 
-impl Resource for IndexBuffer {}
-
 pub struct UniformBuffer {
     pub buffer: vk::Buffer,
-    pub memory: vk::DeviceMemory,
+    pub memory: Allocation,
 }
 
 pub struct UniformBuffers {
@@ -68,127 +51,38 @@ pub struct UniformBuffers {
 
 impl Resource for UniformBuffers {}
 
-pub fn create_vertex_buffer(
-    instance: Res<VulkanInstance>,
-    physical_device: Res<PhysicalDevice>,
-    device: Res<Device>,
-    command_pools: Res<CommandPools>,
-    mut commands: Commands,
-) -> Result<(), vk::Result> {
-    debug!("Creating vertex buffer");
-
-    let size = (size_of::<Vertex>() * VERTICES.len()) as u64;
-
-    let (staging_buffer, staging_buffer_memory) = create_buffer(
-        &instance,
-        &physical_device,
-        &device,
-        size,
-        vk::BufferUsageFlags::TRANSFER_SRC,
-        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-    )?;
-
-    let memory =
-        unsafe { device.map_memory(staging_buffer_memory, 0, size, vk::MemoryMapFlags::empty())? };
-
-    unsafe {
-        memcpy(
-            VERTICES.as_ptr() as *const u8,
-            memory.cast(),
-            VERTICES.len(),
-        )
-    }
-
-    unsafe { device.unmap_memory(staging_buffer_memory) };
-
-    let (vertex_buffer, vertex_buffer_memory) = create_buffer(
-        &instance,
-        &physical_device,
-        &device,
-        size,
-        vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::VERTEX_BUFFER,
-        vk::MemoryPropertyFlags::DEVICE_LOCAL,
-    )?;
-
-    copy_buffer(&device, &command_pools, staging_buffer, vertex_buffer, size)?;
-
-    unsafe {
-        device.destroy_buffer(staging_buffer, None);
-        device.free_memory(staging_buffer_memory, None);
-    }
-
-    let vertex_buffer_resource = VertexBuffer {
-        buffer: vertex_buffer,
-        memory: vertex_buffer_memory,
-    };
-
-    commands.insert_resource(vertex_buffer_resource);
-
-    Ok(())
-}
-
-pub fn create_index_buffer(
-    instance: Res<VulkanInstance>,
-    physical_device: Res<PhysicalDevice>,
-    device: Res<Device>,
-    command_pools: Res<CommandPools>,
-    mut commands: Commands,
-) -> Result<(), vk::Result> {
-    debug!("Creating index buffer");
-
-    let indices: [u32; 3] = [0, 1, 2];
-    let size = (size_of::<u32>() * indices.len()) as u64;
-
-    let (staging_buffer, staging_buffer_memory) = create_buffer(
-        &instance,
-        &physical_device,
-        &device,
-        size,
-        vk::BufferUsageFlags::TRANSFER_SRC,
-        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-    )?;
-
-    let memory =
-        unsafe { device.map_memory(staging_buffer_memory, 0, size, vk::MemoryMapFlags::empty())? };
-
-    unsafe {
-        memcpy(indices.as_ptr() as *const u8, memory.cast(), indices.len());
-    }
-
-    unsafe { device.unmap_memory(staging_buffer_memory) };
-
-    let (index_buffer, index_buffer_memory) = create_buffer(
-        &instance,
-        &physical_device,
-        &device,
-        size,
-        vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::INDEX_BUFFER,
-        vk::MemoryPropertyFlags::DEVICE_LOCAL,
-    )?;
-
-    copy_buffer(&device, &command_pools, staging_buffer, index_buffer, size)?;
-
-    unsafe {
-        device.destroy_buffer(staging_buffer, None);
-        device.free_memory(staging_buffer_memory, None);
+impl UniformBuffers {
+    /// Writes the uniforms read by the frames rendering to swapchain image `image`.
+    ///
+    /// # Safety
+    /// The uniform buffer of the image must not be used by a pending submission.
+    pub(crate) unsafe fn write(&self, image: usize, uniforms: &UniformBufferObject) {
+        let buffer = &self.buffers[image % self.buffers.len()];
+        let mapped = buffer
+            .memory
+            .mapped_ptr()
+            .expect("Host visible allocations are mapped");
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                (uniforms as *const UniformBufferObject).cast::<u8>(),
+                mapped,
+                size_of::<UniformBufferObject>(),
+            )
+        };
     }
-
-    commands.insert_resource(IndexBuffer {
-        buffer: index_buffer,
-        memory: index_buffer_memory,
-    });
-
-    Ok(())
 }
 
 pub fn create_uniform_buffer(
-    instance: Res<VulkanInstance>,
-    physical_device: Res<PhysicalDevice>,
     device: Res<Device>,
-    swapchain: Res<Swapchain>,
+    allocator: Res<GpuAllocator>,
+    swapchain: Option<Res<Swapchain>>,
     mut commands: Commands,
 ) -> Result<(), vk::Result> {
-    debug!("Creating uniform buffer");
+    let Some(swapchain) = swapchain else {
+        return Ok(());
+    };
+
+    debug!(target: log_targets::RESOURCES, "Creating uniform buffer");
 
     let mut buffers = UniformBuffers {
         buffers: Vec::with_capacity(swapchain.images.len()),
@@ -196,9 +90,8 @@ pub fn create_uniform_buffer(
 
     for _ in 0..swapchain.images.len() {
         let (uniform_buffer, uniform_buffer_memory) = create_buffer(
-            &instance,
-            &physical_device,
             &device,
+            &allocator,
             size_of::<UniformBufferObject>() as u64,
             vk::BufferUsageFlags::UNIFORM_BUFFER,
             vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
@@ -215,14 +108,35 @@ pub fn create_uniform_buffer(
     Ok(())
 }
 
-fn create_buffer(
-    instance: &VulkanInstance,
-    physical_device: &PhysicalDevice,
+pub fn destroy_buffers(
+    device: Res<Device>,
+    allocator: Res<GpuAllocator>,
+    uniform_buffers: Option<Res<UniformBuffers>>,
+    mut commands: Commands,
+) {
+    debug!(target: log_targets::RESOURCES, "Destroying buffers");
+
+    let buffers = uniform_buffers
+        .iter()
+        .flat_map(|buffers| buffers.buffers.iter().map(|b| (b.buffer, b.memory)));
+
+    for (buffer, memory) in buffers {
+        unsafe {
+            device.destroy_buffer(buffer, None);
+        }
+        allocator.free(&device, memory);
+    }
+
+    commands.remove_resource::<UniformBuffers>();
+}
+
+pub(crate) fn create_buffer(
     device: &Device,
+    allocator: &GpuAllocator,
     size: vk::DeviceSize,
     usage: vk::BufferUsageFlags,
     properties: vk::MemoryPropertyFlags,
-) -> Result<(vk::Buffer, vk::DeviceMemory), vk::Result> {
+) -> Result<(vk::Buffer, Allocation), vk::Result> {
     let buffer_info = vk::BufferCreateInfo::default()
         .size(size)
         .usage(usage)
@@ -231,24 +145,29 @@ fn create_buffer(
     let buffer = unsafe { device.create_buffer(&buffer_info, None)? };
     let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
 
-    let memory_info = vk::MemoryAllocateInfo::default()
-        .allocation_size(requirements.size)
-        .memory_type_index(
-            get_memory_type_index(instance, physical_device, properties, requirements).unwrap(),
-        );
-
-    let buffer_memory = unsafe { device.allocate_memory(&memory_info, None)? };
+    let allocation = match allocator.allocate(device, requirements, properties) {
+        Ok(allocation) => allocation,
+        Err(err) => {
+            unsafe { device.destroy_buffer(buffer, None) };
+            return Err(err);
+        }
+    };
 
-    unsafe {
-        device.bind_buffer_memory(buffer, buffer_memory, 0)?;
+    let bound =
+        unsafe { device.bind_buffer_memory(buffer, allocation.memory(), allocation.offset()) };
+    if let Err(err) = bound {
+        unsafe { device.destroy_buffer(buffer, None) };
+        allocator.free(device, allocation);
+        return Err(err);
     }
 
-    Ok((buffer, buffer_memory))
+    Ok((buffer, allocation))
 }
 
-fn copy_buffer(
+pub(crate) fn copy_buffer(
     device: &Device,
     command_pools: &CommandPools,
+    stats: &RenderStats,
     src_buffer: vk::Buffer,
     dst_buffer: vk::Buffer,
     size: vk::DeviceSize,
@@ -265,10 +184,12 @@ fn copy_buffer(
         command_buffer,
     )?;
 
+    stats.record_buffer_upload(size);
+
     Ok(())
 }
 
-unsafe fn begin_single_time_commands(
+pub(crate) unsafe fn begin_single_time_commands(
     device: &Device,
     command_pool: vk::CommandPool,
 ) -> Result<vk::CommandBuffer, vk::Result> {
@@ -287,7 +208,7 @@ unsafe fn begin_single_time_commands(
     Ok(command_buffer)
 }
 
-fn end_single_time_commands(
+pub(crate) fn end_single_time_commands(
     device: &Device,
     queue: vk::Queue,
     command_pool: vk::CommandPool,
@@ -305,3 +226,33 @@ fn end_single_time_commands(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniforms_follow_the_camera() {
+        // Two column major mat4 as in the std140 block of the shaders
+        assert_eq!(size_of::<UniformBufferObject>(), 128);
+
+        let extent = vk::Extent2D {
+            width: 200,
+            height: 100,
+        };
+        let uniforms = UniformBufferObject::from_camera(None, extent);
+        assert_eq!(uniforms.view, Mat4::identity());
+        assert_eq!(uniforms.projection, Mat4::identity());
+
+        let camera = Camera {
+            view: Mat4::from_translation(cgmath::Vector3::new(0.0, 0.0, -5.0)),
+            ..Default::default()
+        };
+        let uniforms = UniformBufferObject::from_camera(Some(&camera), extent);
+        assert_eq!(uniforms.view, camera.view);
+        assert_eq!(
+            uniforms.projection,
+            camera.projection(Vector2::new(200.0, 100.0))
+        );
+    }
+}
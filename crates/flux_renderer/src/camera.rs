@@ -0,0 +1,325 @@
+use ash::vk;
+use cgmath::{InnerSpace, Matrix, Matrix4, Point3, SquareMatrix, Vector2, Vector3, Vector4};
+use flux_ecs::component::Component;
+
+/// A perspective camera, the single source of the view and projection used for rendering and
+/// for mapping between the viewport and the world, e.g. for picking, UI anchoring and debug
+/// labels.
+///
+/// Culling and ray casts should go through [`Camera::frustum`] and [`Camera::viewport_to_world`]
+/// so that they agree with what is rendered.
+///
+/// The projection follows the Vulkan conventions: clip space y points down and depth runs from 0
+/// to 1, see [`DepthRange`]. Viewport positions are in pixels from the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// The world to view space transform, the camera looks down the negative z axis.
+    pub view: Matrix4<f32>,
+    /// The vertical field of view in radians.
+    pub fov_y: f32,
+    pub near: f32,
+    /// [`f32::INFINITY`] for an infinite far plane, which is usually paired with
+    /// [`DepthRange::Reversed`] to keep the depth precision in the distance.
+    pub far: f32,
+    pub depth_range: DepthRange,
+}
+
+impl Component for Camera {}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            view: Matrix4::identity(),
+            fov_y: std::f32::consts::FRAC_PI_3,
+            near: 0.1,
+            far: 1000.0,
+            depth_range: DepthRange::default(),
+        }
+    }
+}
+
+/// How view space depth maps to the depth buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DepthRange {
+    /// The near plane maps to 0 and the far plane to 1.
+    #[default]
+    Standard,
+    /// The near plane maps to 1 and the far plane to 0, which spreads the floating point
+    /// precision of the depth buffer evenly over the distance.
+    Reversed,
+}
+
+impl DepthRange {
+    /// The depth test that passes for the fragment closer to the camera.
+    pub fn compare_op(self) -> vk::CompareOp {
+        match self {
+            DepthRange::Standard => vk::CompareOp::LESS,
+            DepthRange::Reversed => vk::CompareOp::GREATER,
+        }
+    }
+
+    /// The depth the depth buffer is cleared to, the depth of the far plane.
+    pub fn clear_depth(self) -> f32 {
+        match self {
+            DepthRange::Standard => 1.0,
+            DepthRange::Reversed => 0.0,
+        }
+    }
+
+    /// The depth of the near plane.
+    pub fn near_depth(self) -> f32 {
+        1.0 - self.clear_depth()
+    }
+}
+
+/// A half line, e.g. the world space ray under the cursor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    /// Normalized.
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+}
+
+impl Camera {
+    /// The view to clip space transform for a viewport of the given size in pixels.
+    pub fn projection(&self, viewport_size: Vector2<f32>) -> Matrix4<f32> {
+        let focal = 1.0 / (self.fov_y / 2.0).tan();
+        let aspect_ratio = viewport_size.x / viewport_size.y;
+        // Clip space depth is `scale * z + offset` for the view space depth `z`, divided by `-z`
+        let (near, far) = (self.near, self.far);
+        let (scale, offset) = match (self.depth_range, far.is_finite()) {
+            (DepthRange::Standard, true) => (far / (near - far), near * far / (near - far)),
+            (DepthRange::Standard, false) => (-1.0, -near),
+            (DepthRange::Reversed, true) => (near / (far - near), near * far / (far - near)),
+            (DepthRange::Reversed, false) => (0.0, near),
+        };
+        #[rustfmt::skip]
+        let projection = Matrix4::new(
+            focal / aspect_ratio, 0.0, 0.0, 0.0,
+            0.0, -focal, 0.0, 0.0,
+            0.0, 0.0, scale, -1.0,
+            0.0, 0.0, offset, 0.0,
+        );
+        projection
+    }
+
+    /// The world to clip space transform for a viewport of the given size in pixels.
+    pub fn view_projection(&self, viewport_size: Vector2<f32>) -> Matrix4<f32> {
+        self.projection(viewport_size) * self.view
+    }
+
+    /// The world space position of the camera.
+    pub fn position(&self) -> Option<Point3<f32>> {
+        let world = self.view.invert()?;
+        Some(Point3::new(world.w.x, world.w.y, world.w.z))
+    }
+
+    /// The ray from the near plane through the viewport position, `None` for an empty viewport or
+    /// a degenerate view.
+    pub fn viewport_to_world(
+        &self,
+        viewport_position: Vector2<f32>,
+        viewport_size: Vector2<f32>,
+    ) -> Option<Ray> {
+        if viewport_size.x <= 0.0 || viewport_size.y <= 0.0 {
+            return None;
+        }
+        let clip_to_world = self.view_projection(viewport_size).invert()?;
+        let ndc_x = viewport_position.x / viewport_size.x * 2.0 - 1.0;
+        let ndc_y = viewport_position.y / viewport_size.y * 2.0 - 1.0;
+        let unproject = |depth| {
+            let world = clip_to_world * Vector4::new(ndc_x, ndc_y, depth, 1.0);
+            Point3::from_homogeneous(world)
+        };
+
+        // The far plane may be at infinity, the halfway depth is always a finite point
+        let near = unproject(self.depth_range.near_depth());
+        let halfway = unproject(0.5);
+        Some(Ray {
+            origin: near,
+            direction: (halfway - near).normalize(),
+        })
+    }
+
+    /// The viewport position of the world space point in pixels, `None` if the point is behind
+    /// the camera. Points outside the viewport map to positions outside of it.
+    pub fn world_to_viewport(
+        &self,
+        point: Point3<f32>,
+        viewport_size: Vector2<f32>,
+    ) -> Option<Vector2<f32>> {
+        let clip = self.view_projection(viewport_size) * point.to_homogeneous();
+        if clip.w <= 0.0 {
+            return None;
+        }
+        Some(Vector2::new(
+            (clip.x / clip.w + 1.0) / 2.0 * viewport_size.x,
+            (clip.y / clip.w + 1.0) / 2.0 * viewport_size.y,
+        ))
+    }
+
+    /// The world space view frustum for a viewport of the given size in pixels.
+    pub fn frustum(&self, viewport_size: Vector2<f32>) -> Frustum {
+        Frustum::from_view_projection(self.view_projection(viewport_size))
+    }
+}
+
+/// The planes bounding the visible volume of a [`Camera`], for culling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    /// The normals point inwards and are normalized, `w` is the plane offset. The far plane of
+    /// an infinite projection has a zero normal and never rejects anything.
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a world to clip space transform, clip space depth is bounded by
+    /// 0 and `w` regardless of the [`DepthRange`].
+    pub fn from_view_projection(view_projection: Matrix4<f32>) -> Self {
+        let rows = view_projection.transpose();
+        let planes = [
+            rows.w + rows.x,
+            rows.w - rows.x,
+            rows.w + rows.y,
+            rows.w - rows.y,
+            rows.z,
+            rows.w - rows.z,
+        ]
+        .map(|plane| {
+            let length = plane.truncate().magnitude();
+            if length > 0.0 { plane / length } else { plane }
+        });
+        Self { planes }
+    }
+
+    /// Whether any part of the world space sphere may be visible.
+    pub fn intersects_sphere(&self, center: Point3<f32>, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.dot(center.to_homogeneous()) >= -radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, EuclideanSpace};
+
+    const VIEWPORT: Vector2<f32> = Vector2::new(200.0, 100.0);
+
+    fn assert_close<const N: usize>(actual: [f32; N], expected: [f32; N]) {
+        for (actual, expected) in actual.into_iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-2,
+                "{actual} is not {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn projection_maps_the_frustum_to_vulkan_clip_space() {
+        let camera = Camera {
+            fov_y: std::f32::consts::FRAC_PI_2,
+            near: 1.0,
+            far: 100.0,
+            ..Default::default()
+        };
+
+        // With a 90 degree field of view the frustum edges at depth 5 are 5 units up and, with
+        // an aspect ratio of 2, 10 units to the side
+        let top_right = camera.world_to_viewport(Point3::new(10.0, 5.0, -5.0), VIEWPORT);
+        assert_close(top_right.unwrap().into(), [200.0, 0.0]);
+        let center = camera.world_to_viewport(Point3::new(0.0, 0.0, -50.0), VIEWPORT);
+        assert_close(center.unwrap().into(), [100.0, 50.0]);
+        assert_eq!(
+            camera.world_to_viewport(Point3::new(0.0, 0.0, 5.0), VIEWPORT),
+            None
+        );
+
+        let projection = camera.projection(VIEWPORT);
+        let near = projection * Vector4::new(0.0, 0.0, -1.0, 1.0);
+        let far = projection * Vector4::new(0.0, 0.0, -100.0, 1.0);
+        assert_close([near.z / near.w, far.z / far.w], [0.0, 1.0]);
+    }
+
+    #[test]
+    fn viewport_and_world_round_trip() {
+        let eye = Point3::new(3.0, 2.0, 10.0);
+        let camera = Camera {
+            view: Matrix4::from_angle_y(Deg(-30.0)) * Matrix4::from_translation(-eye.to_vec()),
+            ..Default::default()
+        };
+        assert_close(camera.position().unwrap().into(), eye.into());
+
+        let ray = camera
+            .viewport_to_world(Vector2::new(100.0, 50.0), VIEWPORT)
+            .unwrap();
+        let forward = camera.view.invert().unwrap() * Vector4::new(0.0, 0.0, -1.0, 0.0);
+        assert_close(ray.direction.into(), forward.truncate().into());
+        assert_close(
+            ray.origin.into(),
+            (eye + forward.truncate() * camera.near).into(),
+        );
+
+        let cursor = Vector2::new(37.0, 81.0);
+        let ray = camera.viewport_to_world(cursor, VIEWPORT).unwrap();
+        let projected = camera.world_to_viewport(ray.at(25.0), VIEWPORT).unwrap();
+        assert_close(projected.into(), cursor.into());
+
+        assert_eq!(
+            camera.viewport_to_world(cursor, Vector2::new(0.0, 0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn depth_ranges_and_infinite_far_planes() {
+        let depth = |camera: &Camera, z: f32| {
+            let clip = camera.projection(VIEWPORT) * Vector4::new(0.0, 0.0, z, 1.0);
+            clip.z / clip.w
+        };
+        let reversed = Camera {
+            near: 0.5,
+            far: 100.0,
+            depth_range: DepthRange::Reversed,
+            ..Default::default()
+        };
+        assert_close(
+            [depth(&reversed, -0.5), depth(&reversed, -100.0)],
+            [1.0, 0.0],
+        );
+
+        let infinite = Camera {
+            far: f32::INFINITY,
+            ..reversed
+        };
+        assert_close([depth(&infinite, -0.5), depth(&infinite, -1.0)], [1.0, 0.5]);
+        assert!(depth(&infinite, -1.0e6) > 0.0);
+        let standard = Camera {
+            depth_range: DepthRange::Standard,
+            ..infinite
+        };
+        assert_close([depth(&standard, -0.5), depth(&standard, -1.0)], [0.0, 0.5]);
+        assert!(depth(&standard, -1.0e6) < 1.0);
+
+        let ray = infinite
+            .viewport_to_world(Vector2::new(100.0, 50.0), VIEWPORT)
+            .unwrap();
+        assert_close(ray.origin.into(), [0.0, 0.0, -0.5]);
+        assert_close(ray.direction.into(), [0.0, 0.0, -1.0]);
+
+        let frustum = infinite.frustum(VIEWPORT);
+        assert!(frustum.intersects_sphere(Point3::new(0.0, 0.0, -1.0e6), 1.0));
+        assert!(!frustum.intersects_sphere(Point3::new(0.0, 0.0, 2.0), 1.0));
+        assert!(!frustum.intersects_sphere(Point3::new(-50.0, 0.0, -10.0), 1.0));
+        let frustum = reversed.frustum(VIEWPORT);
+        assert!(frustum.intersects_sphere(Point3::new(0.0, 0.0, -100.5), 1.0));
+        assert!(!frustum.intersects_sphere(Point3::new(0.0, 0.0, -102.0), 1.0));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::device::DeviceFeatures;
+use ash::{ext, google, khr};
+use flux_ecs::resource::Resource;
+use std::ffi::CStr;
+
+/// What the selected device supports beyond the required
+/// [`DeviceRequirements`](crate::DeviceRequirements), inserted when the logical device is
+/// created.
+///
+/// The flags report whether the extension of a feature was enabled, renderer features with a
+/// fallback path check them instead of assuming support.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RendererCapabilities {
+    /// The optional extensions that were enabled.
+    pub optional_extensions: Vec<&'static CStr>,
+    /// The Vulkan 1.1 to 1.3 features that were enabled, required and optional.
+    pub features: DeviceFeatures,
+    /// Whether the dynamic rendering feature is enabled, the renderer falls back to render passes
+    /// otherwise, see [`RenderPath`](crate::RenderPath).
+    pub dynamic_rendering: bool,
+    pub descriptor_indexing: bool,
+    pub mesh_shaders: bool,
+    pub incremental_present: bool,
+    /// Whether heap budgets can be queried, see [`GpuMemoryBudget`](crate::GpuMemoryBudget).
+    pub memory_budget: bool,
+    /// Whether the `pipelineStatisticsQuery` feature is enabled, see
+    /// [`PipelineStatisticsQueries`](crate::PipelineStatisticsQueries).
+    pub pipeline_statistics: bool,
+    /// Whether present timings can be read back, see [`PresentTiming`](crate::PresentTiming).
+    pub display_timing: bool,
+}
+
+impl Resource for RendererCapabilities {}
+
+impl RendererCapabilities {
+    /// Derives the capabilities from all enabled extensions, required and optional.
+    pub fn new(
+        enabled_extensions: &[&'static CStr],
+        optional_extensions: &[&'static CStr],
+    ) -> Self {
+        let enabled = |extension: &CStr| enabled_extensions.contains(&extension);
+
+        Self {
+            optional_extensions: optional_extensions
+                .iter()
+                .copied()
+                .filter(|extension| enabled(extension))
+                .collect(),
+            features: DeviceFeatures::default(),
+            dynamic_rendering: enabled(khr::dynamic_rendering::NAME),
+            descriptor_indexing: enabled(ext::descriptor_indexing::NAME),
+            mesh_shaders: enabled(ext::mesh_shader::NAME),
+            incremental_present: enabled(khr::incremental_present::NAME),
+            memory_budget: enabled(ext::memory_budget::NAME),
+            pipeline_statistics: false,
+            display_timing: enabled(google::display_timing::NAME),
+        }
+    }
+
+    pub fn has_optional_extension(&self, extension: &CStr) -> bool {
+        self.optional_extensions.contains(&extension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_follow_the_enabled_extensions() {
+        let optional = [ext::mesh_shader::NAME, ext::descriptor_indexing::NAME];
+        let enabled = [khr::swapchain::NAME, ext::descriptor_indexing::NAME];
+
+        let capabilities = RendererCapabilities::new(&enabled, &optional);
+
+        assert!(capabilities.descriptor_indexing);
+        assert!(!capabilities.mesh_shaders);
+        assert!(!capabilities.dynamic_rendering);
+        assert_eq!(
+            capabilities.optional_extensions,
+            [ext::descriptor_indexing::NAME]
+        );
+    }
+}
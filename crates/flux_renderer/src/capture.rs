@@ -0,0 +1,362 @@
+use crate::allocator::{Allocation, GpuAllocator};
+use crate::buffers::{begin_single_time_commands, create_buffer, end_single_time_commands};
+use crate::command_pool::CommandPools;
+use crate::device::Device;
+use crate::layout_tracker::ImageLayoutTracker;
+use crate::log_targets;
+use crate::swapchain::Swapchain;
+use ash::vk;
+use flux_ecs::resource::{Res, Resource};
+use flux_ecs::world::World;
+use log::{debug, warn};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use thiserror::Error;
+
+/// A rendered frame read back from the GPU by [`FrameCapture`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedFrame {
+    pub width: u32,
+    pub height: u32,
+    pub format: vk::Format,
+    /// The texels row by row without padding, 4 bytes each with alpha last.
+    pub pixels: Vec<u8>,
+}
+
+impl CapturedFrame {
+    /// Whether any texel has a color channel other than zero, alpha is ignored.
+    pub fn has_non_black_pixels(&self) -> bool {
+        self.pixels
+            .chunks_exact(4)
+            .any(|texel| texel[..3].iter().any(|&channel| channel != 0))
+    }
+
+    /// The texels whose coverage differs from `expected`, as `(x, y)` from the top left corner.
+    ///
+    /// `expected` returns whether a texel should be non-black, or `None` to skip it, e.g. for the
+    /// texels on the edges of a shape that rasterization may or may not cover.
+    pub fn mismatched_pixels(
+        &self,
+        expected: impl Fn(u32, u32) -> Option<bool>,
+    ) -> Vec<(u32, u32)> {
+        let texels = self.pixels.chunks_exact(4);
+        let positions = (0..self.height).flat_map(|y| (0..self.width).map(move |x| (x, y)));
+        positions
+            .zip(texels)
+            .filter(|&((x, y), texel)| {
+                let covered = texel[..3].iter().any(|&channel| channel != 0);
+                expected(x, y).is_some_and(|expected| expected != covered)
+            })
+            .map(|(position, _)| position)
+            .collect()
+    }
+}
+
+/// The host visible buffer the render target is copied into.
+struct Readback {
+    buffer: vk::Buffer,
+    memory: Allocation,
+    size: vk::DeviceSize,
+}
+
+#[derive(Default)]
+struct CaptureState {
+    requested: bool,
+    readback: Option<Readback>,
+    captured: Option<CapturedFrame>,
+}
+
+/// Reads the render target of a frame back to the CPU, e.g. for screenshots and tests.
+///
+/// The render target must be a transfer source, request `TRANSFER_SRC` in
+/// [`GraphicsSettings::swapchain_usage`](crate::GraphicsSettings::swapchain_usage). The captured
+/// frame waits for the GPU before it is presented.
+#[derive(Default)]
+pub struct FrameCapture {
+    state: Mutex<CaptureState>,
+}
+
+impl Resource for FrameCapture {}
+
+impl FrameCapture {
+    fn state(&self) -> MutexGuard<'_, CaptureState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Captures the next rendered frame.
+    pub fn request(&self) {
+        self.state().requested = true;
+    }
+
+    /// Whether a requested frame was not captured yet.
+    pub fn is_pending(&self) -> bool {
+        self.state().requested
+    }
+
+    /// Takes the last captured frame.
+    pub fn take(&self) -> Option<CapturedFrame> {
+        self.state().captured.take()
+    }
+
+    /// Returns the buffer to copy the render target into if a capture was requested, `None` if
+    /// there is nothing to capture or the swapchain can not be captured.
+    pub(crate) fn prepare(
+        &self,
+        device: &Device,
+        allocator: &GpuAllocator,
+        swapchain: &Swapchain,
+    ) -> Result<Option<vk::Buffer>, vk::Result> {
+        let mut state = self.state();
+        if !state.requested {
+            return Ok(None);
+        }
+        if !is_capturable(swapchain) {
+            warn!(
+                target: log_targets::RESOURCES,
+                "The render target can not be captured, it needs the TRANSFER_SRC usage and a \
+                 4 byte 8 bit format"
+            );
+            state.requested = false;
+            return Ok(None);
+        }
+
+        let size = capture_size(swapchain.render_extent);
+        if let Some(readback) = state.readback.take_if(|readback| readback.size != size) {
+            unsafe { device.destroy_buffer(readback.buffer, None) };
+            allocator.free(device, readback.memory);
+        }
+        if let Some(readback) = &state.readback {
+            return Ok(Some(readback.buffer));
+        }
+
+        debug!(target: log_targets::RESOURCES, "Creating the frame capture buffer of {size} bytes");
+        let (buffer, memory) = create_buffer(
+            device,
+            allocator,
+            size,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        state.readback = Some(Readback {
+            buffer,
+            memory,
+            size,
+        });
+
+        Ok(Some(buffer))
+    }
+
+    /// Copies the captured frame out of the readback buffer.
+    ///
+    /// # Safety
+    /// The frame the buffer was recorded into must have finished.
+    pub(crate) unsafe fn complete(&self, swapchain: &Swapchain) {
+        let mut state = self.state();
+        let Some(readback) = &state.readback else {
+            return;
+        };
+        let Some(mapped) = readback.memory.mapped_ptr() else {
+            warn!(target: log_targets::RESOURCES, "The frame capture buffer is not mapped");
+            return;
+        };
+
+        let pixels =
+            unsafe { std::slice::from_raw_parts(mapped, readback.size as usize) }.to_vec();
+        state.captured = Some(CapturedFrame {
+            width: swapchain.render_extent.width,
+            height: swapchain.render_extent.height,
+            format: swapchain.format.format,
+            pixels,
+        });
+        state.requested = false;
+    }
+
+    /// Records the copy of the render target at `index` into `buffer`.
+    ///
+    /// # Safety
+    /// The command buffer must be recording outside of a rendering pass and the render target
+    /// must be in the `TRANSFER_SRC_OPTIMAL` layout.
+    pub(crate) unsafe fn record_copy(
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        swapchain: &Swapchain,
+        index: usize,
+        buffer: vk::Buffer,
+    ) {
+        let (image, _) = swapchain.render_target(index);
+        let region = vk::BufferImageCopy::default()
+            .image_subresource(
+                vk::ImageSubresourceLayers::default()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .layer_count(1),
+            )
+            .image_extent(vk::Extent3D {
+                width: swapchain.render_extent.width,
+                height: swapchain.render_extent.height,
+                depth: 1,
+            });
+        let host_barrier = vk::BufferMemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(buffer)
+            .size(vk::WHOLE_SIZE);
+
+        unsafe {
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer,
+                &[region],
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[host_barrier],
+                &[],
+            );
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ReadbackError {
+    #[error("the renderer is not initialized")]
+    NotInitialized,
+    #[error("the renderer is not rendering headless")]
+    NotHeadless,
+    #[error("no frame was rendered yet")]
+    NoFrame,
+    #[error(transparent)]
+    Vulkan(#[from] vk::Result),
+}
+
+/// Reads the last frame rendered headless back from the GPU, the pixels are RGBA with 8 bits
+/// per channel in sRGB, e.g. for golden image tests.
+///
+/// Waits for the device to finish its work, call it between frames.
+pub fn read_back_framebuffer(world: &World) -> Result<CapturedFrame, ReadbackError> {
+    let (Some(device), Some(allocator), Some(command_pools), Some(layouts)) = (
+        world.get_resource::<Device>(),
+        world.get_resource::<GpuAllocator>(),
+        world.get_resource::<CommandPools>(),
+        world.get_resource::<ImageLayoutTracker>(),
+    ) else {
+        return Err(ReadbackError::NotInitialized);
+    };
+    let Some(swapchain) = world.get_resource::<Swapchain>() else {
+        return Err(ReadbackError::NotInitialized);
+    };
+    if !swapchain.is_headless() {
+        return Err(ReadbackError::NotHeadless);
+    }
+    let image = swapchain.images[0];
+    if layouts.layout(image, 0, 0) != Some(swapchain.final_layout()) {
+        return Err(ReadbackError::NoFrame);
+    }
+
+    unsafe { device.device_wait_idle()? };
+
+    let size = capture_size(swapchain.render_extent);
+    let (buffer, memory) = create_buffer(
+        device,
+        allocator,
+        size,
+        vk::BufferUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+    )?;
+
+    let copied = unsafe { begin_single_time_commands(device, command_pools.graphics) }
+        .and_then(|command_buffer| {
+            unsafe { FrameCapture::record_copy(device, command_buffer, swapchain, 0, buffer) };
+            end_single_time_commands(
+                device,
+                device.graphics_queue,
+                command_pools.graphics,
+                command_buffer,
+            )
+        });
+    let pixels = memory
+        .mapped_ptr()
+        .map(|mapped| unsafe { std::slice::from_raw_parts(mapped, size as usize) }.to_vec());
+
+    unsafe { device.destroy_buffer(buffer, None) };
+    allocator.free(device, memory);
+    copied?;
+
+    Ok(CapturedFrame {
+        width: swapchain.render_extent.width,
+        height: swapchain.render_extent.height,
+        format: swapchain.format.format,
+        pixels: pixels.expect("Host visible allocations are mapped"),
+    })
+}
+
+/// Whether the render target can be copied into a buffer and read as 4 byte texels.
+fn is_capturable(swapchain: &Swapchain) -> bool {
+    let transfer_source = swapchain.intermediate.is_some()
+        || swapchain.usage.contains(vk::ImageUsageFlags::TRANSFER_SRC);
+    transfer_source && has_8bit_rgba_texels(swapchain.format.format)
+}
+
+fn has_8bit_rgba_texels(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::B8G8R8A8_UNORM
+            | vk::Format::B8G8R8A8_SRGB
+            | vk::Format::R8G8B8A8_UNORM
+            | vk::Format::R8G8B8A8_SRGB
+    )
+}
+
+fn capture_size(extent: vk::Extent2D) -> vk::DeviceSize {
+    extent.width as vk::DeviceSize * extent.height as vk::DeviceSize * 4
+}
+
+pub fn destroy_frame_capture(
+    device: Res<Device>,
+    allocator: Res<GpuAllocator>,
+    capture: Res<FrameCapture>,
+) {
+    if let Some(readback) = capture.state().readback.take() {
+        debug!(target: log_targets::RESOURCES, "Destroying the frame capture buffer");
+        unsafe { device.destroy_buffer(readback.buffer, None) };
+        allocator.free(&device, readback.memory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_black_pixels_ignore_alpha() {
+        let mut frame = CapturedFrame {
+            width: 2,
+            height: 1,
+            format: vk::Format::B8G8R8A8_UNORM,
+            pixels: vec![0, 0, 0, 255, 0, 0, 0, 255],
+        };
+        assert!(!frame.has_non_black_pixels());
+
+        frame.pixels[5] = 12;
+        assert!(frame.has_non_black_pixels());
+        assert_eq!(frame.mismatched_pixels(|x, _| Some(x == 1)), []);
+        assert_eq!(
+            frame.mismatched_pixels(|x, _| Some(x == 0)),
+            [(0, 0), (1, 0)]
+        );
+        assert_eq!(
+            frame.mismatched_pixels(|x, _| (x == 0).then_some(true)),
+            [(0, 0)]
+        );
+
+        assert!(has_8bit_rgba_texels(vk::Format::R8G8B8A8_SRGB));
+        assert!(!has_8bit_rgba_texels(vk::Format::A2B10G10R10_UNORM_PACK32));
+        assert_eq!(capture_size(vk::Extent2D { width: 3, height: 2 }), 24);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::log_targets;
+use ash::vk;
+use log::warn;
+
+/// Formats with both an sRGB and a linear (`UNORM`) variant, as `(unorm, srgb)`.
+const SRGB_VARIANTS: &[(vk::Format, vk::Format)] = &[
+    (vk::Format::R8_UNORM, vk::Format::R8_SRGB),
+    (vk::Format::R8G8_UNORM, vk::Format::R8G8_SRGB),
+    (vk::Format::R8G8B8_UNORM, vk::Format::R8G8B8_SRGB),
+    (vk::Format::B8G8R8_UNORM, vk::Format::B8G8R8_SRGB),
+    (vk::Format::R8G8B8A8_UNORM, vk::Format::R8G8B8A8_SRGB),
+    (vk::Format::B8G8R8A8_UNORM, vk::Format::B8G8R8A8_SRGB),
+    (
+        vk::Format::A8B8G8R8_UNORM_PACK32,
+        vk::Format::A8B8G8R8_SRGB_PACK32,
+    ),
+    (
+        vk::Format::BC1_RGB_UNORM_BLOCK,
+        vk::Format::BC1_RGB_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::BC1_RGBA_UNORM_BLOCK,
+        vk::Format::BC1_RGBA_SRGB_BLOCK,
+    ),
+    (vk::Format::BC2_UNORM_BLOCK, vk::Format::BC2_SRGB_BLOCK),
+    (vk::Format::BC3_UNORM_BLOCK, vk::Format::BC3_SRGB_BLOCK),
+    (vk::Format::BC7_UNORM_BLOCK, vk::Format::BC7_SRGB_BLOCK),
+    (
+        vk::Format::ETC2_R8G8B8_UNORM_BLOCK,
+        vk::Format::ETC2_R8G8B8_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK,
+        vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK,
+    ),
+    (
+        vk::Format::ASTC_4X4_UNORM_BLOCK,
+        vk::Format::ASTC_4X4_SRGB_BLOCK,
+    ),
+];
+
+/// How the texels of a texture are encoded, decided when the texture is imported.
+///
+/// Color textures authored in image editors (albedo, emissive, UI) are sRGB encoded and sampled
+/// through an `_SRGB` format, the hardware decodes them so shaders light in linear space. Data
+/// textures (normals, roughness, masks) are linear and must not be decoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    #[default]
+    Srgb,
+    Linear,
+}
+
+impl ColorSpace {
+    /// The color space a format is decoded from when sampled, formats without an sRGB variant
+    /// are linear.
+    pub fn of_format(format: vk::Format) -> Self {
+        if SRGB_VARIANTS.iter().any(|&(_, srgb)| srgb == format) {
+            ColorSpace::Srgb
+        } else {
+            ColorSpace::Linear
+        }
+    }
+
+    /// The variant of `format` to store texels of this color space in, e.g. `R8G8B8A8_SRGB` for
+    /// an sRGB `R8G8B8A8_UNORM` texture. Formats without an sRGB variant are returned as is.
+    pub fn texture_format(self, format: vk::Format) -> vk::Format {
+        let variant = SRGB_VARIANTS
+            .iter()
+            .find(|&&(unorm, srgb)| unorm == format || srgb == format);
+        match (self, variant) {
+            (ColorSpace::Srgb, Some(&(_, srgb))) => srgb,
+            (ColorSpace::Linear, Some(&(unorm, _))) => unorm,
+            (_, None) => format,
+        }
+    }
+}
+
+/// Who applies the sRGB encoding of the final image presented to the display.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputEncoding {
+    /// The swapchain has an sRGB format, the hardware encodes the linear output of the shaders
+    /// on write.
+    Hardware,
+    /// The swapchain has a linear format, the final pass has to encode its output to sRGB.
+    Shader,
+}
+
+impl OutputEncoding {
+    pub fn for_swapchain(format: vk::Format) -> Self {
+        match ColorSpace::of_format(format) {
+            ColorSpace::Srgb => OutputEncoding::Hardware,
+            ColorSpace::Linear => OutputEncoding::Shader,
+        }
+    }
+}
+
+/// Checks in debug builds that the final pass encodes its output to sRGB exactly once, warning
+/// about washed out (encoded twice) or too dark (never encoded) images.
+pub fn validate_output_encoding(swapchain_format: vk::Format, shader_encodes_srgb: bool) -> bool {
+    if !cfg!(debug_assertions) {
+        return true;
+    }
+
+    match (
+        OutputEncoding::for_swapchain(swapchain_format),
+        shader_encodes_srgb,
+    ) {
+        (OutputEncoding::Hardware, true) => {
+            warn!(
+                target: log_targets::SWAPCHAIN,
+                "sRGB is applied twice: the final pass encodes to sRGB, but the swapchain format \
+                 {swapchain_format:?} encodes on write as well"
+            );
+            false
+        }
+        (OutputEncoding::Shader, false) => {
+            warn!(
+                target: log_targets::SWAPCHAIN,
+                "sRGB is never applied: the swapchain format {swapchain_format:?} is linear, but \
+                 the final pass does not encode its output"
+            );
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Decodes an sRGB encoded color channel, e.g. of a color picked in an editor, to linear space.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear color channel to sRGB.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn textures_and_swapchains_pick_the_format_of_their_color_space() {
+        let albedo = ColorSpace::Srgb.texture_format(vk::Format::BC7_UNORM_BLOCK);
+        assert_eq!(albedo, vk::Format::BC7_SRGB_BLOCK);
+        assert_eq!(ColorSpace::of_format(albedo), ColorSpace::Srgb);
+
+        let normals = ColorSpace::Linear.texture_format(vk::Format::R8G8B8A8_SRGB);
+        assert_eq!(normals, vk::Format::R8G8B8A8_UNORM);
+        assert_eq!(
+            ColorSpace::Srgb.texture_format(vk::Format::R16G16B16A16_SFLOAT),
+            vk::Format::R16G16B16A16_SFLOAT
+        );
+
+        assert!(validate_output_encoding(vk::Format::B8G8R8A8_SRGB, false));
+        assert!(validate_output_encoding(vk::Format::B8G8R8A8_UNORM, true));
+        if cfg!(debug_assertions) {
+            assert!(!validate_output_encoding(vk::Format::B8G8R8A8_SRGB, true));
+            assert!(!validate_output_encoding(vk::Format::B8G8R8A8_UNORM, false));
+        }
+
+        for value in [0.0, 0.002, 0.2, 0.5, 1.0] {
+            assert!((linear_to_srgb(srgb_to_linear(value)) - value).abs() < 1e-5);
+        }
+        assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+    }
+}
@@ -1,110 +1,476 @@
-use crate::buffers::{IndexBuffer, VertexBuffer};
-use crate::command_pool::CommandPools;
+use crate::barrier_validation::BarrierValidator;
+use crate::camera::Frustum;
+use crate::capture::FrameCapture;
 use crate::depth_buffers::DepthBuffers;
 use crate::descriptors::Descriptors;
 use crate::device::Device;
-use crate::pipeline::Pipeline;
+use crate::fullscreen::{FullscreenPasses, FullscreenStage};
+use crate::gpu_particles::{
+    GpuParticleEmitter, ParticleComputePipeline, ParticleDrawPipeline, record_gpu_particle_draw,
+    record_gpu_particle_simulation,
+};
+use crate::layout_tracker::ImageLayoutTracker;
+use crate::mesh::GpuMeshes;
+use crate::occlusion::OcclusionQueries;
+use crate::permutations::PipelinePermutations;
+use crate::pipeline::{MeshPushConstants, Pipeline};
+use crate::pipeline_statistics::PipelineStatisticsQueries;
+use crate::raw::{RawVulkan, RawVulkanHooks};
+use crate::render_path::{ClassicRenderPass, PassRecorder, PassTargets};
+use crate::scratch::FrameScratch;
+use crate::stats::RenderStats;
 use crate::swapchain::Swapchain;
 use ash::vk;
-use flux_ecs::resource::Res;
-use log::debug;
-
-pub fn create_command_buffer(
-    device: Res<Device>,
-    command_pools: Res<CommandPools>,
-    swapchain: Res<Swapchain>,
-    depth_buffers: Res<DepthBuffers>,
-    pipeline: Res<Pipeline>,
-    vertex_buffer: Res<VertexBuffer>,
-    index_buffer: Res<IndexBuffer>,
-    descriptors: Res<Descriptors>,
-) -> Result<(), vk::Result> {
-    debug!("Creating command buffer");
-
-    let allocate_info = vk::CommandBufferAllocateInfo::default()
-        .command_pool(command_pools.graphics)
-        .level(vk::CommandBufferLevel::PRIMARY)
-        .command_buffer_count(1);
-
-    let command_buffer = unsafe { device.allocate_command_buffers(&allocate_info)? };
-
-    for (i, command_buffer) in command_buffer.iter().enumerate() {
-        let inheritance = vk::CommandBufferInheritanceInfo::default();
+use crate::log_targets;
+use log::{debug, trace};
+
+/// The resources the main pass of a frame is recorded from.
+pub(crate) struct FrameRecorder<'a> {
+    pub device: &'a Device,
+    pub swapchain: &'a Swapchain,
+    pub depth_buffers: &'a DepthBuffers,
+    pub pipeline: &'a Pipeline,
+    pub permutations: &'a PipelinePermutations,
+    pub meshes: &'a GpuMeshes,
+    /// The view frustum of the camera, meshes outside of it are not drawn.
+    pub frustum: Option<Frustum>,
+    pub descriptors: &'a Descriptors,
+    pub stats: &'a RenderStats,
+    pub raw_vulkan: &'a RawVulkan,
+    pub raw_vulkan_hooks: &'a RawVulkanHooks,
+    pub layouts: &'a ImageLayoutTracker,
+    pub scratch: &'a FrameScratch,
+    pub render_pass: Option<&'a ClassicRenderPass>,
+    pub occlusion: Option<&'a OcclusionQueries>,
+    pub pipeline_statistics: Option<&'a PipelineStatisticsQueries>,
+    /// The buffer the render target is copied into for a [`FrameCapture`].
+    pub capture_buffer: Option<vk::Buffer>,
+    pub fullscreen_passes: Option<&'a FullscreenPasses>,
+    pub gpu_particles: Option<GpuParticleFrame<'a>>,
+}
+
+/// The GPU particle emitters simulated before and drawn in the main pass.
+pub(crate) struct GpuParticleFrame<'a> {
+    pub compute: &'a ParticleComputePipeline,
+    pub draw: &'a ParticleDrawPipeline,
+    pub emitters: Vec<&'a GpuParticleEmitter>,
+    pub validator: &'a BarrierValidator,
+}
+
+impl FrameRecorder<'_> {
+    /// Records the main pass into `command_buffer`, rendering to the swapchain image `i` and
+    /// leaving it ready for presentation, or for read back when running headless.
+    ///
+    /// # Safety
+    /// The command buffer must be reset and not in use by the GPU, and the per image resources
+    /// of image `i` must not be used by a pending submission.
+    pub(crate) unsafe fn record(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        i: usize,
+    ) -> Result<(), vk::Result> {
+        let device = self.device;
+        let swapchain = self.swapchain;
+        let layouts = self.layouts;
+        let stats = self.stats;
+
+        debug!(target: log_targets::COMMANDS, "Recording the command buffer of image {i}");
 
         let info = vk::CommandBufferBeginInfo::default()
-            .flags(vk::CommandBufferUsageFlags::empty())
-            .inheritance_info(&inheritance);
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
 
         unsafe {
-            device.begin_command_buffer(*command_buffer, &info)?;
+            device.begin_command_buffer(command_buffer, &info)?;
         }
 
-        let render_area = vk::Rect2D::default()
-            .offset(vk::Offset2D::default())
-            .extent(swapchain.extent);
+        let color_range = subresource_range(vk::ImageAspectFlags::COLOR);
+        let depth_range = subresource_range(vk::ImageAspectFlags::DEPTH);
+        let (target_image, target_view) = swapchain.render_target(i);
 
-        let color_clear_value = vk::ClearValue {
-            color: vk::ClearColorValue {
-                float32: [0.0, 0.0, 0.0, 1.0],
-            },
-        };
+        unsafe {
+            wait_for_shared_attachments(device, command_buffer);
+            layouts.record_transition(
+                device,
+                command_buffer,
+                self.scratch,
+                target_image,
+                color_range,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            );
+            layouts.record_transition(
+                device,
+                command_buffer,
+                self.scratch,
+                self.depth_buffers.depth_image,
+                depth_range,
+                vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            );
+        }
 
-        let depth_clear_value = vk::ClearValue {
-            depth_stencil: vk::ClearDepthStencilValue {
-                depth: 1.0,
-                stencil: 0,
-            },
-        };
+        if let Some(occlusion) = self.occlusion {
+            unsafe { occlusion.reset(device, command_buffer, i) };
+        }
+        if let Some(particles) = &self.gpu_particles {
+            for emitter in &particles.emitters {
+                unsafe {
+                    record_gpu_particle_simulation(
+                        device,
+                        command_buffer,
+                        particles.compute,
+                        emitter,
+                        particles.validator,
+                    )
+                };
+            }
+        }
 
-        // TODO: Compare values with framebuffer attachments
-        let color_attachment_info = vk::RenderingAttachmentInfo::default()
-            .image_view(swapchain.image_views[i])
-            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-            .load_op(vk::AttachmentLoadOp::CLEAR)
-            .store_op(vk::AttachmentStoreOp::STORE)
-            .clear_value(color_clear_value);
-
-        let depth_attachment_info = vk::RenderingAttachmentInfo::default()
-            .image_view(depth_buffers.depth_image_view)
-            .image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
-            .load_op(vk::AttachmentLoadOp::CLEAR)
-            .store_op(vk::AttachmentStoreOp::STORE)
-            .clear_value(depth_clear_value);
-
-        let color_attachments = &[color_attachment_info];
-        let rendering_info = vk::RenderingInfo::default()
-            .render_area(render_area)
-            .layer_count(1)
-            .color_attachments(color_attachments)
-            .depth_attachment(&depth_attachment_info);
+        let pass = PassRecorder::new(self.render_pass);
+        let targets = PassTargets {
+            image_index: i,
+            color_view: target_view,
+            depth_view: self.depth_buffers.depth_image_view,
+            extent: swapchain.render_extent,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+        };
 
         unsafe {
-            device.cmd_begin_rendering(*command_buffer, &rendering_info);
-            device.cmd_bind_pipeline(*command_buffer, vk::PipelineBindPoint::GRAPHICS, **pipeline);
+            if let Some(pipeline_statistics) = self.pipeline_statistics {
+                pipeline_statistics.begin(device, command_buffer, i);
+            }
+            pass.begin(device, command_buffer, &targets);
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                **self.pipeline,
+            );
+            stats.record_pipeline_bind();
+            let viewport = vk::Viewport::default()
+                .width(swapchain.render_extent.width as f32)
+                .height(swapchain.render_extent.height as f32)
+                .max_depth(1.0);
+            let scissor = vk::Rect2D::default().extent(swapchain.render_extent);
+            device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+            device.cmd_set_scissor(command_buffer, 0, &[scissor]);
 
-            device.cmd_bind_vertex_buffers(*command_buffer, 0, &[vertex_buffer.buffer], &[0]);
-            device.cmd_bind_index_buffer(
-                *command_buffer,
-                index_buffer.buffer,
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline.pipeline_layout,
                 0,
-                vk::IndexType::UINT32,
+                // Secondary windows may have more images than the main swapchain
+                &[self.descriptors.descriptor_sets[i % self.descriptors.descriptor_sets.len()]],
+                &[],
             );
+            stats.record_descriptor_bind();
+
+            self.draw_meshes(command_buffer, i);
+            self.draw_fullscreen_passes(command_buffer, FullscreenStage::AfterOpaque, i);
+            self.draw_gpu_particles(command_buffer, i);
+
+            self.raw_vulkan_hooks
+                .record(self.raw_vulkan, command_buffer);
+            self.draw_fullscreen_passes(command_buffer, FullscreenStage::AfterPostProcess, i);
+
+            pass.end(device, command_buffer);
+            if let Some(pipeline_statistics) = self.pipeline_statistics {
+                pipeline_statistics.end(device, command_buffer, i);
+            }
 
+            if let Some(buffer) = self.capture_buffer {
+                layouts.record_transition(
+                    device,
+                    command_buffer,
+                    self.scratch,
+                    target_image,
+                    color_range,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                );
+                FrameCapture::record_copy(device, command_buffer, swapchain, i, buffer);
+            }
+
+            if swapchain.intermediate.is_some() {
+                copy_to_swapchain(device, command_buffer, layouts, self.scratch, swapchain, i);
+            }
+
+            layouts.record_transition(
+                device,
+                command_buffer,
+                self.scratch,
+                swapchain.images[i],
+                color_range,
+                swapchain.final_layout(),
+            );
+            device.end_command_buffer(command_buffer)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Orders the attachment writes of this frame after the ones of the previous frames. The frames
+/// in flight share the depth buffer and the intermediate image, which stay in their layout and
+/// are therefore not synchronized by a layout transition.
+///
+/// # Safety
+/// The command buffer must be recording outside of a rendering pass.
+unsafe fn wait_for_shared_attachments(device: &Device, command_buffer: vk::CommandBuffer) {
+    let attachment_stages = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT
+        | vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+        | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+    let barrier = vk::MemoryBarrier::default()
+        .src_access_mask(
+            vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        )
+        .dst_access_mask(
+            vk::AccessFlags::COLOR_ATTACHMENT_READ
+                | vk::AccessFlags::COLOR_ATTACHMENT_WRITE
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        );
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            attachment_stages,
+            attachment_stages,
+            vk::DependencyFlags::empty(),
+            &[barrier],
+            &[],
+            &[],
+        );
+    }
+}
+
+/// Copies the intermediate render target into the swapchain image at `index`, scaling it with
+/// linear filtering if it was rendered at a different resolution.
+///
+/// # Safety
+/// The command buffer must be recording outside of a rendering pass.
+unsafe fn copy_to_swapchain(
+    device: &Device,
+    command_buffer: vk::CommandBuffer,
+    layouts: &ImageLayoutTracker,
+    scratch: &FrameScratch,
+    swapchain: &Swapchain,
+    index: usize,
+) {
+    let (source, _) = swapchain.render_target(index);
+    let destination = swapchain.images[index];
+    let range = subresource_range(vk::ImageAspectFlags::COLOR);
+
+    let subresource = vk::ImageSubresourceLayers::default()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .mip_level(0)
+        .base_array_layer(0)
+        .layer_count(1);
+    let corner = |extent: vk::Extent2D| vk::Offset3D {
+        x: extent.width as i32,
+        y: extent.height as i32,
+        z: 1,
+    };
+
+    unsafe {
+        layouts.record_transition(
+            device,
+            command_buffer,
+            scratch,
+            source,
+            range,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        );
+        layouts.record_transition(
+            device,
+            command_buffer,
+            scratch,
+            destination,
+            range,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        );
+        if swapchain.render_extent == swapchain.extent {
+            let region = vk::ImageCopy::default()
+                .src_subresource(subresource)
+                .dst_subresource(subresource)
+                .extent(vk::Extent3D {
+                    width: swapchain.extent.width,
+                    height: swapchain.extent.height,
+                    depth: 1,
+                });
+            device.cmd_copy_image(
+                command_buffer,
+                source,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                destination,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[region],
+            );
+        } else {
+            let region = vk::ImageBlit::default()
+                .src_subresource(subresource)
+                .src_offsets([vk::Offset3D::default(), corner(swapchain.render_extent)])
+                .dst_subresource(subresource)
+                .dst_offsets([vk::Offset3D::default(), corner(swapchain.extent)]);
+            device.cmd_blit_image(
+                command_buffer,
+                source,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                destination,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[region],
+                vk::Filter::LINEAR,
+            );
+        }
+    }
+}
+
+impl FrameRecorder<'_> {
+    /// Draws every uploaded mesh inside the frustum with the pipeline permutation of its
+    /// material, see [`PipelineKey::for_mesh`](crate::PipelineKey::for_mesh). The model matrix of
+    /// each mesh is pushed before its draw.
+    ///
+    /// # Safety
+    /// The command buffer must be recording inside the main pass with the main pipeline bound.
+    unsafe fn draw_meshes(&self, command_buffer: vk::CommandBuffer, i: usize) {
+        let device = self.device;
+        let mut bound = self.pipeline.pipeline;
+
+        self.meshes.for_each(|entity, mesh| {
+            if self
+                .frustum
+                .is_some_and(|frustum| !mesh.is_visible(&frustum))
+            {
+                return;
+            }
+            let pipeline = mesh
+                .pipeline_key()
+                .and_then(|key| self.permutations.get(key));
+            let Some(pipeline) = pipeline else {
+                trace!(
+                    target: log_targets::COMMANDS,
+                    "Skipping mesh of entity {entity}, no pipeline matching its material and \
+                     vertex layout is warmed up"
+                );
+                return;
+            };
+            if pipeline != bound {
+                // Permutations share the layout of the main pipeline, the camera set stays bound
+                unsafe {
+                    device.cmd_bind_pipeline(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        pipeline,
+                    )
+                };
+                self.stats.record_pipeline_bind();
+                bound = pipeline;
+            }
+
+            let push_constants = MeshPushConstants {
+                model: mesh.model(),
+            };
+            unsafe {
+                device.cmd_push_constants(
+                    command_buffer,
+                    self.pipeline.pipeline_layout,
+                    vk::ShaderStageFlags::VERTEX,
+                    0,
+                    push_constants.as_bytes(),
+                );
+                mesh.streams().bind(device, command_buffer);
+                device.cmd_bind_index_buffer(
+                    command_buffer,
+                    mesh.index_buffer(),
+                    0,
+                    vk::IndexType::UINT32,
+                );
+
+                let index_count = mesh.index_count();
+                let query = self
+                    .occlusion
+                    .filter(|occlusion| occlusion.should_query(index_count))
+                    .and_then(|occlusion| occlusion.begin(device, command_buffer, i));
+                device.cmd_draw_indexed(command_buffer, index_count, 1, 0, 0, 0);
+                self.stats.record_draw(index_count, 1);
+                if let (Some(occlusion), Some(query)) = (self.occlusion, query) {
+                    occlusion.end(device, command_buffer, query);
+                }
+            }
+        });
+    }
+}
+
+impl FrameRecorder<'_> {
+    /// Draws the particles simulated at the start of the frame over the opaque geometry.
+    ///
+    /// # Safety
+    /// The command buffer must be recording inside the main pass.
+    unsafe fn draw_gpu_particles(&self, command_buffer: vk::CommandBuffer, i: usize) {
+        let Some(particles) = &self.gpu_particles else {
+            return;
+        };
+        if particles.emitters.is_empty() {
+            return;
+        }
+
+        let device = self.device;
+        let layout = particles.draw.pipeline_layout;
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                particles.draw.pipeline,
+            );
+            self.stats.record_pipeline_bind();
+            // The push constant ranges differ from the main pipeline, the camera set is rebound
             device.cmd_bind_descriptor_sets(
-                *command_buffer,
+                command_buffer,
                 vk::PipelineBindPoint::GRAPHICS,
-                pipeline.pipeline_layout,
+                layout,
                 0,
-                &[descriptors.descriptor_sets[i]],
+                &[self.descriptors.descriptor_sets[i % self.descriptors.descriptor_sets.len()]],
                 &[],
             );
+            self.stats.record_descriptor_bind();
+            for emitter in &particles.emitters {
+                record_gpu_particle_draw(
+                    device,
+                    command_buffer,
+                    particles.draw,
+                    emitter,
+                    particles.validator,
+                );
+            }
+        }
+    }
 
-            device.cmd_draw_indexed(*command_buffer, 3, 1, 0, 0, 0);
-
-            device.cmd_end_rendering(*command_buffer);
-            device.end_command_buffer(*command_buffer)?;
+    /// # Safety
+    /// The command buffer must be recording inside the main pass.
+    unsafe fn draw_fullscreen_passes(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        stage: FullscreenStage,
+        i: usize,
+    ) {
+        if let Some(passes) = self.fullscreen_passes {
+            unsafe {
+                passes.record(
+                    self.device,
+                    command_buffer,
+                    stage,
+                    i,
+                    self.swapchain.render_extent,
+                    self.stats,
+                )
+            };
         }
     }
+}
 
-    Ok(())
+fn subresource_range(aspect_mask: vk::ImageAspectFlags) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange::default()
+        .aspect_mask(aspect_mask)
+        .base_mip_level(0)
+        .level_count(vk::REMAINING_MIP_LEVELS)
+        .base_array_layer(0)
+        .layer_count(vk::REMAINING_ARRAY_LAYERS)
 }
@@ -1,4 +1,5 @@
 use ash::vk;
+use crate::log_targets;
 use log::debug;
 use flux_ecs::commands::Commands;
 use flux_ecs::resource::{Res, Resource};
@@ -12,9 +13,11 @@ pub struct CommandPools {
 impl Resource for CommandPools {}
 
 pub fn create_command_pools(device: Res<Device>, mut commands: Commands) -> Result<(), vk::Result> {
-    debug!("Creating command pools");
+    debug!(target: log_targets::COMMANDS, "Creating command pools");
 
+    // The frame command buffers are reset and re-recorded every frame
     let info = vk::CommandPoolCreateInfo::default()
+        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
         .queue_family_index(device.graphics_queue_index);
 
     let graphics_pool = unsafe {
@@ -41,7 +44,7 @@ pub fn destroy_command_pools(
     command_pools: Res<CommandPools>,
     mut commands: Commands,
 ) {
-    debug!("Destroying command pools");
+    debug!(target: log_targets::COMMANDS, "Destroying command pools");
 
     unsafe {
         device.destroy_command_pool(command_pools.graphics, None);
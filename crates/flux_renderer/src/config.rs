@@ -0,0 +1,329 @@
+use crate::instance::VALIDATION_ENABLED;
+use crate::log_targets;
+use crate::window::WindowDescriptor;
+use ash::vk;
+use flux_ecs::plugin::Plugin;
+use flux_ecs::resource::Resource;
+use flux_ecs::world::World;
+use log::{error, info, warn};
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Graphics options that can be changed without recompiling, see [`ConfigPlugin`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphicsSettings {
+    /// Synchronizes presentation with the display refresh to avoid tearing.
+    pub vsync: bool,
+    /// Enables the Vulkan validation layers, on by default in debug builds.
+    pub validation: bool,
+    /// Logs the output of `debugPrintfEXT` in shaders to the [`log_targets::SHADER_PRINTF`]
+    /// target, only takes effect with `validation`. On by default in debug builds.
+    pub shader_debug_printf: bool,
+    /// The resolution of the rendered image relative to the window size.
+    pub render_scale: f32,
+    /// The index of the physical device to use as enumerated by Vulkan, the most suitable device
+    /// is selected if unset or if the device is not suitable.
+    pub device_index: Option<usize>,
+    /// Usages required from the presented image in addition to `COLOR_ATTACHMENT`, e.g.
+    /// `TRANSFER_SRC` for screenshots. If the surface does not support them the frame is rendered
+    /// into an intermediate image with these usages and copied into the swapchain.
+    pub swapchain_usage: vk::ImageUsageFlags,
+    /// How long to wait for a swapchain image before the frame is skipped.
+    pub acquire_timeout: Duration,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            vsync: true,
+            validation: VALIDATION_ENABLED,
+            shader_debug_printf: VALIDATION_ENABLED,
+            render_scale: 1.0,
+            device_index: None,
+            swapchain_usage: vk::ImageUsageFlags::empty(),
+            acquire_timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Resource for GraphicsSettings {}
+
+impl GraphicsSettings {
+    /// The size of the rendered image for a window of the given size.
+    pub fn render_extent(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        let scale = |size: u32| ((size as f32 * self.render_scale).round() as u32).max(1);
+        (scale(width), scale(height))
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ConfigError {
+    #[error("missing value for {0}")]
+    MissingValue(String),
+    #[error("invalid value '{value}' for {key}")]
+    InvalidValue { key: String, value: String },
+}
+
+/// Configuration values parsed from the command line and the environment, unset values keep the
+/// current configuration.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigOverrides {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub vsync: Option<bool>,
+    pub validation: Option<bool>,
+    pub render_scale: Option<f32>,
+    pub device_index: Option<usize>,
+}
+
+impl ConfigOverrides {
+    /// Parses the arguments and environment of the current process.
+    pub fn from_process() -> Result<Self, ConfigError> {
+        Self::parse(std::env::args().skip(1), std::env::vars())
+    }
+
+    /// Parses `FLUX_*` environment variables and command line flags, flags take precedence.
+    ///
+    /// Supported flags are `--width <u32>`, `--height <u32>`, `--vsync`/`--no-vsync`,
+    /// `--validation`/`--no-validation`, `--render-scale <f32>` and `--device <index>`, flag
+    /// values may also be passed as `--flag=value`, e.g. `--vsync=false`. The environment
+    /// variables are `FLUX_WIDTH`, `FLUX_HEIGHT`, `FLUX_VSYNC`, `FLUX_VALIDATION`,
+    /// `FLUX_RENDER_SCALE` and `FLUX_DEVICE`. Unknown flags are ignored so applications can define
+    /// their own.
+    ///
+    /// Invalid flags are returned as an error, invalid environment variables are logged and
+    /// skipped since they may be set for another application.
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let mut overrides = Self::default();
+
+        for (key, value) in vars {
+            if let Err(err) = overrides.parse_var(&key, &value) {
+                warn!(target: log_targets::CONFIG, "Ignoring the environment variable: {err}");
+            }
+        }
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| ConfigError::MissingValue(flag.clone()))
+            };
+
+            match flag.as_str() {
+                "--width" => overrides.width = Some(parse_value(&flag, &value()?)?),
+                "--height" => overrides.height = Some(parse_value(&flag, &value()?)?),
+                "--vsync" => overrides.vsync = Some(parse_switch(&flag, inline_value.as_deref())?),
+                "--no-vsync" => overrides.vsync = Some(false),
+                "--validation" => {
+                    overrides.validation = Some(parse_switch(&flag, inline_value.as_deref())?)
+                }
+                "--no-validation" => overrides.validation = Some(false),
+                "--render-scale" => {
+                    overrides.render_scale = Some(parse_render_scale(&flag, &value()?)?)
+                }
+                "--device" => overrides.device_index = Some(parse_value(&flag, &value()?)?),
+                _ => {}
+            }
+        }
+
+        Ok(overrides)
+    }
+
+    fn parse_var(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        match key {
+            "FLUX_WIDTH" => self.width = Some(parse_value(key, value)?),
+            "FLUX_HEIGHT" => self.height = Some(parse_value(key, value)?),
+            "FLUX_VSYNC" => self.vsync = Some(parse_bool(key, value)?),
+            "FLUX_VALIDATION" => self.validation = Some(parse_bool(key, value)?),
+            "FLUX_RENDER_SCALE" => self.render_scale = Some(parse_render_scale(key, value)?),
+            "FLUX_DEVICE" => self.device_index = Some(parse_value(key, value)?),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Applies the overrides to the [`WindowDescriptor`] and [`GraphicsSettings`] of the world,
+    /// inserting default resources if they do not exist yet.
+    pub fn apply(&self, world: &mut World) {
+        if world.get_resource::<WindowDescriptor>().is_none() {
+            world.add_resource(WindowDescriptor::default());
+        }
+        if world.get_resource::<GraphicsSettings>().is_none() {
+            world.add_resource(GraphicsSettings::default());
+        }
+
+        let window = world
+            .get_resource_mut::<WindowDescriptor>()
+            .expect("The window descriptor was just inserted");
+        if let Some(width) = self.width {
+            window.width = width;
+        }
+        if let Some(height) = self.height {
+            window.height = height;
+        }
+
+        let graphics = world
+            .get_resource_mut::<GraphicsSettings>()
+            .expect("The graphics settings were just inserted");
+        if let Some(vsync) = self.vsync {
+            graphics.vsync = vsync;
+        }
+        if let Some(validation) = self.validation {
+            graphics.validation = validation;
+        }
+        if let Some(render_scale) = self.render_scale {
+            graphics.render_scale = render_scale;
+        }
+        if let Some(device_index) = self.device_index {
+            graphics.device_index = Some(device_index);
+        }
+    }
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, ConfigError> {
+    value.trim().parse().map_err(|_| ConfigError::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+    })
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, ConfigError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "on" | "yes" => Ok(true),
+        "0" | "false" | "off" | "no" => Ok(false),
+        _ => Err(ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        }),
+    }
+}
+
+/// Parses an on/off flag, which is on unless a value like `--vsync=false` turns it off.
+fn parse_switch(flag: &str, inline_value: Option<&str>) -> Result<bool, ConfigError> {
+    inline_value.map_or(Ok(true), |value| parse_bool(flag, value))
+}
+
+fn parse_render_scale(key: &str, value: &str) -> Result<f32, ConfigError> {
+    let scale: f32 = parse_value(key, value)?;
+    if scale.is_finite() && scale > 0.0 {
+        Ok(scale)
+    } else {
+        Err(ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+/// Configures the renderer from command line flags and environment variables, see
+/// [`ConfigOverrides::parse`].
+///
+/// Add it before the renderer plugin, values the application inserted itself are only replaced
+/// for the options that were actually passed.
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn init(&self, world: &mut World) {
+        match ConfigOverrides::from_process() {
+            Ok(overrides) => {
+                if overrides != ConfigOverrides::default() {
+                    info!(target: log_targets::CONFIG, "Applying configuration {overrides:?}");
+                }
+                overrides.apply(world);
+            }
+            Err(err) => {
+                error!(target: log_targets::CONFIG, "Ignoring the configuration: {err}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn flags_take_precedence_over_environment() {
+        let vars = [
+            ("FLUX_WIDTH".to_string(), "800".to_string()),
+            ("FLUX_VSYNC".to_string(), "off".to_string()),
+            ("FLUX_DEVICE".to_string(), "1".to_string()),
+        ];
+
+        let overrides = ConfigOverrides::parse(
+            args(&["--width=1920", "--vsync", "--render-scale", "0.5", "--unknown"]),
+            vars,
+        )
+        .unwrap();
+
+        assert_eq!(
+            overrides,
+            ConfigOverrides {
+                width: Some(1920),
+                height: None,
+                vsync: Some(true),
+                validation: None,
+                render_scale: Some(0.5),
+                device_index: Some(1),
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        assert_eq!(
+            ConfigOverrides::parse(args(&["--height"]), []),
+            Err(ConfigError::MissingValue("--height".to_string()))
+        );
+        assert!(ConfigOverrides::parse(args(&["--render-scale=-1"]), []).is_err());
+        assert!(ConfigOverrides::parse(args(&["--vsync=maybe"]), []).is_err());
+    }
+
+    #[test]
+    fn switches_accept_inline_values() {
+        let overrides =
+            ConfigOverrides::parse(args(&["--vsync=false", "--validation=on"]), []).unwrap();
+        assert_eq!(overrides.vsync, Some(false));
+        assert_eq!(overrides.validation, Some(true));
+
+        let overrides = ConfigOverrides::parse(args(&["--vsync", "--validation=0"]), []).unwrap();
+        assert_eq!(overrides.vsync, Some(true));
+        assert_eq!(overrides.validation, Some(false));
+    }
+
+    #[test]
+    fn invalid_environment_variables_are_skipped() {
+        let vars = [
+            ("FLUX_WIDTH".to_string(), "abc".to_string()),
+            ("FLUX_HEIGHT".to_string(), "720".to_string()),
+            ("FLUX_VALIDATION".to_string(), "maybe".to_string()),
+            ("FLUX_VSYNC".to_string(), "off".to_string()),
+        ];
+
+        let overrides = ConfigOverrides::parse([], vars).unwrap();
+
+        assert_eq!(
+            overrides,
+            ConfigOverrides {
+                height: Some(720),
+                vsync: Some(false),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use ash::vk;
+use flux_ecs::resource::Resource;
+use std::sync::{Mutex, PoisonError};
+
+/// The regions of the swapchain image that changed this frame.
+///
+/// Passes that only update part of the screen, e.g. UI overlays, report their regions here and
+/// the presentation passes them on with `VK_KHR_incremental_present` if the device supports it,
+/// letting the compositor skip the rest of the image. A frame without reported damage or with
+/// [`PresentDamage::mark_full`] presents the whole image.
+#[derive(Debug, Default)]
+pub struct PresentDamage {
+    state: Mutex<DamageState>,
+}
+
+#[derive(Debug, Default)]
+struct DamageState {
+    full: bool,
+    rects: Vec<vk::Rect2D>,
+}
+
+impl Resource for PresentDamage {}
+
+impl PresentDamage {
+    pub fn add_rect(&self, rect: vk::Rect2D) {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .rects
+            .push(rect);
+    }
+
+    /// Presents the whole image this frame regardless of the reported regions.
+    pub fn mark_full(&self) {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .full = true;
+    }
+
+    /// Takes the damage of the frame clamped to `extent`, an empty list means the whole image
+    /// changed.
+    pub fn take(&self, extent: vk::Extent2D) -> Vec<vk::RectLayerKHR> {
+        let state = std::mem::take(&mut *self.state.lock().unwrap_or_else(PoisonError::into_inner));
+        if state.full {
+            return Vec::new();
+        }
+
+        state
+            .rects
+            .into_iter()
+            .filter_map(|rect| clamp(rect, extent))
+            .map(|rect| vk::RectLayerKHR {
+                offset: rect.offset,
+                extent: rect.extent,
+                layer: 0,
+            })
+            .collect()
+    }
+}
+
+fn clamp(rect: vk::Rect2D, extent: vk::Extent2D) -> Option<vk::Rect2D> {
+    let x = i64::from(rect.offset.x).clamp(0, i64::from(extent.width));
+    let y = i64::from(rect.offset.y).clamp(0, i64::from(extent.height));
+    let right = (i64::from(rect.offset.x) + i64::from(rect.extent.width)).min(extent.width.into());
+    let bottom =
+        (i64::from(rect.offset.y) + i64::from(rect.extent.height)).min(extent.height.into());
+
+    (right > x && bottom > y).then(|| vk::Rect2D {
+        offset: vk::Offset2D {
+            x: x as i32,
+            y: y as i32,
+        },
+        extent: vk::Extent2D {
+            width: (right - x) as u32,
+            height: (bottom - y) as u32,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> vk::Rect2D {
+        vk::Rect2D {
+            offset: vk::Offset2D { x, y },
+            extent: vk::Extent2D { width, height },
+        }
+    }
+
+    #[test]
+    fn damage_is_clamped_and_reset() {
+        let extent = vk::Extent2D {
+            width: 100,
+            height: 50,
+        };
+        let damage = PresentDamage::default();
+        damage.add_rect(rect(-10, 40, 20, 20));
+        damage.add_rect(rect(200, 0, 10, 10));
+
+        let rects = damage.take(extent);
+        assert_eq!(rects.len(), 1);
+        assert_eq!(rects[0].offset, vk::Offset2D { x: 0, y: 40 });
+        assert_eq!(
+            rects[0].extent,
+            vk::Extent2D {
+                width: 10,
+                height: 10
+            }
+        );
+        assert!(damage.take(extent).is_empty());
+
+        damage.add_rect(rect(0, 0, 10, 10));
+        damage.mark_full();
+        assert!(damage.take(extent).is_empty());
+    }
+}
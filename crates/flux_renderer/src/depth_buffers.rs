@@ -1,16 +1,19 @@
+use crate::allocator::{Allocation, GpuAllocator};
 use crate::device::{Device, PhysicalDevice};
 use crate::image::{create_image, create_image_view};
 use crate::instance::VulkanInstance;
+use crate::layout_tracker::ImageLayoutTracker;
 use crate::swapchain::Swapchain;
 use ash::vk;
 use flux_ecs::commands::Commands;
 use flux_ecs::resource::{Res, Resource};
+use crate::log_targets;
 use log::debug;
 
 pub struct DepthBuffers {
     pub depth_image: vk::Image,
     pub depth_image_view: vk::ImageView,
-    pub depth_image_memory: vk::DeviceMemory,
+    pub depth_image_memory: Allocation,
     pub depth_format: vk::Format,
 }
 
@@ -20,45 +23,114 @@ pub fn create_depth_buffers(
     instance: Res<VulkanInstance>,
     physical_device: Res<PhysicalDevice>,
     device: Res<Device>,
-    swapchain: Res<Swapchain>,
+    allocator: Res<GpuAllocator>,
+    swapchain: Option<Res<Swapchain>>,
+    layouts: Res<ImageLayoutTracker>,
     mut commands: Commands,
 ) -> Result<(), vk::Result> {
-    debug!("Creating depth buffers");
+    let Some(swapchain) = swapchain else {
+        return Ok(());
+    };
 
-    let depth_format = get_depth_format(&instance, &physical_device).unwrap();
+    debug!(target: log_targets::RESOURCES, "Creating depth buffers");
 
-    let (depth_image, depth_image_memory) = create_image(
+    let depth_buffers = build_depth_buffers(
         &instance,
         &physical_device,
         &device,
-        swapchain.extent.width,
-        swapchain.extent.height,
+        &allocator,
+        swapchain.render_extent,
+        &layouts,
+    )?;
+
+    commands.insert_resource(depth_buffers);
+
+    Ok(())
+}
+
+/// Creates a depth buffer with the extent of a swapchain and registers it with the layout tracker.
+pub(crate) fn build_depth_buffers(
+    instance: &VulkanInstance,
+    physical_device: &PhysicalDevice,
+    device: &Device,
+    allocator: &GpuAllocator,
+    extent: vk::Extent2D,
+    layouts: &ImageLayoutTracker,
+) -> Result<DepthBuffers, vk::Result> {
+    let depth_format = get_depth_format(instance, physical_device).unwrap();
+
+    let (depth_image, depth_image_memory) = create_image(
+        device,
+        allocator,
+        extent.width,
+        extent.height,
         depth_format,
         vk::ImageTiling::OPTIMAL,
         vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
     )?;
 
-    let depth_image_view = create_image_view(
-        &device,
+    let depth_image_view =
+        match create_image_view(device, depth_image, depth_format, vk::ImageAspectFlags::DEPTH) {
+            Ok(view) => view,
+            Err(err) => {
+                unsafe { device.destroy_image(depth_image, None) };
+                allocator.free(device, depth_image_memory);
+                return Err(err);
+            }
+        };
+
+    layouts.register(
         depth_image,
-        depth_format,
         vk::ImageAspectFlags::DEPTH,
-    )?;
+        1,
+        1,
+        vk::ImageLayout::UNDEFINED,
+    );
 
-    let depth_buffers = DepthBuffers {
+    Ok(DepthBuffers {
         depth_image,
         depth_image_view,
         depth_image_memory,
         depth_format,
+    })
+}
+
+/// # Safety
+/// The depth buffer must no longer be in use by the device.
+pub(crate) unsafe fn destroy_depth_buffer_objects(
+    device: &Device,
+    allocator: &GpuAllocator,
+    depth_buffers: &DepthBuffers,
+    layouts: &ImageLayoutTracker,
+) {
+    layouts.unregister(depth_buffers.depth_image);
+    unsafe {
+        device.destroy_image_view(depth_buffers.depth_image_view, None);
+        device.destroy_image(depth_buffers.depth_image, None);
+    }
+    allocator.free(device, depth_buffers.depth_image_memory);
+}
+
+pub fn destroy_depth_buffers(
+    device: Res<Device>,
+    allocator: Res<GpuAllocator>,
+    depth_buffers: Option<Res<DepthBuffers>>,
+    layouts: Res<ImageLayoutTracker>,
+    mut commands: Commands,
+) {
+    let Some(depth_buffers) = depth_buffers else {
+        return;
     };
 
-    commands.insert_resource(depth_buffers);
+    debug!(target: log_targets::RESOURCES, "Destroying depth buffers");
 
-    Ok(())
+    unsafe { destroy_depth_buffer_objects(&device, &allocator, &depth_buffers, &layouts) };
+
+    commands.remove_resource::<DepthBuffers>();
 }
 
-fn get_depth_format(
+pub(crate) fn get_depth_format(
     instance: &VulkanInstance,
     physical_device: &PhysicalDevice,
 ) -> Option<vk::Format> {
@@ -1,5 +1,6 @@
 use crate::buffers::{UniformBufferObject, UniformBuffers};
 use crate::device::Device;
+use crate::light_clusters::LightClusterBuffers;
 use crate::pipeline::Pipeline;
 use crate::swapchain::Swapchain;
 use ash::vk;
@@ -15,13 +16,26 @@ impl Resource for Descriptors {}
 
 pub fn create_descriptors(
     device: Res<Device>,
-    pipeline: Res<Pipeline>,
-    swapchain: Res<Swapchain>,
-    uniform_buffer: Res<UniformBuffers>,
+    pipeline: Option<Res<Pipeline>>,
+    swapchain: Option<Res<Swapchain>>,
+    uniform_buffer: Option<Res<UniformBuffers>>,
+    light_buffers: Option<Res<LightClusterBuffers>>,
     mut commands: Commands,
 ) -> Result<(), vk::Result> {
+    let (Some(pipeline), Some(swapchain), Some(uniform_buffer), Some(light_buffers)) =
+        (pipeline, swapchain, uniform_buffer, light_buffers)
+    else {
+        return Ok(());
+    };
+
     let pool = create_descriptor_pool(&device, &swapchain)?;
-    let sets = create_descriptor_sets(&device, &pipeline, &swapchain, pool, &uniform_buffer)?;
+    let sets = create_descriptor_sets(
+        &device,
+        &pipeline,
+        &swapchain,
+        pool,
+        (&uniform_buffer, &light_buffers),
+    )?;
 
     commands.insert_resource(Descriptors {
         descriptor_pool: pool,
@@ -31,6 +45,21 @@ pub fn create_descriptors(
     Ok(())
 }
 
+/// Destroys the descriptor pool, which frees its descriptor sets.
+pub fn destroy_descriptors(
+    device: Res<Device>,
+    descriptors: Option<Res<Descriptors>>,
+    mut commands: Commands,
+) {
+    let Some(descriptors) = descriptors else {
+        return;
+    };
+
+    unsafe { device.destroy_descriptor_pool(descriptors.descriptor_pool, None) };
+
+    commands.remove_resource::<Descriptors>();
+}
+
 fn create_descriptor_pool(
     device: &Device,
     swapchain: &Swapchain,
@@ -43,7 +72,11 @@ fn create_descriptor_pool(
         .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
         .descriptor_count(swapchain.image_views.len() as u32);
 
-    let pool_sizes = &[ubo_size];
+    let light_size = vk::DescriptorPoolSize::default()
+        .ty(vk::DescriptorType::STORAGE_BUFFER)
+        .descriptor_count(swapchain.image_views.len() as u32 * 3);
+
+    let pool_sizes = &[ubo_size, light_size];
     let info = vk::DescriptorPoolCreateInfo::default()
         .pool_sizes(pool_sizes)
         .max_sets(swapchain.image_views.len() as u32);
@@ -56,7 +89,7 @@ fn create_descriptor_sets(
     pipeline: &Pipeline,
     swapchain: &Swapchain,
     pool: vk::DescriptorPool,
-    uniform_buffers: &UniformBuffers,
+    (uniform_buffers, light_buffers): (&UniformBuffers, &LightClusterBuffers),
 ) -> Result<Vec<vk::DescriptorSet>, vk::Result> {
     let layouts = vec![pipeline.descriptor_set_layout; swapchain.image_views.len()];
     let info = vk::DescriptorSetAllocateInfo::default()
@@ -79,7 +112,24 @@ fn create_descriptor_sets(
             .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
             .buffer_info(buffer_info);
 
-        unsafe { device.update_descriptor_sets(&[ubo_write], &[] as &[vk::CopyDescriptorSet]) };
+        // The cluster grid, light indices and lights at bindings 2 to 4
+        let light_infos = light_buffers.buffers(i).map(|buffer| {
+            [vk::DescriptorBufferInfo::default()
+                .buffer(buffer)
+                .offset(0)
+                .range(vk::WHOLE_SIZE)]
+        });
+        let light_writes = light_infos.iter().zip(2..).map(|(info, binding)| {
+            vk::WriteDescriptorSet::default()
+                .dst_set(sets[i])
+                .dst_binding(binding)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(info)
+        });
+        let writes: Vec<_> = std::iter::once(ubo_write).chain(light_writes).collect();
+
+        unsafe { device.update_descriptor_sets(&writes, &[] as &[vk::CopyDescriptorSet]) };
     }
 
     Ok(sets)
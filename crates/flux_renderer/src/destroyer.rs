@@ -0,0 +1,179 @@
+use crate::allocator::{Allocation, GpuAllocator};
+use crate::device::Device;
+use crate::log_targets;
+use ash::vk;
+use flux_ecs::resource::{Res, Resource};
+use log::debug;
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// A Vulkan object that is no longer used by new frames but may still be used by frames in
+/// flight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetiredHandle {
+    Buffer(vk::Buffer, Allocation),
+    Image(vk::Image, Allocation),
+    ImageView(vk::ImageView),
+    Framebuffer(vk::Framebuffer),
+    Pipeline(vk::Pipeline),
+    PipelineLayout(vk::PipelineLayout),
+    DescriptorSetLayout(vk::DescriptorSetLayout),
+    /// Destroying the pool frees its descriptor sets.
+    DescriptorPool(vk::DescriptorPool),
+}
+
+impl RetiredHandle {
+    /// # Safety
+    /// The device must no longer use the handle.
+    pub(crate) unsafe fn destroy(self, device: &Device, allocator: &GpuAllocator) {
+        match self {
+            RetiredHandle::Buffer(buffer, memory) => {
+                unsafe { device.destroy_buffer(buffer, None) };
+                allocator.free(device, memory);
+            }
+            RetiredHandle::Image(image, memory) => {
+                unsafe { device.destroy_image(image, None) };
+                allocator.free(device, memory);
+            }
+            RetiredHandle::ImageView(view) => unsafe { device.destroy_image_view(view, None) },
+            RetiredHandle::Framebuffer(framebuffer) => unsafe {
+                device.destroy_framebuffer(framebuffer, None)
+            },
+            RetiredHandle::Pipeline(pipeline) => unsafe { device.destroy_pipeline(pipeline, None) },
+            RetiredHandle::PipelineLayout(layout) => unsafe {
+                device.destroy_pipeline_layout(layout, None)
+            },
+            RetiredHandle::DescriptorSetLayout(layout) => unsafe {
+                device.destroy_descriptor_set_layout(layout, None)
+            },
+            RetiredHandle::DescriptorPool(pool) => unsafe {
+                device.destroy_descriptor_pool(pool, None)
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+struct DestroyerState {
+    /// The index of the next submitted frame.
+    next_frame: u64,
+    /// The retired handles with the index of the frame that was next when they were retired,
+    /// in retirement order.
+    pending: VecDeque<(u64, RetiredHandle)>,
+}
+
+/// Destroys retired Vulkan objects once every frame submitted before their retirement finished.
+///
+/// The frame driver reports submitted frames and waits for the fence of a frame before reusing
+/// its slot, which frees the handles that frame could still use.
+#[derive(Default)]
+pub struct DeferredDestroyer {
+    state: Mutex<DestroyerState>,
+}
+
+impl Resource for DeferredDestroyer {}
+
+impl DeferredDestroyer {
+    fn state(&self) -> MutexGuard<'_, DestroyerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Queues the handle for destruction, it must not be used by frames recorded afterward.
+    pub fn retire(&self, handle: RetiredHandle) {
+        let mut state = self.state();
+        let frame = state.next_frame;
+        state.pending.push_back((frame, handle));
+    }
+
+    /// The number of handles waiting for their frames to finish.
+    pub fn pending(&self) -> usize {
+        self.state().pending.len()
+    }
+
+    /// Returns the index of the submitted frame.
+    pub(crate) fn frame_submitted(&self) -> u64 {
+        let mut state = self.state();
+        state.next_frame += 1;
+        state.next_frame - 1
+    }
+
+    /// Takes the handles that no frame up to `completed` can use anymore, frames complete in
+    /// submission order.
+    fn take_completed(&self, completed: u64) -> Vec<RetiredHandle> {
+        let mut state = self.state();
+        let ready = state
+            .pending
+            .iter()
+            .take_while(|(frame, _)| *frame <= completed + 1)
+            .count();
+        state
+            .pending
+            .drain(..ready)
+            .map(|(_, handle)| handle)
+            .collect()
+    }
+
+    /// Destroys the handles retired before frame `completed + 1` was submitted, call it once
+    /// the fence of frame `completed` is signaled.
+    pub(crate) fn frame_completed(
+        &self,
+        completed: u64,
+        device: &Device,
+        allocator: &GpuAllocator,
+    ) {
+        for handle in self.take_completed(completed) {
+            unsafe { handle.destroy(device, allocator) };
+        }
+    }
+
+    /// Destroys every pending handle.
+    ///
+    /// # Safety
+    /// The device must be idle.
+    pub(crate) unsafe fn destroy_all(&self, device: &Device, allocator: &GpuAllocator) {
+        let pending: Vec<_> = self.state().pending.drain(..).collect();
+        if !pending.is_empty() {
+            debug!(
+                target: log_targets::RESOURCES,
+                "Destroying {} retired handles",
+                pending.len()
+            );
+        }
+        for (_, handle) in pending {
+            unsafe { handle.destroy(device, allocator) };
+        }
+    }
+}
+
+/// Destroys the handles still waiting for their frames, runs once the device is idle.
+pub fn destroy_retired_handles(
+    device: Res<Device>,
+    allocator: Res<GpuAllocator>,
+    destroyer: Res<DeferredDestroyer>,
+) {
+    unsafe { destroyer.destroy_all(&device, &allocator) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    #[test]
+    fn handles_wait_for_the_frames_submitted_before_their_retirement() {
+        let destroyer = DeferredDestroyer::default();
+        let view = |raw| RetiredHandle::ImageView(vk::ImageView::from_raw(raw));
+
+        // Retired before any submission, freed once any frame completed
+        destroyer.retire(view(1));
+        assert_eq!(destroyer.frame_submitted(), 0);
+        destroyer.retire(view(2));
+        assert_eq!(destroyer.frame_submitted(), 1);
+        destroyer.retire(view(3));
+
+        assert_eq!(destroyer.take_completed(0), [view(1), view(2)]);
+        assert_eq!(destroyer.pending(), 1);
+        assert_eq!(destroyer.take_completed(0), []);
+        assert_eq!(destroyer.take_completed(1), [view(3)]);
+    }
+}
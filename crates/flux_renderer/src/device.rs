@@ -1,9 +1,13 @@
+use crate::capabilities::RendererCapabilities;
+use crate::config::GraphicsSettings;
 use crate::instance::VulkanInstance;
 use crate::surface::VulkanSurface;
-use ash::{khr, vk};
+use ash::{ext, google, khr, vk};
 use flux_ecs::commands::Commands;
 use flux_ecs::resource::{Res, Resource};
-use log::{debug, info};
+use crate::log_targets;
+use crate::progress::InitializationProgress;
+use log::{debug, info, warn};
 use std::collections::HashSet;
 use std::ffi::CStr;
 use std::fmt::{Debug, Display};
@@ -14,6 +18,14 @@ use thiserror::Error;
 #[derive(Debug, Clone)]
 pub struct DeviceRequirements {
     pub extensions: Vec<&'static CStr>,
+    /// Extensions that are enabled if the device supports them, the result is recorded in the
+    /// [`RendererCapabilities`].
+    pub optional_extensions: Vec<&'static CStr>,
+    /// Vulkan 1.1 to 1.3 features the device must support.
+    pub features: DeviceFeatures,
+    /// Features that are enabled if the device supports them, the enabled features are recorded
+    /// in the [`RendererCapabilities`].
+    pub optional_features: DeviceFeatures,
     pub prefer_discrete_gpu: bool,
 }
 
@@ -22,10 +34,20 @@ impl Default for DeviceRequirements {
         Self {
             extensions: vec![
                 khr::swapchain::NAME,
-                khr::dynamic_rendering::NAME,
                 #[cfg(any(target_os = "macos", target_os = "ios"))]
                 khr::portability_subset::NAME,
             ],
+            optional_extensions: vec![
+                khr::dynamic_rendering::NAME,
+                khr::incremental_present::NAME,
+                ext::descriptor_indexing::NAME,
+                ext::mesh_shader::NAME,
+                ext::memory_budget::NAME,
+                google::display_timing::NAME,
+                khr::shader_non_semantic_info::NAME,
+            ],
+            features: DeviceFeatures::default(),
+            optional_features: DeviceFeatures::all(),
             prefer_discrete_gpu: true,
         }
     }
@@ -33,6 +55,172 @@ impl Default for DeviceRequirements {
 
 impl Resource for DeviceRequirements {}
 
+/// Features of the Vulkan 1.1, 1.2 and 1.3 feature structures, chained into the creation of the
+/// logical device.
+///
+/// Devices older than Vulkan 1.3 only get dynamic rendering through its extension, the other
+/// features are reported as unsupported on devices older than the version that made them core.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DeviceFeatures {
+    /// `shaderDrawParameters`, Vulkan 1.1.
+    pub shader_draw_parameters: bool,
+    /// `timelineSemaphore`, Vulkan 1.2.
+    pub timeline_semaphores: bool,
+    /// `descriptorIndexing` with runtime sized, partially bound, variably sized and non-uniformly
+    /// indexed sampled image arrays, Vulkan 1.2.
+    pub descriptor_indexing: bool,
+    /// `bufferDeviceAddress`, Vulkan 1.2.
+    pub buffer_device_address: bool,
+    /// `dynamicRendering`, Vulkan 1.3 or `VK_KHR_dynamic_rendering`.
+    pub dynamic_rendering: bool,
+    /// `synchronization2`, Vulkan 1.3.
+    pub synchronization2: bool,
+}
+
+impl DeviceFeatures {
+    const NAMES: [&'static str; 6] = [
+        "shader_draw_parameters",
+        "timeline_semaphores",
+        "descriptor_indexing",
+        "buffer_device_address",
+        "dynamic_rendering",
+        "synchronization2",
+    ];
+
+    pub const fn all() -> Self {
+        Self::from_flags([true; 6])
+    }
+
+    /// The features enabled in both sets.
+    pub fn intersection(self, other: Self) -> Self {
+        let (flags, other) = (self.flags(), other.flags());
+        Self::from_flags(std::array::from_fn(|index| flags[index] && other[index]))
+    }
+
+    /// The features enabled in either set.
+    pub fn union(self, other: Self) -> Self {
+        let (flags, other) = (self.flags(), other.flags());
+        Self::from_flags(std::array::from_fn(|index| flags[index] || other[index]))
+    }
+
+    /// The name of the first feature of this set that is not `supported`.
+    pub fn first_missing(self, supported: Self) -> Option<&'static str> {
+        let missing = self.flags().into_iter().zip(supported.flags());
+        Self::NAMES
+            .into_iter()
+            .zip(missing)
+            .find_map(|(name, (required, supported))| (required && !supported).then_some(name))
+    }
+
+    const fn from_flags(flags: [bool; 6]) -> Self {
+        Self {
+            shader_draw_parameters: flags[0],
+            timeline_semaphores: flags[1],
+            descriptor_indexing: flags[2],
+            buffer_device_address: flags[3],
+            dynamic_rendering: flags[4],
+            synchronization2: flags[5],
+        }
+    }
+
+    fn flags(self) -> [bool; 6] {
+        [
+            self.shader_draw_parameters,
+            self.timeline_semaphores,
+            self.descriptor_indexing,
+            self.buffer_device_address,
+            self.dynamic_rendering,
+            self.synchronization2,
+        ]
+    }
+}
+
+/// The feature structures chained into [`vk::PhysicalDeviceFeatures2`], to query the supported
+/// [`DeviceFeatures`] and to enable them. The structures of a chain must only be pushed once.
+#[derive(Default)]
+struct FeatureChain {
+    vulkan11: vk::PhysicalDeviceVulkan11Features<'static>,
+    vulkan12: vk::PhysicalDeviceVulkan12Features<'static>,
+    vulkan13: vk::PhysicalDeviceVulkan13Features<'static>,
+    /// Dynamic rendering of devices older than Vulkan 1.3.
+    dynamic_rendering: vk::PhysicalDeviceDynamicRenderingFeatures<'static>,
+}
+
+impl FeatureChain {
+    fn enabling(features: DeviceFeatures) -> Self {
+        let indexing = features.descriptor_indexing;
+        Self {
+            vulkan11: vk::PhysicalDeviceVulkan11Features::default()
+                .shader_draw_parameters(features.shader_draw_parameters),
+            vulkan12: vk::PhysicalDeviceVulkan12Features::default()
+                .timeline_semaphore(features.timeline_semaphores)
+                .buffer_device_address(features.buffer_device_address)
+                .descriptor_indexing(indexing)
+                .runtime_descriptor_array(indexing)
+                .descriptor_binding_partially_bound(indexing)
+                .descriptor_binding_variable_descriptor_count(indexing)
+                .shader_sampled_image_array_non_uniform_indexing(indexing),
+            vulkan13: vk::PhysicalDeviceVulkan13Features::default()
+                .dynamic_rendering(features.dynamic_rendering)
+                .synchronization2(features.synchronization2),
+            dynamic_rendering: vk::PhysicalDeviceDynamicRenderingFeatures::default()
+                .dynamic_rendering(features.dynamic_rendering),
+        }
+    }
+
+    /// The features set in the structures that are chained for the API version.
+    fn features(&self, api_version: u32) -> DeviceFeatures {
+        let core_1_2 = api_version >= vk::API_VERSION_1_2;
+        let core_1_3 = api_version >= vk::API_VERSION_1_3;
+        let vulkan12 = &self.vulkan12;
+        let dynamic_rendering = if core_1_3 {
+            self.vulkan13.dynamic_rendering
+        } else {
+            self.dynamic_rendering.dynamic_rendering
+        };
+
+        DeviceFeatures {
+            shader_draw_parameters: core_1_2 && self.vulkan11.shader_draw_parameters == vk::TRUE,
+            timeline_semaphores: core_1_2 && vulkan12.timeline_semaphore == vk::TRUE,
+            descriptor_indexing: core_1_2
+                && [
+                    vulkan12.descriptor_indexing,
+                    vulkan12.runtime_descriptor_array,
+                    vulkan12.descriptor_binding_partially_bound,
+                    vulkan12.descriptor_binding_variable_descriptor_count,
+                    vulkan12.shader_sampled_image_array_non_uniform_indexing,
+                ]
+                .iter()
+                .all(|&feature| feature == vk::TRUE),
+            buffer_device_address: core_1_2 && vulkan12.buffer_device_address == vk::TRUE,
+            dynamic_rendering: dynamic_rendering == vk::TRUE,
+            synchronization2: core_1_3 && self.vulkan13.synchronization2 == vk::TRUE,
+        }
+    }
+
+    /// Chains the structures valid for the API version, the Vulkan 1.1 and 1.2 structures need a
+    /// Vulkan 1.2 device. `dynamic_rendering_extension` chains the dynamic rendering extension
+    /// structure on devices older than Vulkan 1.3.
+    fn push<'a>(
+        &'a mut self,
+        mut features: vk::PhysicalDeviceFeatures2<'a>,
+        api_version: u32,
+        dynamic_rendering_extension: bool,
+    ) -> vk::PhysicalDeviceFeatures2<'a> {
+        if api_version >= vk::API_VERSION_1_2 {
+            features = features
+                .push_next(&mut self.vulkan11)
+                .push_next(&mut self.vulkan12);
+        }
+        if api_version >= vk::API_VERSION_1_3 {
+            features = features.push_next(&mut self.vulkan13);
+        } else if dynamic_rendering_extension {
+            features = features.push_next(&mut self.dynamic_rendering);
+        }
+        features
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum SuitabilityError {
     #[error("device {device:?} does not support required queue family: {queue_family:?}")]
@@ -99,11 +287,12 @@ impl Deref for PhysicalDevice {
 
 pub fn create_physical_device(
     instance: Res<VulkanInstance>,
-    surface: Res<VulkanSurface>,
+    surface: Option<Res<VulkanSurface>>,
     device_requirements: Option<Res<DeviceRequirements>>,
+    graphics_settings: Option<Res<GraphicsSettings>>,
     mut commands: Commands,
 ) -> Result<(), NoPhysicalDevicesFoundError> {
-    info!("Selecting a physical device");
+    info!(target: log_targets::DEVICE, "Selecting a physical device");
     let physical_devices = unsafe {
         instance
             .enumerate_physical_devices()
@@ -114,43 +303,66 @@ pub fn create_physical_device(
         .map(|res| res.into_inner())
         .unwrap_or_default();
 
-    let best_device_evaluation = physical_devices
+    let mut evaluations = physical_devices
         .iter()
-        .map(|&device| {
-            evaluate_physical_device(
+        .enumerate()
+        .filter_map(|(index, &device)| {
+            match evaluate_physical_device(
                 &instance.entry,
                 &instance,
                 device,
-                **surface,
+                surface.as_ref().map(|surface| surface.surface),
                 &device_requirements,
-            )
-        })
-        .filter_map(|evaluation| match evaluation {
-            Ok(evaluation) => Some(evaluation),
-            Err(err) => {
-                debug!("Physical device is not suitable: {err}");
-                None
+            ) {
+                Ok(evaluation) => Some((index, evaluation)),
+                Err(err) => {
+                    debug!(
+                        target: log_targets::DEVICE,
+                        "Physical device {index} is not suitable: {err}"
+                    );
+                    None
+                }
             }
         })
-        .max_by_key(|evaluation| evaluation.score)
+        .collect::<Vec<_>>();
+
+    let requested_index = graphics_settings.and_then(|settings| settings.device_index);
+    let requested = requested_index
+        .and_then(|requested| evaluations.iter().position(|(index, _)| *index == requested));
+    if let (Some(requested_index), None) = (requested_index, requested) {
+        warn!(
+            target: log_targets::DEVICE,
+            "Physical device {requested_index} is not available or not suitable, selecting the \
+             best device instead"
+        );
+    }
+
+    let selected = requested
+        .or_else(|| {
+            evaluations
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, (_, evaluation))| evaluation.score)
+                .map(|(position, _)| position)
+        })
         .ok_or(NoPhysicalDevicesFoundError)?;
+    let (_, best_device_evaluation) = evaluations.swap_remove(selected);
 
-    info!(
-        "Best physical device found: {0:?}",
-        unsafe {
-            CStr::from_ptr(
-                best_device_evaluation
-                    .physical_device
-                    .properties
-                    .device_name
-                    .as_ptr(),
-            )
-        }
-        .to_str()
-        .unwrap()
-    );
+    let name = unsafe {
+        CStr::from_ptr(
+            best_device_evaluation
+                .physical_device
+                .properties
+                .device_name
+                .as_ptr(),
+        )
+    }
+    .to_string_lossy()
+    .into_owned();
+    info!(target: log_targets::DEVICE, "Best physical device found: {name:?}");
 
     commands.insert_resource(best_device_evaluation.physical_device);
+    commands.send_event(InitializationProgress::DeviceSelected { name });
 
     Ok(())
 }
@@ -164,7 +376,7 @@ fn evaluate_physical_device(
     entry: &ash::Entry,
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
-    surface: vk::SurfaceKHR,
+    surface: Option<vk::SurfaceKHR>,
     device_requirements: &DeviceRequirements,
 ) -> Result<DeviceEvaluation, SuitabilityError> {
     let properties = unsafe {
@@ -180,13 +392,28 @@ fn evaluate_physical_device(
             .to_string()
     };
 
-    debug!("Checking suitability of physical device: {0:}", &name);
+    debug!(
+        target: log_targets::DEVICE,
+        "Checking suitability of physical device: {0:}",
+        &name
+    );
 
     let indices = QueueFamilyIndices::get(entry, instance, physical_device, surface)?;
     check_required_device_extensions(instance, physical_device, &device_requirements.extensions)?;
-    check_required_features(instance, physical_device)?;
-    let (capabilities, formats, present_modes) =
-        query_swapchain_support(entry, instance, physical_device, surface)?;
+    check_required_features(
+        instance,
+        physical_device,
+        &properties,
+        &device_requirements.features,
+    )?;
+    let SwapchainSupport {
+        capabilities,
+        formats,
+        present_modes,
+    } = match surface {
+        Some(surface) => query_swapchain_support(entry, instance, physical_device, surface)?,
+        None => SwapchainSupport::default(),
+    };
 
     let score = get_physical_device_score(&properties, &indices, device_requirements);
 
@@ -211,9 +438,30 @@ fn check_required_device_extensions(
     physical_device: vk::PhysicalDevice,
     required_extensions: &Vec<&'static CStr>,
 ) -> Result<(), SuitabilityError> {
-    debug!("Checking device for required extensions {required_extensions:?}",);
+    debug!(
+        target: log_targets::DEVICE,
+        "Checking device for required extensions {required_extensions:?}",
+    );
+
+    let available_extensions = get_available_device_extensions(instance, physical_device)?;
+
+    for required_extension in required_extensions {
+        if !available_extensions.contains(required_extension.to_str().unwrap()) {
+            return Err(SuitabilityError::MissingDeviceExtension {
+                device: physical_device,
+                extension: required_extension,
+            });
+        }
+    }
+
+    Ok(())
+}
 
-    let available_extensions = unsafe {
+fn get_available_device_extensions(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+) -> Result<HashSet<String>, SuitabilityError> {
+    let extensions = unsafe {
         instance
             .enumerate_device_extension_properties(physical_device)
             .or(Err(SuitabilityError::DeviceExtensionsNotFound {
@@ -225,64 +473,83 @@ fn check_required_device_extensions(
                     .to_string_lossy()
                     .into_owned()
             })
-            .collect::<HashSet<String>>()
+            .collect()
     };
 
-    for required_extension in required_extensions {
-        if !available_extensions.contains(required_extension.to_str().unwrap()) {
-            return Err(SuitabilityError::MissingDeviceExtension {
-                device: physical_device,
-                extension: required_extension,
-            });
-        }
-    }
-
-    Ok(())
+    Ok(extensions)
 }
 
 fn check_required_features(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
+    properties: &vk::PhysicalDeviceProperties,
+    required_features: &DeviceFeatures,
 ) -> Result<(), SuitabilityError> {
-    let mut dynamic_rendering_features = vk::PhysicalDeviceDynamicRenderingFeatures::default();
-
-    let mut features =
-        vk::PhysicalDeviceFeatures2::default().push_next(&mut dynamic_rendering_features);
-
-    unsafe {
-        instance.get_physical_device_features2(physical_device, &mut features);
-    }
+    let features = unsafe { instance.get_physical_device_features(physical_device) };
 
-    if features.features.sampler_anisotropy != vk::TRUE {
+    if features.sampler_anisotropy != vk::TRUE {
         return Err(SuitabilityError::MissingDeviceFeatures {
             device: physical_device,
             feature: "sampler_anisotropy",
         });
     }
 
-    if dynamic_rendering_features.dynamic_rendering != vk::TRUE {
+    let available_extensions = get_available_device_extensions(instance, physical_device)?;
+    let supported = supported_device_features(
+        instance,
+        physical_device,
+        properties.api_version,
+        &available_extensions,
+    );
+    if let Some(feature) = required_features.first_missing(supported) {
         return Err(SuitabilityError::MissingDeviceFeatures {
             device: physical_device,
-            feature: "dynamic_rendering",
+            feature,
         });
     }
 
     Ok(())
 }
 
-fn query_swapchain_support(
+/// Queries the [`DeviceFeatures`] of the device through the feature structures of its API
+/// version. Dynamic rendering is core since Vulkan 1.3, older drivers may only expose it through
+/// the extension or not at all.
+fn supported_device_features(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+    api_version: u32,
+    available_extensions: &HashSet<String>,
+) -> DeviceFeatures {
+    let dynamic_rendering_extension =
+        available_extensions.contains(khr::dynamic_rendering::NAME.to_str().unwrap_or_default());
+    let mut chain = FeatureChain::default();
+    let mut features = chain.push(
+        vk::PhysicalDeviceFeatures2::default(),
+        api_version,
+        dynamic_rendering_extension,
+    );
+
+    unsafe {
+        instance.get_physical_device_features2(physical_device, &mut features);
+    }
+
+    chain.features(api_version)
+}
+
+/// The surface capabilities of a physical device, empty when rendering without a surface.
+#[derive(Debug, Clone, Default)]
+pub struct SwapchainSupport {
+    pub capabilities: vk::SurfaceCapabilitiesKHR,
+    pub formats: Vec<vk::SurfaceFormatKHR>,
+    pub present_modes: Vec<vk::PresentModeKHR>,
+}
+
+pub(crate) fn query_swapchain_support(
     entry: &ash::Entry,
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
     surface: vk::SurfaceKHR,
-) -> Result<
-    (
-        vk::SurfaceCapabilitiesKHR,
-        Vec<vk::SurfaceFormatKHR>,
-        Vec<vk::PresentModeKHR>,
-    ),
-    SuitabilityError,
-> {
+) -> Result<SwapchainSupport, SuitabilityError> {
     let surface_loader = khr::surface::Instance::new(entry, instance);
 
     let capablities = unsafe {
@@ -319,7 +586,11 @@ fn query_swapchain_support(
         });
     }
 
-    Ok((capablities, formats, present_modes))
+    Ok(SwapchainSupport {
+        capabilities: capablities,
+        formats,
+        present_modes,
+    })
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -330,11 +601,13 @@ pub struct QueueFamilyIndices {
 }
 
 impl QueueFamilyIndices {
+    /// Finds the queue families of the device, without a surface presenting uses the graphics
+    /// queue family.
     pub fn get(
         entry: &ash::Entry,
         instance: &ash::Instance,
         physical_device: vk::PhysicalDevice,
-        surface: vk::SurfaceKHR,
+        surface: Option<vk::SurfaceKHR>,
     ) -> Result<Self, SuitabilityError> {
         let properties = unsafe {
             let len = instance.get_physical_device_queue_family_properties2_len(physical_device);
@@ -364,20 +637,26 @@ impl QueueFamilyIndices {
             })
             .unwrap_or(graphics); // The graphics queue can also handle transfers
 
-        let surface_loader = khr::surface::Instance::new(entry, instance);
-        let present = properties
-            .iter()
-            .enumerate()
-            .map(|(index, _)| unsafe {
-                surface_loader
-                    .get_physical_device_surface_support(physical_device, index as u32, surface)
-                    .ok()
-            })
-            .position(|index| index.is_some())
-            .ok_or(SuitabilityError::MissingQueueFamily {
-                device: physical_device,
-                queue_family: "present",
-            })?;
+        let present = match surface {
+            Some(surface) => {
+                let surface_loader = khr::surface::Instance::new(entry, instance);
+                (0..properties.len())
+                    .position(|index| unsafe {
+                        surface_loader
+                            .get_physical_device_surface_support(
+                                physical_device,
+                                index as u32,
+                                surface,
+                            )
+                            .unwrap_or(false)
+                    })
+                    .ok_or(SuitabilityError::MissingQueueFamily {
+                        device: physical_device,
+                        queue_family: "present",
+                    })?
+            }
+            None => graphics,
+        };
 
         Ok(QueueFamilyIndices {
             graphics: graphics as u32,
@@ -421,10 +700,18 @@ pub struct Device {
     pub present_queue_index: u32,
     pub transfer_queue: vk::Queue,
     pub transfer_queue_index: u32,
+    /// The required and supported optional extensions the device was created with.
+    pub enabled_extensions: Vec<&'static CStr>,
 }
 
 impl Resource for Device {}
 
+impl Device {
+    pub fn has_extension(&self, extension: &CStr) -> bool {
+        self.enabled_extensions.contains(&extension)
+    }
+}
+
 impl Deref for Device {
     type Target = ash::Device;
 
@@ -439,7 +726,10 @@ pub fn create_logical_device(
     device_requirements: Option<Res<DeviceRequirements>>,
     mut commands: Commands,
 ) -> Result<(), vk::Result> {
-    info!("Creating logical device for physical device: {physical_device:?}",);
+    info!(
+        target: log_targets::DEVICE,
+        "Creating logical device for physical device: {physical_device:?}",
+    );
 
     let mut unique_indices = HashSet::new();
     unique_indices.insert(physical_device.indices.graphics);
@@ -447,6 +737,7 @@ pub fn create_logical_device(
     unique_indices.insert(physical_device.indices.transfer);
 
     debug!(
+        target: log_targets::DEVICE,
         "Creating logical device with {} queue families",
         unique_indices.len()
     );
@@ -464,20 +755,54 @@ pub fn create_logical_device(
         .map(|res| res.into_inner())
         .unwrap_or_default();
 
-    let extensions = requirements
-        .extensions
+    let available_extensions = get_available_device_extensions(&instance, **physical_device)
+        .unwrap_or_default();
+    let mut enabled_extensions = requirements.extensions.clone();
+    for &extension in &requirements.optional_extensions {
+        if available_extensions.contains(extension.to_str().unwrap_or_default()) {
+            enabled_extensions.push(extension);
+        } else {
+            debug!(
+                target: log_targets::DEVICE,
+                "Optional device extension {extension:?} is not supported"
+            );
+        }
+    }
+
+    let api_version = physical_device.properties.api_version;
+    let supported_features = supported_device_features(
+        &instance,
+        **physical_device,
+        api_version,
+        &available_extensions,
+    );
+    let optional_features = requirements.optional_features.intersection(supported_features);
+    let enabled_features = requirements.features.union(optional_features);
+    let dynamic_rendering_extension =
+        enabled_features.dynamic_rendering && api_version < vk::API_VERSION_1_3;
+    if dynamic_rendering_extension && !enabled_extensions.contains(&khr::dynamic_rendering::NAME) {
+        enabled_extensions.push(khr::dynamic_rendering::NAME);
+    }
+    debug!(target: log_targets::DEVICE, "Enabling device features {enabled_features:?}");
+
+    let extensions = enabled_extensions
         .iter()
         .map(|&e| e.as_ptr())
         .collect::<Vec<_>>();
 
-    let features = vk::PhysicalDeviceFeatures::default().sampler_anisotropy(true);
-
-    let mut dynamic_rendering_features =
-        vk::PhysicalDeviceDynamicRenderingFeatures::default().dynamic_rendering(true);
-
-    let mut physical_device_features_2 = vk::PhysicalDeviceFeatures2::default()
-        .features(features)
-        .push_next(&mut dynamic_rendering_features);
+    let supported_core_features =
+        unsafe { instance.get_physical_device_features(**physical_device) };
+    let pipeline_statistics = supported_core_features.pipeline_statistics_query == vk::TRUE;
+    let features = vk::PhysicalDeviceFeatures::default()
+        .sampler_anisotropy(true)
+        .pipeline_statistics_query(pipeline_statistics);
+
+    let mut feature_chain = FeatureChain::enabling(enabled_features);
+    let mut physical_device_features_2 = feature_chain.push(
+        vk::PhysicalDeviceFeatures2::default().features(features),
+        api_version,
+        dynamic_rendering_extension,
+    );
 
     let create_info = vk::DeviceCreateInfo::default()
         .queue_create_infos(&queue_create_infos)
@@ -490,6 +815,14 @@ pub fn create_logical_device(
     let present_queue = unsafe { device.get_device_queue(physical_device.indices.present, 0) };
     let transfer_queue = unsafe { device.get_device_queue(physical_device.indices.transfer, 0) };
 
+    let mut capabilities =
+        RendererCapabilities::new(&enabled_extensions, &requirements.optional_extensions);
+    capabilities.features = enabled_features;
+    capabilities.dynamic_rendering = enabled_features.dynamic_rendering;
+    capabilities.descriptor_indexing |= enabled_features.descriptor_indexing;
+    capabilities.pipeline_statistics = pipeline_statistics;
+    info!(target: log_targets::DEVICE, "Device capabilities: {capabilities:?}");
+
     let logical_device = Device {
         device,
         graphics_queue,
@@ -498,17 +831,63 @@ pub fn create_logical_device(
         present_queue_index: physical_device.indices.present,
         transfer_queue,
         transfer_queue_index: physical_device.indices.transfer,
+        enabled_extensions,
     };
 
     commands.insert_resource(logical_device);
+    commands.insert_resource(capabilities);
+    commands.send_event(InitializationProgress::DeviceCreated);
 
     Ok(())
 }
 
 pub fn destroy_logical_device(device: Res<Device>, mut commands: Commands) {
-    info!("Destroying logical device");
+    info!(target: log_targets::DEVICE, "Destroying logical device");
 
     unsafe { device.destroy_device(None) };
 
     commands.remove_resource::<Device>();
+    commands.remove_resource::<RendererCapabilities>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feature_chains_follow_the_api_version() {
+        let enabled = DeviceFeatures {
+            timeline_semaphores: true,
+            descriptor_indexing: true,
+            dynamic_rendering: true,
+            ..Default::default()
+        };
+        let chain = FeatureChain::enabling(enabled);
+
+        assert_eq!(chain.features(vk::API_VERSION_1_3), enabled);
+        assert_eq!(
+            chain.features(vk::API_VERSION_1_2),
+            enabled,
+            "dynamic rendering falls back to the extension"
+        );
+        assert_eq!(
+            chain.features(vk::API_VERSION_1_1),
+            DeviceFeatures {
+                dynamic_rendering: true,
+                ..Default::default()
+            }
+        );
+
+        let supported = DeviceFeatures {
+            synchronization2: true,
+            ..enabled
+        };
+        assert_eq!(enabled.first_missing(supported), None);
+        assert_eq!(
+            DeviceFeatures::all().first_missing(supported),
+            Some("shader_draw_parameters")
+        );
+        assert_eq!(DeviceFeatures::all().intersection(supported), supported);
+        assert_eq!(DeviceFeatures::default().union(supported), supported);
+    }
 }
@@ -0,0 +1,684 @@
+use crate::allocator::GpuAllocator;
+use crate::barrier_validation::BarrierValidator;
+use crate::buffers::{UniformBufferObject, UniformBuffers};
+use crate::camera::Camera;
+use crate::capture::FrameCapture;
+use crate::command_buffer::{FrameRecorder, GpuParticleFrame};
+use crate::command_pool::CommandPools;
+use crate::config::GraphicsSettings;
+use crate::damage::PresentDamage;
+use crate::depth_buffers::DepthBuffers;
+use crate::descriptors::Descriptors;
+use crate::destroyer::DeferredDestroyer;
+use crate::device::Device;
+use crate::fullscreen::FullscreenPasses;
+use crate::gpu_particles::{GpuParticleEmitter, ParticleComputePipeline, ParticleDrawPipeline};
+use crate::instance::VulkanInstance;
+use crate::layout_tracker::ImageLayoutTracker;
+use crate::light_clusters::{LightClusterBuffers, LightClusters};
+use crate::log_targets;
+use crate::mesh::GpuMeshes;
+use crate::occlusion::OcclusionQueries;
+use crate::permutations::PipelinePermutations;
+use crate::pipeline::Pipeline;
+use crate::pipeline_statistics::PipelineStatisticsQueries;
+use crate::present_timing::PresentTiming;
+use crate::raw::{RawVulkan, RawVulkanHooks};
+use crate::recreate::SwapchainRecreation;
+use crate::render_path::ClassicRenderPass;
+use crate::scratch::FrameScratch;
+use crate::shutdown::InFlightWork;
+use crate::stats::RenderStats;
+use crate::swapchain::Swapchain;
+use ash::{google, khr, vk};
+use cgmath::Vector2;
+use flux_ecs::app::AppExit;
+use flux_ecs::commands::Commands;
+use flux_ecs::query::Query;
+use flux_ecs::resource::{NonSend, Res, ResMut, Resource};
+use log::{debug, error, info, trace};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// The result of acquiring a swapchain image, tells the frame driver how to continue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameOutcome {
+    /// The image can be rendered to and presented.
+    Acquired { image_index: u32 },
+    /// The image can still be presented but the swapchain should be recreated afterward.
+    Suboptimal { image_index: u32 },
+    /// No image became available within the timeout, the frame is skipped.
+    Skip,
+    /// The swapchain no longer matches the surface and must be recreated before rendering.
+    RecreateSwapchain,
+    /// The logical device was lost, all device objects must be recreated.
+    DeviceLost,
+}
+
+impl FrameOutcome {
+    /// Maps the result of `vkAcquireNextImageKHR`, errors that are not recoverable by the frame
+    /// driver are returned as is.
+    pub fn from_acquire_result(
+        result: Result<(u32, bool), vk::Result>,
+    ) -> Result<Self, vk::Result> {
+        match result {
+            Ok((image_index, false)) => Ok(Self::Acquired { image_index }),
+            Ok((image_index, true)) => Ok(Self::Suboptimal { image_index }),
+            Err(vk::Result::TIMEOUT | vk::Result::NOT_READY) => Ok(Self::Skip),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(Self::RecreateSwapchain),
+            Err(vk::Result::ERROR_DEVICE_LOST) => Ok(Self::DeviceLost),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Maps the result of `vkQueuePresentKHR` like [`FrameOutcome::from_acquire_result`].
+    pub fn from_present_result(
+        image_index: u32,
+        result: Result<bool, vk::Result>,
+    ) -> Result<Self, vk::Result> {
+        Self::from_acquire_result(result.map(|suboptimal| (image_index, suboptimal)))
+    }
+
+    /// Whether the swapchain has to be recreated, see
+    /// [`SwapchainRecreation`](crate::SwapchainRecreation).
+    pub fn needs_recreation(&self) -> bool {
+        matches!(self, Self::Suboptimal { .. } | Self::RecreateSwapchain)
+    }
+
+    /// The image to render to, if one was acquired.
+    pub fn image_index(&self) -> Option<u32> {
+        match self {
+            Self::Acquired { image_index } | Self::Suboptimal { image_index } => Some(*image_index),
+            _ => None,
+        }
+    }
+}
+
+impl Swapchain {
+    /// Acquires the next image, signaling `semaphore` once it is ready, and records the outcome in
+    /// the render stats.
+    pub fn acquire_next_image(
+        &self,
+        instance: &ash::Instance,
+        device: &ash::Device,
+        semaphore: vk::Semaphore,
+        timeout: Duration,
+        stats: &RenderStats,
+    ) -> Result<FrameOutcome, vk::Result> {
+        // The offscreen image is always available, `semaphore` is not signaled
+        if self.is_headless() {
+            let outcome = FrameOutcome::Acquired { image_index: 0 };
+            stats.record_frame_outcome(outcome);
+            return Ok(outcome);
+        }
+
+        let loader = khr::swapchain::Device::new(instance, device);
+        let timeout = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+
+        let result = unsafe {
+            loader.acquire_next_image(self.swapchain, timeout, semaphore, vk::Fence::null())
+        };
+
+        let outcome = FrameOutcome::from_acquire_result(result).inspect_err(|err| {
+            error!(target: log_targets::SWAPCHAIN, "Could not acquire a swapchain image: {err}")
+        })?;
+
+        match outcome {
+            FrameOutcome::Acquired { .. } => {}
+            FrameOutcome::DeviceLost => {
+                error!(target: log_targets::SWAPCHAIN, "Device lost while acquiring an image")
+            }
+            _ => debug!(target: log_targets::SWAPCHAIN, "Acquiring an image returned {outcome:?}"),
+        }
+        stats.record_frame_outcome(outcome);
+
+        Ok(outcome)
+    }
+
+    /// Presents the image on the present queue. Only the damaged regions are passed to the
+    /// presentation engine if the device supports `VK_KHR_incremental_present`, the image is
+    /// tagged with `present_id` for `VK_GOOGLE_display_timing` if given. Headless frames are not
+    /// presented.
+    pub fn present(
+        &self,
+        instance: &ash::Instance,
+        device: &Device,
+        image_index: u32,
+        wait_semaphores: &[vk::Semaphore],
+        damage: &PresentDamage,
+        present_id: Option<u32>,
+    ) -> Result<FrameOutcome, vk::Result> {
+        if self.is_headless() {
+            return Ok(FrameOutcome::Acquired { image_index });
+        }
+
+        let loader = khr::swapchain::Device::new(instance, device);
+        let rects = damage.take(self.extent);
+
+        let swapchains = [self.swapchain];
+        let image_indices = [image_index];
+        let mut info = vk::PresentInfoKHR::default()
+            .wait_semaphores(wait_semaphores)
+            .swapchains(&swapchains)
+            .image_indices(&image_indices);
+
+        let regions = [vk::PresentRegionKHR::default().rectangles(&rects)];
+        let mut present_regions = vk::PresentRegionsKHR::default().regions(&regions);
+        if !rects.is_empty() && device.has_extension(khr::incremental_present::NAME) {
+            info = info.push_next(&mut present_regions);
+        }
+
+        let times = [vk::PresentTimeGOOGLE {
+            present_id: present_id.unwrap_or_default(),
+            desired_present_time: 0,
+        }];
+        let mut present_times = vk::PresentTimesInfoGOOGLE::default().times(&times);
+        if present_id.is_some() {
+            info = info.push_next(&mut present_times);
+        }
+
+        let result = unsafe { loader.queue_present(device.present_queue, &info) };
+        FrameOutcome::from_present_result(image_index, result).inspect_err(|err| {
+            error!(target: log_targets::SWAPCHAIN, "Could not present the swapchain image: {err}")
+        })
+    }
+}
+
+/// The number of frames the CPU may record ahead of the GPU, `2` for double and `3` for triple
+/// buffering. Read once when the frame slots are created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramesInFlight {
+    pub count: usize,
+}
+
+impl Resource for FramesInFlight {}
+
+impl Default for FramesInFlight {
+    fn default() -> Self {
+        Self { count: 2 }
+    }
+}
+
+/// The objects of a frame in flight, reused every [`FramesInFlight::count`] frames.
+struct FrameSlot {
+    command_buffer: vk::CommandBuffer,
+    image_available: vk::Semaphore,
+    /// Signaled once the GPU finished the frame, created signaled.
+    fence: vk::Fence,
+}
+
+#[derive(Default)]
+struct FrameState {
+    /// The slot of the next frame.
+    current: usize,
+    /// The fence of the frame last rendering to each swapchain image.
+    image_fences: Vec<vk::Fence>,
+    /// The swapchain image each slot rendered to last, its queries are read once the slot's
+    /// fence is signaled.
+    slot_images: Vec<Option<usize>>,
+    /// The index of the frame each slot submitted last, see [`DeferredDestroyer`].
+    slot_frames: Vec<Option<u64>>,
+}
+
+/// The frames in flight and the synchronization between acquiring, rendering and presenting.
+pub struct FrameSlots {
+    slots: Vec<FrameSlot>,
+    /// Signaled when rendering to a swapchain image finished, one per image since presentation
+    /// may still wait on it after the slot is reused.
+    render_finished: Vec<vk::Semaphore>,
+    state: Mutex<FrameState>,
+}
+
+impl Resource for FrameSlots {}
+
+impl FrameSlots {
+    fn state(&self) -> MutexGuard<'_, FrameState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+/// Picks the slot after `current`, wrapping around after `count` slots.
+fn next_slot(current: usize, count: usize) -> usize {
+    (current + 1) % count
+}
+
+/// Creates the frame slots once a swapchain exists, a recreated swapchain only resizes them with
+/// [`resize_frame_slots`].
+pub fn create_frame_slots(
+    device: Res<Device>,
+    command_pools: Res<CommandPools>,
+    swapchain: Option<Res<Swapchain>>,
+    (frames_in_flight, existing): (Res<FramesInFlight>, Option<Res<FrameSlots>>),
+    mut commands: Commands,
+) -> Result<(), vk::Result> {
+    let Some(swapchain) = swapchain else {
+        debug!(target: log_targets::COMMANDS, "No swapchain available, skipping frame slots");
+        return Ok(());
+    };
+    if existing.is_some() {
+        return Ok(());
+    }
+
+    let count = frames_in_flight.count.max(1);
+    info!(target: log_targets::COMMANDS, "Creating {count} frames in flight");
+
+    let allocate_info = vk::CommandBufferAllocateInfo::default()
+        .command_pool(command_pools.graphics)
+        .level(vk::CommandBufferLevel::PRIMARY)
+        .command_buffer_count(count as u32);
+    let command_buffers = unsafe { device.allocate_command_buffers(&allocate_info)? };
+
+    let semaphore_info = vk::SemaphoreCreateInfo::default();
+    let fence_info = vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED);
+
+    let mut slots = Vec::with_capacity(count);
+    for command_buffer in command_buffers {
+        slots.push(FrameSlot {
+            command_buffer,
+            image_available: unsafe { device.create_semaphore(&semaphore_info, None)? },
+            fence: unsafe { device.create_fence(&fence_info, None)? },
+        });
+    }
+
+    let render_finished = swapchain
+        .images
+        .iter()
+        .map(|_| unsafe { device.create_semaphore(&semaphore_info, None) })
+        .collect::<Result<_, _>>()?;
+
+    commands.insert_resource(FrameSlots {
+        slots,
+        render_finished,
+        state: Mutex::new(FrameState {
+            current: 0,
+            image_fences: vec![vk::Fence::null(); swapchain.images.len()],
+            slot_images: vec![None; count],
+            slot_frames: vec![None; count],
+        }),
+    });
+
+    Ok(())
+}
+
+/// Matches the per image objects of the frame slots to a recreated swapchain, runs in
+/// [`RendererSchedule::RecreateSwapchain`](crate::RendererSchedule::RecreateSwapchain) once the
+/// device is idle.
+pub fn resize_frame_slots(
+    device: Res<Device>,
+    frame_slots: Option<ResMut<FrameSlots>>,
+    swapchain: Option<Res<Swapchain>>,
+) -> Result<(), vk::Result> {
+    let (Some(mut frame_slots), Some(swapchain)) = (frame_slots, swapchain) else {
+        return Ok(());
+    };
+    let image_count = swapchain.images.len();
+    debug!(target: log_targets::COMMANDS, "Resizing frame slots to {image_count} images");
+
+    for semaphore in frame_slots.render_finished.drain(..) {
+        unsafe { device.destroy_semaphore(semaphore, None) };
+    }
+    let semaphore_info = vk::SemaphoreCreateInfo::default();
+    for _ in 0..image_count {
+        let semaphore = unsafe { device.create_semaphore(&semaphore_info, None)? };
+        frame_slots.render_finished.push(semaphore);
+    }
+
+    // The queries of the previous images were recreated as well
+    let mut state = frame_slots.state();
+    state.image_fences = vec![vk::Fence::null(); image_count];
+    state.slot_images.fill(None);
+
+    Ok(())
+}
+
+/// The resources the main pass draws with.
+type SceneResources<'w> = (
+    Option<Res<'w, DepthBuffers>>,
+    Option<Res<'w, Pipeline>>,
+    Option<Res<'w, Descriptors>>,
+    Res<'w, GpuMeshes>,
+    Option<Res<'w, ClassicRenderPass>>,
+    Res<'w, PipelinePermutations>,
+);
+
+/// The uniforms and light clusters the main pass is drawn with and the camera the uniforms are
+/// written from.
+type CameraResources<'w, 's> = (
+    Option<Res<'w, UniformBuffers>>,
+    Query<'w, 's, &'static Camera>,
+    Option<Res<'w, LightClusterBuffers>>,
+    Res<'w, LightClusters>,
+);
+
+/// The pipelines simulating and drawing the GPU particle emitters.
+type GpuParticleResources<'w, 's> = (
+    Option<Res<'w, ParticleComputePipeline>>,
+    Option<Res<'w, ParticleDrawPipeline>>,
+    Query<'w, 's, &'static GpuParticleEmitter>,
+    Res<'w, BarrierValidator>,
+);
+
+/// The resources recorded into the main pass besides the scene.
+type HookResources<'w> = (
+    Res<'w, RawVulkan>,
+    Res<'w, RawVulkanHooks>,
+    Res<'w, ImageLayoutTracker>,
+    NonSend<'w, FrameScratch>,
+    Res<'w, FullscreenPasses>,
+);
+
+/// Resets the per frame render stats, runs first every frame.
+pub fn begin_render_stats_frame(stats: Res<RenderStats>) {
+    stats.begin_frame();
+}
+
+/// Renders and presents a frame: waits until the next frame slot is free, acquires a swapchain
+/// image, records the main pass into the slot's command buffer, submits it and presents the
+/// image.
+///
+/// The view and projection of the first [`Camera`] are written to the uniform buffer of the
+/// image before recording, see [`UniformBufferObject::from_camera`], together with the
+/// [`LightClusters`]. Meshes outside of its [`Frustum`](crate::Frustum) are not drawn. The
+/// [`GpuParticleEmitter`]s are simulated and drawn in the main window only.
+///
+/// Frames whose image could not be acquired within [`GraphicsSettings::acquire_timeout`] are
+/// skipped. Outdated or suboptimal swapchains are recreated through the [`SwapchainRecreation`],
+/// the outcome is recorded in the [`RenderStats`]. A frame failing after its image was acquired
+/// releases the image again and requests a recreation before returning the error.
+#[allow(clippy::too_many_arguments)]
+pub fn render_frame(
+    instance: Res<VulkanInstance>,
+    device: Res<Device>,
+    frame_slots: Option<Res<FrameSlots>>,
+    swapchain: Option<Res<Swapchain>>,
+    scene: SceneResources,
+    (uniform_buffers, cameras, light_buffers, clusters): CameraResources,
+    particles: GpuParticleResources,
+    hooks: HookResources,
+    queries: (
+        Option<Res<OcclusionQueries>>,
+        Option<Res<PipelineStatisticsQueries>>,
+    ),
+    (stats, settings): (Res<RenderStats>, Option<Res<GraphicsSettings>>),
+    (present, recreation, mut commands): (
+        (Res<PresentDamage>, Res<PresentTiming>),
+        Res<SwapchainRecreation>,
+        Commands,
+    ),
+    (in_flight, destroyer, allocator, capture): (
+        Res<InFlightWork>,
+        Res<DeferredDestroyer>,
+        Res<GpuAllocator>,
+        Res<FrameCapture>,
+    ),
+) -> Result<(), vk::Result> {
+    let (depth_buffers, pipeline, descriptors, meshes, render_pass, permutations) = scene;
+    let (raw_vulkan, raw_vulkan_hooks, layouts, scratch, fullscreen_passes) = hooks;
+    let (occlusion, pipeline_statistics) = queries;
+    let (damage, timing) = present;
+    let (
+        Some(frame_slots),
+        Some(swapchain),
+        Some(depth_buffers),
+        Some(pipeline),
+        Some(descriptors),
+    ) = (frame_slots, swapchain, depth_buffers, pipeline, descriptors)
+    else {
+        return Ok(());
+    };
+    if in_flight.is_stopping() || recreation.is_requested() {
+        return Ok(());
+    }
+
+    let mut state = frame_slots.state();
+    let slot_index = state.current;
+    let slot = &frame_slots.slots[slot_index];
+
+    unsafe { device.wait_for_fences(&[slot.fence], true, u64::MAX)? };
+    in_flight.retire_completed(&device);
+    if let Some(frame) = state.slot_frames[slot_index].take() {
+        destroyer.frame_completed(frame, &device, &allocator);
+    }
+    if let Some(image) = state.slot_images[slot_index].take() {
+        if let Some(occlusion) = &occlusion {
+            occlusion.frame_completed(image);
+        }
+        if let Some(pipeline_statistics) = &pipeline_statistics {
+            pipeline_statistics.frame_completed(image);
+        }
+    }
+
+    let timeout = settings
+        .as_deref()
+        .cloned()
+        .unwrap_or_default()
+        .acquire_timeout;
+    let outcome =
+        swapchain.acquire_next_image(&instance, &device, slot.image_available, timeout, &stats)?;
+    recreation.observe(outcome);
+    if outcome == FrameOutcome::DeviceLost {
+        stop_after_device_loss(&in_flight, &mut commands);
+        return Ok(());
+    }
+    let Some(image_index) = outcome.image_index() else {
+        return Ok(());
+    };
+    let image = image_index as usize;
+
+    // Headless frames neither wait for an acquired image nor signal a presentation
+    let semaphore_count = if swapchain.is_headless() { 0 } else { 1 };
+    let wait_semaphores = [slot.image_available];
+    let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+    let command_buffers = [slot.command_buffer];
+    let signal_semaphores = [frame_slots.render_finished[image]];
+
+    // Neither the semaphore of the acquired image nor the slot fence may be left pending when
+    // the frame fails before it was submitted
+    let submitted = (|| -> Result<_, vk::Result> {
+        // The image may still be rendered to by the frame of another slot
+        let image_fence = state.image_fences[image];
+        if image_fence != vk::Fence::null() && image_fence != slot.fence {
+            unsafe { device.wait_for_fences(&[image_fence], true, u64::MAX)? };
+        }
+
+        let camera = cameras.iter().next();
+        if let Some(uniform_buffers) = &uniform_buffers {
+            let uniforms = UniformBufferObject::from_camera(camera, swapchain.render_extent);
+            unsafe { uniform_buffers.write(image, &uniforms) };
+        }
+        let extent = swapchain.render_extent;
+        let viewport_size = Vector2::new(extent.width as f32, extent.height as f32);
+        let frustum = camera.map(|camera| camera.frustum(viewport_size));
+        if let Some(light_buffers) = &light_buffers {
+            unsafe { light_buffers.write(image, &clusters) };
+        }
+
+        let capture_buffer = capture.prepare(&device, &allocator, &swapchain)?;
+        let (particle_compute, particle_draw, emitters, validator) = &particles;
+        let gpu_particles = match (particle_compute, particle_draw) {
+            (Some(compute), Some(draw)) => Some(GpuParticleFrame {
+                compute,
+                draw,
+                emitters: emitters.iter().collect(),
+                validator,
+            }),
+            _ => None,
+        };
+        let recorder = FrameRecorder {
+            device: &device,
+            swapchain: &swapchain,
+            depth_buffers: &depth_buffers,
+            pipeline: &pipeline,
+            permutations: &permutations,
+            meshes: &meshes,
+            frustum,
+            descriptors: &descriptors,
+            stats: &stats,
+            raw_vulkan: &raw_vulkan,
+            raw_vulkan_hooks: &raw_vulkan_hooks,
+            layouts: &layouts,
+            scratch: &scratch,
+            render_pass: render_pass.as_deref(),
+            occlusion: occlusion.as_deref(),
+            pipeline_statistics: pipeline_statistics.as_deref(),
+            capture_buffer,
+            fullscreen_passes: Some(&fullscreen_passes),
+            gpu_particles,
+        };
+
+        let submit_info = vk::SubmitInfo::default()
+            .wait_semaphores(&wait_semaphores[..semaphore_count])
+            .wait_dst_stage_mask(&wait_stages[..semaphore_count])
+            .command_buffers(&command_buffers)
+            .signal_semaphores(&signal_semaphores[..semaphore_count]);
+
+        unsafe {
+            let flags = vk::CommandBufferResetFlags::empty();
+            device.reset_command_buffer(slot.command_buffer, flags)?;
+            recorder.record(slot.command_buffer, image)?;
+            device.reset_fences(&[slot.fence])?;
+            device.queue_submit(device.graphics_queue, &[submit_info], slot.fence)?;
+        }
+        Ok(capture_buffer)
+    })();
+    let capture_buffer = match submitted {
+        Ok(capture_buffer) => capture_buffer,
+        Err(err) => {
+            let wait_semaphores = &wait_semaphores[..semaphore_count];
+            unsafe { release_acquired_image(&device, slot, wait_semaphores)? };
+            if !swapchain.is_headless() {
+                recreation.request();
+            }
+            return Err(err);
+        }
+    };
+    state.image_fences[image] = slot.fence;
+    in_flight.submitted(slot.fence);
+    if capture_buffer.is_some() {
+        unsafe {
+            device.wait_for_fences(&[slot.fence], true, u64::MAX)?;
+            capture.complete(&swapchain);
+        }
+    }
+    state.slot_images[slot_index] = Some(image);
+    state.slot_frames[slot_index] = Some(destroyer.frame_submitted());
+    state.current = next_slot(slot_index, frame_slots.len());
+
+    let present_id = device
+        .has_extension(google::display_timing::NAME)
+        .then(|| timing.next_present_id());
+    let presented = swapchain.present(
+        &instance,
+        &device,
+        image_index,
+        &signal_semaphores,
+        &damage,
+        present_id,
+    )?;
+    let grown = scratch.end_frame();
+    if grown > 0 {
+        trace!(target: log_targets::COMMANDS, "{grown} frame scratch vectors grew");
+    }
+    // A suboptimal acquire was already recorded
+    if matches!(outcome, FrameOutcome::Acquired { .. }) {
+        stats.record_frame_outcome(presented);
+    }
+    recreation.observe(presented);
+    if presented == FrameOutcome::DeviceLost {
+        stop_after_device_loss(&in_flight, &mut commands);
+    }
+
+    Ok(())
+}
+
+/// Releases the image of a frame that failed between acquiring and submitting it: an empty
+/// submission consumes the `image_available` semaphore and signals the slot fence, which may
+/// already have been reset. The image itself is only returned by recreating the swapchain.
+///
+/// # Safety
+/// The fence of the slot must not be in use by a pending submission.
+unsafe fn release_acquired_image(
+    device: &Device,
+    slot: &FrameSlot,
+    wait_semaphores: &[vk::Semaphore],
+) -> Result<(), vk::Result> {
+    let wait_stages = [vk::PipelineStageFlags::ALL_COMMANDS];
+    let submit_info = vk::SubmitInfo::default()
+        .wait_semaphores(wait_semaphores)
+        .wait_dst_stage_mask(&wait_stages[..wait_semaphores.len()]);
+    unsafe {
+        device.reset_fences(&[slot.fence])?;
+        device.queue_submit(device.graphics_queue, &[submit_info], slot.fence)
+    }
+}
+
+/// Recreating every device object is not supported, the frame driver stops and the app exits.
+fn stop_after_device_loss(in_flight: &InFlightWork, commands: &mut Commands) {
+    error!(target: log_targets::DEVICE, "The device was lost, exiting");
+    in_flight.request_stop();
+    commands.send_event(AppExit);
+}
+
+pub fn destroy_frame_slots(
+    device: Res<Device>,
+    frame_slots: Option<Res<FrameSlots>>,
+    mut commands: Commands,
+) {
+    let Some(frame_slots) = frame_slots else {
+        return;
+    };
+
+    debug!(target: log_targets::COMMANDS, "Destroying frame slots");
+    unsafe {
+        for slot in &frame_slots.slots {
+            device.destroy_semaphore(slot.image_available, None);
+            device.destroy_fence(slot.fence, None);
+        }
+        for &semaphore in &frame_slots.render_finished {
+            device.destroy_semaphore(semaphore, None);
+        }
+    }
+    commands.remove_resource::<FrameSlots>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acquire_results_are_mapped_to_outcomes() {
+        assert_eq!(
+            FrameOutcome::from_acquire_result(Ok((2, true))),
+            Ok(FrameOutcome::Suboptimal { image_index: 2 })
+        );
+        assert_eq!(
+            FrameOutcome::from_acquire_result(Err(vk::Result::TIMEOUT)),
+            Ok(FrameOutcome::Skip)
+        );
+        assert_eq!(
+            FrameOutcome::from_acquire_result(Err(vk::Result::ERROR_OUT_OF_DATE_KHR)),
+            Ok(FrameOutcome::RecreateSwapchain)
+        );
+        assert_eq!(
+            FrameOutcome::from_acquire_result(Err(vk::Result::ERROR_SURFACE_LOST_KHR)),
+            Err(vk::Result::ERROR_SURFACE_LOST_KHR)
+        );
+
+        let stats = RenderStats::default();
+        stats.record_frame_outcome(FrameOutcome::Skip);
+        stats.record_frame_outcome(FrameOutcome::Acquired { image_index: 0 });
+        assert_eq!(stats.skipped_frames(), 1);
+        assert_eq!(stats.swapchain_recreations(), 0);
+
+        assert_eq!(next_slot(0, 2), 1);
+        assert_eq!(next_slot(2, 3), 0);
+    }
+}
@@ -0,0 +1,660 @@
+use crate::allocator::{Allocation, GpuAllocator};
+use crate::buffers::create_buffer;
+use crate::depth_buffers::DepthBuffers;
+use crate::destroyer::{DeferredDestroyer, RetiredHandle};
+use crate::device::Device;
+use crate::log_targets;
+use crate::pipeline::read_spv;
+use crate::render_path::ClassicRenderPass;
+use crate::stats::RenderStats;
+use crate::swapchain::Swapchain;
+use ash::vk;
+use flux_ecs::resource::{Res, ResMut, Resource};
+use log::debug;
+use std::io;
+use thiserror::Error;
+
+/// The vertex count of the triangle covering the screen, see `fullscreen.vert`.
+const FULLSCREEN_VERTEX_COUNT: u32 = 3;
+
+#[derive(Error, Debug)]
+pub enum FullscreenPassError {
+    #[error("invalid full-screen fragment shader: {0}")]
+    InvalidSpirv(#[source] io::Error),
+    #[error("full-screen pass {0:?} does not exist")]
+    UnknownPass(FullscreenPassId),
+    #[error("binding {binding} is not a uniform")]
+    NotAUniform { binding: u32 },
+    #[error("binding {binding} holds {expected} bytes, got {actual}")]
+    UniformSize {
+        binding: u32,
+        expected: usize,
+        actual: usize,
+    },
+    #[error(transparent)]
+    Vulkan(#[from] vk::Result),
+}
+
+/// Where in the frame a [`FullscreenPass`] is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FullscreenStage {
+    /// After the meshes, before the [`RawVulkanHooks`](crate::RawVulkanHooks).
+    AfterOpaque,
+    /// Last in the frame, after the hooks. The renderer has no post-processing chain of its own
+    /// yet, passes of this stage are the post-processing.
+    AfterPostProcess,
+}
+
+/// A resource read by the fragment shader of a [`FullscreenPass`], bound in set 0 at the index it
+/// was added at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FullscreenBinding {
+    /// A `sampler2D`. The view and sampler are owned by the caller and must outlive the pass, the
+    /// image must be in `SHADER_READ_ONLY_OPTIMAL` whenever the pass is drawn.
+    Texture {
+        view: vk::ImageView,
+        sampler: vk::Sampler,
+    },
+    /// A uniform block with the given contents, see [`FullscreenPasses::set_uniform`].
+    Uniform(Vec<u8>),
+}
+
+/// A fragment shader drawn over the whole render target, for simple effects like vignettes,
+/// tints or overlays without building pipelines by hand.
+///
+/// The engine draws a triangle covering the screen, the fragment shader receives the texture
+/// coordinates of the render target at `layout(location = 0) in vec2`, from `(0, 0)` at the top
+/// left to `(1, 1)` at the bottom right. Its output is alpha blended over the frame, depth is
+/// neither tested nor written.
+#[derive(Debug, Clone)]
+pub struct FullscreenPass {
+    fragment: Vec<u32>,
+    stage: FullscreenStage,
+    bindings: Vec<FullscreenBinding>,
+}
+
+impl FullscreenPass {
+    pub fn new(fragment_spv: &[u8], stage: FullscreenStage) -> Result<Self, FullscreenPassError> {
+        let fragment = read_spv(&mut io::Cursor::new(fragment_spv))
+            .map_err(FullscreenPassError::InvalidSpirv)?;
+        Ok(Self {
+            fragment,
+            stage,
+            bindings: Vec::new(),
+        })
+    }
+
+    /// Binds a texture at the next binding.
+    #[must_use]
+    pub fn with_texture(mut self, view: vk::ImageView, sampler: vk::Sampler) -> Self {
+        self.bindings
+            .push(FullscreenBinding::Texture { view, sampler });
+        self
+    }
+
+    /// Binds a uniform block at the next binding, its size is fixed to `contents`.
+    #[must_use]
+    pub fn with_uniform(mut self, contents: impl Into<Vec<u8>>) -> Self {
+        self.bindings
+            .push(FullscreenBinding::Uniform(contents.into()));
+        self
+    }
+
+    pub fn stage(&self) -> FullscreenStage {
+        self.stage
+    }
+
+    pub fn bindings(&self) -> &[FullscreenBinding] {
+        &self.bindings
+    }
+}
+
+/// Identifies a pass added to the [`FullscreenPasses`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FullscreenPassId(u32);
+
+/// The Vulkan objects of a pass, one descriptor set and set of uniform buffers per swapchain
+/// image.
+struct FullscreenPipeline {
+    pipeline: vk::Pipeline,
+    pipeline_layout: vk::PipelineLayout,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_sets: Vec<vk::DescriptorSet>,
+    /// The uniform buffers of every image as `(binding, buffer, memory)`.
+    uniform_buffers: Vec<Vec<(u32, vk::Buffer, Allocation)>>,
+}
+
+impl FullscreenPipeline {
+    fn handles(self) -> impl Iterator<Item = RetiredHandle> {
+        let buffers = self
+            .uniform_buffers
+            .into_iter()
+            .flatten()
+            .map(|(_, buffer, memory)| RetiredHandle::Buffer(buffer, memory));
+        [
+            RetiredHandle::Pipeline(self.pipeline),
+            RetiredHandle::PipelineLayout(self.pipeline_layout),
+            RetiredHandle::DescriptorPool(self.descriptor_pool),
+            RetiredHandle::DescriptorSetLayout(self.descriptor_set_layout),
+        ]
+        .into_iter()
+        .chain(buffers)
+    }
+}
+
+struct RegisteredPass {
+    id: FullscreenPassId,
+    pass: FullscreenPass,
+    enabled: bool,
+    pipeline: Option<FullscreenPipeline>,
+}
+
+/// The [`FullscreenPass`]es drawn every frame, in the order they were added.
+///
+/// The pipelines of added passes are created by [`prepare_fullscreen_passes`] before the next
+/// frame. Passes are only drawn in the primary window.
+#[derive(Default)]
+pub struct FullscreenPasses {
+    passes: Vec<RegisteredPass>,
+    next_id: u32,
+    /// The pipelines of removed passes, retired by the next [`prepare_fullscreen_passes`].
+    removed: Vec<FullscreenPipeline>,
+}
+
+impl Resource for FullscreenPasses {}
+
+impl FullscreenPasses {
+    pub fn add(&mut self, pass: FullscreenPass) -> FullscreenPassId {
+        let id = FullscreenPassId(self.next_id);
+        self.next_id += 1;
+        self.passes.push(RegisteredPass {
+            id,
+            pass,
+            enabled: true,
+            pipeline: None,
+        });
+        id
+    }
+
+    /// Removes the pass, returns `false` if it did not exist.
+    pub fn remove(&mut self, id: FullscreenPassId) -> bool {
+        let Some(index) = self.passes.iter().position(|pass| pass.id == id) else {
+            return false;
+        };
+        let pass = self.passes.remove(index);
+        self.removed.extend(pass.pipeline);
+        true
+    }
+
+    pub fn get(&self, id: FullscreenPassId) -> Option<&FullscreenPass> {
+        self.registered(id).map(|pass| &pass.pass)
+    }
+
+    /// Skips drawing the pass without destroying its pipeline.
+    pub fn set_enabled(
+        &mut self,
+        id: FullscreenPassId,
+        enabled: bool,
+    ) -> Result<(), FullscreenPassError> {
+        self.registered_mut(id)?.enabled = enabled;
+        Ok(())
+    }
+
+    pub fn is_enabled(&self, id: FullscreenPassId) -> bool {
+        self.registered(id).is_some_and(|pass| pass.enabled)
+    }
+
+    /// Replaces the contents of the uniform at `binding`, visible from the next recorded frame.
+    pub fn set_uniform(
+        &mut self,
+        id: FullscreenPassId,
+        binding: u32,
+        contents: &[u8],
+    ) -> Result<(), FullscreenPassError> {
+        let pass = self.registered_mut(id)?;
+        let Some(FullscreenBinding::Uniform(uniform)) =
+            pass.pass.bindings.get_mut(binding as usize)
+        else {
+            return Err(FullscreenPassError::NotAUniform { binding });
+        };
+        if uniform.len() != contents.len() {
+            return Err(FullscreenPassError::UniformSize {
+                binding,
+                expected: uniform.len(),
+                actual: contents.len(),
+            });
+        }
+
+        uniform.copy_from_slice(contents);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    fn registered(&self, id: FullscreenPassId) -> Option<&RegisteredPass> {
+        self.passes.iter().find(|pass| pass.id == id)
+    }
+
+    fn registered_mut(
+        &mut self,
+        id: FullscreenPassId,
+    ) -> Result<&mut RegisteredPass, FullscreenPassError> {
+        self.passes
+            .iter_mut()
+            .find(|pass| pass.id == id)
+            .ok_or(FullscreenPassError::UnknownPass(id))
+    }
+
+    /// The enabled passes of the stage in drawing order.
+    fn drawn(&self, stage: FullscreenStage) -> impl Iterator<Item = &RegisteredPass> {
+        self.passes
+            .iter()
+            .filter(move |pass| pass.enabled && pass.pass.stage == stage)
+    }
+
+    /// Draws the passes of `stage` into the render target of image `image`.
+    ///
+    /// # Safety
+    /// The command buffer must be recording inside the main pass, and the uniform buffers of
+    /// image `image` must not be used by a pending submission.
+    pub(crate) unsafe fn record(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        stage: FullscreenStage,
+        image: usize,
+        extent: vk::Extent2D,
+        stats: &RenderStats,
+    ) {
+        for registered in self.drawn(stage) {
+            let Some(pipeline) = &registered.pipeline else {
+                continue;
+            };
+            let image = image % pipeline.uniform_buffers.len().max(1);
+
+            for &(binding, _, memory) in pipeline.uniform_buffers.get(image).into_iter().flatten() {
+                let Some(FullscreenBinding::Uniform(contents)) =
+                    registered.pass.bindings.get(binding as usize)
+                else {
+                    continue;
+                };
+                let mapped = memory
+                    .mapped_ptr()
+                    .expect("Host visible allocations are mapped");
+                unsafe {
+                    std::ptr::copy_nonoverlapping(contents.as_ptr(), mapped, contents.len());
+                }
+            }
+
+            let viewport = vk::Viewport::default()
+                .width(extent.width as f32)
+                .height(extent.height as f32)
+                .max_depth(1.0);
+            let scissor = vk::Rect2D::default().extent(extent);
+
+            unsafe {
+                device.cmd_bind_pipeline(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline.pipeline,
+                );
+                stats.record_pipeline_bind();
+                device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+                device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+
+                if let Some(&descriptor_set) = pipeline.descriptor_sets.get(image) {
+                    device.cmd_bind_descriptor_sets(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        pipeline.pipeline_layout,
+                        0,
+                        &[descriptor_set],
+                        &[],
+                    );
+                    stats.record_descriptor_bind();
+                }
+
+                device.cmd_draw(command_buffer, FULLSCREEN_VERTEX_COUNT, 1, 0, 0);
+                stats.record_draw(FULLSCREEN_VERTEX_COUNT, 1);
+            }
+        }
+    }
+}
+
+/// Creates the pipelines of added passes and retires the pipelines of removed ones.
+pub fn prepare_fullscreen_passes(
+    device: Res<Device>,
+    allocator: Res<GpuAllocator>,
+    destroyer: Res<DeferredDestroyer>,
+    mut passes: ResMut<FullscreenPasses>,
+    swapchain: Option<Res<Swapchain>>,
+    depth_buffers: Option<Res<DepthBuffers>>,
+    render_pass: Option<Res<ClassicRenderPass>>,
+) -> Result<(), FullscreenPassError> {
+    for pipeline in passes.removed.drain(..) {
+        for handle in pipeline.handles() {
+            destroyer.retire(handle);
+        }
+    }
+
+    let (Some(swapchain), Some(depth_buffers)) = (swapchain, depth_buffers) else {
+        return Ok(());
+    };
+    for registered in &mut passes.passes {
+        if registered.pipeline.is_some() {
+            continue;
+        }
+
+        debug!(
+            target: log_targets::PIPELINE,
+            "Creating the pipeline of full-screen pass {:?}",
+            registered.id
+        );
+        registered.pipeline = Some(create_fullscreen_pipeline(
+            &device,
+            &allocator,
+            &registered.pass,
+            &swapchain,
+            &depth_buffers,
+            render_pass.as_deref(),
+        )?);
+    }
+
+    Ok(())
+}
+
+fn descriptor_type(binding: &FullscreenBinding) -> vk::DescriptorType {
+    match binding {
+        FullscreenBinding::Texture { .. } => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+        FullscreenBinding::Uniform(_) => vk::DescriptorType::UNIFORM_BUFFER,
+    }
+}
+
+fn create_fullscreen_pipeline(
+    device: &Device,
+    allocator: &GpuAllocator,
+    pass: &FullscreenPass,
+    swapchain: &Swapchain,
+    depth_buffers: &DepthBuffers,
+    render_pass: Option<&ClassicRenderPass>,
+) -> Result<FullscreenPipeline, vk::Result> {
+    let layout_bindings: Vec<_> = pass
+        .bindings
+        .iter()
+        .enumerate()
+        .map(|(binding, resource)| {
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(binding as u32)
+                .descriptor_type(descriptor_type(resource))
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+        })
+        .collect();
+    let layout_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&layout_bindings);
+    let descriptor_set_layout = unsafe { device.create_descriptor_set_layout(&layout_info, None)? };
+
+    let set_layouts = [descriptor_set_layout];
+    let set_layouts = if pass.bindings.is_empty() {
+        &[][..]
+    } else {
+        &set_layouts[..]
+    };
+    let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default().set_layouts(set_layouts);
+    let pipeline_layout = unsafe { device.create_pipeline_layout(&pipeline_layout_info, None)? };
+
+    let pipeline = create_graphics_pipeline(
+        device,
+        &pass.fragment,
+        pipeline_layout,
+        swapchain.format.format,
+        depth_buffers.depth_format,
+        render_pass,
+    )?;
+
+    let image_count = swapchain.images.len() as u32;
+    let pool_sizes: Vec<_> = pass
+        .bindings
+        .iter()
+        .map(|binding| {
+            vk::DescriptorPoolSize::default()
+                .ty(descriptor_type(binding))
+                .descriptor_count(image_count)
+        })
+        .collect();
+    let pool_info = vk::DescriptorPoolCreateInfo::default()
+        .pool_sizes(&pool_sizes)
+        .max_sets(image_count);
+    let descriptor_pool = unsafe { device.create_descriptor_pool(&pool_info, None)? };
+
+    let mut descriptor_sets = Vec::new();
+    let mut uniform_buffers = Vec::new();
+    if !pass.bindings.is_empty() {
+        let layouts = vec![descriptor_set_layout; image_count as usize];
+        let info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&layouts);
+        descriptor_sets = unsafe { device.allocate_descriptor_sets(&info)? };
+
+        for &set in &descriptor_sets {
+            let mut buffers = Vec::new();
+            for (binding, resource) in pass.bindings.iter().enumerate() {
+                let binding = binding as u32;
+                let write = vk::WriteDescriptorSet::default()
+                    .dst_set(set)
+                    .dst_binding(binding)
+                    .descriptor_type(descriptor_type(resource));
+                match resource {
+                    FullscreenBinding::Texture { view, sampler } => {
+                        let image_info = [vk::DescriptorImageInfo::default()
+                            .image_view(*view)
+                            .sampler(*sampler)
+                            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)];
+                        unsafe {
+                            device.update_descriptor_sets(&[write.image_info(&image_info)], &[])
+                        };
+                    }
+                    FullscreenBinding::Uniform(contents) => {
+                        let (buffer, memory) = create_buffer(
+                            device,
+                            allocator,
+                            contents.len().max(1) as vk::DeviceSize,
+                            vk::BufferUsageFlags::UNIFORM_BUFFER,
+                            vk::MemoryPropertyFlags::HOST_VISIBLE
+                                | vk::MemoryPropertyFlags::HOST_COHERENT,
+                        )?;
+                        buffers.push((binding, buffer, memory));
+                        let buffer_info = [vk::DescriptorBufferInfo::default()
+                            .buffer(buffer)
+                            .range(vk::WHOLE_SIZE)];
+                        unsafe {
+                            device.update_descriptor_sets(&[write.buffer_info(&buffer_info)], &[])
+                        };
+                    }
+                }
+            }
+            uniform_buffers.push(buffers);
+        }
+    }
+
+    Ok(FullscreenPipeline {
+        pipeline,
+        pipeline_layout,
+        descriptor_set_layout,
+        descriptor_pool,
+        descriptor_sets,
+        uniform_buffers,
+    })
+}
+
+fn create_graphics_pipeline(
+    device: &Device,
+    fragment: &[u32],
+    layout: vk::PipelineLayout,
+    color_format: vk::Format,
+    depth_format: vk::Format,
+    render_pass: Option<&ClassicRenderPass>,
+) -> Result<vk::Pipeline, vk::Result> {
+    let vertex = read_spv(&mut io::Cursor::new(
+        &include_bytes!("../shaders/fullscreen_vert.spv")[..],
+    ))
+    .map_err(|_| vk::Result::ERROR_INITIALIZATION_FAILED)?;
+    let vertex_info = vk::ShaderModuleCreateInfo::default().code(&vertex);
+    let vertex_module = unsafe { device.create_shader_module(&vertex_info, None)? };
+    let fragment_info = vk::ShaderModuleCreateInfo::default().code(fragment);
+    let fragment_module = match unsafe { device.create_shader_module(&fragment_info, None) } {
+        Ok(module) => module,
+        Err(err) => {
+            unsafe { device.destroy_shader_module(vertex_module, None) };
+            return Err(err);
+        }
+    };
+
+    let stages = [
+        vk::PipelineShaderStageCreateInfo::default()
+            .stage(vk::ShaderStageFlags::VERTEX)
+            .module(vertex_module)
+            .name(c"main"),
+        vk::PipelineShaderStageCreateInfo::default()
+            .stage(vk::ShaderStageFlags::FRAGMENT)
+            .module(fragment_module)
+            .name(c"main"),
+    ];
+
+    let vertex_input = vk::PipelineVertexInputStateCreateInfo::default();
+    let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+    let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+        .viewport_count(1)
+        .scissor_count(1);
+    let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state =
+        vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+    let rasterization = vk::PipelineRasterizationStateCreateInfo::default()
+        .polygon_mode(vk::PolygonMode::FILL)
+        .cull_mode(vk::CullModeFlags::NONE)
+        .line_width(1.0);
+    let multisample = vk::PipelineMultisampleStateCreateInfo::default()
+        .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+    let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default()
+        .depth_test_enable(false)
+        .depth_write_enable(false);
+    let attachments = [vk::PipelineColorBlendAttachmentState::default()
+        .color_write_mask(vk::ColorComponentFlags::RGBA)
+        .blend_enable(true)
+        .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+        .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+        .color_blend_op(vk::BlendOp::ADD)
+        .src_alpha_blend_factor(vk::BlendFactor::ONE)
+        .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+        .alpha_blend_op(vk::BlendOp::ADD)];
+    let color_blend = vk::PipelineColorBlendStateCreateInfo::default().attachments(&attachments);
+
+    let color_formats = [color_format];
+    let mut rendering_info = vk::PipelineRenderingCreateInfo::default()
+        .color_attachment_formats(&color_formats)
+        .depth_attachment_format(depth_format);
+
+    let info = vk::GraphicsPipelineCreateInfo::default()
+        .stages(&stages)
+        .vertex_input_state(&vertex_input)
+        .input_assembly_state(&input_assembly)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization)
+        .multisample_state(&multisample)
+        .depth_stencil_state(&depth_stencil)
+        .color_blend_state(&color_blend)
+        .dynamic_state(&dynamic_state)
+        .layout(layout);
+    let info = match render_pass {
+        Some(render_pass) => info.render_pass(render_pass.render_pass).subpass(0),
+        None => info.push_next(&mut rendering_info),
+    };
+
+    let pipelines =
+        unsafe { device.create_graphics_pipelines(vk::PipelineCache::null(), &[info], None) };
+    unsafe {
+        device.destroy_shader_module(vertex_module, None);
+        device.destroy_shader_module(fragment_module, None);
+    }
+
+    Ok(pipelines.map_err(|(_, result)| result)?[0])
+}
+
+pub fn destroy_fullscreen_passes(
+    device: Res<Device>,
+    allocator: Res<GpuAllocator>,
+    mut passes: ResMut<FullscreenPasses>,
+) {
+    let passes = &mut *passes;
+    let pipelines = passes
+        .passes
+        .iter_mut()
+        .filter_map(|pass| pass.pipeline.take())
+        .chain(passes.removed.drain(..));
+
+    for pipeline in pipelines {
+        debug!(target: log_targets::PIPELINE, "Destroying a full-screen pass pipeline");
+        for handle in pipeline.handles() {
+            unsafe { handle.destroy(&device, &allocator) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Any valid module passes [`read_spv`], the stage is only checked by the driver.
+    const VERTEX_SPV: &[u8] = include_bytes!("../shaders/fullscreen_vert.spv");
+
+    #[test]
+    fn passes_validate_their_uniforms() {
+        let tint = FullscreenPass::new(VERTEX_SPV, FullscreenStage::AfterPostProcess)
+            .unwrap()
+            .with_uniform([0u8; 16])
+            .with_texture(vk::ImageView::null(), vk::Sampler::null());
+        let overlay = FullscreenPass::new(VERTEX_SPV, FullscreenStage::AfterOpaque).unwrap();
+        assert!(FullscreenPass::new(&[1, 2, 3, 4], FullscreenStage::AfterOpaque).is_err());
+
+        let mut passes = FullscreenPasses::default();
+        let tint = passes.add(tint);
+        let overlay = passes.add(overlay);
+
+        assert!(passes.set_uniform(tint, 0, &[1; 16]).is_ok());
+        assert!(matches!(
+            passes.set_uniform(tint, 0, &[1; 4]),
+            Err(FullscreenPassError::UniformSize {
+                expected: 16,
+                actual: 4,
+                ..
+            })
+        ));
+        assert!(matches!(
+            passes.set_uniform(tint, 1, &[]),
+            Err(FullscreenPassError::NotAUniform { binding: 1 })
+        ));
+        assert_eq!(
+            passes.get(tint).unwrap().bindings()[0],
+            FullscreenBinding::Uniform(vec![1; 16])
+        );
+
+        passes.set_enabled(overlay, false).unwrap();
+        assert_eq!(passes.drawn(FullscreenStage::AfterOpaque).count(), 0);
+        assert_eq!(passes.drawn(FullscreenStage::AfterPostProcess).count(), 1);
+
+        assert!(passes.remove(overlay));
+        assert!(!passes.remove(overlay));
+        assert!(matches!(
+            passes.set_enabled(overlay, true),
+            Err(FullscreenPassError::UnknownPass(_))
+        ));
+        assert_eq!(passes.len(), 1);
+    }
+}
@@ -0,0 +1,880 @@
+use crate::barrier_validation::{BarrierValidator, GpuResource};
+use crate::allocator::{Allocation, GpuAllocator};
+use crate::buffers::create_buffer;
+use crate::depth_buffers::DepthBuffers;
+use crate::device::Device;
+use crate::log_targets;
+use crate::particles::EmitterSettings;
+use crate::pipeline::{Pipeline, read_spv};
+use crate::render_path::ClassicRenderPass;
+use crate::swapchain::Swapchain;
+use ash::vk;
+use flux_ecs::commands::Commands;
+use flux_ecs::component::Component;
+use flux_ecs::query::Query;
+use flux_ecs::resource::{Res, Resource};
+use flux_time::time::Time;
+use log::debug;
+use std::io;
+use thiserror::Error;
+
+/// The local workgroup size of `particles.comp`.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// The number of GPU emitters the descriptor pool of the compute pipeline has room for.
+const MAX_GPU_EMITTERS: u32 = 64;
+
+/// The vertices of a particle quad, drawn as two triangles without a vertex buffer.
+const QUAD_VERTEX_COUNT: u32 = 6;
+
+#[derive(Error, Debug)]
+pub enum GpuParticleError {
+    #[error("invalid particle compute shader: {0}")]
+    InvalidSpirv(#[source] io::Error),
+    #[error(transparent)]
+    Vulkan(#[from] vk::Result),
+}
+
+/// The compiled `particles.comp` shader, insert it to enable GPU particles.
+pub struct GpuParticleShader {
+    code: Vec<u32>,
+}
+
+impl Resource for GpuParticleShader {}
+
+impl GpuParticleShader {
+    pub fn new(spv: &[u8]) -> Result<Self, GpuParticleError> {
+        let code = read_spv(&mut io::Cursor::new(spv)).map_err(GpuParticleError::InvalidSpirv)?;
+        Ok(Self { code })
+    }
+}
+
+/// The layout of a particle in the storage buffers, see `particles.comp`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GpuParticle {
+    pub position_age: [f32; 4],
+    pub velocity_lifetime: [f32; 4],
+}
+
+/// The push constants of one simulation dispatch.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct SimulationConstants {
+    origin: [f32; 4],
+    velocity_min: [f32; 4],
+    velocity_max: [f32; 4],
+    acceleration: [f32; 4],
+    spawn_count: u32,
+    seed: u32,
+    capacity: u32,
+    _padding: u32,
+}
+
+impl SimulationConstants {
+    fn as_bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts((self as *const Self).cast::<u8>(), size_of::<Self>()) }
+    }
+}
+
+/// The push constants of one particle draw, see `particles.vert`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct DrawConstants {
+    color_start: [f32; 4],
+    color_end: [f32; 4],
+    size: f32,
+    _padding: [u32; 3],
+}
+
+impl DrawConstants {
+    fn as_bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts((self as *const Self).cast::<u8>(), size_of::<Self>()) }
+    }
+}
+
+/// Two values that alternate between being read and written every frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PingPong<T> {
+    items: [T; 2],
+    read: usize,
+}
+
+impl<T> PingPong<T> {
+    pub fn new(first: T, second: T) -> Self {
+        Self {
+            items: [first, second],
+            read: 0,
+        }
+    }
+
+    /// The value written last frame.
+    pub fn read(&self) -> &T {
+        &self.items[self.read]
+    }
+
+    /// The value written this frame.
+    pub fn write(&self) -> &T {
+        &self.items[1 - self.read]
+    }
+
+    /// The index of the value that is read this frame.
+    pub fn read_index(&self) -> usize {
+        self.read
+    }
+
+    pub fn swap(&mut self) {
+        self.read = 1 - self.read;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+}
+
+/// The particle storage buffer and the indirect draw arguments of one side of the ping-pong pair.
+#[derive(Debug, Clone, Copy)]
+pub struct ParticleBuffers {
+    pub particles: vk::Buffer,
+    pub particles_memory: Allocation,
+    /// A `vk::DrawIndirectCommand`, its instance count is the number of alive particles.
+    pub draw: vk::Buffer,
+    pub draw_memory: Allocation,
+}
+
+struct GpuEmitterState {
+    buffers: PingPong<ParticleBuffers>,
+    /// The descriptor set reading the buffers at the same index.
+    descriptor_sets: [vk::DescriptorSet; 2],
+    constants: SimulationConstants,
+}
+
+/// Simulates particles with a compute dispatch per frame instead of on the CPU, for emitters
+/// with more particles than [`ParticleEmitter`](crate::ParticleEmitter) can handle.
+///
+/// The particles never leave the GPU, they are drawn with an indirect draw of the alive
+/// particles.
+pub struct GpuParticleEmitter {
+    pub settings: EmitterSettings,
+    /// The maximum number of alive particles, fixed once the buffers are created.
+    pub capacity: u32,
+    spawn_accumulator: f32,
+    seed: u32,
+    state: Option<GpuEmitterState>,
+}
+
+impl Component for GpuParticleEmitter {}
+
+impl GpuParticleEmitter {
+    pub fn new(settings: EmitterSettings, capacity: u32) -> Self {
+        Self {
+            settings,
+            capacity,
+            spawn_accumulator: 0.0,
+            seed: 0x9e37_79b9,
+            state: None,
+        }
+    }
+
+    /// The buffers of the emitter once they were created by [`prepare_gpu_particles`].
+    pub fn buffers(&self) -> Option<&PingPong<ParticleBuffers>> {
+        self.state.as_ref().map(|state| &state.buffers)
+    }
+
+    /// Swaps the buffers and updates the simulation constants of the frame.
+    fn advance(&mut self, delta: f32) {
+        let spawn_count = take_spawn_count(
+            &mut self.spawn_accumulator,
+            self.settings.spawn_rate,
+            delta,
+            self.capacity,
+        );
+
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+
+        let settings = &self.settings;
+        let [lifetime_min, lifetime_max] = settings.lifetime;
+        let extend = |v: [f32; 3], w: f32| [v[0], v[1], v[2], w];
+
+        if let Some(state) = &mut self.state {
+            state.buffers.swap();
+            state.constants = SimulationConstants {
+                origin: extend(settings.origin, 0.0),
+                velocity_min: extend(settings.velocity_min, lifetime_min),
+                velocity_max: extend(settings.velocity_max, lifetime_max),
+                acceleration: extend(settings.acceleration, delta),
+                spawn_count,
+                seed: self.seed,
+                capacity: self.capacity,
+                _padding: 0,
+            };
+        }
+    }
+}
+
+/// Accumulates the particles to spawn over `delta` seconds and takes the whole ones.
+fn take_spawn_count(accumulator: &mut f32, rate: f32, delta: f32, capacity: u32) -> u32 {
+    *accumulator += rate * delta;
+    let count = accumulator.floor();
+    *accumulator -= count;
+    (count as u32).min(capacity)
+}
+
+fn dispatch_group_count(capacity: u32) -> u32 {
+    capacity.div_ceil(WORKGROUP_SIZE)
+}
+
+/// The compute pipeline simulating all [`GpuParticleEmitter`]s.
+pub struct ParticleComputePipeline {
+    pub pipeline: vk::Pipeline,
+    pub pipeline_layout: vk::PipelineLayout,
+    pub descriptor_set_layout: vk::DescriptorSetLayout,
+    pub descriptor_pool: vk::DescriptorPool,
+}
+
+impl Resource for ParticleComputePipeline {}
+
+pub fn create_particle_compute_pipeline(
+    device: Res<Device>,
+    shader: Option<Res<GpuParticleShader>>,
+    mut commands: Commands,
+) -> Result<(), GpuParticleError> {
+    let Some(shader) = shader else {
+        return Ok(());
+    };
+
+    debug!(target: log_targets::PIPELINE, "Creating particle compute pipeline");
+
+    let bindings: Vec<_> = (0..4)
+        .map(|binding| {
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(binding)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(1)
+                // The draw reads the simulated particles through the same set
+                .stage_flags(vk::ShaderStageFlags::COMPUTE | vk::ShaderStageFlags::VERTEX)
+        })
+        .collect();
+    let layout_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&bindings);
+    let descriptor_set_layout = unsafe { device.create_descriptor_set_layout(&layout_info, None)? };
+
+    let push_constant_ranges = [vk::PushConstantRange::default()
+        .stage_flags(vk::ShaderStageFlags::COMPUTE)
+        .offset(0)
+        .size(size_of::<SimulationConstants>() as u32)];
+    let set_layouts = [descriptor_set_layout];
+    let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default()
+        .set_layouts(&set_layouts)
+        .push_constant_ranges(&push_constant_ranges);
+    let pipeline_layout = unsafe { device.create_pipeline_layout(&pipeline_layout_info, None)? };
+
+    let module_info = vk::ShaderModuleCreateInfo::default().code(&shader.code);
+    let module = unsafe { device.create_shader_module(&module_info, None)? };
+
+    let stage = vk::PipelineShaderStageCreateInfo::default()
+        .stage(vk::ShaderStageFlags::COMPUTE)
+        .module(module)
+        .name(c"main");
+    let info = vk::ComputePipelineCreateInfo::default()
+        .stage(stage)
+        .layout(pipeline_layout);
+    let pipelines =
+        unsafe { device.create_compute_pipelines(vk::PipelineCache::null(), &[info], None) };
+    unsafe { device.destroy_shader_module(module, None) };
+    let pipeline = pipelines.map_err(|(_, result)| result)?[0];
+
+    let pool_sizes = [vk::DescriptorPoolSize::default()
+        .ty(vk::DescriptorType::STORAGE_BUFFER)
+        .descriptor_count(MAX_GPU_EMITTERS * 2 * 4)];
+    let pool_info = vk::DescriptorPoolCreateInfo::default()
+        .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+        .pool_sizes(&pool_sizes)
+        .max_sets(MAX_GPU_EMITTERS * 2);
+    let descriptor_pool = unsafe { device.create_descriptor_pool(&pool_info, None)? };
+
+    commands.insert_resource(ParticleComputePipeline {
+        pipeline,
+        pipeline_layout,
+        descriptor_set_layout,
+        descriptor_pool,
+    });
+
+    Ok(())
+}
+
+/// The graphics pipeline drawing the particles of the [`GpuParticleEmitter`]s as camera facing
+/// quads, alpha blended over the opaque geometry.
+pub struct ParticleDrawPipeline {
+    pub pipeline: vk::Pipeline,
+    /// The camera uniforms of the main [`Pipeline`] at set `0` and the simulation set of the
+    /// [`ParticleComputePipeline`] at set `1`.
+    pub pipeline_layout: vk::PipelineLayout,
+}
+
+impl Resource for ParticleDrawPipeline {}
+
+/// Creates the particle draw pipeline once the compute pipeline and a swapchain exist, it is kept
+/// when the swapchain is recreated.
+pub fn create_particle_draw_pipeline(
+    device: Res<Device>,
+    (swapchain, depth_buffers): (Option<Res<Swapchain>>, Option<Res<DepthBuffers>>),
+    (pipeline, compute): (Option<Res<Pipeline>>, Option<Res<ParticleComputePipeline>>),
+    render_pass: Option<Res<ClassicRenderPass>>,
+    existing: Option<Res<ParticleDrawPipeline>>,
+    mut commands: Commands,
+) -> Result<(), GpuParticleError> {
+    let (Some(swapchain), Some(depth_buffers), Some(pipeline), Some(compute)) =
+        (swapchain, depth_buffers, pipeline, compute)
+    else {
+        return Ok(());
+    };
+    if existing.is_some() {
+        return Ok(());
+    }
+
+    debug!(target: log_targets::PIPELINE, "Creating particle draw pipeline");
+
+    let push_constant_ranges = [vk::PushConstantRange::default()
+        .stage_flags(vk::ShaderStageFlags::VERTEX)
+        .offset(0)
+        .size(size_of::<DrawConstants>() as u32)];
+    let set_layouts = [
+        pipeline.descriptor_set_layout,
+        compute.descriptor_set_layout,
+    ];
+    let layout_info = vk::PipelineLayoutCreateInfo::default()
+        .set_layouts(&set_layouts)
+        .push_constant_ranges(&push_constant_ranges);
+    let pipeline_layout = unsafe { device.create_pipeline_layout(&layout_info, None)? };
+
+    let result = create_particle_draw_pipeline_object(
+        &device,
+        pipeline_layout,
+        swapchain.format.format,
+        depth_buffers.depth_format,
+        render_pass.as_deref(),
+    );
+    let pipeline = match result {
+        Ok(pipeline) => pipeline,
+        Err(err) => {
+            unsafe { device.destroy_pipeline_layout(pipeline_layout, None) };
+            return Err(err);
+        }
+    };
+
+    commands.insert_resource(ParticleDrawPipeline {
+        pipeline,
+        pipeline_layout,
+    });
+
+    Ok(())
+}
+
+fn create_particle_draw_pipeline_object(
+    device: &Device,
+    layout: vk::PipelineLayout,
+    color_format: vk::Format,
+    depth_format: vk::Format,
+    render_pass: Option<&ClassicRenderPass>,
+) -> Result<vk::Pipeline, GpuParticleError> {
+    let read =
+        |spv: &[u8]| read_spv(&mut io::Cursor::new(spv)).map_err(GpuParticleError::InvalidSpirv);
+    let vertex_code = read(include_bytes!("../shaders/particles_vert.spv"))?;
+    let fragment_code = read(include_bytes!("../shaders/particles_frag.spv"))?;
+
+    let vertex_info = vk::ShaderModuleCreateInfo::default().code(&vertex_code);
+    let vertex = unsafe { device.create_shader_module(&vertex_info, None)? };
+    let fragment_info = vk::ShaderModuleCreateInfo::default().code(&fragment_code);
+    let fragment = match unsafe { device.create_shader_module(&fragment_info, None) } {
+        Ok(fragment) => fragment,
+        Err(err) => {
+            unsafe { device.destroy_shader_module(vertex, None) };
+            return Err(err.into());
+        }
+    };
+
+    let stages = [
+        vk::PipelineShaderStageCreateInfo::default()
+            .stage(vk::ShaderStageFlags::VERTEX)
+            .module(vertex)
+            .name(c"main"),
+        vk::PipelineShaderStageCreateInfo::default()
+            .stage(vk::ShaderStageFlags::FRAGMENT)
+            .module(fragment)
+            .name(c"main"),
+    ];
+
+    // The quad corners are generated from the vertex index
+    let vertex_input = vk::PipelineVertexInputStateCreateInfo::default();
+    let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+    let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+        .viewport_count(1)
+        .scissor_count(1);
+    let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state =
+        vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+    let rasterization = vk::PipelineRasterizationStateCreateInfo::default()
+        .polygon_mode(vk::PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(vk::CullModeFlags::NONE);
+    let multisample = vk::PipelineMultisampleStateCreateInfo::default()
+        .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+    // Tested against the opaque geometry but not written, the particles are not sorted
+    let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default()
+        .depth_test_enable(true)
+        .depth_write_enable(false)
+        .depth_compare_op(vk::CompareOp::LESS)
+        .max_depth_bounds(1.0);
+    let attachments = [vk::PipelineColorBlendAttachmentState::default()
+        .color_write_mask(vk::ColorComponentFlags::RGBA)
+        .blend_enable(true)
+        .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+        .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+        .color_blend_op(vk::BlendOp::ADD)
+        .src_alpha_blend_factor(vk::BlendFactor::ONE)
+        .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+        .alpha_blend_op(vk::BlendOp::ADD)];
+    let color_blend = vk::PipelineColorBlendStateCreateInfo::default().attachments(&attachments);
+
+    let color_formats = [color_format];
+    let mut rendering_info = vk::PipelineRenderingCreateInfo::default()
+        .color_attachment_formats(&color_formats)
+        .depth_attachment_format(depth_format);
+    let info = vk::GraphicsPipelineCreateInfo::default()
+        .stages(&stages)
+        .vertex_input_state(&vertex_input)
+        .input_assembly_state(&input_assembly)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization)
+        .multisample_state(&multisample)
+        .depth_stencil_state(&depth_stencil)
+        .color_blend_state(&color_blend)
+        .dynamic_state(&dynamic_state)
+        .layout(layout);
+    let info = match render_pass {
+        Some(render_pass) => info.render_pass(render_pass.render_pass).subpass(0),
+        None => info.push_next(&mut rendering_info),
+    };
+
+    let pipelines =
+        unsafe { device.create_graphics_pipelines(vk::PipelineCache::null(), &[info], None) };
+    unsafe {
+        device.destroy_shader_module(vertex, None);
+        device.destroy_shader_module(fragment, None);
+    }
+
+    Ok(pipelines.map_err(|(_, result)| result)?[0])
+}
+
+fn create_particle_buffers(
+    device: &Device,
+    allocator: &GpuAllocator,
+    capacity: u32,
+) -> Result<ParticleBuffers, vk::Result> {
+    let (particles, particles_memory) = create_buffer(
+        device,
+        allocator,
+        (size_of::<GpuParticle>() as u64) * u64::from(capacity.max(1)),
+        vk::BufferUsageFlags::STORAGE_BUFFER,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )?;
+
+    let draw_size = size_of::<vk::DrawIndirectCommand>() as u64;
+    let (draw, draw_memory) = create_buffer(
+        device,
+        allocator,
+        draw_size,
+        vk::BufferUsageFlags::STORAGE_BUFFER
+            | vk::BufferUsageFlags::INDIRECT_BUFFER
+            | vk::BufferUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+    )?;
+
+    let initial = vk::DrawIndirectCommand {
+        vertex_count: QUAD_VERTEX_COUNT,
+        instance_count: 0,
+        first_vertex: 0,
+        first_instance: 0,
+    };
+    let memory = draw_memory
+        .mapped_ptr()
+        .expect("Host visible allocations are mapped");
+    unsafe { memory.cast::<vk::DrawIndirectCommand>().write(initial) };
+
+    Ok(ParticleBuffers {
+        particles,
+        particles_memory,
+        draw,
+        draw_memory,
+    })
+}
+
+fn create_emitter_state(
+    device: &Device,
+    allocator: &GpuAllocator,
+    pipeline: &ParticleComputePipeline,
+    capacity: u32,
+) -> Result<GpuEmitterState, vk::Result> {
+    let buffers = PingPong::new(
+        create_particle_buffers(device, allocator, capacity)?,
+        create_particle_buffers(device, allocator, capacity)?,
+    );
+
+    let layouts = [pipeline.descriptor_set_layout; 2];
+    let info = vk::DescriptorSetAllocateInfo::default()
+        .descriptor_pool(pipeline.descriptor_pool)
+        .set_layouts(&layouts);
+    let sets = unsafe { device.allocate_descriptor_sets(&info)? };
+
+    let [a, b] = buffers.items;
+    for (set, (source, target)) in sets.iter().zip([(a, b), (b, a)]) {
+        let infos = [source.particles, source.draw, target.particles, target.draw].map(|buffer| {
+            [vk::DescriptorBufferInfo::default()
+                .buffer(buffer)
+                .range(vk::WHOLE_SIZE)]
+        });
+        let writes: Vec<_> = infos
+            .iter()
+            .enumerate()
+            .map(|(binding, info)| {
+                vk::WriteDescriptorSet::default()
+                    .dst_set(*set)
+                    .dst_binding(binding as u32)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(info)
+            })
+            .collect();
+        unsafe { device.update_descriptor_sets(&writes, &[]) };
+    }
+
+    Ok(GpuEmitterState {
+        buffers,
+        descriptor_sets: [sets[0], sets[1]],
+        constants: SimulationConstants::default(),
+    })
+}
+
+/// Creates the buffers of new GPU emitters, swaps the buffers of the existing ones and updates
+/// the simulation constants of the frame.
+pub fn prepare_gpu_particles(
+    device: Res<Device>,
+    allocator: Res<GpuAllocator>,
+    pipeline: Option<Res<ParticleComputePipeline>>,
+    time: Option<Res<Time>>,
+    emitters: Query<&mut GpuParticleEmitter>,
+) -> Result<(), vk::Result> {
+    let Some(pipeline) = pipeline else {
+        return Ok(());
+    };
+    let delta = time.map_or(0.0, |time| time.delta_secs());
+
+    for emitter in emitters {
+        if emitter.state.is_none() {
+            debug!(
+                target: log_targets::RESOURCES,
+                "Creating particle buffers for {} particles",
+                emitter.capacity
+            );
+            emitter.state = Some(create_emitter_state(
+                &device,
+                &allocator,
+                &pipeline,
+                emitter.capacity,
+            )?);
+        }
+        emitter.advance(delta);
+    }
+
+    Ok(())
+}
+
+/// Records the simulation of the emitter for this frame, the particles are ready for
+/// [`record_gpu_particle_draw`] afterward. The buffer accesses are declared to the `validator`.
+///
+/// # Safety
+/// `command_buffer` must be recording outside of a render pass.
+pub unsafe fn record_gpu_particle_simulation(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    pipeline: &ParticleComputePipeline,
+    emitter: &GpuParticleEmitter,
+    validator: &BarrierValidator,
+) {
+    let Some(state) = &emitter.state else {
+        return;
+    };
+    let source = *state.buffers.read();
+    let target = *state.buffers.write();
+    let descriptor_set = state.descriptor_sets[state.buffers.read_index()];
+
+    // The target buffers were drawn from and simulated from in earlier frames
+    let drawn_stages = vk::PipelineStageFlags::DRAW_INDIRECT
+        | vk::PipelineStageFlags::VERTEX_INPUT
+        | vk::PipelineStageFlags::VERTEX_SHADER
+        | vk::PipelineStageFlags::COMPUTE_SHADER;
+    let reused_stages = vk::PipelineStageFlags::TRANSFER | vk::PipelineStageFlags::COMPUTE_SHADER;
+    let reuse_barriers = [
+        (
+            target.draw,
+            vk::AccessFlags::INDIRECT_COMMAND_READ | vk::AccessFlags::SHADER_READ,
+        ),
+        (target.particles, vk::AccessFlags::SHADER_READ),
+    ]
+    .map(|(buffer, src_access_mask)| {
+        vk::BufferMemoryBarrier::default()
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE | vk::AccessFlags::SHADER_WRITE)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(buffer)
+            .size(vk::WHOLE_SIZE)
+    });
+
+    let instance_count_offset = std::mem::offset_of!(vk::DrawIndirectCommand, instance_count);
+    let reset_barrier = vk::BufferMemoryBarrier::default()
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .buffer(target.draw)
+        .size(vk::WHOLE_SIZE);
+
+    // The particles are read by the simulation of the next frame as well
+    let simulated_stages = vk::PipelineStageFlags::DRAW_INDIRECT
+        | vk::PipelineStageFlags::VERTEX_SHADER
+        | vk::PipelineStageFlags::COMPUTE_SHADER;
+    let simulated_barriers = [target.particles, target.draw].map(|buffer| {
+        vk::BufferMemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(vk::AccessFlags::INDIRECT_COMMAND_READ | vk::AccessFlags::SHADER_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(buffer)
+            .size(vk::WHOLE_SIZE)
+    });
+
+    let compute = vk::PipelineStageFlags::COMPUTE_SHADER;
+    validator.buffer_barriers(drawn_stages, reused_stages, &reuse_barriers);
+    validator.begin_pass("particle reset");
+    validator.write(
+        GpuResource::Buffer(target.draw),
+        vk::PipelineStageFlags::TRANSFER,
+        vk::AccessFlags::TRANSFER_WRITE,
+    );
+    validator.buffer_barriers(vk::PipelineStageFlags::TRANSFER, compute, &[reset_barrier]);
+
+    validator.begin_pass("particle simulation");
+    validator.read(
+        GpuResource::Buffer(source.particles),
+        compute,
+        vk::AccessFlags::SHADER_READ,
+    );
+    for buffer in [target.particles, target.draw] {
+        let buffer = GpuResource::Buffer(buffer);
+        validator.read(buffer, compute, vk::AccessFlags::SHADER_READ);
+        validator.write(buffer, compute, vk::AccessFlags::SHADER_WRITE);
+    }
+    validator.buffer_barriers(compute, simulated_stages, &simulated_barriers);
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            drawn_stages,
+            reused_stages,
+            vk::DependencyFlags::empty(),
+            &[],
+            &reuse_barriers,
+            &[],
+        );
+        device.cmd_fill_buffer(
+            command_buffer,
+            target.draw,
+            instance_count_offset as u64,
+            4,
+            0,
+        );
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[reset_barrier],
+            &[],
+        );
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline.pipeline,
+        );
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            pipeline.pipeline_layout,
+            0,
+            &[descriptor_set],
+            &[],
+        );
+        device.cmd_push_constants(
+            command_buffer,
+            pipeline.pipeline_layout,
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            state.constants.as_bytes(),
+        );
+        device.cmd_dispatch(command_buffer, dispatch_group_count(emitter.capacity), 1, 1);
+
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            simulated_stages,
+            vk::DependencyFlags::empty(),
+            &[],
+            &simulated_barriers,
+            &[],
+        );
+    }
+}
+
+/// Draws the particles the emitter simulated this frame, the vertex shader reads them from the
+/// particle storage buffer by instance index.
+///
+/// # Safety
+/// `command_buffer` must be recording inside a render pass with the [`ParticleDrawPipeline`] and
+/// the camera uniforms at set `0` bound, after [`record_gpu_particle_simulation`] of the emitter.
+pub unsafe fn record_gpu_particle_draw(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    pipeline: &ParticleDrawPipeline,
+    emitter: &GpuParticleEmitter,
+    validator: &BarrierValidator,
+) {
+    let Some(state) = &emitter.state else {
+        return;
+    };
+    let drawn = *state.buffers.write();
+    let settings = &emitter.settings;
+    let constants = DrawConstants {
+        color_start: settings.color_start,
+        color_end: settings.color_end,
+        size: settings.size,
+        _padding: [0; 3],
+    };
+
+    validator.begin_pass("particle draw");
+    validator.read(
+        GpuResource::Buffer(drawn.draw),
+        vk::PipelineStageFlags::DRAW_INDIRECT,
+        vk::AccessFlags::INDIRECT_COMMAND_READ,
+    );
+    validator.read(
+        GpuResource::Buffer(drawn.particles),
+        vk::PipelineStageFlags::VERTEX_SHADER,
+        vk::AccessFlags::SHADER_READ,
+    );
+
+    unsafe {
+        // The simulation set binds this frame's target particles at binding 2
+        device.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            pipeline.pipeline_layout,
+            1,
+            &[state.descriptor_sets[state.buffers.read_index()]],
+            &[],
+        );
+        device.cmd_push_constants(
+            command_buffer,
+            pipeline.pipeline_layout,
+            vk::ShaderStageFlags::VERTEX,
+            0,
+            constants.as_bytes(),
+        );
+        device.cmd_draw_indirect(
+            command_buffer,
+            drawn.draw,
+            0,
+            1,
+            size_of::<vk::DrawIndirectCommand>() as u32,
+        );
+    }
+}
+
+pub fn destroy_gpu_particles(
+    device: Res<Device>,
+    allocator: Res<GpuAllocator>,
+    validator: Res<BarrierValidator>,
+    (pipeline, draw_pipeline): (
+        Option<Res<ParticleComputePipeline>>,
+        Option<Res<ParticleDrawPipeline>>,
+    ),
+    emitters: Query<&mut GpuParticleEmitter>,
+    mut commands: Commands,
+) {
+    for emitter in emitters {
+        let Some(state) = emitter.state.take() else {
+            continue;
+        };
+        for buffers in state.buffers.iter() {
+            validator.forget(GpuResource::Buffer(buffers.particles));
+            validator.forget(GpuResource::Buffer(buffers.draw));
+            unsafe {
+                device.destroy_buffer(buffers.particles, None);
+                device.destroy_buffer(buffers.draw, None);
+            }
+            allocator.free(&device, buffers.particles_memory);
+            allocator.free(&device, buffers.draw_memory);
+        }
+    }
+
+    if let Some(draw_pipeline) = draw_pipeline {
+        debug!(target: log_targets::PIPELINE, "Destroying particle draw pipeline");
+        unsafe {
+            device.destroy_pipeline(draw_pipeline.pipeline, None);
+            device.destroy_pipeline_layout(draw_pipeline.pipeline_layout, None);
+        }
+        commands.remove_resource::<ParticleDrawPipeline>();
+    }
+
+    let Some(pipeline) = pipeline else {
+        return;
+    };
+
+    debug!(target: log_targets::PIPELINE, "Destroying particle compute pipeline");
+
+    unsafe {
+        device.destroy_descriptor_pool(pipeline.descriptor_pool, None);
+        device.destroy_pipeline(pipeline.pipeline, None);
+        device.destroy_pipeline_layout(pipeline.pipeline_layout, None);
+        device.destroy_descriptor_set_layout(pipeline.descriptor_set_layout, None);
+    }
+
+    commands.remove_resource::<ParticleComputePipeline>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emitters_alternate_buffers_and_spawn_whole_particles() {
+        let mut buffers = PingPong::new('a', 'b');
+        assert_eq!((*buffers.read(), *buffers.write()), ('a', 'b'));
+        buffers.swap();
+        assert_eq!((*buffers.read(), *buffers.write()), ('b', 'a'));
+
+        let mut accumulator = 0.0;
+        assert_eq!(take_spawn_count(&mut accumulator, 10.0, 0.25, 100), 2);
+        assert_eq!(take_spawn_count(&mut accumulator, 10.0, 0.25, 100), 3);
+        assert_eq!(take_spawn_count(&mut accumulator, 1000.0, 1.0, 100), 100);
+
+        assert_eq!(dispatch_group_count(1), 1);
+        assert_eq!(dispatch_group_count(128), 2);
+        assert_eq!(size_of::<SimulationConstants>(), 80);
+        assert_eq!(size_of::<DrawConstants>(), 48);
+    }
+}
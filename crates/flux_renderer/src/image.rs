@@ -1,18 +1,17 @@
-use crate::device::{Device, PhysicalDevice};
-use crate::instance::VulkanInstance;
+use crate::allocator::{Allocation, GpuAllocator};
+use crate::device::Device;
 use ash::vk;
 
 pub fn create_image(
-    instance: &VulkanInstance,
-    physical_device: &PhysicalDevice,
     device: &Device,
+    allocator: &GpuAllocator,
     width: u32,
     height: u32,
     format: vk::Format,
     tiling: vk::ImageTiling,
     usage: vk::ImageUsageFlags,
     properties: vk::MemoryPropertyFlags,
-) -> Result<(vk::Image, vk::DeviceMemory), vk::Result> {
+) -> Result<(vk::Image, Allocation), vk::Result> {
     let info = vk::ImageCreateInfo::default()
         .image_type(vk::ImageType::TYPE_2D)
         .extent(vk::Extent3D {
@@ -34,40 +33,23 @@ pub fn create_image(
 
     let requirements = unsafe { device.get_image_memory_requirements(image) };
 
-    let info = vk::MemoryAllocateInfo::default()
-        .allocation_size(requirements.size)
-        .memory_type_index(
-            get_memory_type_index(instance, physical_device, properties, requirements).unwrap(),
-        );
-
-    let image_memory = unsafe { device.allocate_memory(&info, None)? };
-
-    unsafe {
-        device.bind_image_memory(image, image_memory, 0)?;
-    }
-
-    Ok((image, image_memory))
-}
-
-pub fn get_memory_type_index(
-    instance: &VulkanInstance,
-    physical_device: &PhysicalDevice,
-    properties: vk::MemoryPropertyFlags,
-    requirements: vk::MemoryRequirements,
-) -> Option<u32> {
-    let mut memory_properties = vk::PhysicalDeviceMemoryProperties2::default();
-    unsafe {
-        instance.get_physical_device_memory_properties2(**physical_device, &mut memory_properties)
+    let allocation = match allocator.allocate(device, requirements, properties) {
+        Ok(allocation) => allocation,
+        Err(err) => {
+            unsafe { device.destroy_image(image, None) };
+            return Err(err);
+        }
     };
 
-    (0..memory_properties.memory_properties.memory_type_count).find(|i| {
-        let suitable = (requirements.memory_type_bits & (1 << i) as u32) != 0;
-        let memory_type = memory_properties.memory_properties.memory_types[*i as usize];
-
-        let has_required_properties = memory_type.property_flags.contains(properties);
+    let bound =
+        unsafe { device.bind_image_memory(image, allocation.memory(), allocation.offset()) };
+    if let Err(err) = bound {
+        unsafe { device.destroy_image(image, None) };
+        allocator.free(device, allocation);
+        return Err(err);
+    }
 
-        suitable && has_required_properties
-    })
+    Ok((image, allocation))
 }
 
 pub fn create_image_view(
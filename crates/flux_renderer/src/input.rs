@@ -0,0 +1,302 @@
+use flux_ecs::world::World;
+use flux_input::raw::{Axis, AxisInput, ButtonInput, GamepadButton, Key, MouseButton};
+use std::hash::Hash;
+use winit::event::{DeviceEvent, ElementState, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+/// Touchpads scroll in pixels, the wheel axis counts lines.
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// Feeds the keys, mouse buttons and mouse wheel of a window event into the raw input resources
+/// of the [`InputPlugin`](flux_input::InputPlugin). Does nothing without the plugin.
+pub(crate) fn feed_window_event(world: &mut World, event: &WindowEvent) {
+    match event {
+        WindowEvent::KeyboardInput { event, .. } => {
+            if let PhysicalKey::Code(code) = event.physical_key {
+                set_button(world, key(code), event.state);
+            }
+        }
+        WindowEvent::MouseInput { state, button, .. } => {
+            set_button(world, mouse_button(*button), *state);
+        }
+        WindowEvent::MouseWheel { delta, .. } => {
+            let lines = match delta {
+                MouseScrollDelta::LineDelta(_, y) => *y,
+                MouseScrollDelta::PixelDelta(position) => (position.y / PIXELS_PER_LINE) as f32,
+            };
+            add_to_axis(world, Axis::MouseWheel, lines);
+        }
+        _ => {}
+    }
+}
+
+/// Accumulates the raw mouse movement, which is not affected by the cursor hitting the edge of
+/// the screen.
+pub(crate) fn feed_device_event(world: &mut World, event: &DeviceEvent) {
+    if let DeviceEvent::MouseMotion { delta: (x, y) } = event {
+        add_to_axis(world, Axis::MouseX, *x as f32);
+        add_to_axis(world, Axis::MouseY, *y as f32);
+    }
+}
+
+/// Ends the input frame after the app was updated: buttons are only just pressed or released
+/// for one frame and the mouse axes hold the movement since the last frame.
+pub(crate) fn end_input_frame(world: &mut World) {
+    clear_buttons::<Key>(world);
+    clear_buttons::<MouseButton>(world);
+    clear_buttons::<GamepadButton>(world);
+    if let Some(axes) = world.get_resource_mut::<AxisInput>() {
+        for axis in [Axis::MouseX, Axis::MouseY, Axis::MouseWheel] {
+            axes.set(axis, 0.0);
+        }
+    }
+}
+
+fn set_button<T>(world: &mut World, button: Option<T>, state: ElementState)
+where
+    T: Copy + Eq + Hash + Send + Sync + 'static,
+{
+    let (Some(button), Some(input)) = (button, world.get_resource_mut::<ButtonInput<T>>()) else {
+        return;
+    };
+    match state {
+        ElementState::Pressed => input.press(button),
+        ElementState::Released => input.release(button),
+    }
+}
+
+fn clear_buttons<T>(world: &mut World)
+where
+    T: Copy + Eq + Hash + Send + Sync + 'static,
+{
+    if let Some(input) = world.get_resource_mut::<ButtonInput<T>>() {
+        input.clear();
+    }
+}
+
+fn add_to_axis(world: &mut World, axis: Axis, value: f32) {
+    if let Some(axes) = world.get_resource_mut::<AxisInput>() {
+        axes.set(axis, axes.get(axis) + value);
+    }
+}
+
+fn key(code: KeyCode) -> Option<Key> {
+    let key = match code {
+        KeyCode::KeyA => Key::A,
+        KeyCode::KeyB => Key::B,
+        KeyCode::KeyC => Key::C,
+        KeyCode::KeyD => Key::D,
+        KeyCode::KeyE => Key::E,
+        KeyCode::KeyF => Key::F,
+        KeyCode::KeyG => Key::G,
+        KeyCode::KeyH => Key::H,
+        KeyCode::KeyI => Key::I,
+        KeyCode::KeyJ => Key::J,
+        KeyCode::KeyK => Key::K,
+        KeyCode::KeyL => Key::L,
+        KeyCode::KeyM => Key::M,
+        KeyCode::KeyN => Key::N,
+        KeyCode::KeyO => Key::O,
+        KeyCode::KeyP => Key::P,
+        KeyCode::KeyQ => Key::Q,
+        KeyCode::KeyR => Key::R,
+        KeyCode::KeyS => Key::S,
+        KeyCode::KeyT => Key::T,
+        KeyCode::KeyU => Key::U,
+        KeyCode::KeyV => Key::V,
+        KeyCode::KeyW => Key::W,
+        KeyCode::KeyX => Key::X,
+        KeyCode::KeyY => Key::Y,
+        KeyCode::KeyZ => Key::Z,
+        KeyCode::Digit0 => Key::Digit0,
+        KeyCode::Digit1 => Key::Digit1,
+        KeyCode::Digit2 => Key::Digit2,
+        KeyCode::Digit3 => Key::Digit3,
+        KeyCode::Digit4 => Key::Digit4,
+        KeyCode::Digit5 => Key::Digit5,
+        KeyCode::Digit6 => Key::Digit6,
+        KeyCode::Digit7 => Key::Digit7,
+        KeyCode::Digit8 => Key::Digit8,
+        KeyCode::Digit9 => Key::Digit9,
+        KeyCode::ArrowUp => Key::Up,
+        KeyCode::ArrowDown => Key::Down,
+        KeyCode::ArrowLeft => Key::Left,
+        KeyCode::ArrowRight => Key::Right,
+        KeyCode::Space => Key::Space,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Escape => Key::Escape,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::ShiftLeft => Key::ShiftLeft,
+        KeyCode::ShiftRight => Key::ShiftRight,
+        KeyCode::ControlLeft => Key::ControlLeft,
+        KeyCode::ControlRight => Key::ControlRight,
+        KeyCode::AltLeft => Key::AltLeft,
+        KeyCode::AltRight => Key::AltRight,
+        KeyCode::F1 => Key::F1,
+        KeyCode::F2 => Key::F2,
+        KeyCode::F3 => Key::F3,
+        KeyCode::F4 => Key::F4,
+        KeyCode::F5 => Key::F5,
+        KeyCode::F6 => Key::F6,
+        KeyCode::F7 => Key::F7,
+        KeyCode::F8 => Key::F8,
+        KeyCode::F9 => Key::F9,
+        KeyCode::F10 => Key::F10,
+        KeyCode::F11 => Key::F11,
+        KeyCode::F12 => Key::F12,
+        _ => return None,
+    };
+    Some(key)
+}
+
+fn mouse_button(button: winit::event::MouseButton) -> Option<MouseButton> {
+    let button = match button {
+        winit::event::MouseButton::Left => MouseButton::Left,
+        winit::event::MouseButton::Right => MouseButton::Right,
+        winit::event::MouseButton::Middle => MouseButton::Middle,
+        winit::event::MouseButton::Back => MouseButton::Back,
+        winit::event::MouseButton::Forward => MouseButton::Forward,
+        winit::event::MouseButton::Other(_) => return None,
+    };
+    Some(button)
+}
+
+/// Feeds the buttons, sticks and triggers of all connected gamepads into the raw input resources,
+/// they act as a single gamepad.
+#[cfg(feature = "gamepad")]
+pub(crate) struct Gamepads {
+    gilrs: gilrs::Gilrs,
+}
+
+#[cfg(feature = "gamepad")]
+impl Gamepads {
+    /// `None` if the platform does not support gamepads.
+    pub(crate) fn new() -> Option<Self> {
+        match gilrs::Gilrs::new() {
+            Ok(gilrs) => Some(Self { gilrs }),
+            Err(err) => {
+                log::warn!(target: crate::log_targets::INPUT, "Gamepads are unavailable: {err}");
+                None
+            }
+        }
+    }
+
+    /// Applies the gamepad events received since the last call.
+    pub(crate) fn feed(&mut self, world: &mut World) {
+        use gilrs::EventType;
+
+        while let Some(gilrs::Event { event, .. }) = self.gilrs.next_event() {
+            match event {
+                EventType::ButtonPressed(button, _) => {
+                    set_button(world, gamepad_button(button), ElementState::Pressed);
+                }
+                EventType::ButtonReleased(button, _) => {
+                    set_button(world, gamepad_button(button), ElementState::Released);
+                }
+                EventType::ButtonChanged(gilrs::Button::LeftTrigger2, value, _) => {
+                    set_axis(world, Axis::GamepadLeftTrigger, value);
+                }
+                EventType::ButtonChanged(gilrs::Button::RightTrigger2, value, _) => {
+                    set_axis(world, Axis::GamepadRightTrigger, value);
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    if let Some(axis) = gamepad_axis(axis) {
+                        set_axis(world, axis, value);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(feature = "gamepad")]
+fn set_axis(world: &mut World, axis: Axis, value: f32) {
+    if let Some(axes) = world.get_resource_mut::<AxisInput>() {
+        axes.set(axis, value);
+    }
+}
+
+#[cfg(feature = "gamepad")]
+fn gamepad_button(button: gilrs::Button) -> Option<GamepadButton> {
+    let button = match button {
+        gilrs::Button::South => GamepadButton::South,
+        gilrs::Button::East => GamepadButton::East,
+        gilrs::Button::North => GamepadButton::North,
+        gilrs::Button::West => GamepadButton::West,
+        gilrs::Button::LeftTrigger => GamepadButton::LeftBumper,
+        gilrs::Button::RightTrigger => GamepadButton::RightBumper,
+        gilrs::Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+        gilrs::Button::RightTrigger2 => GamepadButton::RightTrigger,
+        gilrs::Button::Select => GamepadButton::Select,
+        gilrs::Button::Start => GamepadButton::Start,
+        gilrs::Button::Mode => GamepadButton::Mode,
+        gilrs::Button::LeftThumb => GamepadButton::LeftThumb,
+        gilrs::Button::RightThumb => GamepadButton::RightThumb,
+        gilrs::Button::DPadUp => GamepadButton::DPadUp,
+        gilrs::Button::DPadDown => GamepadButton::DPadDown,
+        gilrs::Button::DPadLeft => GamepadButton::DPadLeft,
+        gilrs::Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    };
+    Some(button)
+}
+
+#[cfg(feature = "gamepad")]
+fn gamepad_axis(axis: gilrs::Axis) -> Option<Axis> {
+    let axis = match axis {
+        gilrs::Axis::LeftStickX => Axis::GamepadLeftX,
+        gilrs::Axis::LeftStickY => Axis::GamepadLeftY,
+        gilrs::Axis::RightStickX => Axis::GamepadRightX,
+        gilrs::Axis::RightStickY => Axis::GamepadRightY,
+        // Some gamepads report their analog triggers as axes instead of buttons
+        gilrs::Axis::LeftZ => Axis::GamepadLeftTrigger,
+        gilrs::Axis::RightZ => Axis::GamepadRightTrigger,
+        _ => return None,
+    };
+    Some(axis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::event::DeviceId;
+
+    #[test]
+    fn window_and_device_events_feed_the_raw_input() {
+        let mut world = World::new();
+        world.add_resource(ButtonInput::<MouseButton>::default());
+        world.add_resource(AxisInput::default());
+        let device_id = DeviceId::dummy();
+
+        let press = WindowEvent::MouseInput {
+            device_id,
+            state: ElementState::Pressed,
+            button: winit::event::MouseButton::Left,
+        };
+        feed_window_event(&mut world, &press);
+        feed_device_event(&mut world, &DeviceEvent::MouseMotion { delta: (2.0, -1.0) });
+        feed_device_event(&mut world, &DeviceEvent::MouseMotion { delta: (3.0, 0.0) });
+
+        let buttons = world.get_resource::<ButtonInput<MouseButton>>().unwrap();
+        assert!(buttons.just_pressed(MouseButton::Left));
+        let axes = world.get_resource::<AxisInput>().unwrap();
+        assert_eq!(axes.get(Axis::MouseX), 5.0);
+        assert_eq!(axes.get(Axis::MouseY), -1.0);
+
+        end_input_frame(&mut world);
+        let buttons = world.get_resource::<ButtonInput<MouseButton>>().unwrap();
+        assert!(buttons.pressed(MouseButton::Left));
+        assert!(!buttons.just_pressed(MouseButton::Left));
+        let axes = world.get_resource::<AxisInput>().unwrap();
+        assert_eq!(axes.get(Axis::MouseX), 0.0);
+    }
+
+    #[test]
+    fn unmapped_keys_are_ignored() {
+        assert_eq!(key(KeyCode::KeyW), Some(Key::W));
+        assert_eq!(key(KeyCode::ArrowLeft), Some(Key::Left));
+        assert_eq!(key(KeyCode::NumLock), None);
+    }
+}
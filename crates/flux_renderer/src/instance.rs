@@ -1,25 +1,54 @@
 use ash::ext::debug_utils;
 use ash::vk::DebugUtilsMessengerEXT;
-use ash::{Instance, vk};
+use ash::{Instance, ext, vk};
 use flux_ecs::commands::Commands;
 use flux_ecs::resource::{Res, Resource};
-use log::{debug, error, info, warn};
+use crate::config::GraphicsSettings;
+use crate::log_targets;
+use crate::progress::InitializationProgress;
+use log::{Level, error, info, log};
 use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
 use std::collections::HashSet;
 use std::ffi::{CStr, c_void};
 use std::ops::Deref;
 
-const VALIDATION_ENABLED: bool = cfg!(debug_assertions);
+pub(crate) const VALIDATION_ENABLED: bool = cfg!(debug_assertions);
 const VALIDATION_LAYER: &CStr = c"VK_LAYER_KHRONOS_validation";
 
-pub trait SurfaceProvider {
-    fn get_display_handle(&self) -> RawDisplayHandle;
+/// Provides the native window the renderer presents to.
+///
+/// A provider without a display handle renders headless. A provider whose window handle is
+/// `None` currently has no native surface, e.g. an Android app between suspend and resume. The
+/// renderer tears down the surface and swapchain when the window handle goes away and rebuilds
+/// them once it is available again.
+pub trait SurfaceProvider: Send + Sync {
+    fn get_display_handle(&self) -> Option<RawDisplayHandle>;
 
-    fn get_window_handle(&self) -> RawWindowHandle;
+    fn get_window_handle(&self) -> Option<RawWindowHandle>;
 
     fn get_extent(&self) -> (u32, u32);
 }
 
+/// A surface provider without a window for headless rendering.
+pub struct NullSurfaceProvider {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl SurfaceProvider for NullSurfaceProvider {
+    fn get_display_handle(&self) -> Option<RawDisplayHandle> {
+        None
+    }
+
+    fn get_window_handle(&self) -> Option<RawWindowHandle> {
+        None
+    }
+
+    fn get_extent(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
 pub struct SurfaceProviderResource {
     pub provider: Box<dyn SurfaceProvider>,
 }
@@ -48,6 +77,19 @@ pub struct RendererSettings {
 
 impl Resource for RendererSettings {}
 
+/// Inserted instead of the [`VulkanInstance`] when Vulkan is not available on this system, e.g.
+/// the loader is not installed or no driver supports the requested version.
+///
+/// The systems using the instance are skipped and initialization fails, an
+/// [`App::on_init_error`](flux_ecs::app::App::on_init_error) handler can check for this resource
+/// to explain the problem to the user or to set up a fallback.
+#[derive(Debug, Clone)]
+pub struct RendererUnavailable {
+    pub reason: String,
+}
+
+impl Resource for RendererUnavailable {}
+
 pub struct VulkanInstance {
     pub(crate) entry: ash::Entry,
     pub(crate) instance: Instance,
@@ -65,12 +107,27 @@ impl Deref for VulkanInstance {
 }
 
 pub fn create_instance(
-    surface_provider_resource: Res<SurfaceProviderResource>,
+    surface_provider_resource: Option<Res<SurfaceProviderResource>>,
     renderer_settings: Option<Res<RendererSettings>>,
+    graphics_settings: Option<Res<GraphicsSettings>>,
     mut commands: Commands,
 ) -> Result<(), vk::Result> {
-    info!("Creating the vulkan instance");
-    let entry = ash::Entry::linked();
+    info!(target: log_targets::INSTANCE, "Creating the vulkan instance");
+    let validation_enabled = graphics_settings
+        .as_ref()
+        .map_or(VALIDATION_ENABLED, |settings| settings.validation);
+    let debug_printf_enabled = validation_enabled
+        && graphics_settings.map_or(VALIDATION_ENABLED, |settings| settings.shader_debug_printf);
+    let entry = match load_entry() {
+        Ok(entry) => entry,
+        Err(err) => {
+            error!(target: log_targets::INSTANCE, "Could not load the Vulkan loader: {err}");
+            commands.insert_resource(RendererUnavailable {
+                reason: format!("the Vulkan loader could not be loaded: {err}"),
+            });
+            return Ok(());
+        }
+    };
 
     // TODO: How do we make this configurable? As well as the application version?
     let app_name = renderer_settings.as_ref().map_or_else(
@@ -108,12 +165,13 @@ pub fn create_instance(
         .map(|l| unsafe { CStr::from_ptr(l.layer_name.as_ptr()) })
         .collect::<HashSet<_>>();
 
-    if VALIDATION_ENABLED && !available_layers.contains(&VALIDATION_LAYER) {
-        error!("Validation layers are not available");
+    if validation_enabled && !available_layers.contains(&VALIDATION_LAYER) {
+        error!(target: log_targets::INSTANCE, "Validation layers are not available");
     }
 
-    let enabled_layers = if VALIDATION_ENABLED {
+    let enabled_layers = if validation_enabled {
         info!(
+            target: log_targets::INSTANCE,
             "Enabling validation layers {}",
             VALIDATION_LAYER.to_str().unwrap()
         );
@@ -122,18 +180,27 @@ pub fn create_instance(
         Vec::new()
     };
 
-    let mut extensions = ash_window::enumerate_required_extensions(
-        surface_provider_resource.provider.get_display_handle(),
-    )?
-    .to_vec();
+    let display_handle =
+        surface_provider_resource.and_then(|provider| provider.get_display_handle());
+    let mut extensions = match display_handle {
+        Some(display_handle) => ash_window::enumerate_required_extensions(display_handle)?.to_vec(),
+        None => {
+            info!(target: log_targets::INSTANCE, "No display available, rendering headless");
+            Vec::new()
+        }
+    };
 
-    if VALIDATION_ENABLED {
+    if validation_enabled {
         extensions.push(debug_utils::NAME.as_ptr());
     }
+    if debug_printf_enabled {
+        info!(target: log_targets::INSTANCE, "Enabling shader debug printf");
+        extensions.push(ext::validation_features::NAME.as_ptr());
+    }
 
     #[cfg(any(target_os = "macos", target_os = "ios"))]
     {
-        info!("Enabling apple portability extensions");
+        info!(target: log_targets::INSTANCE, "Enabling apple portability extensions");
         extensions.push(ash::khr::portability_enumeration::NAME.as_ptr());
         extensions.push(ash::khr::get_physical_device_properties2::NAME.as_ptr());
     }
@@ -151,14 +218,34 @@ pub fn create_instance(
         .flags(create_flags);
 
     let mut debug_info = get_debug_messenger_create_info();
-    if VALIDATION_ENABLED {
+    if validation_enabled {
         create_info = create_info.push_next(&mut debug_info);
     }
 
-    let instance: Instance = unsafe { entry.create_instance(&create_info, None)? };
+    let enabled_validation_features = [vk::ValidationFeatureEnableEXT::DEBUG_PRINTF];
+    let mut validation_features = vk::ValidationFeaturesEXT::default()
+        .enabled_validation_features(&enabled_validation_features);
+    if debug_printf_enabled {
+        create_info = create_info.push_next(&mut validation_features);
+    }
+
+    let instance: Instance = match unsafe { entry.create_instance(&create_info, None) } {
+        Ok(instance) => instance,
+        Err(vk::Result::ERROR_INCOMPATIBLE_DRIVER) => {
+            error!(
+                target: log_targets::INSTANCE,
+                "No driver supports the requested Vulkan version"
+            );
+            commands.insert_resource(RendererUnavailable {
+                reason: "no installed driver supports Vulkan 1.4".to_string(),
+            });
+            return Ok(());
+        }
+        Err(err) => return Err(err),
+    };
 
     let mut debug_messenger = None;
-    if VALIDATION_ENABLED {
+    if validation_enabled {
         let debug_utils_loader = debug_utils::Instance::new(&entry, &instance);
         debug_messenger =
             unsafe { Some(debug_utils_loader.create_debug_utils_messenger(&debug_info, None)?) };
@@ -169,10 +256,22 @@ pub fn create_instance(
         instance,
         debug_messenger,
     });
+    commands.send_event(InitializationProgress::InstanceCreated);
 
     Ok(())
 }
 
+/// Links the loader with the `linked` feature, otherwise loads it from the system at runtime.
+#[cfg(feature = "linked")]
+fn load_entry() -> Result<ash::Entry, ash::LoadingError> {
+    Ok(ash::Entry::linked())
+}
+
+#[cfg(not(feature = "linked"))]
+fn load_entry() -> Result<ash::Entry, ash::LoadingError> {
+    unsafe { ash::Entry::load() }
+}
+
 fn get_debug_messenger_create_info<'a>() -> vk::DebugUtilsMessengerCreateInfoEXT<'a> {
     vk::DebugUtilsMessengerCreateInfoEXT::default()
         .message_severity(
@@ -200,21 +299,47 @@ extern "system" fn debug_callback(
     let message_id_number = data.message_id_number;
     let message = unsafe { CStr::from_ptr(data.p_message).to_string_lossy() };
 
-    if severity == vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE {
-        debug!("{type_:?} [{message_id_name} ({message_id_number})]: {message}");
+    if is_debug_printf(&message_id_name) {
+        info!(target: log_targets::SHADER_PRINTF, "{}", debug_printf_output(&message));
+        return vk::FALSE;
+    }
+
+    let level = if severity == vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE {
+        Level::Debug
     } else if severity == vk::DebugUtilsMessageSeverityFlagsEXT::INFO {
-        info!("{type_:?} [{message_id_name} ({message_id_number})]: {message}");
+        Level::Info
     } else if severity == vk::DebugUtilsMessageSeverityFlagsEXT::WARNING {
-        warn!("{type_:?} [{message_id_name} ({message_id_number})]: {message}");
-    } else if severity == vk::DebugUtilsMessageSeverityFlagsEXT::ERROR {
-        error!("{type_:?} [{message_id_name} ({message_id_number})]: {message}");
-    }
+        Level::Warn
+    } else {
+        Level::Error
+    };
+
+    log!(
+        target: log_targets::VALIDATION,
+        level,
+        "{type_:?} [{message_id_name} ({message_id_number})]: {message}"
+    );
 
     vk::FALSE
 }
 
+/// Shader printf messages are reported as `WARNING-DEBUG-PRINTF` by older validation layers and
+/// as `DEBUG-PRINTF` by newer ones.
+fn is_debug_printf(message_id_name: &str) -> bool {
+    message_id_name.ends_with("DEBUG-PRINTF")
+}
+
+/// Strips the object and message id header the validation layer puts in front of the printed
+/// text.
+fn debug_printf_output(message: &str) -> &str {
+    message
+        .rsplit_once(" | ")
+        .map_or(message, |(_, output)| output)
+        .trim_end()
+}
+
 pub fn destroy_instance(instance: Res<VulkanInstance>, mut commands: Commands) {
-    info!("Destroying vulkan instance");
+    info!(target: log_targets::INSTANCE, "Destroying vulkan instance");
     if let Some(debug_messenger) = instance.debug_messenger {
         unsafe {
             let debug_utils_loader = debug_utils::Instance::new(&instance.entry, &instance);
@@ -228,3 +353,21 @@ pub fn destroy_instance(instance: Res<VulkanInstance>, mut commands: Commands) {
 
     commands.remove_resource::<VulkanInstance>();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_printf_messages_are_recognized_and_stripped() {
+        assert!(is_debug_printf("WARNING-DEBUG-PRINTF"));
+        assert!(is_debug_printf("DEBUG-PRINTF"));
+        assert!(!is_debug_printf("VUID-vkCmdDraw-None-08600"));
+
+        let message = "Validation Information: [ WARNING-DEBUG-PRINTF ] Object 0: handle = \
+                       0x5630, type = VK_OBJECT_TYPE_QUEUE; | MessageID = 0x76589099 | \
+                       vertex 3 at 0.5, 1.0\n";
+        assert_eq!(debug_printf_output(message), "vertex 3 at 0.5, 1.0");
+        assert_eq!(debug_printf_output("value 1"), "value 1");
+    }
+}
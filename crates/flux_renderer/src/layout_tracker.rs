@@ -0,0 +1,402 @@
+use crate::log_targets;
+use crate::scratch::FrameScratch;
+use ash::vk;
+use flux_ecs::resource::Resource;
+use log::warn;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+struct TrackedImage {
+    aspect_mask: vk::ImageAspectFlags,
+    mip_levels: u32,
+    array_layers: u32,
+    /// The layout of every subresource, indexed by `layer * mip_levels + mip`.
+    layouts: Vec<vk::ImageLayout>,
+}
+
+impl TrackedImage {
+    fn index(&self, mip_level: u32, array_layer: u32) -> usize {
+        (array_layer * self.mip_levels + mip_level) as usize
+    }
+
+    /// Resolves `REMAINING_*` counts and clamps the range to the image in release builds.
+    fn resolve(&self, range: vk::ImageSubresourceRange) -> (u32, u32, u32, u32) {
+        let level_count = if range.level_count == vk::REMAINING_MIP_LEVELS {
+            self.mip_levels.saturating_sub(range.base_mip_level)
+        } else {
+            range.level_count
+        };
+        let layer_count = if range.layer_count == vk::REMAINING_ARRAY_LAYERS {
+            self.array_layers.saturating_sub(range.base_array_layer)
+        } else {
+            range.layer_count
+        };
+
+        debug_assert!(
+            range.base_mip_level + level_count <= self.mip_levels
+                && range.base_array_layer + layer_count <= self.array_layers,
+            "Subresource range {range:?} exceeds the image ({} mips, {} layers)",
+            self.mip_levels,
+            self.array_layers
+        );
+        debug_assert!(
+            self.aspect_mask.contains(range.aspect_mask),
+            "Aspect {:?} is not part of the image aspects {:?}",
+            range.aspect_mask,
+            self.aspect_mask
+        );
+
+        let base_mip = range.base_mip_level.min(self.mip_levels);
+        let base_layer = range.base_array_layer.min(self.array_layers);
+        (
+            base_mip,
+            level_count.min(self.mip_levels - base_mip),
+            base_layer,
+            layer_count.min(self.array_layers - base_layer),
+        )
+    }
+}
+
+/// Tracks the current layout of every subresource of the registered images and emits the
+/// barriers needed to move them into the layout a pass requires.
+///
+/// The tracked layouts reflect the order in which transitions are recorded, not the GPU timeline.
+#[derive(Default)]
+pub struct ImageLayoutTracker {
+    images: Mutex<HashMap<vk::Image, TrackedImage>>,
+}
+
+impl Resource for ImageLayoutTracker {}
+
+impl ImageLayoutTracker {
+    fn images(&self) -> MutexGuard<'_, HashMap<vk::Image, TrackedImage>> {
+        self.images.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn register(
+        &self,
+        image: vk::Image,
+        aspect_mask: vk::ImageAspectFlags,
+        mip_levels: u32,
+        array_layers: u32,
+        layout: vk::ImageLayout,
+    ) {
+        self.images().insert(
+            image,
+            TrackedImage {
+                aspect_mask,
+                mip_levels,
+                array_layers,
+                layouts: vec![layout; (mip_levels * array_layers) as usize],
+            },
+        );
+    }
+
+    /// Stops tracking the image, call it before the image is destroyed since handles are reused.
+    pub fn unregister(&self, image: vk::Image) {
+        self.images().remove(&image);
+    }
+
+    pub fn layout(
+        &self,
+        image: vk::Image,
+        mip_level: u32,
+        array_layer: u32,
+    ) -> Option<vk::ImageLayout> {
+        let images = self.images();
+        let tracked = images.get(&image)?;
+        if mip_level >= tracked.mip_levels || array_layer >= tracked.array_layers {
+            return None;
+        }
+        Some(tracked.layouts[tracked.index(mip_level, array_layer)])
+    }
+
+    /// Moves the subresources into `new_layout` and returns the barriers for the subresources
+    /// that were in a different layout. Neighbouring mip levels sharing a layout are merged into
+    /// a single barrier.
+    pub fn transition(
+        &self,
+        image: vk::Image,
+        range: vk::ImageSubresourceRange,
+        new_layout: vk::ImageLayout,
+    ) -> Vec<vk::ImageMemoryBarrier<'static>> {
+        let mut barriers = Vec::new();
+        self.transition_into(image, range, new_layout, &mut barriers);
+        barriers
+    }
+
+    /// Like [`ImageLayoutTracker::transition`], but appends the barriers to `barriers`, e.g. a
+    /// [`FrameScratch`] vector.
+    pub fn transition_into(
+        &self,
+        image: vk::Image,
+        range: vk::ImageSubresourceRange,
+        new_layout: vk::ImageLayout,
+        barriers: &mut Vec<vk::ImageMemoryBarrier<'static>>,
+    ) {
+        let mut images = self.images();
+        let Some(tracked) = images.get_mut(&image) else {
+            debug_assert!(
+                false,
+                "Image {image:?} is not registered in the layout tracker"
+            );
+            warn!(target: log_targets::RESOURCES, "Transitioning untracked image {image:?}");
+            barriers.push(barrier(
+                image,
+                range,
+                vk::ImageLayout::UNDEFINED,
+                new_layout,
+            ));
+            return;
+        };
+
+        let (base_mip, level_count, base_layer, layer_count) = tracked.resolve(range);
+        let first = barriers.len();
+
+        for layer in base_layer..base_layer + layer_count {
+            let mut mip = base_mip;
+            while mip < base_mip + level_count {
+                let old_layout = tracked.layouts[tracked.index(mip, layer)];
+                let run_start = mip;
+                while mip < base_mip + level_count
+                    && tracked.layouts[tracked.index(mip, layer)] == old_layout
+                {
+                    let index = tracked.index(mip, layer);
+                    tracked.layouts[index] = new_layout;
+                    mip += 1;
+                }
+
+                if old_layout != new_layout {
+                    let subresource = vk::ImageSubresourceRange::default()
+                        .aspect_mask(range.aspect_mask)
+                        .base_mip_level(run_start)
+                        .level_count(mip - run_start)
+                        .base_array_layer(layer)
+                        .layer_count(1);
+                    push_merged(
+                        barriers,
+                        first,
+                        barrier(image, subresource, old_layout, new_layout),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Records the barriers returned by [`ImageLayoutTracker::transition`] into the command
+    /// buffer, collecting them in a scratch vector.
+    ///
+    /// # Safety
+    /// The command buffer must be in the recording state and outside of a rendering pass.
+    pub unsafe fn record_transition(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        scratch: &FrameScratch,
+        image: vk::Image,
+        range: vk::ImageSubresourceRange,
+        new_layout: vk::ImageLayout,
+    ) {
+        let mut barriers = scratch.vec();
+        self.transition_into(image, range, new_layout, &mut barriers);
+        if barriers.is_empty() {
+            return;
+        }
+
+        let (src_stage, dst_stage) = barriers.iter().fold(
+            (
+                vk::PipelineStageFlags::empty(),
+                vk::PipelineStageFlags::empty(),
+            ),
+            |(src, dst), barrier| {
+                (
+                    src | layout_access(barrier.old_layout).0,
+                    dst | layout_access(barrier.new_layout).0,
+                )
+            },
+        );
+
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                src_stage,
+                dst_stage,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            );
+        }
+    }
+
+    /// Checks in debug builds that a pass using the subresources without a transition finds them
+    /// in the layout it expects.
+    pub fn expect_layout(
+        &self,
+        image: vk::Image,
+        range: vk::ImageSubresourceRange,
+        expected: vk::ImageLayout,
+    ) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        let images = self.images();
+        let Some(tracked) = images.get(&image) else {
+            panic!("Image {image:?} is not registered in the layout tracker");
+        };
+
+        let (base_mip, level_count, base_layer, layer_count) = tracked.resolve(range);
+        for layer in base_layer..base_layer + layer_count {
+            for mip in base_mip..base_mip + level_count {
+                let layout = tracked.layouts[tracked.index(mip, layer)];
+                assert_eq!(
+                    layout, expected,
+                    "Image {image:?} mip {mip} layer {layer} is in layout {layout:?}"
+                );
+            }
+        }
+    }
+}
+
+fn barrier(
+    image: vk::Image,
+    range: vk::ImageSubresourceRange,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+) -> vk::ImageMemoryBarrier<'static> {
+    vk::ImageMemoryBarrier::default()
+        .image(image)
+        .subresource_range(range)
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_access_mask(layout_access(old_layout).1)
+        .dst_access_mask(layout_access(new_layout).1)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+}
+
+/// Adds the barrier to `barriers`, merging it into a barrier from `first` on of the previous
+/// layer that covers the same mip levels with the same layouts.
+fn push_merged(
+    barriers: &mut Vec<vk::ImageMemoryBarrier<'static>>,
+    first: usize,
+    barrier: vk::ImageMemoryBarrier<'static>,
+) {
+    if let Some(previous) = barriers[first..].iter_mut().find(|previous| {
+        let range = previous.subresource_range;
+        previous.old_layout == barrier.old_layout
+            && range.base_mip_level == barrier.subresource_range.base_mip_level
+            && range.level_count == barrier.subresource_range.level_count
+            && range.base_array_layer + range.layer_count
+                == barrier.subresource_range.base_array_layer
+    }) {
+        previous.subresource_range.layer_count += 1;
+    } else {
+        barriers.push(barrier);
+    }
+}
+
+/// The pipeline stages and accesses that use an image in the given layout.
+fn layout_access(layout: vk::ImageLayout) -> (vk::PipelineStageFlags, vk::AccessFlags) {
+    match layout {
+        vk::ImageLayout::UNDEFINED | vk::ImageLayout::PREINITIALIZED => (
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::AccessFlags::empty(),
+        ),
+        vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL => (
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        ),
+        vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL
+        | vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL => (
+            vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        ),
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => (
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::AccessFlags::SHADER_READ,
+        ),
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL => (
+            vk::PipelineStageFlags::TRANSFER,
+            vk::AccessFlags::TRANSFER_READ,
+        ),
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL => (
+            vk::PipelineStageFlags::TRANSFER,
+            vk::AccessFlags::TRANSFER_WRITE,
+        ),
+        vk::ImageLayout::PRESENT_SRC_KHR => (
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            vk::AccessFlags::empty(),
+        ),
+        _ => (
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    fn color_range(base_mip_level: u32, level_count: u32) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(base_mip_level)
+            .level_count(level_count)
+            .base_array_layer(0)
+            .layer_count(vk::REMAINING_ARRAY_LAYERS)
+    }
+
+    #[test]
+    fn only_subresources_in_other_layouts_are_transitioned() {
+        let image = vk::Image::from_raw(1);
+        let tracker = ImageLayoutTracker::default();
+        tracker.register(
+            image,
+            vk::ImageAspectFlags::COLOR,
+            4,
+            2,
+            vk::ImageLayout::UNDEFINED,
+        );
+
+        let barriers = tracker.transition(
+            image,
+            color_range(0, 2),
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        );
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].subresource_range.layer_count, 2);
+
+        let barriers = tracker.transition(
+            image,
+            color_range(0, vk::REMAINING_MIP_LEVELS),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        );
+        assert_eq!(
+            barriers
+                .iter()
+                .map(|barrier| (barrier.old_layout, barrier.subresource_range.base_mip_level))
+                .collect::<Vec<_>>(),
+            [
+                (vk::ImageLayout::TRANSFER_DST_OPTIMAL, 0),
+                (vk::ImageLayout::UNDEFINED, 2),
+            ]
+        );
+
+        let barriers = tracker.transition(
+            image,
+            color_range(0, vk::REMAINING_MIP_LEVELS),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        );
+        assert!(barriers.is_empty());
+        assert_eq!(
+            tracker.layout(image, 3, 1),
+            Some(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+        );
+    }
+}
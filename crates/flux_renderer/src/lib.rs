@@ -1,84 +1,394 @@
+use crate::allocator::{create_gpu_allocator, destroy_gpu_allocator};
+use crate::capture::destroy_frame_capture;
 use crate::command_pool::{create_command_pools, destroy_command_pools};
 use crate::device::{create_logical_device, create_physical_device, destroy_logical_device};
-use crate::instance::{
-    SurfaceProvider, SurfaceProviderResource, create_instance, destroy_instance,
-};
+use crate::instance::{create_instance, destroy_instance};
+use crate::permutations::{destroy_pipeline_permutations, warm_up_pipelines};
 use crate::pipeline::{create_pipeline, destroy_pipeline};
-use crate::surface::{create_surface, destroy_surface};
+use crate::progress::finish_initialization;
+use crate::renderables::{collect_renderable_changes, RenderableTracker};
+use crate::surface::{create_surface, destroy_surface, handle_surface_lifecycle};
 use crate::swapchain::{create_swapchain, destroy_swapchain};
+use flux_ecs::app::AppRunner;
 use flux_ecs::plugin::Plugin;
-use flux_ecs::schedule::ScheduleLabel;
+use flux_ecs::schedule::control::SystemSet;
+use flux_ecs::schedule::CoreSchedule;
 use flux_ecs::world::World;
-use raw_window_handle::{
-    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
-};
-use winit::event_loop::EventLoop;
-use crate::buffers::{create_index_buffer, create_uniform_buffer, create_vertex_buffer};
-use crate::command_buffer::create_command_buffer;
-use crate::depth_buffers::create_depth_buffers;
-use crate::descriptors::create_descriptors;
+use crate::buffers::{create_uniform_buffer, destroy_buffers};
+use crate::depth_buffers::{create_depth_buffers, destroy_depth_buffers};
+use crate::descriptors::{create_descriptors, destroy_descriptors};
+use crate::light_clusters::{create_light_cluster_buffers, destroy_light_cluster_buffers};
+use crate::destroyer::destroy_retired_handles;
+use crate::pipeline_statistics::{
+    collect_pipeline_statistics, create_pipeline_statistics_queries,
+    destroy_pipeline_statistics_queries,
+};
+use crate::quality::apply_quality_settings;
+use crate::raw::{create_raw_vulkan, destroy_raw_vulkan};
+use crate::recreate::{rebuild_swapchain, recreate_swapchain, wait_for_device_idle};
+use crate::render_path::{
+    create_framebuffers, create_render_pass, destroy_framebuffers, destroy_render_pass,
+};
+use crate::frame::{
+    begin_render_stats_frame, create_frame_slots, destroy_frame_slots, render_frame,
+    resize_frame_slots,
+};
+use crate::fullscreen::{destroy_fullscreen_passes, prepare_fullscreen_passes};
+use crate::gpu_particles::{
+    create_particle_compute_pipeline, create_particle_draw_pipeline, destroy_gpu_particles,
+    prepare_gpu_particles,
+};
+use crate::memory_budget::sample_gpu_memory_budget;
+use crate::mesh::{destroy_meshes, upload_meshes};
+use crate::present_timing::collect_present_timing;
+use crate::occlusion::{
+    collect_occlusion_results, create_occlusion_queries, destroy_occlusion_queries,
+};
+use crate::particles::simulate_particles;
+use crate::shutdown::wait_for_in_flight_work;
+use crate::window::{create_window, destroy_window};
+use crate::window_targets::{
+    destroy_window_targets, render_window_targets, sync_window_targets,
+};
 
+mod allocator;
+mod barrier_validation;
+mod camera;
+mod capabilities;
+mod capture;
+mod color;
 mod command_pool;
+mod config;
+mod damage;
 mod device;
+mod frame;
+mod fullscreen;
+mod gpu_particles;
 mod instance;
+pub mod log_targets;
+mod permutations;
 mod pipeline;
+mod pipeline_statistics;
+mod present_timing;
+mod progress;
+mod quality;
 mod surface;
 mod swapchain;
 mod command_buffer;
 mod depth_buffers;
 mod image;
+mod input;
+mod layout_tracker;
+mod light_clusters;
+mod memory_budget;
+mod mesh;
+mod occlusion;
+mod particles;
 mod buffers;
 mod descriptors;
+mod destroyer;
+mod raw;
+mod recreate;
+mod render_path;
+mod renderables;
+mod scratch;
+mod shader_library;
+mod shutdown;
+mod sprite;
+mod stats;
+mod terrain;
+mod vertex_layout;
+mod window;
+mod window_targets;
+
+pub use allocator::{Allocation, GpuAllocator, DEFAULT_BLOCK_SIZE};
+pub use barrier_validation::{BarrierValidator, GpuResource};
+pub use camera::{Camera, DepthRange, Frustum, Ray};
+pub use capabilities::RendererCapabilities;
+pub use capture::{read_back_framebuffer, CapturedFrame, FrameCapture, ReadbackError};
+pub use color::{
+    linear_to_srgb, srgb_to_linear, validate_output_encoding, ColorSpace, OutputEncoding,
+};
+pub use config::{ConfigError, ConfigOverrides, ConfigPlugin, GraphicsSettings};
+pub use instance::{
+    AppVersion, NullSurfaceProvider, RendererSettings, RendererUnavailable, SurfaceProvider,
+    SurfaceProviderResource,
+};
+pub use damage::PresentDamage;
+pub use destroyer::{DeferredDestroyer, RetiredHandle};
+pub use device::{DeviceFeatures, DeviceRequirements};
+pub use frame::{FrameOutcome, FrameSlots, FramesInFlight};
+pub use fullscreen::{
+    FullscreenBinding, FullscreenPass, FullscreenPassError, FullscreenPassId, FullscreenPasses,
+    FullscreenStage,
+};
+pub use gpu_particles::{
+    record_gpu_particle_draw, record_gpu_particle_simulation, GpuParticle, GpuParticleEmitter,
+    GpuParticleError, GpuParticleShader, ParticleBuffers, ParticleComputePipeline,
+    ParticleDrawPipeline, PingPong,
+};
+pub use layout_tracker::ImageLayoutTracker;
+pub use light_clusters::{
+    assign_lights_to_clusters, ClusterSettings, LightClusterBuffers, LightClusters, PointLight,
+};
+pub use memory_budget::{
+    pressure_changes, GpuMemoryBudget, GpuMemoryPressure, HeapBudget, MemoryBudgetSettings,
+    PressureChange,
+};
+pub use mesh::{
+    GpuMesh, GpuMeshes, Mesh, MeshBuilder, MeshVertex, StandardAttributes, StandardVertices,
+};
+pub use occlusion::{OcclusionQueries, OcclusionQuery, OcclusionResults, OcclusionSettings};
+pub use particles::{
+    prepare_particle_batches, EmitterSettings, ParticleBatch, ParticleBatches, ParticleEmitter,
+    ParticleInstance, PARTICLE_TIME_STEP,
+};
+pub use permutations::{
+    MaterialId, PipelineKey, PipelinePermutations, PipelineWarmupError, RenderPass,
+};
+pub use pipeline_statistics::{
+    PipelineStatistics, PipelineStatisticsQueries, PipelineStatisticsSettings,
+};
+pub use present_timing::{PresentPacing, PresentTiming};
+pub use progress::{InitializationProgress, InitializationStage};
+pub use quality::{
+    AppliedQuality, QualityChanges, QualityPreset, QualitySettings, QualitySettingsChanged,
+};
+pub use raw::{RawVulkan, RawVulkanHooks};
+pub use recreate::{RendererSchedule, SwapchainRecreation};
+pub use render_path::{ClassicRenderPass, RenderPath};
+pub use renderables::RenderableChanges;
+pub use scratch::{FrameScratch, ScratchVec};
+pub use shader_library::{ShaderIncludeError, ShaderLibrary};
+pub use shutdown::InFlightWork;
+pub use sprite::{
+    prepare_sprite_batches, AtlasId, AtlasRegion, Sprite, SpriteBatch, SpriteBatches,
+    SpriteInstance, TextureAtlas, TextureAtlases, ZIndex,
+};
+pub use stats::RenderStats;
+pub use swapchain::{IntermediateImage, Swapchain};
+pub use vertex_layout::{
+    format_size, VertexAttribute, VertexBinding, VertexLayout, VertexStreams,
+};
+pub use terrain::{
+    stream_terrain, ChunkCoord, Heightmap, Terrain, TerrainChunk, TerrainSettings, TerrainVertex,
+    TerrainViewer,
+};
+pub use window::{
+    winit_runner, Headless, NativeWindow, Window, WindowDescriptor, WindowError, WinitEventLoop,
+};
+pub use window_targets::WindowTargets;
 
 pub struct RendererPlugin;
 
-struct WinitSurfaceProvider {
-    window: winit::window::Window,
-}
+impl Plugin for RendererPlugin {
+    fn init(&self, world: &mut World) {
+        if world.get_resource::<ShaderLibrary>().is_none() {
+            world.add_resource(ShaderLibrary::default());
+        }
+        if world.get_resource::<RawVulkanHooks>().is_none() {
+            world.add_resource(RawVulkanHooks::default());
+        }
+        if world.get_resource::<TextureAtlases>().is_none() {
+            world.add_resource(TextureAtlases::default());
+        }
+        if world.get_resource::<ImageLayoutTracker>().is_none() {
+            world.add_resource(ImageLayoutTracker::default());
+        }
+        if world.get_resource::<BarrierValidator>().is_none() {
+            world.add_resource(BarrierValidator::default());
+        }
+        if world.get_resource::<PresentDamage>().is_none() {
+            world.add_resource(PresentDamage::default());
+        }
+        if world.get_resource::<PresentTiming>().is_none() {
+            world.add_resource(PresentTiming::default());
+        }
+        if world.get_resource::<PipelinePermutations>().is_none() {
+            world.add_resource(PipelinePermutations::default());
+        }
+        // Applications may bring their own permutations, they need the built-in materials too
+        if let Some(permutations) = world.get_resource::<PipelinePermutations>() {
+            permutations.register_builtin_materials();
+        }
+        if world.get_resource::<MemoryBudgetSettings>().is_none() {
+            world.add_resource(MemoryBudgetSettings::default());
+        }
+        if world.get_resource::<QualitySettings>().is_none() {
+            world.add_resource(QualitySettings::default());
+        }
+        if world.get_resource::<GpuMeshes>().is_none() {
+            world.add_resource(GpuMeshes::default());
+        }
+        if world.get_resource::<RenderableTracker>().is_none() {
+            let changes = world.subscribe_changes();
+            world.add_resource(RenderableTracker::new(changes));
+            world.add_resource(RenderableChanges::default());
+        }
+        if world.get_resource::<LightClusters>().is_none() {
+            world.add_resource(LightClusters::default());
+        }
+        if world.get_resource::<PipelineStatisticsSettings>().is_none() {
+            world.add_resource(PipelineStatisticsSettings::default());
+        }
+        if world.get_resource::<FramesInFlight>().is_none() {
+            world.add_resource(FramesInFlight::default());
+        }
+        if world.get_resource::<FullscreenPasses>().is_none() {
+            world.add_resource(FullscreenPasses::default());
+        }
+        if world.get_resource::<OcclusionSettings>().is_none() {
+            world.add_resource(OcclusionSettings::default());
+        }
+        if world.get_non_send_resource::<FrameScratch>().is_none() {
+            world.add_non_send_resource(FrameScratch::default());
+        }
+        if world.get_non_send_resource::<AppRunner>().is_none() {
+            world.add_non_send_resource(AppRunner::new(winit_runner));
+        }
+        if world.get_resource::<RenderStats>().is_none() {
+            world.add_resource(RenderStats::default());
+        }
+        if world.get_resource::<InFlightWork>().is_none() {
+            world.add_resource(InFlightWork::default());
+        }
+        if world.get_resource::<DeferredDestroyer>().is_none() {
+            world.add_resource(DeferredDestroyer::default());
+        }
+        if world.get_resource::<FrameCapture>().is_none() {
+            world.add_resource(FrameCapture::default());
+        }
+        if world.get_resource::<WindowTargets>().is_none() {
+            world.add_resource(WindowTargets::default());
+        }
+        if world.get_resource::<SwapchainRecreation>().is_none() {
+            world.add_resource(SwapchainRecreation::default());
+        }
 
-impl SurfaceProvider for WinitSurfaceProvider {
-    fn get_display_handle(&self) -> RawDisplayHandle {
-        self.window.raw_display_handle().unwrap()
-    }
+        world.add_system(CoreSchedule::Initialization, create_window);
+        world.add_system(CoreSchedule::Initialization, create_instance);
+        world.add_system(CoreSchedule::Initialization, create_surface);
+        world.add_system(CoreSchedule::Initialization, create_physical_device);
+        world.add_system(CoreSchedule::Initialization, create_logical_device);
+        world.add_system(CoreSchedule::Initialization, create_gpu_allocator);
+        world.add_system(CoreSchedule::Initialization, create_swapchain);
+        world.add_system(CoreSchedule::Initialization, create_render_pass);
+        world.add_system(CoreSchedule::Initialization, create_pipeline);
+        world.add_system(CoreSchedule::Initialization, create_depth_buffers);
+        world.add_system(CoreSchedule::Initialization, create_framebuffers);
+        world.add_system(CoreSchedule::Initialization, warm_up_pipelines);
+        world.add_system(CoreSchedule::Initialization, create_particle_compute_pipeline);
+        world.add_system(CoreSchedule::Initialization, create_particle_draw_pipeline);
+        world.add_system(CoreSchedule::Initialization, create_command_pools);
+        world.add_system(CoreSchedule::Initialization, create_raw_vulkan);
+        world.add_system(CoreSchedule::Initialization, create_uniform_buffer);
+        world.add_system(CoreSchedule::Initialization, create_light_cluster_buffers);
+        world.add_system(CoreSchedule::Initialization, create_descriptors);
+        world.add_system(CoreSchedule::Initialization, create_occlusion_queries);
+        world.add_system(CoreSchedule::Initialization, create_pipeline_statistics_queries);
+        world.add_system(CoreSchedule::Initialization, create_frame_slots);
+        world.add_system(CoreSchedule::Initialization, finish_initialization);
 
-    fn get_window_handle(&self) -> RawWindowHandle {
-        self.window.raw_window_handle().unwrap()
-    }
+        // Keeps rendering while PreUpdate or Main are paused, see `ScheduleControl`
+        let rendering = SystemSet::RENDERING;
+        world.add_system_to_set(CoreSchedule::PreUpdate, rendering, begin_render_stats_frame);
+        world.add_system_to_set(CoreSchedule::PreUpdate, rendering, collect_renderable_changes);
 
-    fn get_extent(&self) -> (u32, u32) {
-        let size = self.window.inner_size();
-        (size.width, size.height)
+        world.add_system_to_set(CoreSchedule::Main, rendering, apply_quality_settings);
+        world.add_system_to_set(CoreSchedule::Main, rendering, handle_surface_lifecycle);
+        world.add_system_to_set(CoreSchedule::Main, rendering, recreate_swapchain);
+        world.add_system_to_set(CoreSchedule::Main, rendering, sync_window_targets);
+        // Simulations pause with their schedule, the other systems keep the frame rendering
+        world.add_system(CoreSchedule::Main, simulate_particles);
+        world.add_system(CoreSchedule::Main, prepare_gpu_particles);
+        world.add_system(CoreSchedule::Main, stream_terrain);
+        world.add_system_to_set(CoreSchedule::Main, rendering, upload_meshes);
+        // After the uploads so the permutations of new meshes are drawn in the same frame
+        world.add_system_to_set(CoreSchedule::Main, rendering, warm_up_pipelines);
+        world.add_system_to_set(CoreSchedule::Main, rendering, prepare_fullscreen_passes);
+        world.add_system_to_set(CoreSchedule::Main, rendering, assign_lights_to_clusters);
+        world.add_system_to_set(CoreSchedule::Main, rendering, collect_occlusion_results);
+        world.add_system_to_set(CoreSchedule::Main, rendering, collect_pipeline_statistics);
+        world.add_system_to_set(CoreSchedule::Main, rendering, sample_gpu_memory_budget);
+        world.add_system_to_set(CoreSchedule::Main, rendering, collect_present_timing);
+
+        world.add_system(CoreSchedule::Render, render_window_targets);
+        world.add_system(CoreSchedule::Render, render_frame);
+
+        // Rebuilds everything sized or counted by the swapchain images, see `SwapchainRecreation`
+        let recreate = RendererSchedule::RecreateSwapchain;
+        world.add_system(recreate, wait_for_device_idle);
+        add_swapchain_teardown(world, recreate);
+        world.add_system(recreate, rebuild_swapchain);
+        add_swapchain_setup(world, recreate);
+
+        // The surface follows the native window, see `handle_surface_lifecycle`
+        let release = RendererSchedule::ReleaseSurface;
+        world.add_system(release, wait_for_device_idle);
+        add_swapchain_teardown(world, release);
+        world.add_system(release, destroy_swapchain);
+        world.add_system(release, destroy_surface);
+
+        let acquire = RendererSchedule::AcquireSurface;
+        world.add_system(acquire, create_surface);
+        world.add_system(acquire, create_swapchain);
+        world.add_system(acquire, create_render_pass);
+        world.add_system(acquire, create_pipeline);
+        add_swapchain_setup(world, acquire);
+        world.add_system(acquire, create_particle_draw_pipeline);
+        world.add_system(acquire, create_frame_slots);
+
+        // Destroy systems run in reverse dependency order once the GPU is idle
+        world.add_system(CoreSchedule::Destroy, wait_for_in_flight_work);
+        world.add_system(CoreSchedule::Destroy, destroy_retired_handles);
+        world.add_system(CoreSchedule::Destroy, destroy_window_targets);
+        world.add_system(CoreSchedule::Destroy, destroy_frame_capture);
+        world.add_system(CoreSchedule::Destroy, destroy_frame_slots);
+        world.add_system(CoreSchedule::Destroy, destroy_raw_vulkan);
+        world.add_system(CoreSchedule::Destroy, destroy_command_pools);
+        world.add_system(CoreSchedule::Destroy, destroy_occlusion_queries);
+        world.add_system(CoreSchedule::Destroy, destroy_pipeline_statistics_queries);
+        world.add_system(CoreSchedule::Destroy, destroy_descriptors);
+        world.add_system(CoreSchedule::Destroy, destroy_buffers);
+        world.add_system(CoreSchedule::Destroy, destroy_light_cluster_buffers);
+        world.add_system(CoreSchedule::Destroy, destroy_meshes);
+        world.add_system(CoreSchedule::Destroy, destroy_pipeline_permutations);
+        world.add_system(CoreSchedule::Destroy, destroy_fullscreen_passes);
+        world.add_system(CoreSchedule::Destroy, destroy_gpu_particles);
+        world.add_system(CoreSchedule::Destroy, destroy_pipeline);
+        world.add_system(CoreSchedule::Destroy, destroy_render_pass);
+        world.add_system(CoreSchedule::Destroy, destroy_depth_buffers);
+        world.add_system(CoreSchedule::Destroy, destroy_swapchain);
+        world.add_system(CoreSchedule::Destroy, destroy_gpu_allocator);
+        world.add_system(CoreSchedule::Destroy, destroy_logical_device);
+        world.add_system(CoreSchedule::Destroy, destroy_surface);
+        world.add_system(CoreSchedule::Destroy, destroy_instance);
+        world.add_system(CoreSchedule::Destroy, destroy_window);
     }
 }
 
-impl Plugin for RendererPlugin {
-    fn init(&self, world: &mut World) {
-        let event_loop = EventLoop::new().unwrap();
-        let window = event_loop.create_window(Default::default()).unwrap();
-        let surface_provider = WinitSurfaceProvider { window };
-        let surface_provider_resource = SurfaceProviderResource {
-            provider: Box::new(surface_provider),
-        };
-        world.add_resource(surface_provider_resource);
-        world.add_system(ScheduleLabel::Initialization, create_instance);
-        world.add_system(ScheduleLabel::Initialization, create_surface);
-        world.add_system(ScheduleLabel::Initialization, create_physical_device);
-        world.add_system(ScheduleLabel::Initialization, create_logical_device);
-        world.add_system(ScheduleLabel::Initialization, create_swapchain);
-        world.add_system(ScheduleLabel::Initialization, create_pipeline);
-        world.add_system(ScheduleLabel::Initialization, create_depth_buffers);
-        world.add_system(ScheduleLabel::Initialization, create_command_pools);
-        world.add_system(ScheduleLabel::Initialization, create_vertex_buffer);
-        world.add_system(ScheduleLabel::Initialization, create_index_buffer);
-        world.add_system(ScheduleLabel::Initialization, create_uniform_buffer);
-        world.add_system(ScheduleLabel::Initialization, create_descriptors);
-        world.add_system(ScheduleLabel::Initialization, create_command_buffer);
+/// Adds the systems destroying the resources derived from the swapchain images, the device must
+/// be idle.
+fn add_swapchain_teardown(world: &mut World, schedule: RendererSchedule) {
+    world.add_system(schedule, destroy_framebuffers);
+    world.add_system(schedule, destroy_fullscreen_passes);
+    world.add_system(schedule, destroy_occlusion_queries);
+    world.add_system(schedule, destroy_pipeline_statistics_queries);
+    world.add_system(schedule, destroy_descriptors);
+    world.add_system(schedule, destroy_buffers);
+    world.add_system(schedule, destroy_light_cluster_buffers);
+    world.add_system(schedule, destroy_depth_buffers);
+}
 
-        world.add_system(ScheduleLabel::Destroy, destroy_command_pools);
-        world.add_system(ScheduleLabel::Destroy, destroy_pipeline);
-        world.add_system(ScheduleLabel::Destroy, destroy_swapchain);
-        world.add_system(ScheduleLabel::Destroy, destroy_logical_device);
-        world.add_system(ScheduleLabel::Destroy, destroy_surface);
-        world.add_system(ScheduleLabel::Destroy, destroy_instance);
-    }
+/// Adds the systems creating the resources derived from the swapchain images once a swapchain
+/// exists.
+fn add_swapchain_setup(world: &mut World, schedule: RendererSchedule) {
+    world.add_system(schedule, create_depth_buffers);
+    world.add_system(schedule, create_framebuffers);
+    world.add_system(schedule, create_uniform_buffer);
+    world.add_system(schedule, create_light_cluster_buffers);
+    world.add_system(schedule, create_descriptors);
+    world.add_system(schedule, create_occlusion_queries);
+    world.add_system(schedule, create_pipeline_statistics_queries);
+    world.add_system(schedule, resize_frame_slots);
 }
@@ -0,0 +1,504 @@
+use crate::allocator::{Allocation, GpuAllocator};
+use crate::buffers::create_buffer;
+use crate::camera::Camera;
+use crate::device::Device;
+use crate::log_targets;
+use crate::swapchain::Swapchain;
+use ash::vk;
+use cgmath::{Matrix4, Point3, SquareMatrix, Vector2, Vector4};
+use flux_ecs::commands::Commands;
+use flux_ecs::component::Component;
+use flux_ecs::query::Query;
+use flux_ecs::resource::{Res, ResMut, Resource};
+use log::debug;
+
+/// A light shining in all directions up to its radius.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    /// The world space position.
+    pub position: [f32; 3],
+    pub radius: f32,
+    /// Linear RGB color, premultiplied with the intensity.
+    pub color: [f32; 3],
+}
+
+impl Component for PointLight {}
+
+/// The resolution of the cluster grid, the depth slices are distributed exponentially so that
+/// clusters stay roughly cubic.
+///
+/// The GPU buffers are sized from the settings when the swapchain is created, see
+/// [`LightClusterBuffers`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClusterSettings {
+    pub tiles_x: u32,
+    pub tiles_y: u32,
+    pub depth_slices: u32,
+    /// The depth the last slice ends at if the camera's far plane is further away, e.g. at
+    /// infinity. Fragments beyond it receive no point lights.
+    pub max_depth: f32,
+    /// Further lights are ignored.
+    pub max_lights: u32,
+    /// Further lights reaching a cluster are ignored in that cluster.
+    pub max_lights_per_cluster: u32,
+}
+
+impl Resource for ClusterSettings {}
+
+impl Default for ClusterSettings {
+    fn default() -> Self {
+        Self {
+            tiles_x: 16,
+            tiles_y: 9,
+            depth_slices: 24,
+            max_depth: 1000.0,
+            max_lights: 1024,
+            max_lights_per_cluster: 64,
+        }
+    }
+}
+
+impl ClusterSettings {
+    pub fn cluster_count(&self) -> usize {
+        (self.tiles_x * self.tiles_y * self.depth_slices) as usize
+    }
+
+    /// The index of the cluster, tiles are ordered row by row and slice by slice.
+    pub fn cluster_index(&self, x: u32, y: u32, slice: u32) -> usize {
+        ((slice * self.tiles_y + y) * self.tiles_x + x) as usize
+    }
+}
+
+/// The lights affecting every cluster of the view frustum, laid out for the storage buffers the
+/// fragment shader indexes: cluster `i` uses the `grid[i].1` light indices starting at
+/// `light_indices[grid[i].0]`, the indices refer to [`LightClusters::lights`].
+#[derive(Debug, Clone, Default)]
+pub struct LightClusters {
+    pub settings: ClusterSettings,
+    /// The view space depths the first slice starts and the last slice ends at.
+    pub depth_range: (f32, f32),
+    /// The size in pixels of the viewport the tiles divide.
+    pub viewport_size: [f32; 2],
+    /// The offset and count of the light indices of every cluster.
+    pub grid: Vec<(u32, u32)>,
+    pub light_indices: Vec<u32>,
+    pub lights: Vec<PointLight>,
+}
+
+impl Resource for LightClusters {}
+
+impl LightClusters {
+    /// The lights affecting the cluster.
+    pub fn cluster_lights(&self, cluster: usize) -> &[u32] {
+        let (offset, count) = self.grid[cluster];
+        &self.light_indices[offset as usize..(offset + count) as usize]
+    }
+
+    /// Rebuilds the clusters of the camera's view frustum for a viewport of the given size in
+    /// pixels, assigning every light to the clusters its sphere of influence touches. Lights
+    /// outside of the [`Camera::frustum`] are dropped.
+    pub fn assign(
+        &mut self,
+        settings: ClusterSettings,
+        camera: &Camera,
+        viewport_size: Vector2<f32>,
+        lights: &[PointLight],
+    ) {
+        let frustum = camera.frustum(viewport_size);
+        let lights: Vec<_> = lights
+            .iter()
+            .filter(|light| frustum.intersects_sphere(Point3::from(light.position), light.radius))
+            .take(settings.max_lights as usize)
+            .copied()
+            .collect();
+        let depth_range = (camera.near, camera.far.min(settings.max_depth));
+        // A degenerate projection leaves the grid empty, which disables the point lights
+        let bounds = camera
+            .projection(viewport_size)
+            .invert()
+            .map(|clip_to_view| cluster_bounds(&settings, camera, clip_to_view, depth_range))
+            .unwrap_or_default();
+        let view_lights: Vec<_> = lights
+            .iter()
+            .map(|light| {
+                let [x, y, z] = light.position;
+                let position = camera.view * Vector4::new(x, y, z, 1.0);
+                ([position.x, position.y, position.z], light.radius)
+            })
+            .collect();
+
+        self.settings = settings;
+        self.depth_range = depth_range;
+        self.viewport_size = viewport_size.into();
+        self.lights.clear();
+        self.lights.extend_from_slice(&lights);
+        self.grid.clear();
+        self.light_indices.clear();
+        for (min, max) in bounds {
+            let offset = self.light_indices.len() as u32;
+            let reaching = view_lights
+                .iter()
+                .enumerate()
+                .filter(|(_, (center, radius))| sphere_intersects_box(*center, *radius, min, max))
+                .map(|(index, _)| index as u32)
+                .take(settings.max_lights_per_cluster as usize);
+            self.light_indices.extend(reaching);
+            self.grid
+                .push((offset, self.light_indices.len() as u32 - offset));
+        }
+    }
+}
+
+/// The view space bounding boxes of all clusters, in cluster index order. The tile corners are
+/// unprojected with the inverse of the camera's projection, `clip_to_view`.
+fn cluster_bounds(
+    settings: &ClusterSettings,
+    camera: &Camera,
+    clip_to_view: Matrix4<f32>,
+    (near, far): (f32, f32),
+) -> Vec<([f32; 3], [f32; 3])> {
+    // The view space point at a depth of 1 behind a position on the near plane
+    let near_depth = camera.depth_range.near_depth();
+    let unproject = |ndc_x: f32, ndc_y: f32| {
+        let point = clip_to_view * Vector4::new(ndc_x, ndc_y, near_depth, 1.0);
+        point.truncate() / -point.z
+    };
+    let slice_depth =
+        |slice: u32| near * (far / near).powf(slice as f32 / settings.depth_slices as f32);
+    let ndc = |tile: u32, tiles: u32| tile as f32 / tiles as f32 * 2.0 - 1.0;
+
+    let mut bounds = Vec::with_capacity(settings.cluster_count());
+    for slice in 0..settings.depth_slices {
+        let (near, far) = (slice_depth(slice), slice_depth(slice + 1));
+        for y in 0..settings.tiles_y {
+            for x in 0..settings.tiles_x {
+                let mut min = [f32::MAX; 3];
+                let mut max = [f32::MIN; 3];
+                for depth in [near, far] {
+                    for ndc_x in [ndc(x, settings.tiles_x), ndc(x + 1, settings.tiles_x)] {
+                        // Tile rows count up the view while clip space y points down
+                        for ndc_y in [ndc(y, settings.tiles_y), ndc(y + 1, settings.tiles_y)] {
+                            let corner: [f32; 3] = (unproject(ndc_x, -ndc_y) * depth).into();
+                            for axis in 0..3 {
+                                min[axis] = min[axis].min(corner[axis]);
+                                max[axis] = max[axis].max(corner[axis]);
+                            }
+                        }
+                    }
+                }
+                bounds.push((min, max));
+            }
+        }
+    }
+    bounds
+}
+
+fn sphere_intersects_box(center: [f32; 3], radius: f32, min: [f32; 3], max: [f32; 3]) -> bool {
+    let distance_squared: f32 = (0..3)
+        .map(|axis| {
+            let closest = center[axis].clamp(min[axis], max[axis]);
+            (center[axis] - closest).powi(2)
+        })
+        .sum();
+    distance_squared <= radius * radius
+}
+
+/// Assigns the point lights to the clusters of the first [`Camera`] on the CPU, every frame. The
+/// tiles divide the render target of the swapchain like the main pass does.
+pub fn assign_lights_to_clusters(
+    cameras: Query<&Camera>,
+    (swapchain, settings): (Option<Res<Swapchain>>, Option<Res<ClusterSettings>>),
+    mut clusters: ResMut<LightClusters>,
+    lights: Query<&PointLight>,
+) {
+    let (Some(camera), Some(swapchain)) = (cameras.iter().next(), swapchain) else {
+        return;
+    };
+    let settings = settings.map_or_else(ClusterSettings::default, |settings| *settings);
+    let extent = swapchain.render_extent;
+    let viewport_size = Vector2::new(extent.width as f32, extent.height as f32);
+    let lights: Vec<_> = lights.into_iter().copied().collect();
+    clusters.assign(settings, camera, viewport_size, &lights);
+}
+
+/// The header of the cluster grid buffer, followed by the offset and count of every cluster.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct ClusterGridHeader {
+    /// The tiles in x and y and the depth slices, no slices disable the point lights.
+    size: [u32; 4],
+    /// The depth range of the slices and the viewport size.
+    depth_viewport: [f32; 4],
+}
+
+/// A light as the fragment shader reads it.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct GpuPointLight {
+    position_radius: [f32; 4],
+    color: [f32; 4],
+}
+
+struct HostBuffer {
+    buffer: vk::Buffer,
+    memory: Allocation,
+    size: vk::DeviceSize,
+}
+
+impl HostBuffer {
+    fn create(
+        device: &Device,
+        allocator: &GpuAllocator,
+        size: vk::DeviceSize,
+    ) -> Result<Self, vk::Result> {
+        let (buffer, memory) = create_buffer(
+            device,
+            allocator,
+            size,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        Ok(Self {
+            buffer,
+            memory,
+            size,
+        })
+    }
+
+    /// Copies `items` to `offset` bytes into the buffer, as many as fit.
+    ///
+    /// # Safety
+    /// The buffer must not be used by a pending submission.
+    unsafe fn write<T: Copy>(&self, offset: usize, items: &[T]) {
+        let available = (self.size as usize).saturating_sub(offset) / size_of::<T>();
+        let count = items.len().min(available);
+        let mapped = self
+            .memory
+            .mapped_ptr()
+            .expect("Host visible allocations are mapped");
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                items.as_ptr().cast::<u8>(),
+                mapped.add(offset),
+                count * size_of::<T>(),
+            )
+        };
+    }
+}
+
+/// The storage buffers the clusters of each swapchain image are uploaded to, bound at bindings
+/// `2` to `4` of the main descriptor set: the grid with its header, the light indices and the
+/// lights.
+pub struct LightClusterBuffers {
+    /// The settings the buffers are sized for.
+    settings: ClusterSettings,
+    images: Vec<[HostBuffer; 3]>,
+}
+
+impl Resource for LightClusterBuffers {}
+
+impl LightClusterBuffers {
+    /// The grid, light index and light buffers of swapchain image `image`.
+    pub fn buffers(&self, image: usize) -> [vk::Buffer; 3] {
+        self.images[image % self.images.len()]
+            .each_ref()
+            .map(|buffer| buffer.buffer)
+    }
+
+    /// Uploads the clusters read by the frames rendering to swapchain image `image`. Clusters
+    /// built with other settings than the buffers were sized for disable the point lights until
+    /// the buffers are recreated with the swapchain.
+    ///
+    /// # Safety
+    /// The buffers of the image must not be used by a pending submission.
+    pub(crate) unsafe fn write(&self, image: usize, clusters: &LightClusters) {
+        let [grid, indices, lights] = &self.images[image % self.images.len()];
+        let settings = clusters.settings;
+        let fits = settings == self.settings && clusters.grid.len() == settings.cluster_count();
+        let header = ClusterGridHeader {
+            size: [
+                settings.tiles_x,
+                settings.tiles_y,
+                if fits { settings.depth_slices } else { 0 },
+                clusters.lights.len() as u32,
+            ],
+            depth_viewport: [
+                clusters.depth_range.0,
+                clusters.depth_range.1,
+                clusters.viewport_size[0],
+                clusters.viewport_size[1],
+            ],
+        };
+        let cells: Vec<_> = clusters
+            .grid
+            .iter()
+            .map(|&(offset, count)| [offset, count])
+            .collect();
+        let gpu_lights: Vec<_> = clusters
+            .lights
+            .iter()
+            .map(|light| {
+                let [x, y, z] = light.position;
+                let [r, g, b] = light.color;
+                GpuPointLight {
+                    position_radius: [x, y, z, light.radius],
+                    color: [r, g, b, 0.0],
+                }
+            })
+            .collect();
+
+        unsafe {
+            grid.write(0, &[header]);
+            if fits {
+                grid.write(size_of::<ClusterGridHeader>(), &cells);
+                indices.write(0, &clusters.light_indices);
+                lights.write(0, &gpu_lights);
+            }
+        }
+    }
+}
+
+/// Creates the [`LightClusterBuffers`] for each swapchain image, sized for the
+/// [`ClusterSettings`].
+pub fn create_light_cluster_buffers(
+    device: Res<Device>,
+    allocator: Res<GpuAllocator>,
+    swapchain: Option<Res<Swapchain>>,
+    settings: Option<Res<ClusterSettings>>,
+    mut commands: Commands,
+) -> Result<(), vk::Result> {
+    let Some(swapchain) = swapchain else {
+        return Ok(());
+    };
+    let settings = settings.map_or_else(ClusterSettings::default, |settings| *settings);
+
+    debug!(target: log_targets::RESOURCES, "Creating light cluster buffers");
+
+    let clusters = settings.cluster_count();
+    let sizes = [
+        size_of::<ClusterGridHeader>() + clusters * size_of::<[u32; 2]>(),
+        clusters * settings.max_lights_per_cluster as usize * size_of::<u32>(),
+        settings.max_lights as usize * size_of::<GpuPointLight>(),
+    ]
+    // Storage buffers may not be empty
+    .map(|size| size.max(size_of::<GpuPointLight>()) as vk::DeviceSize);
+
+    let mut images = Vec::with_capacity(swapchain.images.len());
+    for _ in 0..swapchain.images.len() {
+        let [grid, indices, lights] = sizes;
+        images.push([
+            HostBuffer::create(&device, &allocator, grid)?,
+            HostBuffer::create(&device, &allocator, indices)?,
+            HostBuffer::create(&device, &allocator, lights)?,
+        ]);
+    }
+
+    commands.insert_resource(LightClusterBuffers { settings, images });
+
+    Ok(())
+}
+
+pub fn destroy_light_cluster_buffers(
+    device: Res<Device>,
+    allocator: Res<GpuAllocator>,
+    buffers: Option<Res<LightClusterBuffers>>,
+    mut commands: Commands,
+) {
+    let Some(buffers) = buffers else {
+        return;
+    };
+
+    debug!(target: log_targets::RESOURCES, "Destroying light cluster buffers");
+
+    for buffer in buffers.images.iter().flatten() {
+        unsafe { device.destroy_buffer(buffer.buffer, None) };
+        allocator.free(&device, buffer.memory);
+    }
+
+    commands.remove_resource::<LightClusterBuffers>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::DepthRange;
+
+    const VIEWPORT: Vector2<f32> = Vector2::new(100.0, 100.0);
+
+    #[test]
+    fn lights_are_assigned_to_the_clusters_they_reach() {
+        let settings = ClusterSettings {
+            tiles_x: 4,
+            tiles_y: 4,
+            depth_slices: 8,
+            ..Default::default()
+        };
+        let camera = Camera {
+            fov_y: std::f32::consts::FRAC_PI_2,
+            near: 1.0,
+            far: 256.0,
+            ..Default::default()
+        };
+        let light = |position, radius| PointLight {
+            position,
+            radius,
+            color: [1.0; 3],
+        };
+        let lights = [
+            // Small light straight ahead, in the first depth slice
+            light([0.1, 0.1, -1.5], 0.1),
+            // Behind the camera
+            light([0.0, 0.0, 10.0], 1.0),
+            // Covers the whole near end of the frustum
+            light([0.0, 0.0, -1.0], 4.0),
+        ];
+
+        let mut clusters = LightClusters::default();
+        clusters.assign(settings, &camera, VIEWPORT, &lights);
+        assert_eq!(clusters.grid.len(), settings.cluster_count());
+
+        // The light behind the camera is culled, the indices refer to the remaining lights
+        assert_eq!(clusters.lights, [lights[0], lights[2]]);
+        let center = settings.cluster_index(2, 2, 0);
+        assert_eq!(clusters.cluster_lights(center), [0, 1]);
+        assert_eq!(
+            clusters.cluster_lights(settings.cluster_index(0, 0, 0)),
+            [1]
+        );
+        assert!(
+            clusters
+                .cluster_lights(settings.cluster_index(2, 2, 7))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn clusters_end_at_the_max_depth_and_cap_their_lights() {
+        let settings = ClusterSettings {
+            tiles_x: 1,
+            tiles_y: 1,
+            depth_slices: 4,
+            max_depth: 100.0,
+            max_lights_per_cluster: 1,
+            ..Default::default()
+        };
+        let camera = Camera {
+            near: 1.0,
+            far: f32::INFINITY,
+            depth_range: DepthRange::Reversed,
+            ..Default::default()
+        };
+        let light = |z, radius| PointLight {
+            position: [0.0, 0.0, z],
+            radius,
+            color: [1.0; 3],
+        };
+        let lights = [light(-2.0, 0.5), light(-2.0, 0.5), light(-500.0, 1.0)];
+
+        let mut clusters = LightClusters::default();
+        clusters.assign(settings, &camera, VIEWPORT, &lights);
+        assert_eq!(clusters.depth_range, (1.0, 100.0));
+        assert_eq!(clusters.cluster_lights(0), [0]);
+        assert!(!clusters.light_indices.contains(&2));
+    }
+}
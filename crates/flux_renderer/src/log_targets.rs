@@ -0,0 +1,16 @@
+//! Log targets used by the renderer, configure their levels with
+//! [`LogSettings`](flux_ecs::logging::LogSettings).
+
+pub const INSTANCE: &str = "flux_renderer::instance";
+pub const VALIDATION: &str = "flux_renderer::validation";
+/// The output of `debugPrintfEXT` in shaders, see
+/// [`GraphicsSettings::shader_debug_printf`](crate::GraphicsSettings::shader_debug_printf).
+pub const SHADER_PRINTF: &str = "flux_renderer::shader_printf";
+pub const SURFACE: &str = "flux_renderer::surface";
+pub const INPUT: &str = "flux_renderer::input";
+pub const DEVICE: &str = "flux_renderer::device";
+pub const SWAPCHAIN: &str = "flux_renderer::swapchain";
+pub const PIPELINE: &str = "flux_renderer::pipeline";
+pub const RESOURCES: &str = "flux_renderer::resources";
+pub const COMMANDS: &str = "flux_renderer::commands";
+pub const CONFIG: &str = "flux_renderer::config";
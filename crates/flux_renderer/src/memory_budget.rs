@@ -0,0 +1,200 @@
+use crate::capabilities::RendererCapabilities;
+use crate::device::PhysicalDevice;
+use crate::instance::VulkanInstance;
+use crate::log_targets;
+use ash::vk;
+use flux_ecs::commands::Commands;
+use flux_ecs::resource::{Res, Resource};
+use log::{info, warn};
+
+/// The usage of a memory heap by this and other processes, reported by `VK_EXT_memory_budget`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HeapBudget {
+    pub usage: u64,
+    /// How much the process can use before allocations may fail or degrade performance.
+    pub budget: u64,
+    pub size: u64,
+    pub device_local: bool,
+}
+
+impl HeapBudget {
+    /// The used fraction of the budget.
+    pub fn fraction(&self) -> f32 {
+        if self.budget == 0 {
+            return 0.0;
+        }
+        self.usage as f32 / self.budget as f32
+    }
+}
+
+/// The budget of every memory heap, sampled every frame if the device supports
+/// `VK_EXT_memory_budget`, see [`RendererCapabilities::memory_budget`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GpuMemoryBudget {
+    pub heaps: Vec<HeapBudget>,
+}
+
+impl Resource for GpuMemoryBudget {}
+
+impl GpuMemoryBudget {
+    /// The highest used fraction of a device local heap.
+    pub fn device_local_pressure(&self) -> f32 {
+        self.heaps
+            .iter()
+            .filter(|heap| heap.device_local)
+            .map(HeapBudget::fraction)
+            .fold(0.0, f32::max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryBudgetSettings {
+    /// The used fraction of a heap budget at which [`GpuMemoryPressure`] is sent.
+    pub warning_fraction: f32,
+}
+
+impl Resource for MemoryBudgetSettings {}
+
+impl Default for MemoryBudgetSettings {
+    fn default() -> Self {
+        Self {
+            warning_fraction: 0.9,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PressureChange {
+    /// The heap usage reached the warning fraction, assets should be downgraded or evicted.
+    Rising,
+    /// The heap usage dropped below the warning fraction again.
+    Relieved,
+}
+
+/// Sent as an [`Events<GpuMemoryPressure>`](flux_ecs::event::Events) event when a heap crosses
+/// the [`MemoryBudgetSettings::warning_fraction`], e.g. for texture residency to drop mip levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GpuMemoryPressure {
+    pub heap: usize,
+    pub usage: u64,
+    pub budget: u64,
+    pub change: PressureChange,
+}
+
+/// The heaps that crossed the warning fraction between two samples.
+pub fn pressure_changes(
+    previous: &GpuMemoryBudget,
+    current: &GpuMemoryBudget,
+    warning_fraction: f32,
+) -> Vec<GpuMemoryPressure> {
+    current
+        .heaps
+        .iter()
+        .enumerate()
+        .filter_map(|(heap, budget)| {
+            let before = previous
+                .heaps
+                .get(heap)
+                .is_some_and(|before| before.fraction() >= warning_fraction);
+            let now = budget.fraction() >= warning_fraction;
+
+            let change = match (before, now) {
+                (false, true) => PressureChange::Rising,
+                (true, false) => PressureChange::Relieved,
+                _ => return None,
+            };
+
+            Some(GpuMemoryPressure {
+                heap,
+                usage: budget.usage,
+                budget: budget.budget,
+                change,
+            })
+        })
+        .collect()
+}
+
+fn query_budget(instance: &VulkanInstance, physical_device: vk::PhysicalDevice) -> GpuMemoryBudget {
+    let mut budget_properties = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+    let mut properties =
+        vk::PhysicalDeviceMemoryProperties2::default().push_next(&mut budget_properties);
+    unsafe { instance.get_physical_device_memory_properties2(physical_device, &mut properties) };
+
+    let memory_properties = properties.memory_properties;
+    let heaps = memory_properties
+        .memory_heaps_as_slice()
+        .iter()
+        .enumerate()
+        .map(|(index, heap)| HeapBudget {
+            usage: budget_properties.heap_usage[index],
+            budget: budget_properties.heap_budget[index],
+            size: heap.size,
+            device_local: heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+        })
+        .collect();
+
+    GpuMemoryBudget { heaps }
+}
+
+pub fn sample_gpu_memory_budget(
+    instance: Res<VulkanInstance>,
+    physical_device: Res<PhysicalDevice>,
+    capabilities: Option<Res<RendererCapabilities>>,
+    settings: Res<MemoryBudgetSettings>,
+    previous: Option<Res<GpuMemoryBudget>>,
+    mut commands: Commands,
+) {
+    if !capabilities.is_some_and(|capabilities| capabilities.memory_budget) {
+        return;
+    }
+
+    let current = query_budget(&instance, physical_device.physical_device);
+
+    let previous = previous.as_deref().cloned().unwrap_or_default();
+    for event in pressure_changes(&previous, &current, settings.warning_fraction) {
+        match event.change {
+            PressureChange::Rising => warn!(
+                target: log_targets::RESOURCES,
+                "Memory heap {} is nearing its budget: {} of {} bytes used",
+                event.heap,
+                event.usage,
+                event.budget
+            ),
+            PressureChange::Relieved => info!(
+                target: log_targets::RESOURCES,
+                "Memory heap {} is below its budget warning again",
+                event.heap
+            ),
+        }
+        commands.send_event(event);
+    }
+
+    commands.insert_resource(current);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(usage: u64) -> GpuMemoryBudget {
+        GpuMemoryBudget {
+            heaps: vec![HeapBudget {
+                usage,
+                budget: 100,
+                size: 128,
+                device_local: true,
+            }],
+        }
+    }
+
+    #[test]
+    fn pressure_is_reported_when_crossing_the_warning_fraction() {
+        let changes = pressure_changes(&GpuMemoryBudget::default(), &budget(95), 0.9);
+        assert_eq!(changes[0].change, PressureChange::Rising);
+        assert_eq!(budget(95).device_local_pressure(), 0.95);
+
+        assert!(pressure_changes(&budget(95), &budget(99), 0.9).is_empty());
+        let changes = pressure_changes(&budget(95), &budget(50), 0.9);
+        assert_eq!(changes[0].change, PressureChange::Relieved);
+    }
+}
@@ -0,0 +1,796 @@
+use crate::allocator::{Allocation, GpuAllocator};
+use crate::buffers::{copy_buffer, create_buffer};
+use crate::camera::Frustum;
+use crate::command_pool::CommandPools;
+use crate::destroyer::{DeferredDestroyer, RetiredHandle};
+use crate::device::Device;
+use crate::log_targets;
+use crate::permutations::{MaterialId, PipelineKey, PipelinePermutations, RenderPass};
+use crate::renderables::RenderableChanges;
+use crate::stats::RenderStats;
+use crate::vertex_layout::{format_size, VertexLayout, VertexStreams};
+use ash::vk;
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, SquareMatrix};
+use flux_ecs::component::Component;
+use flux_ecs::entity::Entity;
+use flux_ecs::query::Query;
+use flux_ecs::resource::{Res, Resource};
+use flux_transform::transform::GlobalTransform;
+use log::debug;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Geometry generated at runtime, e.g. terrain, voxel chunks or UI panels.
+///
+/// The vertices are stored in one stream per binding of the layout, either interleaved in a
+/// single binding or de-interleaved with an attribute per binding. Every change bumps the
+/// revision, [`upload_meshes`] re-uploads only the meshes whose revision changed. Revisions are
+/// unique across all meshes, so replacing the mesh of an entity with another one is an upload
+/// as well.
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    layout: VertexLayout,
+    /// The vertex bytes of every binding, in binding order.
+    streams: Vec<Vec<u8>>,
+    indices: Vec<u32>,
+    revision: u64,
+}
+
+impl Component for Mesh {}
+
+impl Mesh {
+    /// # Panics
+    /// Panics if the layout has no bindings or they are not numbered in order starting at `0`,
+    /// as the streams are bound by [`VertexStreams::bind`].
+    pub fn new(layout: VertexLayout) -> Self {
+        assert!(
+            !layout.bindings.is_empty()
+                && layout
+                    .bindings
+                    .iter()
+                    .zip(0..)
+                    .all(|(binding, i)| binding.binding == i),
+            "Mesh streams must be bound in order starting at binding 0"
+        );
+        Self {
+            streams: vec![Vec::new(); layout.bindings.len()],
+            layout,
+            indices: Vec::new(),
+            revision: next_revision(),
+        }
+    }
+
+    fn stride(&self, binding: usize) -> usize {
+        self.layout.bindings[binding].stride as usize
+    }
+
+    /// Replaces the vertices of an interleaved mesh, `V` must match the stride of the layout.
+    ///
+    /// # Panics
+    /// Panics if the layout has more than one binding or the size of `V` is not its stride.
+    pub fn set_vertices<V: Copy>(&mut self, vertices: &[V]) {
+        assert_eq!(
+            self.streams.len(),
+            1,
+            "De-interleaved meshes are set stream by stream"
+        );
+        self.set_stream(0, vertices);
+    }
+
+    /// Replaces the vertices of a binding, `V` must match the stride of the binding.
+    ///
+    /// # Panics
+    /// Panics if the layout has no such binding or the size of `V` is not its stride.
+    pub fn set_stream<V: Copy>(&mut self, binding: u32, values: &[V]) {
+        let binding = binding as usize;
+        assert_eq!(
+            size_of::<V>(),
+            self.stride(binding),
+            "Vertex size does not match the stride of the binding"
+        );
+        let bytes = unsafe {
+            std::slice::from_raw_parts(values.as_ptr().cast::<u8>(), size_of_val(values))
+        };
+        let stream = &mut self.streams[binding];
+        stream.clear();
+        stream.extend_from_slice(bytes);
+        self.revision = next_revision();
+    }
+
+    /// Builds a mesh in the standard vertex format with the attributes present in `vertices`,
+    /// see [`StandardAttributes`].
+    ///
+    /// # Panics
+    /// Panics if the attributes do not have one value per position.
+    pub fn from_standard(vertices: &StandardVertices, indices: Vec<u32>) -> Self {
+        let count = vertices.positions.len();
+        let attributes = vertices.attributes();
+        assert!(
+            vertices.uvs.len() == count
+                && vertices.colors.is_none_or(|colors| colors.len() == count)
+                && vertices.uv2.is_none_or(|uv2| uv2.len() == count),
+            "Every vertex attribute needs one value per position"
+        );
+
+        let mut mesh = Mesh::new(attributes.layout());
+        let mut bytes = Vec::with_capacity(count * mesh.stride(0));
+        for i in 0..count {
+            let position = vertices.positions[i];
+            let color = vertices.colors.map(|colors| colors[i]);
+            let uv2 = vertices.uv2.map(|uv2| uv2[i]);
+            let floats = position
+                .iter()
+                .chain(color.iter().flatten())
+                .chain(&vertices.uvs[i])
+                .chain(uv2.iter().flatten());
+            for float in floats {
+                bytes.extend_from_slice(&float.to_ne_bytes());
+            }
+        }
+        mesh.streams[0] = bytes;
+        mesh.set_indices(indices);
+        mesh
+    }
+
+    pub fn set_indices(&mut self, indices: Vec<u32>) {
+        self.indices = indices;
+        self.revision = next_revision();
+    }
+
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    /// The vertices of binding `0`, all vertices of an interleaved mesh.
+    pub fn vertex_bytes(&self) -> &[u8] {
+        self.stream_bytes(0)
+    }
+
+    /// # Panics
+    /// Panics if the layout has no such binding.
+    pub fn stream_bytes(&self, binding: u32) -> &[u8] {
+        &self.streams[binding as usize]
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.streams[0].len() / self.stride(0)
+    }
+
+    /// Changes with every change of the vertices or indices.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// The object space center and radius of a sphere around all vertices, `None` without
+    /// vertices or `R32G32B32_SFLOAT` positions at location `0`.
+    pub fn bounding_sphere(&self) -> Option<(Point3<f32>, f32)> {
+        let position = self.layout.attributes.iter().find(|attribute| {
+            attribute.location == 0 && attribute.format == vk::Format::R32G32B32_SFLOAT
+        })?;
+        let offset = position.offset as usize;
+        let positions: Vec<_> = self.streams[position.binding as usize]
+            .chunks_exact(self.stride(position.binding as usize))
+            .map(|vertex| {
+                let float = |i: usize| {
+                    let start = offset + i * size_of::<f32>();
+                    f32::from_ne_bytes(vertex[start..start + 4].try_into().unwrap())
+                };
+                Point3::new(float(0), float(1), float(2))
+            })
+            .collect();
+
+        let first = *positions.first()?;
+        let bound = |pick: fn(f32, f32) -> f32| {
+            positions.iter().fold(first, |bound, position| {
+                Point3::new(
+                    pick(bound.x, position.x),
+                    pick(bound.y, position.y),
+                    pick(bound.z, position.z),
+                )
+            })
+        };
+        let center = bound(f32::min).midpoint(bound(f32::max));
+        let radius = positions
+            .iter()
+            .map(|position| (position - center).magnitude())
+            .fold(0.0, f32::max);
+        Some((center, radius))
+    }
+}
+
+fn next_revision() -> u64 {
+    static NEXT_REVISION: AtomicU64 = AtomicU64::new(0);
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The vertex of the meshes drawn by the main pass, stored interleaved as described by
+/// [`MeshVertex::layout`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub tex_coords: [f32; 2],
+}
+
+impl MeshVertex {
+    pub fn layout() -> VertexLayout {
+        VertexLayout::interleaved(
+            size_of::<MeshVertex>() as u32,
+            [
+                (0, vk::Format::R32G32B32_SFLOAT, 0),
+                (1, vk::Format::R32G32B32_SFLOAT, 12),
+                (2, vk::Format::R32G32_SFLOAT, 24),
+            ],
+        )
+    }
+}
+
+/// The optional attributes of the standard vertex format, e.g. the `COLOR_0` and `TEXCOORD_1`
+/// of glTF meshes.
+///
+/// Attributes keep their location whether the others are present or not. Meshes only store the
+/// attributes they have, meshes without a [`MaterialId`] are drawn with the standard material
+/// variant for their attributes, see [`PipelineKey::for_mesh`](crate::PipelineKey::for_mesh).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct StandardAttributes {
+    /// Linear RGB vertex colors at location [`StandardAttributes::COLOR`].
+    pub colors: bool,
+    /// A second set of texture coordinates, e.g. for lightmaps and decals, at location
+    /// [`StandardAttributes::UV2`].
+    pub uv2: bool,
+}
+
+impl StandardAttributes {
+    pub const POSITION: u32 = 0;
+    pub const COLOR: u32 = 1;
+    pub const UV: u32 = 2;
+    pub const UV2: u32 = 3;
+
+    /// The interleaved layout of the position, the present optional attributes and the texture
+    /// coordinates. With colors and without a second UV set it is the [`MeshVertex::layout`].
+    pub fn layout(self) -> VertexLayout {
+        let attributes = [
+            (Self::POSITION, vk::Format::R32G32B32_SFLOAT, true),
+            (Self::COLOR, vk::Format::R32G32B32_SFLOAT, self.colors),
+            (Self::UV, vk::Format::R32G32_SFLOAT, true),
+            (Self::UV2, vk::Format::R32G32_SFLOAT, self.uv2),
+        ];
+
+        let mut offset = 0;
+        let attributes: Vec<_> = attributes
+            .into_iter()
+            .filter(|&(_, _, present)| present)
+            .map(|(location, format, _)| {
+                let attribute = (location, format, offset);
+                offset += format_size(format).expect("Standard attributes have a known size");
+                attribute
+            })
+            .collect();
+        VertexLayout::interleaved(offset, attributes)
+    }
+
+    /// The attributes of a layout in the standard vertex format, `None` for other layouts.
+    pub fn matching(layout: &VertexLayout) -> Option<Self> {
+        let attributes = Self::of_layout(layout);
+        (attributes.layout() == *layout).then_some(attributes)
+    }
+
+    /// The built-in material drawing meshes with these attributes, the second UV set is not read.
+    pub fn material(self) -> MaterialId {
+        if self.colors {
+            MaterialId::STANDARD
+        } else {
+            MaterialId::STANDARD_UNCOLORED
+        }
+    }
+
+    /// The optional attributes present in a layout.
+    pub fn of_layout(layout: &VertexLayout) -> Self {
+        let has = |location| {
+            layout
+                .attributes
+                .iter()
+                .any(|attribute| attribute.location == location)
+        };
+        Self {
+            colors: has(Self::COLOR),
+            uv2: has(Self::UV2),
+        }
+    }
+}
+
+/// The attribute streams of a mesh in the standard vertex format, see [`Mesh::from_standard`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StandardVertices<'a> {
+    pub positions: &'a [[f32; 3]],
+    pub colors: Option<&'a [[f32; 3]]>,
+    pub uvs: &'a [[f32; 2]],
+    pub uv2: Option<&'a [[f32; 2]]>,
+}
+
+impl StandardVertices<'_> {
+    pub fn attributes(&self) -> StandardAttributes {
+        StandardAttributes {
+            colors: self.colors.is_some(),
+            uv2: self.uv2.is_some(),
+        }
+    }
+}
+
+/// Builds a [`Mesh`] vertex by vertex.
+pub struct MeshBuilder<V> {
+    layout: VertexLayout,
+    vertices: Vec<V>,
+    indices: Vec<u32>,
+}
+
+impl<V: Copy> MeshBuilder<V> {
+    pub fn new(layout: VertexLayout) -> Self {
+        Self {
+            layout,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    /// Adds a vertex and returns its index.
+    pub fn vertex(&mut self, vertex: V) -> u32 {
+        self.vertices.push(vertex);
+        (self.vertices.len() - 1) as u32
+    }
+
+    pub fn triangle(&mut self, a: u32, b: u32, c: u32) -> &mut Self {
+        self.indices.extend([a, b, c]);
+        self
+    }
+
+    /// Adds two triangles for the quad with counter-clockwise corners `a`, `b`, `c` and `d`.
+    pub fn quad(&mut self, a: u32, b: u32, c: u32, d: u32) -> &mut Self {
+        self.triangle(a, b, c).triangle(a, c, d)
+    }
+
+    pub fn build(self) -> Mesh {
+        let mut mesh = Mesh::new(self.layout);
+        mesh.set_vertices(&self.vertices);
+        mesh.set_indices(self.indices);
+        mesh
+    }
+}
+
+struct DeviceBuffer {
+    buffer: vk::Buffer,
+    memory: Allocation,
+    capacity: vk::DeviceSize,
+}
+
+/// The device buffers of an uploaded [`Mesh`].
+pub struct GpuMesh {
+    /// One buffer per stream of the mesh.
+    vertices: Vec<DeviceBuffer>,
+    streams: VertexStreams,
+    indices: DeviceBuffer,
+    index_count: u32,
+    layout: VertexLayout,
+    revision: u64,
+    /// The [`GlobalTransform`] of the entity, the identity if it has none.
+    model: Matrix4<f32>,
+    /// The [`MaterialId`] of the entity.
+    material: Option<MaterialId>,
+    /// The permutation drawing the mesh, `None` if it has no material and is not in the standard
+    /// vertex format.
+    pipeline: Option<PipelineKey>,
+    /// The [`Mesh::bounding_sphere`].
+    bounds: Option<(Point3<f32>, f32)>,
+}
+
+impl GpuMesh {
+    /// The vertex buffers, bound in binding order.
+    pub fn streams(&self) -> &VertexStreams {
+        &self.streams
+    }
+
+    pub fn index_buffer(&self) -> vk::Buffer {
+        self.indices.buffer
+    }
+
+    pub fn index_count(&self) -> u32 {
+        self.index_count
+    }
+
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    /// The object to world space transform the mesh is drawn with.
+    pub fn model(&self) -> Matrix4<f32> {
+        self.model
+    }
+
+    /// The pipeline permutation the mesh is drawn with, see [`PipelineKey::for_mesh`].
+    pub fn pipeline_key(&self) -> Option<&PipelineKey> {
+        self.pipeline.as_ref()
+    }
+
+    /// Whether any part of the mesh may be inside the world space `frustum`, meshes without a
+    /// [`Mesh::bounding_sphere`] always are.
+    pub fn is_visible(&self, frustum: &Frustum) -> bool {
+        let Some((center, radius)) = self.bounds else {
+            return true;
+        };
+        let center = Point3::from_homogeneous(self.model * center.to_homogeneous());
+        // The sphere grows with the largest scale of the model
+        let scale = [self.model.x, self.model.y, self.model.z]
+            .map(|axis| axis.truncate().magnitude())
+            .into_iter()
+            .fold(0.0, f32::max);
+        frustum.intersects_sphere(center, radius * scale)
+    }
+}
+
+/// The uploaded meshes of all entities with a [`Mesh`].
+#[derive(Default)]
+pub struct GpuMeshes {
+    meshes: Mutex<HashMap<Entity, GpuMesh>>,
+}
+
+impl Resource for GpuMeshes {}
+
+impl GpuMeshes {
+    fn meshes(&self) -> MutexGuard<'_, HashMap<Entity, GpuMesh>> {
+        self.meshes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn with<R>(&self, entity: Entity, f: impl FnOnce(&GpuMesh) -> R) -> Option<R> {
+        self.meshes().get(&entity).map(f)
+    }
+
+    /// Calls `f` with every uploaded mesh.
+    pub fn for_each(&self, mut f: impl FnMut(Entity, &GpuMesh)) {
+        for (&entity, gpu_mesh) in self.meshes().iter() {
+            f(entity, gpu_mesh);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.meshes().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meshes().is_empty()
+    }
+}
+
+struct Uploader<'a> {
+    device: &'a Device,
+    allocator: &'a GpuAllocator,
+    destroyer: &'a DeferredDestroyer,
+    command_pools: &'a CommandPools,
+    stats: &'a RenderStats,
+}
+
+impl Uploader<'_> {
+    /// Copies `data` into `target` through a staging buffer, growing it if it is too small.
+    fn upload(
+        &self,
+        data: &[u8],
+        usage: vk::BufferUsageFlags,
+        target: Option<DeviceBuffer>,
+    ) -> Result<DeviceBuffer, vk::Result> {
+        // Zero sized buffers are invalid, empty meshes keep a minimal buffer
+        let size = (data.len() as vk::DeviceSize).max(4);
+
+        let target = match target {
+            Some(target) if target.capacity >= size => target,
+            target => {
+                if let Some(target) = target {
+                    self.retire(target);
+                }
+                let (buffer, memory) = create_buffer(
+                    self.device,
+                    self.allocator,
+                    size,
+                    usage | vk::BufferUsageFlags::TRANSFER_DST,
+                    vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                )?;
+                DeviceBuffer {
+                    buffer,
+                    memory,
+                    capacity: size,
+                }
+            }
+        };
+
+        if data.is_empty() {
+            return Ok(target);
+        }
+
+        let (staging, staging_memory) = create_buffer(
+            self.device,
+            self.allocator,
+            size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+
+        let memory = staging_memory
+            .mapped_ptr()
+            .expect("Host visible allocations are mapped");
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), memory, data.len()) };
+        let result = copy_buffer(
+            self.device,
+            self.command_pools,
+            self.stats,
+            staging,
+            target.buffer,
+            data.len() as vk::DeviceSize,
+        );
+
+        unsafe { self.device.destroy_buffer(staging, None) };
+        self.allocator.free(self.device, staging_memory);
+
+        match result {
+            Ok(()) => Ok(target),
+            Err(err) => {
+                self.retire(target);
+                Err(err)
+            }
+        }
+    }
+
+    /// Destroys the buffer once the frames in flight no longer draw with it.
+    fn retire(&self, buffer: DeviceBuffer) {
+        self.destroyer
+            .retire(RetiredHandle::Buffer(buffer.buffer, buffer.memory));
+    }
+}
+
+/// Uploads new and changed meshes and retires the buffers of the meshes removed during the last
+/// frame, see [`RenderableChanges`]. Replaced buffers are destroyed by the [`DeferredDestroyer`]
+/// once the frames in flight finished.
+///
+/// The model matrices of all meshes are copied from their [`GlobalTransform`] every frame. The
+/// pipeline permutation of a mesh is declared with the [`PipelinePermutations`] when the mesh or
+/// its [`MaterialId`] changes.
+pub fn upload_meshes(
+    device: Res<Device>,
+    (allocator, destroyer): (Res<GpuAllocator>, Res<DeferredDestroyer>),
+    command_pools: Option<Res<CommandPools>>,
+    (stats, permutations): (Res<RenderStats>, Res<PipelinePermutations>),
+    (gpu_meshes, changes): (Res<GpuMeshes>, Res<RenderableChanges>),
+    meshes: Query<(Entity, &Mesh, Option<&GlobalTransform>, Option<&MaterialId>)>,
+) -> Result<(), vk::Result> {
+    let Some(command_pools) = command_pools else {
+        return Ok(());
+    };
+
+    let uploader = Uploader {
+        device: &device,
+        allocator: &allocator,
+        destroyer: &destroyer,
+        command_pools: &command_pools,
+        stats: &stats,
+    };
+
+    let mut uploaded = gpu_meshes.meshes();
+    for entity in changes.removed() {
+        if let Some(gpu_mesh) = uploaded.remove(entity) {
+            for buffer in gpu_mesh.vertices {
+                uploader.retire(buffer);
+            }
+            uploader.retire(gpu_mesh.indices);
+        }
+    }
+
+    let pipeline_key = |mesh: &Mesh, material: Option<&MaterialId>| {
+        let key = PipelineKey::for_mesh(material.copied(), RenderPass::Opaque, mesh)?;
+        permutations.declare(key.clone());
+        Some(key)
+    };
+
+    for (entity, mesh, transform, material) in meshes {
+        let model = transform.map_or_else(Matrix4::identity, |transform| transform.0);
+        if let Some(gpu_mesh) = uploaded
+            .get_mut(&entity)
+            .filter(|gpu_mesh| gpu_mesh.revision == mesh.revision())
+        {
+            gpu_mesh.model = model;
+            if gpu_mesh.material != material.copied() {
+                gpu_mesh.material = material.copied();
+                gpu_mesh.pipeline = pipeline_key(mesh, material);
+            }
+            continue;
+        }
+        let (mut previous, indices) = match uploaded.remove(&entity) {
+            Some(gpu_mesh) => (gpu_mesh.vertices, Some(gpu_mesh.indices)),
+            None => (Vec::new(), None),
+        };
+
+        debug!(
+            target: log_targets::RESOURCES,
+            "Uploading mesh of entity {entity} (revision {})",
+            mesh.revision()
+        );
+
+        // The buffers of a layout with fewer streams than before are not reused
+        if previous.len() > mesh.streams.len() {
+            for buffer in previous.drain(mesh.streams.len()..) {
+                uploader.retire(buffer);
+            }
+        }
+        let mut previous = previous.into_iter();
+        let mut vertices = Vec::with_capacity(mesh.streams.len());
+        let mut streams = VertexStreams::default();
+        for bytes in &mesh.streams {
+            let buffer =
+                uploader.upload(bytes, vk::BufferUsageFlags::VERTEX_BUFFER, previous.next())?;
+            streams.push(buffer.buffer, 0);
+            vertices.push(buffer);
+        }
+        let index_bytes = unsafe {
+            std::slice::from_raw_parts(
+                mesh.indices().as_ptr().cast::<u8>(),
+                size_of_val(mesh.indices()),
+            )
+        };
+        let indices = uploader.upload(index_bytes, vk::BufferUsageFlags::INDEX_BUFFER, indices)?;
+
+        uploaded.insert(
+            entity,
+            GpuMesh {
+                vertices,
+                streams,
+                indices,
+                index_count: mesh.indices().len() as u32,
+                layout: mesh.layout().clone(),
+                revision: mesh.revision(),
+                model,
+                material: material.copied(),
+                pipeline: pipeline_key(mesh, material),
+                bounds: mesh.bounding_sphere(),
+            },
+        );
+    }
+
+    Ok(())
+}
+
+pub fn destroy_meshes(
+    device: Res<Device>,
+    allocator: Res<GpuAllocator>,
+    gpu_meshes: Res<GpuMeshes>,
+) {
+    for (_, gpu_mesh) in gpu_meshes.meshes().drain() {
+        for buffer in gpu_mesh.vertices.into_iter().chain([gpu_mesh.indices]) {
+            unsafe { device.destroy_buffer(buffer.buffer, None) };
+            allocator.free(&device, buffer.memory);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_meshes_only_store_their_attributes() {
+        let positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]];
+        let uvs = [[0.0, 0.0], [1.0, 0.0]];
+        let uv2 = [[0.5, 0.5], [0.25, 0.25]];
+
+        let mesh = Mesh::from_standard(
+            &StandardVertices {
+                positions: &positions,
+                uvs: &uvs,
+                uv2: Some(&uv2),
+                ..Default::default()
+            },
+            vec![0, 1, 0],
+        );
+        let attributes = StandardAttributes::of_layout(mesh.layout());
+        assert_eq!(
+            attributes,
+            StandardAttributes {
+                colors: false,
+                uv2: true,
+            }
+        );
+        assert_eq!(mesh.vertex_count(), 2);
+        assert_eq!(mesh.layout().bindings[0].stride, 28);
+
+        // The second vertex starts with its position and ends with its second UV set
+        let floats: Vec<f32> = mesh
+            .vertex_bytes()
+            .chunks(4)
+            .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()))
+            .collect();
+        assert_eq!(floats[7..], [1.0, 0.0, 0.0, 1.0, 0.0, 0.25, 0.25]);
+
+        let colored = StandardAttributes {
+            colors: true,
+            uv2: false,
+        };
+        assert_eq!(colored.layout(), MeshVertex::layout());
+    }
+
+    #[test]
+    fn mesh_vertex_layout_matches_its_fields() {
+        let layout = MeshVertex::layout();
+        assert_eq!(layout.bindings[0].stride as usize, size_of::<MeshVertex>());
+
+        let offsets: Vec<_> = layout.attributes.iter().map(|a| a.offset as usize).collect();
+        assert_eq!(
+            offsets,
+            [
+                std::mem::offset_of!(MeshVertex, position),
+                std::mem::offset_of!(MeshVertex, color),
+                std::mem::offset_of!(MeshVertex, tex_coords),
+            ]
+        );
+    }
+
+    #[test]
+    fn deinterleaved_meshes_store_a_stream_per_binding() {
+        let layout = VertexLayout::deinterleaved([
+            (0, vk::Format::R32G32B32_SFLOAT),
+            (2, vk::Format::R32G32_SFLOAT),
+        ]);
+        let mut mesh = Mesh::new(layout);
+        mesh.set_stream(0, &[[0.0f32; 3]; 4]);
+        mesh.set_stream(1, &[[1.0f32; 2]; 4]);
+
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.stream_bytes(0).len(), 48);
+        assert_eq!(mesh.stream_bytes(1).len(), 32);
+    }
+
+    #[test]
+    fn bounding_spheres_enclose_the_positions() {
+        let mut mesh = Mesh::new(MeshVertex::layout());
+        assert_eq!(mesh.bounding_sphere(), None);
+
+        let vertex = |position| MeshVertex {
+            position,
+            color: [1.0; 3],
+            tex_coords: [0.0; 2],
+        };
+        mesh.set_vertices(&[vertex([-1.0, 0.0, 2.0]), vertex([3.0, 0.0, 2.0])]);
+        assert_eq!(
+            mesh.bounding_sphere(),
+            Some((Point3::new(1.0, 0.0, 2.0), 2.0))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "starting at binding 0")]
+    fn mesh_streams_start_at_binding_zero() {
+        let layout = VertexLayout::deinterleaved([
+            (0, vk::Format::R32G32B32_SFLOAT),
+            (2, vk::Format::R32G32_SFLOAT),
+        ]);
+        Mesh::new(layout.only(&[2]));
+    }
+
+    #[test]
+    fn builder_and_setters_track_revisions() {
+        let layout = VertexLayout::interleaved(8, [(0, vk::Format::R32G32_SFLOAT, 0)]);
+        let mut builder = MeshBuilder::new(layout);
+        let corners =
+            [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0f32]].map(|v| builder.vertex(v));
+        builder.quad(corners[0], corners[1], corners[2], corners[3]);
+
+        let mut mesh = builder.build();
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.indices(), [0, 1, 2, 0, 2, 3]);
+
+        let revision = mesh.revision();
+        mesh.set_vertices(&[[0.0f32; 2]; 3]);
+        assert_eq!(mesh.vertex_count(), 3);
+        assert!(mesh.revision() > revision);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::device::Device;
+use crate::log_targets;
+use crate::stats::RenderStats;
+use crate::swapchain::Swapchain;
+use ash::vk;
+use flux_ecs::commands::Commands;
+use flux_ecs::resource::{Res, Resource};
+use log::debug;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Enables hardware occlusion queries around large draws to collect visibility statistics.
+///
+/// Only the statistics are collected, occluded draws are still submitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OcclusionSettings {
+    pub enabled: bool,
+    /// Draws with fewer indices are not queried, the query overhead outweighs small objects.
+    pub min_index_count: u32,
+    /// The queries available per frame, further draws are not queried.
+    pub max_queries_per_frame: u32,
+}
+
+impl Resource for OcclusionSettings {}
+
+impl Default for OcclusionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_index_count: 3 * 1024,
+            max_queries_per_frame: 256,
+        }
+    }
+}
+
+/// The visibility of the queried draws of one frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OcclusionResults {
+    pub tested: u32,
+    /// The draws of which no sample passed the depth test.
+    pub occluded: u32,
+}
+
+impl OcclusionResults {
+    pub fn from_samples(samples: &[u64]) -> Self {
+        Self {
+            tested: samples.len() as u32,
+            occluded: samples.iter().filter(|&&samples| samples == 0).count() as u32,
+        }
+    }
+}
+
+/// A query recorded with [`OcclusionQueries::begin`].
+#[derive(Debug, Clone, Copy)]
+#[must_use]
+pub struct OcclusionQuery {
+    index: u32,
+}
+
+#[derive(Debug, Default)]
+struct FrameQueries {
+    issued: u32,
+    completed: bool,
+}
+
+/// A query pool with a range of occlusion queries for every swapchain image.
+///
+/// The command buffer of an image resets its range with [`OcclusionQueries::reset`] and wraps
+/// large draws in [`OcclusionQueries::begin`] and [`OcclusionQueries::end`]. Once the fence of
+/// the frame is signaled the frame driver calls [`OcclusionQueries::frame_completed`], and
+/// [`collect_occlusion_results`] reads the results back into the [`RenderStats`].
+pub struct OcclusionQueries {
+    pool: vk::QueryPool,
+    settings: OcclusionSettings,
+    frames: Vec<Mutex<FrameQueries>>,
+}
+
+impl Resource for OcclusionQueries {}
+
+impl OcclusionQueries {
+    fn frame(&self, frame: usize) -> MutexGuard<'_, FrameQueries> {
+        self.frames[frame]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn first_query(&self, frame: usize) -> u32 {
+        frame as u32 * self.settings.max_queries_per_frame
+    }
+
+    /// Whether a draw with `index_count` indices should be queried.
+    pub fn should_query(&self, index_count: u32) -> bool {
+        index_count >= self.settings.min_index_count
+    }
+
+    /// Resets the queries of `frame`.
+    ///
+    /// # Safety
+    /// The command buffer must be recording outside of a rendering pass.
+    pub unsafe fn reset(&self, device: &Device, command_buffer: vk::CommandBuffer, frame: usize) {
+        *self.frame(frame) = FrameQueries::default();
+        unsafe {
+            device.cmd_reset_query_pool(
+                command_buffer,
+                self.pool,
+                self.first_query(frame),
+                self.settings.max_queries_per_frame,
+            );
+        }
+    }
+
+    /// Begins a query for the next draw, `None` if all queries of the frame are in use.
+    ///
+    /// # Safety
+    /// The command buffer must be recording inside a rendering pass, after [`Self::reset`].
+    pub unsafe fn begin(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        frame: usize,
+    ) -> Option<OcclusionQuery> {
+        let mut queries = self.frame(frame);
+        if queries.issued >= self.settings.max_queries_per_frame {
+            return None;
+        }
+
+        let query = OcclusionQuery {
+            index: self.first_query(frame) + queries.issued,
+        };
+        queries.issued += 1;
+
+        unsafe {
+            device.cmd_begin_query(
+                command_buffer,
+                self.pool,
+                query.index,
+                vk::QueryControlFlags::empty(),
+            );
+        }
+        Some(query)
+    }
+
+    /// # Safety
+    /// `query` must have been begun on the same command buffer and rendering pass.
+    pub unsafe fn end(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        query: OcclusionQuery,
+    ) {
+        unsafe { device.cmd_end_query(command_buffer, self.pool, query.index) };
+    }
+
+    /// Marks the queries of `frame` as ready for readback, call it once its fence is signaled.
+    pub fn frame_completed(&self, frame: usize) {
+        self.frame(frame).completed = true;
+    }
+
+    /// Reads the results of `frame` without waiting, `None` if they are not available yet.
+    pub fn read_results(
+        &self,
+        device: &Device,
+        frame: usize,
+    ) -> Result<Option<OcclusionResults>, vk::Result> {
+        let issued = self.frame(frame).issued;
+        if issued == 0 {
+            return Ok(Some(OcclusionResults::default()));
+        }
+
+        let mut samples = vec![0u64; issued as usize];
+        let result = unsafe {
+            device.get_query_pool_results(
+                self.pool,
+                self.first_query(frame),
+                &mut samples,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        };
+
+        match result {
+            Ok(()) => Ok(Some(OcclusionResults::from_samples(&samples))),
+            Err(vk::Result::NOT_READY) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+pub fn create_occlusion_queries(
+    device: Res<Device>,
+    settings: Option<Res<OcclusionSettings>>,
+    swapchain: Option<Res<Swapchain>>,
+    mut commands: Commands,
+) -> Result<(), vk::Result> {
+    let (Some(settings), Some(swapchain)) = (settings, swapchain) else {
+        return Ok(());
+    };
+    if !settings.enabled || settings.max_queries_per_frame == 0 {
+        return Ok(());
+    }
+
+    let frames = swapchain.images.len();
+    debug!(
+        target: log_targets::RESOURCES,
+        "Creating {} occlusion queries for {frames} frames",
+        settings.max_queries_per_frame
+    );
+
+    let info = vk::QueryPoolCreateInfo::default()
+        .query_type(vk::QueryType::OCCLUSION)
+        .query_count(settings.max_queries_per_frame * frames as u32);
+    let pool = unsafe { device.create_query_pool(&info, None) }?;
+
+    commands.insert_resource(OcclusionQueries {
+        pool,
+        settings: *settings,
+        frames: (0..frames).map(|_| Mutex::default()).collect(),
+    });
+
+    Ok(())
+}
+
+/// Records the results of completed frames in the [`RenderStats`].
+pub fn collect_occlusion_results(
+    device: Res<Device>,
+    queries: Option<Res<OcclusionQueries>>,
+    stats: Res<RenderStats>,
+) -> Result<(), vk::Result> {
+    let Some(queries) = queries else {
+        return Ok(());
+    };
+
+    for frame in 0..queries.frames.len() {
+        if !queries.frame(frame).completed {
+            continue;
+        }
+
+        if let Some(results) = queries.read_results(&device, frame)? {
+            stats.record_occlusion(results);
+            queries.frame(frame).completed = false;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn destroy_occlusion_queries(
+    device: Res<Device>,
+    queries: Option<Res<OcclusionQueries>>,
+    mut commands: Commands,
+) {
+    let Some(queries) = queries else {
+        return;
+    };
+
+    debug!(target: log_targets::RESOURCES, "Destroying occlusion queries");
+    unsafe { device.destroy_query_pool(queries.pool, None) };
+    commands.remove_resource::<OcclusionQueries>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_without_samples_are_occluded() {
+        let results = OcclusionResults::from_samples(&[0, 12, 0, 1]);
+        assert_eq!(
+            results,
+            OcclusionResults {
+                tested: 4,
+                occluded: 2
+            }
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use flux_ecs::commands::Commands;
+use flux_ecs::component::Component;
+use flux_ecs::query::Query;
+use flux_ecs::resource::{Res, Resource};
+use flux_time::time::Time;
+
+/// The particle simulation advances in fixed steps of this length, independent of the frame rate.
+pub const PARTICLE_TIME_STEP: f32 = 1.0 / 60.0;
+
+/// Caps the steps simulated per frame so a long frame does not stall the simulation further.
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+/// Describes the particles spawned by a [`ParticleEmitter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitterSettings {
+    /// The particles spawned per second.
+    pub spawn_rate: f32,
+    /// The lifetime of a particle in seconds, sampled uniformly between the bounds.
+    pub lifetime: [f32; 2],
+    pub origin: [f32; 3],
+    /// The initial velocity of a particle, each component is sampled uniformly between the
+    /// bounds.
+    pub velocity_min: [f32; 3],
+    pub velocity_max: [f32; 3],
+    pub acceleration: [f32; 3],
+    pub size: f32,
+    /// The color is interpolated from the start to the end color over the particle lifetime.
+    pub color_start: [f32; 4],
+    pub color_end: [f32; 4],
+    /// Spawning pauses while this many particles are alive.
+    pub max_particles: usize,
+    /// The depth difference to opaque geometry over which particles should fade out instead of
+    /// clipping, zero disables the fade. Passed on in the [`ParticleBatch`].
+    pub soft_depth_distance: f32,
+}
+
+impl Default for EmitterSettings {
+    fn default() -> Self {
+        Self {
+            spawn_rate: 10.0,
+            lifetime: [1.0, 1.0],
+            origin: [0.0; 3],
+            velocity_min: [0.0; 3],
+            velocity_max: [0.0; 3],
+            acceleration: [0.0; 3],
+            size: 1.0,
+            color_start: [1.0; 4],
+            color_end: [1.0, 1.0, 1.0, 0.0],
+            max_particles: 1024,
+            soft_depth_distance: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Particle {
+    position: [f32; 3],
+    velocity: [f32; 3],
+    age: f32,
+    lifetime: f32,
+}
+
+/// Spawns and simulates particles on the CPU, see [`prepare_particle_batches`] for drawing them.
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+    pub settings: EmitterSettings,
+    particles: Vec<Particle>,
+    spawn_accumulator: f32,
+    step_accumulator: f32,
+    rng: u32,
+}
+
+impl Component for ParticleEmitter {}
+
+impl ParticleEmitter {
+    pub fn new(settings: EmitterSettings) -> Self {
+        Self::with_seed(settings, 0x9e37_79b9)
+    }
+
+    /// Creates an emitter with a fixed random seed, emitters with the same seed and settings
+    /// produce the same particles.
+    pub fn with_seed(settings: EmitterSettings, seed: u32) -> Self {
+        Self {
+            settings,
+            particles: Vec::new(),
+            spawn_accumulator: 0.0,
+            step_accumulator: 0.0,
+            rng: seed.max(1),
+        }
+    }
+
+    pub fn alive(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Advances the simulation by `delta` seconds in steps of [`PARTICLE_TIME_STEP`].
+    pub fn advance(&mut self, delta: f32) {
+        self.step_accumulator += delta;
+
+        let mut steps = 0;
+        while self.step_accumulator >= PARTICLE_TIME_STEP {
+            self.step_accumulator -= PARTICLE_TIME_STEP;
+            steps += 1;
+            if steps > MAX_STEPS_PER_FRAME {
+                self.step_accumulator = 0.0;
+                break;
+            }
+            self.step();
+        }
+    }
+
+    fn step(&mut self) {
+        let dt = PARTICLE_TIME_STEP;
+        let acceleration = self.settings.acceleration;
+
+        self.particles.retain_mut(|particle| {
+            particle.age += dt;
+            for (axis, acceleration) in acceleration.iter().enumerate() {
+                particle.velocity[axis] += acceleration * dt;
+                particle.position[axis] += particle.velocity[axis] * dt;
+            }
+            particle.age < particle.lifetime
+        });
+
+        self.spawn_accumulator += self.settings.spawn_rate * dt;
+        while self.spawn_accumulator >= 1.0 {
+            self.spawn_accumulator -= 1.0;
+            if self.particles.len() < self.settings.max_particles {
+                let particle = self.spawn_particle();
+                self.particles.push(particle);
+            }
+        }
+    }
+
+    fn spawn_particle(&mut self) -> Particle {
+        let [lifetime_min, lifetime_max] = self.settings.lifetime;
+        let (velocity_min, velocity_max) = (self.settings.velocity_min, self.settings.velocity_max);
+
+        Particle {
+            position: self.settings.origin,
+            velocity: [0, 1, 2].map(|axis| self.sample(velocity_min[axis], velocity_max[axis])),
+            age: 0.0,
+            lifetime: self.sample(lifetime_min, lifetime_max),
+        }
+    }
+
+    /// Samples uniformly between `min` and `max` using a xorshift generator.
+    fn sample(&mut self, min: f32, max: f32) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        let unit = (self.rng >> 8) as f32 / (1u32 << 24) as f32;
+        min + (max - min) * unit
+    }
+
+    fn instances(&self) -> impl Iterator<Item = ParticleInstance> + '_ {
+        let (start, end) = (self.settings.color_start, self.settings.color_end);
+
+        self.particles.iter().map(move |particle| {
+            let t = (particle.age / particle.lifetime).clamp(0.0, 1.0);
+            ParticleInstance {
+                position: particle.position,
+                size: self.settings.size,
+                color: [0, 1, 2, 3].map(|i| start[i] + (end[i] - start[i]) * t),
+            }
+        })
+    }
+}
+
+/// Per-instance data of a particle quad.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleInstance {
+    pub position: [f32; 3],
+    pub size: f32,
+    pub color: [f32; 4],
+}
+
+/// The particles of one emitter, meant to be drawn with a single instanced, alpha blended draw.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleBatch {
+    pub soft_depth_distance: f32,
+    pub instances: Vec<ParticleInstance>,
+}
+
+/// The particle batches of the current frame, see [`prepare_particle_batches`].
+#[derive(Debug, Default)]
+pub struct ParticleBatches {
+    pub batches: Vec<ParticleBatch>,
+}
+
+impl Resource for ParticleBatches {}
+
+impl ParticleBatches {
+    /// The size of the instance data of all batches in bytes.
+    pub fn instance_bytes(&self) -> usize {
+        self.batches
+            .iter()
+            .map(|batch| size_of_val(batch.instances.as_slice()))
+            .sum()
+    }
+}
+
+pub fn simulate_particles(time: Option<Res<Time>>, emitters: Query<&mut ParticleEmitter>) {
+    let Some(time) = time else {
+        return;
+    };
+
+    for emitter in emitters {
+        emitter.advance(time.delta_secs());
+    }
+}
+
+/// Collects the instances of every emitter with alive particles into the [`ParticleBatches`].
+///
+/// The renderer does not draw the batches yet, so the [`RendererPlugin`](crate::RendererPlugin)
+/// does not run this system. Add it to a schedule to draw the batches yourself, e.g. from
+/// [`RawVulkanHooks`](crate::RawVulkanHooks), or use a
+/// [`GpuParticleEmitter`](crate::GpuParticleEmitter) which is drawn.
+pub fn prepare_particle_batches(emitters: Query<&ParticleEmitter>, mut commands: Commands) {
+    let batches = emitters
+        .into_iter()
+        .filter(|emitter| emitter.alive() > 0)
+        .map(|emitter| ParticleBatch {
+            soft_depth_distance: emitter.settings.soft_depth_distance,
+            instances: emitter.instances().collect(),
+        })
+        .collect();
+
+    commands.insert_resource(ParticleBatches { batches });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn particles_spawn_at_the_rate_and_expire() {
+        let mut emitter = ParticleEmitter::new(EmitterSettings {
+            spawn_rate: 60.0,
+            lifetime: [0.5, 0.5],
+            velocity_min: [-1.0, 1.0, 0.0],
+            velocity_max: [1.0, 2.0, 0.0],
+            ..EmitterSettings::default()
+        });
+
+        for _ in 0..15 {
+            emitter.advance(PARTICLE_TIME_STEP);
+        }
+        assert_eq!(emitter.alive(), 15);
+        assert!(
+            emitter
+                .instances()
+                .all(|instance| instance.position[1] >= 0.0)
+        );
+
+        emitter.settings.spawn_rate = 0.0;
+        for _ in 0..30 {
+            emitter.advance(PARTICLE_TIME_STEP);
+        }
+        assert_eq!(emitter.alive(), 0);
+    }
+}
@@ -0,0 +1,505 @@
+use crate::depth_buffers::DepthBuffers;
+use crate::device::Device;
+use crate::log_targets;
+use crate::mesh::{Mesh, StandardAttributes};
+use crate::pipeline::{Pipeline, read_spv};
+use crate::render_path::ClassicRenderPass;
+use crate::stats::RenderStats;
+use crate::swapchain::Swapchain;
+use crate::vertex_layout::VertexLayout;
+use ash::vk;
+use flux_ecs::component::Component;
+use flux_ecs::resource::{Res, Resource};
+use log::{debug, info};
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+use thiserror::Error;
+
+/// A material registered with the [`PipelinePermutations`].
+///
+/// As a component it selects the material the [`Mesh`] of the entity is drawn with, meshes
+/// without one are drawn with the standard material, see [`PipelineKey::for_mesh`]. Ids counting
+/// down from `u32::MAX` are reserved for the built-in materials.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MaterialId(pub u32);
+
+impl Component for MaterialId {}
+
+impl MaterialId {
+    /// Draws the vertex colors of meshes in the standard vertex format.
+    pub const STANDARD: Self = Self(u32::MAX);
+    /// Draws meshes in the standard vertex format without vertex colors in white.
+    pub const STANDARD_UNCOLORED: Self = Self(u32::MAX - 1);
+    /// Lights the [`TerrainVertex`](crate::TerrainVertex) chunks of the terrain by the sun and the
+    /// clustered [`PointLight`](crate::PointLight)s, see [`LightClusters`](crate::LightClusters).
+    pub const TERRAIN: Self = Self(u32::MAX - 2);
+}
+
+/// The pass a pipeline permutation renders in, it selects the blend and depth state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RenderPass {
+    /// Depth tested and written, no blending.
+    Opaque,
+    /// Alpha blended, depth tested but not written.
+    Transparent,
+    /// Only writes depth.
+    DepthPrepass,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub material: MaterialId,
+    pub pass: RenderPass,
+    pub vertex_layout: VertexLayout,
+}
+
+impl PipelineKey {
+    /// The permutation drawing the mesh, it follows the vertex attributes the mesh has.
+    ///
+    /// Meshes without a material are drawn with the standard material variant for their
+    /// [`StandardAttributes`], `None` if their layout is not in the standard vertex format.
+    pub fn for_mesh(material: Option<MaterialId>, pass: RenderPass, mesh: &Mesh) -> Option<Self> {
+        let material = match material {
+            Some(material) => material,
+            None => StandardAttributes::matching(mesh.layout())?.material(),
+        };
+        Some(Self {
+            material,
+            pass,
+            vertex_layout: mesh.layout().clone(),
+        })
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PipelineWarmupError {
+    #[error("invalid SPIR-V for material {material:?}: {source}")]
+    InvalidSpirv {
+        material: MaterialId,
+        source: io::Error,
+    },
+    #[error("no shaders registered for material {0:?}")]
+    UnknownMaterial(MaterialId),
+    #[error("could not create the pipelines: {0}")]
+    Vulkan(#[from] vk::Result),
+}
+
+struct MaterialShaders {
+    vertex: Vec<u32>,
+    fragment: Vec<u32>,
+}
+
+/// The graphics pipelines of all material, pass and vertex layout combinations.
+///
+/// Declare the permutations a scene needs while loading its assets, they are created together by
+/// the next run of [`warm_up_pipelines`] instead of on first use in the middle of a frame. The
+/// main pass looks the pipelines of meshes with a [`MaterialId`] up here, permutations that were
+/// not declared are declared when their mesh is uploaded and drawn from the next frame on.
+#[derive(Default)]
+pub struct PipelinePermutations {
+    materials: Mutex<HashMap<MaterialId, MaterialShaders>>,
+    pending: Mutex<Vec<PipelineKey>>,
+    pipelines: Mutex<HashMap<PipelineKey, vk::Pipeline>>,
+}
+
+impl Resource for PipelinePermutations {}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl PipelinePermutations {
+    /// Registers the SPIR-V shaders of a material, replacing previously registered shaders.
+    /// Pipelines that were already created keep using the old shaders.
+    pub fn register_material(
+        &self,
+        material: MaterialId,
+        vertex_spv: &[u8],
+        fragment_spv: &[u8],
+    ) -> Result<(), PipelineWarmupError> {
+        let read = |code: &[u8]| {
+            read_spv(&mut io::Cursor::new(code))
+                .map_err(|source| PipelineWarmupError::InvalidSpirv { material, source })
+        };
+
+        let shaders = MaterialShaders {
+            vertex: read(vertex_spv)?,
+            fragment: read(fragment_spv)?,
+        };
+        lock(&self.materials).insert(material, shaders);
+
+        Ok(())
+    }
+
+    /// Queues the permutation for creation, returns `false` if it exists or is already queued.
+    pub fn declare(&self, key: PipelineKey) -> bool {
+        if lock(&self.pipelines).contains_key(&key) {
+            return false;
+        }
+
+        let mut pending = lock(&self.pending);
+        if pending.contains(&key) {
+            return false;
+        }
+        pending.push(key);
+        true
+    }
+
+    /// Registers the shaders of the built-in materials, e.g. the standard materials, see
+    /// [`StandardAttributes::material`].
+    pub(crate) fn register_builtin_materials(&self) {
+        let standard_fragment = &include_bytes!("../shaders/frag.spv")[..];
+        let materials = [
+            (
+                MaterialId::STANDARD,
+                &include_bytes!("../shaders/vert.spv")[..],
+                standard_fragment,
+            ),
+            (
+                MaterialId::STANDARD_UNCOLORED,
+                &include_bytes!("../shaders/standard_uncolored_vert.spv")[..],
+                standard_fragment,
+            ),
+            (
+                MaterialId::TERRAIN,
+                &include_bytes!("../shaders/terrain_vert.spv")[..],
+                &include_bytes!("../shaders/terrain_frag.spv")[..],
+            ),
+        ];
+        for (material, vertex, fragment) in materials {
+            self.register_material(material, vertex, fragment)
+                .expect("The built-in shaders are valid SPIR-V");
+        }
+    }
+
+    pub fn get(&self, key: &PipelineKey) -> Option<vk::Pipeline> {
+        lock(&self.pipelines).get(key).copied()
+    }
+
+    pub fn pending_count(&self) -> usize {
+        lock(&self.pending).len()
+    }
+
+    pub fn len(&self) -> usize {
+        lock(&self.pipelines).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        lock(&self.pipelines).is_empty()
+    }
+}
+
+/// Creates every declared permutation in a single `vkCreateGraphicsPipelines` call.
+pub fn warm_up_pipelines(
+    device: Res<Device>,
+    swapchain: Option<Res<Swapchain>>,
+    depth_buffers: Option<Res<DepthBuffers>>,
+    pipeline: Option<Res<Pipeline>>,
+    render_pass: Option<Res<ClassicRenderPass>>,
+    permutations: Res<PipelinePermutations>,
+    stats: Res<RenderStats>,
+) -> Result<(), PipelineWarmupError> {
+    let (Some(swapchain), Some(depth_buffers), Some(pipeline)) =
+        (swapchain, depth_buffers, pipeline)
+    else {
+        return Ok(());
+    };
+
+    let keys = std::mem::take(&mut *lock(&permutations.pending));
+    if keys.is_empty() {
+        return Ok(());
+    }
+
+    debug!(target: log_targets::PIPELINE, "Warming up {} pipeline permutations", keys.len());
+    let start = Instant::now();
+
+    let materials = lock(&permutations.materials);
+    let mut modules = Vec::with_capacity(keys.len() * 2);
+    let result = (|| {
+        let mut shaders = Vec::with_capacity(keys.len());
+        for key in &keys {
+            let material = materials
+                .get(&key.material)
+                .ok_or(PipelineWarmupError::UnknownMaterial(key.material))?;
+            let vertex = create_shader_module(&device, &material.vertex)?;
+            modules.push(vertex);
+            let fragment = create_shader_module(&device, &material.fragment)?;
+            modules.push(fragment);
+            shaders.push((vertex, fragment));
+        }
+
+        let targets = RenderTargets {
+            color_format: swapchain.format.format,
+            depth_format: depth_buffers.depth_format,
+            render_pass: render_pass.as_ref().map(|render_pass| render_pass.render_pass),
+        };
+        create_permutations(&device, pipeline.pipeline_layout, &targets, &keys, &shaders)
+    })();
+    drop(materials);
+
+    unsafe {
+        for module in modules {
+            device.destroy_shader_module(module, None);
+        }
+    }
+
+    let pipelines = match result {
+        Ok(pipelines) => pipelines,
+        Err(err) => {
+            // Keep the permutations queued so a later run can retry them
+            lock(&permutations.pending).extend(keys);
+            return Err(err);
+        }
+    };
+
+    let elapsed = start.elapsed();
+    stats.record_pipeline_warmup(pipelines.len() as u32, elapsed);
+    info!(
+        target: log_targets::PIPELINE,
+        "Created {} pipeline permutations in {elapsed:?}",
+        pipelines.len()
+    );
+
+    lock(&permutations.pipelines).extend(keys.into_iter().zip(pipelines));
+
+    Ok(())
+}
+
+pub fn destroy_pipeline_permutations(device: Res<Device>, permutations: Res<PipelinePermutations>) {
+    let mut pipelines = lock(&permutations.pipelines);
+    if pipelines.is_empty() {
+        return;
+    }
+
+    debug!(target: log_targets::PIPELINE, "Destroying {} pipeline permutations", pipelines.len());
+    for (_, pipeline) in pipelines.drain() {
+        unsafe { device.destroy_pipeline(pipeline, None) };
+    }
+}
+
+struct RenderTargets {
+    color_format: vk::Format,
+    depth_format: vk::Format,
+    /// Pipelines are created for this render pass instead of dynamic rendering if set.
+    render_pass: Option<vk::RenderPass>,
+}
+
+fn create_shader_module(device: &Device, code: &[u32]) -> Result<vk::ShaderModule, vk::Result> {
+    let info = vk::ShaderModuleCreateInfo::default().code(code);
+    unsafe { device.create_shader_module(&info, None) }
+}
+
+fn create_permutations(
+    device: &Device,
+    layout: vk::PipelineLayout,
+    targets: &RenderTargets,
+    keys: &[PipelineKey],
+    shaders: &[(vk::ShaderModule, vk::ShaderModule)],
+) -> Result<Vec<vk::Pipeline>, PipelineWarmupError> {
+    // Like the main pipeline, the viewport follows the swapchain extent
+    let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+        .viewport_count(1)
+        .scissor_count(1);
+    let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state =
+        vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+
+    let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+
+    let rasterization = vk::PipelineRasterizationStateCreateInfo::default()
+        .polygon_mode(vk::PolygonMode::FILL)
+        .line_width(1.0)
+        .cull_mode(vk::CullModeFlags::BACK)
+        .front_face(vk::FrontFace::COUNTER_CLOCKWISE);
+
+    let multisample = vk::PipelineMultisampleStateCreateInfo::default()
+        .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+
+    let color_formats = [targets.color_format];
+
+    // The create infos borrow these, so they are built for every key before any create info
+    let stages: Vec<_> = shaders
+        .iter()
+        .map(|&(vertex, fragment)| {
+            [
+                vk::PipelineShaderStageCreateInfo::default()
+                    .stage(vk::ShaderStageFlags::VERTEX)
+                    .module(vertex)
+                    .name(c"main"),
+                vk::PipelineShaderStageCreateInfo::default()
+                    .stage(vk::ShaderStageFlags::FRAGMENT)
+                    .module(fragment)
+                    .name(c"main"),
+            ]
+        })
+        .collect();
+    let bindings: Vec<_> = keys
+        .iter()
+        .map(|key| key.vertex_layout.binding_descriptions())
+        .collect();
+    let attributes: Vec<_> = keys
+        .iter()
+        .map(|key| key.vertex_layout.attribute_descriptions())
+        .collect();
+    let vertex_inputs: Vec<_> = bindings
+        .iter()
+        .zip(&attributes)
+        .map(|(bindings, attributes)| {
+            vk::PipelineVertexInputStateCreateInfo::default()
+                .vertex_binding_descriptions(bindings)
+                .vertex_attribute_descriptions(attributes)
+        })
+        .collect();
+    let depth_stencils: Vec<_> = keys
+        .iter()
+        .map(|key| {
+            vk::PipelineDepthStencilStateCreateInfo::default()
+                .depth_test_enable(true)
+                .depth_write_enable(key.pass != RenderPass::Transparent)
+                .depth_compare_op(vk::CompareOp::LESS)
+                .max_depth_bounds(1.0)
+        })
+        .collect();
+    let blend_attachments: Vec<_> = keys
+        .iter()
+        .map(|key| [blend_attachment(key.pass)])
+        .collect();
+    let color_blends: Vec<_> = blend_attachments
+        .iter()
+        .map(|attachments| {
+            vk::PipelineColorBlendStateCreateInfo::default()
+                .logic_op(vk::LogicOp::COPY)
+                .attachments(attachments)
+        })
+        .collect();
+    let mut rendering_infos: Vec<_> = keys
+        .iter()
+        .map(|_| {
+            vk::PipelineRenderingCreateInfo::default()
+                .color_attachment_formats(&color_formats)
+                .depth_attachment_format(targets.depth_format)
+        })
+        .collect();
+
+    let infos: Vec<_> = rendering_infos
+        .iter_mut()
+        .enumerate()
+        .map(|(i, rendering_info)| {
+            let info = vk::GraphicsPipelineCreateInfo::default()
+                .stages(&stages[i])
+                .vertex_input_state(&vertex_inputs[i])
+                .input_assembly_state(&input_assembly)
+                .viewport_state(&viewport_state)
+                .rasterization_state(&rasterization)
+                .multisample_state(&multisample)
+                .depth_stencil_state(&depth_stencils[i])
+                .color_blend_state(&color_blends[i])
+                .dynamic_state(&dynamic_state)
+                .layout(layout);
+            match targets.render_pass {
+                Some(render_pass) => info.render_pass(render_pass).subpass(0),
+                None => info.push_next(rendering_info),
+            }
+        })
+        .collect();
+
+    let pipelines =
+        unsafe { device.create_graphics_pipelines(vk::PipelineCache::null(), &infos, None) }
+            .map_err(|(pipelines, err)| {
+                for pipeline in pipelines {
+                    if pipeline != vk::Pipeline::null() {
+                        unsafe { device.destroy_pipeline(pipeline, None) };
+                    }
+                }
+                err
+            })?;
+
+    Ok(pipelines)
+}
+
+fn blend_attachment(pass: RenderPass) -> vk::PipelineColorBlendAttachmentState {
+    let attachment = vk::PipelineColorBlendAttachmentState::default();
+    match pass {
+        RenderPass::Opaque => attachment.color_write_mask(vk::ColorComponentFlags::RGBA),
+        RenderPass::Transparent => attachment
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+            .alpha_blend_op(vk::BlendOp::ADD),
+        RenderPass::DepthPrepass => attachment.color_write_mask(vk::ColorComponentFlags::empty()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::StandardVertices;
+
+    fn key(pass: RenderPass) -> PipelineKey {
+        PipelineKey {
+            material: MaterialId(0),
+            pass,
+            vertex_layout: VertexLayout::interleaved(12, [(0, vk::Format::R32G32B32_SFLOAT, 0)]),
+        }
+    }
+
+    #[test]
+    fn permutations_are_declared_once() {
+        let permutations = PipelinePermutations::default();
+
+        assert!(permutations.declare(key(RenderPass::Opaque)));
+        assert!(!permutations.declare(key(RenderPass::Opaque)));
+        assert!(permutations.declare(key(RenderPass::Transparent)));
+        assert_eq!(permutations.pending_count(), 2);
+
+        assert!(matches!(
+            permutations.register_material(MaterialId(0), &[1, 2, 3], &[]),
+            Err(PipelineWarmupError::InvalidSpirv { .. })
+        ));
+    }
+
+    #[test]
+    fn meshes_without_a_material_use_the_standard_variant() {
+        let positions = [[0.0; 3]; 3];
+        let uvs = [[0.0; 2]; 3];
+        let colors = [[1.0; 3]; 3];
+        let vertices = StandardVertices {
+            positions: &positions,
+            uvs: &uvs,
+            ..Default::default()
+        };
+        let uncolored = Mesh::from_standard(&vertices, vec![0, 1, 2]);
+        let colored = Mesh::from_standard(
+            &StandardVertices {
+                colors: Some(&colors),
+                ..vertices
+            },
+            vec![0, 1, 2],
+        );
+
+        let material = |material, mesh| {
+            PipelineKey::for_mesh(material, RenderPass::Opaque, mesh).map(|key| key.material)
+        };
+        assert_eq!(material(None, &colored), Some(MaterialId::STANDARD));
+        assert_eq!(
+            material(None, &uncolored),
+            Some(MaterialId::STANDARD_UNCOLORED)
+        );
+        assert_eq!(
+            material(Some(MaterialId(4)), &uncolored),
+            Some(MaterialId(4))
+        );
+
+        let positions_only = VertexLayout::interleaved(12, [(0, vk::Format::R32G32B32_SFLOAT, 0)]);
+        assert_eq!(material(None, &Mesh::new(positions_only)), None);
+
+        PipelinePermutations::default().register_builtin_materials();
+    }
+}
@@ -1,20 +1,17 @@
 use crate::device::Device;
+use crate::mesh::MeshVertex;
+use crate::render_path::ClassicRenderPass;
 use crate::swapchain::Swapchain;
+use crate::log_targets;
+use crate::progress::InitializationProgress;
 use ash::vk;
 use flux_ecs::commands::Commands;
 use flux_ecs::resource::{Res, Resource};
+use log::debug;
 use std::{io, slice};
 use std::ops::Deref;
 // TODO: Error handling is just a placeholder, needs to be improved
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-struct Vertex {
-    pos: [f32; 3],
-    color: [f32; 3],
-    tex_coords: [f32; 2],
-}
-
 pub struct Pipeline {
     pub pipeline: vk::Pipeline,
     // TODO: Not sure if this belongs here
@@ -24,6 +21,20 @@ pub struct Pipeline {
 
 impl Resource for Pipeline {}
 
+/// The push constants of every mesh draw, the vertex stage reads them as `push.model`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct MeshPushConstants {
+    /// The object to world space transform of the mesh.
+    pub model: cgmath::Matrix4<f32>,
+}
+
+impl MeshPushConstants {
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts((self as *const Self).cast::<u8>(), size_of::<Self>()) }
+    }
+}
+
 impl Deref for Pipeline {
     type Target = vk::Pipeline;
 
@@ -32,11 +43,25 @@ impl Deref for Pipeline {
     }
 }
 
+/// Creates the main pipeline once a swapchain exists, the pipeline is kept when the swapchain is
+/// recreated.
 pub fn create_pipeline(
     device: Res<Device>,
-    swapchain: Res<Swapchain>,
+    swapchain: Option<Res<Swapchain>>,
+    render_pass: Option<Res<ClassicRenderPass>>,
+    existing: Option<Res<Pipeline>>,
     mut commands: Commands,
 ) -> Result<(), vk::Result> {
+    let Some(swapchain) = swapchain else {
+        debug!(target: log_targets::PIPELINE, "No swapchain available, skipping pipeline");
+        return Ok(());
+    };
+    if existing.is_some() {
+        return Ok(());
+    }
+
+    debug!(target: log_targets::PIPELINE, "Creating graphics pipeline");
+
     let vertex_shader_module =
         create_shader_module(&device, &include_bytes!("../shaders/vert.spv")[..])?;
     let frag_shader_module =
@@ -52,31 +77,9 @@ pub fn create_pipeline(
         .module(frag_shader_module)
         .name(c"main");
 
-    let vertex_binding_descriptions = [vk::VertexInputBindingDescription::default()
-        .binding(0)
-        .stride(size_of::<Vertex>() as u32)
-        .input_rate(vk::VertexInputRate::VERTEX)];
-
-    let vertex_attribute_descriptions = [
-        // Position attribute
-        vk::VertexInputAttributeDescription::default()
-            .binding(0)
-            .location(0)
-            .format(vk::Format::R32G32B32_SFLOAT)
-            .offset(0),
-        // Color attribute
-        vk::VertexInputAttributeDescription::default()
-            .binding(0)
-            .location(1)
-            .format(vk::Format::R32G32B32_SFLOAT)
-            .offset(size_of::<[f32; 3]>() as u32),
-        // Texture coordinates attribute
-        vk::VertexInputAttributeDescription::default()
-            .binding(0)
-            .location(2)
-            .format(vk::Format::R32G32_SFLOAT)
-            .offset((size_of::<[f32; 3]>() + size_of::<[f32; 3]>()) as u32),
-    ];
+    let vertex_layout = MeshVertex::layout();
+    let vertex_binding_descriptions = vertex_layout.binding_descriptions();
+    let vertex_attribute_descriptions = vertex_layout.attribute_descriptions();
 
     let vertex_input_info = vk::PipelineVertexInputStateCreateInfo::default()
         .vertex_binding_descriptions(&vertex_binding_descriptions)
@@ -86,23 +89,13 @@ pub fn create_pipeline(
         .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
         .primitive_restart_enable(false);
 
-    let viewport = vk::Viewport::default()
-        .x(0.0)
-        .y(0.0)
-        .width(swapchain.extent.width as f32)
-        .height(swapchain.extent.height as f32)
-        .min_depth(0.0)
-        .max_depth(1.0);
-
-    let scissor = vk::Rect2D::default()
-        .offset(vk::Offset2D::default())
-        .extent(swapchain.extent);
-
-    let viewports = &[viewport];
-    let scissors = &[scissor];
+    // The viewport follows the swapchain extent, which changes when the swapchain is recreated
     let viewport_state_info = vk::PipelineViewportStateCreateInfo::default()
-        .viewports(viewports)
-        .scissors(scissors);
+        .viewport_count(1)
+        .scissor_count(1);
+    let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state_info =
+        vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
 
     let rasterization_state = vk::PipelineRasterizationStateCreateInfo::default()
         .depth_clamp_enable(false)
@@ -155,7 +148,16 @@ pub fn create_pipeline(
         .descriptor_count(1)
         .stage_flags(vk::ShaderStageFlags::FRAGMENT);
 
-    let bindings = &[ubo_binding];
+    // The light clusters lighting the fragments, see `LightClusterBuffers`
+    let light_bindings = [2, 3, 4].map(|binding| {
+        vk::DescriptorSetLayoutBinding::default()
+            .binding(binding)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+    });
+
+    let bindings = &[ubo_binding, light_bindings[0], light_bindings[1], light_bindings[2]];
     let descriptor_set_layout_create_info =
         vk::DescriptorSetLayoutCreateInfo::default().bindings(bindings);
 
@@ -163,9 +165,13 @@ pub fn create_pipeline(
         unsafe { device.create_descriptor_set_layout(&descriptor_set_layout_create_info, None) }?;
 
     let descriptor_set_layouts = &[descriptor_set_layout];
+    let push_constant_ranges = &[vk::PushConstantRange::default()
+        .stage_flags(vk::ShaderStageFlags::VERTEX)
+        .offset(0)
+        .size(size_of::<MeshPushConstants>() as u32)];
     let layout_create_info = vk::PipelineLayoutCreateInfo::default()
         .set_layouts(descriptor_set_layouts)
-        .push_constant_ranges(&[]);
+        .push_constant_ranges(push_constant_ranges);
 
     let pipeline_layout = unsafe { device.create_pipeline_layout(&layout_create_info, None) }?;
 
@@ -186,8 +192,12 @@ pub fn create_pipeline(
         .multisample_state(&multisample_state)
         .depth_stencil_state(&depth_stencil_state)
         .color_blend_state(&color_blend_state)
-        .layout(pipeline_layout)
-        .push_next(&mut rendering_info);
+        .dynamic_state(&dynamic_state_info)
+        .layout(pipeline_layout);
+    let info = match &render_pass {
+        Some(render_pass) => info.render_pass(render_pass.render_pass).subpass(0),
+        None => info.push_next(&mut rendering_info),
+    };
 
     let pipelines =
         unsafe { device.create_graphics_pipelines(vk::PipelineCache::null(), &[info], None) }
@@ -206,6 +216,7 @@ pub fn create_pipeline(
     };
 
     commands.insert_resource(pipeline);
+    commands.send_event(InitializationProgress::PipelineCreated);
 
     Ok(())
 }
@@ -220,7 +231,7 @@ fn create_shader_module(device: &Device, code: &[u8]) -> Result<vk::ShaderModule
     unsafe { device.create_shader_module(&create_info, None) }
 }
 
-fn read_spv<R: io::Read + io::Seek>(x: &mut R) -> io::Result<Vec<u32>> {
+pub(crate) fn read_spv<R: io::Read + io::Seek>(x: &mut R) -> io::Result<Vec<u32>> {
     let size = x.seek(io::SeekFrom::End(0))?;
     x.rewind()?;
     if !size.is_multiple_of(4) {
@@ -262,9 +273,15 @@ fn read_spv<R: io::Read + io::Seek>(x: &mut R) -> io::Result<Vec<u32>> {
 
 pub fn destroy_pipeline(
     device: Res<Device>,
-    pipeline: Res<Pipeline>,
+    pipeline: Option<Res<Pipeline>>,
     mut commands: Commands,
 ) -> Result<(), vk::Result> {
+    let Some(pipeline) = pipeline else {
+        return Ok(());
+    };
+
+    debug!(target: log_targets::PIPELINE, "Destroying graphics pipeline");
+
     unsafe {
         device.destroy_pipeline(pipeline.pipeline, None);
         device.destroy_descriptor_set_layout(pipeline.descriptor_set_layout, None);
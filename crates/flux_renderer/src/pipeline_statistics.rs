@@ -0,0 +1,214 @@
+use crate::capabilities::RendererCapabilities;
+use crate::device::Device;
+use crate::log_targets;
+use crate::stats::RenderStats;
+use crate::swapchain::Swapchain;
+use ash::vk;
+use flux_ecs::commands::Commands;
+use flux_ecs::resource::{Res, Resource};
+use log::{debug, info};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The counters queried around the main pass, the results are returned in this order.
+const STATISTICS: vk::QueryPipelineStatisticFlags = vk::QueryPipelineStatisticFlags::from_raw(
+    vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES.as_raw()
+        | vk::QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS.as_raw()
+        | vk::QueryPipelineStatisticFlags::CLIPPING_PRIMITIVES.as_raw()
+        | vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS.as_raw(),
+);
+
+/// Enables pipeline statistics queries around the main pass if the device supports them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineStatisticsSettings {
+    pub enabled: bool,
+}
+
+impl Resource for PipelineStatisticsSettings {}
+
+/// The work the GPU did for the main pass of a frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineStatistics {
+    pub input_vertices: u64,
+    pub vertex_invocations: u64,
+    /// The primitives that reached the clipping stage.
+    pub clipped_primitives: u64,
+    pub fragment_invocations: u64,
+}
+
+impl PipelineStatistics {
+    fn from_results(results: [u64; 4]) -> Self {
+        Self {
+            input_vertices: results[0],
+            vertex_invocations: results[1],
+            clipped_primitives: results[2],
+            fragment_invocations: results[3],
+        }
+    }
+
+    /// The fragment shader invocations per vertex shader invocation, a high ratio hints at a
+    /// fragment bound frame and a low one at a vertex bound frame.
+    pub fn fragments_per_vertex(&self) -> f64 {
+        if self.vertex_invocations == 0 {
+            return 0.0;
+        }
+        self.fragment_invocations as f64 / self.vertex_invocations as f64
+    }
+}
+
+/// A pipeline statistics query for every swapchain image.
+///
+/// The command buffer of an image wraps its main pass in [`PipelineStatisticsQueries::begin`]
+/// and [`PipelineStatisticsQueries::end`]. Once the fence of the frame is signaled the frame
+/// driver calls [`PipelineStatisticsQueries::frame_completed`], and
+/// [`collect_pipeline_statistics`] resolves the query into the [`RenderStats`] a frame later.
+pub struct PipelineStatisticsQueries {
+    pool: vk::QueryPool,
+    completed: Vec<AtomicBool>,
+}
+
+impl Resource for PipelineStatisticsQueries {}
+
+impl PipelineStatisticsQueries {
+    /// Resets and begins the query of `frame`.
+    ///
+    /// # Safety
+    /// The command buffer must be recording outside of a rendering pass.
+    pub unsafe fn begin(&self, device: &Device, command_buffer: vk::CommandBuffer, frame: usize) {
+        unsafe {
+            device.cmd_reset_query_pool(command_buffer, self.pool, frame as u32, 1);
+            device.cmd_begin_query(
+                command_buffer,
+                self.pool,
+                frame as u32,
+                vk::QueryControlFlags::empty(),
+            );
+        }
+    }
+
+    /// # Safety
+    /// The query of `frame` must have been begun on the same command buffer.
+    pub unsafe fn end(&self, device: &Device, command_buffer: vk::CommandBuffer, frame: usize) {
+        unsafe { device.cmd_end_query(command_buffer, self.pool, frame as u32) };
+    }
+
+    /// Marks the query of `frame` as ready for readback, call it once its fence is signaled.
+    pub fn frame_completed(&self, frame: usize) {
+        self.completed[frame].store(true, Ordering::Release);
+    }
+
+    /// Reads the statistics of `frame` without waiting, `None` if they are not available yet.
+    pub fn read_results(
+        &self,
+        device: &Device,
+        frame: usize,
+    ) -> Result<Option<PipelineStatistics>, vk::Result> {
+        let mut results = [[0u64; 4]];
+        let result = unsafe {
+            device.get_query_pool_results(
+                self.pool,
+                frame as u32,
+                &mut results,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        };
+
+        match result {
+            Ok(()) => Ok(Some(PipelineStatistics::from_results(results[0]))),
+            Err(vk::Result::NOT_READY) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+pub fn create_pipeline_statistics_queries(
+    device: Res<Device>,
+    settings: Option<Res<PipelineStatisticsSettings>>,
+    capabilities: Option<Res<RendererCapabilities>>,
+    swapchain: Option<Res<Swapchain>>,
+    mut commands: Commands,
+) -> Result<(), vk::Result> {
+    let (Some(settings), Some(swapchain)) = (settings, swapchain) else {
+        return Ok(());
+    };
+    if !settings.enabled {
+        return Ok(());
+    }
+    if !capabilities.is_some_and(|capabilities| capabilities.pipeline_statistics) {
+        info!(
+            target: log_targets::RESOURCES,
+            "Pipeline statistics queries are not supported by the device"
+        );
+        return Ok(());
+    }
+
+    let frames = swapchain.images.len();
+    debug!(
+        target: log_targets::RESOURCES,
+        "Creating pipeline statistics queries for {frames} frames"
+    );
+
+    let info = vk::QueryPoolCreateInfo::default()
+        .query_type(vk::QueryType::PIPELINE_STATISTICS)
+        .query_count(frames as u32)
+        .pipeline_statistics(STATISTICS);
+    let pool = unsafe { device.create_query_pool(&info, None) }?;
+
+    commands.insert_resource(PipelineStatisticsQueries {
+        pool,
+        completed: (0..frames).map(|_| AtomicBool::new(false)).collect(),
+    });
+
+    Ok(())
+}
+
+/// Records the statistics of completed frames in the [`RenderStats`].
+pub fn collect_pipeline_statistics(
+    device: Res<Device>,
+    queries: Option<Res<PipelineStatisticsQueries>>,
+    stats: Res<RenderStats>,
+) -> Result<(), vk::Result> {
+    let Some(queries) = queries else {
+        return Ok(());
+    };
+
+    for (frame, completed) in queries.completed.iter().enumerate() {
+        if !completed.load(Ordering::Acquire) {
+            continue;
+        }
+
+        if let Some(statistics) = queries.read_results(&device, frame)? {
+            stats.record_pipeline_statistics(statistics);
+            completed.store(false, Ordering::Release);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn destroy_pipeline_statistics_queries(
+    device: Res<Device>,
+    queries: Option<Res<PipelineStatisticsQueries>>,
+    mut commands: Commands,
+) {
+    let Some(queries) = queries else {
+        return;
+    };
+
+    debug!(target: log_targets::RESOURCES, "Destroying pipeline statistics queries");
+    unsafe { device.destroy_query_pool(queries.pool, None) };
+    commands.remove_resource::<PipelineStatisticsQueries>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_are_mapped_in_flag_order() {
+        let statistics = PipelineStatistics::from_results([300, 300, 100, 1200]);
+        assert_eq!(statistics.vertex_invocations, 300);
+        assert_eq!(statistics.fragment_invocations, 1200);
+        assert_eq!(statistics.fragments_per_vertex(), 4.0);
+        assert_eq!(STATISTICS.as_raw().count_ones(), 4);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::capabilities::RendererCapabilities;
+use crate::device::Device;
+use crate::instance::VulkanInstance;
+use crate::log_targets;
+use crate::stats::RenderStats;
+use crate::swapchain::Swapchain;
+use ash::{google, vk};
+use flux_ecs::resource::{Res, Resource};
+use log::{trace, warn};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Tags presented images with ids and reads back when they actually reached the display with
+/// `VK_GOOGLE_display_timing`, recording the frame pacing in the [`RenderStats`].
+///
+/// Does nothing if the device does not support the extension, see
+/// [`RendererCapabilities::display_timing`].
+#[derive(Debug, Default)]
+pub struct PresentTiming {
+    state: Mutex<TimingState>,
+}
+
+#[derive(Debug, Default)]
+struct TimingState {
+    next_present_id: u32,
+    /// The id and actual present time of the last image that reached the display.
+    last_present: Option<(u32, u64)>,
+}
+
+/// The pacing of the images reported by one readback of the past presentation timings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PresentPacing {
+    /// The refresh cycles in which the previous image stayed on the display because no new one
+    /// was ready.
+    pub missed_vsyncs: u64,
+    /// How long before its deadline the last image was ready, the latency a frame limiter could
+    /// remove by starting the frame later.
+    pub present_margin: Duration,
+    /// The time between the last two images reaching the display.
+    pub present_interval: Duration,
+}
+
+impl Resource for PresentTiming {}
+
+impl PresentTiming {
+    /// The id to present the next image with, ids start at one and wrap around.
+    pub fn next_present_id(&self) -> u32 {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.next_present_id = state.next_present_id.wrapping_add(1).max(1);
+        state.next_present_id
+    }
+
+    /// Derives the pacing from the timings of the images presented since the last call, in
+    /// present order. Intervals are only measured between consecutive ids, an image that never
+    /// reached the display is not counted as missed vsyncs.
+    pub fn record(
+        &self,
+        timings: &[vk::PastPresentationTimingGOOGLE],
+        refresh_duration: u64,
+    ) -> Option<PresentPacing> {
+        let last = timings.last()?;
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut pacing = PresentPacing {
+            present_margin: Duration::from_nanos(last.present_margin),
+            ..Default::default()
+        };
+        for timing in timings {
+            if let Some((id, actual_present_time)) = state.last_present
+                && timing.present_id == id.wrapping_add(1).max(1)
+            {
+                let interval = timing
+                    .actual_present_time
+                    .saturating_sub(actual_present_time);
+                pacing.missed_vsyncs += missed_vsyncs(interval, refresh_duration);
+                pacing.present_interval = Duration::from_nanos(interval);
+            }
+            state.last_present = Some((timing.present_id, timing.actual_present_time));
+        }
+
+        Some(pacing)
+    }
+}
+
+/// The refresh cycles skipped between two images reaching the display `interval` nanoseconds
+/// apart, with half a cycle of tolerance for jitter.
+fn missed_vsyncs(interval: u64, refresh_duration: u64) -> u64 {
+    if refresh_duration == 0 {
+        return 0;
+    }
+    ((interval + refresh_duration / 2) / refresh_duration).saturating_sub(1)
+}
+
+/// Reads back the timings of the presented images and records the pacing in the
+/// [`RenderStats`].
+pub fn collect_present_timing(
+    instance: Res<VulkanInstance>,
+    device: Res<Device>,
+    capabilities: Option<Res<RendererCapabilities>>,
+    swapchain: Option<Res<Swapchain>>,
+    timing: Res<PresentTiming>,
+    stats: Res<RenderStats>,
+) {
+    let Some(swapchain) = swapchain.filter(|swapchain| !swapchain.is_headless()) else {
+        return;
+    };
+    if !capabilities.is_some_and(|capabilities| capabilities.display_timing) {
+        return;
+    }
+
+    let loader = google::display_timing::Device::new(&instance, &device);
+    let result = unsafe {
+        loader
+            .get_refresh_cycle_duration(swapchain.swapchain)
+            .and_then(|refresh| {
+                let timings = loader.get_past_presentation_timing(swapchain.swapchain)?;
+                Ok((refresh.refresh_duration, timings))
+            })
+    };
+    let (refresh_duration, timings) = match result {
+        Ok(result) => result,
+        Err(err) => {
+            warn!(target: log_targets::SWAPCHAIN, "Could not read the presentation timing: {err}");
+            return;
+        }
+    };
+
+    if let Some(pacing) = timing.record(&timings, refresh_duration) {
+        trace!(target: log_targets::SWAPCHAIN, "Present pacing {pacing:?}");
+        stats.record_present_pacing(pacing);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFRESH: u64 = 16_666_667;
+
+    fn presented(present_id: u32, actual_present_time: u64) -> vk::PastPresentationTimingGOOGLE {
+        vk::PastPresentationTimingGOOGLE {
+            present_id,
+            actual_present_time,
+            earliest_present_time: actual_present_time,
+            present_margin: 2_000_000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn late_images_count_the_refresh_cycles_they_missed() {
+        let timing = PresentTiming::default();
+        assert_eq!(timing.next_present_id(), 1);
+        assert_eq!(timing.record(&[], REFRESH), None);
+
+        let pacing = timing
+            .record(
+                &[
+                    presented(1, 0),
+                    presented(2, REFRESH + 300_000),
+                    // Shown three refreshes after the previous one
+                    presented(3, 4 * REFRESH + 300_000),
+                ],
+                REFRESH,
+            )
+            .unwrap();
+        assert_eq!(pacing.missed_vsyncs, 2);
+        assert_eq!(pacing.present_interval, Duration::from_nanos(3 * REFRESH));
+        assert_eq!(pacing.present_margin, Duration::from_millis(2));
+
+        // Image 4 never reached the display
+        let pacing = timing
+            .record(&[presented(5, 10 * REFRESH)], REFRESH)
+            .unwrap();
+        assert_eq!(pacing.missed_vsyncs, 0);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::device::{Device, PhysicalDevice};
+use crate::frame::FrameSlots;
+use crate::instance::{SurfaceProviderResource, VulkanInstance};
+use crate::log_targets;
+use crate::pipeline::Pipeline;
+use crate::surface::VulkanSurface;
+use crate::swapchain::Swapchain;
+use crate::window::Headless;
+use flux_ecs::commands::Commands;
+use flux_ecs::resource::Res;
+use log::{error, info};
+use std::fmt::{Display, Formatter};
+
+/// A stage of the renderer initialization, in the order the stages run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InitializationStage {
+    Window,
+    Instance,
+    Surface,
+    DeviceSelection,
+    Device,
+    Swapchain,
+    Pipeline,
+    FrameSlots,
+}
+
+impl Display for InitializationStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            InitializationStage::Window => "window creation",
+            InitializationStage::Instance => "instance creation",
+            InitializationStage::Surface => "surface creation",
+            InitializationStage::DeviceSelection => "device selection",
+            InitializationStage::Device => "device creation",
+            InitializationStage::Swapchain => "swapchain creation",
+            InitializationStage::Pipeline => "pipeline creation",
+            InitializationStage::FrameSlots => "frame slot creation",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Sent as an [`Events<InitializationProgress>`](flux_ecs::event::Events) event by the renderer
+/// initialization systems, e.g. to drive a splash screen or to report which stage failed on a
+/// user machine.
+///
+/// Initialization ends with either [`InitializationProgress::Ready`] or
+/// [`InitializationProgress::Failed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitializationProgress {
+    /// Not sent when the application provides its own surface provider.
+    WindowCreated,
+    InstanceCreated,
+    /// Not sent when there is no native window and the renderer runs headless.
+    SurfaceCreated,
+    DeviceSelected {
+        name: String,
+    },
+    DeviceCreated,
+    SwapchainReady {
+        width: u32,
+        height: u32,
+        image_count: usize,
+    },
+    PipelineCreated,
+    /// Every stage completed, `headless` if frames are not presented to a swapchain.
+    Ready {
+        headless: bool,
+    },
+    Failed {
+        stage: InitializationStage,
+    },
+}
+
+/// The renderer resources that exist after the initialization systems ran.
+#[derive(Debug, Default, Clone, Copy)]
+struct CreatedResources {
+    surface_provider: bool,
+    /// Whether [`Headless`] rendering was requested, it needs no surface provider.
+    headless: bool,
+    /// Whether the surface provider has a native window, renderers without one run headless.
+    native_window: bool,
+    instance: bool,
+    physical_device: bool,
+    device: bool,
+    surface: bool,
+    swapchain: bool,
+    pipeline: bool,
+    frame_slots: bool,
+}
+
+impl CreatedResources {
+    /// The first stage that did not create its resource. The surface and everything presenting
+    /// to it are skipped when running headless.
+    fn failed_stage(&self) -> Option<InitializationStage> {
+        let stages = [
+            (
+                self.surface_provider || self.headless,
+                InitializationStage::Window,
+            ),
+            (self.instance, InitializationStage::Instance),
+            (
+                self.surface || !self.native_window,
+                InitializationStage::Surface,
+            ),
+            (self.physical_device, InitializationStage::DeviceSelection),
+            (self.device, InitializationStage::Device),
+            (
+                self.swapchain || !self.surface,
+                InitializationStage::Swapchain,
+            ),
+            (
+                self.pipeline || !self.swapchain,
+                InitializationStage::Pipeline,
+            ),
+            (
+                self.frame_slots || !self.swapchain,
+                InitializationStage::FrameSlots,
+            ),
+        ];
+
+        stages
+            .into_iter()
+            .find(|(created, _)| !created)
+            .map(|(_, stage)| stage)
+    }
+}
+
+type RendererResources<'w> = (
+    (
+        Option<Res<'w, SurfaceProviderResource>>,
+        Option<Res<'w, Headless>>,
+    ),
+    Option<Res<'w, VulkanInstance>>,
+    Option<Res<'w, PhysicalDevice>>,
+    Option<Res<'w, Device>>,
+    Option<Res<'w, VulkanSurface>>,
+    Option<Res<'w, Swapchain>>,
+    Option<Res<'w, Pipeline>>,
+    Option<Res<'w, FrameSlots>>,
+);
+
+/// Runs last in the initialization and reports whether the renderer is ready, or the first
+/// stage that failed.
+pub fn finish_initialization(resources: RendererResources, mut commands: Commands) {
+    let (
+        (surface_provider, headless),
+        instance,
+        physical_device,
+        device,
+        surface,
+        swapchain,
+        pipeline,
+        slots,
+    ) = resources;
+    let presenting = swapchain
+        .as_ref()
+        .is_some_and(|swapchain| !swapchain.is_headless());
+    let created = CreatedResources {
+        headless: headless.is_some(),
+        native_window: surface_provider.as_ref().is_some_and(|provider| {
+            provider.get_display_handle().is_some() && provider.get_window_handle().is_some()
+        }),
+        surface_provider: surface_provider.is_some(),
+        instance: instance.is_some(),
+        physical_device: physical_device.is_some(),
+        device: device.is_some(),
+        surface: surface.is_some(),
+        swapchain: swapchain.is_some(),
+        pipeline: pipeline.is_some(),
+        frame_slots: slots.is_some(),
+    };
+
+    match created.failed_stage() {
+        Some(stage) => {
+            error!(target: log_targets::INSTANCE, "Renderer initialization failed at {stage}");
+            commands.send_event(InitializationProgress::Failed { stage });
+        }
+        None => {
+            let headless = !presenting;
+            info!(target: log_targets::INSTANCE, "Renderer initialized (headless: {headless})");
+            commands.send_event(InitializationProgress::Ready { headless });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_first_missing_stage_failed() {
+        let mut created = CreatedResources {
+            surface_provider: true,
+            instance: true,
+            physical_device: true,
+            ..Default::default()
+        };
+        assert_eq!(created.failed_stage(), Some(InitializationStage::Device));
+
+        // Headless renderers have no surface and nothing presenting to it
+        created.device = true;
+        assert_eq!(created.failed_stage(), None);
+
+        created.native_window = true;
+        assert_eq!(created.failed_stage(), Some(InitializationStage::Surface));
+        created.surface = true;
+        assert_eq!(created.failed_stage(), Some(InitializationStage::Swapchain));
+        created.swapchain = true;
+        created.frame_slots = true;
+        assert_eq!(created.failed_stage(), Some(InitializationStage::Pipeline));
+        assert!(InitializationStage::Pipeline > InitializationStage::Swapchain);
+    }
+
+    #[test]
+    fn headless_rendering_needs_no_surface_provider() {
+        let mut created = CreatedResources {
+            headless: true,
+            instance: true,
+            physical_device: true,
+            device: true,
+            swapchain: true,
+            ..Default::default()
+        };
+        assert_eq!(created.failed_stage(), Some(InitializationStage::Pipeline));
+
+        created.pipeline = true;
+        created.frame_slots = true;
+        assert_eq!(created.failed_stage(), None);
+
+        created.headless = false;
+        assert_eq!(created.failed_stage(), Some(InitializationStage::Window));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::config::GraphicsSettings;
+use crate::log_targets;
+use crate::recreate::SwapchainRecreation;
+use flux_ecs::commands::Commands;
+use flux_ecs::resource::{Res, Resource};
+use log::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+/// Rendering quality options that can be switched at runtime, e.g. from a settings menu.
+///
+/// Insert a new value to switch, [`apply_quality_settings`] detects the change and sends a
+/// [`QualitySettingsChanged`] event telling each subsystem what to rebuild.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualitySettings {
+    /// Copied into [`GraphicsSettings::render_scale`], values above `1.0` supersample the frame.
+    pub render_scale: f32,
+}
+
+impl Resource for QualitySettings {}
+
+impl Default for QualitySettings {
+    fn default() -> Self {
+        Self::preset(QualityPreset::High)
+    }
+}
+
+impl QualitySettings {
+    pub fn preset(preset: QualityPreset) -> Self {
+        let render_scale = match preset {
+            QualityPreset::Low => 0.5,
+            QualityPreset::Medium => 0.75,
+            QualityPreset::High => 1.0,
+            QualityPreset::Ultra => 1.5,
+        };
+        Self { render_scale }
+    }
+
+    /// What has to be rebuilt to switch from `previous` to these settings.
+    pub fn changes_from(&self, previous: &QualitySettings) -> QualityChanges {
+        QualityChanges {
+            render_targets: self.render_scale != previous.render_scale,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QualityChanges {
+    /// The color and depth targets changed size.
+    pub render_targets: bool,
+}
+
+impl QualityChanges {
+    pub fn any(&self) -> bool {
+        self.render_targets
+    }
+}
+
+/// Sent as an [`Events<QualitySettingsChanged>`](flux_ecs::event::Events) event when the
+/// [`QualitySettings`] resource was replaced with different settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualitySettingsChanged {
+    pub previous: QualitySettings,
+    pub current: QualitySettings,
+    pub changes: QualityChanges,
+}
+
+/// The quality settings the renderer currently uses.
+#[derive(Debug, Clone, Copy)]
+pub struct AppliedQuality(pub QualitySettings);
+
+impl Resource for AppliedQuality {}
+
+/// Compares [`QualitySettings`] with the settings applied last and propagates changes. Changed
+/// render targets are rebuilt by requesting a [`SwapchainRecreation`].
+pub fn apply_quality_settings(
+    settings: Option<Res<QualitySettings>>,
+    applied: Option<Res<AppliedQuality>>,
+    (graphics_settings, recreation): (
+        Option<Res<GraphicsSettings>>,
+        Option<Res<SwapchainRecreation>>,
+    ),
+    mut commands: Commands,
+) {
+    let Some(settings) = settings else {
+        return;
+    };
+    let Some(applied) = applied else {
+        commands.insert_resource(AppliedQuality(*settings));
+        return;
+    };
+
+    let changes = settings.changes_from(&applied.0);
+    if !changes.any() {
+        return;
+    }
+
+    info!(target: log_targets::CONFIG, "Applying quality settings {changes:?}");
+
+    if let Some(graphics_settings) = graphics_settings
+        && graphics_settings.render_scale != settings.render_scale
+    {
+        commands.insert_resource(GraphicsSettings {
+            render_scale: settings.render_scale,
+            ..(*graphics_settings).clone()
+        });
+    }
+
+    if changes.render_targets
+        && let Some(recreation) = recreation
+    {
+        recreation.request();
+    }
+
+    commands.send_event(QualitySettingsChanged {
+        previous: applied.0,
+        current: *settings,
+        changes,
+    });
+    commands.insert_resource(AppliedQuality(*settings));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flux_ecs::world::World;
+
+    #[test]
+    fn changes_name_the_affected_subsystems() {
+        let high = QualitySettings::preset(QualityPreset::High);
+        assert!(!high.changes_from(&high).any());
+
+        let changes = QualitySettings::preset(QualityPreset::Low).changes_from(&high);
+        assert_eq!(
+            changes,
+            QualityChanges {
+                render_targets: true
+            }
+        );
+    }
+
+    #[test]
+    fn changed_render_targets_request_a_recreation() {
+        let requested = |world: &World| {
+            world
+                .get_resource::<SwapchainRecreation>()
+                .unwrap()
+                .is_requested()
+        };
+        let mut world = World::new();
+        world.add_resource(GraphicsSettings::default());
+        world.add_resource(SwapchainRecreation::default());
+        world.add_resource(QualitySettings::preset(QualityPreset::High));
+        world.run_system_once(apply_quality_settings).unwrap();
+        assert!(!requested(&world));
+
+        world.add_resource(QualitySettings::preset(QualityPreset::Low));
+        world.run_system_once(apply_quality_settings).unwrap();
+        assert!(requested(&world));
+        let graphics_settings = world.get_resource::<GraphicsSettings>().unwrap();
+        assert_eq!(graphics_settings.render_scale, 0.5);
+        assert_eq!(
+            world.get_resource::<AppliedQuality>().unwrap().0,
+            QualitySettings::preset(QualityPreset::Low)
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use crate::command_pool::CommandPools;
+use crate::device::{Device, PhysicalDevice};
+use crate::instance::VulkanInstance;
+use crate::log_targets;
+use ash::vk;
+use flux_ecs::commands::Commands;
+use flux_ecs::resource::{Res, Resource};
+use log::debug;
+
+/// Raw access to the Vulkan objects of the renderer for integrating external Vulkan code.
+///
+/// The resource exists from the end of renderer initialization until the start of the `Destroy`
+/// schedule. The handles are owned by the renderer: external code may create and destroy its own
+/// objects with them but must never destroy or reconfigure the engine objects.
+pub struct RawVulkan {
+    pub entry: ash::Entry,
+    pub instance: ash::Instance,
+    pub physical_device: vk::PhysicalDevice,
+    pub device: ash::Device,
+    pub graphics_queue: vk::Queue,
+    pub graphics_queue_index: u32,
+    pub present_queue: vk::Queue,
+    pub present_queue_index: u32,
+    pub graphics_command_pool: vk::CommandPool,
+}
+
+impl Resource for RawVulkan {}
+
+type RecordingHook = Box<dyn Fn(&RawVulkan, vk::CommandBuffer) + Send + Sync>;
+
+/// Callbacks that record external commands into the renderer's command buffers.
+///
+/// The hooks run while the frame's command buffer is recording inside the main rendering pass,
+/// after the engine draws, in the order they were added.
+#[derive(Default)]
+pub struct RawVulkanHooks {
+    recording_hooks: Vec<RecordingHook>,
+}
+
+impl Resource for RawVulkanHooks {}
+
+impl RawVulkanHooks {
+    pub fn add_recording_hook(
+        &mut self,
+        hook: impl Fn(&RawVulkan, vk::CommandBuffer) + Send + Sync + 'static,
+    ) {
+        self.recording_hooks.push(Box::new(hook));
+    }
+
+    pub(crate) fn record(&self, raw_vulkan: &RawVulkan, command_buffer: vk::CommandBuffer) {
+        for hook in &self.recording_hooks {
+            hook(raw_vulkan, command_buffer);
+        }
+    }
+}
+
+pub fn create_raw_vulkan(
+    instance: Res<VulkanInstance>,
+    physical_device: Res<PhysicalDevice>,
+    device: Res<Device>,
+    command_pools: Res<CommandPools>,
+    mut commands: Commands,
+) {
+    debug!(target: log_targets::INSTANCE, "Exposing raw vulkan handles");
+
+    commands.insert_resource(RawVulkan {
+        entry: instance.entry.clone(),
+        instance: instance.instance.clone(),
+        physical_device: **physical_device,
+        device: device.device.clone(),
+        graphics_queue: device.graphics_queue,
+        graphics_queue_index: device.graphics_queue_index,
+        present_queue: device.present_queue,
+        present_queue_index: device.present_queue_index,
+        graphics_command_pool: command_pools.graphics,
+    });
+}
+
+pub fn destroy_raw_vulkan(mut commands: Commands) {
+    commands.remove_resource::<RawVulkan>();
+}
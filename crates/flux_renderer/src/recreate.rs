@@ -0,0 +1,247 @@
+use crate::allocator::GpuAllocator;
+use crate::config::GraphicsSettings;
+use crate::device::{Device, PhysicalDevice, query_swapchain_support};
+use crate::frame::FrameOutcome;
+use crate::instance::{SurfaceProviderResource, VulkanInstance};
+use crate::layout_tracker::ImageLayoutTracker;
+use crate::log_targets;
+use crate::surface::VulkanSurface;
+use crate::swapchain::{Swapchain, build_swapchain, destroy_swapchain_objects};
+use ash::khr::surface;
+use ash::vk;
+use flux_ecs::commands::{Command, CommandError, Commands};
+use flux_ecs::resource::{Res, Resource};
+use flux_ecs::schedule::ScheduleLabel;
+use flux_ecs::system::SystemError;
+use flux_ecs::world::World;
+use log::{debug, error, info, trace};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The schedules of the renderer besides the [`CoreSchedule`](flux_ecs::schedule::CoreSchedule)s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RendererSchedule {
+    /// Waits until the device is idle, destroys the swapchain and every resource derived from it
+    /// and creates them again. Run by [`recreate_swapchain`] once a recreation was requested.
+    RecreateSwapchain,
+    /// Waits until the device is idle and destroys the surface, the swapchain and every resource
+    /// derived from it. Run once the native window was lost.
+    ReleaseSurface,
+    /// Creates the surface, the swapchain and everything rendering to it for a new native window.
+    /// Run once the native window became available, the pipeline and the frame slots are only
+    /// created if the app started without a window.
+    AcquireSurface,
+}
+
+impl ScheduleLabel for RendererSchedule {}
+
+/// Requests recreating the swapchain before the next frame, e.g. because it no longer matches
+/// the surface or the window was resized.
+///
+/// The frame driver requests it when acquiring or presenting an image reports an outdated or
+/// suboptimal swapchain and skips frames until the swapchain was recreated. A request is kept
+/// while the window is minimized, since no swapchain can be created for an empty surface.
+#[derive(Debug, Default)]
+pub struct SwapchainRecreation {
+    requested: AtomicBool,
+}
+
+impl Resource for SwapchainRecreation {}
+
+impl SwapchainRecreation {
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Release);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Acquire)
+    }
+
+    /// Requests a recreation if the outcome of acquiring or presenting an image asks for one.
+    pub fn observe(&self, outcome: FrameOutcome) {
+        if outcome.needs_recreation() {
+            self.request();
+        }
+    }
+
+    /// Whether the swapchain should be recreated for a surface of `extent` now, clears the
+    /// request if so. Empty surfaces keep the request pending.
+    fn take_if_ready(&self, extent: vk::Extent2D) -> bool {
+        if extent.width == 0 || extent.height == 0 {
+            return false;
+        }
+        self.requested.swap(false, Ordering::AcqRel)
+    }
+
+    fn clear(&self) {
+        self.requested.store(false, Ordering::Release);
+    }
+}
+
+/// The window surface and the swapchain presenting to it.
+type SurfaceResources<'w> = (
+    Option<Res<'w, VulkanSurface>>,
+    Option<Res<'w, SurfaceProviderResource>>,
+    Option<Res<'w, Swapchain>>,
+);
+
+/// Runs the [`RendererSchedule::RecreateSwapchain`] schedule when a recreation was requested and
+/// the surface is not empty.
+pub fn recreate_swapchain(
+    (instance, physical_device): (Res<VulkanInstance>, Res<PhysicalDevice>),
+    (surface, surface_provider, swapchain): SurfaceResources,
+    recreation: Res<SwapchainRecreation>,
+    mut commands: Commands,
+) -> Result<(), vk::Result> {
+    if !recreation.is_requested() {
+        return Ok(());
+    }
+    // Headless targets are never outdated, a lost surface gets a new swapchain once it returns
+    let (Some(surface), Some(surface_provider), Some(_)) = (surface, surface_provider, swapchain)
+    else {
+        recreation.clear();
+        return Ok(());
+    };
+
+    let surface_loader = surface::Instance::new(&instance.entry, &instance);
+    let capabilities = unsafe {
+        surface_loader.get_physical_device_surface_capabilities(**physical_device, **surface)?
+    };
+    let extent = if capabilities.current_extent.width != u32::MAX {
+        capabilities.current_extent
+    } else {
+        let (width, height) = surface_provider.get_extent();
+        vk::Extent2D { width, height }
+    };
+
+    if !recreation.take_if_ready(extent) {
+        trace!(target: log_targets::SWAPCHAIN, "The surface is empty, postponing the recreation");
+        return Ok(());
+    }
+
+    info!(
+        target: log_targets::SWAPCHAIN,
+        "Recreating the swapchain ({}x{})",
+        extent.width,
+        extent.height
+    );
+    commands.push(RunSchedule(RendererSchedule::RecreateSwapchain));
+
+    Ok(())
+}
+
+/// Runs a renderer schedule once the commands are applied, see [`run_renderer_schedule`].
+pub(crate) struct RunSchedule(pub RendererSchedule);
+
+impl Command for RunSchedule {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        run_renderer_schedule(world, self.0);
+        Ok(())
+    }
+}
+
+/// Runs a renderer schedule and logs its errors, systems skipped because an optional resource
+/// does not exist are expected.
+pub(crate) fn run_renderer_schedule(world: &mut World, schedule: RendererSchedule) {
+    let Err(schedule_error) = world.run_system(&schedule) else {
+        return;
+    };
+    for system_error in &schedule_error.errors {
+        match system_error {
+            SystemError::MissingResource { .. } => {
+                debug!(target: log_targets::SWAPCHAIN, "{system_error}")
+            }
+            SystemError::Failed { .. } => error!(target: log_targets::SWAPCHAIN, "{system_error}"),
+        }
+    }
+}
+
+/// Waits until the GPU finished all submitted work, the first system of
+/// [`RendererSchedule::RecreateSwapchain`].
+pub fn wait_for_device_idle(device: Res<Device>) -> Result<(), vk::Result> {
+    unsafe { device.device_wait_idle() }
+}
+
+/// Replaces the swapchain with one matching the current surface. The old swapchain is retired
+/// into the new one and destroyed afterward, it is kept if the surface can not be queried.
+pub fn rebuild_swapchain(
+    (instance, physical_device, device): (Res<VulkanInstance>, Res<PhysicalDevice>, Res<Device>),
+    (allocator, layouts): (Res<GpuAllocator>, Res<ImageLayoutTracker>),
+    (surface, surface_provider, swapchain): SurfaceResources,
+    graphics_settings: Option<Res<GraphicsSettings>>,
+    mut commands: Commands,
+) -> Result<(), vk::Result> {
+    let (Some(surface), Some(surface_provider), Some(old)) = (surface, surface_provider, swapchain)
+    else {
+        return Ok(());
+    };
+
+    let support =
+        match query_swapchain_support(&instance.entry, &instance, **physical_device, **surface) {
+            Ok(support) => support,
+            Err(err) => {
+                error!(target: log_targets::SWAPCHAIN, "Could not query the surface: {err}");
+                return Ok(());
+            }
+        };
+
+    let swapchain = build_swapchain(
+        &instance,
+        &physical_device,
+        &device,
+        &allocator,
+        **surface,
+        &support,
+        surface_provider.get_extent(),
+        &graphics_settings.as_deref().cloned().unwrap_or_default(),
+        old.swapchain,
+    )?;
+    unsafe { destroy_swapchain_objects(&instance, &device, &allocator, &old, &layouts) };
+
+    swapchain.track_layouts(&layouts);
+    commands.insert_resource(swapchain);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTENT: vk::Extent2D = vk::Extent2D {
+        width: 800,
+        height: 600,
+    };
+
+    #[test]
+    fn outdated_outcomes_request_a_recreation() {
+        let recreation = SwapchainRecreation::default();
+        recreation.observe(FrameOutcome::Acquired { image_index: 0 });
+        recreation.observe(FrameOutcome::Skip);
+        assert!(!recreation.is_requested());
+        assert!(!recreation.take_if_ready(EXTENT));
+
+        recreation.observe(FrameOutcome::Suboptimal { image_index: 1 });
+        assert!(recreation.is_requested());
+
+        let recreation = SwapchainRecreation::default();
+        recreation.observe(FrameOutcome::RecreateSwapchain);
+        assert!(recreation.is_requested());
+    }
+
+    #[test]
+    fn minimized_surfaces_postpone_the_recreation() {
+        let recreation = SwapchainRecreation::default();
+        recreation.request();
+
+        let minimized = vk::Extent2D {
+            width: 0,
+            height: 0,
+        };
+        assert!(!recreation.take_if_ready(minimized));
+        assert!(recreation.is_requested());
+
+        assert!(recreation.take_if_ready(EXTENT));
+        assert!(!recreation.is_requested());
+        assert!(!recreation.take_if_ready(EXTENT));
+    }
+}
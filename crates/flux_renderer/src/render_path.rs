@@ -0,0 +1,324 @@
+use crate::capabilities::RendererCapabilities;
+use crate::depth_buffers::{DepthBuffers, get_depth_format};
+use crate::device::{Device, PhysicalDevice};
+use crate::instance::VulkanInstance;
+use crate::log_targets;
+use crate::swapchain::Swapchain;
+use ash::vk;
+use flux_ecs::commands::Commands;
+use flux_ecs::resource::{Res, ResMut, Resource};
+use log::{debug, info};
+
+/// How passes are begun, selected once the logical device is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderPath {
+    /// `vkCmdBeginRendering`, requires Vulkan 1.3 or `VK_KHR_dynamic_rendering`.
+    Dynamic,
+    /// A classic render pass with a framebuffer per swapchain image, for older drivers.
+    RenderPass,
+}
+
+impl RenderPath {
+    pub fn select(capabilities: &RendererCapabilities) -> Self {
+        if capabilities.dynamic_rendering {
+            RenderPath::Dynamic
+        } else {
+            RenderPath::RenderPass
+        }
+    }
+}
+
+/// The render pass and framebuffers of the [`RenderPath::RenderPass`] fallback.
+///
+/// The attachments are expected in their attachment layouts when the pass begins and are left
+/// in them, layout transitions stay with the [`ImageLayoutTracker`](crate::ImageLayoutTracker)
+/// like on the dynamic rendering path.
+pub struct ClassicRenderPass {
+    pub render_pass: vk::RenderPass,
+    /// One framebuffer per swapchain image, empty until [`create_framebuffers`] ran.
+    pub framebuffers: Vec<vk::Framebuffer>,
+}
+
+impl Resource for ClassicRenderPass {}
+
+/// Creates the render pass if the device does not support dynamic rendering. It is created
+/// before the pipelines, which are compiled against it, and kept when the swapchain is recreated.
+pub fn create_render_pass(
+    (instance, physical_device, device): (Res<VulkanInstance>, Res<PhysicalDevice>, Res<Device>),
+    capabilities: Res<RendererCapabilities>,
+    swapchain: Option<Res<Swapchain>>,
+    existing: Option<Res<ClassicRenderPass>>,
+    mut commands: Commands,
+) -> Result<(), vk::Result> {
+    let Some(swapchain) = swapchain else {
+        return Ok(());
+    };
+    if existing.is_some() || RenderPath::select(&capabilities) == RenderPath::Dynamic {
+        return Ok(());
+    }
+
+    info!(target: log_targets::PIPELINE, "Dynamic rendering is not supported, using render passes");
+
+    let depth_format = get_depth_format(&instance, &physical_device)
+        .ok_or(vk::Result::ERROR_FORMAT_NOT_SUPPORTED)?;
+
+    let attachment = |format, layout| {
+        vk::AttachmentDescription::default()
+            .format(format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(layout)
+            .final_layout(layout)
+    };
+    let attachments = [
+        attachment(
+            swapchain.format.format,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        ),
+        attachment(
+            depth_format,
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        ),
+    ];
+
+    let color_references = [vk::AttachmentReference::default()
+        .attachment(0)
+        .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
+    let depth_reference = vk::AttachmentReference::default()
+        .attachment(1)
+        .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+    let subpasses = [vk::SubpassDescription::default()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(&color_references)
+        .depth_stencil_attachment(&depth_reference)];
+
+    let info = vk::RenderPassCreateInfo::default()
+        .attachments(&attachments)
+        .subpasses(&subpasses);
+    let render_pass = unsafe { device.create_render_pass(&info, None)? };
+
+    commands.insert_resource(ClassicRenderPass {
+        render_pass,
+        framebuffers: Vec::new(),
+    });
+
+    Ok(())
+}
+
+/// Creates a framebuffer per swapchain image once the depth buffer exists.
+pub fn create_framebuffers(
+    device: Res<Device>,
+    render_pass: Option<Res<ClassicRenderPass>>,
+    swapchain: Option<Res<Swapchain>>,
+    depth_buffers: Option<Res<DepthBuffers>>,
+    mut commands: Commands,
+) -> Result<(), vk::Result> {
+    let (Some(render_pass), Some(swapchain), Some(depth_buffers)) =
+        (render_pass, swapchain, depth_buffers)
+    else {
+        return Ok(());
+    };
+
+    debug!(target: log_targets::RESOURCES, "Creating {} framebuffers", swapchain.images.len());
+
+    let framebuffers =
+        build_framebuffers(&device, render_pass.render_pass, &swapchain, &depth_buffers)?;
+
+    commands.insert_resource(ClassicRenderPass {
+        render_pass: render_pass.render_pass,
+        framebuffers,
+    });
+
+    Ok(())
+}
+
+/// Creates a framebuffer of `render_pass` for every image of a swapchain.
+pub(crate) fn build_framebuffers(
+    device: &Device,
+    render_pass: vk::RenderPass,
+    swapchain: &Swapchain,
+    depth_buffers: &DepthBuffers,
+) -> Result<Vec<vk::Framebuffer>, vk::Result> {
+    let mut framebuffers = Vec::with_capacity(swapchain.images.len());
+    for index in 0..swapchain.images.len() {
+        let (_, target_view) = swapchain.render_target(index);
+        let attachments = [target_view, depth_buffers.depth_image_view];
+        let info = vk::FramebufferCreateInfo::default()
+            .render_pass(render_pass)
+            .attachments(&attachments)
+            .width(swapchain.render_extent.width)
+            .height(swapchain.render_extent.height)
+            .layers(1);
+
+        match unsafe { device.create_framebuffer(&info, None) } {
+            Ok(framebuffer) => framebuffers.push(framebuffer),
+            Err(err) => {
+                unsafe { destroy_framebuffer_objects(device, &framebuffers) };
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(framebuffers)
+}
+
+/// # Safety
+/// The framebuffers must no longer be in use by the device.
+pub(crate) unsafe fn destroy_framebuffer_objects(
+    device: &Device,
+    framebuffers: &[vk::Framebuffer],
+) {
+    for &framebuffer in framebuffers {
+        unsafe { device.destroy_framebuffer(framebuffer, None) };
+    }
+}
+
+/// Destroys the framebuffers but keeps the render pass, the framebuffers of a recreated
+/// swapchain are created by [`create_framebuffers`].
+pub fn destroy_framebuffers(device: Res<Device>, render_pass: Option<ResMut<ClassicRenderPass>>) {
+    let Some(mut render_pass) = render_pass else {
+        return;
+    };
+
+    debug!(target: log_targets::RESOURCES, "Destroying framebuffers");
+
+    unsafe { destroy_framebuffer_objects(&device, &render_pass.framebuffers) };
+    render_pass.framebuffers.clear();
+}
+
+pub fn destroy_render_pass(
+    device: Res<Device>,
+    render_pass: Option<Res<ClassicRenderPass>>,
+    mut commands: Commands,
+) {
+    let Some(render_pass) = render_pass else {
+        return;
+    };
+
+    debug!(target: log_targets::PIPELINE, "Destroying render pass");
+
+    unsafe {
+        destroy_framebuffer_objects(&device, &render_pass.framebuffers);
+        device.destroy_render_pass(render_pass.render_pass, None);
+    }
+
+    commands.remove_resource::<ClassicRenderPass>();
+}
+
+/// The attachments a pass renders into.
+pub(crate) struct PassTargets {
+    pub image_index: usize,
+    pub color_view: vk::ImageView,
+    pub depth_view: vk::ImageView,
+    pub extent: vk::Extent2D,
+    pub clear_color: [f32; 4],
+}
+
+/// Begins and ends passes on either render path so the draw recording between them is shared.
+#[derive(Clone, Copy)]
+pub(crate) enum PassRecorder<'a> {
+    Dynamic,
+    RenderPass(&'a ClassicRenderPass),
+}
+
+impl<'a> PassRecorder<'a> {
+    pub fn new(render_pass: Option<&'a ClassicRenderPass>) -> Self {
+        render_pass.map_or(PassRecorder::Dynamic, PassRecorder::RenderPass)
+    }
+
+    /// Begins a pass that clears the color and depth attachments.
+    ///
+    /// # Safety
+    /// The command buffer must be recording outside of a pass, with the attachments in their
+    /// attachment layouts.
+    pub unsafe fn begin(
+        &self,
+        device: &Device,
+        command_buffer: vk::CommandBuffer,
+        targets: &PassTargets,
+    ) {
+        let render_area = vk::Rect2D::default()
+            .offset(vk::Offset2D::default())
+            .extent(targets.extent);
+
+        let color_clear_value = vk::ClearValue {
+            color: vk::ClearColorValue {
+                float32: targets.clear_color,
+            },
+        };
+        let depth_clear_value = vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue {
+                depth: 1.0,
+                stencil: 0,
+            },
+        };
+
+        match self {
+            PassRecorder::Dynamic => {
+                let color_attachment_info = vk::RenderingAttachmentInfo::default()
+                    .image_view(targets.color_view)
+                    .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                    .load_op(vk::AttachmentLoadOp::CLEAR)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .clear_value(color_clear_value);
+
+                let depth_attachment_info = vk::RenderingAttachmentInfo::default()
+                    .image_view(targets.depth_view)
+                    .image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                    .load_op(vk::AttachmentLoadOp::CLEAR)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .clear_value(depth_clear_value);
+
+                let color_attachments = &[color_attachment_info];
+                let rendering_info = vk::RenderingInfo::default()
+                    .render_area(render_area)
+                    .layer_count(1)
+                    .color_attachments(color_attachments)
+                    .depth_attachment(&depth_attachment_info);
+
+                unsafe { device.cmd_begin_rendering(command_buffer, &rendering_info) };
+            }
+            PassRecorder::RenderPass(render_pass) => {
+                let clear_values = [color_clear_value, depth_clear_value];
+                let info = vk::RenderPassBeginInfo::default()
+                    .render_pass(render_pass.render_pass)
+                    .framebuffer(render_pass.framebuffers[targets.image_index])
+                    .render_area(render_area)
+                    .clear_values(&clear_values);
+
+                unsafe {
+                    device.cmd_begin_render_pass(command_buffer, &info, vk::SubpassContents::INLINE)
+                };
+            }
+        }
+    }
+
+    /// # Safety
+    /// The command buffer must be recording inside a pass begun by [`PassRecorder::begin`].
+    pub unsafe fn end(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        match self {
+            PassRecorder::Dynamic => unsafe { device.cmd_end_rendering(command_buffer) },
+            PassRecorder::RenderPass(_) => unsafe { device.cmd_end_render_pass(command_buffer) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_pass_is_used_without_dynamic_rendering() {
+        let mut capabilities = RendererCapabilities {
+            dynamic_rendering: true,
+            ..RendererCapabilities::default()
+        };
+        assert_eq!(RenderPath::select(&capabilities), RenderPath::Dynamic);
+
+        capabilities.dynamic_rendering = false;
+        assert_eq!(RenderPath::select(&capabilities), RenderPath::RenderPass);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::mesh::Mesh;
+use flux_ecs::changes::WorldChange;
+use flux_ecs::entity::Entity;
+use flux_ecs::resource::{Res, ResMut, Resource};
+use std::any::type_name;
+use std::collections::HashSet;
+use std::sync::mpsc::Receiver;
+use std::sync::{Mutex, PoisonError};
+
+/// The entities that gained or lost a [`Mesh`] since the previous frame, collected once per
+/// frame so GPU-side bookkeeping can be updated incrementally instead of diffing full queries.
+///
+/// An entity whose mesh was removed and added again within the frame is listed in both, apply
+/// the removals first. Entities that gained and lost their mesh within the frame are not listed.
+#[derive(Debug, Default)]
+pub struct RenderableChanges {
+    added: Vec<Entity>,
+    removed: Vec<Entity>,
+}
+
+impl Resource for RenderableChanges {}
+
+impl RenderableChanges {
+    pub fn added(&self) -> &[Entity] {
+        &self.added
+    }
+
+    pub fn removed(&self) -> &[Entity] {
+        &self.removed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Follows the structural changes of the world to know which entities are renderable.
+pub(crate) struct RenderableTracker {
+    changes: Mutex<Receiver<WorldChange>>,
+    renderables: Mutex<HashSet<Entity>>,
+}
+
+impl Resource for RenderableTracker {}
+
+impl RenderableTracker {
+    /// Tracks the changes received from [`World::subscribe_changes`].
+    ///
+    /// [`World::subscribe_changes`]: flux_ecs::world::World::subscribe_changes
+    pub fn new(changes: Receiver<WorldChange>) -> Self {
+        Self {
+            changes: Mutex::new(changes),
+            renderables: Mutex::default(),
+        }
+    }
+
+    /// Replaces `changes` with everything received since the last call.
+    fn collect(&self, changes: &mut RenderableChanges) {
+        changes.added.clear();
+        changes.removed.clear();
+
+        let receiver = self.changes.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut renderables = self.renderables.lock().unwrap_or_else(PoisonError::into_inner);
+        for change in receiver.try_iter() {
+            match change {
+                WorldChange::ComponentAdded { entity, name, .. } if is_mesh(name) => {
+                    if renderables.insert(entity) {
+                        changes.added.push(entity);
+                    }
+                }
+                WorldChange::ComponentRemoved { entity, name, .. } if is_mesh(name) => {
+                    remove(&mut renderables, changes, entity);
+                }
+                WorldChange::EntityDespawned(entity) => {
+                    remove(&mut renderables, changes, entity);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn is_mesh(component: &str) -> bool {
+    component == type_name::<Mesh>()
+}
+
+fn remove(renderables: &mut HashSet<Entity>, changes: &mut RenderableChanges, entity: Entity) {
+    if !renderables.remove(&entity) {
+        return;
+    }
+    match changes.added.iter().position(|&added| added == entity) {
+        Some(index) => {
+            changes.added.swap_remove(index);
+        }
+        None => changes.removed.push(entity),
+    }
+}
+
+/// Runs first every frame and collects the [`RenderableChanges`] of the previous frame.
+pub fn collect_renderable_changes(
+    tracker: Res<RenderableTracker>,
+    mut changes: ResMut<RenderableChanges>,
+) {
+    tracker.collect(&mut changes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vertex_layout::VertexLayout;
+    use flux_ecs::component::Component;
+    use flux_ecs::world::World;
+
+    struct Light;
+
+    impl Component for Light {}
+
+    #[test]
+    fn changes_are_aggregated_per_frame() {
+        let mut world = World::new();
+        let tracker = RenderableTracker::new(world.subscribe_changes());
+        let mut changes = RenderableChanges::default();
+        let mesh = || Mesh::new(VertexLayout::interleaved(4, []));
+
+        let kept = world.spawn((mesh(),));
+        let removed = world.spawn((mesh(),));
+        world.spawn((Light,));
+        tracker.collect(&mut changes);
+        assert_eq!(changes.added(), [kept, removed]);
+        assert!(changes.removed().is_empty());
+
+        world.remove_component::<Mesh>(removed);
+        let short_lived = world.spawn((mesh(),));
+        world.despawn(short_lived);
+        world.despawn(kept);
+        tracker.collect(&mut changes);
+        assert!(changes.added().is_empty());
+        assert_eq!(changes.removed(), [removed, kept]);
+
+        tracker.collect(&mut changes);
+        assert!(changes.is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+use flux_ecs::resource::NonSendResource;
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+
+/// Temporary vectors for command recording, e.g. barrier and attachment arrays, that keep their
+/// capacity from frame to frame so recording stops allocating once the largest frame was seen.
+///
+/// The vectors live until the end of the frame at most, [`FrameScratch::end_frame`] runs after
+/// the image was presented.
+#[derive(Default)]
+pub struct FrameScratch {
+    /// The unused vectors per element type, each a `Vec<Vec<T>>`.
+    pools: RefCell<HashMap<TypeId, Box<dyn Any>>>,
+    in_use: Cell<usize>,
+    /// The vectors that had to grow during the current frame.
+    grown: Cell<usize>,
+}
+
+impl NonSendResource for FrameScratch {}
+
+impl FrameScratch {
+    /// An empty vector with the capacity of a previously returned one, it is returned to the
+    /// scratch when dropped.
+    pub fn vec<T: 'static>(&self) -> ScratchVec<'_, T> {
+        let vec = self
+            .pools
+            .borrow_mut()
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|pool| pool.downcast_mut::<Vec<Vec<T>>>())
+            .and_then(Vec::pop)
+            .unwrap_or_default();
+        self.in_use.set(self.in_use.get() + 1);
+
+        ScratchVec {
+            capacity: vec.capacity(),
+            vec,
+            scratch: self,
+        }
+    }
+
+    fn give_back<T: 'static>(&self, mut vec: Vec<T>, capacity: usize) {
+        if vec.capacity() > capacity {
+            self.grown.set(self.grown.get() + 1);
+        }
+        self.in_use.set(self.in_use.get() - 1);
+
+        vec.clear();
+        self.pools
+            .borrow_mut()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Vec::<Vec<T>>::new()))
+            .downcast_mut::<Vec<Vec<T>>>()
+            .expect("Scratch pool has the wrong element type")
+            .push(vec);
+    }
+
+    /// The vectors that had to allocate since the last [`FrameScratch::end_frame`], zero once the
+    /// scratch warmed up.
+    pub fn grown_this_frame(&self) -> usize {
+        self.grown.get()
+    }
+
+    /// Starts the next frame, returns how many vectors had to grow during the frame.
+    ///
+    /// # Panics
+    /// Panics in debug builds if a scratch vector outlived the frame.
+    pub fn end_frame(&self) -> usize {
+        debug_assert_eq!(
+            self.in_use.get(),
+            0,
+            "Scratch vectors must be dropped before the end of the frame"
+        );
+        self.grown.replace(0)
+    }
+}
+
+/// A vector borrowed from the [`FrameScratch`].
+pub struct ScratchVec<'a, T: 'static> {
+    vec: Vec<T>,
+    /// The capacity the vector was handed out with.
+    capacity: usize,
+    scratch: &'a FrameScratch,
+}
+
+impl<T> Deref for ScratchVec<'_, T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.vec
+    }
+}
+
+impl<T> DerefMut for ScratchVec<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.vec
+    }
+}
+
+impl<T> Drop for ScratchVec<'_, T> {
+    fn drop(&mut self) {
+        self.scratch
+            .give_back(std::mem::take(&mut self.vec), self.capacity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectors_keep_their_capacity_across_frames() {
+        let scratch = FrameScratch::default();
+
+        for frame in 0..3 {
+            let mut barriers = scratch.vec::<u64>();
+            let mut attachments = scratch.vec::<u64>();
+            barriers.extend(0..100);
+            attachments.extend(0..10);
+            assert_eq!(barriers.len(), 100);
+            drop((barriers, attachments));
+
+            // The second frame may hand the small vector to the large use once more
+            let grown = scratch.end_frame();
+            match frame {
+                0 => assert_eq!(grown, 2),
+                2 => assert_eq!(grown, 0),
+                _ => {}
+            }
+        }
+
+        assert!(scratch.vec::<u64>().capacity() >= 10);
+        assert!(scratch.vec::<u32>().is_empty());
+    }
+}
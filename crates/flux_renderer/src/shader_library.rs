@@ -0,0 +1,160 @@
+use flux_ecs::resource::Resource;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+const ENGINE_SNIPPETS: [(&str, &str); 2] = [
+    ("camera.glsl", include_str!("../shaders/include/camera.glsl")),
+    ("lighting.glsl", include_str!("../shaders/include/lighting.glsl")),
+];
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ShaderIncludeError {
+    #[error("line {line}: unknown shader include {name:?}")]
+    UnknownInclude { name: String, line: usize },
+    #[error("line {line}: malformed include directive {directive:?}")]
+    MalformedDirective { directive: String, line: usize },
+    #[error("include cycle detected: {}", .chain.join(" -> "))]
+    IncludeCycle { chain: Vec<String> },
+}
+
+/// A virtual filesystem of GLSL snippets that shaders can pull in with `#include "name"`.
+///
+/// The engine snippets (`camera.glsl`, `lighting.glsl`) are always available, applications can
+/// register their own snippets with [`ShaderLibrary::add_source`]. Every snippet is included at
+/// most once per shader.
+pub struct ShaderLibrary {
+    sources: HashMap<String, String>,
+}
+
+impl Resource for ShaderLibrary {}
+
+impl Default for ShaderLibrary {
+    fn default() -> Self {
+        let sources = ENGINE_SNIPPETS
+            .iter()
+            .map(|(name, source)| (name.to_string(), source.to_string()))
+            .collect();
+
+        Self { sources }
+    }
+}
+
+impl ShaderLibrary {
+    /// Registers a snippet under `name`, replacing an existing snippet with the same name.
+    pub fn add_source(&mut self, name: impl Into<String>, source: impl Into<String>) {
+        self.sources.insert(name.into(), source.into());
+    }
+
+    pub fn get_source(&self, name: &str) -> Option<&str> {
+        self.sources.get(name).map(String::as_str)
+    }
+
+    /// Expands all `#include` directives of `source` recursively.
+    pub fn resolve(&self, source: &str) -> Result<String, ShaderIncludeError> {
+        let mut output = String::with_capacity(source.len());
+        let mut included = HashSet::new();
+        let mut stack = Vec::new();
+
+        self.expand(source, &mut output, &mut included, &mut stack)?;
+
+        Ok(output)
+    }
+
+    fn expand(
+        &self,
+        source: &str,
+        output: &mut String,
+        included: &mut HashSet<String>,
+        stack: &mut Vec<String>,
+    ) -> Result<(), ShaderIncludeError> {
+        for (index, line) in source.lines().enumerate() {
+            let Some(directive) = line.trim_start().strip_prefix("#include") else {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            };
+
+            let name = parse_include_name(directive).ok_or_else(|| {
+                ShaderIncludeError::MalformedDirective {
+                    directive: line.trim().to_string(),
+                    line: index + 1,
+                }
+            })?;
+
+            if stack.iter().any(|entry| entry == name) {
+                let mut chain = stack.clone();
+                chain.push(name.to_string());
+                return Err(ShaderIncludeError::IncludeCycle { chain });
+            }
+
+            if !included.insert(name.to_string()) {
+                continue;
+            }
+
+            let snippet =
+                self.get_source(name)
+                    .ok_or_else(|| ShaderIncludeError::UnknownInclude {
+                        name: name.to_string(),
+                        line: index + 1,
+                    })?;
+
+            stack.push(name.to_string());
+            self.expand(snippet, output, included, stack)?;
+            stack.pop();
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_include_name(directive: &str) -> Option<&str> {
+    let directive = directive.trim();
+    let (open, close) = match directive.chars().next()? {
+        '"' => ('"', '"'),
+        '<' => ('<', '>'),
+        _ => return None,
+    };
+
+    let name = directive.strip_prefix(open)?.strip_suffix(close)?;
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn includes_are_expanded_once() {
+        let mut library = ShaderLibrary::default();
+        library.add_source("common.glsl", "#include \"camera.glsl\"\nfloat common_value;");
+
+        let resolved = library
+            .resolve("#include \"camera.glsl\"\n#include <common.glsl>\nvoid main() {}")
+            .unwrap();
+
+        assert_eq!(resolved.matches("uniform UniformBufferObject").count(), 1);
+        assert!(resolved.contains("float common_value;"));
+        assert!(resolved.ends_with("void main() {}\n"));
+    }
+
+    #[test]
+    fn cycles_and_unknown_includes_are_reported() {
+        let mut library = ShaderLibrary::default();
+        library.add_source("a.glsl", "#include \"b.glsl\"");
+        library.add_source("b.glsl", "#include \"a.glsl\"");
+
+        assert_eq!(
+            library.resolve("#include \"a.glsl\""),
+            Err(ShaderIncludeError::IncludeCycle {
+                chain: vec!["a.glsl".into(), "b.glsl".into(), "a.glsl".into()]
+            })
+        );
+        assert_eq!(
+            library.resolve("\n#include \"missing.glsl\""),
+            Err(ShaderIncludeError::UnknownInclude {
+                name: "missing.glsl".into(),
+                line: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use crate::device::Device;
+use crate::log_targets;
+use ash::vk;
+use flux_ecs::resource::{Res, Resource};
+use log::{info, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// How long shutdown waits for submitted work before falling back to `vkDeviceWaitIdle`.
+const IN_FLIGHT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Tracks the GPU work that was submitted but may not have finished yet.
+///
+/// The frame driver records the fence of every submission with [`InFlightWork::submitted`] and
+/// stops submitting new frames once [`InFlightWork::is_stopping`] returns `true`. The Destroy
+/// schedule starts with [`wait_for_in_flight_work`], so no object is destroyed while the GPU may
+/// still use it.
+#[derive(Default)]
+pub struct InFlightWork {
+    fences: Mutex<Vec<vk::Fence>>,
+    stopping: AtomicBool,
+}
+
+impl Resource for InFlightWork {}
+
+impl InFlightWork {
+    fn fences(&self) -> MutexGuard<'_, Vec<vk::Fence>> {
+        self.fences.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Records a submission that signals `fence` once it completes.
+    pub fn submitted(&self, fence: vk::Fence) {
+        self.fences().push(fence);
+    }
+
+    /// Forgets the fences that are signaled, call it after waiting for a frame.
+    pub fn retire_completed(&self, device: &ash::Device) {
+        self.fences()
+            .retain(|&fence| !matches!(unsafe { device.get_fence_status(fence) }, Ok(true)));
+    }
+
+    pub fn pending(&self) -> usize {
+        self.fences().len()
+    }
+
+    /// Signals the frame driver to stop submitting frames.
+    pub fn request_stop(&self) {
+        self.stopping.store(true, Ordering::Release);
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::Acquire)
+    }
+}
+
+/// Stops the frame driver and waits until the GPU finished all submitted work, it runs before
+/// every other renderer Destroy system.
+pub fn wait_for_in_flight_work(
+    device: Res<Device>,
+    in_flight: Res<InFlightWork>,
+) -> Result<(), vk::Result> {
+    in_flight.request_stop();
+
+    let fences = in_flight.fences().clone();
+    info!(
+        target: log_targets::DEVICE,
+        "Waiting for {} in-flight submissions before shutdown",
+        fences.len()
+    );
+
+    if !fences.is_empty() {
+        let timeout = IN_FLIGHT_TIMEOUT.as_nanos() as u64;
+        match unsafe { device.wait_for_fences(&fences, true, timeout) } {
+            Ok(()) => {}
+            Err(vk::Result::TIMEOUT) => warn!(
+                target: log_targets::DEVICE,
+                "In-flight work did not finish within {IN_FLIGHT_TIMEOUT:?}, waiting for the device"
+            ),
+            Err(err) => return Err(err),
+        }
+    }
+
+    unsafe { device.device_wait_idle()? };
+
+    in_flight.retire_completed(&device);
+    debug_assert_eq!(
+        in_flight.pending(),
+        0,
+        "Submitted work remains after the device is idle"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,257 @@
+use flux_ecs::commands::Commands;
+use flux_ecs::component::Component;
+use flux_ecs::query::Query;
+use flux_ecs::resource::{Res, Resource};
+
+/// Draw order of a sprite, sprites with a higher index are drawn on top.
+///
+/// Sprites with the same index keep their query order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ZIndex(pub i32);
+
+impl Component for ZIndex {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AtlasId(pub usize);
+
+/// A sub-rectangle of an atlas texture in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A texture split into regions that sprites address by index.
+#[derive(Debug, Clone)]
+pub struct TextureAtlas {
+    pub width: u32,
+    pub height: u32,
+    pub regions: Vec<AtlasRegion>,
+}
+
+impl TextureAtlas {
+    /// Creates an atlas of equally sized cells, indexed row by row.
+    ///
+    /// # Panics
+    /// Panics if `columns` or `rows` is zero.
+    pub fn from_grid(width: u32, height: u32, columns: u32, rows: u32) -> Self {
+        assert!(
+            columns > 0 && rows > 0,
+            "An atlas grid needs at least one cell, got {columns}x{rows}"
+        );
+        let cell_width = width / columns;
+        let cell_height = height / rows;
+
+        let regions = (0..rows)
+            .flat_map(|row| {
+                (0..columns).map(move |column| AtlasRegion {
+                    x: column * cell_width,
+                    y: row * cell_height,
+                    width: cell_width,
+                    height: cell_height,
+                })
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            regions,
+        }
+    }
+
+    /// The normalized `[u_min, v_min, u_max, v_max]` coordinates of a region.
+    pub fn uv_rect(&self, region: usize) -> Option<[f32; 4]> {
+        let region = self.regions.get(region)?;
+        let width = self.width as f32;
+        let height = self.height as f32;
+
+        Some([
+            region.x as f32 / width,
+            region.y as f32 / height,
+            (region.x + region.width) as f32 / width,
+            (region.y + region.height) as f32 / height,
+        ])
+    }
+}
+
+#[derive(Default)]
+pub struct TextureAtlases {
+    atlases: Vec<TextureAtlas>,
+}
+
+impl Resource for TextureAtlases {}
+
+impl TextureAtlases {
+    pub fn add(&mut self, atlas: TextureAtlas) -> AtlasId {
+        self.atlases.push(atlas);
+        AtlasId(self.atlases.len() - 1)
+    }
+
+    pub fn get(&self, id: AtlasId) -> Option<&TextureAtlas> {
+        self.atlases.get(id.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sprite {
+    pub atlas: AtlasId,
+    pub region: usize,
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl Component for Sprite {}
+
+/// Per-instance data of a sprite in a batch.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteInstance {
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub uv_rect: [f32; 4],
+    pub color: [f32; 4],
+}
+
+/// Consecutive sprites, in draw order, that share an atlas and can be drawn with a single
+/// instanced draw.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteBatch {
+    pub atlas: AtlasId,
+    pub instances: Vec<SpriteInstance>,
+}
+
+/// The sprite batches of the current frame, in draw order.
+#[derive(Debug, Default)]
+pub struct SpriteBatches {
+    pub batches: Vec<SpriteBatch>,
+}
+
+impl Resource for SpriteBatches {}
+
+/// Sorts sprites by their [`ZIndex`] and merges neighbouring sprites of the same atlas into
+/// batches. Sprites referencing unknown atlases or regions are skipped.
+pub fn batch_sprites<'a>(
+    sprites: impl IntoIterator<Item = (&'a Sprite, &'a ZIndex)>,
+    atlases: &TextureAtlases,
+) -> Vec<SpriteBatch> {
+    let mut sprites = sprites.into_iter().collect::<Vec<_>>();
+    sprites.sort_by_key(|(_, z_index)| **z_index);
+
+    let mut batches: Vec<SpriteBatch> = Vec::new();
+    for (sprite, _) in sprites {
+        let Some(uv_rect) = atlases
+            .get(sprite.atlas)
+            .and_then(|atlas| atlas.uv_rect(sprite.region))
+        else {
+            continue;
+        };
+
+        let instance = SpriteInstance {
+            position: sprite.position,
+            size: sprite.size,
+            uv_rect,
+            color: sprite.color,
+        };
+
+        match batches.last_mut() {
+            Some(batch) if batch.atlas == sprite.atlas => batch.instances.push(instance),
+            _ => batches.push(SpriteBatch {
+                atlas: sprite.atlas,
+                instances: vec![instance],
+            }),
+        }
+    }
+
+    batches
+}
+
+/// Batches the sprites of the world, sprites without a [`ZIndex`] are drawn at index `0`.
+///
+/// The renderer has no textures to draw the atlases with yet, so the
+/// [`RendererPlugin`](crate::RendererPlugin) does not run this system. Add it to a schedule to
+/// draw the [`SpriteBatches`] yourself, e.g. from [`RawVulkanHooks`](crate::RawVulkanHooks).
+pub fn prepare_sprite_batches(
+    sprites: Query<(&Sprite, Option<&ZIndex>)>,
+    atlases: Res<TextureAtlases>,
+    mut commands: Commands,
+) {
+    const DEFAULT_Z_INDEX: ZIndex = ZIndex(0);
+    let sprites = sprites
+        .into_iter()
+        .map(|(sprite, z_index)| (sprite, z_index.unwrap_or(&DEFAULT_Z_INDEX)));
+    let batches = batch_sprites(sprites, &atlases);
+    commands.insert_resource(SpriteBatches { batches });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flux_ecs::world::World;
+
+    fn sprite(atlas: AtlasId, region: usize) -> Sprite {
+        Sprite {
+            atlas,
+            region,
+            position: [0.0, 0.0],
+            size: [1.0, 1.0],
+            color: [1.0; 4],
+        }
+    }
+
+    #[test]
+    fn sprites_are_sorted_and_batched_by_atlas() {
+        let mut atlases = TextureAtlases::default();
+        let ui = atlases.add(TextureAtlas::from_grid(64, 64, 2, 2));
+        let world = atlases.add(TextureAtlas::from_grid(32, 32, 1, 1));
+
+        let sprites = [
+            (sprite(ui, 3), ZIndex(2)),
+            (sprite(world, 0), ZIndex(0)),
+            (sprite(ui, 0), ZIndex(1)),
+            (sprite(ui, 1), ZIndex(1)),
+        ];
+
+        let batches = batch_sprites(sprites.iter().map(|(s, z)| (s, z)), &atlases);
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].atlas, world);
+        assert_eq!(batches[1].atlas, ui);
+        assert_eq!(
+            batches[1]
+                .instances
+                .iter()
+                .map(|instance| instance.uv_rect)
+                .collect::<Vec<_>>(),
+            [[0.0, 0.0, 0.5, 0.5], [0.5, 0.0, 1.0, 0.5], [0.5, 0.5, 1.0, 1.0]]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one cell")]
+    fn empty_grids_are_rejected() {
+        TextureAtlas::from_grid(64, 64, 0, 2);
+    }
+
+    #[test]
+    fn sprites_without_z_index_are_drawn_at_zero() {
+        let mut world = World::new();
+        let mut atlases = TextureAtlases::default();
+        let below = atlases.add(TextureAtlas::from_grid(32, 32, 1, 1));
+        let default = atlases.add(TextureAtlas::from_grid(32, 32, 1, 1));
+        let above = atlases.add(TextureAtlas::from_grid(32, 32, 1, 1));
+        world.add_resource(atlases);
+        world.spawn((sprite(above, 0), ZIndex(1)));
+        world.spawn((sprite(default, 0),));
+        world.spawn((sprite(below, 0), ZIndex(-1)));
+
+        world.run_system_once(prepare_sprite_batches).unwrap();
+
+        let batches = &world.get_resource::<SpriteBatches>().unwrap().batches;
+        let order: Vec<_> = batches.iter().map(|batch| batch.atlas).collect();
+        assert_eq!(order, [below, default, above]);
+    }
+}
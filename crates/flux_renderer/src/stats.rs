@@ -0,0 +1,208 @@
+use crate::frame::FrameOutcome;
+use crate::occlusion::OcclusionResults;
+use crate::pipeline_statistics::PipelineStatistics;
+use crate::present_timing::PresentPacing;
+use flux_ecs::resource::Resource;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Rendering statistics of the current frame.
+///
+/// The counters are atomics so systems can record into the stats through `Res<RenderStats>`.
+/// [`RenderStats::begin_frame`] advances the frame index and resets the per-frame counters, the
+/// frame outcome and pipeline warm-up counters accumulate over the lifetime of the renderer.
+#[derive(Debug, Default)]
+pub struct RenderStats {
+    frame_index: AtomicU64,
+    draw_calls: AtomicU32,
+    triangles: AtomicU64,
+    pipeline_binds: AtomicU32,
+    descriptor_binds: AtomicU32,
+    buffer_uploads: AtomicU32,
+    uploaded_bytes: AtomicU64,
+    occlusion_tests: AtomicU32,
+    occluded_draws: AtomicU32,
+    input_vertices: AtomicU64,
+    vertex_invocations: AtomicU64,
+    clipped_primitives: AtomicU64,
+    fragment_invocations: AtomicU64,
+    skipped_frames: AtomicU64,
+    swapchain_recreations: AtomicU64,
+    device_losses: AtomicU64,
+    warmed_pipelines: AtomicU32,
+    pipeline_warmup_nanos: AtomicU64,
+    missed_vsyncs: AtomicU64,
+    present_margin_nanos: AtomicU64,
+    present_interval_nanos: AtomicU64,
+}
+
+impl Resource for RenderStats {}
+
+impl RenderStats {
+    pub fn begin_frame(&self) {
+        self.frame_index.fetch_add(1, Ordering::Relaxed);
+        self.draw_calls.store(0, Ordering::Relaxed);
+        self.triangles.store(0, Ordering::Relaxed);
+        self.pipeline_binds.store(0, Ordering::Relaxed);
+        self.descriptor_binds.store(0, Ordering::Relaxed);
+        self.buffer_uploads.store(0, Ordering::Relaxed);
+        self.uploaded_bytes.store(0, Ordering::Relaxed);
+        self.occlusion_tests.store(0, Ordering::Relaxed);
+        self.occluded_draws.store(0, Ordering::Relaxed);
+    }
+
+    pub fn record_draw(&self, index_count: u32, instance_count: u32) {
+        self.draw_calls.fetch_add(1, Ordering::Relaxed);
+        self.triangles.fetch_add(
+            u64::from(index_count / 3) * u64::from(instance_count),
+            Ordering::Relaxed,
+        );
+    }
+
+    pub fn record_pipeline_bind(&self) {
+        self.pipeline_binds.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_descriptor_bind(&self) {
+        self.descriptor_binds.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_buffer_upload(&self, size: u64) {
+        self.buffer_uploads.fetch_add(1, Ordering::Relaxed);
+        self.uploaded_bytes.fetch_add(size, Ordering::Relaxed);
+    }
+
+    pub fn record_occlusion(&self, results: OcclusionResults) {
+        self.occlusion_tests.fetch_add(results.tested, Ordering::Relaxed);
+        self.occluded_draws.fetch_add(results.occluded, Ordering::Relaxed);
+    }
+
+    /// Stores the statistics of the last resolved frame, they are not reset by
+    /// [`RenderStats::begin_frame`] since the queries resolve a frame later.
+    pub fn record_pipeline_statistics(&self, statistics: PipelineStatistics) {
+        self.input_vertices.store(statistics.input_vertices, Ordering::Relaxed);
+        self.vertex_invocations.store(statistics.vertex_invocations, Ordering::Relaxed);
+        self.clipped_primitives.store(statistics.clipped_primitives, Ordering::Relaxed);
+        self.fragment_invocations.store(statistics.fragment_invocations, Ordering::Relaxed);
+    }
+
+    /// Counts the frames that did not acquire an image or need a new swapchain or device.
+    pub fn record_frame_outcome(&self, outcome: FrameOutcome) {
+        let counter = match outcome {
+            FrameOutcome::Acquired { .. } => return,
+            FrameOutcome::Suboptimal { .. } | FrameOutcome::RecreateSwapchain => {
+                &self.swapchain_recreations
+            }
+            FrameOutcome::Skip => &self.skipped_frames,
+            FrameOutcome::DeviceLost => &self.device_losses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_pipeline_warmup(&self, pipelines: u32, elapsed: Duration) {
+        self.warmed_pipelines.fetch_add(pipelines, Ordering::Relaxed);
+        self.pipeline_warmup_nanos.fetch_add(nanos(elapsed), Ordering::Relaxed);
+    }
+
+    /// Accumulates the missed vsyncs and stores the latest present timing, see
+    /// [`PresentTiming`](crate::PresentTiming).
+    pub fn record_present_pacing(&self, pacing: PresentPacing) {
+        self.missed_vsyncs.fetch_add(pacing.missed_vsyncs, Ordering::Relaxed);
+        self.present_margin_nanos.store(nanos(pacing.present_margin), Ordering::Relaxed);
+        if !pacing.present_interval.is_zero() {
+            self.present_interval_nanos
+                .store(nanos(pacing.present_interval), Ordering::Relaxed);
+        }
+    }
+
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index.load(Ordering::Relaxed)
+    }
+
+    pub fn draw_calls(&self) -> u32 {
+        self.draw_calls.load(Ordering::Relaxed)
+    }
+
+    pub fn triangles(&self) -> u64 {
+        self.triangles.load(Ordering::Relaxed)
+    }
+
+    pub fn pipeline_binds(&self) -> u32 {
+        self.pipeline_binds.load(Ordering::Relaxed)
+    }
+
+    pub fn descriptor_binds(&self) -> u32 {
+        self.descriptor_binds.load(Ordering::Relaxed)
+    }
+
+    pub fn buffer_uploads(&self) -> u32 {
+        self.buffer_uploads.load(Ordering::Relaxed)
+    }
+
+    pub fn uploaded_bytes(&self) -> u64 {
+        self.uploaded_bytes.load(Ordering::Relaxed)
+    }
+
+    /// The draws wrapped in an occlusion query, see [`crate::OcclusionSettings`].
+    pub fn occlusion_tests(&self) -> u32 {
+        self.occlusion_tests.load(Ordering::Relaxed)
+    }
+
+    /// The queried draws that were fully occluded.
+    pub fn occluded_draws(&self) -> u32 {
+        self.occluded_draws.load(Ordering::Relaxed)
+    }
+
+    /// The pipeline statistics of the last resolved frame, see
+    /// [`PipelineStatisticsSettings`](crate::PipelineStatisticsSettings).
+    pub fn pipeline_statistics(&self) -> PipelineStatistics {
+        PipelineStatistics {
+            input_vertices: self.input_vertices.load(Ordering::Relaxed),
+            vertex_invocations: self.vertex_invocations.load(Ordering::Relaxed),
+            clipped_primitives: self.clipped_primitives.load(Ordering::Relaxed),
+            fragment_invocations: self.fragment_invocations.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn skipped_frames(&self) -> u64 {
+        self.skipped_frames.load(Ordering::Relaxed)
+    }
+
+    pub fn swapchain_recreations(&self) -> u64 {
+        self.swapchain_recreations.load(Ordering::Relaxed)
+    }
+
+    pub fn device_losses(&self) -> u64 {
+        self.device_losses.load(Ordering::Relaxed)
+    }
+
+    /// The number of pipeline permutations created ahead of use.
+    pub fn warmed_pipelines(&self) -> u32 {
+        self.warmed_pipelines.load(Ordering::Relaxed)
+    }
+
+    /// The total time spent creating pipeline permutations.
+    pub fn pipeline_warmup_time(&self) -> Duration {
+        Duration::from_nanos(self.pipeline_warmup_nanos.load(Ordering::Relaxed))
+    }
+
+    /// The refresh cycles in which no new image was ready to be displayed, only measured if the
+    /// device supports `VK_GOOGLE_display_timing`.
+    pub fn missed_vsyncs(&self) -> u64 {
+        self.missed_vsyncs.load(Ordering::Relaxed)
+    }
+
+    /// How long before its deadline the last measured image was ready to be displayed.
+    pub fn present_margin(&self) -> Duration {
+        Duration::from_nanos(self.present_margin_nanos.load(Ordering::Relaxed))
+    }
+
+    /// The time between the last two measured images reaching the display.
+    pub fn present_interval(&self) -> Duration {
+        Duration::from_nanos(self.present_interval_nanos.load(Ordering::Relaxed))
+    }
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
@@ -1,4 +1,7 @@
-use crate::instance::{SurfaceProviderResource, VulkanInstance};
+use crate::instance::{SurfaceProvider, SurfaceProviderResource, VulkanInstance};
+use crate::log_targets;
+use crate::progress::InitializationProgress;
+use crate::recreate::{RendererSchedule, RunSchedule};
 use ash::khr::surface;
 use ash::vk;
 use flux_ecs::commands::Commands;
@@ -20,36 +23,189 @@ impl Deref for VulkanSurface {
     }
 }
 
-pub fn create_surface(
-    surface_provider_resource: Res<SurfaceProviderResource>,
-    instance: Res<VulkanInstance>,
-    mut commands: Commands,
-) -> Result<(), vk::Result> {
-    info!("Creating vulkan surface");
+/// Creates a surface for the provider's window, returns `None` if the provider has no window.
+pub(crate) fn build_surface(
+    instance: &VulkanInstance,
+    surface_provider: &dyn SurfaceProvider,
+) -> Result<Option<vk::SurfaceKHR>, vk::Result> {
+    let (Some(display_handle), Some(window_handle)) = (
+        surface_provider.get_display_handle(),
+        surface_provider.get_window_handle(),
+    ) else {
+        return Ok(None);
+    };
+
     let surface = unsafe {
         ash_window::create_surface(
             &instance.entry,
-            &instance,
-            surface_provider_resource.get_display_handle(),
-            surface_provider_resource.get_window_handle(),
+            instance,
+            display_handle,
+            window_handle,
             None,
         )
     }?;
 
-    commands.insert_resource(VulkanSurface { surface });
+    Ok(Some(surface))
+}
+
+pub fn create_surface(
+    surface_provider_resource: Option<Res<SurfaceProviderResource>>,
+    instance: Res<VulkanInstance>,
+    mut commands: Commands,
+) -> Result<(), vk::Result> {
+    let Some(surface_provider_resource) = surface_provider_resource else {
+        info!(target: log_targets::SURFACE, "No surface provider, skipping surface creation");
+        return Ok(());
+    };
+
+    info!(target: log_targets::SURFACE, "Creating vulkan surface");
+
+    match build_surface(&instance, &***surface_provider_resource)? {
+        Some(surface) => {
+            commands.insert_resource(VulkanSurface { surface });
+            commands.send_event(InitializationProgress::SurfaceCreated);
+        }
+        None => info!(
+            target: log_targets::SURFACE,
+            "No native window available, skipping surface creation"
+        ),
+    }
 
     Ok(())
 }
 
+/// What [`handle_surface_lifecycle`] does with the surface of a provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SurfaceTransition {
+    Keep,
+    /// The native window was lost, e.g. because an Android app was suspended.
+    Release,
+    /// A native window became available, e.g. because an Android app was resumed or the app
+    /// started without one.
+    Acquire,
+}
+
+impl SurfaceTransition {
+    pub(crate) fn of(provider: Option<&dyn SurfaceProvider>, has_surface: bool) -> Self {
+        // Headless renderers have no window and their offscreen target is never lost
+        let Some(provider) = provider else {
+            return Self::Keep;
+        };
+        let window_available =
+            provider.get_display_handle().is_some() && provider.get_window_handle().is_some();
+
+        match (has_surface, window_available) {
+            (true, false) => Self::Release,
+            (false, true) => Self::Acquire,
+            _ => Self::Keep,
+        }
+    }
+}
+
+/// Destroys the surface and everything presenting to it when the provider loses its native
+/// window and creates them once a window is available again, see
+/// [`RendererSchedule::ReleaseSurface`] and [`RendererSchedule::AcquireSurface`].
+pub fn handle_surface_lifecycle(
+    surface_provider: Option<Res<SurfaceProviderResource>>,
+    surface: Option<Res<VulkanSurface>>,
+    mut commands: Commands,
+) {
+    let provider = surface_provider
+        .as_deref()
+        .map(|provider| &*provider.provider);
+
+    match SurfaceTransition::of(provider, surface.is_some()) {
+        SurfaceTransition::Keep => {}
+        SurfaceTransition::Release => {
+            info!(target: log_targets::SURFACE, "Native window lost, destroying the surface");
+            commands.push(RunSchedule(RendererSchedule::ReleaseSurface));
+        }
+        SurfaceTransition::Acquire => {
+            info!(target: log_targets::SURFACE, "Native window available, creating the surface");
+            commands.push(RunSchedule(RendererSchedule::AcquireSurface));
+        }
+    }
+}
+
 pub fn destroy_surface(
-    surface: Res<VulkanSurface>,
+    surface: Option<Res<VulkanSurface>>,
     instance: Res<VulkanInstance>,
     mut commands: Commands,
 ) {
-    info!("Destroying vulkan surface");
+    let Some(surface) = surface else {
+        return;
+    };
+
+    info!(target: log_targets::SURFACE, "Destroying vulkan surface");
     unsafe {
         let surface_loader = surface::Instance::new(&instance.entry, &instance);
         surface::Instance::destroy_surface(&surface_loader, **surface, None)
     }
     commands.remove_resource::<VulkanSurface>();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::NullSurfaceProvider;
+    use raw_window_handle::{RawDisplayHandle, RawWindowHandle, WebDisplayHandle, WebWindowHandle};
+
+    /// A window whose native handle can be lost and restored like an Android window.
+    struct SuspendableWindow {
+        available: bool,
+    }
+
+    impl SurfaceProvider for SuspendableWindow {
+        fn get_display_handle(&self) -> Option<RawDisplayHandle> {
+            Some(RawDisplayHandle::Web(WebDisplayHandle::new()))
+        }
+
+        fn get_window_handle(&self) -> Option<RawWindowHandle> {
+            self.available
+                .then(|| RawWindowHandle::Web(WebWindowHandle::new(1)))
+        }
+
+        fn get_extent(&self) -> (u32, u32) {
+            (800, 600)
+        }
+    }
+
+    #[test]
+    fn headless_providers_keep_their_target() {
+        let provider = NullSurfaceProvider {
+            width: 64,
+            height: 64,
+        };
+        assert_eq!(
+            SurfaceTransition::of(Some(&provider), false),
+            SurfaceTransition::Keep
+        );
+        assert_eq!(SurfaceTransition::of(None, false), SurfaceTransition::Keep);
+    }
+
+    #[test]
+    fn the_surface_follows_the_native_window() {
+        let mut window = SuspendableWindow { available: false };
+        // Started without a window, e.g. before the first resume on Android
+        assert_eq!(
+            SurfaceTransition::of(Some(&window), false),
+            SurfaceTransition::Keep
+        );
+
+        window.available = true;
+        assert_eq!(
+            SurfaceTransition::of(Some(&window), false),
+            SurfaceTransition::Acquire
+        );
+        assert_eq!(
+            SurfaceTransition::of(Some(&window), true),
+            SurfaceTransition::Keep
+        );
+
+        window.available = false;
+        assert_eq!(
+            SurfaceTransition::of(Some(&window), true),
+            SurfaceTransition::Release
+        );
+    }
+}
@@ -1,22 +1,146 @@
-use crate::device::{Device, PhysicalDevice};
+use crate::allocator::{Allocation, GpuAllocator};
+use crate::color::OutputEncoding;
+use crate::config::GraphicsSettings;
+use crate::device::{Device, PhysicalDevice, SwapchainSupport, query_swapchain_support};
+use crate::image::{create_image, create_image_view};
 use crate::instance::{SurfaceProviderResource, VulkanInstance};
+use crate::layout_tracker::ImageLayoutTracker;
 use crate::surface::VulkanSurface;
+use crate::window::Headless;
 use ash::{khr, vk};
 use flux_ecs::commands::Commands;
 use flux_ecs::resource::{Res, Resource};
-use log::debug;
+use crate::log_targets;
+use crate::progress::InitializationProgress;
+use log::{debug, error, warn};
 use std::ops::Deref;
 
 pub struct Swapchain {
     pub format: vk::SurfaceFormatKHR,
     pub extent: vk::Extent2D,
+    /// The extent of the [`render_target`](Swapchain::render_target), the `extent` scaled by
+    /// [`GraphicsSettings::render_scale`]. It only differs from `extent` when rendering into an
+    /// intermediate image, which is scaled when copied into the swapchain image.
+    pub render_extent: vk::Extent2D,
     pub swapchain: vk::SwapchainKHR,
     pub images: Vec<vk::Image>,
     pub image_views: Vec<vk::ImageView>,
+    /// The usages the swapchain images were created with.
+    pub usage: vk::ImageUsageFlags,
+    /// The image frames are rendered into when the swapchain images lack a requested usage or
+    /// the frames are rendered at a different resolution.
+    pub intermediate: Option<IntermediateImage>,
+    /// The memory of the single image rendered into when running [`Headless`], the image is
+    /// owned by the renderer instead of a presentation engine.
+    pub offscreen_memory: Option<Allocation>,
 }
 
 impl Resource for Swapchain {}
 
+impl Swapchain {
+    /// Whether the final pass has to encode its output to sRGB itself.
+    pub fn output_encoding(&self) -> OutputEncoding {
+        OutputEncoding::for_swapchain(self.format.format)
+    }
+
+    /// Whether frames are rendered into an offscreen image and never presented.
+    pub fn is_headless(&self) -> bool {
+        self.offscreen_memory.is_some()
+    }
+
+    /// The layout the images are left in at the end of a frame, headless frames stay ready to be
+    /// read back.
+    pub fn final_layout(&self) -> vk::ImageLayout {
+        if self.is_headless() {
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+        } else {
+            vk::ImageLayout::PRESENT_SRC_KHR
+        }
+    }
+}
+
+/// The format of the offscreen image, the pixels read back are RGBA.
+const OFFSCREEN_FORMAT: vk::SurfaceFormatKHR = vk::SurfaceFormatKHR {
+    format: vk::Format::R8G8B8A8_SRGB,
+    color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+};
+
+/// A color target with the swapchain format and the render extent that is copied into the
+/// acquired swapchain image before presenting.
+pub struct IntermediateImage {
+    pub image: vk::Image,
+    pub image_view: vk::ImageView,
+    pub memory: Allocation,
+    pub usage: vk::ImageUsageFlags,
+}
+
+/// How the requested usages can be provided for a surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SwapchainUsage {
+    pub swapchain: vk::ImageUsageFlags,
+    /// The usages of the intermediate image, if one is needed.
+    pub intermediate: Option<vk::ImageUsageFlags>,
+}
+
+impl SwapchainUsage {
+    /// Frames rendered at a different resolution than the swapchain, `scaled`, always render
+    /// into an intermediate image.
+    pub fn resolve(
+        supported: vk::ImageUsageFlags,
+        requested: vk::ImageUsageFlags,
+        scaled: bool,
+    ) -> Self {
+        let required = vk::ImageUsageFlags::COLOR_ATTACHMENT;
+        if !scaled && supported.contains(required | requested) {
+            return Self {
+                swapchain: required | requested,
+                intermediate: None,
+            };
+        }
+
+        Self {
+            swapchain: required | vk::ImageUsageFlags::TRANSFER_DST,
+            intermediate: Some(required | vk::ImageUsageFlags::TRANSFER_SRC | requested),
+        }
+    }
+}
+
+/// The requested usages images of a format with the given features can be created with.
+fn usage_supported_by_format(
+    requested: vk::ImageUsageFlags,
+    features: vk::FormatFeatureFlags,
+) -> vk::ImageUsageFlags {
+    if features.contains(vk::FormatFeatureFlags::STORAGE_IMAGE) {
+        requested
+    } else {
+        requested & !vk::ImageUsageFlags::STORAGE
+    }
+}
+
+impl Swapchain {
+    /// The image frames are rendered into for the swapchain image at `index`.
+    pub fn render_target(&self, index: usize) -> (vk::Image, vk::ImageView) {
+        match &self.intermediate {
+            Some(intermediate) => (intermediate.image, intermediate.image_view),
+            None => (self.images[index], self.image_views[index]),
+        }
+    }
+
+    /// Registers the swapchain images with the layout tracker, they start out undefined.
+    pub(crate) fn track_layouts(&self, layouts: &ImageLayoutTracker) {
+        let intermediate = self.intermediate.as_ref().map(|intermediate| intermediate.image);
+        for &image in self.images.iter().chain(&intermediate) {
+            layouts.register(
+                image,
+                vk::ImageAspectFlags::COLOR,
+                1,
+                1,
+                vk::ImageLayout::UNDEFINED,
+            );
+        }
+    }
+}
+
 impl Deref for Swapchain {
     type Target = vk::SwapchainKHR;
 
@@ -25,17 +149,99 @@ impl Deref for Swapchain {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_swapchain(
     instance: Res<VulkanInstance>,
     physical_device: Res<PhysicalDevice>,
     device: Res<Device>,
-    surface: Res<VulkanSurface>,
-    surface_provider: Res<SurfaceProviderResource>,
+    allocator: Res<GpuAllocator>,
+    surface: Option<Res<VulkanSurface>>,
+    surface_provider: Option<Res<SurfaceProviderResource>>,
+    headless: Option<Res<Headless>>,
+    graphics_settings: Option<Res<GraphicsSettings>>,
+    layouts: Res<ImageLayoutTracker>,
     mut commands: Commands,
 ) -> Result<(), vk::Result> {
-    debug!("Creating swapchain");
+    let Some(surface) = surface else {
+        let extent = headless.map(|headless| (headless.width, headless.height)).or_else(|| {
+            surface_provider
+                .filter(|provider| provider.get_display_handle().is_none())
+                .map(|provider| provider.get_extent())
+        });
+        let Some((width, height)) = extent else {
+            debug!(target: log_targets::SWAPCHAIN, "No surface available, skipping swapchain");
+            return Ok(());
+        };
+
+        let swapchain = build_offscreen_target(
+            &device,
+            &allocator,
+            vk::Extent2D { width, height },
+            &graphics_settings.as_deref().cloned().unwrap_or_default(),
+        )?;
+        swapchain.track_layouts(&layouts);
+        commands.send_event(InitializationProgress::SwapchainReady {
+            width,
+            height,
+            image_count: swapchain.images.len(),
+        });
+        commands.insert_resource(swapchain);
+        return Ok(());
+    };
+    let Some(surface_provider) = surface_provider else {
+        return Ok(());
+    };
+
+    // The surface may be newer than the physical device, e.g. after an Android app resumed
+    let support =
+        match query_swapchain_support(&instance.entry, &instance, **physical_device, **surface) {
+            Ok(support) => support,
+            Err(err) => {
+                error!(target: log_targets::SWAPCHAIN, "The surface is unusable: {err}");
+                return Ok(());
+            }
+        };
 
-    let surface_format = physical_device
+    let swapchain = build_swapchain(
+        &instance,
+        &physical_device,
+        &device,
+        &allocator,
+        **surface,
+        &support,
+        surface_provider.get_extent(),
+        &graphics_settings.as_deref().cloned().unwrap_or_default(),
+        vk::SwapchainKHR::null(),
+    )?;
+
+    swapchain.track_layouts(&layouts);
+    commands.send_event(InitializationProgress::SwapchainReady {
+        width: swapchain.extent.width,
+        height: swapchain.extent.height,
+        image_count: swapchain.images.len(),
+    });
+    commands.insert_resource(swapchain);
+
+    Ok(())
+}
+
+/// Creates a swapchain for `surface`, `old_swapchain` is retired into it when recreating the
+/// swapchain of the same surface and null otherwise.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn build_swapchain(
+    instance: &VulkanInstance,
+    physical_device: &PhysicalDevice,
+    device: &Device,
+    allocator: &GpuAllocator,
+    surface: vk::SurfaceKHR,
+    support: &SwapchainSupport,
+    (width, height): (u32, u32),
+    settings: &GraphicsSettings,
+    old_swapchain: vk::SwapchainKHR,
+) -> Result<Swapchain, vk::Result> {
+    debug!(target: log_targets::SWAPCHAIN, "Creating swapchain");
+
+    let surface_format = support
         .formats
         .iter()
         .cloned()
@@ -43,21 +249,35 @@ pub fn create_swapchain(
             format.format == vk::Format::B8G8R8A8_SRGB
                 && format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
         })
-        .unwrap_or(physical_device.formats[0]);
+        .unwrap_or(support.formats[0]);
+    if OutputEncoding::for_swapchain(surface_format.format) == OutputEncoding::Shader {
+        warn!(
+            target: log_targets::SWAPCHAIN,
+            "No sRGB swapchain format available, using {:?}: the final pass has to encode its \
+             output to sRGB",
+            surface_format.format
+        );
+    }
 
-    let present_mode = physical_device
-        .present_modes
+    // Without vsync presenting immediately is preferred, tearing is accepted
+    let preferred_modes: &[vk::PresentModeKHR] = if settings.vsync {
+        &[vk::PresentModeKHR::MAILBOX]
+    } else {
+        &[vk::PresentModeKHR::IMMEDIATE, vk::PresentModeKHR::MAILBOX]
+    };
+    let present_mode = preferred_modes
         .iter()
         .cloned()
-        .find(|mode| *mode == vk::PresentModeKHR::MAILBOX)
+        .find(|mode| support.present_modes.contains(mode))
         .unwrap_or(vk::PresentModeKHR::FIFO); // The spec requires FIFO to be available
+    debug!(target: log_targets::SWAPCHAIN, "Using present mode {present_mode:?}");
 
-    let extent = if physical_device.capabilities.current_extent.width != u32::MAX {
-        physical_device.capabilities.current_extent
+    let capabilities = &support.capabilities;
+    let extent = if capabilities.current_extent.width != u32::MAX {
+        capabilities.current_extent
     } else {
-        let (width, height) = surface_provider.get_extent();
-        let min_size = physical_device.capabilities.min_image_extent;
-        let max_size = physical_device.capabilities.max_image_extent;
+        let min_size = capabilities.min_image_extent;
+        let max_size = capabilities.max_image_extent;
 
         vk::Extent2D {
             width: width.clamp(min_size.width, max_size.width),
@@ -65,60 +285,206 @@ pub fn create_swapchain(
         }
     };
 
-    let mut image_count = physical_device.capabilities.min_image_count + 1;
-    if physical_device.capabilities.max_image_count > 0
-        && image_count > physical_device.capabilities.max_image_count
-    {
-        image_count = physical_device.capabilities.max_image_count;
+    let mut image_count = capabilities.min_image_count + 1;
+    if capabilities.max_image_count > 0 && image_count > capabilities.max_image_count {
+        image_count = capabilities.max_image_count;
+    }
+
+    // Both the swapchain and the intermediate image use the surface format
+    let format_features = unsafe {
+        instance.get_physical_device_format_properties(**physical_device, surface_format.format)
+    }
+    .optimal_tiling_features;
+    let requested = usage_supported_by_format(settings.swapchain_usage, format_features);
+    if requested != settings.swapchain_usage {
+        warn!(
+            target: log_targets::SWAPCHAIN,
+            "{:?} does not support {:?}, the usages are ignored",
+            surface_format.format,
+            settings.swapchain_usage & !requested
+        );
+    }
+
+    let (width, height) = settings.render_extent((extent.width, extent.height));
+    let mut render_extent = vk::Extent2D { width, height };
+    let scalable = format_features
+        .contains(vk::FormatFeatureFlags::BLIT_SRC | vk::FormatFeatureFlags::BLIT_DST);
+    if render_extent != extent && !scalable {
+        warn!(
+            target: log_targets::SWAPCHAIN,
+            "{:?} can not be scaled, rendering at the resolution of the surface",
+            surface_format.format
+        );
+        render_extent = extent;
+    }
+
+    let scaled = render_extent != extent;
+    let mut usage = SwapchainUsage::resolve(capabilities.supported_usage_flags, requested, scaled);
+    if usage.intermediate.is_some() {
+        debug!(
+            target: log_targets::SWAPCHAIN,
+            "Rendering into an intermediate image at {}x{}",
+            render_extent.width,
+            render_extent.height
+        );
+        if !capabilities.supported_usage_flags.contains(usage.swapchain) {
+            warn!(
+                target: log_targets::SWAPCHAIN,
+                "The surface can not be copied into, the requested usages and the render scale \
+                 are ignored"
+            );
+            render_extent = extent;
+            usage = SwapchainUsage::resolve(
+                capabilities.supported_usage_flags,
+                vk::ImageUsageFlags::empty(),
+                false,
+            );
+        } else if !capabilities.supported_usage_flags.contains(requested) {
+            warn!(
+                target: log_targets::SWAPCHAIN,
+                "The surface does not support {requested:?}, rendering into an intermediate image"
+            );
+        }
     }
 
+    let indices = &physical_device.indices;
     let mut queue_family_indices = vec![];
-    let image_sharing_mode = if physical_device.indices.graphics != physical_device.indices.present
-    {
-        queue_family_indices.push(physical_device.indices.graphics);
-        queue_family_indices.push(physical_device.indices.present);
+    let image_sharing_mode = if indices.graphics != indices.present {
+        queue_family_indices.push(indices.graphics);
+        queue_family_indices.push(indices.present);
         vk::SharingMode::CONCURRENT
     } else {
         vk::SharingMode::EXCLUSIVE
     };
 
     let create_info = vk::SwapchainCreateInfoKHR::default()
-        .surface(**surface)
+        .surface(surface)
         .min_image_count(image_count)
         .image_format(surface_format.format)
         .image_color_space(surface_format.color_space)
         .image_extent(extent)
         .image_array_layers(1)
-        .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+        .image_usage(usage.swapchain)
         .image_sharing_mode(image_sharing_mode)
         .queue_family_indices(&queue_family_indices)
-        .pre_transform(physical_device.capabilities.current_transform)
+        .pre_transform(capabilities.current_transform)
         .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
         .present_mode(present_mode)
         .clipped(true)
-        .old_swapchain(vk::SwapchainKHR::null());
+        .old_swapchain(old_swapchain);
 
-    let loader = khr::swapchain::Device::new(&instance, &device);
+    let loader = khr::swapchain::Device::new(instance, device);
     let swapchain = unsafe { loader.create_swapchain(&create_info, None) }?;
     let images = unsafe { loader.get_swapchain_images(swapchain)? };
 
     let image_views = images
         .iter()
-        .map(|image| create_image_view(*image, surface_format.format, &device))
+        .map(|image| create_swapchain_image_view(*image, surface_format.format, device))
         .collect::<Vec<_>>();
 
-    commands.insert_resource(Swapchain {
+    let intermediate = usage
+        .intermediate
+        .map(|usage| {
+            create_intermediate_image(
+                device,
+                allocator,
+                surface_format.format,
+                render_extent,
+                usage,
+            )
+        })
+        .transpose()?;
+
+    Ok(Swapchain {
         swapchain,
         images,
         format: surface_format,
         extent,
+        render_extent,
         image_views,
-    });
+        usage: usage.swapchain,
+        intermediate,
+        offscreen_memory: None,
+    })
+}
 
-    Ok(())
+/// Creates the single image headless frames are rendered into, it stands in for the swapchain.
+pub(crate) fn build_offscreen_target(
+    device: &Device,
+    allocator: &GpuAllocator,
+    extent: vk::Extent2D,
+    settings: &GraphicsSettings,
+) -> Result<Swapchain, vk::Result> {
+    debug!(
+        target: log_targets::SWAPCHAIN,
+        "Creating offscreen target ({}x{})",
+        extent.width,
+        extent.height
+    );
+
+    let usage = vk::ImageUsageFlags::COLOR_ATTACHMENT
+        | vk::ImageUsageFlags::TRANSFER_SRC
+        | settings.swapchain_usage;
+    let (image, memory) = create_image(
+        device,
+        allocator,
+        extent.width,
+        extent.height,
+        OFFSCREEN_FORMAT.format,
+        vk::ImageTiling::OPTIMAL,
+        usage,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )?;
+    let image_view = create_swapchain_image_view(image, OFFSCREEN_FORMAT.format, device);
+
+    Ok(Swapchain {
+        swapchain: vk::SwapchainKHR::null(),
+        images: vec![image],
+        format: OFFSCREEN_FORMAT,
+        extent,
+        // The offscreen image is read back at the requested size
+        render_extent: extent,
+        image_views: vec![image_view],
+        usage,
+        intermediate: None,
+        offscreen_memory: Some(memory),
+    })
+}
+
+fn create_intermediate_image(
+    device: &Device,
+    allocator: &GpuAllocator,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    usage: vk::ImageUsageFlags,
+) -> Result<IntermediateImage, vk::Result> {
+    debug!(target: log_targets::SWAPCHAIN, "Creating intermediate image with usage {usage:?}");
+
+    let (image, memory) = create_image(
+        device,
+        allocator,
+        extent.width,
+        extent.height,
+        format,
+        vk::ImageTiling::OPTIMAL,
+        usage,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )?;
+    let image_view = create_image_view(device, image, format, vk::ImageAspectFlags::COLOR)?;
+
+    Ok(IntermediateImage {
+        image,
+        image_view,
+        memory,
+        usage,
+    })
 }
 
-fn create_image_view(image: vk::Image, format: vk::Format, device: &Device) -> vk::ImageView {
+fn create_swapchain_image_view(
+    image: vk::Image,
+    format: vk::Format,
+    device: &Device,
+) -> vk::ImageView {
     let create_info = vk::ImageViewCreateInfo::default()
         .image(image)
         .view_type(vk::ImageViewType::TYPE_2D)
@@ -140,21 +506,99 @@ fn create_image_view(image: vk::Image, format: vk::Format, device: &Device) -> v
     unsafe { device.create_image_view(&create_info, None).unwrap() }
 }
 
-pub fn destroy_swapchain(
-    instance: Res<VulkanInstance>,
-    device: Res<Device>,
-    swapchain: Res<Swapchain>,
-    mut commands: Commands,
+/// Destroys the image views and the swapchain itself.
+///
+/// # Safety
+/// The swapchain images must no longer be in use by the device.
+pub(crate) unsafe fn destroy_swapchain_objects(
+    instance: &VulkanInstance,
+    device: &Device,
+    allocator: &GpuAllocator,
+    swapchain: &Swapchain,
+    layouts: &ImageLayoutTracker,
 ) {
-    debug!("Destroying swapchain");
-    let loader = khr::swapchain::Device::new(&instance, &device);
+    debug!(target: log_targets::SWAPCHAIN, "Destroying swapchain");
+    for &image in &swapchain.images {
+        layouts.unregister(image);
+    }
+    if let Some(intermediate) = &swapchain.intermediate {
+        layouts.unregister(intermediate.image);
+    }
+
+    let loader = khr::swapchain::Device::new(instance, device);
 
     unsafe {
         for &image_view in &swapchain.image_views {
             device.destroy_image_view(image_view, None);
         }
-        loader.destroy_swapchain(**swapchain, None);
+        if let Some(intermediate) = &swapchain.intermediate {
+            device.destroy_image_view(intermediate.image_view, None);
+            device.destroy_image(intermediate.image, None);
+            allocator.free(device, intermediate.memory);
+        }
+        match swapchain.offscreen_memory {
+            Some(memory) => {
+                device.destroy_image(swapchain.images[0], None);
+                allocator.free(device, memory);
+            }
+            None => loader.destroy_swapchain(**swapchain, None),
+        }
     }
+}
+
+pub fn destroy_swapchain(
+    instance: Res<VulkanInstance>,
+    device: Res<Device>,
+    allocator: Res<GpuAllocator>,
+    swapchain: Option<Res<Swapchain>>,
+    layouts: Res<ImageLayoutTracker>,
+    mut commands: Commands,
+) {
+    let Some(swapchain) = swapchain else {
+        return;
+    };
+
+    unsafe { destroy_swapchain_objects(&instance, &device, &allocator, &swapchain, &layouts) };
 
     commands.remove_resource::<Swapchain>();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_usage_falls_back_to_an_intermediate_image() {
+        let supported = vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_DST;
+
+        let usage = SwapchainUsage::resolve(supported, vk::ImageUsageFlags::TRANSFER_DST, false);
+        assert_eq!(usage.intermediate, None);
+        assert!(usage.swapchain.contains(vk::ImageUsageFlags::TRANSFER_DST));
+
+        // Scaled frames are copied into the swapchain even if it supports every usage
+        let usage = SwapchainUsage::resolve(supported, vk::ImageUsageFlags::empty(), true);
+        assert_eq!(usage.swapchain, supported);
+        assert!(usage.intermediate.is_some());
+
+        let usage = SwapchainUsage::resolve(supported, vk::ImageUsageFlags::STORAGE, false);
+        assert_eq!(usage.swapchain, supported);
+        assert!(usage.intermediate.unwrap().contains(
+            vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC
+        ));
+    }
+
+    #[test]
+    fn storage_requires_a_storage_format() {
+        let requested = vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC;
+        let color = vk::FormatFeatureFlags::COLOR_ATTACHMENT | vk::FormatFeatureFlags::TRANSFER_SRC;
+
+        assert_eq!(
+            usage_supported_by_format(requested, color),
+            vk::ImageUsageFlags::TRANSFER_SRC
+        );
+        assert_eq!(
+            usage_supported_by_format(requested, color | vk::FormatFeatureFlags::STORAGE_IMAGE),
+            requested
+        );
+    }
+}
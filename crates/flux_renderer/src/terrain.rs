@@ -0,0 +1,383 @@
+use crate::log_targets;
+use crate::mesh::{Mesh, MeshBuilder};
+use crate::permutations::MaterialId;
+use crate::vertex_layout::VertexLayout;
+use ash::vk;
+use flux_ecs::commands::{Command, CommandError, Commands};
+use flux_ecs::component::Component;
+use flux_ecs::entity::Entity;
+use flux_ecs::query::Query;
+use flux_ecs::resource::{Res, Resource};
+use flux_ecs::world::World;
+use log::debug;
+use std::collections::HashMap;
+
+/// A grid of heights, row by row along the z axis.
+#[derive(Clone, Debug)]
+pub struct Heightmap {
+    width: u32,
+    depth: u32,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    /// # Panics
+    /// Panics if the number of heights is not `width * depth` or the grid is smaller than 2x2.
+    pub fn new(width: u32, depth: u32, heights: Vec<f32>) -> Self {
+        assert!(
+            width >= 2 && depth >= 2,
+            "A heightmap needs at least 2x2 samples"
+        );
+        assert_eq!(
+            heights.len(),
+            width as usize * depth as usize,
+            "The number of heights does not match the size of the heightmap"
+        );
+        Self {
+            width,
+            depth,
+            heights,
+        }
+    }
+
+    pub fn from_fn(width: u32, depth: u32, height: impl Fn(u32, u32) -> f32) -> Self {
+        let heights = (0..depth)
+            .flat_map(|z| (0..width).map(move |x| (x, z)))
+            .map(|(x, z)| height(x, z))
+            .collect();
+        Self::new(width, depth, heights)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// The height at the sample, coordinates outside of the map are clamped to its edge.
+    pub fn height(&self, x: i64, z: i64) -> f32 {
+        let x = x.clamp(0, i64::from(self.width) - 1) as usize;
+        let z = z.clamp(0, i64::from(self.depth) - 1) as usize;
+        self.heights[z * self.width as usize + x]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerrainSettings {
+    /// The number of quads along each side of a chunk at LOD `0`, must be divisible by
+    /// `2^max_lod` so every LOD covers the chunk exactly.
+    pub chunk_size: u32,
+    /// The distance between two samples of the heightmap in world units.
+    pub horizontal_scale: f32,
+    /// Multiplied with the heights of the heightmap.
+    pub vertical_scale: f32,
+    /// The distances from the viewer at which the chunks switch to the next coarser LOD, each
+    /// LOD halves the samples along both axes.
+    pub lod_distances: Vec<f32>,
+    /// Chunks farther away from the viewer are not loaded.
+    pub view_distance: f32,
+    /// The chunk meshes generated per frame, for new chunks and LOD changes.
+    pub max_chunks_per_frame: usize,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            chunk_size: 64,
+            horizontal_scale: 1.0,
+            vertical_scale: 1.0,
+            lod_distances: vec![128.0, 256.0, 512.0],
+            view_distance: 1024.0,
+            max_chunks_per_frame: 4,
+        }
+    }
+}
+
+impl TerrainSettings {
+    pub fn max_lod(&self) -> u32 {
+        self.lod_distances.len() as u32
+    }
+
+    pub fn lod_for_distance(&self, distance: f32) -> u32 {
+        self.lod_distances
+            .iter()
+            .take_while(|&&lod_distance| distance >= lod_distance)
+            .count() as u32
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct ChunkCoord {
+    pub x: u32,
+    pub z: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TerrainVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+}
+
+impl TerrainVertex {
+    /// The position at location `0` and the normal at location `1`, as read by the
+    /// [`MaterialId::TERRAIN`] shaders.
+    pub fn layout() -> VertexLayout {
+        VertexLayout::interleaved(
+            size_of::<Self>() as u32,
+            [
+                (0, vk::Format::R32G32B32_SFLOAT, 0),
+                (1, vk::Format::R32G32B32_SFLOAT, 12),
+            ],
+        )
+    }
+}
+
+/// A heightmap terrain split into chunks, streamed and LOD'd around the [`TerrainViewer`].
+pub struct Terrain {
+    heightmap: Heightmap,
+    settings: TerrainSettings,
+}
+
+impl Resource for Terrain {}
+
+impl Terrain {
+    /// # Panics
+    /// Panics if the chunk size is zero or not divisible by `2^max_lod`.
+    pub fn new(heightmap: Heightmap, settings: TerrainSettings) -> Self {
+        assert!(
+            settings.chunk_size > 0 && settings.chunk_size.is_multiple_of(1 << settings.max_lod()),
+            "The chunk size must be divisible by 2^max_lod"
+        );
+        Self {
+            heightmap,
+            settings,
+        }
+    }
+
+    pub fn heightmap(&self) -> &Heightmap {
+        &self.heightmap
+    }
+
+    pub fn settings(&self) -> &TerrainSettings {
+        &self.settings
+    }
+
+    /// The number of chunks along the x and z axis.
+    pub fn chunk_count(&self) -> (u32, u32) {
+        let size = self.settings.chunk_size;
+        (
+            (self.heightmap.width - 1).div_ceil(size),
+            (self.heightmap.depth - 1).div_ceil(size),
+        )
+    }
+
+    /// The horizontal distance from `position` to the closest point of the chunk.
+    pub fn chunk_distance(&self, chunk: ChunkCoord, position: [f32; 3]) -> f32 {
+        let extent = self.settings.chunk_size as f32 * self.settings.horizontal_scale;
+        let distance_along = |coord: u32, position: f32| {
+            let min = coord as f32 * extent;
+            (min - position).max(position - (min + extent)).max(0.0)
+        };
+        distance_along(chunk.x, position[0]).hypot(distance_along(chunk.z, position[2]))
+    }
+
+    /// The chunks within the view distance of `position` with their LOD.
+    pub fn visible_chunks(&self, position: [f32; 3]) -> HashMap<ChunkCoord, u32> {
+        let (chunks_x, chunks_z) = self.chunk_count();
+        (0..chunks_z)
+            .flat_map(|z| (0..chunks_x).map(move |x| ChunkCoord { x, z }))
+            .filter_map(|chunk| {
+                let distance = self.chunk_distance(chunk, position);
+                (distance <= self.settings.view_distance)
+                    .then(|| (chunk, self.settings.lod_for_distance(distance)))
+            })
+            .collect()
+    }
+
+    /// Generates the mesh of a chunk, every LOD skips every other sample of the previous one.
+    pub fn generate_chunk(&self, chunk: ChunkCoord, lod: u32) -> Mesh {
+        let step = 1i64 << lod;
+        let quads = self.settings.chunk_size >> lod;
+        let origin_x = i64::from(chunk.x * self.settings.chunk_size);
+        let origin_z = i64::from(chunk.z * self.settings.chunk_size);
+        let horizontal = self.settings.horizontal_scale;
+        let vertical = self.settings.vertical_scale;
+
+        let mut builder = MeshBuilder::new(TerrainVertex::layout());
+        for z in 0..=i64::from(quads) {
+            for x in 0..=i64::from(quads) {
+                let (sample_x, sample_z) = (origin_x + x * step, origin_z + z * step);
+                let height = |dx, dz| self.heightmap.height(sample_x + dx, sample_z + dz);
+
+                let slope_x = (height(1, 0) - height(-1, 0)) * vertical / (2.0 * horizontal);
+                let slope_z = (height(0, 1) - height(0, -1)) * vertical / (2.0 * horizontal);
+                let length = (slope_x * slope_x + 1.0 + slope_z * slope_z).sqrt();
+
+                builder.vertex(TerrainVertex {
+                    position: [
+                        sample_x as f32 * horizontal,
+                        height(0, 0) * vertical,
+                        sample_z as f32 * horizontal,
+                    ],
+                    normal: [-slope_x / length, 1.0 / length, -slope_z / length],
+                });
+            }
+        }
+
+        let row = quads + 1;
+        for z in 0..quads {
+            for x in 0..quads {
+                let index = z * row + x;
+                builder.quad(index, index + row, index + row + 1, index + 1);
+            }
+        }
+
+        builder.build()
+    }
+}
+
+/// The position the terrain is streamed around, set every frame from the camera.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TerrainViewer {
+    pub position: [f32; 3],
+}
+
+impl Resource for TerrainViewer {}
+
+/// A chunk of the [`Terrain`], its [`Mesh`] is on the same entity and drawn with the
+/// [`MaterialId::TERRAIN`] material.
+///
+/// Chunks that leave the view distance keep their entity with an empty mesh and are reused for
+/// the next chunk that comes into view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TerrainChunk {
+    /// The chunk shown by the entity, `None` while the entity is unused.
+    pub coord: Option<ChunkCoord>,
+    pub lod: u32,
+}
+
+impl Component for TerrainChunk {}
+
+struct SpawnTerrainChunk {
+    chunk: TerrainChunk,
+    mesh: Mesh,
+}
+
+impl Command for SpawnTerrainChunk {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        world.spawn((self.chunk, self.mesh, MaterialId::TERRAIN));
+        Ok(())
+    }
+}
+
+/// Loads the chunks that came into view, updates the LOD of the loaded chunks and unloads the
+/// chunks out of view. At most [`TerrainSettings::max_chunks_per_frame`] meshes are generated
+/// per frame, the closest chunks first.
+pub fn stream_terrain(
+    terrain: Option<Res<Terrain>>,
+    viewer: Option<Res<TerrainViewer>>,
+    chunks: Query<(Entity, &mut TerrainChunk, &mut Mesh)>,
+    mut commands: Commands,
+) {
+    let (Some(terrain), Some(viewer)) = (terrain, viewer) else {
+        return;
+    };
+
+    let mut visible = terrain.visible_chunks(viewer.position);
+    let mut budget = terrain.settings.max_chunks_per_frame;
+    let mut unused = Vec::new();
+
+    for (entity, chunk, mesh) in chunks {
+        match chunk.coord.map(|coord| (coord, visible.remove(&coord))) {
+            Some((coord, Some(lod))) => {
+                if lod != chunk.lod && budget > 0 {
+                    *mesh = terrain.generate_chunk(coord, lod);
+                    chunk.lod = lod;
+                    budget -= 1;
+                }
+            }
+            Some((coord, None)) => {
+                debug!(target: log_targets::RESOURCES, "Unloading terrain chunk {coord:?}");
+                *mesh = Mesh::new(TerrainVertex::layout());
+                chunk.coord = None;
+                unused.push((entity, chunk, mesh));
+            }
+            None => unused.push((entity, chunk, mesh)),
+        }
+    }
+
+    let mut pending: Vec<_> = visible.into_iter().collect();
+    pending.sort_by(|(a, _), (b, _)| {
+        let distance = |chunk| terrain.chunk_distance(chunk, viewer.position);
+        distance(*a).total_cmp(&distance(*b)).then(a.cmp(b))
+    });
+
+    for (coord, lod) in pending.into_iter().take(budget) {
+        debug!(target: log_targets::RESOURCES, "Loading terrain chunk {coord:?} at LOD {lod}");
+        let mesh = terrain.generate_chunk(coord, lod);
+        let loaded = TerrainChunk {
+            coord: Some(coord),
+            lod,
+        };
+        match unused.pop() {
+            Some((_, chunk, chunk_mesh)) => {
+                *chunk = loaded;
+                *chunk_mesh = mesh;
+            }
+            None => commands.push(SpawnTerrainChunk {
+                chunk: loaded,
+                mesh,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terrain() -> Terrain {
+        let heightmap = Heightmap::from_fn(129, 65, |x, _| x as f32);
+        let settings = TerrainSettings {
+            chunk_size: 32,
+            lod_distances: vec![16.0, 48.0],
+            view_distance: 80.0,
+            ..Default::default()
+        };
+        Terrain::new(heightmap, settings)
+    }
+
+    #[test]
+    fn chunks_are_selected_and_meshed_by_distance() {
+        let terrain = terrain();
+        assert_eq!(terrain.chunk_count(), (4, 2));
+
+        let visible = terrain.visible_chunks([10.0, 0.0, 10.0]);
+        assert_eq!(visible[&ChunkCoord { x: 0, z: 0 }], 0);
+        assert_eq!(visible[&ChunkCoord { x: 1, z: 1 }], 1);
+        assert_eq!(visible[&ChunkCoord { x: 2, z: 0 }], 2);
+        assert!(!visible.contains_key(&ChunkCoord { x: 3, z: 1 }));
+
+        let full = terrain.generate_chunk(ChunkCoord { x: 1, z: 0 }, 0);
+        assert_eq!(full.vertex_count(), 33 * 33);
+        assert_eq!(full.indices().len(), 32 * 32 * 6);
+
+        let coarse = terrain.generate_chunk(ChunkCoord { x: 1, z: 0 }, 2);
+        assert_eq!(coarse.vertex_count(), 9 * 9);
+        // Replacing the mesh of a chunk on a LOD change uploads the new mesh
+        assert_ne!(coarse.revision(), full.revision());
+        let vertex = unsafe {
+            coarse
+                .vertex_bytes()
+                .as_ptr()
+                .cast::<TerrainVertex>()
+                .read_unaligned()
+        };
+        assert_eq!(vertex.position, [32.0, 32.0, 0.0]);
+        let slope = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((vertex.normal[0] + slope).abs() < 1e-6);
+    }
+}
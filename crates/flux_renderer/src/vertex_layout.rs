@@ -0,0 +1,204 @@
+use crate::device::Device;
+use ash::vk;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VertexAttribute {
+    pub location: u32,
+    /// The vertex buffer binding the attribute is read from.
+    pub binding: u32,
+    pub format: vk::Format,
+    /// The offset within an element of the binding.
+    pub offset: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VertexBinding {
+    pub binding: u32,
+    pub stride: u32,
+}
+
+/// The vertex buffers a permutation reads from and how its attributes are laid out in them.
+///
+/// Meshes either interleave all attributes in a single buffer or store each attribute in its own
+/// buffer (structure of arrays), as some importers and procedural generators produce. Separate
+/// streams also let depth and shadow passes bind only the positions, see [`VertexLayout::only`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VertexLayout {
+    pub bindings: Vec<VertexBinding>,
+    pub attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    /// All attributes in a single buffer at binding `0`, given as `(location, format, offset)`.
+    pub fn interleaved(
+        stride: u32,
+        attributes: impl IntoIterator<Item = (u32, vk::Format, u32)>,
+    ) -> Self {
+        Self {
+            bindings: vec![VertexBinding { binding: 0, stride }],
+            attributes: attributes
+                .into_iter()
+                .map(|(location, format, offset)| VertexAttribute {
+                    location,
+                    binding: 0,
+                    format,
+                    offset,
+                })
+                .collect(),
+        }
+    }
+
+    /// Every attribute in its own tightly packed buffer, bound in the given order starting at
+    /// binding `0`.
+    ///
+    /// # Panics
+    /// Panics if the size of a format is unknown, see [`format_size`].
+    pub fn deinterleaved(attributes: impl IntoIterator<Item = (u32, vk::Format)>) -> Self {
+        let (bindings, attributes) = attributes
+            .into_iter()
+            .zip(0..)
+            .map(|((location, format), binding)| {
+                let stride = format_size(format)
+                    .unwrap_or_else(|| panic!("Unknown size of vertex format {format:?}"));
+                let attribute = VertexAttribute {
+                    location,
+                    binding,
+                    format,
+                    offset: 0,
+                };
+                (VertexBinding { binding, stride }, attribute)
+            })
+            .unzip();
+
+        Self {
+            bindings,
+            attributes,
+        }
+    }
+
+    /// The layout reading only the attributes at `locations`, e.g. the positions for a depth
+    /// pass. Bindings without any of the attributes are dropped, the remaining bindings keep
+    /// their numbers.
+    pub fn only(&self, locations: &[u32]) -> Self {
+        let attributes: Vec<_> = self
+            .attributes
+            .iter()
+            .filter(|attribute| locations.contains(&attribute.location))
+            .copied()
+            .collect();
+        let bindings = self
+            .bindings
+            .iter()
+            .filter(|binding| {
+                attributes
+                    .iter()
+                    .any(|attribute| attribute.binding == binding.binding)
+            })
+            .copied()
+            .collect();
+
+        Self {
+            bindings,
+            attributes,
+        }
+    }
+
+    pub fn binding_descriptions(&self) -> Vec<vk::VertexInputBindingDescription> {
+        self.bindings
+            .iter()
+            .map(|binding| {
+                vk::VertexInputBindingDescription::default()
+                    .binding(binding.binding)
+                    .stride(binding.stride)
+                    .input_rate(vk::VertexInputRate::VERTEX)
+            })
+            .collect()
+    }
+
+    pub fn attribute_descriptions(&self) -> Vec<vk::VertexInputAttributeDescription> {
+        self.attributes
+            .iter()
+            .map(|attribute| {
+                vk::VertexInputAttributeDescription::default()
+                    .binding(attribute.binding)
+                    .location(attribute.location)
+                    .format(attribute.format)
+                    .offset(attribute.offset)
+            })
+            .collect()
+    }
+}
+
+/// The size in bytes of the common vertex attribute formats.
+pub fn format_size(format: vk::Format) -> Option<u32> {
+    let size = match format {
+        vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SNORM | vk::Format::R8G8B8A8_UINT => 4,
+        vk::Format::R16G16_SFLOAT | vk::Format::R16G16_UNORM => 4,
+        vk::Format::R16G16B16A16_SFLOAT | vk::Format::R16G16B16A16_UNORM => 8,
+        vk::Format::R32_SFLOAT | vk::Format::R32_UINT | vk::Format::R32_SINT => 4,
+        vk::Format::R32G32_SFLOAT | vk::Format::R32G32_UINT => 8,
+        vk::Format::R32G32B32_SFLOAT | vk::Format::R32G32B32_UINT => 12,
+        vk::Format::R32G32B32A32_SFLOAT | vk::Format::R32G32B32A32_UINT => 16,
+        _ => return None,
+    };
+    Some(size)
+}
+
+/// The buffers of a mesh, one per binding of its [`VertexLayout`] in binding order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VertexStreams {
+    pub buffers: Vec<vk::Buffer>,
+    pub offsets: Vec<vk::DeviceSize>,
+}
+
+impl VertexStreams {
+    pub fn push(&mut self, buffer: vk::Buffer, offset: vk::DeviceSize) {
+        self.buffers.push(buffer);
+        self.offsets.push(offset);
+    }
+
+    /// Binds all streams starting at binding `0`.
+    ///
+    /// # Safety
+    /// The command buffer must be recording and the buffers must be valid vertex buffers.
+    pub unsafe fn bind(&self, device: &Device, command_buffer: vk::CommandBuffer) {
+        unsafe { device.cmd_bind_vertex_buffers(command_buffer, 0, &self.buffers, &self.offsets) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deinterleaved_attributes_get_their_own_bindings() {
+        let layout = VertexLayout::deinterleaved([
+            (0, vk::Format::R32G32B32_SFLOAT),
+            (1, vk::Format::R32G32B32_SFLOAT),
+            (2, vk::Format::R32G32_SFLOAT),
+        ]);
+
+        assert_eq!(
+            layout.bindings,
+            [
+                VertexBinding {
+                    binding: 0,
+                    stride: 12
+                },
+                VertexBinding {
+                    binding: 1,
+                    stride: 12
+                },
+                VertexBinding {
+                    binding: 2,
+                    stride: 8
+                },
+            ]
+        );
+        assert_eq!(layout.attributes[2].binding, 2);
+
+        let positions = layout.only(&[0]);
+        assert_eq!(positions.bindings.len(), 1);
+        assert_eq!(positions.attribute_descriptions().len(), 1);
+    }
+}
@@ -0,0 +1,368 @@
+use crate::input::{end_input_frame, feed_device_event, feed_window_event};
+use crate::instance::{RendererSettings, SurfaceProvider, SurfaceProviderResource};
+use crate::log_targets;
+use crate::progress::InitializationProgress;
+use crate::recreate::{RendererSchedule, SwapchainRecreation, run_renderer_schedule};
+use crate::surface::{VulkanSurface, handle_surface_lifecycle};
+use crate::window_targets::WindowTargets;
+use flux_ecs::app::{App, AppExit, FramePacing};
+use flux_ecs::commands::Commands;
+use flux_ecs::component::Component;
+use flux_ecs::entity::Entity;
+use flux_ecs::resource::{NonSend, NonSendResource, Res, Resource};
+use log::{debug, error, info};
+use raw_window_handle::{HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle};
+use std::collections::HashMap;
+use std::time::Instant;
+use thiserror::Error;
+use winit::application::ApplicationHandler;
+use winit::dpi::LogicalSize;
+use winit::error::{EventLoopError, OsError};
+use winit::event::{DeviceEvent, DeviceId, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::window::{Window as WinitWindow, WindowId};
+
+/// Describes the window created by the renderer.
+///
+/// Insert it before running the `Initialization` schedule, the window is created by
+/// [`create_window`] as the first renderer system.
+#[derive(Debug, Clone)]
+pub struct WindowDescriptor {
+    /// The window title, defaults to the application name of the [`RendererSettings`].
+    pub title: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub resizable: bool,
+    /// Hidden windows are still rendered to, e.g. for automated tests.
+    pub visible: bool,
+}
+
+impl Default for WindowDescriptor {
+    fn default() -> Self {
+        Self {
+            title: None,
+            width: 1280,
+            height: 720,
+            resizable: true,
+            visible: true,
+        }
+    }
+}
+
+impl Resource for WindowDescriptor {}
+
+/// Renders into an offscreen image of the given size instead of a window, e.g. in CI and tools.
+///
+/// Insert it before running the `Initialization` schedule: no window is created, no
+/// [`SurfaceProviderResource`] is needed and frames are never presented. The last frame can be
+/// read with [`read_back_framebuffer`](crate::read_back_framebuffer).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Headless {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Resource for Headless {}
+
+/// An additional window, spawn an entity with it to open the window at runtime.
+///
+/// The [`winit_runner`] opens the native window and inserts its [`NativeWindow`], the renderer
+/// then creates a surface and swapchain for it and renders every frame into it. Closing the
+/// window despawns the entity, despawning the entity closes the window.
+#[derive(Debug, Clone, Default)]
+pub struct Window {
+    pub descriptor: WindowDescriptor,
+}
+
+impl Component for Window {}
+
+/// The native window of a [`Window`] entity.
+///
+/// Applications with their own windowing insert it themselves instead of using the
+/// [`winit_runner`].
+pub struct NativeWindow {
+    pub provider: Box<dyn SurfaceProvider>,
+}
+
+impl Component for NativeWindow {}
+
+#[derive(Error, Debug)]
+pub enum WindowError {
+    #[error("could not create the event loop: {0}")]
+    EventLoop(#[from] EventLoopError),
+    #[error("could not create the window: {0}")]
+    Window(#[from] OsError),
+}
+
+/// Keeps the event loop of the renderer window alive.
+pub struct WinitEventLoop {
+    pub event_loop: EventLoop<()>,
+    /// The window created from the [`WindowDescriptor`], closing it exits the app.
+    pub primary: WindowId,
+}
+
+impl NonSendResource for WinitEventLoop {}
+
+struct WinitSurfaceProvider {
+    window: WinitWindow,
+}
+
+impl SurfaceProvider for WinitSurfaceProvider {
+    fn get_display_handle(&self) -> Option<RawDisplayHandle> {
+        self.window
+            .display_handle()
+            .ok()
+            .map(|handle| handle.as_raw())
+    }
+
+    /// Winit reports the window handle as unavailable while an Android app is suspended.
+    fn get_window_handle(&self) -> Option<RawWindowHandle> {
+        self.window
+            .window_handle()
+            .ok()
+            .map(|handle| handle.as_raw())
+    }
+
+    fn get_extent(&self) -> (u32, u32) {
+        let size = self.window.inner_size();
+        (size.width, size.height)
+    }
+}
+
+/// Creates the window unless the application already provided a [`SurfaceProviderResource`],
+/// e.g. a [`NullSurfaceProvider`](crate::instance::NullSurfaceProvider), or renders [`Headless`].
+pub fn create_window(
+    window_descriptor: Option<Res<WindowDescriptor>>,
+    renderer_settings: Option<Res<RendererSettings>>,
+    (surface_provider, headless): (Option<Res<SurfaceProviderResource>>, Option<Res<Headless>>),
+    mut commands: Commands,
+) -> Result<(), WindowError> {
+    if surface_provider.is_some() {
+        info!(target: log_targets::SURFACE, "Using the application provided surface provider");
+        return Ok(());
+    }
+    if headless.is_some() {
+        info!(target: log_targets::SURFACE, "Rendering headless, no window is created");
+        return Ok(());
+    }
+
+    let descriptor = window_descriptor
+        .map(|res| res.into_inner())
+        .unwrap_or_default();
+
+    let title = descriptor.title.unwrap_or_else(|| {
+        renderer_settings.map_or_else(
+            || "Flux Engine".to_string(),
+            |settings| settings.app_name.trim_end_matches('\0').to_string(),
+        )
+    });
+
+    info!(
+        target: log_targets::SURFACE,
+        "Creating window '{title}' ({}x{})",
+        descriptor.width,
+        descriptor.height
+    );
+
+    let event_loop = EventLoop::new()?;
+    let attributes = WinitWindow::default_attributes()
+        .with_title(title)
+        .with_inner_size(LogicalSize::new(descriptor.width, descriptor.height))
+        .with_resizable(descriptor.resizable)
+        .with_visible(descriptor.visible);
+    let window = event_loop.create_window(attributes)?;
+    let id = window.id();
+
+    commands.insert_resource(SurfaceProviderResource {
+        provider: Box::new(WinitSurfaceProvider { window }),
+    });
+    commands.insert_non_send_resource(WinitEventLoop { event_loop, primary: id });
+    commands.send_event(InitializationProgress::WindowCreated);
+
+    Ok(())
+}
+
+pub fn destroy_window(event_loop: Option<NonSend<WinitEventLoop>>, mut commands: Commands) {
+    info!(target: log_targets::SURFACE, "Destroying window");
+
+    commands.remove_resource::<SurfaceProviderResource>();
+    if event_loop.is_some() {
+        commands.remove_non_send_resource::<WinitEventLoop>();
+    }
+}
+
+/// Drives the app from the winit event loop, installed by the
+/// [`RendererPlugin`](crate::RendererPlugin).
+///
+/// After initialization the event loop is taken out of the world and the app is updated whenever
+/// the loop is about to wait for new events. Closing the window sends an [`AppExit`]. Without a
+/// window, e.g. with a headless surface provider, this falls back to [`App::run_frames`].
+///
+/// Keyboard, mouse and, with the `gamepad` feature, gamepad events received since the last frame
+/// are fed into the resources of the [`InputPlugin`](flux_input::InputPlugin) before each update.
+pub fn winit_runner(mut app: App) {
+    if app.initialize().is_err() {
+        app.shutdown();
+        return;
+    }
+
+    match app.world_mut().remove_non_send_resource::<WinitEventLoop>() {
+        Some(WinitEventLoop {
+            event_loop,
+            primary,
+        }) => {
+            let mut handler = WinitApp {
+                app: &mut app,
+                primary,
+                windows: HashMap::new(),
+                #[cfg(feature = "gamepad")]
+                gamepads: crate::input::Gamepads::new(),
+            };
+            if let Err(err) = event_loop.run_app(&mut handler) {
+                error!(target: log_targets::SURFACE, "The event loop failed: {err}");
+            }
+        }
+        None => app.run_frames(),
+    }
+
+    app.shutdown();
+}
+
+struct WinitApp<'a> {
+    app: &'a mut App,
+    primary: WindowId,
+    /// The entities of the opened [`Window`]s.
+    windows: HashMap<WindowId, Entity>,
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<crate::input::Gamepads>,
+}
+
+impl WinitApp<'_> {
+    /// Opens the native windows of new [`Window`] entities and forgets the ones of despawned
+    /// entities, dropping a winit window closes it.
+    fn sync_windows(&mut self, event_loop: &ActiveEventLoop) {
+        let world = self.app.world_mut();
+        self.windows.retain(|_, entity| world.is_alive(*entity));
+
+        let pending: Vec<_> = world
+            .entities()
+            .filter(|&entity| world.get::<NativeWindow>(entity).is_none())
+            .filter_map(|entity| Some((entity, world.get::<Window>(entity)?.descriptor.clone())))
+            .collect();
+        for (entity, descriptor) in pending {
+            let title = descriptor.title.unwrap_or_else(|| "Flux Engine".to_string());
+            info!(target: log_targets::SURFACE, "Opening window '{title}' of entity {entity}");
+
+            let attributes = WinitWindow::default_attributes()
+                .with_title(title)
+                .with_inner_size(LogicalSize::new(descriptor.width, descriptor.height))
+                .with_resizable(descriptor.resizable)
+                .with_visible(descriptor.visible);
+            match event_loop.create_window(attributes) {
+                Ok(window) => {
+                    self.windows.insert(window.id(), entity);
+                    world.insert_component(
+                        entity,
+                        NativeWindow {
+                            provider: Box::new(WinitSurfaceProvider { window }),
+                        },
+                    );
+                }
+                Err(err) => {
+                    error!(target: log_targets::SURFACE, "Could not open the window: {err}");
+                    world.remove_component::<Window>(entity);
+                }
+            }
+        }
+    }
+}
+
+impl ApplicationHandler for WinitApp<'_> {
+    /// Android only provides the native window between `resumed` and `suspended`, the surface is
+    /// created once it is available. Does nothing if the surface exists already.
+    fn resumed(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Err(err) = self
+            .app
+            .world_mut()
+            .run_system_once(handle_surface_lifecycle)
+        {
+            error!(target: log_targets::SURFACE, "Could not create the surface: {err}");
+        }
+    }
+
+    /// Destroys the surface before the native window is gone.
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        let world = self.app.world_mut();
+        if world.get_resource::<VulkanSurface>().is_none() {
+            return;
+        }
+
+        info!(target: log_targets::SURFACE, "Suspended, destroying the surface");
+        run_renderer_schedule(world, RendererSchedule::ReleaseSurface);
+    }
+
+    fn window_event(&mut self, _event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        feed_window_event(self.app.world_mut(), &event);
+        match event {
+            WindowEvent::CloseRequested if id == self.primary => {
+                info!(target: log_targets::SURFACE, "Window close requested");
+                self.app.world_mut().send_event(AppExit);
+            }
+            WindowEvent::CloseRequested => {
+                if let Some(entity) = self.windows.remove(&id) {
+                    info!(target: log_targets::SURFACE, "Closing the window of entity {entity}");
+                    self.app.world_mut().despawn(entity);
+                }
+            }
+            // Frames are skipped until the swapchain matches the new size
+            WindowEvent::Resized(size) if id == self.primary => {
+                debug!(
+                    target: log_targets::SURFACE,
+                    "Window resized to {}x{}",
+                    size.width,
+                    size.height
+                );
+                if let Some(recreation) = self.app.world().get_resource::<SwapchainRecreation>() {
+                    recreation.request();
+                }
+            }
+            WindowEvent::Resized(_) => {
+                let world = self.app.world();
+                if let (Some(&entity), Some(targets)) =
+                    (self.windows.get(&id), world.get_resource::<WindowTargets>())
+                {
+                    targets.request_recreation(entity);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn device_event(&mut self, _event_loop: &ActiveEventLoop, _id: DeviceId, event: DeviceEvent) {
+        feed_device_event(self.app.world_mut(), &event);
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let frame_start = Instant::now();
+        self.sync_windows(event_loop);
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepads) = &mut self.gamepads {
+            gamepads.feed(self.app.world_mut());
+        }
+        self.app.update_logged();
+        end_input_frame(self.app.world_mut());
+
+        if self.app.should_exit() {
+            event_loop.exit();
+            return;
+        }
+
+        let pacing = self.app.world().get_resource::<FramePacing>().copied();
+        match pacing.and_then(|pacing| pacing.target_frame_time) {
+            Some(target) => {
+                event_loop.set_control_flow(ControlFlow::WaitUntil(frame_start + target));
+            }
+            None => event_loop.set_control_flow(ControlFlow::Poll),
+        }
+    }
+}
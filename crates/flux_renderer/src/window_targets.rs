@@ -0,0 +1,549 @@
+use crate::allocator::GpuAllocator;
+use crate::command_buffer::FrameRecorder;
+use crate::command_pool::CommandPools;
+use crate::config::GraphicsSettings;
+use crate::damage::PresentDamage;
+use crate::depth_buffers::{DepthBuffers, build_depth_buffers, destroy_depth_buffer_objects};
+use crate::descriptors::Descriptors;
+use crate::device::{Device, PhysicalDevice, query_swapchain_support};
+use crate::instance::VulkanInstance;
+use crate::layout_tracker::ImageLayoutTracker;
+use crate::log_targets;
+use crate::mesh::GpuMeshes;
+use crate::permutations::PipelinePermutations;
+use crate::pipeline::Pipeline;
+use crate::raw::{RawVulkan, RawVulkanHooks};
+use crate::render_path::{ClassicRenderPass, build_framebuffers, destroy_framebuffer_objects};
+use crate::scratch::FrameScratch;
+use crate::shutdown::InFlightWork;
+use crate::stats::RenderStats;
+use crate::surface::build_surface;
+use crate::swapchain::{Swapchain, build_swapchain, destroy_swapchain_objects};
+use crate::window::NativeWindow;
+use ash::khr::surface;
+use ash::vk;
+use flux_ecs::entity::Entity;
+use flux_ecs::query::Query;
+use flux_ecs::resource::{NonSend, Res, Resource};
+use log::{debug, error, info, warn};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// The surface, swapchain and frame objects of an additional window. Unlike the main window it
+/// has a single frame in flight.
+struct WindowTarget {
+    surface: vk::SurfaceKHR,
+    swapchain: Swapchain,
+    depth_buffers: DepthBuffers,
+    command_buffer: vk::CommandBuffer,
+    image_available: vk::Semaphore,
+    /// One per swapchain image, presentation may still wait on it after the next frame started.
+    render_finished: Vec<vk::Semaphore>,
+    /// Signaled once the last frame of the window finished, created signaled.
+    fence: vk::Fence,
+    damage: PresentDamage,
+    /// The main render pass with framebuffers for the images of this window, on the
+    /// [`RenderPath::RenderPass`](crate::RenderPath::RenderPass) fallback.
+    render_pass: Option<ClassicRenderPass>,
+    /// Set when the swapchain no longer matches the window, the target is rebuilt by
+    /// [`sync_window_targets`] and not rendered to until then.
+    outdated: bool,
+}
+
+#[derive(Default)]
+struct TargetsState {
+    targets: HashMap<Entity, WindowTarget>,
+    /// Windows whose surface can not be rendered to, they are not retried.
+    unsupported: HashSet<Entity>,
+}
+
+/// The render targets of the [`Window`](crate::window::Window) entities, the main window is
+/// rendered by the frame driver itself.
+#[derive(Default)]
+pub struct WindowTargets {
+    state: Mutex<TargetsState>,
+}
+
+impl Resource for WindowTargets {}
+
+impl WindowTargets {
+    fn state(&self) -> MutexGuard<'_, TargetsState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The number of windows rendered besides the main window.
+    pub fn len(&self) -> usize {
+        self.state().targets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state().targets.is_empty()
+    }
+
+    pub fn contains(&self, window: Entity) -> bool {
+        self.state().targets.contains_key(&window)
+    }
+
+    /// Rebuilds the target of a window before its next frame, e.g. after it was resized.
+    pub fn request_recreation(&self, window: Entity) {
+        if let Some(target) = self.state().targets.get_mut(&window) {
+            target.outdated = true;
+        }
+    }
+}
+
+/// The device objects needed to build and destroy window targets.
+struct TargetContext<'a> {
+    instance: &'a VulkanInstance,
+    physical_device: &'a PhysicalDevice,
+    device: &'a Device,
+    allocator: &'a GpuAllocator,
+    layouts: &'a ImageLayoutTracker,
+    command_pools: &'a CommandPools,
+}
+
+impl TargetContext<'_> {
+    /// Builds the target of a window, `None` if the surface can not be rendered to with the main
+    /// pipeline.
+    fn build(
+        &self,
+        provider: &NativeWindow,
+        main_format: Option<vk::Format>,
+        render_pass: Option<vk::RenderPass>,
+        settings: &GraphicsSettings,
+    ) -> Result<Option<WindowTarget>, vk::Result> {
+        let Some(surface) = build_surface(self.instance, &*provider.provider)? else {
+            return Ok(None);
+        };
+        let surface_loader = surface::Instance::new(&self.instance.entry, self.instance);
+        let destroy_surface = || unsafe { surface_loader.destroy_surface(surface, None) };
+
+        let presentable = unsafe {
+            surface_loader.get_physical_device_surface_support(
+                **self.physical_device,
+                self.physical_device.indices.present,
+                surface,
+            )
+        };
+        let support = match presentable {
+            Ok(true) => query_swapchain_support(
+                &self.instance.entry,
+                self.instance,
+                **self.physical_device,
+                surface,
+            )
+            .ok(),
+            _ => None,
+        };
+        let Some(support) = support else {
+            warn!(target: log_targets::SURFACE, "The window can not be presented to");
+            destroy_surface();
+            return Ok(None);
+        };
+
+        let swapchain = match build_swapchain(
+            self.instance,
+            self.physical_device,
+            self.device,
+            self.allocator,
+            surface,
+            &support,
+            provider.provider.get_extent(),
+            settings,
+            vk::SwapchainKHR::null(),
+        ) {
+            Ok(swapchain) => swapchain,
+            Err(err) => {
+                destroy_surface();
+                return Err(err);
+            }
+        };
+        if main_format.is_some_and(|format| format != swapchain.format.format) {
+            warn!(
+                target: log_targets::SURFACE,
+                "The window uses {:?}, the main pipeline renders {main_format:?}",
+                swapchain.format.format
+            );
+            unsafe {
+                destroy_swapchain_objects(
+                    self.instance,
+                    self.device,
+                    self.allocator,
+                    &swapchain,
+                    self.layouts,
+                );
+            }
+            destroy_surface();
+            return Ok(None);
+        }
+        swapchain.track_layouts(self.layouts);
+
+        let depth_buffers = build_depth_buffers(
+            self.instance,
+            self.physical_device,
+            self.device,
+            self.allocator,
+            swapchain.render_extent,
+            self.layouts,
+        )?;
+        let render_pass = render_pass
+            .map(|render_pass| {
+                let framebuffers =
+                    build_framebuffers(self.device, render_pass, &swapchain, &depth_buffers)?;
+                Ok(ClassicRenderPass {
+                    render_pass,
+                    framebuffers,
+                })
+            })
+            .transpose()?;
+
+        let allocate_info = vk::CommandBufferAllocateInfo::default()
+            .command_pool(self.command_pools.graphics)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        let semaphore_info = vk::SemaphoreCreateInfo::default();
+        let fence_info = vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED);
+        let device = self.device;
+
+        let target = unsafe {
+            WindowTarget {
+                surface,
+                command_buffer: device.allocate_command_buffers(&allocate_info)?[0],
+                image_available: device.create_semaphore(&semaphore_info, None)?,
+                render_finished: swapchain
+                    .images
+                    .iter()
+                    .map(|_| device.create_semaphore(&semaphore_info, None))
+                    .collect::<Result<_, _>>()?,
+                fence: device.create_fence(&fence_info, None)?,
+                swapchain,
+                depth_buffers,
+                damage: PresentDamage::default(),
+                render_pass,
+                outdated: false,
+            }
+        };
+
+        Ok(Some(target))
+    }
+
+    /// # Safety
+    /// The last frame of the window must have finished.
+    unsafe fn destroy(&self, target: WindowTarget) {
+        let device = self.device;
+        unsafe {
+            device.free_command_buffers(self.command_pools.graphics, &[target.command_buffer]);
+            device.destroy_semaphore(target.image_available, None);
+            for &semaphore in &target.render_finished {
+                device.destroy_semaphore(semaphore, None);
+            }
+            device.destroy_fence(target.fence, None);
+            // The render pass itself is shared with the main window
+            if let Some(render_pass) = &target.render_pass {
+                destroy_framebuffer_objects(device, &render_pass.framebuffers);
+            }
+            destroy_depth_buffer_objects(
+                device,
+                self.allocator,
+                &target.depth_buffers,
+                self.layouts,
+            );
+            destroy_swapchain_objects(
+                self.instance,
+                device,
+                self.allocator,
+                &target.swapchain,
+                self.layouts,
+            );
+
+            let surface_loader = surface::Instance::new(&self.instance.entry, self.instance);
+            surface_loader.destroy_surface(target.surface, None);
+        }
+    }
+}
+
+/// Creates the targets of newly opened windows, rebuilds outdated ones and destroys the ones of
+/// closed windows.
+pub fn sync_window_targets(
+    (instance, physical_device, device): (Res<VulkanInstance>, Res<PhysicalDevice>, Res<Device>),
+    (allocator, layouts, command_pools): (
+        Res<GpuAllocator>,
+        Res<ImageLayoutTracker>,
+        Option<Res<CommandPools>>,
+    ),
+    (targets, settings): (Res<WindowTargets>, Option<Res<GraphicsSettings>>),
+    (main_swapchain, main_render_pass): (Option<Res<Swapchain>>, Option<Res<ClassicRenderPass>>),
+    windows: Query<(Entity, &NativeWindow)>,
+) -> Result<(), vk::Result> {
+    let Some(command_pools) = command_pools else {
+        return Ok(());
+    };
+    let context = TargetContext {
+        instance: &instance,
+        physical_device: &physical_device,
+        device: &device,
+        allocator: &allocator,
+        layouts: &layouts,
+        command_pools: &command_pools,
+    };
+
+    let windows: HashMap<_, _> = windows.into_iter().collect();
+    let mut state = targets.state();
+    state.unsupported.retain(|entity| windows.contains_key(entity));
+
+    let closed: Vec<_> = state
+        .targets
+        .keys()
+        .filter(|entity| !windows.contains_key(entity))
+        .copied()
+        .collect();
+    for entity in closed {
+        let target = state.targets.remove(&entity).unwrap();
+        debug!(target: log_targets::SURFACE, "Destroying the target of window {entity}");
+        unsafe {
+            device.wait_for_fences(&[target.fence], true, u64::MAX)?;
+            context.destroy(target);
+        }
+    }
+
+    // Minimized windows keep their outdated target until they have a size again
+    let outdated: Vec<_> = state
+        .targets
+        .iter()
+        .filter(|(entity, target)| {
+            let (width, height) = windows[*entity].provider.get_extent();
+            target.outdated && width > 0 && height > 0
+        })
+        .map(|(&entity, _)| entity)
+        .collect();
+    if !outdated.is_empty() {
+        // Presentation may still wait on the semaphores of the outdated targets
+        unsafe { device.device_wait_idle()? };
+    }
+    for entity in outdated {
+        debug!(target: log_targets::SURFACE, "Recreating the target of window {entity}");
+        let target = state.targets.remove(&entity).unwrap();
+        unsafe { context.destroy(target) };
+    }
+
+    let settings = settings.as_deref().cloned().unwrap_or_default();
+    let main_format = main_swapchain.map(|swapchain| swapchain.format.format);
+    let render_pass = main_render_pass.map(|render_pass| render_pass.render_pass);
+    for (&entity, window) in &windows {
+        if state.targets.contains_key(&entity) || state.unsupported.contains(&entity) {
+            continue;
+        }
+        match context.build(window, main_format, render_pass, &settings) {
+            Ok(Some(target)) => {
+                info!(target: log_targets::SURFACE, "Rendering to the window of entity {entity}");
+                state.targets.insert(entity, target);
+            }
+            Ok(None) => {
+                state.unsupported.insert(entity);
+            }
+            Err(err) => {
+                error!(
+                    target: log_targets::SURFACE,
+                    "Could not create the target of window {entity}: {err}"
+                );
+                state.unsupported.insert(entity);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The resources the main pass draws with, the depth buffers and framebuffers are per window.
+type WindowSceneResources<'w> = (
+    Option<Res<'w, Pipeline>>,
+    Option<Res<'w, Descriptors>>,
+    Res<'w, GpuMeshes>,
+    Res<'w, PipelinePermutations>,
+);
+
+/// The resources shared by the frames of all windows.
+struct WindowFrame<'a> {
+    instance: &'a VulkanInstance,
+    device: &'a Device,
+    pipeline: &'a Pipeline,
+    descriptors: &'a Descriptors,
+    meshes: &'a GpuMeshes,
+    permutations: &'a PipelinePermutations,
+    raw_vulkan: &'a RawVulkan,
+    raw_vulkan_hooks: &'a RawVulkanHooks,
+    layouts: &'a ImageLayoutTracker,
+    scratch: &'a FrameScratch,
+    stats: &'a RenderStats,
+    in_flight: &'a InFlightWork,
+    timeout: Duration,
+}
+
+impl WindowFrame<'_> {
+    /// Renders and presents a frame of the window, marks the target outdated if its swapchain
+    /// has to be recreated.
+    fn render(&self, target: &mut WindowTarget) -> Result<(), vk::Result> {
+        let device = self.device;
+        unsafe { device.wait_for_fences(&[target.fence], true, u64::MAX)? };
+
+        let outcome = target.swapchain.acquire_next_image(
+            self.instance,
+            device,
+            target.image_available,
+            self.timeout,
+            self.stats,
+        )?;
+        target.outdated = outcome.needs_recreation();
+        let Some(image_index) = outcome.image_index() else {
+            return Ok(());
+        };
+        let image = image_index as usize;
+
+        let recorder = FrameRecorder {
+            device,
+            swapchain: &target.swapchain,
+            depth_buffers: &target.depth_buffers,
+            pipeline: self.pipeline,
+            permutations: self.permutations,
+            meshes: self.meshes,
+            // The camera uniforms are written for the main window, its frustum may not fit here
+            frustum: None,
+            descriptors: self.descriptors,
+            stats: self.stats,
+            raw_vulkan: self.raw_vulkan,
+            raw_vulkan_hooks: self.raw_vulkan_hooks,
+            layouts: self.layouts,
+            scratch: self.scratch,
+            render_pass: target.render_pass.as_ref(),
+            occlusion: None,
+            pipeline_statistics: None,
+            capture_buffer: None,
+            fullscreen_passes: None,
+            gpu_particles: None,
+        };
+
+        let wait_semaphores = [target.image_available];
+        let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+        let command_buffers = [target.command_buffer];
+        let signal_semaphores = [target.render_finished[image]];
+        let submit_info = vk::SubmitInfo::default()
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
+            .command_buffers(&command_buffers)
+            .signal_semaphores(&signal_semaphores);
+
+        unsafe {
+            device.reset_command_buffer(
+                target.command_buffer,
+                vk::CommandBufferResetFlags::empty(),
+            )?;
+            recorder.record(target.command_buffer, image)?;
+            device.reset_fences(&[target.fence])?;
+            device.queue_submit(device.graphics_queue, &[submit_info], target.fence)?;
+        }
+        self.in_flight.submitted(target.fence);
+
+        let presented = target.swapchain.present(
+            self.instance,
+            device,
+            image_index,
+            &signal_semaphores,
+            &target.damage,
+            None,
+        )?;
+        target.outdated |= presented.needs_recreation();
+
+        Ok(())
+    }
+}
+
+/// Renders the main pass into every additional window and presents it, runs before
+/// [`render_frame`](crate::frame::render_frame) so that the completion of a main frame implies
+/// the completion of the window frames submitted before it.
+///
+/// A window that fails to render is skipped for the frame, the other windows are still rendered.
+#[allow(clippy::too_many_arguments)]
+pub fn render_window_targets(
+    (instance, device, targets): (Res<VulkanInstance>, Res<Device>, Res<WindowTargets>),
+    (pipeline, descriptors, meshes, permutations): WindowSceneResources,
+    (raw_vulkan, raw_vulkan_hooks): (Res<RawVulkan>, Res<RawVulkanHooks>),
+    layouts: Res<ImageLayoutTracker>,
+    scratch: NonSend<FrameScratch>,
+    (stats, settings): (Res<RenderStats>, Option<Res<GraphicsSettings>>),
+    in_flight: Res<InFlightWork>,
+) {
+    let (Some(pipeline), Some(descriptors)) = (pipeline, descriptors) else {
+        return;
+    };
+    let mut state = targets.state();
+    if state.targets.is_empty() || in_flight.is_stopping() {
+        return;
+    }
+
+    let frame = WindowFrame {
+        instance: &instance,
+        device: &device,
+        pipeline: &pipeline,
+        descriptors: &descriptors,
+        meshes: &meshes,
+        permutations: &permutations,
+        raw_vulkan: &raw_vulkan,
+        raw_vulkan_hooks: &raw_vulkan_hooks,
+        layouts: &layouts,
+        scratch: &scratch,
+        stats: &stats,
+        in_flight: &in_flight,
+        timeout: settings
+            .as_deref()
+            .cloned()
+            .unwrap_or_default()
+            .acquire_timeout,
+    };
+    for (entity, target) in state.targets.iter_mut() {
+        if target.outdated {
+            continue;
+        }
+        if let Err(err) = frame.render(target) {
+            error!(
+                target: log_targets::SURFACE,
+                "Could not render the window of entity {entity}: {err}"
+            );
+        }
+    }
+}
+
+/// Destroys the targets of all windows, runs once the device is idle.
+pub fn destroy_window_targets(
+    (instance, physical_device, device): (Res<VulkanInstance>, Res<PhysicalDevice>, Res<Device>),
+    (allocator, layouts, command_pools): (
+        Res<GpuAllocator>,
+        Res<ImageLayoutTracker>,
+        Option<Res<CommandPools>>,
+    ),
+    targets: Res<WindowTargets>,
+) {
+    let Some(command_pools) = command_pools else {
+        return;
+    };
+    let context = TargetContext {
+        instance: &instance,
+        physical_device: &physical_device,
+        device: &device,
+        allocator: &allocator,
+        layouts: &layouts,
+        command_pools: &command_pools,
+    };
+
+    let mut state = targets.state();
+    if !state.targets.is_empty() {
+        debug!(
+            target: log_targets::SURFACE,
+            "Destroying {} window targets",
+            state.targets.len()
+        );
+    }
+    for (_, target) in state.targets.drain() {
+        unsafe { context.destroy(target) };
+    }
+}
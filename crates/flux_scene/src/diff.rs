@@ -0,0 +1,383 @@
+use flux_ecs::commands::{Command, CommandError, Commands};
+use flux_ecs::component::Component;
+use flux_ecs::entity::Entity;
+use flux_ecs::resource::{Res, Resource};
+use flux_ecs::world::World;
+use log::{debug, warn};
+use std::any::{Any, type_name};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// A component stored in a [`Scene`], compared and applied to the world without knowing its type.
+pub trait SceneComponent: Send + Sync + 'static {
+    fn type_name(&self) -> &'static str;
+
+    fn as_any(&self) -> &dyn Any;
+
+    /// `true` if `other` is the same component type with the same value.
+    fn reflect_eq(&self, other: &dyn SceneComponent) -> bool;
+
+    fn clone_boxed(&self) -> Box<dyn SceneComponent>;
+
+    /// Inserts a copy of the component into the entity, replacing the existing value.
+    fn insert_into(&self, world: &mut World, entity: Entity);
+
+    /// Removes the component type from the entity.
+    fn remove_from(&self, world: &mut World, entity: Entity);
+}
+
+impl<T: Component + Clone + PartialEq + Send + Sync> SceneComponent for T {
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn reflect_eq(&self, other: &dyn SceneComponent) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+
+    fn clone_boxed(&self) -> Box<dyn SceneComponent> {
+        Box::new(self.clone())
+    }
+
+    fn insert_into(&self, world: &mut World, entity: Entity) {
+        world.insert_component(entity, self.clone());
+    }
+
+    fn remove_from(&self, world: &mut World, entity: Entity) {
+        world.remove_component::<T>(entity);
+    }
+}
+
+impl Clone for Box<dyn SceneComponent> {
+    fn clone(&self) -> Self {
+        self.clone_boxed()
+    }
+}
+
+impl Debug for dyn SceneComponent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.type_name())
+    }
+}
+
+/// The entities of a scene file, keyed by the name they have in the file so they can be matched
+/// across reloads.
+#[derive(Clone, Debug, Default)]
+pub struct Scene {
+    entities: BTreeMap<String, Vec<Box<dyn SceneComponent>>>,
+}
+
+impl Scene {
+    /// Adds a component to the named entity, replacing a component of the same type.
+    pub fn insert(&mut self, entity: impl Into<String>, component: impl SceneComponent) {
+        let components = self.entities.entry(entity.into()).or_default();
+        let component: Box<dyn SceneComponent> = Box::new(component);
+        match components
+            .iter_mut()
+            .find(|c| c.as_any().type_id() == component.as_any().type_id())
+        {
+            Some(existing) => *existing = component,
+            None => components.push(component),
+        }
+    }
+
+    #[must_use]
+    pub fn with(mut self, entity: impl Into<String>, component: impl SceneComponent) -> Self {
+        self.insert(entity, component);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+/// A change to a component of an entity that exists in both versions of a scene.
+#[derive(Debug)]
+pub enum ComponentChange {
+    /// The component was added or its value changed.
+    Set(Box<dyn SceneComponent>),
+    Removed(Box<dyn SceneComponent>),
+}
+
+/// The difference between two versions of a [`Scene`].
+#[derive(Debug, Default)]
+pub struct SceneDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<(String, ComponentChange)>,
+}
+
+impl SceneDiff {
+    pub fn between(old: &Scene, new: &Scene) -> Self {
+        let mut diff = Self::default();
+
+        for (name, new_components) in &new.entities {
+            let Some(old_components) = old.entities.get(name) else {
+                diff.added.push(name.clone());
+                continue;
+            };
+
+            for component in new_components {
+                let unchanged = old_components
+                    .iter()
+                    .any(|old| old.reflect_eq(&**component));
+                if !unchanged {
+                    diff.changed
+                        .push((name.clone(), ComponentChange::Set(component.clone())));
+                }
+            }
+            for component in old_components {
+                let type_id = component.as_any().type_id();
+                if !new_components
+                    .iter()
+                    .any(|new| new.as_any().type_id() == type_id)
+                {
+                    diff.changed
+                        .push((name.clone(), ComponentChange::Removed(component.clone())));
+                }
+            }
+        }
+
+        diff.removed = old
+            .entities
+            .keys()
+            .filter(|name| !new.entities.contains_key(*name))
+            .cloned()
+            .collect();
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A scene spawned into the world, remembers which entity belongs to which scene entity.
+#[derive(Debug)]
+pub struct LoadedScene {
+    scene: Scene,
+    entities: HashMap<String, Entity>,
+}
+
+impl LoadedScene {
+    pub fn spawn(world: &mut World, scene: Scene) -> Self {
+        let mut entities = HashMap::with_capacity(scene.len());
+        for (name, components) in &scene.entities {
+            entities.insert(name.clone(), spawn_entity(world, name, components));
+        }
+        Self { scene, entities }
+    }
+
+    /// The entity spawned for the named scene entity.
+    pub fn entity(&self, name: &str) -> Option<Entity> {
+        self.entities.get(name).copied()
+    }
+
+    /// Applies only the differences to `scene`, components the scene file did not change keep
+    /// their runtime values.
+    pub fn reload(&mut self, world: &mut World, scene: Scene) -> SceneDiff {
+        let diff = SceneDiff::between(&self.scene, &scene);
+
+        for name in &diff.removed {
+            if let Some(entity) = self.entities.remove(name) {
+                world.despawn(entity);
+            }
+        }
+        for name in &diff.added {
+            let entity = spawn_entity(world, name, &scene.entities[name]);
+            self.entities.insert(name.clone(), entity);
+        }
+        for (name, change) in &diff.changed {
+            let Some(&entity) = self.entities.get(name) else {
+                continue;
+            };
+            match change {
+                ComponentChange::Set(component) => component.insert_into(world, entity),
+                ComponentChange::Removed(component) => component.remove_from(world, entity),
+            }
+        }
+
+        self.scene = scene;
+        diff
+    }
+}
+
+/// The name of the scene entity this entity was spawned for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SceneEntityName(pub String);
+
+impl Component for SceneEntityName {}
+
+fn spawn_entity(world: &mut World, name: &str, components: &[Box<dyn SceneComponent>]) -> Entity {
+    let entity = world.spawn((SceneEntityName(name.to_owned()),));
+    for component in components {
+        component.insert_into(world, entity);
+    }
+    entity
+}
+
+/// Parses the contents of a scene file.
+pub type SceneParser = fn(&str) -> Result<Scene, String>;
+
+struct WatchedScene {
+    path: PathBuf,
+    parse: SceneParser,
+    modified: Option<SystemTime>,
+    loaded: Option<LoadedScene>,
+}
+
+/// Spawns scene files and reloads them by applying a [`SceneDiff`] whenever they change on disk.
+#[derive(Default)]
+pub struct SceneHotReload {
+    scenes: Mutex<Vec<WatchedScene>>,
+}
+
+impl Resource for SceneHotReload {}
+
+impl SceneHotReload {
+    /// Watches the file, it is spawned the next time [`hot_reload_scenes`] runs.
+    pub fn watch(&self, path: impl Into<PathBuf>, parse: SceneParser) {
+        self.scenes.lock().unwrap().push(WatchedScene {
+            path: path.into(),
+            parse,
+            modified: None,
+            loaded: None,
+        });
+    }
+
+    fn has_changes(&self) -> bool {
+        self.scenes
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|scene| modified(&scene.path).is_some_and(|time| Some(time) != scene.modified))
+    }
+
+    /// Reloads every watched file that was modified since it was last loaded.
+    pub fn reload_changed(&self, world: &mut World) {
+        for watched in self.scenes.lock().unwrap().iter_mut() {
+            let Some(time) = modified(&watched.path) else {
+                continue;
+            };
+            if watched.modified == Some(time) {
+                continue;
+            }
+            watched.modified = Some(time);
+
+            let scene = std::fs::read_to_string(&watched.path)
+                .map_err(|err| err.to_string())
+                .and_then(|contents| (watched.parse)(&contents));
+            let scene = match scene {
+                Ok(scene) => scene,
+                Err(err) => {
+                    warn!("Could not load scene {}: {err}", watched.path.display());
+                    continue;
+                }
+            };
+
+            match &mut watched.loaded {
+                Some(loaded) => {
+                    let diff = loaded.reload(world, scene);
+                    debug!(
+                        "Reloaded scene {}: {} added, {} removed, {} component changes",
+                        watched.path.display(),
+                        diff.added.len(),
+                        diff.removed.len(),
+                        diff.changed.len()
+                    );
+                }
+                None => watched.loaded = Some(LoadedScene::spawn(world, scene)),
+            }
+        }
+    }
+}
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+struct ReloadScenes;
+
+impl Command for ReloadScenes {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        world.try_resource_scope(|world, hot_reload: &mut SceneHotReload| {
+            hot_reload.reload_changed(world)
+        });
+        Ok(())
+    }
+}
+
+/// Checks the watched scene files for changes and applies them once the commands of the frame
+/// are applied.
+pub fn hot_reload_scenes(hot_reload: Res<SceneHotReload>, mut commands: Commands) {
+    if hot_reload.has_changes() {
+        commands.push(ReloadScenes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Position(f32);
+
+    impl Component for Position {}
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Health(u32);
+
+    impl Component for Health {}
+
+    #[test]
+    fn reloading_applies_only_the_differences() {
+        let mut world = World::new();
+        let scene = Scene::default()
+            .with("player", Position(0.0))
+            .with("player", Health(100))
+            .with("tree", Position(5.0))
+            .with("rock", Position(9.0));
+        let mut loaded = LoadedScene::spawn(&mut world, scene);
+        let player = loaded.entity("player").unwrap();
+        let tree = loaded.entity("tree").unwrap();
+        let rock = loaded.entity("rock").unwrap();
+
+        // Runtime state the scene file does not change must survive the reload
+        world.get_mut::<Health>(player).unwrap().0 = 42;
+        world.get_mut::<Position>(tree).unwrap().0 = 6.0;
+
+        let scene = Scene::default()
+            .with("player", Position(1.0))
+            .with("player", Health(100))
+            .with("tree", Health(10))
+            .with("lamp", Position(3.0));
+        let diff = loaded.reload(&mut world, scene);
+
+        assert_eq!(diff.added, ["lamp"]);
+        assert_eq!(diff.removed, ["rock"]);
+        assert_eq!(diff.changed.len(), 3);
+        assert!(!world.is_alive(rock));
+        assert_eq!(world.get::<Position>(player), Some(&Position(1.0)));
+        assert_eq!(world.get::<Health>(player), Some(&Health(42)));
+        assert_eq!(world.get::<Position>(tree), None);
+        assert_eq!(world.get::<Health>(tree), Some(&Health(10)));
+        let lamp = loaded.entity("lamp").unwrap();
+        assert_eq!(world.get::<Position>(lamp), Some(&Position(3.0)));
+        assert!(SceneDiff::between(&loaded.scene, &loaded.scene).is_empty());
+    }
+}
@@ -0,0 +1,24 @@
+use crate::diff::{SceneHotReload, hot_reload_scenes};
+use crate::streaming::{SceneStreamer, stream_scenes};
+use flux_ecs::plugin::Plugin;
+use flux_ecs::schedule::CoreSchedule;
+use flux_ecs::world::World;
+
+pub mod diff;
+pub mod streaming;
+
+/// Adds background scene streaming, see [`SceneStreamer`], and hot reloading of scene files, see
+/// [`SceneHotReload`].
+///
+/// The per-frame spawn budget can be changed by inserting a
+/// [`SpawnBudget`](streaming::SpawnBudget) resource.
+pub struct ScenePlugin;
+
+impl Plugin for ScenePlugin {
+    fn init(&self, world: &mut World) {
+        world.add_resource(SceneStreamer::default());
+        world.add_resource(SceneHotReload::default());
+        world.add_system(CoreSchedule::Main, stream_scenes);
+        world.add_system(CoreSchedule::Main, hot_reload_scenes);
+    }
+}
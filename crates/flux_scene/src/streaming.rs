@@ -0,0 +1,282 @@
+use flux_ecs::commands::{Command, CommandError, Commands};
+use flux_ecs::component::ComponentBundle;
+use flux_ecs::entity::Entity;
+use flux_ecs::resource::{Res, Resource};
+use flux_ecs::world::World;
+use log::{debug, warn};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// An entity loaded in the background, spawned on the main thread by the [`SceneStreamer`].
+pub trait SceneEntity: Send + 'static {
+    fn spawn(self: Box<Self>, world: &mut World) -> Entity;
+}
+
+impl<B: ComponentBundle + Send + 'static> SceneEntity for B {
+    fn spawn(self: Box<Self>, world: &mut World) -> Entity {
+        world.spawn(*self)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct SceneId(u64);
+
+/// Limits how much of the streamed scenes is spawned per frame to avoid hitches.
+#[derive(Clone, Copy, Debug)]
+pub struct SpawnBudget {
+    pub max_entities: usize,
+    /// Spawning stops once this much time was spent in a frame, at least one entity is spawned.
+    pub max_time: Duration,
+}
+
+impl Default for SpawnBudget {
+    fn default() -> Self {
+        Self {
+            max_entities: 1024,
+            max_time: Duration::from_millis(2),
+        }
+    }
+}
+
+impl Resource for SpawnBudget {}
+
+/// Sent as an [`Events<SceneLoadProgress>`](flux_ecs::event::Events) event every frame entities
+/// of the scene were spawned.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SceneLoadProgress {
+    pub scene: SceneId,
+    pub spawned: usize,
+    /// The number of entities of the scene, known once the loader finished.
+    pub total: Option<usize>,
+}
+
+impl SceneLoadProgress {
+    /// The loaded fraction between `0.0` and `1.0`, `None` while the total is unknown.
+    pub fn fraction(&self) -> Option<f32> {
+        self.total.map(|total| {
+            if total == 0 {
+                1.0
+            } else {
+                self.spawned as f32 / total as f32
+            }
+        })
+    }
+}
+
+/// Sent as an [`Events<SceneLoaded>`](flux_ecs::event::Events) event once all entities of the
+/// scene were spawned.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SceneLoaded {
+    pub scene: SceneId,
+    pub entities: Vec<Entity>,
+}
+
+/// Passed to the scene loader to send loaded entities to the main thread in chunks.
+pub struct SceneChunkSender {
+    sender: Sender<Vec<Box<dyn SceneEntity>>>,
+}
+
+impl SceneChunkSender {
+    /// Queues the entities for spawning, returns `false` if the scene was dropped.
+    pub fn send(&self, chunk: Vec<Box<dyn SceneEntity>>) -> bool {
+        self.sender.send(chunk).is_ok()
+    }
+}
+
+struct StreamingScene {
+    id: SceneId,
+    receiver: Receiver<Vec<Box<dyn SceneEntity>>>,
+    pending: VecDeque<Box<dyn SceneEntity>>,
+    spawned: Vec<Entity>,
+    loader_finished: bool,
+}
+
+impl StreamingScene {
+    fn receive(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(chunk) => self.pending.extend(chunk),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.loader_finished = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.loader_finished && self.pending.is_empty()
+    }
+
+    fn total(&self) -> Option<usize> {
+        self.loader_finished
+            .then(|| self.spawned.len() + self.pending.len())
+    }
+}
+
+/// Loads scenes on background threads and spawns their entities within the [`SpawnBudget`].
+#[derive(Default)]
+pub struct SceneStreamer {
+    scenes: Mutex<Vec<StreamingScene>>,
+    next_id: Mutex<u64>,
+}
+
+impl Resource for SceneStreamer {}
+
+impl SceneStreamer {
+    /// Runs `loader` on a background thread, it sends the loaded entities in chunks and returns
+    /// once the whole scene was sent.
+    ///
+    /// A panicking loader ends the scene early with the entities sent so far.
+    pub fn load(&self, loader: impl FnOnce(SceneChunkSender) + Send + 'static) -> SceneId {
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            SceneId(*next_id)
+        };
+
+        let (sender, receiver) = channel();
+        let spawned = thread::Builder::new()
+            .name(format!("scene-loader-{}", id.0))
+            .spawn(move || loader(SceneChunkSender { sender }));
+        if let Err(err) = spawned {
+            warn!("Could not start the loader of scene {id:?}: {err}");
+        }
+
+        self.scenes.lock().unwrap().push(StreamingScene {
+            id,
+            receiver,
+            pending: VecDeque::new(),
+            spawned: Vec::new(),
+            loader_finished: false,
+        });
+        debug!("Streaming scene {id:?}");
+
+        id
+    }
+
+    /// The number of scenes that are still loading.
+    pub fn loading(&self) -> usize {
+        self.scenes.lock().unwrap().len()
+    }
+
+    /// Spawns pending entities of all scenes, oldest scene first, and sends the progress events.
+    pub fn spawn_pending(&self, world: &mut World, budget: SpawnBudget) {
+        let start = Instant::now();
+        let mut remaining = budget.max_entities;
+        let mut scenes = self.scenes.lock().unwrap();
+
+        for scene in scenes.iter_mut() {
+            scene.receive();
+
+            let spawned_before = scene.spawned.len();
+            while remaining > 0 {
+                let over_time = start.elapsed() >= budget.max_time;
+                if over_time && remaining < budget.max_entities {
+                    break;
+                }
+                let Some(entity) = scene.pending.pop_front() else {
+                    break;
+                };
+                scene.spawned.push(entity.spawn(world));
+                remaining -= 1;
+            }
+
+            if scene.spawned.len() > spawned_before || scene.is_finished() {
+                world.send_event(SceneLoadProgress {
+                    scene: scene.id,
+                    spawned: scene.spawned.len(),
+                    total: scene.total(),
+                });
+            }
+        }
+
+        scenes.retain_mut(|scene| {
+            if !scene.is_finished() {
+                return true;
+            }
+            debug!(
+                "Scene {:?} loaded {} entities",
+                scene.id,
+                scene.spawned.len()
+            );
+            world.send_event(SceneLoaded {
+                scene: scene.id,
+                entities: std::mem::take(&mut scene.spawned),
+            });
+            false
+        });
+    }
+}
+
+struct SpawnPendingScenes;
+
+impl Command for SpawnPendingScenes {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        let budget = world
+            .get_resource::<SpawnBudget>()
+            .copied()
+            .unwrap_or_default();
+        world.try_resource_scope(|world, streamer: &mut SceneStreamer| {
+            streamer.spawn_pending(world, budget)
+        });
+        Ok(())
+    }
+}
+
+/// Spawns the entities of streamed scenes once the commands of the frame are applied.
+pub fn stream_scenes(streamer: Res<SceneStreamer>, mut commands: Commands) {
+    if streamer.loading() > 0 {
+        commands.push(SpawnPendingScenes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flux_ecs::component::Component;
+    use flux_ecs::event::Events;
+
+    struct Tree;
+
+    impl Component for Tree {}
+
+    #[test]
+    fn scenes_are_spawned_within_the_budget() {
+        let mut world = World::new();
+        let streamer = SceneStreamer::default();
+        let scene = streamer.load(|sender| {
+            sender.send(
+                (0..5)
+                    .map(|_| Box::new((Tree,)) as Box<dyn SceneEntity>)
+                    .collect(),
+            );
+        });
+        let budget = SpawnBudget {
+            max_entities: 2,
+            max_time: Duration::from_secs(1),
+        };
+
+        let mut frames = 0;
+        while streamer.loading() > 0 {
+            streamer.spawn_pending(&mut world, budget);
+            frames += 1;
+            assert!(frames < 1000, "The scene did not finish loading");
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let loaded = world.get_resource::<Events<SceneLoaded>>().unwrap();
+        let loaded: Vec<_> = loaded.iter().collect();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].scene, scene);
+        assert_eq!(loaded[0].entities.len(), 5);
+
+        let progress = world.get_resource::<Events<SceneLoadProgress>>().unwrap();
+        assert!(progress.iter().all(|progress| progress.spawned <= 5));
+        assert_eq!(progress.iter().last().unwrap().fraction(), Some(1.0));
+    }
+}
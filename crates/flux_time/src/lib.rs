@@ -0,0 +1,58 @@
+use crate::time::{Time, update_time};
+use flux_ecs::plugin::Plugin;
+use flux_ecs::schedule::CoreSchedule;
+use flux_ecs::schedule::control::{ScheduleControl, SystemSet};
+use flux_ecs::world::World;
+
+pub mod time;
+
+/// Adds the [`Time`] and advances it at the start of every frame.
+///
+/// Add it before the other plugins so that their `PreUpdate` systems already see the time of
+/// the current frame. The time keeps advancing while `PreUpdate` is paused, pause the [`Time`]
+/// itself to stop gameplay.
+pub struct TimePlugin;
+
+impl TimePlugin {
+    /// The set of the system advancing the [`Time`], kept running by the [`ScheduleControl`].
+    pub const SET: SystemSet = SystemSet("time");
+}
+
+impl Plugin for TimePlugin {
+    fn init(&self, world: &mut World) {
+        if world.get_resource::<Time>().is_none() {
+            world.add_resource(Time::new());
+        }
+        if let Some(control) = world.get_resource_mut::<ScheduleControl>() {
+            control.always_run(Self::SET);
+        }
+
+        world.add_system_to_set(CoreSchedule::PreUpdate, Self::SET, update_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flux_ecs::app::App;
+    use flux_ecs::resource::Res;
+
+    #[test]
+    fn frames_advance_the_time() {
+        let mut app = App::new();
+        app.add_plugin(TimePlugin);
+        app.world_mut()
+            .add_system(CoreSchedule::Main, |time: Res<Time>| {
+                assert!(time.frame_count() > 0);
+            });
+        app.world_mut()
+            .get_resource_mut::<ScheduleControl>()
+            .unwrap()
+            .pause(CoreSchedule::PreUpdate);
+
+        app.update().unwrap();
+        app.update().unwrap();
+
+        assert_eq!(app.world().get_resource::<Time>().unwrap().frame_count(), 2);
+    }
+}
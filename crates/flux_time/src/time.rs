@@ -0,0 +1,158 @@
+use flux_ecs::resource::{ResMut, Resource};
+use std::time::{Duration, Instant};
+
+/// Frame timing, updated once at the start of every frame by [`update_time`].
+///
+/// [`Time::delta`] and [`Time::elapsed`] are scaled by the relative speed and stop while the time
+/// is paused, gameplay and animation should use them. The unscaled clock keeps running in real
+/// time, e.g. for debug overlays and UI.
+#[derive(Debug, Clone)]
+pub struct Time {
+    last_update: Option<Instant>,
+    delta: Duration,
+    elapsed: Duration,
+    raw_delta: Duration,
+    raw_elapsed: Duration,
+    relative_speed: f64,
+    paused: bool,
+    frame_count: u64,
+}
+
+impl Resource for Time {}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Time {
+    pub fn new() -> Self {
+        Self {
+            last_update: None,
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            raw_delta: Duration::ZERO,
+            raw_elapsed: Duration::ZERO,
+            relative_speed: 1.0,
+            paused: false,
+            frame_count: 0,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.update_with_instant(Instant::now());
+    }
+
+    /// Advances the time to `now`, the first update has a delta of zero.
+    pub fn update_with_instant(&mut self, now: Instant) {
+        if let Some(last_update) = self.last_update {
+            self.raw_delta = now.saturating_duration_since(last_update);
+            self.raw_elapsed += self.raw_delta;
+
+            self.delta = if self.paused {
+                Duration::ZERO
+            } else {
+                self.raw_delta.mul_f64(self.relative_speed)
+            };
+            self.elapsed += self.delta;
+        }
+        self.last_update = Some(now);
+        self.frame_count = self.frame_count.wrapping_add(1);
+    }
+
+    /// The number of updates so far, the current frame is frame `frame_count - 1`. Counts
+    /// paused frames as well.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_secs(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    /// The delta of the last frame in real time, ignoring the relative speed and pausing.
+    pub fn raw_delta(&self) -> Duration {
+        self.raw_delta
+    }
+
+    pub fn raw_delta_secs(&self) -> f32 {
+        self.raw_delta.as_secs_f32()
+    }
+
+    pub fn raw_elapsed(&self) -> Duration {
+        self.raw_elapsed
+    }
+
+    pub fn raw_elapsed_secs(&self) -> f32 {
+        self.raw_elapsed.as_secs_f32()
+    }
+
+    pub fn relative_speed(&self) -> f64 {
+        self.relative_speed
+    }
+
+    /// Scales the time, e.g. `0.5` for slow motion. Takes effect on the next update.
+    ///
+    /// # Panics
+    /// Panics if the speed is negative or not finite.
+    pub fn set_relative_speed(&mut self, speed: f64) {
+        assert!(
+            speed.is_finite() && speed >= 0.0,
+            "The relative speed must be finite and non-negative, got {speed}"
+        );
+        self.relative_speed = speed;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+/// Advances the [`Time`], added to `PreUpdate` by the [`TimePlugin`](crate::TimePlugin).
+pub fn update_time(mut time: ResMut<Time>) {
+    time.update();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_time_respects_speed_and_pause() {
+        let start = Instant::now();
+        let mut time = Time::new();
+        time.update_with_instant(start);
+
+        time.set_relative_speed(0.5);
+        time.update_with_instant(start + Duration::from_secs(2));
+        assert_eq!(time.delta(), Duration::from_secs(1));
+
+        time.pause();
+        time.update_with_instant(start + Duration::from_secs(3));
+        assert_eq!(time.delta(), Duration::ZERO);
+        assert_eq!(time.elapsed(), Duration::from_secs(1));
+        assert_eq!(time.raw_elapsed(), Duration::from_secs(3));
+        assert_eq!(time.frame_count(), 3);
+    }
+}
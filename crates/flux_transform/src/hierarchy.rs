@@ -0,0 +1,153 @@
+use flux_ecs::commands::{Command, CommandError, Commands};
+use flux_ecs::component::Component;
+use flux_ecs::entity::Entity;
+use flux_ecs::world::World;
+
+/// The entity this entity's [`Transform`](crate::transform::Transform) is relative to.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(pub Entity);
+
+/// The entities whose [`Parent`] is this entity, kept in sync by [`set_parent`] and
+/// [`remove_parent`].
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(Vec<Entity>);
+
+impl Children {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Makes `child` a child of `parent`, detaching it from its previous parent.
+///
+/// # Panics
+/// Panics if `child` is `parent` or one of its ancestors, the hierarchy must not contain cycles.
+pub fn set_parent(world: &mut World, child: Entity, parent: Entity) {
+    let mut ancestor = Some(parent);
+    while let Some(entity) = ancestor {
+        assert_ne!(entity, child, "Entity {child} cannot be its own ancestor");
+        ancestor = world.get::<Parent>(entity).map(|parent| parent.0);
+    }
+
+    remove_parent(world, child);
+    world.insert_component(child, Parent(parent));
+    match world.get_mut::<Children>(parent) {
+        Some(children) => children.0.push(child),
+        None => {
+            world.insert_component(parent, Children(vec![child]));
+        }
+    }
+}
+
+/// Detaches `child` from its parent, its transform becomes relative to the world.
+pub fn remove_parent(world: &mut World, child: Entity) {
+    let Some(Parent(parent)) = world.remove_component::<Parent>(child) else {
+        return;
+    };
+    if let Some(children) = world.get_mut::<Children>(parent) {
+        children.0.retain(|&entity| entity != child);
+    }
+}
+
+/// Despawns the entity together with all its descendants.
+///
+/// The whole subtree is collected before anything is despawned, and descendants are despawned
+/// before their ancestors, so no [`Children`] is read after one of its entities was moved by a
+/// despawn.
+pub fn despawn_recursive(world: &mut World, entity: Entity) {
+    remove_parent(world, entity);
+
+    let mut subtree = vec![entity];
+    let mut next = 0;
+    while let Some(&entity) = subtree.get(next) {
+        if let Some(children) = world.get::<Children>(entity) {
+            subtree.extend(children.iter());
+        }
+        next += 1;
+    }
+
+    for entity in subtree.into_iter().rev() {
+        world.despawn(entity);
+    }
+}
+
+/// Despawns an entity and its descendants, see [`despawn_recursive`].
+pub struct DespawnRecursive {
+    pub entity: Entity,
+}
+
+impl Command for DespawnRecursive {
+    fn execute(self: Box<Self>, world: &mut World) -> Result<(), CommandError> {
+        if !world.contains(self.entity) {
+            return Err(CommandError::EntityNotFound {
+                operation: "despawn_recursive",
+                entity: self.entity,
+            });
+        }
+
+        despawn_recursive(world, self.entity);
+        Ok(())
+    }
+
+    fn entity(&self) -> Option<Entity> {
+        Some(self.entity)
+    }
+}
+
+/// Hierarchy operations queued on [`Commands`].
+pub trait HierarchyCommands {
+    /// Despawns the entity and all its descendants once the commands are flushed.
+    fn despawn_recursive(&mut self, entity: Entity);
+}
+
+impl HierarchyCommands for Commands {
+    fn despawn_recursive(&mut self, entity: Entity) {
+        self.push(DespawnRecursive { entity });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn despawn_recursive_command_removes_the_subtree_only() {
+        let mut world = World::new();
+        let root = world.spawn((Children::default(),));
+        let sibling = world.spawn((Children::default(),));
+        let node = world.spawn((Children::default(),));
+        let leaves: Vec<_> = (0..4)
+            .map(|_| world.spawn((Children::default(),)))
+            .collect();
+        set_parent(&mut world, sibling, root);
+        set_parent(&mut world, node, root);
+        for &leaf in &leaves {
+            set_parent(&mut world, leaf, node);
+        }
+
+        world
+            .run_system_once(move |mut commands: Commands| commands.despawn_recursive(node))
+            .unwrap();
+
+        assert!(!world.is_alive(node));
+        assert!(leaves.iter().all(|&leaf| !world.is_alive(leaf)));
+        assert!(world.is_alive(sibling));
+        assert_eq!(
+            world
+                .get::<Children>(root)
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            [sibling]
+        );
+        assert_eq!(world.entities().count(), 2);
+    }
+}
@@ -0,0 +1,19 @@
+use crate::propagation::propagate_transforms;
+use flux_ecs::plugin::Plugin;
+use flux_ecs::schedule::CoreSchedule;
+use flux_ecs::world::World;
+
+pub mod hierarchy;
+pub mod propagation;
+pub mod transform;
+
+/// Computes the [`GlobalTransform`](transform::GlobalTransform) of every entity with a
+/// [`Transform`](transform::Transform) from its parents each frame, after gameplay moved things
+/// around in `Main`.
+pub struct TransformPlugin;
+
+impl Plugin for TransformPlugin {
+    fn init(&self, world: &mut World) {
+        world.add_system(CoreSchedule::PostUpdate, propagate_transforms);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::hierarchy::Parent;
+use crate::transform::{GlobalTransform, Transform};
+use cgmath::{Matrix4, One};
+use flux_ecs::entity::Entity;
+use flux_ecs::query::{Query, Without};
+use std::collections::HashMap;
+
+/// Computes the world matrix of every [`Transform`] by multiplying it with the world matrices of
+/// its ancestors.
+///
+/// Ancestors without a `Transform` count as the identity.
+pub fn propagate_transforms(
+    roots: Query<(Entity, &Transform), Without<Parent>>,
+    children: Query<(Entity, &Transform, &Parent)>,
+    mut globals: Query<(Entity, &mut GlobalTransform)>,
+) {
+    let mut world_matrices: HashMap<Entity, Matrix4<f32>> = roots
+        .into_iter()
+        .map(|(entity, transform)| (entity, transform.to_matrix()))
+        .collect();
+    let locals: HashMap<Entity, (Matrix4<f32>, Entity)> = children
+        .into_iter()
+        .map(|(entity, transform, parent)| (entity, (transform.to_matrix(), parent.0)))
+        .collect();
+
+    let mut chain = Vec::new();
+    for &entity in locals.keys() {
+        // Walk up to the first ancestor whose world matrix is known, then resolve back down
+        let mut current = entity;
+        let mut parent_matrix = loop {
+            if let Some(matrix) = world_matrices.get(&current) {
+                break *matrix;
+            }
+            let Some(&(_, parent)) = locals.get(&current) else {
+                break Matrix4::one();
+            };
+            chain.push(current);
+            current = parent;
+        };
+        while let Some(entity) = chain.pop() {
+            parent_matrix = parent_matrix * locals[&entity].0;
+            world_matrices.insert(entity, parent_matrix);
+        }
+    }
+
+    for (entity, global) in &mut globals {
+        if let Some(matrix) = world_matrices.get(&entity) {
+            global.0 = *matrix;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hierarchy::{Children, despawn_recursive, set_parent};
+    use cgmath::Vector3;
+    use flux_ecs::world::World;
+
+    #[test]
+    fn children_inherit_the_transforms_of_their_ancestors() {
+        let mut world = World::new();
+        let root =
+            world.spawn((Transform::from_translation(10.0, 0.0, 0.0).with_scale(2.0, 2.0, 2.0),));
+        let child = world.spawn((Transform::from_translation(1.0, 0.0, 0.0),));
+        let grandchild = world.spawn((Transform::from_translation(0.0, 1.0, 0.0),));
+        set_parent(&mut world, child, root);
+        set_parent(&mut world, grandchild, child);
+
+        world.run_system_once(propagate_transforms).unwrap();
+
+        let translation = |entity| world.get::<GlobalTransform>(entity).unwrap().translation();
+        assert_eq!(translation(root), Vector3::new(10.0, 0.0, 0.0));
+        assert_eq!(translation(child), Vector3::new(12.0, 0.0, 0.0));
+        assert_eq!(translation(grandchild), Vector3::new(12.0, 2.0, 0.0));
+
+        assert_eq!(
+            world
+                .get::<Children>(root)
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            [child]
+        );
+        set_parent(&mut world, grandchild, root);
+        assert!(world.get::<Children>(child).unwrap().is_empty());
+        assert_eq!(world.get::<Children>(root).unwrap().len(), 2);
+
+        despawn_recursive(&mut world, root);
+        assert!(!world.is_alive(grandchild));
+        assert_eq!(world.entities().count(), 0);
+    }
+}
@@ -0,0 +1,72 @@
+use cgmath::{Matrix4, One, Quaternion, Vector3};
+use flux_ecs::component::Component;
+
+/// The position, rotation and scale of an entity relative to its [`Parent`], or to the world if
+/// it has none.
+///
+/// [`Parent`]: crate::hierarchy::Parent
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+#[component(require(GlobalTransform))]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vector3::new(0.0, 0.0, 0.0),
+        rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+        scale: Vector3::new(1.0, 1.0, 1.0),
+    };
+
+    pub fn from_translation(x: f32, y: f32, z: f32) -> Self {
+        Self {
+            translation: Vector3::new(x, y, z),
+            ..Self::IDENTITY
+        }
+    }
+
+    #[must_use]
+    pub fn with_rotation(mut self, rotation: Quaternion<f32>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    #[must_use]
+    pub fn with_scale(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.scale = Vector3::new(x, y, z);
+        self
+    }
+
+    /// Scales, then rotates, then translates.
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+}
+
+/// The world space transform of an entity, written by
+/// [`propagate_transforms`](crate::propagation::propagate_transforms). Added together with the
+/// [`Transform`].
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform(pub Matrix4<f32>);
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self(Matrix4::one())
+    }
+}
+
+impl GlobalTransform {
+    pub fn translation(&self) -> Vector3<f32> {
+        self.0.w.truncate()
+    }
+}
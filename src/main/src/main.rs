@@ -1,15 +1,65 @@
-use flux_ecs::schedule::ScheduleLabel::{Destroy, Initialization};
-use flux_ecs::world::World;
-use flux_renderer::RendererPlugin;
-use std::thread::sleep;
-use std::time::Duration;
+use flux_ecs::app::App;
+use flux_ecs::logging::{self, LogSettings};
+use flux_renderer::{
+    ConfigPlugin, Mesh, MeshBuilder, MeshVertex, RendererPlugin, RendererUnavailable,
+};
+use flux_time::TimePlugin;
+use log::{LevelFilter, error};
+use std::env;
 
 fn main() {
-    pretty_env_logger::init();
+    let mut log_settings = LogSettings::default();
+    // RUST_LOG replaces the default level of the settings, targets configured at runtime still
+    // apply on top of it
+    if env::var_os("RUST_LOG").is_some() {
+        log_settings.set_default_level(LevelFilter::Trace);
+    }
+    let logger = pretty_env_logger::formatted_builder()
+        .filter_level(LevelFilter::Trace)
+        .parse_env("RUST_LOG")
+        .build();
+    logging::init(logger, &log_settings).expect("Failed to initialize the logger");
 
-    let mut world = World::new();
-    world.add_plugin(RendererPlugin);
-    world.run_system(&Initialization);
-    sleep(Duration::from_secs(1));
-    world.run_system(&Destroy);
+    let mut app = App::new();
+    app.world_mut().add_resource(log_settings);
+    app.world_mut().spawn((triangle(),));
+    app.add_plugin(TimePlugin)
+        .add_plugin(ConfigPlugin)
+        .add_plugin(RendererPlugin)
+        .on_init_error(|world, init_error| {
+            if let Some(unavailable) = world.get_resource::<RendererUnavailable>() {
+                error!(
+                    "Flux Engine requires Vulkan, {}. Install or update the graphics driver.",
+                    unavailable.reason
+                );
+                return;
+            }
+            error!("Flux Engine could not start: {}", init_error.first());
+            for system_error in init_error.errors.iter().skip(1) {
+                error!("  {system_error}");
+            }
+        });
+
+    app.run();
+}
+
+fn triangle() -> Mesh {
+    let mut builder = MeshBuilder::new(MeshVertex::layout());
+    let a = builder.vertex(MeshVertex {
+        position: [-0.5, -0.5, 0.0],
+        color: [1.0, 0.0, 0.0],
+        tex_coords: [0.0, 1.0],
+    });
+    let b = builder.vertex(MeshVertex {
+        position: [0.5, -0.5, 0.0],
+        color: [0.0, 1.0, 0.0],
+        tex_coords: [1.0, 1.0],
+    });
+    let c = builder.vertex(MeshVertex {
+        position: [0.0, 0.5, 0.0],
+        color: [0.0, 0.0, 1.0],
+        tex_coords: [1.0, 0.0],
+    });
+    builder.triangle(a, b, c);
+    builder.build()
 }